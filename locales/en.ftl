@@ -0,0 +1,502 @@
+# English messages for the dromos shell, and the fallback for anything a
+# translation leaves out.
+#
+# To translate, copy this file to <language>.ftl (such as pt-BR.ftl or
+# ja.ftl) in the `locales` folder next to dromos.conf, and translate the
+# text after each `=`. Keep the ids before the `=` and placeholders like
+# { $path } as they are. Messages not translated yet appear in English.
+
+## Startup
+
+banner-hint-command = type a command, e.g. "help" or "exit"
+banner-hint-keys = press tab for autocomplete, and up/down for history
+banner-library = library: { $path }
+
+## Message prefixes
+
+error = Error:
+warning = Warning:
+usage = Usage:
+
+## Confirmation prompts
+
+confirm-choices = [y/N]
+# Answers taken as yes, separated by commas
+confirm-yes = y, yes
+# For questions that default to yes
+confirm-choices-yes = [Y/n]
+# Answers taken as no, separated by commas
+confirm-no = n, no
+# For questions that can also abort the whole command
+confirm-choices-abort = [y/N/a]
+# Answers taken as abort, separated by commas
+confirm-abort = a, abort
+cancelled = Cancelled.
+
+## Errors
+
+ambiguous-hash-prefix = Ambiguous hash prefix:
+ambiguous-title = Ambiguous title:
+attach-failed = Attach failed:
+banks-failed = Banks failed:
+base-rom-not-in-database = Base ROM not in database:
+build-failed = Build failed:
+cannot-advertise = Cannot advertise:
+cannot-listen = Cannot listen:
+cannot-merge-into-itself = Can't merge a ROM into itself:
+cannot-read-script = Cannot read script:
+cannot-save = Cannot save:
+cannot-search-network = Cannot search the network:
+cannot-share = Cannot share:
+cannot-write = Cannot write:
+catalog-export-failed = Catalog export failed:
+checksums-failed = Checksums failed:
+collection-not-changed = Collection not changed:
+compare-failed = Compare failed:
+could-not-open-browser = Could not open browser:
+could-not-run-emulator = Could not run emulator:
+create-failed = Create failed:
+csv-edit-failed = CSV edit failed:
+dat-export-failed = DAT export failed:
+dat-import-failed = DAT import failed:
+delete-failed = Delete failed:
+detach-failed = Detach failed:
+diff-check-failed = Diff check failed:
+export-failed = Export failed:
+failed-to-add = Failed to add:
+failed-to-convert = Failed to convert:
+failed-to-hash = Failed to hash:
+failed-to-link = Failed to link:
+file-not-found = File not found:
+folder-not-found = Folder not found:
+git-commit-failed = Git commit failed:
+import-failed = Import failed:
+inspect-failed = Inspect failed:
+invalid-date-format = Invalid date format,
+invalid-edit = Invalid edit:
+invalid-file-argument = Invalid file argument:
+invalid-rating = Invalid rating,
+invalid-regex = Invalid regex:
+invalid-search = Invalid search:
+invalid-status = Invalid status,
+invalid-value = Invalid value:
+list-failed = List failed:
+locate-failed = Locate failed:
+log-file-failed = log file { $path }: { $error }
+merge-failed = Merge failed:
+missing-argument = Missing argument:
+no-emulator-configured-for = No emulator configured for
+no-files-match = No files match:
+no-plugin-named = No plugin named
+no-previous-rom = No previous ROM to link to.
+no-roms-match = No ROMs match:
+no-source-url-for = No source URL for
+not-a-full-sha256 = Not a full SHA-256:
+not-a-web-url = Not a web URL:
+not-available = Not available:
+nothing-to-edit = Nothing to edit:
+nothing-to-share = Nothing to share:
+output-file-already-exists = Output file already exists:
+report-failed = Report failed:
+rom-already-exists = ROM already exists:
+rom-not-found = ROM not found:
+rom-not-found-in-database = ROM not found in database:
+scripts-nested-too-deeply = Scripts nested too deeply:
+sdcard-export-failed = SD card export failed:
+search-failed = Search failed:
+server-failed = Server failed:
+share-not-found = Share not found:
+sql-error = SQL error:
+sync-failed = Sync failed:
+target-rom-not-found = Target ROM not found:
+unknown-command = Unknown command:
+unknown-option = Unknown option:
+validation-failed = Validation failed:
+
+## Error details
+
+built-without = dromos was built without the { $feature } feature
+give-a-field = give at least one field, e.g. --title <t>
+hash-or-title-required = a hash or title is required when not running interactively
+library-empty = the library is empty
+link-two-files-instead = Use 'link <file1> <file2>' instead.
+no-config-directory = no config directory
+no-roms-tagged = no ROMs tagged { $tags }
+not-with-rpc = { $what } can't be used with --rpc
+use-overwrite = { $path } (use --overwrite to replace it)
+
+## Labels
+
+added = Added:
+adding-file = Adding file
+applied = Applied
+archived = Archived:
+attached = Attached:
+attachments = Attachments:
+broken = Broken:
+building = Building
+built = Built:
+calls-need = Calls need
+committed = Committed:
+comparing-with = Comparing with
+conflicts = Conflicts:
+converted = Converted:
+created = Created:
+damaged = Damaged:
+deleted = Deleted:
+detached = Detached:
+differs = Differs:
+downloading-from = Downloading from
+exported = Exported:
+exported-dat = Exported DAT:
+failed = Failed:
+favorited = Favorited:
+folder-contains = Folder contains:
+found = Found:
+found-wishlist-rom = Found wishlist ROM:
+healthy = Healthy:
+help-aliases = Aliases:
+help-commands = Commands:
+help-examples = Examples:
+help-related = Related:
+identical = Identical:
+imported = Imported:
+imported-dat = Imported DAT:
+inconsistent = Inconsistent:
+last-export = Last export:
+last-import = Last import:
+library = Library:
+linked = Linked:
+links = Links:
+located = Located:
+located-at = Located at:
+merged = Merged:
+merging = Merging
+missing-diff-files = Missing diff files:
+opened = Opened:
+playing = Playing
+pulled = Pulled:
+pushed = Pushed:
+recently-added = Recently added:
+recorded = Recorded:
+related = Related:
+removed = Removed:
+reported = Reported:
+same-title = Same title:
+saved = Saved
+scanned = Scanned:
+serving = Serving
+sharing = Sharing
+similar-titles = Similar titles:
+skipped = Skipped:
+status-added = added
+status-built = built
+status-dup = dup
+status-error = error
+status-exact = exact
+status-failed = failed
+status-found = found
+status-header = header
+status-known = known
+status-new = new
+status-unknown = unknown
+status-wish = wish
+stopped = Stopped:
+stopped-serving = Stopped serving
+stopped-sharing = Stopped sharing
+summary = Summary:
+this-session = This session:
+timings = Timings:
+unarchived = Unarchived:
+unbuilt = Unbuilt:
+unfavorited = Unfavorited:
+unlinked-roms = Unlinked ROMs:
+unrelated = Unrelated:
+up-to-date = Up to date:
+updated = Updated:
+valid = Valid:
+verified = Verified:
+wanted = Wanted:
+watching = Watching
+wished-for = Wished for:
+would-pull = Would pull:
+would-push = Would push:
+wrote = Wrote:
+wrote-file = Wrote
+
+## Report fields
+
+field-a = A:
+field-added = Added:
+field-also-seen-as = Also seen as:
+field-archived = Archived:
+field-attachments = Attachments:
+field-b = B:
+field-battery = Battery:
+field-built = Built:
+field-changed = Changed:
+field-chr-nvram = CHR NVRAM:
+field-chr-ram = CHR RAM:
+field-chr-rom = CHR ROM:
+field-collections = Collections:
+field-crc32 = CRC32:
+field-data-rev = Data rev:
+field-database = Database:
+field-description = Description:
+field-diff-size = Diff size:
+field-diffs = Diffs:
+field-edges = Edges:
+field-edited = Edited:
+field-favorite = Favorite:
+field-file = File:
+field-filename = Filename:
+field-format = Format:
+field-hash = Hash:
+field-header = Header:
+field-language = Language:
+field-link-to = Link to:
+field-mapper = Mapper:
+field-mirroring = Mirroring:
+field-prg-nvram = PRG NVRAM:
+field-prg-ram = PRG RAM:
+field-prg-rom = PRG ROM:
+field-rating = Rating:
+field-region = Region:
+field-released = Released:
+field-roms = ROMs:
+field-sha-256 = SHA-256:
+field-sha1 = SHA-1:
+field-size = Size:
+field-source-url = Source URL:
+field-status = Status:
+field-tags = Tags:
+field-timing = Timing:
+field-trainer = Trainer:
+field-type = Type:
+field-verified = Verified:
+field-version = Version:
+field-wanted = Wanted:
+no = no
+yes = yes
+
+## Counts
+
+attachments-one = { $count } attachment
+attachments-other = { $count } attachments
+banks-one = { $count } bank
+banks-other = { $count } banks
+bytes-one = { $count } byte
+bytes-other = { $count } bytes
+calls-one = { $count } call
+calls-other = { $count } calls
+checksums-recorded-one = Recorded checksums and sizes for { $count } older diff
+checksums-recorded-other = Recorded checksums and sizes for { $count } older diffs
+diff-files-one = { $count } diff file
+diff-files-other = { $count } diff files
+diffs-one = { $count } diff
+diffs-other = { $count } diffs
+duplicates-one = { $count } duplicate
+duplicates-other = { $count } duplicates
+edges-one = { $count } edge
+edges-other = { $count } edges
+entries-one = { $count } entry
+entries-other = { $count } entries
+files-one = { $count } file
+files-other = { $count } files
+groups-one = { $count } group
+groups-other = { $count } groups
+kept-existing-files-one = Kept { $count } existing file
+kept-existing-files-other = Kept { $count } existing files
+links-one = { $count } link
+links-other = { $count } links
+locations-one = { $count } location
+locations-other = { $count } locations
+matching-roms-one = { $count } matching ROM
+matching-roms-other = { $count } matching ROMs
+nodes-one = { $count } node
+nodes-other = { $count } nodes
+ranges-one = { $count } range
+ranges-other = { $count } ranges
+regions-one = { $count } region
+regions-other = { $count } regions
+roms-one = { $count } ROM
+roms-other = { $count } ROMs
+rows-one = { $count } row
+rows-other = { $count } rows
+
+## Messages
+
+add-summary = { $added } added, { $existing } already present, { $failed } failed
+add-summary-found = { $added } added, { $found } found from the wishlist, { $existing } already present, { $failed } failed
+added-while-watching = { $roms } added while watching
+ambiguous-title-note = (ambiguous title)
+archived-hidden = yes (hidden from list and search without --all)
+area-other = Other
+attached-to = { $name } ({ $size }) to { $place }
+attachments-summary = { $added } added, { $skipped } already attached
+builds-failed = { $roms } of { $total } couldn't be built; { $written } written to { $path }
+bytes-in-banks = { $bytes } in { $count } of { $banks }
+bytes-in-ranges = { $bytes } in { $ranges }
+bytes-to = { $count } bytes to { $path }
+catalog-of-roms = catalog of { $roms } to { $path }
+changed = changed
+collection-name = collection { $name }
+column-area = area
+column-offset = offset
+current-description = Current description: { $description }
+detached-from = { $name } from { $place }
+diff-check-on-open = { $files } failed the check on open; run check-diffs for details
+diff-files-checked = { $files } checked
+diff-files-of = { $files } of { $total }:
+diff-size-of-b = { $size } ({ $percent }% of B)
+different = different
+different-header = different header
+dry-run-would-add = Dry run: { $roms } would be added.
+dry-run-would-change = Dry run: { $roms } would change.
+duplicate-groups-found = { $groups } found; combine two with `merge <keep> <duplicate>`
+empty-emulator-command = empty emulator command
+empty-value = (empty)
+emulator-setting = { $rom_type } ROMs (set emulator.{ $extension } in { $path })
+every-rom-archived = Every ROM is archived; list --all shows them.
+expected-range = expected { $min } to { $max }
+expected-values = expected { $values }
+export-aborted = Export aborted.
+exported-with-plugin = { $roms } to { $path } ({ $plugin })
+from-the-duplicate = from the duplicate
+grpc-on = gRPC on { $address }
+header-differs = DIFFERS from stored
+header-matches = matches stored
+header-not-stored = (no stored header to compare)
+header-problem = header { $problem }
+heatmap-key = . none  ░ up to 25%  ▒ 50%  ▓ 75%  █ more
+help-details = Type `help <command>` for details and examples.
+import-conflicts = { $nodes } with different metadata:
+import-summary = { $added } added, { $skipped } skipped, { $overwritten } overwritten, { $edges_added } added, { $edges_skipped } skipped, { $diffs } copied
+libraries-match = both libraries have the same ROMs and links
+likely-related = likely versions of the same ROM; link them to keep the diff
+likely-unrelated = most bytes differ or the diff is large, so these are probably different games
+line-number = line { $line }:
+line-reason = line { $line }: { $reason }
+link-between = the link between { $a } and { $b }
+link-needs-both-files = Note: To create links, you need both ROM files. Use 'link <file1> <file2>' with both files.
+link-to = link to
+link-with = link with { $title }
+linked-note = (linked)
+links-failed-to-rebuild = { $links } failed to rebuild:
+located-summary = { $exact } exact, { $header } with a different header, { $unknown } unknown, { $unreadable } unreadable
+locations-recorded = { $locations }; info lists them
+looking-for-shares = Looking for shares on the local network...
+mapper-named-submapper = { $mapper } ({ $name }), submapper { $submapper }
+mapper-submapper = { $mapper }, submapper { $submapper }
+matches-roms = '{ $target }' matches { $count } ROMs:
+merged-into = { $from } into { $into } ({ $links } moved)
+metadata-not-imported = { $title } ({ $hash }): metadata not imported: { $reason }
+more-rows = { $count } more rows; --all shows every one
+ms-total = { $ms } ms total
+never = never
+no-changes-to-commit = No changes to commit.
+no-collections = No collections.
+no-dat-game-like = No loaded DAT lists a game named like "{ $query }"
+no-duplicates = No duplicates found.
+no-links = (no links)
+no-matches-for = No matches found for "{ $query }"
+no-roms-in = No ROMs in { $name }.
+no-roms-in-database = No ROMs in database.
+no-shares-found = No shares found.
+none-value = (none)
+not-in-database = not in database
+nothing-to-add = Nothing to add; { $known } already in the library, { $unhashed } without a SHA-256.
+nothing-to-change = Nothing to change.
+nothing-to-change-up-to-date = Nothing to change; { $roms } already up to date.
+on-wishlist = on the wishlist; no file seen yet
+press-ctrl-c-to-stop = (press Ctrl+C to stop)
+press-enter-to-stop = (press Enter to stop)
+rating-of = { $rating } of { $max }
+recent-added = added
+recent-edited = edited
+roms-already-in = { $roms } already in { $name }
+roms-behind-broken-links = { $roms } reached only through broken links:
+roms-in-library = { $roms } in the library
+roms-not-in = { $roms } not in { $name }
+roms-not-linked = { $roms } not linked to a source:
+roms-to = { $roms } to { $path }
+roms-to-wishlist = { $roms } to the wishlist
+roms-verified-through = { $roms } rebuilt and verified through { $links }
+rows-up-to-date = { $rows } already up to date
+rpc-paths-from-stdin = reading paths from stdin
+rpc-pipes = pipes
+rpc-shell-commands = shell commands
+same = same
+same-rom-data = same ROM data
+same-rom-data-different-header = same ROM data, different header
+same-rom-data-same-header = same ROM data, same header
+scan-summary = { $known } known, { $new } new, { $duplicates }, { $unreadable } unreadable
+scan-summary-found = { $known } known, { $found } found from the wishlist, { $new } new, { $duplicates }, { $unreadable } unreadable
+seen-at = seen { $time }
+session-added = { $count } added
+session-edited = { $count } edited
+session-linked = { $count } linked
+session-linked-diffs = { $count } linked ({ $diffs }, { $size })
+session-removed = { $count } removed
+set-plugin-in = { $name } (set plugin.{ $name } in { $path })
+setting-saved-in = { $name } = { $value } in { $path }
+sharing-as = { $roms } ({ $description }) as { $name } on port { $port }
+size-bytes = { $size } ({ $count } bytes)
+size-same = { $size } (same)
+summary-to = { $summary } to { $path }
+sync-conflicts = { $roms } with different metadata here and there, left as they are:
+tagged = tagged { $tags }
+timing-other = other
+timing-other-phases = (disk, database, prompts)
+title-from = { $title } from { $name }
+title-in = { $title } in { $program }
+title-into = { $from } into { $into }
+title-to = { $title } to { $name }
+wanted-add-file = on the wishlist; add or scan the file to record it
+without-sha1-one = { $count } ROM is only listed by SHA-256; add or scan the file again to record SHA-1 and CRC32
+without-sha1-other = { $count } ROMs are only listed by SHA-256; add or scan the files again to record SHA-1 and CRC32
+
+## Prompts
+
+add-new-roms-one = Add { $count } new ROM?
+add-new-roms-other = Add { $count } new ROMs?
+add-to-wishlist = Add { $roms } to the wishlist?
+delete-collection = Delete collection "{ $name }" of { $roms }? The ROMs stay in the library.
+export-to-folder = Export { $nodes } to folder "{ $path }"?
+folder-exists-continue = Folder "{ $path }" already exists. Continue?
+link-to-last = Link to "{ $title }"?
+merge-prompt = Merge '{ $from }' into '{ $into }' and remove it?
+overwrite-file = Overwrite "{ $path }"?
+prompt-description-edit = Description (press Enter to edit)
+prompt-description-edit-editor = Description (press Enter to edit, or type e to open { $editor })
+prompt-description-keep = Description (press Enter to keep, - to clear)
+prompt-description-keep-editor = Description (press Enter to keep, - to clear, or e to open { $editor })
+prompt-description-skip = Description (press Enter to skip)
+prompt-description-skip-editor = Description (press Enter to skip, or type e to open { $editor })
+prompt-languages = Languages (comma-separated)
+prompt-output-filename = Output filename
+prompt-rating = Rating (1-{ $max })
+prompt-regions = Regions (comma-separated)
+prompt-release-date = Release Date (YYYY-MM-DD)
+prompt-source-url = Source URL
+prompt-status = Status ({ $statuses })
+prompt-tags = Tags (comma-separated)
+prompt-title = Title
+prompt-version = Version
+remove-rom = Remove '{ $title }' and { $links }?
+remove-roms = Remove { $count } ROMs and { $links }?
+replace-catalog-in-folder = Folder "{ $path }" isn't empty. Replace catalog pages?
+replace-file = Replace "{ $path }"?
+replace-roms-in-folder = Folder "{ $path }" isn't empty. Replace ROMs with the same names?
+resolve-conflict = [a]ccept import, [k]eep local, [e]dit, or A/K for all remaining [k]
+resolve-conflict-answers = Please answer a, k, e, A, or K.
+this-will-remove = This will remove:
+update-roms = Update { $roms }?
+
+## Warnings
+
+diff-file-not-deleted = diff file not deleted: { $path } ({ $reason })
+emulator-exited = emulator exited with { $status }
+game-db-lookup-failed = game database lookup failed: { $error }
+no-header-metadata = No header metadata for NES file, writing raw bytes
+webhook-failed = webhook { $name } didn't take { $event }: { $reason }
@@ -0,0 +1,2 @@
+-- Track when a node's metadata was last edited (NULL if never)
+ALTER TABLE nodes ADD COLUMN updated_at TEXT;
@@ -0,0 +1,21 @@
+-- SHA-1 and CRC32 of the same headerless data as sha256, for matching DAT
+-- entries (NULL for nodes added before they were recorded)
+ALTER TABLE nodes ADD COLUMN sha1 TEXT;
+ALTER TABLE nodes ADD COLUMN crc32 INTEGER;
+
+-- Known good dumps loaded from No-Intro/Redump DAT files
+CREATE TABLE dat_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    dat_name TEXT NOT NULL,
+    game TEXT NOT NULL,
+    rom_name TEXT NOT NULL,
+    size INTEGER,
+    crc32 INTEGER,
+    sha1 TEXT,
+    sha256 TEXT
+);
+
+CREATE INDEX idx_nodes_sha1 ON nodes(sha1);
+CREATE INDEX idx_dat_entries_sha1 ON dat_entries(sha1);
+CREATE INDEX idx_dat_entries_sha256 ON dat_entries(sha256);
+CREATE INDEX idx_dat_entries_dat_name ON dat_entries(dat_name);
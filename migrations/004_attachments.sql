@@ -0,0 +1,18 @@
+-- Files attached to a node, or to the link between two nodes (a hack's
+-- README, a docs PDF). Contents are stored in the attachments directory,
+-- named by their SHA-256, once however many times they're attached.
+CREATE TABLE attachments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    node_id INTEGER NOT NULL REFERENCES nodes(id),
+    -- The other end of a link (greater than node_id), or NULL when the file
+    -- is attached to the node itself
+    linked_node_id INTEGER REFERENCES nodes(id),
+    name TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX idx_attachments_node ON attachments(node_id);
+CREATE INDEX idx_attachments_linked_node ON attachments(linked_node_id);
+CREATE INDEX idx_attachments_sha256 ON attachments(sha256);
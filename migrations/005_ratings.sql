@@ -0,0 +1,4 @@
+-- Backlog tracking: a 1-5 star rating (NULL if unrated), and whether the
+-- ROM is unplayed, being played, finished, or abandoned
+ALTER TABLE nodes ADD COLUMN rating INTEGER CHECK (rating BETWEEN 1 AND 5);
+ALTER TABLE nodes ADD COLUMN play_status TEXT NOT NULL DEFAULT 'unplayed';
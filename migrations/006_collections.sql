@@ -0,0 +1,17 @@
+-- Named collections (playlists) grouping any nodes. Unlike tags, a
+-- collection is a list of its own: a node can be in many, and removing a
+-- collection leaves its nodes alone.
+CREATE TABLE collections (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE collection_nodes (
+    collection_id INTEGER NOT NULL REFERENCES collections(id),
+    node_id INTEGER NOT NULL REFERENCES nodes(id),
+    added_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (collection_id, node_id)
+);
+
+CREATE INDEX idx_collection_nodes_node ON collection_nodes(node_id);
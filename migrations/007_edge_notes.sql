@@ -0,0 +1,3 @@
+-- A free-text note on what changed between two linked ROMs, e.g.
+-- "retranslation, fixes item menu bug"; both directions carry the same note
+ALTER TABLE edges ADD COLUMN note TEXT;
@@ -0,0 +1,4 @@
+-- Regions a ROM was released for (e.g. "Japan,USA") and languages its text
+-- is in (e.g. "en,fr"), comma-separated; NULL if unknown
+ALTER TABLE nodes ADD COLUMN region TEXT;
+ALTER TABLE nodes ADD COLUMN language TEXT;
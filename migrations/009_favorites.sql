@@ -0,0 +1,2 @@
+-- Pinned by the user with `fav`, for quick access in large libraries
+ALTER TABLE nodes ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0;
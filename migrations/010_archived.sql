@@ -0,0 +1,2 @@
+-- Hidden from list and search unless --all is given, e.g. superseded hack versions
+ALTER TABLE nodes ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
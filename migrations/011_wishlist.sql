@@ -0,0 +1,2 @@
+-- Wishlist nodes, created from a hash and metadata before their file is seen
+ALTER TABLE nodes ADD COLUMN missing INTEGER NOT NULL DEFAULT 0;
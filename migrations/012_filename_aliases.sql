@@ -0,0 +1,12 @@
+-- Every file name a node's contents have been seen under (by add, check,
+-- scan, or watch), since the same ROM circulates under many names and
+-- nodes.filename keeps only one
+CREATE TABLE filename_aliases (
+    node_id INTEGER NOT NULL REFERENCES nodes(id),
+    filename TEXT NOT NULL,
+    first_seen TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (node_id, filename)
+);
+
+INSERT INTO filename_aliases (node_id, filename, first_seen)
+SELECT id, filename, created_at FROM nodes WHERE filename IS NOT NULL;
@@ -0,0 +1,4 @@
+-- SHA-256 of the diff file, to catch damaged diffs without rebuilding a ROM;
+-- NULL for diffs linked before checksums were kept, until `check-diffs`
+-- records them
+ALTER TABLE edges ADD COLUMN diff_sha256 TEXT;
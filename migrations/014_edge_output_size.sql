@@ -0,0 +1,4 @@
+-- Size of the ROM data a diff builds, checked whenever it's applied so a
+-- damaged or misfiled diff fails at once; NULL for diffs linked before it
+-- was kept, until `check-diffs` records it
+ALTER TABLE edges ADD COLUMN output_size INTEGER;
@@ -0,0 +1,9 @@
+-- Where on disk a node's file has been found by `locate --record`, and
+-- whether the file there had the header stored with the node
+CREATE TABLE locations (
+    node_id INTEGER NOT NULL REFERENCES nodes(id),
+    path TEXT NOT NULL,
+    header_matches INTEGER NOT NULL,
+    last_seen TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (node_id, path)
+);
@@ -0,0 +1,3 @@
+-- Lets `added:` searches and `list --since` find ROMs by when they were
+-- added without scanning every row
+CREATE INDEX idx_nodes_created_at ON nodes(created_at);
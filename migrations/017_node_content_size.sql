@@ -0,0 +1,4 @@
+-- Length of a node's ROM data, headers excluded, as hashed when it was
+-- added; NULL for nodes added before it was kept, until their file is
+-- added, linked, or scanned again
+ALTER TABLE nodes ADD COLUMN content_size INTEGER;
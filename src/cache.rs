@@ -0,0 +1,149 @@
+//! On-disk cache of metadata lookups that may go over the network (e.g.
+//! plugins querying online databases), so adding the same game again
+//! doesn't fetch it again, and lookups that fail while offline can still
+//! use what was fetched before.
+//!
+//! Each reply is a JSON file named by its source and key, under the
+//! user's cache folder (e.g. `~/.cache/dromos/lookups/<source>/<key>.json`).
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{DromosError, Result};
+
+/// How long cached replies are used before fetching again, by default.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// A folder of cached lookup replies.
+#[derive(Debug, Clone)]
+pub struct LookupCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+/// A reply found in the cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cached {
+    pub reply: Value,
+    /// Whether the reply is newer than the cache's TTL; stale replies are
+    /// only for when fetching fails
+    pub fresh: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// Seconds since the Unix epoch
+    fetched_at: u64,
+    reply: Value,
+}
+
+impl LookupCache {
+    /// A cache in `dir` whose replies are fresh for `ttl`.
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> LookupCache {
+        LookupCache {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// The cache in the user's cache folder, if there is one.
+    pub fn default_location(ttl: Duration) -> Option<LookupCache> {
+        let proj_dirs = ProjectDirs::from("", "", "dromos")?;
+        Some(LookupCache::new(proj_dirs.cache_dir().join("lookups"), ttl))
+    }
+
+    /// The reply cached for `key` from `source`, if any. Unreadable
+    /// entries count as missing.
+    pub fn get(&self, source: &str, key: &str) -> Option<Cached> {
+        let text = std::fs::read_to_string(self.entry_path(source, key)).ok()?;
+        let entry: Entry = serde_json::from_str(&text).ok()?;
+        let age = now().saturating_sub(entry.fetched_at);
+        Some(Cached {
+            reply: entry.reply,
+            fresh: age < self.ttl.as_secs(),
+        })
+    }
+
+    /// Cache `reply` for `key` from `source`, replacing any older reply.
+    pub fn put(&self, source: &str, key: &str, reply: &Value) -> Result<()> {
+        let path = self.entry_path(source, key);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(DromosError::file(parent))?;
+        }
+        let entry = Entry {
+            fetched_at: now(),
+            reply: reply.clone(),
+        };
+        // Write then rename, so a reader never sees half an entry
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, serde_json::to_vec(&entry)?)
+            .map_err(DromosError::file(&partial))?;
+        std::fs::rename(&partial, &path).map_err(DromosError::file(&path))
+    }
+
+    fn entry_path(&self, source: &str, key: &str) -> PathBuf {
+        self.dir
+            .join(safe_name(source))
+            .join(format!("{}.json", safe_name(key)))
+    }
+}
+
+/// `name` with anything but letters, digits, `-`, and `_` replaced, so it
+/// can't leave the cache folder.
+fn safe_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_lookup_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = LookupCache::new(temp_dir.path(), DEFAULT_TTL);
+        assert_eq!(cache.get("romhacking", "abc"), None);
+
+        cache
+            .put("romhacking", "abc", &json!({"title": "Hack"}))
+            .unwrap();
+        assert_eq!(
+            cache.get("romhacking", "abc"),
+            Some(Cached {
+                reply: json!({"title": "Hack"}),
+                fresh: true,
+            })
+        );
+        assert_eq!(cache.get("other", "abc"), None);
+
+        // Nothing is fresh with no TTL, but stale replies are still there
+        let expired = LookupCache::new(temp_dir.path(), Duration::ZERO);
+        assert_eq!(
+            expired.get("romhacking", "abc").map(|c| c.fresh),
+            Some(false)
+        );
+
+        cache.put("../escape", "x", &Value::Null).unwrap();
+        assert!(temp_dir.path().join("___escape/x.json").is_file());
+    }
+}
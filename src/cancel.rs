@@ -0,0 +1,90 @@
+//! Cooperative cancellation for long operations.
+//!
+//! Long operations take a [`CancelToken`] and poll it between steps,
+//! unwinding with [`DromosError::Cancelled`], rolling back their database
+//! writes and removing partial files. A GUI or server cancels an operation by
+//! calling [`CancelToken::cancel`] on a clone from another thread.
+//!
+//! A token only answers to its own flag, and to a signal flag if it was made
+//! with [`with_signal`](CancelToken::with_signal); the library never watches
+//! Ctrl+C itself. The shell links its tokens to Ctrl+C in
+//! `cli::interrupt`.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::{DromosError, Result};
+
+/// Cancels an operation it was passed to. Clones share the same flag, so
+/// keep one and pass another to the operation.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    signal: Option<&'static AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that also counts as cancelled once `signal` is set, e.g. by a
+    /// signal handler, which can only touch statics.
+    pub fn with_signal(signal: &'static AtomicBool) -> Self {
+        Self {
+            cancelled: Arc::default(),
+            signal: Some(signal),
+        }
+    }
+
+    /// Ask the operation to stop at its next step. Safe to call from any
+    /// thread, and more than once.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [`cancel`](Self::cancel) was called on this token or a clone,
+    /// or its signal flag is set.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+            || self.signal.is_some_and(|s| s.load(Ordering::SeqCst))
+    }
+
+    /// Err(Cancelled) if the token was cancelled.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(DromosError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_cancelled_from_clone() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        std::thread::spawn(move || clone.cancel()).join().unwrap();
+        assert!(token.is_cancelled());
+        assert!(matches!(token.check(), Err(DromosError::Cancelled)));
+        assert!(!CancelToken::new().is_cancelled());
+    }
+
+    #[test]
+    fn test_token_with_signal() {
+        static SIGNAL: AtomicBool = AtomicBool::new(false);
+        let token = CancelToken::with_signal(&SIGNAL);
+        let plain = CancelToken::new();
+        assert!(token.check().is_ok());
+        SIGNAL.store(true, Ordering::SeqCst);
+        assert!(matches!(token.check(), Err(DromosError::Cancelled)));
+        assert!(token.clone().is_cancelled());
+        // Tokens without the signal don't see it
+        assert!(plain.check().is_ok());
+    }
+}
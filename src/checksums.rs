@@ -0,0 +1,215 @@
+//! Checksum files for folders of built ROMs, in the formats generic tools
+//! verify: `sha256sum -c` reads SHA256SUMS, and SFV checkers such as
+//! QuickSFV or cksfv read `.sfv` files.
+//!
+//! Checksums cover whole files, headers included, since that's what the
+//! tools hash; paths are relative to the folder, with `/` between folders.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use sha2::{Digest, Sha256};
+
+use crate::cancel::CancelToken;
+use crate::error::{DromosError, Result};
+use crate::progress::{Progress, ProgressSink, Stage};
+
+/// Which checksum file to write.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChecksumFormat {
+    /// `<sha256>  <path>` lines, as written by `sha256sum`
+    #[default]
+    Sha256Sums,
+    /// `<path> <CRC32>` lines, the Simple File Verification format
+    Sfv,
+}
+
+impl ChecksumFormat {
+    /// The name the file is written under in the folder it covers
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ChecksumFormat::Sha256Sums => "SHA256SUMS",
+            ChecksumFormat::Sfv => "checksums.sfv",
+        }
+    }
+}
+
+impl FromStr for ChecksumFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha256sums" | "sha256" => Ok(ChecksumFormat::Sha256Sums),
+            "sfv" => Ok(ChecksumFormat::Sfv),
+            _ => Err(format!(
+                "unknown checksum format `{}` (expected one of: sha256sums, sfv)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ChecksumFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChecksumFormat::Sha256Sums => write!(f, "sha256sums"),
+            ChecksumFormat::Sfv => write!(f, "sfv"),
+        }
+    }
+}
+
+/// One file's line in a checksum file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checksummed {
+    /// Relative to the folder, with `/` between folders
+    pub path: String,
+    /// Lowercase hex SHA-256, or uppercase hex CRC32 for SFV
+    pub checksum: String,
+}
+
+/// Checksum every file under `dir`, sorted by path, reporting progress per
+/// file. Checksum files already in the top folder are left out, so writing
+/// a new one over them doesn't list the old.
+pub fn checksum_folder(
+    dir: &Path,
+    format: ChecksumFormat,
+    progress: &mut dyn ProgressSink,
+    cancel: &CancelToken,
+) -> Result<Vec<Checksummed>> {
+    let files = list_files(dir)?;
+    files
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            cancel.check()?;
+            let relative = relative_path(dir, path);
+            let bytes = fs::read(path).map_err(DromosError::file(path))?;
+            progress.progress(&Progress {
+                stage: Stage::Hashing,
+                item: &relative,
+                current: i + 1,
+                total: files.len(),
+                bytes: bytes.len() as u64,
+            });
+            let checksum = match format {
+                ChecksumFormat::Sha256Sums => hex::encode(Sha256::digest(&bytes)),
+                ChecksumFormat::Sfv => format!("{:08X}", crc32fast::hash(&bytes)),
+            };
+            Ok(Checksummed {
+                path: relative,
+                checksum,
+            })
+        })
+        .collect()
+}
+
+/// The contents of the checksum file listing `entries`
+pub fn render(entries: &[Checksummed], format: ChecksumFormat) -> String {
+    let mut out = String::new();
+    if format == ChecksumFormat::Sfv {
+        out.push_str(&format!("; Generated by dromos {}\n", crate::VERSION));
+    }
+    for entry in entries {
+        match format {
+            ChecksumFormat::Sha256Sums => {
+                out.push_str(&format!("{}  {}\n", entry.checksum, entry.path))
+            }
+            ChecksumFormat::Sfv => out.push_str(&format!("{} {}\n", entry.path, entry.checksum)),
+        }
+    }
+    out
+}
+
+/// Every regular file under `dir` besides checksum files in its top
+/// folder, sorted by path
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let skipped = [
+        ChecksumFormat::Sha256Sums.file_name(),
+        ChecksumFormat::Sfv.file_name(),
+    ];
+    let mut found = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current).map_err(DromosError::file(&current))? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file()
+                && !(current == dir
+                    && skipped
+                        .iter()
+                        .any(|name| entry.file_name().eq_ignore_ascii_case(name)))
+            {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+fn relative_path(dir: &Path, path: &Path) -> String {
+    path.strip_prefix(dir)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::NoProgress;
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("sfv".parse(), Ok(ChecksumFormat::Sfv));
+        assert_eq!("SHA256SUMS".parse(), Ok(ChecksumFormat::Sha256Sums));
+        assert!("md5".parse::<ChecksumFormat>().is_err());
+    }
+
+    #[test]
+    fn test_checksum_folder() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir(dir.join("hacks")).unwrap();
+        fs::write(dir.join("b.nes"), b"abc").unwrap();
+        fs::write(dir.join("hacks/a.nes"), b"").unwrap();
+        fs::write(dir.join("SHA256SUMS"), b"old").unwrap();
+        fs::write(dir.join("hacks/checksums.sfv"), b"kept").unwrap();
+
+        let sums = checksum_folder(
+            dir,
+            ChecksumFormat::Sha256Sums,
+            &mut NoProgress,
+            &CancelToken::new(),
+        )
+        .unwrap();
+        let paths: Vec<&str> = sums.iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(paths, ["b.nes", "hacks/a.nes", "hacks/checksums.sfv"]);
+        assert_eq!(
+            render(&sums[..1], ChecksumFormat::Sha256Sums),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  b.nes\n"
+        );
+
+        let sfv = checksum_folder(
+            dir,
+            ChecksumFormat::Sfv,
+            &mut NoProgress,
+            &CancelToken::new(),
+        )
+        .unwrap();
+        assert_eq!(sfv[0].checksum, "352441C2");
+        assert_eq!(sfv[1].checksum, "00000000");
+        let rendered = render(&sfv, ChecksumFormat::Sfv);
+        assert!(rendered.starts_with("; Generated by dromos"));
+        assert!(rendered.contains("\nb.nes 352441C2\nhacks/a.nes 00000000\n"));
+    }
+}
@@ -0,0 +1,862 @@
+//! Command metadata shared by `help` and tab completion.
+//!
+//! Every shell command has one entry here. The `help` listing, `help <command>`,
+//! and the completer's command names and argument kinds all come from this
+//! table, so adding a command in one place can't leave the others behind.
+
+/// Which arguments of a command complete from the ROM library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeArg {
+    None,
+    /// The argument at this 1-based position
+    At(usize),
+    /// Every argument
+    Every,
+}
+
+/// Help text and completion behavior for one command.
+#[derive(Debug)]
+pub struct CommandInfo {
+    /// The command name followed by any aliases
+    pub names: &'static [&'static str],
+    /// Argument synopsis shown after the names
+    pub args: &'static str,
+    /// One-line description for the `help` listing
+    pub summary: &'static str,
+    /// Longer description for `help <command>`
+    pub details: &'static str,
+    pub examples: &'static [&'static str],
+    /// Names of related commands
+    pub related: &'static [&'static str],
+    /// Arguments complete as file paths (a ROM argument falls back to files
+    /// when no ROM matches)
+    pub files: bool,
+    pub node_arg: NodeArg,
+}
+
+impl CommandInfo {
+    pub fn name(&self) -> &'static str {
+        self.names[0]
+    }
+
+    /// Names and argument synopsis, e.g. `list, ls [--columns <c,...>]`.
+    pub fn usage(&self) -> String {
+        let names = self.names.join(", ");
+        if self.args.is_empty() {
+            names
+        } else {
+            format!("{} {}", names, self.args)
+        }
+    }
+
+    /// True if `position` (1-based) is a ROM hash or title argument.
+    pub fn takes_node_at(&self, position: usize) -> bool {
+        match self.node_arg {
+            NodeArg::None => false,
+            NodeArg::At(p) => p == position,
+            NodeArg::Every => position >= 1,
+        }
+    }
+}
+
+/// Look up a command by name or alias (case-insensitive).
+pub fn find(name: &str) -> Option<&'static CommandInfo> {
+    let name = name.to_lowercase();
+    COMMANDS.iter().find(|c| c.names.contains(&name.as_str()))
+}
+
+/// Every command name and alias, in table order.
+pub fn all_names() -> impl Iterator<Item = &'static str> {
+    COMMANDS.iter().flat_map(|c| c.names.iter().copied())
+}
+
+/// All shell commands, in the order `help` lists them.
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        names: &["add"],
+        args: "<file|pattern>...",
+        summary: "Add ROMs to the database",
+        details: "Hashes each ROM and prompts for its metadata. Glob patterns are expanded \
+                  internally. With --stdin, newline-separated paths are read from stdin and \
+                  titled from their filenames instead of prompting.",
+        examples: &["add \"Super Game (USA).nes\"", "add roms/*.nes"],
+        related: &["link", "scan", "hash"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["attach"],
+        args: "<file> <hash> [hash]",
+        summary: "Attach a file (readme, docs) to a ROM or link",
+        details: "Stores a copy of the file and attaches it to the ROM, or with a second hash, \
+                  to the link between the two ROMs. Attaching another file with the same name \
+                  replaces it. info lists attachments, and export and import carry them along.",
+        examples: &[
+            "attach README.txt abc12345",
+            "attach \"patch notes.pdf\" abc12345 c32154ba",
+        ],
+        related: &["detach", "info"],
+        files: true,
+        node_arg: NodeArg::At(2),
+    },
+    CommandInfo {
+        names: &["build"],
+        args: "<source> [hash|title] [-o <file>]",
+        summary: "Build a ROM from source to target (--overwrite to replace)",
+        details: "Finds the shortest chain of diffs from a ROM you have to the target and \
+                  applies them. The target is a hash prefix or a title, matched exactly, \
+                  by substring, or fuzzily, with a choice offered when several ROMs match. \
+                  Without one, a picker lists the library. -o writes to the \
+                  given path without prompting; --overwrite replaces an existing file.",
+        examples: &[
+            "build base.nes c32154ba",
+            "build base.nes c32154ba -o hack.nes",
+            "build base.nes \"Zelda Redux [2.1]\"",
+        ],
+        related: &["links", "link", "play"],
+        files: true,
+        node_arg: NodeArg::At(2),
+    },
+    CommandInfo {
+        names: &["build-all"],
+        args: "<hash>|--query <q> <folder> [source...]",
+        summary: "Build every ROM in a linked group into a folder",
+        details: "Builds every ROM linked to the given one, or with --query every ROM \
+                  matching the search, into the folder, named from their titles. ROMs are \
+                  built from the source files or the places locate --record found them. \
+                  Each ROM is reported as written or failed, and a failure doesn't stop \
+                  the rest.",
+        examples: &[
+            "build-all abc12345 hacks base.nes",
+            "build-all --query tag:translation out",
+        ],
+        related: &["build", "sdcard", "checksums"],
+        files: true,
+        node_arg: NodeArg::At(1),
+    },
+    CommandInfo {
+        names: &["check"],
+        args: "<file>",
+        summary: "Check if a ROM is in the database",
+        details: "Hashes the file and reports whether the library knows it, comparing the \
+                  file's header against the stored one. Headers that contradict themselves or \
+                  the file, which strict mode refuses to add, are flagged.",
+        examples: &["check mystery.nes"],
+        related: &["hash", "info"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["inspect"],
+        args: "<hash> <hash> [source...] [--all]",
+        summary: "Hexdump where two ROMs differ",
+        details: "Gets both ROMs' data from the source files or the places locate --record \
+                  found them, building through links where needed, and prints the rows \
+                  that differ side by side, each labeled with its PRG or CHR bank. Shows \
+                  the first 64 rows unless --all is given.",
+        examples: &[
+            "inspect abc123 def456",
+            "inspect abc123 def456 base.nes --all",
+        ],
+        related: &["banks", "compare", "links", "locate"],
+        files: true,
+        node_arg: NodeArg::Every,
+    },
+    CommandInfo {
+        names: &["banks"],
+        args: "<hash> <hash> [source...]",
+        summary: "Show which banks differ between two ROMs",
+        details: "Gets both ROMs' data as inspect does and shows, for each 16 KB PRG and \
+                  8 KB CHR bank, how many bytes differ: a heatmap with one cell per bank, \
+                  shaded by the share that changed, followed by the changed banks' counts. \
+                  Data past the banks the header declares is shown in 16 KB blocks.",
+        examples: &["banks abc123 def456", "banks abc123 def456 base.nes"],
+        related: &["inspect", "compare"],
+        files: true,
+        node_arg: NodeArg::Every,
+    },
+    CommandInfo {
+        names: &["compare", "cmp"],
+        args: "<file1> <file2>",
+        summary: "Compare two ROM files before linking",
+        details: "Compares the files' data with headers removed: whether it's identical, \
+                  the size difference, how many bytes changed in each PRG and CHR bank, and \
+                  the size of the diff link would store, to tell whether two files are \
+                  really versions of the same game. Nothing is added.",
+        examples: &["compare \"Game (USA).nes\" \"Game (Hack).nes\""],
+        related: &["link", "check"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["check-diffs"],
+        args: "",
+        summary: "Check every diff file for damage",
+        details: "Checks that each link's diff file exists, has the size and SHA-256 recorded \
+                  when it was made, and is a well-formed bsdiff patch, without building any \
+                  ROM. Diffs linked before checksums were kept have theirs recorded. The \
+                  shell also runs this in the background when it opens a small library.",
+        examples: &["check-diffs"],
+        related: &["validate-all", "links"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["validate-all", "validate"],
+        args: "<source>...",
+        summary: "Rebuild every linked ROM and check each link",
+        details: "Starting from the source files, applies every link's diff and checks the \
+                  hash of what it builds, reporting each link that builds the wrong ROM or \
+                  can't be applied, the ROMs that only broken links reach, and the ROMs not \
+                  linked to any source. Nothing is written. Run it on a freshly imported \
+                  export to prove it can rebuild everything it holds.",
+        examples: &["validate-all smb.nes", "validate-all smb.nes zelda.nes"],
+        related: &["build", "check", "import"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["collection", "collections"],
+        args: "[<sub> <name> [hash...]]",
+        summary: "Group ROMs into named collections",
+        details: "A collection is a named list of ROMs, such as favorites or a tournament \
+                  set; a ROM can be in any number of them. collection create <name> and \
+                  collection delete <name> make and remove one (its ROMs stay in the \
+                  library); collection add <name> <hash...> and collection remove <name> \
+                  <hash...> change what it holds. collection list shows every collection \
+                  with its size, or with a name, its ROMs. collection build <name> \
+                  <folder> <source...> builds its ROMs onto an SD card as sdcard does. \
+                  Search for collection:<name>, or export --collection <name>, to work \
+                  with just its ROMs.",
+        examples: &[
+            "collection create favorites",
+            "collection add favorites abc12345 \"Super Mario Bros. 3\"",
+            "collection list favorites",
+            "collection build favorites /media/sd roms --by letter",
+        ],
+        related: &["search", "export", "sdcard"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["dat"],
+        args: "import|export <file.dat>",
+        summary: "Import a No-Intro/Redump DAT, or export the library as one",
+        details: "dat import loads the DAT's ROM entries, replacing any earlier import of the \
+                  same DAT. ROMs whose hash matches an entry are shown as verified good dumps, \
+                  with their canonical name, in list and info. Use a headerless DAT for NES \
+                  ROMs. dat export writes a DAT listing the library's ROMs, or those matching a search \
+                  query and --component <hash>, so clrmamepro or RomVault can audit a folder of \
+                  built ROMs. --name sets the DAT's name.",
+        examples: &[
+            "dat import \"Nintendo - Nintendo Entertainment System (Headerless).dat\"",
+            "dat export hacks.dat tag:hack",
+            "dat export zelda.dat --component abc123",
+        ],
+        related: &["list", "info", "search", "export"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["detach"],
+        args: "<name> <hash> [hash]",
+        summary: "Remove an attached file from a ROM or link",
+        details: "Removes the attachment with the given file name from the ROM, or with a \
+                  second hash, from the link between the two ROMs. The stored copy is deleted \
+                  once nothing else uses it.",
+        examples: &["detach README.txt abc12345"],
+        related: &["attach", "info"],
+        files: false,
+        node_arg: NodeArg::At(2),
+    },
+    CommandInfo {
+        names: &["edit"],
+        args: "[hash] [--field <value>]",
+        summary: "Edit metadata for a ROM (see README for flags)",
+        details: "Without flags, prompts for each field with the current value filled in. \
+                  Flags change fields directly: --title, --version, --url, --date \
+                  <YYYY-MM-DD>, --description, --rating <1-5>, --status \
+                  <unplayed|playing|finished|abandoned>, --region <r,...>, --lang <l,...>, \
+                  --add-tag, --remove-tag, and --clear \
+                  <version|url|date|tags|description|rating|status|region|lang>. `edit --csv \
+                  <file>` updates many \
+                  ROMs from a CSV with a hash or title column and a column per field, \
+                  such as a corrected `report`; --dry-run shows the changes without \
+                  making them.",
+        examples: &[
+            "edit abc12345",
+            "edit abc12345 --version 1.1 --add-tag translation",
+            "edit abc12345 --rating 4 --status finished",
+            "edit abc12345 --region J --lang en",
+            "edit --csv fixes.csv --dry-run",
+        ],
+        related: &["info", "set", "report", "bulk-edit"],
+        files: false,
+        node_arg: NodeArg::At(1),
+    },
+    CommandInfo {
+        names: &["bulk-edit"],
+        args: "<query> --<field> <value>...",
+        summary: "Edit metadata for every ROM matching a search",
+        details: "Applies edit's flags (all but --title) to each ROM the search query \
+                  matches, such as --add-tag, --remove-tag, --region, --lang, --status, \
+                  --rating, and --clear <field>; --set-<field> works as well. Lists every \
+                  change and asks before making them all together. Archived ROMs are left \
+                  out unless --all is given; --dry-run only lists the changes.",
+        examples: &[
+            "bulk-edit tag:hack mario --add-tag smb-hack",
+            "bulk-edit region:J --set-lang ja --dry-run",
+        ],
+        related: &["edit", "search"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["export"],
+        args: "[hash] <path> [--html|--git|--plugin <p>]",
+        summary: "Export ROMs to a folder",
+        details: "Writes ROM metadata and diffs to a folder that `import` can read. With a \
+                  hash, only the ROMs linked to that one are exported. With --html, writes \
+                  a static web catalog instead: a searchable index and a page per ROM with \
+                  its metadata and a drawing of its links. With --git (or `set export_git \
+                  on`), the folder is also a Git repository: existing files are replaced \
+                  without asking and the changes are committed with a summary. With \
+                  --plugin, the named plugin from the config file writes the export in its \
+                  own format. --collection <name> exports only the ROMs in that collection.",
+        examples: &[
+            "export backup",
+            "export abc12345 family",
+            "export --html catalog",
+            "export shared --git",
+            "export launchbox --plugin launchbox",
+        ],
+        related: &["import"],
+        files: true,
+        node_arg: NodeArg::At(1),
+    },
+    CommandInfo {
+        names: &["fetch"],
+        args: "[name]",
+        summary: "List shares on the local network, or import one",
+        details: "Listens a few seconds for libraries offered with share on the local \
+                  network and lists them, with what they share. Given a share's name, \
+                  imports what it offers, as import does from a dromos server.",
+        examples: &["fetch", "fetch den"],
+        related: &["share", "import"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["import"],
+        args: "<path|url> [hash] [--tag <tag>]...",
+        summary: "Import ROMs from a folder or a dromos server",
+        details: "Adds the ROMs and diffs from an exported folder. For ROMs already in the \
+                  library with different metadata, asks whether to accept the import, keep \
+                  the local values, or edit them. Given the http://host:port address of \
+                  dromos serve instead, downloads from it and imports the same way: all of \
+                  its library, the ROMs linked to a hash, or the ROMs with any --tag given \
+                  (with their links to ROMs either library has).",
+        examples: &[
+            "import backup",
+            "import http://192.168.1.20:8080 abc123",
+            "import http://192.168.1.20:8080 --tag translation",
+        ],
+        related: &["export", "sync"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["info"],
+        args: "<hash|title>",
+        summary: "Show everything known about a ROM",
+        details: "Shows metadata, the decoded header (mapper, NES 2.0 fields), links, \
+                  attached files, and when the ROM was added and last edited.",
+        examples: &["info abc12345", "info Super Game"],
+        related: &["links", "edit", "attach"],
+        files: false,
+        node_arg: NodeArg::At(1),
+    },
+    CommandInfo {
+        names: &["link"],
+        args: "<file1> [file2] [--note <text>]",
+        summary: "Create bidirectional links between ROMs",
+        details: "Creates diffs in both directions between two ROM files, adding either one \
+                  to the library first if needed. With one file, links it to the last ROM \
+                  added. --note records what changed between the two, such as \
+                  \"retranslation, fixes item menu bug\"; links and info show it under the \
+                  link.",
+        examples: &[
+            "link base.nes hack.nes",
+            "link v1.0.nes v1.1.nes --note \"fixes item menu bug\"",
+        ],
+        related: &["links", "build"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["links"],
+        args: "[file|hash]",
+        summary: "Show all links for a ROM",
+        details: "Lists the ROMs directly linked to this one, with diff sizes and any \
+                  notes given when they were linked.",
+        examples: &["links abc12345", "links base.nes"],
+        related: &["link", "info"],
+        files: true,
+        node_arg: NodeArg::At(1),
+    },
+    CommandInfo {
+        names: &["list", "ls"],
+        args: "[--columns <c,...>]",
+        summary: "List all ROMs (--sort <column>, --reverse; see README)",
+        details: "Columns: title, version, hash, type, size, links, tags, date, added, dat, \
+                  rating, status, region, lang. --sort orders by any column and --reverse \
+                  flips the order. --region and --lang list only ROMs for a region or in a \
+                  language, and --fav only favorites. --since <date|age> lists only ROMs \
+                  added on or after a date (YYYY-MM-DD) or within an age like 30d. \
+                  --all includes archived ROMs. The default layout comes from list_columns and list_sort in dromos.conf. \
+                  A search query after the options lists only the ROMs it matches.",
+        examples: &[
+            "list",
+            "list --columns title,size,links --sort size --reverse",
+            "list --columns title,rating,status status:playing",
+            "list --region J --lang en",
+            "list --since 2024-01-01",
+        ],
+        related: &["search", "recent", "fav", "archive"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["open"],
+        args: "[hash|title]",
+        summary: "Open a ROM's source URL in the browser",
+        details: "Opens the ROM's http or https source URL with the system's default browser.",
+        examples: &["open abc12345"],
+        related: &["info", "edit"],
+        files: false,
+        node_arg: NodeArg::At(1),
+    },
+    CommandInfo {
+        names: &["fav"],
+        args: "[hash|title]",
+        summary: "Pin or unpin a ROM as a favorite",
+        details: "Toggles the ROM's favorite flag. Favorites are listed first by the ROM \
+                  picker and marked with ★; list --fav and the is:fav search qualifier show \
+                  only favorites. Favorites are local to the library and are not exported \
+                  or synced.",
+        examples: &["fav abc12345", "list --fav"],
+        related: &["list", "search"],
+        files: false,
+        node_arg: NodeArg::At(1),
+    },
+    CommandInfo {
+        names: &["archive"],
+        args: "[hash|title]",
+        summary: "Hide a ROM from list and search without removing it",
+        details: "Archived ROMs stay in the graph, so builds through them still work, but \
+                  list and search leave them out unless given --all. The is:archived search \
+                  qualifier finds them. Archiving is local to the library and is not \
+                  exported or synced.",
+        examples: &["archive abc12345", "list --all is:archived"],
+        related: &["unarchive", "list", "search"],
+        files: false,
+        node_arg: NodeArg::At(1),
+    },
+    CommandInfo {
+        names: &["unarchive"],
+        args: "[hash|title]",
+        summary: "Show an archived ROM in list and search again",
+        details: "Clears the ROM's archived flag.",
+        examples: &["unarchive abc12345"],
+        related: &["archive"],
+        files: false,
+        node_arg: NodeArg::At(1),
+    },
+    CommandInfo {
+        names: &["play"],
+        args: "<source> [hash]",
+        summary: "Build a ROM and launch it in the configured emulator",
+        details: "Builds the target as build does, writes it to a temporary file, and runs \
+                  the emulator set for its type in dromos.conf (e.g. emulator.nes = mesen \
+                  {rom}). The file is deleted when the emulator exits, so the command must \
+                  wait for it rather than hand the ROM to an already running emulator. \
+                  Ctrl+C stops the emulator and still removes the file.",
+        examples: &["play base.nes c32154ba"],
+        related: &["build"],
+        files: true,
+        node_arg: NodeArg::At(2),
+    },
+    CommandInfo {
+        names: &["rm", "remove"],
+        args: "[hash|title]...",
+        summary: "Remove ROMs and all their links",
+        details: "Removes each ROM with its links and diff files, after one confirmation \
+                  that lists everything affected.",
+        examples: &["rm abc12345", "rm abc12345 c32154ba"],
+        related: &["list", "merge"],
+        files: false,
+        node_arg: NodeArg::Every,
+    },
+    CommandInfo {
+        names: &["dedupe"],
+        args: "[query]",
+        summary: "Find ROMs that look like the same game twice",
+        details: "Groups ROMs whose titles match once case, punctuation, bracketed tags \
+                  like (USA), and \"The\" are ignored, or are nearly the same (but never \
+                  differ by a number, so sequels stay apart), and whose versions match. \
+                  A search query limits the ROMs compared.",
+        examples: &["dedupe", "dedupe tag:translation"],
+        related: &["merge", "search"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["merge"],
+        args: "<keep> <dup> [source...]",
+        summary: "Merge a duplicate ROM into another and remove it",
+        details: "The kept ROM's metadata wins; the duplicate fills in fields it's missing \
+                  and adds its tags, regions, languages, collections, and attachments. \
+                  ROMs linked only to the duplicate are linked to the kept ROM instead, \
+                  which needs ROM files to build both from: give any files linked to them \
+                  after the two ROMs. Shows the changes and asks before merging.",
+        examples: &["merge abc12345 c32154ba", "merge abc12345 c32154ba smb.nes"],
+        related: &["dedupe", "rm"],
+        files: true,
+        node_arg: NodeArg::Every,
+    },
+    CommandInfo {
+        names: &["checksums"],
+        args: "<folder> [--format sha256sums|sfv]",
+        summary: "Write a checksum file for a folder",
+        details: "Checksums every file in the folder and its subfolders, headers included, \
+                  and writes SHA256SUMS (for sha256sum -c) or, with --format sfv, \
+                  checksums.sfv (CRC32, for SFV checkers) into the folder, so built ROMs \
+                  can be verified later without dromos.",
+        examples: &["checksums out", "checksums /media/sd --format sfv"],
+        related: &["build", "sdcard", "export"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["locate"],
+        args: "<folder> [--record]",
+        summary: "Audit a folder's ROMs against the library",
+        details: "Searches the folder recursively and reports each ROM file as an exact \
+                  match for a ROM in the library, the same ROM with a different header, \
+                  unknown, or unreadable. Nothing is added. --record remembers where each \
+                  known ROM was found; info lists the places.",
+        examples: &["locate ~/Downloads", "locate /mnt/old-roms --record"],
+        related: &["scan", "check", "info"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["scan"],
+        args: "<folder> [--prompt]",
+        summary: "Find ROMs in a folder and add the new ones",
+        details: "Searches the folder recursively and adds ROMs not yet in the library, \
+                  titled from their filenames. --prompt asks for a title when a derived \
+                  title would be ambiguous.",
+        examples: &["scan ~/roms"],
+        related: &["add", "watch"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["sdcard"],
+        args: "<folder> <source>... [--by <layout>]",
+        summary: "Build ROMs onto a flash cart's SD card (--query to choose)",
+        details: "Builds every ROM linked to one of the source files into the folder, with \
+                  FAT-safe names like \"Title (Version).nes\", replacing files of the same \
+                  name. --by letter or --by tag puts them in a folder per first letter or \
+                  first tag. --query builds only the ROMs matching a search query.",
+        examples: &[
+            "sdcard /media/everdrive/Hacks smb.nes --by letter",
+            "sdcard /media/everdrive/Translations zelda.nes --query tag:translation",
+        ],
+        related: &["build", "search", "export"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["recent"],
+        args: "[n]",
+        summary: "List the most recently added or edited ROMs",
+        details: "Shows the last n changes (10 by default), newest first.",
+        examples: &["recent", "recent 3"],
+        related: &["list", "status"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["report"],
+        args: "<file|-> [--columns <c,...>]",
+        summary: "Write a CSV or Markdown table of ROMs (--format, --sort; see README)",
+        details: "Writes the ROMs matching an optional search query as a CSV file for \
+                  spreadsheets or a Markdown table for wiki pages, with the format taken from \
+                  the file's extension unless --format is given. `-` writes to standard \
+                  output. Columns default to title, version, hash, type, tags, and links; \
+                  --columns, --sort, and --reverse work as for `list`, with full hashes and \
+                  plain numbers.",
+        examples: &[
+            "report roms.csv",
+            "report hacks.md tag:hack --columns title,version,dat",
+            "report - --format csv --sort links --reverse",
+        ],
+        related: &["list", "search", "dat"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["search"],
+        args: "[--regex] [--all] <query>",
+        summary: "Search ROMs by title (tag:, mapper:, type:, version: qualifiers)",
+        details: "Words match titles. tag:<tag>, mapper:<number>, type:<type>, \
+                  version:<pattern> (with * wildcards), rating:<n> (or rating:>=<n>, with \
+                  >, <, or <=), status:<status>, region:<region>, lang:<language>, \
+                  collection:<name>, filename:<pattern>, added:<date|age> (or \
+                  added:>=<date|age>, with >, <, or <=; ages like 12h, 30d, 2w, 6m, 1y), \
+                  is:fav, is:archived, and is:wanted match structured metadata. \
+                  --since <date|age> and --added-last <age> are shorthand for added:>=. \
+                  Archived ROMs are left out unless --all is given. With --regex, the query is a case-insensitive regular expression matched \
+                  against titles, filenames, and descriptions.",
+        examples: &[
+            "search mario",
+            "search tag:translation version:1.*",
+            "search rating:>=4 status:unplayed",
+            "search region:Europe lang:de",
+            "search --added-last 30d tag:hack",
+        ],
+        related: &["list", "sql"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["serve"],
+        args: "[--listen <address:port>] [--grpc|--stdio]",
+        summary: "Serve the library over an HTTP API and web UI",
+        details: "Answers REST requests for nodes, search, the link graph, builds, and \
+                  imports until Ctrl+C, logging each request, and serves a web UI at / to \
+                  search, edit metadata, see lineage, and download builds. Listens on \
+                  127.0.0.1:8080 unless given --listen; on other addresses, requests must \
+                  carry the token printed with the address. Requests from other web sites \
+                  are refused. --stdio answers requests on standard input and output instead, \
+                  which is how sync reaches a library over SSH. --grpc serves the gRPC \
+                  service in proto/dromos.proto on the address instead, in builds with the \
+                  grpc feature.",
+        examples: &["serve", "serve --listen 0.0.0.0:9000", "serve --grpc"],
+        related: &["export", "import", "sync"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["share"],
+        args: "[hash] [--tag <tag>]... [--name <name>]",
+        summary: "Offer part of the library to others on the local network",
+        details: "Serves the linked group of the ROM given, or the ROMs with any tag given, \
+                  or both, for others to download with fetch, until Ctrl+C. The share is \
+                  advertised over mDNS under this machine's host name, or --name. Only the \
+                  shared ROMs, links, diffs, and attachments can be downloaded, and nothing \
+                  can be changed. Listens on every interface, on a free port, unless given \
+                  --listen <address:port>.",
+        examples: &["share abc123", "share --tag translation --name den"],
+        related: &["fetch", "serve", "export"],
+        files: false,
+        node_arg: NodeArg::At(1),
+    },
+    CommandInfo {
+        names: &["source", "run"],
+        args: "<file>",
+        summary: "Run commands from a file (--continue-on-error)",
+        details: "Runs each line of the file as a shell command; # starts a comment. Stops \
+                  at the first failing command unless --continue-on-error is given.",
+        examples: &["source ingest.txt"],
+        related: &["set"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["sql"],
+        args: "<SELECT ...>",
+        summary: "Run a read-only SQL query and show the results",
+        details: "Runs one SELECT, WITH, VALUES, or EXPLAIN statement against the library \
+                  database, through a read-only connection. Anything else, including \
+                  PRAGMA, ATTACH, and BEGIN, is refused.",
+        examples: &["sql SELECT title, version FROM nodes WHERE version IS NOT NULL"],
+        related: &["search"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["status"],
+        args: "",
+        summary: "Summarize library health",
+        details: "Shows counts, recently added ROMs, ROMs with no links, missing diff files, \
+                  and when the library was last exported or imported.",
+        examples: &["status"],
+        related: &["version", "recent"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["sync"],
+        args: "<remote> [--dry-run]",
+        summary: "Copy what each of two libraries is missing to the other",
+        details: "Compares this library with another machine's and sends only the ROMs, \
+                  links, diffs, and attachments one side lacks, in both directions. The \
+                  remote is the http://host:port address of its dromos serve, or \
+                  [user@]host[:command] to run command serve --stdio over SSH (dromos by \
+                  default). ROMs both libraries have keep their own metadata; differences \
+                  are listed, to settle with export and import. --dry-run only shows what \
+                  would be copied.",
+        examples: &[
+            "sync laptop.local",
+            "sync me@desktop:~/bin/dromos --dry-run",
+            "sync http://192.168.1.20:8080",
+        ],
+        related: &["serve", "export", "import"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["set"],
+        args: "[--save] [option] [value]",
+        summary: "Show or change session options (--save to keep them)",
+        details: "With no arguments, lists the current values. Options: output <text|json>, \
+                  confirm <on|off>, color <on|off>, pager <on|off> (page long listings \
+                  through $PAGER), export_dir <folder|none> (where relative export \
+                  paths go), export_git <on|off> (commit each export folder to Git), \
+                  strict <on|off> (refuse ROMs with inconsistent headers), and plain \
+                  <on|off> (screen-reader-friendly output). \
+                  --save also writes the value to dromos.conf as the default.",
+        examples: &["set", "set output json", "set --save pager on"],
+        related: &["help"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["watch"],
+        args: "<folder> [--link <file>]",
+        summary: "Add new ROMs as they appear in a folder",
+        details: "Adds each ROM copied into the folder once the copy finishes. --link also \
+                  links every new ROM to the given base file. Press Enter to stop.",
+        examples: &["watch incoming --link base.nes"],
+        related: &["scan", "add"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["wish", "want"],
+        args: "<sha256> <name> | --dat <name>",
+        summary: "Track a ROM you don't have yet on the wishlist",
+        details: "Adds a ROM known only by its SHA-256, titled from the name as add titles \
+                  files (so No-Intro and GoodTools codes become regions and tags), and takes \
+                  edit's flags. --dat <name> adds every ROM the loaded DATs list under a \
+                  game name containing <name>, after asking; --dry-run only lists them. \
+                  Wishlist ROMs match is:wanted. When add, scan, or watch sees the file, it \
+                  is recorded and announced; check reports it as wanted.",
+        examples: &[
+            "wish 3f1a...c2 \"Kid Icarus (Japan)\" --add-tag want",
+            "wish --dat \"Zelda II\" --dry-run",
+            "list is:wanted",
+        ],
+        related: &["dat", "scan", "check"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["hash"],
+        args: "<file|pattern>...",
+        summary: "Show ROM hashes without adding to database",
+        details: "Prints the hash of each ROM's data (headers excluded). --stdin-list reads \
+                  newline-separated paths from stdin.",
+        examples: &["hash roms/*.nes"],
+        related: &["check", "add"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["version", "about"],
+        args: "",
+        summary: "Show version, data revision, paths, and counts",
+        details: "Shows the build version and time, the data revision, where the database \
+                  and diffs are stored, and how many ROMs and links they hold.",
+        examples: &["version"],
+        related: &["status"],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["!"],
+        args: "<shell command>",
+        summary: "Run a shell command without leaving dromos",
+        details: "Runs the rest of the line in the system shell (sh, or cmd on Windows), \
+                  e.g. to look for ROM files before adding them.",
+        examples: &["!ls ~/Downloads/*.nes"],
+        related: &["add", "scan"],
+        files: true,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["help", "?"],
+        args: "[command]",
+        summary: "Show this help, or details for one command",
+        details: "Lists all commands, or shows usage, examples, and related commands for one.",
+        examples: &["help", "help build"],
+        related: &[],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+    CommandInfo {
+        names: &["quit", "exit"],
+        args: "",
+        summary: "Exit dromos",
+        details: "Leaves the shell, saving its history.",
+        examples: &["quit"],
+        related: &[],
+        files: false,
+        node_arg: NodeArg::None,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_by_name_or_alias() {
+        assert_eq!(find("ls").map(CommandInfo::name), Some("list"));
+        assert_eq!(find("BUILD").map(CommandInfo::name), Some("build"));
+        assert!(find("frobnicate").is_none());
+    }
+
+    #[test]
+    fn test_usage() {
+        assert_eq!(
+            find("list").unwrap().usage(),
+            "list, ls [--columns <c,...>]"
+        );
+        assert_eq!(find("status").unwrap().usage(), "status");
+    }
+
+    #[test]
+    fn test_table_is_consistent() {
+        let names: Vec<&str> = all_names().collect();
+        for (i, name) in names.iter().enumerate() {
+            assert!(
+                !names[..i].contains(name),
+                "duplicate command name {}",
+                name
+            );
+        }
+        for command in COMMANDS {
+            for related in command.related {
+                assert!(
+                    find(related).is_some(),
+                    "unknown related command {}",
+                    related
+                );
+            }
+        }
+    }
+}
@@ -1,44 +1,313 @@
 use std::path::PathBuf;
 
+use super::listing::{ListColumn, ReportFormat, parse_column, parse_columns};
+use crate::checksums::ChecksumFormat;
+use crate::config::UserConfig;
+use crate::db::{MAX_RATING, NodeMetadata, PlayStatus, check_rating};
+use crate::rom::region;
+use crate::sdcard::SdLayout;
+
+/// Number of ROMs `recent` lists when no count is given.
+pub const DEFAULT_RECENT_COUNT: usize = 10;
+
 #[derive(Debug, Clone)]
 pub enum Command {
     Add {
-        file: PathBuf,
+        files: Vec<PathBuf>,
+        /// Also read newline-separated paths from stdin
+        from_stdin: bool,
     },
+    /// A `None` target (or empty `Rm` targets) means "pick one interactively"
     Build {
         source: PathBuf,
-        target: String,
+        target: Option<String>,
+        output: Option<PathBuf>,
+        overwrite: bool,
+    },
+    /// Build every ROM linked to one, or matching a search, into a folder
+    BuildAll {
+        /// The ROM whose linked group is built; None with a query
+        target: Option<String>,
+        /// Search query selecting the ROMs instead, as for `search`
+        query: Option<String>,
+        dir: PathBuf,
+        /// ROM files to build from, besides recorded locations
+        sources: Vec<PathBuf>,
     },
     Edit {
+        target: Option<String>,
+        edits: Box<MetadataEdits>,
+    },
+    /// Metadata changes for many ROMs from a CSV file
+    EditCsv {
+        file: PathBuf,
+        /// Show the changes without making them
+        dry_run: bool,
+    },
+    /// The same metadata edits for every ROM matching a search query
+    BulkEdit {
+        query: String,
+        edits: Box<MetadataEdits>,
+        /// Include archived ROMs
+        all: bool,
+        /// Show the changes without making them
+        dry_run: bool,
+    },
+    Info {
         target: String,
     },
+    /// Put a ROM known only by its SHA-256 on the wishlist
+    Wish {
+        hash: String,
+        /// Title, with any file name codes read as in `add`
+        name: String,
+        edits: Box<MetadataEdits>,
+    },
+    /// Put the ROMs loaded DATs list under game names containing `query`
+    /// on the wishlist
+    WishDat {
+        query: String,
+        /// Show the ROMs without adding them
+        dry_run: bool,
+    },
     Link {
         files: Vec<PathBuf>,
+        /// What changed between the two ROMs
+        note: Option<String>,
     },
     Links {
-        target: String,
+        target: Option<String>,
+    },
+    /// `None` columns or sort mean "use the configured default layout"
+    List {
+        columns: Option<Vec<ListColumn>>,
+        sort: Option<ListColumn>,
+        reverse: bool,
+        /// Search query the ROMs must match; empty lists them all
+        query: String,
+        /// Include archived ROMs
+        all: bool,
+    },
+    Open {
+        target: Option<String>,
+    },
+    /// Toggle whether a node is a favorite
+    Fav {
+        target: Option<String>,
+    },
+    /// `archive` or `unarchive` a node
+    Archive {
+        target: Option<String>,
+        archived: bool,
+    },
+    Play {
+        source: PathBuf,
+        target: Option<String>,
     },
-    List,
     Rm {
-        target: String,
+        targets: Vec<String>,
+    },
+    Dedupe {
+        /// Search query limiting the ROMs compared
+        query: String,
+    },
+    Merge {
+        keep: String,
+        dup: String,
+        /// ROM files to build from when the duplicate's links are moved
+        sources: Vec<PathBuf>,
+    },
+    Scan {
+        dir: PathBuf,
+        prompt_ambiguous: bool,
+    },
+    /// Write a checksum file covering every file in a folder
+    Checksums {
+        dir: PathBuf,
+        format: ChecksumFormat,
+    },
+    /// Classify the ROM files in a folder against the library
+    Locate {
+        dir: PathBuf,
+        /// Remember where each known ROM was found
+        record: bool,
+    },
+    SdCard {
+        dir: PathBuf,
+        /// ROMs to build from, each for the ROMs linked to it
+        sources: Vec<PathBuf>,
+        layout: SdLayout,
+        /// Search query selecting the ROMs, as for `search`
+        query: String,
+    },
+    Source {
+        file: PathBuf,
+        continue_on_error: bool,
+    },
+    Recent {
+        count: usize,
+    },
+    /// `None` columns mean the report's default columns
+    Report {
+        /// File to write, or `-` for standard output
+        file: PathBuf,
+        format: ReportFormat,
+        columns: Option<Vec<ListColumn>>,
+        sort: Option<ListColumn>,
+        reverse: bool,
+        /// Search query selecting the ROMs, as for `search`
+        query: String,
+    },
+    Sql {
+        query: String,
     },
+    Status,
+    Version,
     Search {
         query: String,
+        /// Treat the query as a regular expression over titles, filenames,
+        /// and descriptions
+        regex: bool,
+        /// Include archived ROMs
+        all: bool,
+    },
+    Set {
+        option: Option<String>,
+        value: Option<String>,
+        /// Also write the value to the config file
+        save: bool,
     },
     Hash {
-        file: PathBuf,
+        files: Vec<PathBuf>,
+        from_stdin: bool,
+    },
+    Watch {
+        dir: PathBuf,
+        link_base: Option<PathBuf>,
+    },
+    Serve {
+        /// Address to listen on, e.g. `127.0.0.1:8080`
+        listen: String,
+        /// Answer requests on stdin and stdout instead, for `sync` over SSH
+        stdio: bool,
+        /// Serve the gRPC service instead of the HTTP API
+        grpc: bool,
+    },
+    Sync {
+        /// `http://host:port` or `[user@]host[:command]`
+        remote: String,
+        dry_run: bool,
+    },
+    /// Serve part of the library to the local network, advertised over mDNS
+    Share {
+        /// Hash prefix of a ROM whose linked group to share
+        component: Option<String>,
+        /// Share ROMs with any of these tags
+        tags: Vec<String>,
+        /// Name to advertise the share as; the host name without one
+        name: Option<String>,
+        listen: String,
+    },
+    /// List shares on the local network, or import the one named
+    Fetch {
+        name: Option<String>,
     },
     Check {
         file: PathBuf,
     },
+    /// Hexdump where two ROMs in the library differ
+    Inspect {
+        a: String,
+        b: String,
+        /// ROM files to build from, besides recorded locations
+        sources: Vec<PathBuf>,
+        /// Show every differing row instead of the first ones
+        all: bool,
+    },
+    /// How many bytes differ in each bank of two ROMs in the library
+    Banks {
+        a: String,
+        b: String,
+        /// ROM files to build from, besides recorded locations
+        sources: Vec<PathBuf>,
+    },
+    /// Compare two ROM files' data, before linking them
+    Compare {
+        a: PathBuf,
+        b: PathBuf,
+    },
+    /// Rebuild every ROM linked to the sources and check each link's output
+    ValidateAll {
+        sources: Vec<PathBuf>,
+    },
+    /// Check every diff file without building anything
+    CheckDiffs,
+    CollectionCreate {
+        name: String,
+    },
+    /// Delete a collection, leaving its ROMs in the library
+    CollectionDelete {
+        name: String,
+    },
+    CollectionAdd {
+        name: String,
+        targets: Vec<String>,
+    },
+    CollectionRemove {
+        name: String,
+        targets: Vec<String>,
+    },
+    /// List the collections, or the ROMs in the one named
+    CollectionList {
+        name: Option<String>,
+    },
+    DatImport {
+        file: PathBuf,
+    },
+    /// Attach a file to a ROM, or to its link with `linked`
+    Attach {
+        file: PathBuf,
+        target: String,
+        linked: Option<String>,
+    },
+    Detach {
+        name: String,
+        target: String,
+        linked: Option<String>,
+    },
+    DatExport {
+        file: PathBuf,
+        /// Limit to the linked group containing this ROM
+        component: Option<String>,
+        /// DAT name; the CLI picks one when None
+        name: Option<String>,
+        /// Search query selecting the ROMs, as for `search`
+        query: String,
+    },
     Export {
         hash_prefix: Option<String>,
         output: PathBuf,
+        /// Write a static HTML catalog instead of an importable export
+        html: bool,
+        /// Commit the folder to Git afterwards, even with `export_git` off
+        git: bool,
+        /// Configured plugin that writes the export instead
+        plugin: Option<String>,
+        /// Export only the ROMs in this collection
+        collection: Option<String>,
     },
     Import {
+        /// An export folder, or the `http://` address of `dromos serve`
         input: PathBuf,
+        /// From a server: hash prefix of a ROM whose linked group to import
+        component: Option<String>,
+        /// From a server: import ROMs with any of these tags
+        tags: Vec<String>,
+    },
+    Help {
+        /// Command to show details for
+        topic: Option<String>,
     },
-    Help,
     Quit,
 }
 
@@ -52,7 +321,27 @@ impl Command {
             return None;
         }
 
-        let parts = parse_quoted_args(line);
+        // SQL keeps its own quoting, so take the rest of the line verbatim
+        if let Some(query) = sql_query(line) {
+            return Some(Command::sql(query));
+        }
+
+        Command::from_args(&parse_quoted_args(line))
+    }
+
+    fn sql(query: &str) -> Result<Command, String> {
+        if query.is_empty() {
+            Err("Usage: sql <SELECT ...>".to_string())
+        } else {
+            Ok(Command::Sql {
+                query: query.to_string(),
+            })
+        }
+    }
+
+    /// Build a Command from already-split arguments (e.g. from the process argv).
+    /// Returns None if there are no arguments.
+    pub fn from_args(parts: &[String]) -> Option<Result<Command, String>> {
         if parts.is_empty() {
             return None;
         }
@@ -62,76 +351,380 @@ impl Command {
 
         Some(match cmd.as_str() {
             "add" => {
-                if args.is_empty() {
-                    Err("Usage: add <file>".to_string())
+                let mut args = args.to_vec();
+                let from_stdin = take_stdin_switch(&mut args);
+                if args.is_empty() && !from_stdin {
+                    Err("Usage: add <file|pattern>... | add --stdin".to_string())
                 } else {
                     Ok(Command::Add {
-                        file: PathBuf::from(&args[0]),
+                        files: args.iter().map(PathBuf::from).collect(),
+                        from_stdin,
                     })
                 }
             }
+            "build-all" => {
+                let mut args = args.to_vec();
+                let usage = "Usage: build-all <hash> <folder> [source_file...] | \
+                             build-all --query <query> <folder> [source_file...]";
+                match take_option(&mut args, "--query") {
+                    Err(e) => Err(e),
+                    Ok(Some(query)) if !args.is_empty() => Ok(Command::BuildAll {
+                        target: None,
+                        query: Some(query),
+                        dir: PathBuf::from(&args[0]),
+                        sources: args[1..].iter().map(PathBuf::from).collect(),
+                    }),
+                    Ok(None) if args.len() >= 2 => Ok(Command::BuildAll {
+                        target: Some(args[0].clone()),
+                        query: None,
+                        dir: PathBuf::from(&args[1]),
+                        sources: args[2..].iter().map(PathBuf::from).collect(),
+                    }),
+                    Ok(_) => Err(usage.to_string()),
+                }
+            }
             "build" => {
+                let mut args = args.to_vec();
+                let overwrite = take_switch(&mut args, "--overwrite");
+                let output = match take_option(&mut args, "--output") {
+                    Ok(None) => take_option(&mut args, "-o"),
+                    other => other,
+                };
+                match output {
+                    Err(e) => Err(e),
+                    Ok(_) if args.is_empty() => Err(
+                        "Usage: build <source_file> [hash|title] [-o <output>] [--overwrite]"
+                            .to_string(),
+                    ),
+                    Ok(output) => Ok(Command::Build {
+                        source: PathBuf::from(&args[0]),
+                        target: (args.len() > 1).then(|| args[1..].join(" ")),
+                        output: output.map(PathBuf::from),
+                        overwrite,
+                    }),
+                }
+            }
+            "edit" => {
+                let mut args = args.to_vec();
+                let dry_run = take_switch(&mut args, "--dry-run");
+                match take_option(&mut args, "--csv") {
+                    Err(e) => Err(e),
+                    Ok(Some(file)) if args.is_empty() => Ok(Command::EditCsv {
+                        file: PathBuf::from(file),
+                        dry_run,
+                    }),
+                    Ok(Some(_)) => Err("Usage: edit --csv <file> [--dry-run]".to_string()),
+                    Ok(None) if dry_run => Err("edit: --dry-run needs --csv <file>".to_string()),
+                    Ok(None) => match MetadataEdits::take_from(&mut args) {
+                        Err(e) => Err(e),
+                        Ok(_) if args.len() > 1 => {
+                            Err("Usage: edit [hash] [--title <t>] [--version <v>] [--url <u>] [--date <YYYY-MM-DD>] [--description <d>] [--rating <1-5>] [--status <s>] [--region <r>] [--lang <l>] [--add-tag <t>] [--remove-tag <t>] [--clear <field>] | edit --csv <file> [--dry-run]".to_string())
+                        }
+                        Ok(edits) => Ok(Command::Edit {
+                            target: args.first().cloned(),
+                            edits: Box::new(edits),
+                        }),
+                    },
+                }
+            }
+            "bulk-edit" => {
+                let mut args = args.to_vec();
+                let dry_run = take_switch(&mut args, "--dry-run");
+                let all = take_switch(&mut args, "--all");
+                match MetadataEdits::take_from(&mut args) {
+                    Err(e) => Err(e),
+                    Ok(edits) if edits.title.is_some() => {
+                        Err("bulk-edit: --title would give every ROM the same title".to_string())
+                    }
+                    Ok(edits) if args.is_empty() || edits.is_empty() => {
+                        Err("Usage: bulk-edit <query> [--add-tag <t>] [--remove-tag <t>] [--region <r>] [--lang <l>] [--status <s>] [--rating <1-5>] [--clear <field>] [...] [--all] [--dry-run]".to_string())
+                    }
+                    Ok(edits) => Ok(Command::BulkEdit {
+                        query: args.join(" "),
+                        edits: Box::new(edits),
+                        all,
+                        dry_run,
+                    }),
+                }
+            }
+            "wish" | "want" => {
+                let usage = "Usage: wish <sha256> <name> [--version <v>] [--add-tag <t>] [...] | wish --dat <name> [--dry-run]";
+                let mut args = args.to_vec();
+                let from_dat = take_switch(&mut args, "--dat");
+                let dry_run = take_switch(&mut args, "--dry-run");
+                if from_dat {
+                    if args.is_empty() {
+                        Err(usage.to_string())
+                    } else {
+                        Ok(Command::WishDat {
+                            query: args.join(" "),
+                            dry_run,
+                        })
+                    }
+                } else if dry_run {
+                    Err("wish: --dry-run goes with --dat".to_string())
+                } else {
+                    match MetadataEdits::take_from(&mut args) {
+                        Err(e) => Err(e),
+                        Ok(edits)
+                            if args.is_empty() || (args.len() == 1 && edits.title.is_none()) =>
+                        {
+                            Err(usage.to_string())
+                        }
+                        Ok(edits) => Ok(Command::Wish {
+                            hash: args[0].clone(),
+                            name: args[1..].join(" "),
+                            edits: Box::new(edits),
+                        }),
+                    }
+                }
+            }
+            "link" => {
+                let mut args = args.to_vec();
+                match take_option(&mut args, "--note") {
+                    Err(e) => Err(e),
+                    Ok(_) if args.is_empty() => {
+                        Err("Usage: link <file1> [file2] [--note <text>]".to_string())
+                    }
+                    Ok(note) => Ok(Command::Link {
+                        files: args.iter().map(PathBuf::from).collect(),
+                        note: note.filter(|n| !n.trim().is_empty()),
+                    }),
+                }
+            }
+            "links" => Ok(Command::Links {
+                target: args.first().cloned(),
+            }),
+            "list" | "ls" => {
+                let mut args = args.to_vec();
+                let reverse = take_switch(&mut args, "--reverse");
+                let favorites = take_switch(&mut args, "--fav");
+                let all = take_switch(&mut args, "--all");
+                let columns = take_option(&mut args, "--columns").and_then(|c| {
+                    c.map(|c| parse_columns(&c).map_err(|e| format!("list: {}", e)))
+                        .transpose()
+                });
+                let sort = take_option(&mut args, "--sort").and_then(|s| {
+                    s.map(|s| parse_column(&s).map_err(|e| format!("list: {}", e)))
+                        .transpose()
+                });
+                // --region and --lang are shorthand for their search qualifiers
+                let qualifiers = take_option(&mut args, "--region").and_then(|region| {
+                    let language = take_option(&mut args, "--lang")?;
+                    let added = take_added_qualifiers(&mut args)?;
+                    Ok(region
+                        .map(|r| format!("region:{}", r))
+                        .into_iter()
+                        .chain(language.map(|l| format!("lang:{}", l)))
+                        .chain(added))
+                });
+                match (columns, sort, qualifiers) {
+                    (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => Err(e),
+                    (Ok(columns), Ok(sort), Ok(qualifiers)) => {
+                        args.extend(qualifiers);
+                        if favorites {
+                            args.push("is:fav".to_string());
+                        }
+                        Ok(Command::List {
+                            columns,
+                            sort,
+                            reverse,
+                            query: args.join(" "),
+                            all,
+                        })
+                    }
+                }
+            }
+            "open" => Ok(Command::Open {
+                target: (!args.is_empty()).then(|| args.join(" ")),
+            }),
+            "fav" => Ok(Command::Fav {
+                target: (!args.is_empty()).then(|| args.join(" ")),
+            }),
+            "archive" | "unarchive" => Ok(Command::Archive {
+                target: (!args.is_empty()).then(|| args.join(" ")),
+                archived: cmd == "archive",
+            }),
+            "play" => match args {
+                [source] | [source, _] => Ok(Command::Play {
+                    source: PathBuf::from(source),
+                    target: args.get(1).cloned(),
+                }),
+                _ => Err("Usage: play <source_file> [target_hash]".to_string()),
+            },
+            "rm" | "remove" => Ok(Command::Rm {
+                targets: args.to_vec(),
+            }),
+            "dedupe" => Ok(Command::Dedupe {
+                query: args.join(" "),
+            }),
+            "merge" => {
                 if args.len() < 2 {
-                    Err("Usage: build <source_file> <target_hash>".to_string())
+                    Err("Usage: merge <keep> <duplicate> [source_file...]".to_string())
                 } else {
-                    Ok(Command::Build {
-                        source: PathBuf::from(&args[0]),
-                        target: args[1].clone(),
+                    Ok(Command::Merge {
+                        keep: args[0].clone(),
+                        dup: args[1].clone(),
+                        sources: args[2..].iter().map(PathBuf::from).collect(),
                     })
                 }
             }
-            "edit" => {
+            "scan" => {
+                let mut args = args.to_vec();
+                let prompt_ambiguous = take_switch(&mut args, "--prompt");
                 if args.is_empty() {
-                    Err("Usage: edit <hash>".to_string())
+                    Err("Usage: scan <folder> [--prompt]".to_string())
                 } else {
-                    Ok(Command::Edit {
-                        target: args[0].clone(),
+                    Ok(Command::Scan {
+                        dir: PathBuf::from(&args[0]),
+                        prompt_ambiguous,
                     })
                 }
             }
-            "link" => {
-                if args.is_empty() {
-                    Err("Usage: link <file1> [file2]".to_string())
+            "checksums" => {
+                let mut args = args.to_vec();
+                match take_option(&mut args, "--format")
+                    .and_then(|format| format.map(|f| f.parse()).transpose())
+                {
+                    Err(e) => Err(e),
+                    Ok(_) if args.len() != 1 => {
+                        Err("Usage: checksums <folder> [--format sha256sums|sfv]".to_string())
+                    }
+                    Ok(format) => Ok(Command::Checksums {
+                        dir: PathBuf::from(&args[0]),
+                        format: format.unwrap_or_default(),
+                    }),
+                }
+            }
+            "locate" => {
+                let mut args = args.to_vec();
+                let record = take_switch(&mut args, "--record");
+                if args.len() != 1 {
+                    Err("Usage: locate <folder> [--record]".to_string())
                 } else {
-                    Ok(Command::Link {
-                        files: args.iter().map(PathBuf::from).collect(),
+                    Ok(Command::Locate {
+                        dir: PathBuf::from(&args[0]),
+                        record,
                     })
                 }
             }
-            "links" => {
+            "info" => {
                 if args.is_empty() {
-                    Err("Usage: links <file|hash>".to_string())
+                    Err("Usage: info <hash|title>".to_string())
                 } else {
-                    Ok(Command::Links {
-                        target: args[0].clone(),
+                    Ok(Command::Info {
+                        target: args.join(" "),
                     })
                 }
             }
-            "list" | "ls" => Ok(Command::List),
-            "rm" | "remove" => {
+            "source" | "run" => {
+                let mut args = args.to_vec();
+                let continue_on_error = take_switch(&mut args, "--continue-on-error");
                 if args.is_empty() {
-                    Err("Usage: rm <hash>".to_string())
+                    Err("Usage: source <file> [--continue-on-error]".to_string())
                 } else {
-                    Ok(Command::Rm {
-                        target: args[0].clone(),
+                    Ok(Command::Source {
+                        file: PathBuf::from(&args[0]),
+                        continue_on_error,
                     })
                 }
             }
+            "status" => Ok(Command::Status),
+            "version" | "about" => Ok(Command::Version),
+            "sql" => Command::sql(&args.join(" ")),
+            "recent" => match args.first().map(|n| n.parse::<usize>()) {
+                None => Ok(Command::Recent {
+                    count: DEFAULT_RECENT_COUNT,
+                }),
+                Some(Ok(count)) if count > 0 => Ok(Command::Recent { count }),
+                Some(_) => Err("Usage: recent [n]".to_string()),
+            },
+            "report" => {
+                let usage = "Usage: report <file.csv|file.md|-> [--format csv|md] \
+                             [--columns <col,...>] [--sort <col>] [--reverse] [query]";
+                let mut args = args.to_vec();
+                let reverse = take_switch(&mut args, "--reverse");
+                let options = take_option(&mut args, "--format").and_then(|format| {
+                    let format = format.map(|f| f.parse::<ReportFormat>()).transpose();
+                    let columns = take_option(&mut args, "--columns")?
+                        .map(|c| parse_columns(&c))
+                        .transpose();
+                    let sort = take_option(&mut args, "--sort")?
+                        .map(|s| parse_column(&s))
+                        .transpose();
+                    Ok((format, columns, sort))
+                });
+                match options {
+                    Err(e) => Err(e),
+                    Ok((Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e))) => {
+                        Err(format!("report: {}", e))
+                    }
+                    Ok(_) if args.is_empty() => Err(usage.to_string()),
+                    Ok((Ok(format), Ok(columns), Ok(sort))) => {
+                        let file = PathBuf::from(&args[0]);
+                        match format.or_else(|| ReportFormat::from_path(&file)) {
+                            None => Err(format!(
+                                "report: can't tell the format of \"{}\"; use --format csv or md",
+                                file.display()
+                            )),
+                            Some(format) => Ok(Command::Report {
+                                file,
+                                format,
+                                columns,
+                                sort,
+                                reverse,
+                                query: args[1..].join(" "),
+                            }),
+                        }
+                    }
+                }
+            }
             "search" => {
-                if args.is_empty() {
-                    Err("Usage: search <query>".to_string())
+                let mut args = args.to_vec();
+                let regex = take_switch(&mut args, "--regex");
+                let all = take_switch(&mut args, "--all");
+                match take_added_qualifiers(&mut args) {
+                    Err(e) => Err(e),
+                    Ok(added) if args.is_empty() && added.is_empty() => {
+                        Err("Usage: search [--regex] [--all] [--since <date|age>] \
+                         [--added-last <age>] <query>"
+                            .to_string())
+                    }
+                    Ok(added) if regex && !added.is_empty() => Err(
+                        "search: --since and --added-last don't combine with --regex".to_string(),
+                    ),
+                    Ok(added) => {
+                        args.extend(added);
+                        Ok(Command::Search {
+                            query: args.join(" "),
+                            regex,
+                            all,
+                        })
+                    }
+                }
+            }
+            "set" => {
+                let mut args = args.to_vec();
+                let save = take_switch(&mut args, "--save");
+                if save && args.len() < 2 {
+                    Err("Usage: set --save <option> <value>".to_string())
                 } else {
-                    Ok(Command::Search {
-                        query: args.join(" "),
+                    Ok(Command::Set {
+                        option: args.first().cloned(),
+                        value: args.get(1).cloned(),
+                        save,
                     })
                 }
             }
             "hash" => {
-                if args.is_empty() {
-                    Err("Usage: hash <file>".to_string())
+                let mut args = args.to_vec();
+                let from_stdin = take_stdin_switch(&mut args);
+                if args.is_empty() && !from_stdin {
+                    Err("Usage: hash <file|pattern>... | hash --stdin-list".to_string())
                 } else {
                     Ok(Command::Hash {
-                        file: PathBuf::from(&args[0]),
+                        files: args.iter().map(PathBuf::from).collect(),
+                        from_stdin,
                     })
                 }
             }
@@ -144,40 +737,643 @@ impl Command {
                     })
                 }
             }
-            "export" => {
-                if args.is_empty() {
-                    Err("Usage: export [hash] <folder>".to_string())
-                } else if args.len() == 1 {
-                    Ok(Command::Export {
-                        hash_prefix: None,
-                        output: PathBuf::from(&args[0]),
+            "check-diffs" => Ok(Command::CheckDiffs),
+            "inspect" => {
+                let mut args = args.to_vec();
+                let all = take_switch(&mut args, "--all");
+                if args.len() < 2 {
+                    Err("Usage: inspect <hash> <hash> [source_file...] [--all]".to_string())
+                } else {
+                    Ok(Command::Inspect {
+                        a: args[0].clone(),
+                        b: args[1].clone(),
+                        sources: args[2..].iter().map(PathBuf::from).collect(),
+                        all,
                     })
+                }
+            }
+            "banks" => {
+                if args.len() < 2 {
+                    Err("Usage: banks <hash> <hash> [source_file...]".to_string())
                 } else {
-                    Ok(Command::Export {
-                        hash_prefix: Some(args[0].clone()),
-                        output: PathBuf::from(&args[1]),
+                    Ok(Command::Banks {
+                        a: args[0].clone(),
+                        b: args[1].clone(),
+                        sources: args[2..].iter().map(PathBuf::from).collect(),
                     })
                 }
             }
-            "import" => {
+            "compare" | "cmp" => {
+                if args.len() != 2 {
+                    Err("Usage: compare <file1> <file2>".to_string())
+                } else {
+                    Ok(Command::Compare {
+                        a: PathBuf::from(&args[0]),
+                        b: PathBuf::from(&args[1]),
+                    })
+                }
+            }
+            "validate-all" | "validate" => {
                 if args.is_empty() {
-                    Err("Usage: import <folder>".to_string())
+                    Err("Usage: validate-all <source_file>...".to_string())
                 } else {
-                    Ok(Command::Import {
-                        input: PathBuf::from(&args[0]),
+                    Ok(Command::ValidateAll {
+                        sources: args.iter().map(PathBuf::from).collect(),
                     })
                 }
             }
-            "help" | "?" => Ok(Command::Help),
+            "collection" | "collections" => {
+                let usage = "Usage: collection [list [name]] | collection create|delete <name> | \
+                             collection add|remove <name> <hash|title>... | \
+                             collection build <name> <folder> <source_file>... [--by flat|letter|tag]";
+                let sub = args.first().map(|s| s.to_lowercase());
+                match (sub.as_deref(), args.get(1..).unwrap_or(&[])) {
+                    (None, _) | (Some("list"), []) => Ok(Command::CollectionList { name: None }),
+                    (Some("list"), [name]) => Ok(Command::CollectionList {
+                        name: Some(name.clone()),
+                    }),
+                    // Names go into `collection:<name>` search qualifiers
+                    (Some("create"), [name]) if name.contains(char::is_whitespace) => {
+                        Err("collection: names can't contain spaces".to_string())
+                    }
+                    (Some("create"), [name]) => {
+                        Ok(Command::CollectionCreate { name: name.clone() })
+                    }
+                    (Some("delete"), [name]) => {
+                        Ok(Command::CollectionDelete { name: name.clone() })
+                    }
+                    (Some("add"), [name, targets @ ..]) if !targets.is_empty() => {
+                        Ok(Command::CollectionAdd {
+                            name: name.clone(),
+                            targets: targets.to_vec(),
+                        })
+                    }
+                    (Some("remove"), [name, targets @ ..]) if !targets.is_empty() => {
+                        Ok(Command::CollectionRemove {
+                            name: name.clone(),
+                            targets: targets.to_vec(),
+                        })
+                    }
+                    (Some("build"), rest) => {
+                        // Builds the collection as `sdcard --query collection:<name>` would
+                        let mut rest = rest.to_vec();
+                        match take_option(&mut rest, "--by")
+                            .and_then(|layout| layout.map(|l| l.parse()).transpose())
+                        {
+                            Err(e) => Err(e),
+                            Ok(_) if rest.len() < 3 => Err(usage.to_string()),
+                            Ok(layout) => Ok(Command::SdCard {
+                                dir: PathBuf::from(&rest[1]),
+                                sources: rest[2..].iter().map(PathBuf::from).collect(),
+                                layout: layout.unwrap_or_default(),
+                                query: format!("collection:{}", rest[0]),
+                            }),
+                        }
+                    }
+                    _ => Err(usage.to_string()),
+                }
+            }
+            "dat" => {
+                let usage = "Usage: dat import <file.dat> | dat export <file.dat> [--component <hash>] [--name <name>] [query]";
+                match args {
+                    [sub, file] if sub.eq_ignore_ascii_case("import") => Ok(Command::DatImport {
+                        file: PathBuf::from(file),
+                    }),
+                    [sub, rest @ ..] if sub.eq_ignore_ascii_case("export") => {
+                        let mut rest = rest.to_vec();
+                        let options = take_option(&mut rest, "--component")
+                            .and_then(|c| Ok((c, take_option(&mut rest, "--name")?)));
+                        match options {
+                            Err(e) => Err(e),
+                            Ok(_) if rest.is_empty() => Err(usage.to_string()),
+                            Ok((component, name)) => Ok(Command::DatExport {
+                                file: PathBuf::from(&rest[0]),
+                                component,
+                                name,
+                                query: rest[1..].join(" "),
+                            }),
+                        }
+                    }
+                    _ => Err(usage.to_string()),
+                }
+            }
+            "attach" => match args {
+                [file, target] | [file, target, _] => Ok(Command::Attach {
+                    file: PathBuf::from(file),
+                    target: target.clone(),
+                    linked: args.get(2).cloned(),
+                }),
+                _ => Err("Usage: attach <file> <hash|title> [linked_hash]".to_string()),
+            },
+            "detach" => match args {
+                [name, target] | [name, target, _] => Ok(Command::Detach {
+                    name: name.clone(),
+                    target: target.clone(),
+                    linked: args.get(2).cloned(),
+                }),
+                _ => Err("Usage: detach <name> <hash|title> [linked_hash]".to_string()),
+            },
+            "export" => {
+                let mut args = args.to_vec();
+                let html = take_switch(&mut args, "--html");
+                let git = take_switch(&mut args, "--git");
+                let options = take_option(&mut args, "--plugin")
+                    .and_then(|plugin| Ok((plugin, take_option(&mut args, "--collection")?)));
+                match options {
+                    Err(e) => Err(e),
+                    Ok(_) if args.is_empty() => Err(
+                        "Usage: export [hash] <folder> [--collection <name>] [--html] [--git] \
+                         [--plugin <name>]"
+                            .to_string(),
+                    ),
+                    Ok((plugin, collection)) if args.len() == 1 => Ok(Command::Export {
+                        hash_prefix: None,
+                        output: PathBuf::from(&args[0]),
+                        html,
+                        git,
+                        plugin,
+                        collection,
+                    }),
+                    Ok((plugin, collection)) => Ok(Command::Export {
+                        hash_prefix: Some(args[0].clone()),
+                        output: PathBuf::from(&args[1]),
+                        html,
+                        git,
+                        plugin,
+                        collection,
+                    }),
+                }
+            }
+            "import" => {
+                let mut args = args.to_vec();
+                let tags = take_repeated_option(&mut args, "--tag");
+                let remote = args.first().is_some_and(|a| a.starts_with("http://"));
+                match (tags, args.as_slice()) {
+                    (Err(e), _) => Err(e),
+                    (Ok(tags), [input]) if remote || tags.is_empty() => Ok(Command::Import {
+                        input: PathBuf::from(input),
+                        component: None,
+                        tags,
+                    }),
+                    (Ok(tags), [input, component]) if remote => Ok(Command::Import {
+                        input: PathBuf::from(input),
+                        component: Some(component.clone()),
+                        tags,
+                    }),
+                    _ => Err(
+                        "Usage: import <folder>, or import <http://host:port> [hash] [--tag <tag>]..."
+                            .to_string(),
+                    ),
+                }
+            }
+            "sdcard" => {
+                let mut args = args.to_vec();
+                let options = take_option(&mut args, "--by").and_then(|layout| {
+                    let layout = layout.map(|l| l.parse()).transpose()?;
+                    Ok((layout, take_option(&mut args, "--query")?))
+                });
+                match options {
+                    Err(e) => Err(e),
+                    Ok(_) if args.len() < 2 => Err(
+                        "Usage: sdcard <folder> <source_file>... [--by flat|letter|tag] [--query <query>]"
+                            .to_string(),
+                    ),
+                    Ok((layout, query)) => Ok(Command::SdCard {
+                        dir: PathBuf::from(&args[0]),
+                        sources: args[1..].iter().map(PathBuf::from).collect(),
+                        layout: layout.unwrap_or_default(),
+                        query: query.unwrap_or_default(),
+                    }),
+                }
+            }
+            "watch" => {
+                let mut args = args.to_vec();
+                match take_option(&mut args, "--link") {
+                    Err(e) => Err(e),
+                    Ok(_) if args.is_empty() => {
+                        Err("Usage: watch <folder> [--link <base_file>]".to_string())
+                    }
+                    Ok(link_base) => Ok(Command::Watch {
+                        dir: PathBuf::from(&args[0]),
+                        link_base: link_base.map(PathBuf::from),
+                    }),
+                }
+            }
+            "serve" => {
+                let mut args = args.to_vec();
+                let stdio = take_switch(&mut args, "--stdio");
+                let grpc = take_switch(&mut args, "--grpc");
+                match take_option(&mut args, "--listen") {
+                    Err(e) => Err(e),
+                    Ok(listen) if args.is_empty() && !(stdio && (grpc || listen.is_some())) => {
+                        Ok(Command::Serve {
+                            listen: listen.unwrap_or_else(|| DEFAULT_LISTEN.to_string()),
+                            stdio,
+                            grpc,
+                        })
+                    }
+                    Ok(_) => Err(
+                        "Usage: serve [--listen <address:port>] [--grpc] | serve --stdio"
+                            .to_string(),
+                    ),
+                }
+            }
+            "sync" => {
+                let mut args = args.to_vec();
+                let dry_run = take_switch(&mut args, "--dry-run");
+                match args.as_slice() {
+                    [remote] => Ok(Command::Sync {
+                        remote: remote.clone(),
+                        dry_run,
+                    }),
+                    _ => Err("Usage: sync <remote> [--dry-run]".to_string()),
+                }
+            }
+            "share" => {
+                let mut args = args.to_vec();
+                let options = take_repeated_option(&mut args, "--tag").and_then(|tags| {
+                    let name = take_option(&mut args, "--name")?;
+                    Ok((tags, name, take_option(&mut args, "--listen")?))
+                });
+                match (options, args.as_slice()) {
+                    (Err(e), _) => Err(e),
+                    (Ok((tags, name, listen)), [] | [_]) if !(args.is_empty() && tags.is_empty()) => {
+                        Ok(Command::Share {
+                            component: args.first().cloned(),
+                            tags,
+                            name,
+                            listen: listen.unwrap_or_else(|| SHARE_LISTEN.to_string()),
+                        })
+                    }
+                    _ => Err(
+                        "Usage: share <hash> | share [hash] --tag <tag>... [--name <name>] [--listen <address:port>]"
+                            .to_string(),
+                    ),
+                }
+            }
+            "fetch" => match args {
+                [] | [_] => Ok(Command::Fetch {
+                    name: args.first().cloned(),
+                }),
+                _ => Err("Usage: fetch [name]".to_string()),
+            },
+            "help" | "?" => Ok(Command::Help {
+                topic: args.first().cloned(),
+            }),
             "quit" | "exit" => Ok(Command::Quit),
             _ => Err(format!("Unknown command: {}", cmd)),
         })
     }
 }
 
+/// Address `serve` listens on without `--listen`: this machine only.
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+
+/// Address `share` listens on without `--listen`: every interface, on a
+/// free port, which the mDNS advertisement carries.
+pub const SHARE_LISTEN: &str = "0.0.0.0:0";
+
+/// Remove a `--switch` from the arguments, returning whether it was present.
+fn take_switch(args: &mut Vec<String>, name: &str) -> bool {
+    let before = args.len();
+    args.retain(|a| a != name);
+    args.len() != before
+}
+
+/// Metadata changes given as `edit` flags, applied without prompting.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetadataEdits {
+    pub title: Option<String>,
+    pub version: Option<String>,
+    pub source_url: Option<String>,
+    pub release_date: Option<String>,
+    pub description: Option<String>,
+    /// 1 to 5, checked when applied
+    pub rating: Option<String>,
+    /// A play status name, checked when applied
+    pub play_status: Option<String>,
+    /// Comma-separated regions, replacing the ROM's
+    pub region: Option<String>,
+    /// Comma-separated languages, replacing the ROM's
+    pub language: Option<String>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+    /// Fields to clear: version, url, date, tags, description, rating, status,
+    /// region, lang
+    pub clear: Vec<String>,
+}
+
+impl MetadataEdits {
+    /// Remove all edit flags from the arguments, leaving positional ones.
+    /// `--set-<field>` is another spelling of `--<field>`, which reads better
+    /// next to `--add-tag` in bulk edits.
+    fn take_from(args: &mut Vec<String>) -> Result<MetadataEdits, String> {
+        for arg in args.iter_mut() {
+            if let Some(field) = arg.strip_prefix("--set-") {
+                *arg = format!("--{}", field);
+            }
+        }
+        let mut edits = MetadataEdits {
+            title: take_option(args, "--title")?,
+            version: take_option(args, "--version")?,
+            source_url: take_option(args, "--url")?,
+            release_date: take_option(args, "--date")?,
+            description: take_option(args, "--description")?,
+            rating: take_option(args, "--rating")?,
+            play_status: take_option(args, "--status")?,
+            region: take_option(args, "--region")?,
+            language: take_option(args, "--lang")?,
+            ..Default::default()
+        };
+        while let Some(tag) = take_option(args, "--add-tag")? {
+            edits.add_tags.push(tag);
+        }
+        while let Some(tag) = take_option(args, "--remove-tag")? {
+            edits.remove_tags.push(tag);
+        }
+        while let Some(field) = take_option(args, "--clear")? {
+            edits.clear.push(field);
+        }
+        if let Some(flag) = args.iter().find(|a| a.starts_with("--")) {
+            return Err(format!("Unknown option: {}", flag));
+        }
+        Ok(edits)
+    }
+
+    /// True when no flags were given (edit interactively instead).
+    pub fn is_empty(&self) -> bool {
+        *self == MetadataEdits::default()
+    }
+
+    /// Apply the edits to existing metadata. Clears run before sets, so
+    /// `--clear tags --add-tag x` replaces the tag list.
+    pub fn apply(&self, metadata: &mut NodeMetadata) -> Result<(), String> {
+        let stored = metadata.clone();
+        for field in &self.clear {
+            match field.as_str() {
+                "version" => metadata.version = None,
+                "url" | "source_url" => metadata.source_url = None,
+                "date" | "release_date" => metadata.release_date = None,
+                "tags" => metadata.tags.clear(),
+                "description" => metadata.description = None,
+                "rating" => metadata.rating = None,
+                "status" | "play_status" => metadata.play_status = PlayStatus::default(),
+                "region" => metadata.regions.clear(),
+                "lang" | "language" => metadata.languages.clear(),
+                "title" => return Err("Title cannot be cleared".to_string()),
+                other => return Err(format!("Unknown field: {}", other)),
+            }
+        }
+
+        if let Some(title) = &self.title {
+            if title.trim().is_empty() {
+                return Err("Title cannot be empty".to_string());
+            }
+            metadata.title = title.trim().to_string();
+        }
+        if let Some(version) = &self.version {
+            metadata.version = Some(version.clone());
+        }
+        if let Some(url) = &self.source_url {
+            metadata.source_url = Some(url.clone());
+        }
+        if let Some(date) = &self.release_date {
+            let parsed = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| format!("Invalid date: {} (expected YYYY-MM-DD)", date))?;
+            metadata.release_date = Some(parsed.format("%Y-%m-%d").to_string());
+        }
+        if let Some(description) = &self.description {
+            metadata.description = Some(description.clone());
+        }
+        if let Some(rating) = &self.rating {
+            let rating = rating.parse().map_err(|_| {
+                format!("Invalid rating: {} (expected 1 to {})", rating, MAX_RATING)
+            })?;
+            metadata.rating = Some(check_rating(rating)?);
+        }
+        if let Some(status) = &self.play_status {
+            metadata.play_status = status.parse()?;
+        }
+        if let Some(region) = &self.region {
+            metadata.regions = region::parse_list(region)?;
+            if metadata.regions.is_empty() {
+                return Err("Region cannot be empty (use --clear region)".to_string());
+            }
+        }
+        if let Some(language) = &self.language {
+            metadata.languages = region::parse_list(language)?;
+            if metadata.languages.is_empty() {
+                return Err("Language cannot be empty (use --clear lang)".to_string());
+            }
+        }
+
+        metadata
+            .tags
+            .retain(|t| !self.remove_tags.iter().any(|r| r.eq_ignore_ascii_case(t)));
+        for tag in &self.add_tags {
+            if !metadata.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                metadata.tags.push(tag.clone());
+            }
+        }
+
+        metadata.normalize_edit(&stored)
+    }
+}
+
+/// Split a line and expand any user alias or macro it starts with.
+/// See [`expand_args`].
+pub fn expand_line(line: &str, config: &UserConfig) -> Result<Vec<Vec<String>>, String> {
+    expand_args(&parse_quoted_args(line.trim()), config)
+}
+
+/// Expand a user alias or macro at the start of an argument list into the
+/// argument lists of the commands to run. Aliases replace the command word
+/// (one level, so an alias may name a macro); macros substitute `$1`..`$9`
+/// and `$*` into each `;`-separated command of their template.
+pub fn expand_args(args: &[String], config: &UserConfig) -> Result<Vec<Vec<String>>, String> {
+    let Some(first) = args.first() else {
+        return Ok(vec![]);
+    };
+
+    let mut args = args.to_vec();
+    if let Some(replacement) = config.aliases.get(&first.to_lowercase()) {
+        let mut expanded = parse_quoted_args(replacement);
+        expanded.extend(args.drain(1..));
+        args = expanded;
+    }
+
+    let Some(name) = args.first().map(|a| a.to_lowercase()) else {
+        return Ok(vec![]);
+    };
+    let Some(template) = config.macros.get(&name) else {
+        return Ok(vec![args]);
+    };
+
+    let params = &args[1..];
+    let mut commands = Vec::new();
+    for segment in template.split(';') {
+        let mut command = Vec::new();
+        for token in parse_quoted_args(segment.trim()) {
+            if token == "$*" {
+                command.extend(params.iter().cloned());
+                continue;
+            }
+            // One left-to-right pass, so a `$2` inside an argument stays literal
+            let mut expanded = String::with_capacity(token.len());
+            let mut chars = token.chars().peekable();
+            while let Some(c) = chars.next() {
+                let digit = chars
+                    .peek()
+                    .and_then(|d| d.to_digit(10))
+                    .filter(|&n| n >= 1);
+                match digit {
+                    Some(n) if c == '$' => {
+                        chars.next();
+                        let value = params.get(n as usize - 1).ok_or_else(|| {
+                            format!("Macro {} needs at least {} argument(s)", name, n)
+                        })?;
+                        expanded.push_str(value);
+                    }
+                    _ => expanded.push(c),
+                }
+            }
+            command.push(expanded);
+        }
+        if !command.is_empty() {
+            commands.push(command);
+        }
+    }
+    Ok(commands)
+}
+
+/// Remove `--stdin` (or its alias `--stdin-list`) from the arguments.
+fn take_stdin_switch(args: &mut Vec<String>) -> bool {
+    // Evaluate both so neither spelling is left behind as a file argument
+    let stdin = take_switch(args, "--stdin");
+    let stdin_list = take_switch(args, "--stdin-list");
+    stdin || stdin_list
+}
+
+/// Remove a `--name <value>` pair from the arguments, returning the value.
+/// Returns Err if the option is present without a value.
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let Some(pos) = args.iter().position(|a| a == name) else {
+        return Ok(None);
+    };
+    if pos + 1 >= args.len() {
+        return Err(format!("Missing value for {}", name));
+    }
+    let value = args.remove(pos + 1);
+    args.remove(pos);
+    Ok(Some(value))
+}
+
+/// Remove `--since <date|age>` and `--added-last <age>`, returning the
+/// `added:` search qualifiers they stand for.
+fn take_added_qualifiers(args: &mut Vec<String>) -> Result<Vec<String>, String> {
+    let since = take_option(args, "--since")?;
+    let added_last = take_option(args, "--added-last")?;
+    Ok(since
+        .into_iter()
+        .chain(added_last)
+        .map(|value| format!("added:>={}", value))
+        .collect())
+}
+
+/// Remove every `--name <value>` pair from the arguments, returning the
+/// values in order.
+fn take_repeated_option(args: &mut Vec<String>, name: &str) -> Result<Vec<String>, String> {
+    let mut values = Vec::new();
+    while let Some(value) = take_option(args, name)? {
+        values.push(value);
+    }
+    Ok(values)
+}
+
+/// The query text if `line` is a `sql` command.
+pub fn sql_query(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    word.eq_ignore_ascii_case("sql").then(|| rest.trim())
+}
+
+/// Where to send a command's output instead of the terminal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Redirect {
+    /// `cmd > file` (or `>>` to append)
+    File { path: PathBuf, append: bool },
+    /// `cmd | shell command`
+    Pipe(String),
+}
+
+/// Split an unquoted `>`, `>>`, or `|` off a line, returning the command
+/// part and where its output should go. Only an operator starting a word
+/// counts, so search qualifiers such as `rating:>=4` stay whole.
+pub fn split_redirection(line: &str) -> Result<(&str, Option<Redirect>), String> {
+    let mut in_quote: Option<char> = None;
+    let mut split_at = None;
+    // Whether the next character starts a word, and whether the word
+    // before it was a qualifier's `field:`
+    let (mut word_start, mut after_field) = (true, false);
+    let mut previous = ' ';
+    for (i, c) in line.char_indices() {
+        match (c, in_quote) {
+            ('"' | '\'', None) => in_quote = Some(c),
+            (q, Some(quote)) if q == quote => in_quote = None,
+            ('>' | '|', None) if word_start && !after_field => {
+                split_at = Some(i);
+                break;
+            }
+            _ => {}
+        }
+        if c.is_whitespace() && in_quote.is_none() {
+            if !previous.is_whitespace() {
+                after_field = previous == ':';
+            }
+            word_start = true;
+        } else {
+            word_start = false;
+        }
+        previous = c;
+    }
+    let Some(i) = split_at else {
+        return Ok((line, None));
+    };
+
+    let command = line[..i].trim_end();
+    if command.trim().is_empty() {
+        return Err("Missing command before redirection".to_string());
+    }
+
+    let rest = &line[i..];
+    if let Some(pipeline) = rest.strip_prefix('|') {
+        let pipeline = pipeline.trim();
+        if pipeline.is_empty() {
+            return Err("Missing command after |".to_string());
+        }
+        return Ok((command, Some(Redirect::Pipe(pipeline.to_string()))));
+    }
+
+    let (append, target) = match rest.strip_prefix(">>") {
+        Some(target) => (true, target),
+        None => (false, &rest[1..]),
+    };
+    match parse_quoted_args(target.trim()).as_slice() {
+        [path] => Ok((
+            command,
+            Some(Redirect::File {
+                path: PathBuf::from(path),
+                append,
+            }),
+        )),
+        [] => Err("Missing file after >".to_string()),
+        _ => Err("Redirect to a single file (quote names with spaces)".to_string()),
+    }
+}
+
 /// Parse a command line respecting quoted strings.
 /// Handles both single and double quotes.
-fn parse_quoted_args(line: &str) -> Vec<String> {
+pub fn parse_quoted_args(line: &str) -> Vec<String> {
     let mut args = Vec::new();
     let mut current = String::new();
     let mut in_quote: Option<char> = None;
@@ -213,9 +1409,26 @@ fn parse_quoted_args(line: &str) -> Vec<String> {
     args
 }
 
+/// Arguments that run an emulator command line from the config on `rom`.
+/// `{rom}` in the command is replaced by the ROM's path; without one, the
+/// path is passed as the last argument.
+pub fn emulator_args(command: &str, rom: &std::path::Path) -> Vec<String> {
+    let rom = rom.display().to_string();
+    let mut args = parse_quoted_args(command);
+    if args.iter().any(|arg| arg.contains("{rom}")) {
+        for arg in &mut args {
+            *arg = arg.replace("{rom}", &rom);
+        }
+    } else {
+        args.push(rom);
+    }
+    args
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rom::{Language, Region};
 
     #[test]
     fn test_parse_quoted_args() {
@@ -239,33 +1452,1075 @@ mod tests {
             Command::parse("add test.nes"),
             Some(Ok(Command::Add { .. }))
         ));
-        assert!(matches!(Command::parse("list"), Some(Ok(Command::List))));
-        assert!(matches!(Command::parse("ls"), Some(Ok(Command::List))));
         assert!(matches!(
-            Command::parse("rm abc123"),
-            Some(Ok(Command::Rm { target })) if target == "abc123"
+            Command::parse("list"),
+            Some(Ok(Command::List {
+                columns: None,
+                sort: None,
+                reverse: false,
+                ..
+            }))
         ));
         assert!(matches!(
-            Command::parse("remove abc123"),
-            Some(Ok(Command::Rm { target })) if target == "abc123"
+            Command::parse("ls"),
+            Some(Ok(Command::List { .. }))
         ));
-        assert!(matches!(Command::parse("rm"), Some(Err(_))));
-        assert!(matches!(Command::parse("quit"), Some(Ok(Command::Quit))));
-        assert!(matches!(Command::parse("exit"), Some(Ok(Command::Quit))));
-        assert!(matches!(Command::parse(""), None));
-        assert!(matches!(Command::parse("   "), None));
-    }
-
-    #[test]
-    fn test_parse_edit_command() {
         assert!(matches!(
-            Command::parse("edit abc123"),
-            Some(Ok(Command::Edit { target })) if target == "abc123"
+            Command::parse("status"),
+            Some(Ok(Command::Status))
+        ));
+        assert!(matches!(
+            Command::parse("about"),
+            Some(Ok(Command::Version))
+        ));
+        assert!(matches!(
+            Command::parse("info Super Mario"),
+            Some(Ok(Command::Info { target })) if target == "Super Mario"
+        ));
+        assert!(matches!(Command::parse("info"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("rm abc123"),
+            Some(Ok(Command::Rm { targets })) if targets == ["abc123"]
+        ));
+        assert!(matches!(
+            Command::parse("remove abc123 def456 'Super Game'"),
+            Some(Ok(Command::Rm { targets })) if targets == ["abc123", "def456", "Super Game"]
+        ));
+        assert!(matches!(
+            Command::parse("rm"),
+            Some(Ok(Command::Rm { targets })) if targets.is_empty()
+        ));
+        assert!(matches!(
+            Command::parse("links"),
+            Some(Ok(Command::Links { target: None }))
+        ));
+        assert!(matches!(
+            Command::parse("link a.nes b.nes --note 'retranslation, fixes item menu bug'"),
+            Some(Ok(Command::Link { files, note: Some(note) }))
+                if files.len() == 2 && note == "retranslation, fixes item menu bug"
+        ));
+        assert!(matches!(
+            Command::parse("link a.nes b.nes"),
+            Some(Ok(Command::Link { note: None, .. }))
+        ));
+        assert!(matches!(Command::parse("link --note fixes"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("open Super Mario"),
+            Some(Ok(Command::Open { target: Some(target) })) if target == "Super Mario"
+        ));
+        assert!(matches!(
+            Command::parse("recent"),
+            Some(Ok(Command::Recent {
+                count: DEFAULT_RECENT_COUNT
+            }))
+        ));
+        assert!(matches!(
+            Command::parse("recent 3"),
+            Some(Ok(Command::Recent { count: 3 }))
+        ));
+        assert!(matches!(Command::parse("recent 0"), Some(Err(_))));
+        assert!(matches!(Command::parse("recent few"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("open"),
+            Some(Ok(Command::Open { target: None }))
+        ));
+        assert!(matches!(Command::parse("quit"), Some(Ok(Command::Quit))));
+        assert!(matches!(Command::parse("exit"), Some(Ok(Command::Quit))));
+        assert!(Command::parse("").is_none());
+        assert!(Command::parse("   ").is_none());
+    }
+
+    #[test]
+    fn test_parse_play_command() {
+        assert!(matches!(
+            Command::parse("play base.nes abc123"),
+            Some(Ok(Command::Play { source, target: Some(target) }))
+                if source == std::path::Path::new("base.nes") && target == "abc123"
+        ));
+        assert!(matches!(
+            Command::parse("play base.nes"),
+            Some(Ok(Command::Play { target: None, .. }))
+        ));
+        assert!(matches!(Command::parse("play"), Some(Err(_))));
+        assert!(matches!(Command::parse("play a.nes b c"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_attach_commands() {
+        assert!(matches!(
+            Command::parse("attach README.txt abc123"),
+            Some(Ok(Command::Attach { file, target, linked: None }))
+                if file == std::path::Path::new("README.txt") && target == "abc123"
+        ));
+        assert!(matches!(
+            Command::parse("attach \"hack notes.pdf\" abc123 def456"),
+            Some(Ok(Command::Attach { linked: Some(linked), .. })) if linked == "def456"
+        ));
+        assert!(matches!(
+            Command::parse("detach README.txt abc123"),
+            Some(Ok(Command::Detach { name, linked: None, .. })) if name == "README.txt"
+        ));
+        assert!(matches!(Command::parse("attach README.txt"), Some(Err(_))));
+        assert!(matches!(Command::parse("detach"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_emulator_args() {
+        let rom = std::path::Path::new("/tmp/My Hack.nes");
+        assert_eq!(
+            emulator_args("fceux", rom),
+            vec!["fceux", "/tmp/My Hack.nes"]
+        );
+        assert_eq!(
+            emulator_args("'/opt/Mesen 2/Mesen' --rom={rom} --fullscreen", rom),
+            vec![
+                "/opt/Mesen 2/Mesen",
+                "--rom=/tmp/My Hack.nes",
+                "--fullscreen"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_build_command() {
+        assert!(matches!(
+            Command::parse("build base.nes abc123"),
+            Some(Ok(Command::Build {
+                output: None,
+                overwrite: false,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            Command::parse("build base.nes abc123 -o out.nes --overwrite"),
+            Some(Ok(Command::Build { target, output: Some(out), overwrite: true, .. }))
+                if target.as_deref() == Some("abc123") && out == std::path::Path::new("out.nes")
+        ));
+        assert!(matches!(
+            Command::parse("build --output out.nes base.nes abc123"),
+            Some(Ok(Command::Build {
+                output: Some(_),
+                ..
+            }))
+        ));
+        assert!(matches!(
+            Command::parse("build base.nes abc123 -o"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            Command::parse("build base.nes"),
+            Some(Ok(Command::Build { target: None, .. }))
+        ));
+        assert!(matches!(
+            Command::parse("build base.nes \"Zelda Redux [2.1]\" -o z.nes"),
+            Some(Ok(Command::Build { target, output: Some(_), .. }))
+                if target.as_deref() == Some("Zelda Redux [2.1]")
+        ));
+        assert!(matches!(
+            Command::parse("build base.nes Zelda Redux"),
+            Some(Ok(Command::Build { target, .. })) if target.as_deref() == Some("Zelda Redux")
+        ));
+        assert!(matches!(Command::parse("build"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_edit_command() {
+        assert!(matches!(
+            Command::parse("edit abc123"),
+            Some(Ok(Command::Edit { target, edits })) if target.as_deref() == Some("abc123") && edits.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_parse_edit_csv_command() {
+        assert!(matches!(
+            Command::parse("edit --csv fixes.csv --dry-run"),
+            Some(Ok(Command::EditCsv { file, dry_run: true })) if file == std::path::Path::new("fixes.csv")
+        ));
+        assert!(matches!(
+            Command::parse("edit --csv fixes.csv"),
+            Some(Ok(Command::EditCsv { dry_run: false, .. }))
+        ));
+        assert!(matches!(
+            Command::parse("edit abc123 --csv fixes.csv"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            Command::parse("edit abc123 --dry-run"),
+            Some(Err(_))
+        ));
+        assert!(matches!(Command::parse("edit --csv"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_wish_command() {
+        let cmd = Command::parse("wish ABC123 Kid Icarus (J) --add-tag want");
+        let Some(Ok(Command::Wish { hash, name, edits })) = cmd else {
+            panic!("expected wish command");
+        };
+        assert_eq!(hash, "ABC123");
+        assert_eq!(name, "Kid Icarus (J)");
+        assert_eq!(edits.add_tags, vec!["want"]);
+        assert!(matches!(
+            Command::parse("want abc123 --title Kid"),
+            Some(Ok(Command::Wish { name, .. })) if name.is_empty()
+        ));
+        assert!(matches!(
+            Command::parse("wish --dat Zelda II --dry-run"),
+            Some(Ok(Command::WishDat { query, dry_run: true })) if query == "Zelda II"
+        ));
+
+        // A hash needs a name, and --dat a search
+        assert!(matches!(Command::parse("wish abc123"), Some(Err(_))));
+        assert!(matches!(Command::parse("wish --dat"), Some(Err(_))));
+        assert!(matches!(Command::parse("wish"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_bulk_edit_command() {
+        let cmd = Command::parse("bulk-edit tag:hack mario --add-tag X --set-region J --dry-run");
+        let Some(Ok(Command::BulkEdit {
+            query,
+            edits,
+            all,
+            dry_run,
+        })) = cmd
+        else {
+            panic!("expected bulk-edit command");
+        };
+        assert_eq!(query, "tag:hack mario");
+        assert_eq!(edits.add_tags, vec!["X"]);
+        assert_eq!(edits.region.as_deref(), Some("J"));
+        assert!(dry_run);
+        assert!(!all);
+
+        // A query and at least one edit are needed, and titles can't be set
+        assert!(matches!(
+            Command::parse("bulk-edit --add-tag X"),
+            Some(Err(_))
+        ));
+        assert!(matches!(Command::parse("bulk-edit tag:hack"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("bulk-edit tag:hack --title Same"),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_edit_flags() {
+        let cmd = Command::parse(
+            r#"edit abc123 --title "Super Mario" --version 1.1 --add-tag translation --clear description"#,
+        );
+        let Some(Ok(Command::Edit { target, edits })) = cmd else {
+            panic!("expected edit command");
+        };
+        assert_eq!(target.as_deref(), Some("abc123"));
+        assert_eq!(edits.title.as_deref(), Some("Super Mario"));
+        assert_eq!(edits.version.as_deref(), Some("1.1"));
+        assert_eq!(edits.add_tags, vec!["translation".to_string()]);
+        assert_eq!(edits.clear, vec!["description".to_string()]);
+
+        let Some(Ok(Command::Edit { edits, .. })) =
+            Command::parse(r#"edit --region "J, U" --lang en"#)
+        else {
+            panic!("expected edit command");
+        };
+        assert_eq!(edits.region.as_deref(), Some("J, U"));
+        assert_eq!(edits.language.as_deref(), Some("en"));
+        let mut metadata = NodeMetadata {
+            languages: vec![Language::Japanese],
+            ..Default::default()
+        };
+        edits.apply(&mut metadata).unwrap();
+        assert_eq!(metadata.regions, vec![Region::Japan, Region::Usa]);
+        assert_eq!(metadata.languages, vec![Language::English]);
+
+        assert!(matches!(
+            Command::parse("edit abc123 --bogus x"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            Command::parse("edit abc123 --title"),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn test_metadata_edits_apply() {
+        let mut metadata = NodeMetadata {
+            title: "Old".to_string(),
+            version: Some("1.0".to_string()),
+            tags: vec!["hack".to_string(), "wip".to_string()],
+            description: Some("desc".to_string()),
+            ..Default::default()
+        };
+        let edits = MetadataEdits {
+            title: Some("New".to_string()),
+            release_date: Some("1986-09-13".to_string()),
+            add_tags: vec!["translation".to_string(), "HACK".to_string()],
+            remove_tags: vec!["wip".to_string()],
+            clear: vec!["description".to_string(), "version".to_string()],
+            ..Default::default()
+        };
+        edits.apply(&mut metadata).unwrap();
+        assert_eq!(metadata.title, "New");
+        assert_eq!(metadata.version, None);
+        assert_eq!(metadata.description, None);
+        assert_eq!(metadata.release_date.as_deref(), Some("1986-09-13"));
+        assert_eq!(metadata.tags, vec!["hack", "translation"]);
+
+        let bad_date = MetadataEdits {
+            release_date: Some("Sept 1986".to_string()),
+            ..Default::default()
+        };
+        assert!(bad_date.apply(&mut metadata).is_err());
+        let bad_field = MetadataEdits {
+            clear: vec!["title".to_string()],
+            ..Default::default()
+        };
+        assert!(bad_field.apply(&mut metadata).is_err());
+    }
+
+    #[test]
+    fn test_parse_edit_no_args() {
+        assert!(matches!(
+            Command::parse("edit"),
+            Some(Ok(Command::Edit { target: None, .. }))
+        ));
+        assert!(matches!(Command::parse("edit abc def"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_set_command() {
+        assert!(matches!(
+            Command::parse("set output json"),
+            Some(Ok(Command::Set { option: Some(o), value: Some(v), save: false })) if o == "output" && v == "json"
+        ));
+        assert!(matches!(
+            Command::parse("set"),
+            Some(Ok(Command::Set {
+                option: None,
+                value: None,
+                save: false
+            }))
+        ));
+        assert!(matches!(
+            Command::parse("set --save pager on"),
+            Some(Ok(Command::Set { option: Some(o), save: true, .. })) if o == "pager"
+        ));
+        assert!(matches!(Command::parse("set --save pager"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_scan_command() {
+        assert!(matches!(
+            Command::parse("scan roms"),
+            Some(Ok(Command::Scan { dir, prompt_ambiguous: false })) if dir == std::path::Path::new("roms")
+        ));
+        assert!(matches!(
+            Command::parse("scan --prompt roms"),
+            Some(Ok(Command::Scan {
+                prompt_ambiguous: true,
+                ..
+            }))
+        ));
+        assert!(matches!(Command::parse("scan"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_source_command() {
+        assert!(matches!(
+            Command::parse("source ingest.txt"),
+            Some(Ok(Command::Source { file, continue_on_error: false }))
+                if file == std::path::Path::new("ingest.txt")
+        ));
+        assert!(matches!(
+            Command::parse("run ingest.txt --continue-on-error"),
+            Some(Ok(Command::Source {
+                continue_on_error: true,
+                ..
+            }))
+        ));
+        assert!(matches!(Command::parse("run"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_serve_command() {
+        assert!(matches!(
+            Command::parse("serve"),
+            Some(Ok(Command::Serve { listen, stdio: false, grpc: false })) if listen == DEFAULT_LISTEN
+        ));
+        assert!(matches!(
+            Command::parse("serve --grpc --listen 0.0.0.0:9000"),
+            Some(Ok(Command::Serve { listen, grpc: true, .. })) if listen == "0.0.0.0:9000"
+        ));
+        assert!(matches!(
+            Command::parse("serve --grpc --stdio"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            Command::parse("serve --listen 0.0.0.0:9000"),
+            Some(Ok(Command::Serve { listen, .. })) if listen == "0.0.0.0:9000"
+        ));
+        assert!(matches!(
+            Command::parse("serve --stdio"),
+            Some(Ok(Command::Serve { stdio: true, .. }))
+        ));
+        assert!(matches!(Command::parse("serve --listen"), Some(Err(_))));
+        assert!(matches!(Command::parse("serve 8080"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("serve --stdio --listen 0.0.0.0:9000"),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_share_and_fetch_commands() {
+        assert!(matches!(
+            Command::parse("share abc123"),
+            Some(Ok(Command::Share { component: Some(c), tags, name: None, listen }))
+                if c == "abc123" && tags.is_empty() && listen == SHARE_LISTEN
+        ));
+        assert!(matches!(
+            Command::parse("share --tag hack --tag translation --name den --listen 0.0.0.0:9000"),
+            Some(Ok(Command::Share { component: None, tags, name: Some(n), listen }))
+                if tags.len() == 2 && n == "den" && listen == "0.0.0.0:9000"
+        ));
+        assert!(matches!(Command::parse("share"), Some(Err(_))));
+        assert!(matches!(Command::parse("share a b"), Some(Err(_))));
+        assert!(matches!(Command::parse("share --tag"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("fetch"),
+            Some(Ok(Command::Fetch { name: None }))
+        ));
+        assert!(matches!(
+            Command::parse("fetch den"),
+            Some(Ok(Command::Fetch { name: Some(n) })) if n == "den"
+        ));
+        assert!(matches!(Command::parse("fetch den attic"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_import_command() {
+        assert!(matches!(
+            Command::parse("import backup"),
+            Some(Ok(Command::Import { input, component: None, tags }))
+                if input == std::path::Path::new("backup") && tags.is_empty()
+        ));
+        assert!(matches!(
+            Command::parse("import http://desk:8080/ abc123 --tag hack --tag translation"),
+            Some(Ok(Command::Import { component: Some(c), tags, .. }))
+                if c == "abc123" && tags == ["hack", "translation"]
+        ));
+        assert!(matches!(Command::parse("import"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("import backup abc123"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            Command::parse("import backup --tag hack"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            Command::parse("import http://desk:8080 --tag"),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_sync_command() {
+        assert!(matches!(
+            Command::parse("sync laptop.local --dry-run"),
+            Some(Ok(Command::Sync { remote, dry_run: true })) if remote == "laptop.local"
+        ));
+        assert!(matches!(
+            Command::parse("sync http://10.0.0.5:8080"),
+            Some(Ok(Command::Sync { dry_run: false, .. }))
+        ));
+        assert!(matches!(Command::parse("sync"), Some(Err(_))));
+        assert!(matches!(Command::parse("sync a b"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_watch_command() {
+        assert!(matches!(
+            Command::parse("watch drop --link base.nes"),
+            Some(Ok(Command::Watch { dir, link_base: Some(base) }))
+                if dir == std::path::Path::new("drop") && base == std::path::Path::new("base.nes")
+        ));
+        assert!(matches!(
+            Command::parse("watch drop"),
+            Some(Ok(Command::Watch {
+                link_base: None,
+                ..
+            }))
+        ));
+        assert!(matches!(Command::parse("watch drop --link"), Some(Err(_))));
+        assert!(matches!(Command::parse("watch"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_dat_command() {
+        assert!(matches!(
+            Command::parse("dat import 'NES (Headerless).dat'"),
+            Some(Ok(Command::DatImport { file }))
+                if file == std::path::Path::new("NES (Headerless).dat")
+        ));
+        assert!(matches!(Command::parse("dat import"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("dat export hacks.dat --component abc tag:hack mario"),
+            Some(Ok(Command::DatExport { file, component: Some(c), name: None, query }))
+                if file == std::path::Path::new("hacks.dat") && c == "abc" && query == "tag:hack mario"
+        ));
+        assert!(matches!(
+            Command::parse("dat export all.dat --name 'My ROMs'"),
+            Some(Ok(Command::DatExport { component: None, name: Some(n), query, .. }))
+                if n == "My ROMs" && query.is_empty()
+        ));
+        assert!(matches!(Command::parse("dat export"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("dat export x.dat --name"),
+            Some(Err(_))
+        ));
+        assert!(matches!(Command::parse("dat nes.dat"), Some(Err(_))));
+        assert!(matches!(Command::parse("dat"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_collection_command() {
+        assert!(matches!(
+            Command::parse("collection"),
+            Some(Ok(Command::CollectionList { name: None }))
+        ));
+        assert!(matches!(
+            Command::parse("collections list favs"),
+            Some(Ok(Command::CollectionList { name: Some(n) })) if n == "favs"
+        ));
+        assert!(matches!(
+            Command::parse("collection create favs"),
+            Some(Ok(Command::CollectionCreate { name })) if name == "favs"
+        ));
+        assert!(matches!(
+            Command::parse("collection create 'my favs'"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            Command::parse("collection delete favs"),
+            Some(Ok(Command::CollectionDelete { name })) if name == "favs"
+        ));
+        assert!(matches!(
+            Command::parse("collection add favs abc12345 'Super Mario Bros.'"),
+            Some(Ok(Command::CollectionAdd { name, targets }))
+                if name == "favs" && targets == ["abc12345", "Super Mario Bros."]
+        ));
+        assert!(matches!(
+            Command::parse("collection remove favs abc12345"),
+            Some(Ok(Command::CollectionRemove { name, targets }))
+                if name == "favs" && targets == ["abc12345"]
+        ));
+        assert!(matches!(
+            Command::parse("collection add favs"),
+            Some(Err(_))
+        ));
+        match Command::parse("collection build favs /media/sd roms --by letter") {
+            Some(Ok(Command::SdCard {
+                dir,
+                sources,
+                layout,
+                query,
+            })) => {
+                assert_eq!(dir, std::path::Path::new("/media/sd"));
+                assert_eq!(sources, [std::path::PathBuf::from("roms")]);
+                assert_eq!(layout, SdLayout::Letter);
+                assert_eq!(query, "collection:favs");
+            }
+            other => panic!("unexpected parse: {:?}", other),
+        }
+        assert!(matches!(
+            Command::parse("collection build favs /media/sd"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            Command::parse("collection rename a b"),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_export_command() {
+        assert!(matches!(
+            Command::parse("export backup"),
+            Some(Ok(Command::Export {
+                hash_prefix: None,
+                html: false,
+                git: false,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            Command::parse("export --html abc12345 site"),
+            Some(Ok(Command::Export { hash_prefix: Some(h), output, html: true, git: false, plugin: None, collection: None }))
+                if h == "abc12345" && output == std::path::Path::new("site")
+        ));
+        assert!(matches!(
+            Command::parse("export shared --git"),
+            Some(Ok(Command::Export {
+                hash_prefix: None,
+                git: true,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            Command::parse("export library.xml --plugin launchbox"),
+            Some(Ok(Command::Export { hash_prefix: None, plugin: Some(p), .. }))
+                if p == "launchbox"
+        ));
+        assert!(matches!(
+            Command::parse("export favorites --html --collection favs"),
+            Some(Ok(Command::Export { html: true, collection: Some(c), .. })) if c == "favs"
+        ));
+        assert!(matches!(Command::parse("export --html"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("export out --plugin"),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_report_command() {
+        match Command::parse(
+            "report roms.csv --columns title,hash,links --sort links --reverse tag:hack",
+        ) {
+            Some(Ok(Command::Report {
+                file,
+                format,
+                columns,
+                sort,
+                reverse,
+                query,
+            })) => {
+                assert_eq!(file, PathBuf::from("roms.csv"));
+                assert_eq!(format, ReportFormat::Csv);
+                assert_eq!(
+                    columns,
+                    Some(vec![ListColumn::Title, ListColumn::Hash, ListColumn::Links])
+                );
+                assert_eq!(sort, Some(ListColumn::Links));
+                assert!(reverse);
+                assert_eq!(query, "tag:hack");
+            }
+            other => panic!("unexpected parse: {:?}", other),
+        }
+        assert!(matches!(
+            Command::parse("report - --format md"),
+            Some(Ok(Command::Report {
+                format: ReportFormat::Markdown,
+                columns: None,
+                ..
+            }))
+        ));
+        assert!(matches!(Command::parse("report roms.txt"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("report roms.csv --columns colour"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            Command::parse("report --format csv"),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_sdcard_command() {
+        assert!(matches!(
+            Command::parse("sdcard /media/sd smb.nes zelda.nes --by letter --query 'tag:hack'"),
+            Some(Ok(Command::SdCard { dir, sources, layout: SdLayout::Letter, query }))
+                if dir == std::path::Path::new("/media/sd")
+                    && sources.len() == 2
+                    && query == "tag:hack"
+        ));
+        assert!(matches!(
+            Command::parse("sdcard sd smb.nes"),
+            Some(Ok(Command::SdCard { layout: SdLayout::Flat, query, .. })) if query.is_empty()
+        ));
+        assert!(matches!(
+            Command::parse("sdcard sd smb.nes --by size"),
+            Some(Err(_))
+        ));
+        assert!(matches!(Command::parse("sdcard sd"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_validate_all_command() {
+        assert!(matches!(
+            Command::parse("validate-all smb.nes zelda.nes"),
+            Some(Ok(Command::ValidateAll { sources })) if sources.len() == 2
+        ));
+        assert!(matches!(
+            Command::parse("validate smb.nes"),
+            Some(Ok(Command::ValidateAll { .. }))
+        ));
+        assert!(matches!(Command::parse("validate-all"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("check-diffs"),
+            Some(Ok(Command::CheckDiffs))
+        ));
+    }
+
+    #[test]
+    fn test_parse_build_all_command() {
+        assert!(matches!(
+            Command::parse("build-all abc123 out base.nes"),
+            Some(Ok(Command::BuildAll { target: Some(t), query: None, dir, sources }))
+                if t == "abc123" && dir == std::path::Path::new("out") && sources.len() == 1
+        ));
+        assert!(matches!(
+            Command::parse("build-all --query 'tag:hack' out"),
+            Some(Ok(Command::BuildAll { target: None, query: Some(q), sources, .. }))
+                if q == "tag:hack" && sources.is_empty()
+        ));
+        assert!(matches!(Command::parse("build-all abc123"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("build-all --query tag:hack"),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_inspect_command() {
+        assert!(matches!(
+            Command::parse("inspect abc123 def456"),
+            Some(Ok(Command::Inspect { a, b, sources, all: false }))
+                if a == "abc123" && b == "def456" && sources.is_empty()
+        ));
+        assert!(matches!(
+            Command::parse("inspect abc123 def456 base.nes --all"),
+            Some(Ok(Command::Inspect { sources, all: true, .. })) if sources.len() == 1
+        ));
+        assert!(matches!(Command::parse("inspect abc123"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_banks_command() {
+        assert!(matches!(
+            Command::parse("banks abc123 def456 base.nes"),
+            Some(Ok(Command::Banks { a, b, sources }))
+                if a == "abc123" && b == "def456" && sources.len() == 1
+        ));
+        assert!(matches!(Command::parse("banks abc123"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_compare_command() {
+        assert!(matches!(
+            Command::parse("compare a.nes b.nes"),
+            Some(Ok(Command::Compare { a, b }))
+                if a == std::path::Path::new("a.nes") && b == std::path::Path::new("b.nes")
+        ));
+        assert!(matches!(
+            Command::parse("cmp a.nes b.nes"),
+            Some(Ok(Command::Compare { .. }))
+        ));
+        assert!(matches!(Command::parse("compare a.nes"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_checksums_command() {
+        assert!(matches!(
+            Command::parse("checksums out"),
+            Some(Ok(Command::Checksums {
+                format: ChecksumFormat::Sha256Sums,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            Command::parse("checksums out --format sfv"),
+            Some(Ok(Command::Checksums { dir, format: ChecksumFormat::Sfv }))
+                if dir == std::path::Path::new("out")
+        ));
+        assert!(matches!(
+            Command::parse("checksums out --format md5"),
+            Some(Err(_))
+        ));
+        assert!(matches!(Command::parse("checksums"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_locate_command() {
+        assert!(matches!(
+            Command::parse("locate roms"),
+            Some(Ok(Command::Locate { dir, record: false })) if dir == std::path::Path::new("roms")
+        ));
+        assert!(matches!(
+            Command::parse("locate --record roms"),
+            Some(Ok(Command::Locate { record: true, .. }))
+        ));
+        assert!(matches!(Command::parse("locate"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_dedupe_and_merge_commands() {
+        assert!(matches!(
+            Command::parse("dedupe type:nes"),
+            Some(Ok(Command::Dedupe { query })) if query == "type:nes"
+        ));
+        assert!(matches!(
+            Command::parse("merge abc123 def456 zelda.nes"),
+            Some(Ok(Command::Merge { keep, dup, sources }))
+                if keep == "abc123" && dup == "def456" && sources == vec![PathBuf::from("zelda.nes")]
+        ));
+        assert!(matches!(Command::parse("merge abc123"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_list_command() {
+        match Command::parse("list --columns title,size,links --sort size --reverse") {
+            Some(Ok(Command::List {
+                columns,
+                sort,
+                reverse,
+                query,
+                all: false,
+            })) => {
+                assert_eq!(
+                    columns,
+                    Some(vec![ListColumn::Title, ListColumn::Size, ListColumn::Links])
+                );
+                assert_eq!(sort, Some(ListColumn::Size));
+                assert!(reverse);
+                assert!(query.is_empty());
+            }
+            other => panic!("unexpected parse result: {:?}", other),
+        }
+
+        assert!(matches!(
+            Command::parse("list --columns title,weight"),
+            Some(Err(_))
+        ));
+        assert!(matches!(Command::parse("list --sort"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("list --sort rating status:playing"),
+            Some(Ok(Command::List { sort: Some(ListColumn::Rating), query, .. }))
+                if query == "status:playing"
+        ));
+        assert!(matches!(
+            Command::parse("list zelda --region J --lang en"),
+            Some(Ok(Command::List { query, .. })) if query == "zelda region:J lang:en"
+        ));
+        assert!(matches!(
+            Command::parse("list --since 2024-01-01 zelda"),
+            Some(Ok(Command::List { query, .. })) if query == "zelda added:>=2024-01-01"
+        ));
+        assert!(matches!(Command::parse("list --since"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("list --fav type:nes"),
+            Some(Ok(Command::List { query, .. })) if query == "type:nes is:fav"
+        ));
+        assert!(matches!(
+            Command::parse("list --all zelda"),
+            Some(Ok(Command::List { query, all: true, .. })) if query == "zelda"
+        ));
+    }
+
+    #[test]
+    fn test_parse_fav_command() {
+        assert!(matches!(
+            Command::parse("fav"),
+            Some(Ok(Command::Fav { target: None }))
+        ));
+        assert!(matches!(
+            Command::parse("fav Super Mario"),
+            Some(Ok(Command::Fav { target: Some(t) })) if t == "Super Mario"
+        ));
+    }
+
+    #[test]
+    fn test_parse_archive_commands() {
+        assert!(matches!(
+            Command::parse("archive Zelda (Beta)"),
+            Some(Ok(Command::Archive { target: Some(t), archived: true })) if t == "Zelda (Beta)"
+        ));
+        assert!(matches!(
+            Command::parse("unarchive"),
+            Some(Ok(Command::Archive {
+                target: None,
+                archived: false
+            }))
+        ));
+        assert!(matches!(
+            Command::parse("search --all zelda"),
+            Some(Ok(Command::Search { all: true, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_parse_search_command() {
+        assert!(matches!(
+            Command::parse("search super mario"),
+            Some(Ok(Command::Search { query, regex: false, all: false })) if query == "super mario"
+        ));
+        assert!(matches!(
+            Command::parse("search --regex \"^Zelda.*(USA)\""),
+            Some(Ok(Command::Search { query, regex: true, all: false })) if query == "^Zelda.*(USA)"
+        ));
+        assert!(matches!(Command::parse("search --regex"), Some(Err(_))));
+        assert!(matches!(
+            Command::parse("search --added-last 30d"),
+            Some(Ok(Command::Search { query, .. })) if query == "added:>=30d"
+        ));
+        assert!(matches!(
+            Command::parse("search --regex ^Zelda --since 30d"),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_sql() {
+        // Quotes and operators are passed through untouched
+        assert!(matches!(
+            Command::parse("SQL SELECT title FROM nodes WHERE title = 'A B' AND id > 1"),
+            Some(Ok(Command::Sql { query })) if query == "SELECT title FROM nodes WHERE title = 'A B' AND id > 1"
+        ));
+        assert!(matches!(Command::parse("sql"), Some(Err(_))));
+        assert!(sql_query("sqlite x").is_none());
+    }
+
+    #[test]
+    fn test_split_redirection() {
+        assert_eq!(split_redirection("list").unwrap(), ("list", None));
+        assert_eq!(
+            split_redirection("list > roms.txt").unwrap(),
+            (
+                "list",
+                Some(Redirect::File {
+                    path: PathBuf::from("roms.txt"),
+                    append: false
+                })
+            )
+        );
+        assert_eq!(
+            split_redirection(r#"search mario >> "my roms.txt""#).unwrap(),
+            (
+                "search mario",
+                Some(Redirect::File {
+                    path: PathBuf::from("my roms.txt"),
+                    append: true
+                })
+            )
+        );
+        assert_eq!(
+            split_redirection("search foo | grep bar | sort").unwrap(),
+            (
+                "search foo",
+                Some(Redirect::Pipe("grep bar | sort".to_string()))
+            )
+        );
+
+        // Operators inside quotes are part of the argument
+        assert_eq!(
+            split_redirection(r#"search "a > b""#).unwrap(),
+            (r#"search "a > b""#, None)
+        );
+        // Only operators starting a word redirect, never a qualifier's
+        assert_eq!(
+            split_redirection("search rating:>=4").unwrap(),
+            ("search rating:>=4", None)
+        );
+        assert_eq!(
+            split_redirection("search rating: >=4").unwrap(),
+            ("search rating: >=4", None)
+        );
+        assert_eq!(
+            split_redirection("search added:>=2024-01-01").unwrap(),
+            ("search added:>=2024-01-01", None)
+        );
+        assert_eq!(
+            split_redirection("search added:<1y tag:hack").unwrap(),
+            ("search added:<1y tag:hack", None)
+        );
+        assert_eq!(
+            split_redirection("search a|b").unwrap(),
+            ("search a|b", None)
+        );
+        assert_eq!(
+            split_redirection("search rating:>=4 > good.txt").unwrap(),
+            (
+                "search rating:>=4",
+                Some(Redirect::File {
+                    path: PathBuf::from("good.txt"),
+                    append: false
+                })
+            )
+        );
+
+        assert!(split_redirection("list >").is_err());
+        assert!(split_redirection("list |").is_err());
+        assert!(split_redirection("> out.txt").is_err());
+        assert!(split_redirection("list > a.txt b.txt").is_err());
+    }
+
+    #[test]
+    fn test_expand_aliases_and_macros() {
+        let config = UserConfig::parse(
+            "alias b = build\nalias i = ingest\nmacro ingest = add $1; link $1 $2\nmacro tagall = edit $1 --add-tag $*",
+        )
+        .unwrap();
+
+        assert_eq!(
+            expand_line("b base.nes abc", &config).unwrap(),
+            vec![vec!["build", "base.nes", "abc"]]
+        );
+        assert_eq!(
+            expand_line(r#"i "my hack.nes" base.nes"#, &config).unwrap(),
+            vec![
+                vec!["add", "my hack.nes"],
+                vec!["link", "my hack.nes", "base.nes"]
+            ]
+        );
+        assert_eq!(
+            expand_line("tagall abc x", &config).unwrap(),
+            vec![vec!["edit", "abc", "--add-tag", "abc", "x"]]
+        );
+        // Arguments are substituted once, never re-expanded
+        assert_eq!(
+            expand_line("ingest $2.nes base.nes", &config).unwrap(),
+            vec![vec!["add", "$2.nes"], vec!["link", "$2.nes", "base.nes"]]
+        );
+        assert_eq!(expand_line("list", &config).unwrap(), vec![vec!["list"]]);
+        assert!(expand_line("ingest only-one.nes", &config).is_err());
+    }
+
+    #[test]
+    fn test_from_args() {
+        let args = vec!["add".to_string(), "foo bar.nes".to_string()];
+        assert!(matches!(
+            Command::from_args(&args),
+            Some(Ok(Command::Add { files, from_stdin: false })) if files == [std::path::Path::new("foo bar.nes")]
+        ));
+        assert!(Command::from_args(&[]).is_none());
+
+        let args = ["hash", "a.nes", "roms/*.nes"].map(String::from);
+        assert!(matches!(
+            Command::from_args(&args),
+            Some(Ok(Command::Hash { files, .. })) if files.len() == 2
+        ));
+
+        let args = ["add", "--stdin"].map(String::from);
+        assert!(matches!(
+            Command::from_args(&args),
+            Some(Ok(Command::Add { files, from_stdin: true })) if files.is_empty()
+        ));
+        let args = ["hash", "--stdin-list"].map(String::from);
+        assert!(matches!(
+            Command::from_args(&args),
+            Some(Ok(Command::Hash {
+                from_stdin: true,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            Command::from_args(&["add".to_string()]),
+            Some(Err(_))
         ));
-    }
-
-    #[test]
-    fn test_parse_edit_no_args() {
-        assert!(matches!(Command::parse("edit"), Some(Err(_))));
     }
 }
@@ -6,17 +6,47 @@ use rustyline::validate::Validator;
 use rustyline::{Context, Helper};
 use std::borrow::Cow;
 
-/// Helper for rustyline that provides command and filename completion.
+use super::catalog;
+
+/// Helper for rustyline that provides command, filename, and ROM completion.
 pub struct DromosHelper {
     file_completer: FilenameCompleter,
+    nodes: Vec<NodeCompletion>,
+    /// The main shell prompt, which is drawn in the prompt color
+    main_prompt: String,
+}
+
+/// A library ROM offered as a completion for hash arguments.
+#[derive(Debug, Clone)]
+pub struct NodeCompletion {
+    /// Full lowercase hex hash
+    pub hash: String,
+    /// Display title ("Title [version]")
+    pub title: String,
+    /// Pinned with `fav`; listed first by the picker
+    pub favorite: bool,
 }
 
 impl DromosHelper {
     pub fn new() -> Self {
         Self {
             file_completer: FilenameCompleter::new(),
+            nodes: Vec::new(),
+            main_prompt: String::new(),
         }
     }
+
+    /// Set the main shell prompt so it can be colored. Other prompts (like
+    /// metadata questions) are drawn as-is.
+    pub fn set_main_prompt(&mut self, prompt: &str) {
+        self.main_prompt = prompt.to_string();
+    }
+
+    /// Replace the ROMs offered for hash completion. The REPL refreshes this
+    /// after every command so completions track adds and removals.
+    pub fn set_nodes(&mut self, nodes: Vec<NodeCompletion>) {
+        self.nodes = nodes;
+    }
 }
 
 impl Default for DromosHelper {
@@ -35,8 +65,13 @@ impl Highlighter for DromosHelper {
         prompt: &'p str,
         default: bool,
     ) -> Cow<'b, str> {
-        if default && prompt == "\ndromos> " {
-            Cow::Owned(format!("\n{}> ", super::theme::prompt("dromos")))
+        if default && prompt == self.main_prompt {
+            // Color the text between any leading newlines and the "> " tail
+            let body = prompt.trim_start_matches('\n');
+            let lead = &prompt[..prompt.len() - body.len()];
+            let text = body.trim_end_matches([' ', '>']);
+            let tail = &body[text.len()..];
+            Cow::Owned(format!("{}{}{}", lead, super::theme::prompt(text), tail))
         } else {
             Cow::Borrowed(prompt)
         }
@@ -44,17 +79,6 @@ impl Highlighter for DromosHelper {
 }
 impl Validator for DromosHelper {}
 
-/// Commands that accept file path arguments.
-const FILE_COMMANDS: &[&str] = &[
-    "add", "build", "check", "export", "import", "link", "links", "hash",
-];
-
-/// All available commands.
-const ALL_COMMANDS: &[&str] = &[
-    "add", "build", "check", "edit", "export", "import", "link", "links", "list", "ls", "rm",
-    "remove", "search", "hash", "help", "quit", "exit",
-];
-
 impl Completer for DromosHelper {
     type Candidate = Pair;
 
@@ -72,6 +96,11 @@ impl Completer for DromosHelper {
             return Ok((0, command_completions("")));
         }
 
+        // Shell escapes take file arguments
+        if line_to_cursor.trim_start().starts_with('!') {
+            return self.file_completer.complete(line, pos, ctx);
+        }
+
         let cmd = parts[0].to_lowercase();
 
         // Still typing the first word (command) - complete commands
@@ -79,20 +108,68 @@ impl Completer for DromosHelper {
             return Ok((0, command_completions(&cmd)));
         }
 
+        let Some(command) = catalog::find(&cmd) else {
+            return Ok((pos, vec![]));
+        };
+
+        // Which argument is being typed (1-based), and where it starts
+        let arg_index = if line_to_cursor.ends_with(' ') {
+            parts.len()
+        } else {
+            parts.len() - 1
+        };
+        let word_start = line_to_cursor
+            .rfind(char::is_whitespace)
+            .map(|i| i + 1)
+            .unwrap_or(0);
+
+        // `help` takes a command name
+        if command.name() == "help" && arg_index == 1 {
+            let prefix = line_to_cursor[word_start..].to_lowercase();
+            return Ok((word_start, command_completions(&prefix)));
+        }
+
+        // Hash arguments complete from the library, matching hash or title
+        if command.takes_node_at(arg_index) {
+            let candidates = node_completions(&self.nodes, &line_to_cursor[word_start..]);
+            if !candidates.is_empty() || !command.files {
+                return Ok((word_start, candidates));
+            }
+        }
+
         // After command - check if it takes file arguments
-        if FILE_COMMANDS.contains(&cmd.as_str()) {
+        if command.files {
             return self.file_completer.complete(line, pos, ctx);
         }
 
-        // No completions for other commands (search takes free text, list/help/quit take nothing)
+        // No completions for other commands (search takes free text, list/quit take nothing)
         Ok((pos, vec![]))
     }
 }
 
+/// Return ROMs whose hash or title starts with the given prefix.
+/// The replacement is always a 16-char hash prefix, so picking a title
+/// inserts an unambiguous hash.
+fn node_completions(nodes: &[NodeCompletion], prefix: &str) -> Vec<Pair> {
+    let prefix_lower = prefix.to_lowercase();
+    nodes
+        .iter()
+        .filter(|n| {
+            n.hash.starts_with(&prefix_lower) || n.title.to_lowercase().starts_with(&prefix_lower)
+        })
+        .map(|n| {
+            let short = &n.hash[..16.min(n.hash.len())];
+            Pair {
+                display: format!("{}  {}", short, n.title),
+                replacement: short.to_string(),
+            }
+        })
+        .collect()
+}
+
 /// Return command completions matching the given prefix.
 fn command_completions(prefix: &str) -> Vec<Pair> {
-    ALL_COMMANDS
-        .iter()
+    catalog::all_names()
         .filter(|c| c.starts_with(prefix))
         .map(|c| Pair {
             display: c.to_string(),
@@ -100,3 +177,37 @@ fn command_completions(prefix: &str) -> Vec<Pair> {
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nodes() -> Vec<NodeCompletion> {
+        vec![
+            NodeCompletion {
+                hash: "abcdef0123456789abcdef".to_string(),
+                title: "Super Mario Bros".to_string(),
+                favorite: false,
+            },
+            NodeCompletion {
+                hash: "0123456789abcdef0123".to_string(),
+                title: "Zelda [1.1]".to_string(),
+                favorite: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_node_completions_by_hash_and_title() {
+        let by_hash = node_completions(&nodes(), "ABC");
+        assert_eq!(by_hash.len(), 1);
+        assert_eq!(by_hash[0].replacement, "abcdef0123456789");
+
+        let by_title = node_completions(&nodes(), "zel");
+        assert_eq!(by_title.len(), 1);
+        assert_eq!(by_title[0].replacement, "0123456789abcdef");
+
+        assert_eq!(node_completions(&nodes(), "").len(), 2);
+        assert!(node_completions(&nodes(), "metroid").is_empty());
+    }
+}
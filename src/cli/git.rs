@@ -0,0 +1,147 @@
+//! Committing export folders to Git (`export --git`, `set export_git on`),
+//! so a shared library keeps its history without extra tooling.
+//!
+//! Runs the `git` command rather than linking a Git library, so the user's
+//! own Git configuration (identity, signing, hooks) applies.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Name and email used when Git has no identity configured.
+const FALLBACK_NAME: &str = "dromos";
+const FALLBACK_EMAIL: &str = "dromos@localhost";
+
+/// Stage everything in `dir` and commit it with `message`, creating the
+/// repository first if `dir` isn't one. A folder inside another repository
+/// gets its own, so nothing else staged there is committed with it.
+/// Returns the new commit's short hash, or None if nothing changed since
+/// the last commit.
+///
+/// Changed lines that all contain `volatile` (e.g. an export timestamp)
+/// don't count as changes: they're put back as last committed, so repeated
+/// exports of the same library don't fill the history with empty commits.
+pub fn commit_folder(dir: &Path, message: &str, volatile: &str) -> Result<Option<String>, String> {
+    if !dir.join(".git").exists() {
+        git(dir, &["init", "--quiet"])?;
+    }
+    git(dir, &["add", "--all", "."])?;
+    let diff = git(dir, &["diff", "--cached", "--unified=0", "--", "."])?;
+    let mut changed = diff
+        .lines()
+        .filter(|line| {
+            (line.starts_with('+') || line.starts_with('-'))
+                && !line.starts_with("+++")
+                && !line.starts_with("---")
+        })
+        .peekable();
+    if changed.peek().is_none() {
+        return Ok(None);
+    }
+    if changed.all(|line| line.contains(volatile)) {
+        git(dir, &["reset", "--quiet", "--", "."])?;
+        git(dir, &["checkout", "--", "."])?;
+        return Ok(None);
+    }
+
+    let mut args = Vec::new();
+    if git(dir, &["config", "user.email"]).is_err() {
+        let name = format!("user.name={}", FALLBACK_NAME);
+        let email = format!("user.email={}", FALLBACK_EMAIL);
+        args.extend(["-c".to_string(), name, "-c".to_string(), email]);
+    }
+    args.extend(["commit", "--quiet", "-m", message].map(String::from));
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    git(dir, &args)?;
+    git(dir, &["rev-parse", "--short", "HEAD"]).map(Some)
+}
+
+/// Run git in `dir`, returning its trimmed output, or its error message if
+/// it fails.
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("can't run git: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("git {}: {}", args.join(" "), stderr.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_folder() {
+        // Nothing to test against without git installed
+        if Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let index = dir.join("index.json");
+        std::fs::write(&index, "exported_at: 1\nfiles: a\n").unwrap();
+
+        let first = commit_folder(dir, "Export 1 ROM", "exported_at").unwrap();
+        assert!(first.is_some());
+        assert!(dir.join(".git").is_dir());
+        assert_eq!(
+            commit_folder(dir, "Export 1 ROM", "exported_at").unwrap(),
+            None
+        );
+
+        // Only the timestamp changed: put back rather than committed
+        std::fs::write(&index, "exported_at: 2\nfiles: a\n").unwrap();
+        assert_eq!(
+            commit_folder(dir, "Export 1 ROM", "exported_at").unwrap(),
+            None
+        );
+        assert_eq!(
+            std::fs::read_to_string(&index).unwrap(),
+            "exported_at: 1\nfiles: a\n"
+        );
+
+        std::fs::write(&index, "exported_at: 3\nfiles: a, b\n").unwrap();
+        let second = commit_folder(dir, "Export 0 ROMs", "exported_at").unwrap();
+        assert!(second.is_some() && second != first);
+        assert_eq!(
+            git(dir, &["log", "-1", "--format=%s"]).unwrap(),
+            "Export 0 ROMs"
+        );
+    }
+
+    #[test]
+    fn test_commit_folder_inside_another_repository() {
+        if Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let temp_dir = tempfile::tempdir().unwrap();
+        let outer = temp_dir.path();
+        git(outer, &["init", "--quiet"]).unwrap();
+        std::fs::write(outer.join("notes.txt"), "unrelated").unwrap();
+        git(outer, &["add", "notes.txt"]).unwrap();
+
+        let dir = outer.join("export");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("index.json"), "files: a\n").unwrap();
+        assert!(
+            commit_folder(&dir, "Export 1 ROM", "exported_at")
+                .unwrap()
+                .is_some()
+        );
+
+        // The export has its own history; the outer repository is untouched
+        assert!(dir.join(".git").is_dir());
+        assert_eq!(git(&dir, &["log", "--format=%s"]).unwrap(), "Export 1 ROM");
+        assert!(git(outer, &["rev-parse", "HEAD"]).is_err());
+        assert_eq!(
+            git(outer, &["diff", "--cached", "--name-only"]).unwrap(),
+            "notes.txt"
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Translations of shell messages.
+//!
+//! Messages are looked up by id in catalogs written in a subset of Project
+//! Fluent's FTL syntax: `id = text` lines, where `{ $name }` is replaced by
+//! an argument, indented lines continue the message above them, and lines
+//! starting with `#` are comments. English (`locales/en.ftl`) is built in
+//! and fills in anything a translation leaves out.
+//!
+//! The language comes from `LC_ALL`, `LC_MESSAGES`, or `LANG`, as for other
+//! Unix tools. Translations are read from the `locales` folder next to
+//! `dromos.conf`, trying the full language tag and then the language alone
+//! (`pt-BR.ftl`, then `pt.ftl`), so a community can ship one without
+//! rebuilding dromos.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::{LazyLock, RwLock};
+
+use crate::config::locales_dir;
+
+/// The built-in English catalog.
+static ENGLISH: LazyLock<Catalog> = LazyLock::new(|| {
+    Catalog::parse(include_str!("../../locales/en.ftl")).expect("locales/en.ftl is valid")
+});
+
+/// The translation in use, if any; set by [`init`].
+static TRANSLATION: RwLock<Option<Catalog>> = RwLock::new(None);
+
+/// Look up message `$id`, filling in `name = value` arguments.
+macro_rules! t {
+    ($id:literal) => {
+        $crate::cli::i18n::message($id, &[])
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::cli::i18n::message(
+            $id,
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+],
+        )
+    };
+}
+pub(crate) use t;
+
+/// Message `$one` or `$other` depending on `count`, which fills in its
+/// `{ $count }`: `1 ROM` or `3 ROMs`.
+macro_rules! counted {
+    ($count:expr, $one:literal, $other:literal) => {
+        match $count {
+            1 => $crate::cli::i18n::t!($one, count = 1),
+            count => $crate::cli::i18n::t!($other, count = count),
+        }
+    };
+}
+pub(crate) use counted;
+
+/// Messages by id, from one FTL file.
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn parse(text: &str) -> Result<Catalog, String> {
+        let mut messages: HashMap<String, String> = HashMap::new();
+        let mut current: Option<String> = None;
+
+        for (i, line) in text.lines().enumerate() {
+            let err = |msg: &str| format!("line {}: {}", i + 1, msg);
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                current = None;
+                continue;
+            }
+            if line.starts_with(char::is_whitespace) {
+                let id = current
+                    .as_ref()
+                    .ok_or_else(|| err("indented line outside a message"))?;
+                let message = messages.get_mut(id).expect("current message was inserted");
+                if !message.is_empty() {
+                    message.push('\n');
+                }
+                message.push_str(trimmed);
+                continue;
+            }
+
+            let (id, value) = line
+                .split_once('=')
+                .ok_or_else(|| err("expected `id = text`"))?;
+            let id = id.trim();
+            let valid_id = id.starts_with(|c: char| c.is_ascii_alphabetic())
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid_id {
+                return Err(err(&format!("invalid message id `{}`", id)));
+            }
+            if messages
+                .insert(id.to_string(), value.trim().to_string())
+                .is_some()
+            {
+                return Err(err(&format!("`{}` is already defined", id)));
+            }
+            current = Some(id.to_string());
+        }
+
+        Ok(Catalog { messages })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.messages.get(id).map(String::as_str)
+    }
+}
+
+/// Load the translation for the language the environment asks for, if
+/// there is one. Errors name the translation file that couldn't be used;
+/// messages stay in English then.
+pub fn init() -> Result<(), String> {
+    let (Some(language), Some(dir)) = (language_from_env(), locales_dir()) else {
+        return Ok(());
+    };
+    if let Some(catalog) = load_translation(&dir, &language)? {
+        *TRANSLATION.write().unwrap_or_else(|e| e.into_inner()) = Some(catalog);
+    }
+    Ok(())
+}
+
+/// The first translation in `dir` for `language`, trying the full tag and
+/// then the language alone.
+fn load_translation(dir: &Path, language: &str) -> Result<Option<Catalog>, String> {
+    let mut tags = vec![language];
+    if let Some((base, _)) = language.split_once('-') {
+        tags.push(base);
+    }
+    for tag in tags {
+        let path = dir.join(format!("{}.ftl", tag));
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                return Catalog::parse(&text)
+                    .map(Some)
+                    .map_err(|e| format!("{}: {}", path.display(), e));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+    Ok(None)
+}
+
+/// The language tag (e.g. `pt-BR`) from `LC_ALL`, `LC_MESSAGES`, or `LANG`,
+/// or None for English and the C locale.
+fn language_from_env() -> Option<String> {
+    let value = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())?;
+    language_tag(&value)
+}
+
+/// `pt_BR.UTF-8` -> `pt-BR`; None for English and the C locale.
+fn language_tag(locale: &str) -> Option<String> {
+    // Drop the encoding and modifier, as in `de_DE.UTF-8@euro`
+    let locale = locale.split(['.', '@']).next()?;
+    let tag = locale.replace('_', "-");
+    match tag.split('-').next()? {
+        "" | "C" | "POSIX" | "en" => None,
+        _ => Some(tag),
+    }
+}
+
+/// Message `id` from the translation, or from English when the translation
+/// leaves it out, with `{ $name }` placeholders filled in from `args`.
+/// Unknown ids come back as the id itself.
+pub fn message(id: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let translation = TRANSLATION.read().unwrap_or_else(|e| e.into_inner());
+    let text = translation
+        .as_ref()
+        .and_then(|catalog| catalog.get(id))
+        .or_else(|| ENGLISH.get(id))
+        .unwrap_or(id);
+    fill_placeholders(text, args)
+}
+
+/// Replace each `{ $name }` in `text` with its argument, leaving
+/// placeholders without one as they are.
+fn fill_placeholders(text: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(len) = rest.find('}') else {
+            break;
+        };
+        let placeholder = &rest[..=len];
+        let name = placeholder[1..len].trim().strip_prefix('$');
+        match args.iter().find(|(arg, _)| Some(*arg) == name) {
+            Some((_, value)) => out.push_str(&value.to_string()),
+            None => out.push_str(placeholder),
+        }
+        rest = &rest[len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_catalog() {
+        let catalog = Catalog::parse(
+            "# comment\n\nrom-not-found = ROM introuvable :\nlong = first\n    second\n",
+        )
+        .unwrap();
+        assert_eq!(catalog.get("rom-not-found"), Some("ROM introuvable :"));
+        assert_eq!(catalog.get("long"), Some("first\nsecond"));
+        assert_eq!(catalog.get("missing"), None);
+
+        assert!(Catalog::parse("no equals sign").is_err());
+        assert!(Catalog::parse("a = 1\na = 2").is_err());
+        assert!(Catalog::parse("  orphan").is_err());
+    }
+
+    #[test]
+    fn test_fill_placeholders() {
+        let path = "/data/dromos.db";
+        assert_eq!(
+            fill_placeholders("library: { $path }", &[("path", &path)]),
+            "library: /data/dromos.db"
+        );
+        assert_eq!(fill_placeholders("{$count} ROMs", &[]), "{$count} ROMs");
+        assert_eq!(fill_placeholders("a { b", &[]), "a { b");
+        assert_eq!(
+            message("banner-library", &[("path", &path)]),
+            "library: /data/dromos.db"
+        );
+        assert_eq!(message("no-such-message", &[]), "no-such-message");
+    }
+
+    #[test]
+    fn test_language_tag() {
+        assert_eq!(language_tag("pt_BR.UTF-8").as_deref(), Some("pt-BR"));
+        assert_eq!(language_tag("de_DE@euro").as_deref(), Some("de-DE"));
+        assert_eq!(language_tag("ja").as_deref(), Some("ja"));
+        assert_eq!(language_tag("en_US.UTF-8"), None);
+        assert_eq!(language_tag("C.UTF-8"), None);
+    }
+
+    #[test]
+    fn test_load_translation() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("pt.ftl"), "cancelled = Cancelado.\n").unwrap();
+        let catalog = load_translation(temp_dir.path(), "pt-BR").unwrap().unwrap();
+        assert_eq!(catalog.get("cancelled"), Some("Cancelado."));
+        assert!(load_translation(temp_dir.path(), "ja").unwrap().is_none());
+
+        std::fs::write(temp_dir.path().join("ja.ftl"), "broken").unwrap();
+        assert!(load_translation(temp_dir.path(), "ja").is_err());
+    }
+
+    /// Every id the shell looks up is in the English catalog.
+    #[test]
+    fn test_english_has_every_message() {
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut pending = vec![src];
+        while let Some(dir) = pending.pop() {
+            for entry in std::fs::read_dir(&dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                let text = std::fs::read_to_string(&path).unwrap();
+                let uses = text
+                    .match_indices("t!(\"")
+                    .filter(|(at, _)| {
+                        // Not the end of another macro's name, like `assert!("`
+                        !text[..*at].ends_with(|c: char| c.is_alphanumeric() || c == '_')
+                    })
+                    .chain(text.match_indices("i18n::message(\""));
+                for (at, _) in uses {
+                    let id = text[at..].split('"').nth(1).unwrap();
+                    assert!(
+                        ENGLISH.get(id).is_some(),
+                        "{} uses `{}`, missing from locales/en.ftl",
+                        path.display(),
+                        id
+                    );
+                }
+            }
+        }
+    }
+}
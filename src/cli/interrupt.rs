@@ -0,0 +1,68 @@
+//! Ctrl+C in the shell.
+//!
+//! Ctrl+C normally ends the process. While a command holds an [`arm`] guard,
+//! the first Ctrl+C only sets a flag that the guard's [`token`](Guard::token)
+//! sees, so the operation stops at its next step. A second Ctrl+C exits
+//! immediately, for operations stuck in a single long step. The flag lives
+//! here rather than in the library, which only sees the tokens it is given.
+//!
+//! Passing `interrupt::arm().token()` straight to an operation arms Ctrl+C
+//! for just that statement, so prompts after it still exit on Ctrl+C.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::cancel::CancelToken;
+
+static ARMED: AtomicBool = AtomicBool::new(false);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Exit code for a process ended by Ctrl+C (128 + SIGINT).
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Install the Ctrl+C handler. Call once at startup.
+pub fn install() -> std::io::Result<()> {
+    let handler = || {
+        if !ARMED.load(Ordering::SeqCst) || INTERRUPTED.swap(true, Ordering::SeqCst) {
+            signal_hook::low_level::exit(INTERRUPTED_EXIT_CODE);
+        }
+    };
+    // SAFETY: the handler only touches atomics and calls the
+    // async-signal-safe `exit`.
+    unsafe { signal_hook::low_level::register(signal_hook::consts::SIGINT, handler) }?;
+    Ok(())
+}
+
+/// Guard that keeps Ctrl+C armed until it is dropped.
+pub struct Guard {
+    was_armed: bool,
+    token: CancelToken,
+}
+
+impl Guard {
+    /// Token for the operation, cancelled by Ctrl+C while the guard lives.
+    pub fn token(&self) -> &CancelToken {
+        &self.token
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        ARMED.store(self.was_armed, Ordering::SeqCst);
+        if !self.was_armed {
+            INTERRUPTED.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Let Ctrl+C cancel the current operation instead of ending the process.
+/// Guards nest; only the outermost one clears a pending Ctrl+C.
+pub fn arm() -> Guard {
+    let was_armed = ARMED.swap(true, Ordering::SeqCst);
+    if !was_armed {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+    }
+    Guard {
+        was_armed,
+        token: CancelToken::with_signal(&INTERRUPTED),
+    }
+}
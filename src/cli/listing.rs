@@ -0,0 +1,326 @@
+//! Column layout for the `list` command, and the Markdown and CSV tables
+//! `report` writes with the same columns.
+//!
+//! The columns and sort order can be chosen per command
+//! (`list --columns title,size --sort size`) or set as the default with
+//! `list_columns` and `list_sort` in `dromos.conf`.
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::csv::quote_field;
+
+/// A field `list` can show or sort by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListColumn {
+    Title,
+    Version,
+    Hash,
+    Type,
+    /// ROM data size (PRG + CHR) from the stored header
+    Size,
+    Links,
+    Tags,
+    /// Release date
+    Date,
+    /// When the ROM was added to the library
+    Added,
+    /// Canonical name from an imported DAT, for verified good dumps
+    Dat,
+    /// 1 to 5, shown as stars
+    Rating,
+    /// Play status: unplayed, playing, finished, or abandoned
+    Status,
+    /// Regions the release is for, e.g. USA, Japan
+    Region,
+    /// Language codes, e.g. en, ja
+    Language,
+}
+
+impl ListColumn {
+    pub const ALL: [ListColumn; 14] = [
+        ListColumn::Title,
+        ListColumn::Version,
+        ListColumn::Hash,
+        ListColumn::Type,
+        ListColumn::Size,
+        ListColumn::Links,
+        ListColumn::Tags,
+        ListColumn::Date,
+        ListColumn::Added,
+        ListColumn::Dat,
+        ListColumn::Rating,
+        ListColumn::Status,
+        ListColumn::Region,
+        ListColumn::Language,
+    ];
+
+    pub fn parse(name: &str) -> Option<ListColumn> {
+        let name = name.trim().to_lowercase();
+        ListColumn::ALL.into_iter().find(|c| c.to_string() == name)
+    }
+}
+
+impl fmt::Display for ListColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ListColumn::Title => "title",
+            ListColumn::Version => "version",
+            ListColumn::Hash => "hash",
+            ListColumn::Type => "type",
+            ListColumn::Size => "size",
+            ListColumn::Links => "links",
+            ListColumn::Tags => "tags",
+            ListColumn::Date => "date",
+            ListColumn::Added => "added",
+            ListColumn::Dat => "dat",
+            ListColumn::Rating => "rating",
+            ListColumn::Status => "status",
+            ListColumn::Region => "region",
+            ListColumn::Language => "lang",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Parse a single column name, listing the valid ones on error.
+pub fn parse_column(name: &str) -> Result<ListColumn, String> {
+    ListColumn::parse(name).ok_or_else(|| {
+        let names: Vec<String> = ListColumn::ALL.iter().map(|c| c.to_string()).collect();
+        format!(
+            "unknown column `{}` (expected one of: {})",
+            name.trim(),
+            names.join(", ")
+        )
+    })
+}
+
+/// Parse a comma-separated list of column names.
+pub fn parse_columns(list: &str) -> Result<Vec<ListColumn>, String> {
+    let columns = list
+        .split(',')
+        .filter(|name| !name.trim().is_empty())
+        .map(parse_column)
+        .collect::<Result<Vec<_>, _>>()?;
+    if columns.is_empty() {
+        return Err("no columns given".to_string());
+    }
+    Ok(columns)
+}
+
+/// Which columns `list` shows and how it orders the rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListLayout {
+    pub columns: Vec<ListColumn>,
+    pub sort: ListColumn,
+    /// Sort in descending order
+    pub reverse: bool,
+}
+
+impl Default for ListLayout {
+    fn default() -> Self {
+        ListLayout {
+            columns: vec![
+                ListColumn::Title,
+                ListColumn::Version,
+                ListColumn::Hash,
+                ListColumn::Type,
+                ListColumn::Links,
+                ListColumn::Dat,
+            ],
+            sort: ListColumn::Title,
+            reverse: false,
+        }
+    }
+}
+
+impl ListLayout {
+    /// Build the default layout from the config file's `list_columns` and
+    /// `list_sort` values, falling back to the built-in layout for either.
+    pub fn from_config(columns: Option<&str>, sort: Option<&str>) -> Result<ListLayout, String> {
+        let mut layout = ListLayout::default();
+        if let Some(columns) = columns {
+            layout.columns = parse_columns(columns)?;
+        }
+        if let Some(sort) = sort {
+            // A leading `-` sorts in descending order, e.g. `list_sort = -size`
+            let (name, reverse) = match sort.trim().strip_prefix('-') {
+                Some(name) => (name, true),
+                None => (sort, false),
+            };
+            layout.sort = parse_column(name)?;
+            layout.reverse = reverse;
+        }
+        Ok(layout)
+    }
+}
+
+/// Columns `report` writes unless given others.
+pub const REPORT_COLUMNS: [ListColumn; 6] = [
+    ListColumn::Title,
+    ListColumn::Version,
+    ListColumn::Hash,
+    ListColumn::Type,
+    ListColumn::Tags,
+    ListColumn::Links,
+];
+
+/// A table format `report` writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Csv,
+    Markdown,
+}
+
+impl ReportFormat {
+    /// The format a file's extension names, if any
+    pub fn from_path(path: &Path) -> Option<ReportFormat> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "csv" => Some(ReportFormat::Csv),
+            "md" | "markdown" => Some(ReportFormat::Markdown),
+            _ => None,
+        }
+    }
+
+    /// Format `rows` as a table headed by the column names.
+    pub fn format(self, columns: &[ListColumn], rows: &[Vec<String>]) -> String {
+        let header: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+        let mut out = String::new();
+        match self {
+            ReportFormat::Csv => {
+                for row in std::iter::once(&header).chain(rows) {
+                    let cells: Vec<String> = row.iter().map(|c| quote_field(c)).collect();
+                    out.push_str(&cells.join(","));
+                    // RFC 4180 ends records with CRLF
+                    out.push_str("\r\n");
+                }
+            }
+            ReportFormat::Markdown => {
+                let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+                out.push_str(&line(header));
+                out.push_str(&line(columns.iter().map(|_| "---".to_string()).collect()));
+                for row in rows {
+                    out.push_str(&line(row.iter().map(|c| markdown_cell(c)).collect()));
+                }
+            }
+        }
+        out
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ReportFormat::Csv),
+            "md" | "markdown" => Ok(ReportFormat::Markdown),
+            _ => Err(format!("unknown format `{}` (expected one of: csv, md)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportFormat::Csv => write!(f, "csv"),
+            ReportFormat::Markdown => write!(f, "md"),
+        }
+    }
+}
+
+/// Keep a Markdown table cell on one line and its pipes from splitting it.
+fn markdown_cell(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ")
+}
+
+/// Width of each column: the longest cell in that column, except the last
+/// column, which is never padded.
+pub fn column_widths(rows: &[Vec<String>]) -> Vec<usize> {
+    let count = rows.first().map_or(0, Vec::len);
+    (0..count)
+        .map(|i| {
+            if i + 1 == count {
+                0
+            } else {
+                rows.iter()
+                    .map(|row| row[i].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_columns() {
+        assert_eq!(
+            parse_columns("title, Size,links").unwrap(),
+            vec![ListColumn::Title, ListColumn::Size, ListColumn::Links]
+        );
+        assert!(parse_columns("title,colour").is_err());
+        assert!(parse_columns(" , ").is_err());
+    }
+
+    #[test]
+    fn test_layout_from_config() {
+        assert_eq!(
+            ListLayout::from_config(None, None).unwrap(),
+            ListLayout::default()
+        );
+
+        let layout = ListLayout::from_config(Some("title,size"), Some("-size")).unwrap();
+        assert_eq!(layout.columns, vec![ListColumn::Title, ListColumn::Size]);
+        assert_eq!(layout.sort, ListColumn::Size);
+        assert!(layout.reverse);
+
+        assert!(ListLayout::from_config(None, Some("weight")).is_err());
+    }
+
+    #[test]
+    fn test_report_formats() {
+        assert_eq!(
+            ReportFormat::from_path(Path::new("roms.CSV")),
+            Some(ReportFormat::Csv)
+        );
+        assert_eq!(
+            ReportFormat::from_path(Path::new("wiki/roms.md")),
+            Some(ReportFormat::Markdown)
+        );
+        assert_eq!(ReportFormat::from_path(Path::new("roms.txt")), None);
+        assert_eq!("markdown".parse(), Ok(ReportFormat::Markdown));
+        assert!("xlsx".parse::<ReportFormat>().is_err());
+
+        let columns = [ListColumn::Title, ListColumn::Tags];
+        let rows = vec![vec![
+            "Tom \"Hack\" | Jerry".to_string(),
+            "hack, fr".to_string(),
+        ]];
+        assert_eq!(
+            ReportFormat::Csv.format(&columns, &rows),
+            "title,tags\r\n\"Tom \"\"Hack\"\" | Jerry\",\"hack, fr\"\r\n"
+        );
+        assert_eq!(
+            ReportFormat::Markdown.format(&columns, &rows),
+            "| title | tags |\n| --- | --- |\n| Tom \"Hack\" \\| Jerry | hack, fr |\n"
+        );
+    }
+
+    #[test]
+    fn test_column_widths() {
+        let rows = vec![
+            vec!["Zelda".to_string(), "NES".to_string(), "x".to_string()],
+            vec!["Metroid".to_string(), "".to_string(), "longest".to_string()],
+        ];
+        assert_eq!(column_widths(&rows), vec![7, 3, 0]);
+        assert!(column_widths(&[]).is_empty());
+    }
+}
@@ -1,9 +1,39 @@
+// `print!` and `println!` for the shell's output, which write through
+// `output::write_stdout` so output can be redirected in-process
+macro_rules! out {
+    ($($arg:tt)*) => {
+        $crate::cli::output::write_stdout(format_args!($($arg)*))
+    };
+}
+macro_rules! outln {
+    () => {
+        $crate::cli::output::write_stdout(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        $crate::cli::output::write_stdout(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+pub mod catalog;
 pub mod commands;
 pub mod completer;
+pub mod git;
+pub mod i18n;
+pub mod interrupt;
+pub mod listing;
 pub mod multiline;
+pub mod output;
+pub mod picker;
+pub mod progress;
 pub mod repl;
+pub mod rpc;
+pub mod session;
+pub mod status;
 pub mod theme;
+pub mod watch;
 
 pub use commands::Command;
-pub use completer::DromosHelper;
+pub use completer::{DromosHelper, NodeCompletion};
+pub use output::OutputFormat;
 pub use repl::ReplState;
+pub use status::ExitStatus;
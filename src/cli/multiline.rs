@@ -1,4 +1,5 @@
 use std::io::{self, Write};
+use std::process;
 
 use crossterm::{
     ExecutableCommand, cursor,
@@ -11,7 +12,8 @@ use crossterm::{
 pub fn edit_multiline(prompt: &str, initial: &str) -> io::Result<Option<String>> {
     let mut stdout = io::stdout();
 
-    // Print prompt
+    // Print prompt, on the terminal itself even while a command's output is
+    // redirected, like the editor
     println!("{}", prompt);
     println!("[Enter: newline | Ctrl+D: save | Esc: cancel]");
     println!();
@@ -30,6 +32,47 @@ pub fn edit_multiline(prompt: &str, initial: &str) -> io::Result<Option<String>>
     result
 }
 
+/// The user's external editor from `$VISUAL` or `$EDITOR`, if either is set.
+pub fn external_editor() -> Option<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+}
+
+/// Edit text in an external editor through a temporary file. The editor
+/// command may include arguments (e.g. `code --wait`). Returns None if the
+/// editor exits with an error, which counts as cancelling.
+pub fn edit_external(editor: &str, initial: &str) -> io::Result<Option<String>> {
+    let mut file = tempfile::Builder::new()
+        .prefix("dromos-description-")
+        .suffix(".txt")
+        .tempfile()?;
+    file.write_all(initial.as_bytes())?;
+    // Closed for the editor, and removed when dropped
+    let path = file.into_temp_path();
+
+    // Run through the shell so the editor command can carry arguments; the
+    // file is passed separately so its path needs no quoting
+    let status = if cfg!(target_os = "windows") {
+        process::Command::new("cmd")
+            .args(["/C", &format!("{} \"{}\"", editor, path.display())])
+            .status()
+    } else {
+        process::Command::new("sh")
+            .args(["-c", &format!("{} \"$1\"", editor), "sh"])
+            .arg(&path)
+            .status()
+    };
+
+    match status {
+        Ok(status) if status.success() => std::fs::read_to_string(&path)
+            .map(|text| Some(text.trim_end_matches(['\n', '\r']).to_string())),
+        Ok(_) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
 fn run_editor(stdout: &mut io::Stdout, initial: &str) -> io::Result<Option<String>> {
     let mut lines: Vec<String> = if initial.is_empty() {
         vec![String::new()]
@@ -107,17 +150,13 @@ fn run_editor(stdout: &mut io::Stdout, initial: &str) -> io::Result<Option<Strin
                         cursor_col = 0;
                     }
                 }
-                (KeyCode::Up, _) => {
-                    if cursor_line > 0 {
-                        cursor_line -= 1;
-                        cursor_col = cursor_col.min(lines[cursor_line].len());
-                    }
+                (KeyCode::Up, _) if cursor_line > 0 => {
+                    cursor_line -= 1;
+                    cursor_col = cursor_col.min(lines[cursor_line].len());
                 }
-                (KeyCode::Down, _) => {
-                    if cursor_line < lines.len() - 1 {
-                        cursor_line += 1;
-                        cursor_col = cursor_col.min(lines[cursor_line].len());
-                    }
+                (KeyCode::Down, _) if cursor_line < lines.len() - 1 => {
+                    cursor_line += 1;
+                    cursor_col = cursor_col.min(lines[cursor_line].len());
                 }
                 // Home/End
                 (KeyCode::Home, _) => {
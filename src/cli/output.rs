@@ -0,0 +1,251 @@
+//! Structured output for `--json` mode, and where the shell's output goes.
+//!
+//! Commands build these view types from storage data and emit one JSON
+//! document per command on stdout.
+//!
+//! The shell's `out!` and `outln!` write through [`write_stdout`], so a
+//! command's output can be sent to a file or pipeline with
+//! [`redirect_stdout`] while it runs in this process.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+
+use crate::graph::RomNode;
+use crate::rom::{NesHeader, RomMetadata, format_hash, mapper_name};
+
+/// How command results are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!(
+                "Unknown output format: {} (expected text or json)",
+                s
+            )),
+        }
+    }
+}
+
+/// A ROM node as seen in JSON output.
+#[derive(Debug, Serialize)]
+pub struct NodeJson {
+    pub sha256: String,
+    pub title: String,
+    pub version: Option<String>,
+    pub rom_type: String,
+    pub filename: Option<String>,
+}
+
+impl From<&RomNode> for NodeJson {
+    fn from(node: &RomNode) -> Self {
+        NodeJson {
+            sha256: format_hash(&node.sha256),
+            title: node.title.clone(),
+            version: node.version.clone(),
+            rom_type: node.rom_type.as_str().to_string(),
+            filename: node.filename.clone(),
+        }
+    }
+}
+
+/// Parsed NES header fields.
+#[derive(Debug, Serialize)]
+pub struct HeaderJson {
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
+    pub has_trainer: bool,
+    pub mapper: u16,
+    pub mapper_name: Option<&'static str>,
+    pub submapper: Option<u8>,
+    pub mirroring: String,
+    pub has_battery: bool,
+    pub is_nes2: bool,
+}
+
+impl From<&NesHeader> for HeaderJson {
+    fn from(header: &NesHeader) -> Self {
+        HeaderJson {
+            prg_rom_size: header.prg_rom_size,
+            chr_rom_size: header.chr_rom_size,
+            has_trainer: header.has_trainer,
+            mapper: header.mapper,
+            mapper_name: mapper_name(header.mapper),
+            submapper: header.submapper,
+            mirroring: format!("{:?}", header.mirroring),
+            has_battery: header.has_battery,
+            is_nes2: header.is_nes2,
+        }
+    }
+}
+
+/// Hash result for a file on disk.
+#[derive(Debug, Serialize)]
+pub struct RomFileJson {
+    pub sha256: String,
+    pub sha1: Option<String>,
+    /// CRC32 as 8 hex digits, as DAT files list it
+    pub crc32: Option<String>,
+    pub rom_type: String,
+    pub nes_header: Option<HeaderJson>,
+}
+
+impl From<&RomMetadata> for RomFileJson {
+    fn from(metadata: &RomMetadata) -> Self {
+        RomFileJson {
+            sha256: format_hash(&metadata.sha256),
+            sha1: metadata.sha1.map(hex::encode),
+            crc32: metadata.crc32.map(|c| format!("{:08x}", c)),
+            rom_type: metadata.rom_type.as_str().to_string(),
+            nes_header: metadata.nes_header.as_ref().map(HeaderJson::from),
+        }
+    }
+}
+
+thread_local! {
+    /// Documents collected by [`capture_json`] instead of being printed
+    static CAPTURED: RefCell<Option<Vec<serde_json::Value>>> = const { RefCell::new(None) };
+
+    /// Where [`write_stdout`] writes instead of stdout, while a
+    /// [`redirect_stdout`] runs
+    static REDIRECT: RefCell<Option<Box<dyn Write>>> = const { RefCell::new(None) };
+}
+
+/// Write shell output to stdout, or where [`redirect_stdout`] sends it.
+/// Failures, like a pager quit before reading everything, are ignored.
+pub fn write_stdout(args: fmt::Arguments) {
+    REDIRECT.with_borrow_mut(|redirect| {
+        let _ = match redirect {
+            Some(to) => to.write_fmt(args),
+            None => io::stdout().write_fmt(args),
+        };
+    });
+}
+
+/// Run `f` with shell output sent to `to` instead of stdout. `to` is
+/// flushed and dropped afterwards, so a pipe to it is closed.
+pub fn redirect_stdout<R>(to: Box<dyn Write>, f: impl FnOnce() -> R) -> R {
+    let outer = REDIRECT.replace(Some(to));
+    let result = f();
+    if let Some(mut to) = REDIRECT.replace(outer) {
+        let _ = to.flush();
+    }
+    result
+}
+
+/// Whether shell output is being sent somewhere other than stdout
+pub fn is_redirected() -> bool {
+    REDIRECT.with_borrow(Option::is_some)
+}
+
+/// Set while the current command's output is JSON
+static PROMPTS_ON_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Send prompts, and listings leading up to them, to stderr so stdout holds
+/// nothing but JSON documents.
+pub fn set_prompts_on_stderr(enabled: bool) {
+    PROMPTS_ON_STDERR.store(enabled, Ordering::Relaxed);
+}
+
+/// Show a prompt for input where the user will see it: on stdout, or on
+/// stderr while output is redirected or JSON.
+pub fn write_prompt(args: fmt::Arguments) -> io::Result<()> {
+    let mut to: Box<dyn Write> = if is_redirected() || PROMPTS_ON_STDERR.load(Ordering::Relaxed) {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    };
+    to.write_fmt(args)?;
+    to.flush()
+}
+
+/// Print a value as a single line of JSON on stdout, or collect it if a
+/// [`capture_json`] is running and the output isn't redirected.
+pub fn print_json<T: Serialize + ?Sized>(value: &T) {
+    if is_redirected() {
+        match serde_json::to_string(value) {
+            Ok(s) => outln!("{}", s),
+            Err(e) => eprintln!("JSON serialization failed: {}", e),
+        }
+        return;
+    }
+    let captured = CAPTURED.with_borrow_mut(|captured| {
+        let documents = captured.as_mut()?;
+        match serde_json::to_value(value) {
+            Ok(v) => documents.push(v),
+            Err(e) => eprintln!("JSON serialization failed: {}", e),
+        }
+        Some(())
+    });
+    if captured.is_some() {
+        return;
+    }
+    match serde_json::to_string(value) {
+        Ok(s) => outln!("{}", s),
+        Err(e) => eprintln!("JSON serialization failed: {}", e),
+    }
+}
+
+/// Run `f`, returning the documents it emitted with [`print_json`] rather
+/// than printing them.
+pub fn capture_json<R>(f: impl FnOnce() -> R) -> (R, Vec<serde_json::Value>) {
+    let outer = CAPTURED.replace(Some(Vec::new()));
+    let result = f();
+    let documents = CAPTURED.replace(outer).unwrap_or_default();
+    (result, documents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_stdout() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("out.txt");
+        let file = std::fs::File::create(&path).unwrap();
+        let ((), documents) = capture_json(|| {
+            redirect_stdout(Box::new(file), || {
+                assert!(is_redirected());
+                outln!("line {}", 1);
+                print_json(&serde_json::json!({ "ok": true }));
+            })
+        });
+        assert!(!is_redirected());
+        assert!(documents.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "line 1\n{\"ok\":true}\n"
+        );
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+        assert_eq!("TEXT".parse::<OutputFormat>(), Ok(OutputFormat::Text));
+        assert!("yaml".parse::<OutputFormat>().is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! Fuzzy ROM picker for commands run without a hash argument.
+//!
+//! Titles match by a simple subsequence search: every query character must
+//! appear in order, and runs of consecutive characters or matches at the
+//! start of a word score higher. Hashes match by prefix only, since nearly
+//! any short query is a subsequence of a 64-character hex string.
+
+use std::fmt;
+
+use rustyline::Editor;
+use rustyline::history::DefaultHistory;
+
+use super::completer::{DromosHelper, NodeCompletion};
+use super::{output, theme};
+
+/// Maximum number of matches shown at once.
+const PICK_LIMIT: usize = 10;
+
+/// Score `text` against `query`, or None if the query isn't a subsequence.
+/// Higher is better; an empty query matches everything with score 0.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut prev_match: Option<usize> = None;
+
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let offset = text[pos..].iter().position(|&c| c == q)?;
+        let idx = pos + offset;
+
+        score += 1;
+        if prev_match.is_some_and(|p| p + 1 == idx) {
+            score += 4;
+        }
+        if idx == 0 || !text[idx - 1].is_alphanumeric() {
+            score += 3;
+        }
+
+        prev_match = Some(idx);
+        pos = idx + 1;
+    }
+    Some(score)
+}
+
+/// Nodes matching `query`, best first. A hash prefix match outranks any
+/// title match, and favorites win ties; other ties keep the input order.
+pub fn rank<'a>(nodes: &'a [NodeCompletion], query: &str) -> Vec<&'a NodeCompletion> {
+    let query = query.trim().to_lowercase();
+    let mut scored: Vec<(u32, &NodeCompletion)> = nodes
+        .iter()
+        .filter_map(|n| {
+            if !query.is_empty() && n.hash.starts_with(&query) {
+                Some((u32::MAX, n))
+            } else {
+                fuzzy_score(&query, &n.title).map(|s| (s, n))
+            }
+        })
+        .collect();
+    scored.sort_by_key(|(score, n)| std::cmp::Reverse((*score, n.favorite)));
+    scored.into_iter().map(|(_, n)| n).collect()
+}
+
+/// Print a line for the user, on the terminal even while a command's output
+/// is redirected.
+fn say(args: fmt::Arguments) {
+    let _ = output::write_prompt(format_args!("{}\n", args));
+}
+
+/// Let the user narrow down `nodes` by typing and choose one by number.
+/// Returns the chosen node's full hash, or None if the user cancels.
+pub fn pick(
+    nodes: &[NodeCompletion],
+    rl: &mut Editor<DromosHelper, DefaultHistory>,
+) -> Option<String> {
+    say(format_args!(
+        "{}",
+        theme::dim("Type to filter, enter a number to choose, or press Enter to cancel.")
+    ));
+
+    let mut query = String::new();
+    loop {
+        let matches = rank(nodes, &query);
+        if matches.is_empty() {
+            say(format_args!("{}", theme::warning("No matches.")));
+        }
+        for (i, node) in matches.iter().take(PICK_LIMIT).enumerate() {
+            say(format_args!(
+                "  {:>2}. {}{}  {}",
+                i + 1,
+                match (node.favorite, theme::plain()) {
+                    (false, _) => "",
+                    (true, false) => "★ ",
+                    (true, true) => "favorite: ",
+                },
+                theme::title(&node.title),
+                theme::styled_hash(&node.hash[..16])
+            ));
+        }
+        if matches.len() > PICK_LIMIT {
+            say(format_args!(
+                "{}",
+                theme::dim(&format!("  ... and {} more", matches.len() - PICK_LIMIT))
+            ));
+        }
+
+        let input = match rl.readline("pick> ") {
+            Ok(line) => line.trim().to_string(),
+            Err(_) => return None,
+        };
+        if input.is_empty() {
+            return None;
+        }
+
+        if let Ok(n) = input.parse::<usize>()
+            && (1..=matches.len().min(PICK_LIMIT)).contains(&n)
+        {
+            return Some(matches[n - 1].hash.clone());
+        }
+
+        // A refined query with a single match needs no further choice
+        let refined = rank(nodes, &input);
+        if refined.len() == 1 {
+            say(format_args!(
+                "{} {}",
+                theme::dim("Picked:"),
+                refined[0].title
+            ));
+            return Some(refined[0].hash.clone());
+        }
+        query = input;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(title: &str, hash: &str) -> NodeCompletion {
+        NodeCompletion {
+            hash: hash.to_string(),
+            title: title.to_string(),
+            favorite: false,
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert!(fuzzy_score("smb", "Super Mario Bros").is_some());
+        assert!(fuzzy_score("bms", "Super Mario Bros").is_none());
+
+        // Word starts and runs beat scattered matches
+        let word_starts = fuzzy_score("smb", "Super Mario Bros").unwrap();
+        let scattered = fuzzy_score("smb", "psoma ob").unwrap();
+        assert!(word_starts > scattered);
+    }
+
+    fn ranked_hashes<'a>(nodes: &'a [NodeCompletion], query: &str) -> Vec<&'a str> {
+        rank(nodes, query).iter().map(|n| n.hash.as_str()).collect()
+    }
+
+    #[test]
+    fn test_rank() {
+        let nodes = vec![
+            node("Zelda [USA]", "aaaa"),
+            node("Super Mario Bros", "bbbb"),
+            node("Super Metroid", "cccc"),
+        ];
+
+        assert_eq!(ranked_hashes(&nodes, "mario"), vec!["bbbb"]);
+        assert_eq!(ranked_hashes(&nodes, "sm"), vec!["bbbb", "cccc"]);
+        assert_eq!(ranked_hashes(&nodes, "").len(), 3);
+
+        // Favorites come first among equal scores
+        let mut nodes = nodes;
+        nodes[2].favorite = true;
+        assert_eq!(ranked_hashes(&nodes, ""), vec!["cccc", "aaaa", "bbbb"]);
+        assert_eq!(ranked_hashes(&nodes, "sm"), vec!["cccc", "bbbb"]);
+        assert_eq!(ranked_hashes(&nodes, "mario"), vec!["bbbb"]);
+
+        // Hashes match by prefix, not as a subsequence
+        assert_eq!(ranked_hashes(&nodes, "cc"), vec!["cccc"]);
+        assert!(ranked_hashes(&nodes, "ac").is_empty());
+    }
+}
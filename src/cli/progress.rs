@@ -0,0 +1,59 @@
+//! The status line shown on stderr during long commands.
+
+use std::io::{self, IsTerminal, Write};
+
+use crate::progress::{Progress, ProgressSink};
+
+/// Draws each progress update over the previous one on a single stderr
+/// line, and erases it when dropped so results print on a clean line.
+/// Does nothing unless stderr is a terminal.
+pub struct ProgressLine {
+    enabled: bool,
+    drawn: bool,
+}
+
+impl ProgressLine {
+    pub fn new(enabled: bool) -> Self {
+        ProgressLine {
+            enabled: enabled && io::stderr().is_terminal(),
+            drawn: false,
+        }
+    }
+}
+
+impl ProgressSink for ProgressLine {
+    fn progress(&mut self, progress: &Progress) {
+        if !self.enabled {
+            return;
+        }
+        let width = crossterm::terminal::size()
+            .map(|(cols, _)| cols as usize)
+            .unwrap_or(80);
+        let line = format!(
+            "{} {}/{} {}",
+            progress.stage, progress.current, progress.total, progress.item
+        );
+        // Keep the end of long paths, where the file name is
+        let chars = line.chars().count();
+        let line: String = if chars >= width {
+            let skip = chars + 2 - width;
+            format!("…{}", line.chars().skip(skip).collect::<String>())
+        } else {
+            line
+        };
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\r\x1b[2K{}", line);
+        let _ = stderr.flush();
+        self.drawn = true;
+    }
+}
+
+impl Drop for ProgressLine {
+    fn drop(&mut self) {
+        if self.drawn {
+            let mut stderr = io::stderr();
+            let _ = write!(stderr, "\r\x1b[2K");
+            let _ = stderr.flush();
+        }
+    }
+}
@@ -1,27 +1,122 @@
-use std::io::{self, Write};
-use std::path::Path;
-
+use std::cell::{Cell, OnceCell};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use regex::RegexBuilder;
 use rustyline::Editor;
 use rustyline::history::DefaultHistory;
-
-use crate::config::StorageConfig;
-use crate::db::NodeMetadata;
-use crate::error::Result;
-use crate::exchange::OverwriteAction;
-use crate::graph::RomNode;
-use crate::rom::{RomType, format_hash, hash_rom_file, reconstruct_nes_file_raw};
-use crate::storage::StorageManager;
+use serde_json::json;
+
+use crate::cache::LookupCache;
+use crate::checksums::{self, ChecksumFormat};
+use crate::config::{StorageConfig, UserConfig, config_file_path, save_setting};
+use crate::csv::{CsvEdits, MetadataChange};
+use crate::dat;
+use crate::db::collation::compare_titles;
+use crate::db::{
+    AttachmentRow, CollectionRow, DATA_REVISION, DatMatch, MAX_RATING, NodeFilter, NodeMetadata,
+    NodeRow, PlayStatus, check_rating,
+};
+use crate::dedupe;
+use crate::error::{DromosError, Result};
+#[cfg(feature = "server")]
+use crate::exchange::ExportManifest;
+use crate::exchange::{ConflictResolution, NodeConflict, RejectedNode};
+use crate::gamedb::GameDb;
+use crate::graph::{DiffEdge, RomNode};
+#[cfg(feature = "lan")]
+use crate::lan::{self, Advertisement, ShareInfo};
+use crate::plugin::{self, Plugin};
+use crate::rom::compare;
+use crate::rom::filename::{self, FileName};
+use crate::rom::{
+    NesHeader, RomMetadata, RomType, expand_file_patterns, find_rom_files, format_hash,
+    hash_rom_file, hash_rom_files, is_rom_file, mapper_name, nes_file_problems, parse_hash,
+    parse_nes_header_bytes, parse_nes2_details, reconstruct_nes_file_raw, region,
+};
+use crate::sdcard::SdLayout;
+#[cfg(feature = "server")]
+use crate::server::{self, Server};
+#[cfg(feature = "server")]
+use crate::storage::ObserverId;
+use crate::storage::health::{self, DiffProblem};
+use crate::storage::{BuildResult, LocateMatch, StorageManager};
+#[cfg(feature = "server")]
+use crate::sync::{Remote, Selection};
+use crate::timings;
+#[cfg(feature = "server")]
+use crate::webhook::{Failure, Webhooks};
 
 use super::Command;
-use super::completer::DromosHelper;
-use super::multiline::edit_multiline;
+use super::catalog;
+use super::commands::{
+    MetadataEdits, Redirect, emulator_args, expand_args, parse_quoted_args, split_redirection,
+    sql_query,
+};
+use super::completer::{DromosHelper, NodeCompletion};
+use super::i18n::{counted, t};
+use super::interrupt;
+use super::listing::{ListColumn, ListLayout, REPORT_COLUMNS, ReportFormat, column_widths};
+use super::multiline::{edit_external, edit_multiline, external_editor};
+use super::output::{self, HeaderJson, NodeJson, OutputFormat, RomFileJson};
+use super::picker;
+use super::progress::ProgressLine;
+use super::session::SessionSummary;
+use super::status::ExitStatus;
 use super::theme;
+use super::watch::FolderWatcher;
 
 pub struct ReplState {
     pub storage: StorageManager,
     pub last_added: Option<LastAdded>,
+    pub output: OutputFormat,
+    /// Suppress progress and confirmation messages; print only results and errors.
+    pub quiet: bool,
+    /// Answer yes to every confirmation prompt (`--yes` / `set confirm off`).
+    pub assume_yes: bool,
+    /// Serving `--rpc` requests, which arrive on stdin: prompts take their
+    /// default answers, and commands that read stdin are refused.
+    pub rpc: bool,
+    /// Outcome of the most recently executed command.
+    status: Cell<ExitStatus>,
+    /// Nesting depth of `source` scripts currently running.
+    source_depth: usize,
+    /// Aliases and macros from the user's config file.
+    pub user_config: UserConfig,
+    /// Default `list` columns and sort order.
+    pub list_layout: ListLayout,
+    /// Page long output through `$PAGER` (`set pager on`).
+    pub pager: bool,
+    /// Folder that relative `export` paths are placed in.
+    pub export_dir: Option<PathBuf>,
+    /// Commit export folders to Git (`set export_git on`).
+    pub export_git: bool,
+    /// OpenVGDB database suggesting metadata for new ROMs (`game_db`).
+    pub game_db: Option<GameDb>,
+    /// Plugins from the config file (`plugin.<name>`), in name order.
+    pub plugins: Vec<Plugin>,
+    /// Cache of plugin metadata lookups (`metadata_cache_days`).
+    pub lookup_cache: Option<LookupCache>,
+    /// Problems found by the diff check run in the background on open
+    diff_check: Option<Receiver<Vec<DiffProblem>>>,
+    /// Changes saved since the shell opened, summed up on `quit`
+    session: Arc<Mutex<SessionSummary>>,
+    /// Folder plugins convert files into, made on first use and removed as
+    /// the shell closes
+    converted_dir: OnceCell<tempfile::TempDir>,
 }
 
+/// Libraries with at most this many bytes of diffs have them checked in the
+/// background when the shell opens
+const BACKGROUND_DIFF_CHECK_BYTES: i64 = 64 * 1024 * 1024;
+
 #[derive(Clone)]
 pub struct LastAdded {
     pub hash: [u8; 32],
@@ -29,710 +124,6682 @@ pub struct LastAdded {
     pub version: Option<String>,
 }
 
+/// A new ROM file found by `scan`, with what its file name says about it
+struct ScanCandidate {
+    path: PathBuf,
+    metadata: RomMetadata,
+    name: FileName,
+    /// Derived title collides with an existing or sibling title
+    ambiguous: bool,
+}
+
+/// One `list` row: a node with the values computed for its columns
+struct ListEntry {
+    row: NodeRow,
+    size: Option<usize>,
+    links: usize,
+    dat: Option<DatMatch>,
+}
+
+impl ListEntry {
+    /// Plain text for one column (styled separately, after padding)
+    fn cell(&self, column: ListColumn) -> String {
+        match column {
+            ListColumn::Title => self.row.title.clone(),
+            ListColumn::Version => match self.row.version.as_deref() {
+                Some(v) if !v.is_empty() => format!("[{}]", v),
+                _ => String::new(),
+            },
+            ListColumn::Hash => format_hash(&self.row.sha256)[..16].to_string(),
+            ListColumn::Type => self.row.rom_type.to_string(),
+            ListColumn::Size => self.size.map(|s| format_size(s as i64)).unwrap_or_default(),
+            ListColumn::Links => match self.links {
+                0 => String::new(),
+                n => format!("[{}]", counted!(n, "links-one", "links-other")),
+            },
+            ListColumn::Tags => self.row.tags.join(", "),
+            ListColumn::Date => self.row.release_date.clone().unwrap_or_default(),
+            ListColumn::Added => self.row.created_at.clone(),
+            ListColumn::Dat => self
+                .dat
+                .as_ref()
+                .map(|m| format!("{} {}", verified_mark(), m.game))
+                .unwrap_or_default(),
+            ListColumn::Rating => match self.row.rating {
+                Some(r) if theme::plain() => t!("rating-of", rating = r, max = MAX_RATING),
+                Some(r) => "★".repeat(r as usize) + &"☆".repeat((MAX_RATING - r) as usize),
+                None => String::new(),
+            },
+            ListColumn::Status => self.row.play_status.to_string(),
+            ListColumn::Region => region::join_list(&self.row.regions),
+            ListColumn::Language => region::join_list(&self.row.languages),
+        }
+    }
+
+    /// Unadorned value for one column in a `report`: full hashes and plain
+    /// numbers, for spreadsheets
+    fn value(&self, column: ListColumn) -> String {
+        match column {
+            ListColumn::Version => self.row.version.clone().unwrap_or_default(),
+            ListColumn::Hash => format_hash(&self.row.sha256),
+            ListColumn::Size => self.size.map(|s| s.to_string()).unwrap_or_default(),
+            ListColumn::Links => self.links.to_string(),
+            ListColumn::Dat => self
+                .dat
+                .as_ref()
+                .map(|m| m.game.clone())
+                .unwrap_or_default(),
+            ListColumn::Rating => self.row.rating.map(|r| r.to_string()).unwrap_or_default(),
+            _ => self.cell(column),
+        }
+    }
+
+    fn compare(&self, other: &ListEntry, column: ListColumn) -> std::cmp::Ordering {
+        match column {
+            ListColumn::Size => self.size.cmp(&other.size),
+            ListColumn::Links => self.links.cmp(&other.links),
+            ListColumn::Rating => self.row.rating.cmp(&other.row.rating),
+            ListColumn::Status => self.row.play_status.cmp(&other.row.play_status),
+            ListColumn::Title | ListColumn::Version => {
+                compare_titles(&self.cell(column), &other.cell(column))
+            }
+            _ => self
+                .cell(column)
+                .to_lowercase()
+                .cmp(&other.cell(column).to_lowercase()),
+        }
+    }
+}
+
 /// Result of ensuring a ROM is in the database
 struct AddResult {
     title: String,
     version: Option<String>,
     hash: [u8; 32],
     newly_added: bool,
+    /// A wishlist ROM whose file this was
+    found: bool,
+}
+
+/// Two library ROMs' data, for comparing them byte by byte
+struct NodePair {
+    a: [u8; 32],
+    b: [u8; 32],
+    data_a: Vec<u8>,
+    data_b: Vec<u8>,
+    /// The header `a` was added with, for telling PRG from CHR
+    header: Option<NesHeader>,
+}
+
+impl AddResult {
+    /// `added`, `found`, or `exists`, for `--json` output
+    fn status(&self) -> &'static str {
+        if self.newly_added {
+            "added"
+        } else if self.found {
+            "found"
+        } else {
+            "exists"
+        }
+    }
 }
 
 impl ReplState {
     pub fn new(config: StorageConfig) -> Result<Self> {
-        let storage = StorageManager::open(config)?;
+        let mut storage = StorageManager::open(config)?;
+        let session = Arc::new(Mutex::new(SessionSummary::default()));
+        storage.add_observer(SessionSummary::observer(&session));
         Ok(ReplState {
             storage,
             last_added: None,
+            output: OutputFormat::Text,
+            quiet: false,
+            assume_yes: false,
+            rpc: false,
+            status: Cell::new(ExitStatus::Success),
+            source_depth: 0,
+            user_config: UserConfig::default(),
+            list_layout: ListLayout::default(),
+            pager: false,
+            export_dir: None,
+            export_git: false,
+            game_db: None,
+            plugins: Vec::new(),
+            lookup_cache: None,
+            diff_check: None,
+            session,
+            converted_dir: OnceCell::new(),
         })
     }
 
-    pub fn execute(
-        &mut self,
-        cmd: Command,
-        rl: &mut Editor<DromosHelper, DefaultHistory>,
-    ) -> Result<bool> {
-        match cmd {
-            Command::Quit => return Ok(false),
-            Command::Help => self.print_help(),
-            Command::Hash { file } => self.cmd_hash(&file)?,
-            Command::Check { file } => self.cmd_check(&file)?,
-            Command::Add { file } => self.cmd_add(&file, rl)?,
-            Command::Build { source, target } => self.cmd_build(&source, &target, rl)?,
-            Command::Edit { target } => self.cmd_edit(&target, rl)?,
-            Command::Export {
-                hash_prefix,
-                output,
-            } => self.cmd_export(hash_prefix.as_deref(), &output)?,
-            Command::Import { input } => self.cmd_import(&input)?,
-            Command::Link { files } => self.cmd_link(&files, rl)?,
-            Command::Links { target } => self.cmd_links(&target)?,
-            Command::List => self.cmd_list(),
-            Command::Rm { target } => self.cmd_rm(&target)?,
-            Command::Search { query } => self.cmd_search(&query),
+    /// Print what the session changed, if anything, as the shell closes,
+    /// and write it to the log.
+    pub fn print_session_summary(&self) {
+        let summary = self.session.lock().unwrap_or_else(|e| e.into_inner());
+        if summary.is_empty() {
+            return;
+        }
+        let links = summary.links.len();
+        let mut parts = vec![t!("session-added", count = summary.added)];
+        parts.push(match summary.diffs {
+            0 => t!("session-linked", count = links),
+            diffs => t!(
+                "session-linked-diffs",
+                count = links,
+                diffs = counted!(diffs, "diffs-one", "diffs-other"),
+                size = format_size(summary.diff_bytes),
+            ),
+        });
+        parts.push(t!("session-edited", count = summary.edited.len()));
+        parts.push(t!("session-removed", count = summary.removed));
+        let text = parts.join(", ");
+        #[cfg(feature = "tracing")]
+        tracing::info!("session: {}", text);
+
+        if self.json() {
+            output::print_json(&json!({
+                "session": {
+                    "added": summary.added,
+                    "linked": links,
+                    "diffs": summary.diffs,
+                    "diff_bytes": summary.diff_bytes,
+                    "edited": summary.edited.len(),
+                    "removed": summary.removed,
+                }
+            }));
+        } else if !self.quiet {
+            outln!("{} {}", theme::header(&t!("this-session")), text);
         }
-        Ok(true)
     }
 
-    fn print_help(&self) {
-        println!("{}", theme::header("Commands:"));
-        println!("  add <file>              Add a ROM to the database");
-        println!("  build <source> <hash>   Build a ROM by applying diffs from source to target");
-        println!("  check <file>            Check if a ROM is in the database");
-        println!("  edit <hash>             Edit metadata for a ROM");
-        println!("  export [hash] <path>    Export ROMs to a folder");
-        println!("  import <path>           Import ROMs from a folder");
-        println!("  link <file1> [file2]    Create bidirectional links between ROMs");
-        println!("  links <file|hash>       Show all links for a ROM");
-        println!("  list, ls                List all ROMs (sorted by title)");
-        println!("  rm, remove <hash>       Remove a ROM and all its links");
-        println!("  search <query>          Search ROMs by title");
-        println!("  hash <file>             Show ROM hash without adding to database");
-        println!("  help                    Show this help");
-        println!("  quit, exit              Exit dromos");
-    }
-
-    fn cmd_hash(&self, file: &Path) -> Result<()> {
-        let metadata = hash_rom_file(file)?;
-
-        println!("Hash: {}", format_hash(&metadata.sha256));
-        println!("Type: {}", metadata.rom_type);
-
-        if let Some(header) = &metadata.nes_header {
-            println!("PRG ROM: {} KB", header.prg_rom_size / 1024);
-            println!("CHR ROM: {} KB", header.chr_rom_size / 1024);
-            println!("Trainer: {}", if header.has_trainer { "Yes" } else { "No" });
+    /// Start checking the diff files on another thread (see `check-diffs`)
+    /// if the library is small enough for it to finish quickly. Problems are
+    /// reported by [`ReplState::report_diff_check`].
+    pub fn start_diff_check(&mut self) {
+        let Ok(diffs) = self.storage.stored_diffs() else {
+            return;
+        };
+        let total: i64 = diffs.iter().map(|d| d.diff_size).sum();
+        if diffs.is_empty() || total > BACKGROUND_DIFF_CHECK_BYTES {
+            return;
         }
-
-        Ok(())
+        let diffs_dir = self.storage.config().diffs_dir.clone();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let problems = diffs
+                .iter()
+                .filter_map(|stored| {
+                    let problem = health::check_diff(&diffs_dir, stored).err()?;
+                    Some(DiffProblem {
+                        from: stored.from,
+                        to: stored.to,
+                        diff_path: stored.diff_path.clone(),
+                        problem,
+                    })
+                })
+                .collect();
+            let _ = tx.send(problems);
+        });
+        self.diff_check = Some(rx);
     }
 
-    fn cmd_check(&self, file: &Path) -> Result<()> {
-        // Check if file exists
-        if !file.exists() {
-            eprintln!("{} {}", theme::error("File not found:"), file.display());
-            return Ok(());
+    /// Warn about damaged diffs once the background check has finished.
+    pub fn report_diff_check(&mut self) {
+        let Some(rx) = &self.diff_check else {
+            return;
+        };
+        let problems = match rx.try_recv() {
+            Ok(problems) => problems,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Vec::new(),
+        };
+        self.diff_check = None;
+        #[cfg(feature = "tracing")]
+        for problem in &problems {
+            tracing::warn!(diff = %problem.diff_path, problem = %problem.problem, "diff failed check");
+        }
+        if !problems.is_empty() {
+            warn(t!(
+                "diff-check-on-open",
+                files = counted!(problems.len(), "diff-files-one", "diff-files-other"),
+            ));
         }
+    }
 
-        // Hash the file and get metadata
-        let metadata = hash_rom_file(file)?;
-        let hash_str = format_hash(&metadata.sha256);
+    /// Outcome of the most recently executed command.
+    pub fn last_status(&self) -> ExitStatus {
+        self.status.get()
+    }
 
-        // Print the hash
-        println!("Hash: {}", hash_str);
-        println!("Type: {}", metadata.rom_type);
+    /// True when progress and confirmation messages should be printed.
+    fn narrate(&self) -> bool {
+        !self.json() && !self.quiet
+    }
 
-        // Look up in database
-        match self.storage.get_node_by_hash(&metadata.sha256) {
-            Some(node) => {
-                // Found in database - show title/version
-                let display_title = format_display_title(&node.title, node.version.as_deref());
-                println!("{} {}", theme::success("Found:"), display_title);
+    /// Status line for a long operation, shown when narrating (but not in
+    /// plain mode, since it redraws itself).
+    fn progress_line(&self) -> ProgressLine {
+        ProgressLine::new(self.narrate() && !theme::plain())
+    }
 
-                // Compare headers if file has one
-                if let Some(ref file_header) = metadata.source_file_header {
-                    // Get full NodeRow from database (has stored header)
-                    if let Ok(Some(node_row)) = self.storage.get_node_row_by_hash(&metadata.sha256)
-                    {
-                        match &node_row.source_file_header {
-                            Some(stored_header) if stored_header == file_header => {
-                                println!("Header: matches stored");
-                            }
-                            Some(_) => {
-                                println!("Header: DIFFERS from stored");
-                            }
-                            None => {
-                                println!("Header: (no stored header to compare)");
-                            }
-                        }
-                    }
-                }
-            }
-            None => {
-                println!("Status: not in database");
-            }
+    /// True when results should be emitted as JSON rather than colored text.
+    fn json(&self) -> bool {
+        self.output == OutputFormat::Json
+    }
+
+    /// Ask a `[y/N]` question on stdin. Always true when `assume_yes` is set.
+    fn confirm(&self, question: &str) -> Result<bool> {
+        if self.assume_yes {
+            return Ok(true);
         }
+        if self.rpc {
+            return Ok(false);
+        }
+        output::write_prompt(format_args!("{} {}: ", question, t!("confirm-choices")))?;
 
-        Ok(())
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        Ok(is_answer(&t!("confirm-yes"), &input))
     }
 
-    /// Ensure a ROM file is in the database, prompting for metadata if new.
-    /// Returns None if file doesn't exist (error already printed).
-    /// Returns AddResult with newly_added=false if ROM already exists.
-    /// Returns AddResult with newly_added=true if ROM was added.
-    fn ensure_rom_added(
-        &mut self,
-        file: &Path,
+    /// Ask for a new ROM's metadata, starting from `suggested`. Under `--rpc`
+    /// the suggestion is used as is.
+    fn prompt_metadata(
+        &self,
         rl: &mut Editor<DromosHelper, DefaultHistory>,
-    ) -> Result<Option<AddResult>> {
-        // Check if file exists
-        if !file.exists() {
-            eprintln!("{} {}", theme::error("File not found:"), file.display());
-            return Ok(None);
+        suggested: &NodeMetadata,
+    ) -> Result<NodeMetadata> {
+        if self.rpc {
+            return Ok(suggested.clone());
         }
+        prompt_metadata_edit(rl, suggested)
+    }
 
-        // Hash the file
-        let metadata = hash_rom_file(file)?;
-
-        // Check if ROM already exists
-        if self.storage.node_exists(&metadata.sha256) {
-            let node = self.storage.get_node_by_hash(&metadata.sha256).unwrap();
-            return Ok(Some(AddResult {
-                title: node.title.clone(),
-                version: node.version.clone(),
-                hash: metadata.sha256,
-                newly_added: false,
-            }));
+    /// Report a user-facing error (e.g. "ROM not found:" plus the target) and
+    /// record `status` as the command's outcome.
+    /// Text mode prints to stderr; JSON mode emits an error object on stdout.
+    fn report_error(&self, status: ExitStatus, label: &str, detail: &str) {
+        #[cfg(feature = "tracing")]
+        tracing::error!("{} {}", label, detail);
+        self.status.set(status);
+        if self.json() {
+            output::print_json(&json!({ "error": format!("{} {}", label, detail) }));
+        } else {
+            eprintln!("{} {}", theme::error(label), detail);
         }
+    }
 
-        // ROM doesn't exist - prompt for metadata and add
-        let filename = file.file_name().and_then(|n| n.to_str()).unwrap_or("file");
-        println!("{} {}", theme::info("Adding file"), filename);
+    /// Report a failed operation like [`Self::report_error`], taking the exit
+    /// status from `error` and adding its code and context to JSON output.
+    fn report_failure(&self, label: &str, detail: &str, error: &DromosError) {
+        #[cfg(feature = "tracing")]
+        tracing::error!(code = error.code(), "{} {}", label, detail);
+        self.status.set(ExitStatus::from_error(error));
+        if self.json() {
+            let mut report = json!({
+                "error": format!("{} {}", label, detail),
+                "code": error.code(),
+            });
+            let context = error.context();
+            if !context.is_empty() {
+                report["context"] = context.into();
+            }
+            output::print_json(&report);
+        } else {
+            eprintln!("{} {}", theme::error(label), detail);
+        }
+    }
 
-        let default_title = title_from_filename(file);
-        let node_metadata = prompt_metadata(rl, &default_title, None)?;
+    /// Report that no ROM matches `target` (a hash prefix, title, or file).
+    fn report_rom_not_found(&self, label: &str, target: &str) {
+        let error = DromosError::RomNotFound {
+            hash: target.to_string(),
+        };
+        self.report_failure(label, target, &error);
+    }
 
-        // Add to database
-        let metadata = self.storage.add_node(file, &node_metadata)?;
+    /// Report that the user declined a confirmation prompt.
+    fn report_cancelled(&self) {
+        self.status.set(ExitStatus::Cancelled);
+        if self.json() {
+            output::print_json(&json!({ "cancelled": true }));
+        } else {
+            outln!("{}", t!("cancelled"));
+        }
+    }
 
-        let display_title =
-            format_display_title(&node_metadata.title, node_metadata.version.as_deref());
-        println!(
-            "{} {} ({})",
-            theme::success("Added:"),
-            display_title,
-            theme::styled_hash(&format_hash(&metadata.sha256)[..16])
-        );
+    /// Resolve a hash prefix or title to a single node.
+    /// Reports an error and returns None if nothing matches; asks the user to
+    /// choose when several ROMs match (see [`Self::choose_node`]).
+    fn resolve_node(
+        &self,
+        target: &str,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Option<&RomNode> {
+        let by_hash = self.storage.find_nodes_by_hash_prefix(target);
+        if !by_hash.is_empty() {
+            return self.choose_node(target, &t!("ambiguous-hash-prefix"), by_hash, rl);
+        }
 
-        Ok(Some(AddResult {
-            title: node_metadata.title,
-            version: node_metadata.version,
-            hash: metadata.sha256,
-            newly_added: true,
-        }))
+        let matches = self.storage.find_nodes_by_title(target);
+        if matches.is_empty() {
+            self.report_rom_not_found(&t!("rom-not-found"), target);
+            return None;
+        }
+        self.choose_node(target, &t!("ambiguous-title"), matches, rl)
     }
 
-    fn cmd_add(
-        &mut self,
-        file: &Path,
+    /// Resolve a build target like [`Self::resolve_node`], falling back to a
+    /// fuzzy match on "Title [version]" (see [`picker::fuzzy_score`]) when no
+    /// title contains `target`. The best-scoring ROM wins; ROMs tied for best
+    /// are offered as a choice.
+    fn resolve_target(
+        &self,
+        target: &str,
         rl: &mut Editor<DromosHelper, DefaultHistory>,
-    ) -> Result<()> {
-        let result = match self.ensure_rom_added(file, rl)? {
-            Some(r) => r,
-            None => return Ok(()), // File not found, error already printed
-        };
-
-        if !result.newly_added {
-            let display_title = format_display_title(&result.title, result.version.as_deref());
-            println!(
-                "{} {} ({})",
-                theme::info("ROM already exists:"),
-                display_title,
-                theme::styled_hash(&format_hash(&result.hash)[..16])
-            );
-            return Ok(());
+    ) -> Option<&RomNode> {
+        let by_hash = self.storage.find_nodes_by_hash_prefix(target);
+        if !by_hash.is_empty() {
+            return self.choose_node(target, &t!("ambiguous-hash-prefix"), by_hash, rl);
         }
 
-        // Update last added
-        self.last_added = Some(LastAdded {
-            hash: result.hash,
-            title: result.title,
-            version: result.version,
-        });
+        let mut matches = self.storage.find_nodes_by_title(target);
+        if matches.is_empty() {
+            let (nodes, _) = self.storage.list();
+            let scored: Vec<(u32, &RomNode)> = nodes
+                .into_iter()
+                .filter_map(|n| {
+                    let title = format_display_title(&n.title, n.version.as_deref());
+                    picker::fuzzy_score(target, &title).map(|score| (score, n))
+                })
+                .collect();
+            let best = scored.iter().map(|(score, _)| *score).max();
+            matches = scored
+                .into_iter()
+                .filter(|(score, _)| Some(*score) == best)
+                .map(|(_, n)| n)
+                .collect();
+        }
+        if matches.is_empty() {
+            self.report_rom_not_found(&t!("target-rom-not-found"), target);
+            return None;
+        }
+        self.choose_node(target, &t!("ambiguous-title"), matches, rl)
+    }
 
-        Ok(())
+    /// Resolve a hash prefix to a single node, reporting `not_found` (e.g.
+    /// "ROM not found:") if nothing matches and asking the user to choose
+    /// when several ROMs share the prefix.
+    fn node_by_hash_prefix(
+        &self,
+        prefix: &str,
+        not_found: &str,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Option<&RomNode> {
+        let matches = self.storage.find_nodes_by_hash_prefix(prefix);
+        if matches.is_empty() {
+            self.report_rom_not_found(not_found, prefix);
+            return None;
+        }
+        self.choose_node(prefix, &t!("ambiguous-hash-prefix"), matches, rl)
     }
 
-    fn cmd_build(
+    /// Pick one of `matches` for `target`. A single match is returned as-is.
+    /// Several matches open a menu when running interactively; otherwise
+    /// the ambiguity is reported under `label` with the candidates listed.
+    fn choose_node<'a>(
         &self,
-        source: &Path,
         target: &str,
+        label: &str,
+        matches: Vec<&'a RomNode>,
         rl: &mut Editor<DromosHelper, DefaultHistory>,
-    ) -> Result<()> {
-        // Validate source exists
-        if !source.exists() {
-            eprintln!("{} {}", theme::error("File not found:"), source.display());
-            return Ok(());
+    ) -> Option<&'a RomNode> {
+        if let [node] = matches.as_slice() {
+            return Some(node);
         }
 
-        // Find target node
-        let target_node = match self.storage.find_node_by_hash_prefix(target) {
-            Some(n) => n,
-            None => {
-                eprintln!("{} {}", theme::error("Target ROM not found:"), target);
-                return Ok(());
-            }
-        };
-        let target_hash = target_node.sha256;
-        let target_title = target_node.title.clone();
-        let target_version = target_node.version.clone();
-        let target_type = target_node.rom_type;
+        if self.json() || !io::stdin().is_terminal() {
+            let candidates: Vec<String> = matches
+                .iter()
+                .map(|n| {
+                    format!(
+                        "{} ({})",
+                        format_display_title(&n.title, n.version.as_deref()),
+                        &format_hash(&n.sha256)[..16]
+                    )
+                })
+                .collect();
+            let error = DromosError::RomNotFoundAmbiguous {
+                query: target.to_string(),
+                candidates,
+            };
+            self.report_failure(label, &error.to_string(), &error);
+            return None;
+        }
 
-        // Build the ROM
-        let display_title = format_display_title(&target_title, target_version.as_deref());
-        println!("{} {}...", theme::info("Building"), display_title);
-        let result = match self.storage.build_rom(source, &target_hash) {
-            Ok(r) => r,
-            Err(e) => {
-                eprintln!("{} {}", theme::error("Build failed:"), e);
-                return Ok(());
-            }
+        outln!(
+            "{} {}",
+            theme::warning(label),
+            t!("matches-roms", target = target, count = matches.len())
+        );
+        let favorites = self.storage.favorites().unwrap_or_default();
+        let choices: Vec<NodeCompletion> = matches
+            .iter()
+            .map(|node| NodeCompletion {
+                favorite: favorites.contains(&node.sha256),
+                ..node_completion(node)
+            })
+            .collect();
+        let Some(hash) = picker::pick(&choices, rl) else {
+            self.report_cancelled();
+            return None;
         };
-        println!("{} {} diff(s)", theme::info("Applied"), result.steps);
+        matches.into_iter().find(|n| format_hash(&n.sha256) == hash)
+    }
 
-        // Prompt for output filename
-        let default_name = sanitize_filename(&target_title);
-        let filename = prompt_with_initial(rl, "Output filename", &default_name)?;
+    /// Use `target` if given; otherwise let the user pick a ROM with the
+    /// fuzzy picker. Reports an error or cancellation and returns None when
+    /// nothing is chosen, including when there is no terminal to pick from.
+    fn target_or_pick(
+        &self,
+        target: Option<String>,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Option<String> {
+        if target.is_some() {
+            return target;
+        }
+        if self.json() || !io::stdin().is_terminal() {
+            self.report_error(
+                ExitStatus::Usage,
+                &t!("missing-argument"),
+                &t!("hash-or-title-required"),
+            );
+            return None;
+        }
 
-        // Ensure correct extension
-        let filename = ensure_extension(&filename, target_type);
-        let output_path = Path::new(&filename);
+        let nodes = self.completion_nodes();
+        if nodes.is_empty() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("rom-not-found"),
+                &t!("library-empty"),
+            );
+            return None;
+        }
+        let picked = picker::pick(&nodes, rl);
+        if picked.is_none() {
+            self.report_cancelled();
+        }
+        picked
+    }
 
-        // Reconstruct with header for NES files
-        let final_bytes = if target_type == RomType::Nes {
-            if let Some(ref raw_header) = result.target_row.source_file_header {
-                reconstruct_nes_file_raw(raw_header, &result.bytes)
-            } else {
-                eprintln!(
-                    "{} No header metadata for NES file, writing raw bytes",
-                    theme::warning("Warning:")
-                );
-                result.bytes
-            }
-        } else {
-            result.bytes
-        };
+    /// The shell prompt, rendered from the configured template.
+    pub fn prompt(&self) -> String {
+        let (node_count, _) = self.storage.counts();
+        render_prompt(
+            &self.user_config.prompt,
+            node_count,
+            &self.storage.config().db_path.display().to_string(),
+        )
+    }
 
-        // Write to disk
-        std::fs::write(output_path, &final_bytes)?;
-        println!(
-            "{} {} bytes to {}",
-            theme::success("Wrote"),
-            final_bytes.len(),
-            output_path.display()
-        );
+    /// Library ROMs for hash/title tab completion, with favorites marked.
+    pub fn completion_nodes(&self) -> Vec<NodeCompletion> {
+        let favorites = self.storage.favorites().unwrap_or_default();
+        let (nodes, _) = self.storage.list();
+        nodes
+            .into_iter()
+            .map(|node| NodeCompletion {
+                favorite: favorites.contains(&node.sha256),
+                ..node_completion(node)
+            })
+            .collect()
+    }
 
-        Ok(())
+    /// Look up a node in the graph and convert it for JSON output.
+    fn node_json(&self, sha256: &[u8; 32]) -> Option<NodeJson> {
+        self.storage.get_node_by_hash(sha256).map(NodeJson::from)
     }
 
-    fn cmd_link(
+    /// Parse and run one line of input, expanding aliases and macros and
+    /// honoring `>`, `>>`, and `|` redirection. Returns false if quit was requested.
+    pub fn execute_line(
         &mut self,
-        files: &[std::path::PathBuf],
+        line: &str,
         rl: &mut Editor<DromosHelper, DefaultHistory>,
-    ) -> Result<()> {
-        match files.len() {
-            1 => self.link_to_last(&files[0], rl),
-            2 => self.link_two_files(&files[0], &files[1], rl),
-            _ => {
-                eprintln!("{}", theme::error("Usage: link <file1> [file2]"));
-                Ok(())
-            }
-        }
+    ) -> Result<bool> {
+        self.run_line(line, None, rl)
     }
 
-    fn link_to_last(
+    fn run_line(
         &mut self,
-        file: &Path,
+        line: &str,
+        location: Option<&str>,
         rl: &mut Editor<DromosHelper, DefaultHistory>,
-    ) -> Result<()> {
-        let last = match &self.last_added {
-            Some(last) => last.clone(),
-            None => {
-                eprintln!(
-                    "{}",
-                    theme::error("No previous ROM to link to. Use 'link <file1> <file2>' instead.")
-                );
-                return Ok(());
+    ) -> Result<bool> {
+        if let Some(shell_line) = line.trim_start().strip_prefix('!') {
+            if self.rpc {
+                self.report_stdin_in_use(location, &t!("rpc-shell-commands"));
+            } else if shell_line.trim().is_empty() {
+                self.report_usage(location, &format!("{} !<shell command>", t!("usage")));
+            } else {
+                self.run_shell(shell_line)?;
             }
+            return Ok(true);
+        }
+
+        // SQL uses `>` and `||` itself, so it is never split for redirection
+        if sql_query(line).is_some() {
+            return match Command::parse(line) {
+                Some(Ok(cmd)) => self.execute(cmd, rl),
+                Some(Err(e)) => {
+                    self.report_usage(location, &e);
+                    Ok(true)
+                }
+                None => Ok(true),
+            };
+        }
+
+        match split_redirection(line.trim()) {
+            Err(e) => {
+                self.report_usage(location, &e);
+                Ok(true)
+            }
+            Ok((command, None)) => self.execute_args(&parse_quoted_args(command), location, rl),
+            Ok((_, Some(Redirect::Pipe(_)))) if self.rpc => {
+                self.report_stdin_in_use(location, &t!("rpc-pipes"));
+                Ok(true)
+            }
+            Ok((command, Some(redirect))) => {
+                let args = parse_quoted_args(command);
+                self.run_redirected(&redirect, |repl| repl.execute_args(&args, location, rl))
+            }
+        }
+    }
+
+    /// Run a `!` line in the platform shell, attached to the terminal. Any
+    /// nonzero exit counts as a failure, since shell exit codes don't share
+    /// dromos's meanings.
+    fn run_shell(&self, shell_line: &str) -> Result<()> {
+        let status = shell_command(shell_line).status()?;
+        self.status.set(if status.success() {
+            ExitStatus::Success
+        } else {
+            ExitStatus::Failure
+        });
+        Ok(())
+    }
+
+    /// Run `run` in this session with its output sent to a file or a shell
+    /// pipeline, uncolored. Prompts are shown on stderr meanwhile.
+    fn run_redirected(
+        &mut self,
+        redirect: &Redirect,
+        run: impl FnOnce(&mut Self) -> Result<bool>,
+    ) -> Result<bool> {
+        let (to, shell): (Box<dyn Write>, _) = match redirect {
+            Redirect::File { path, append } => {
+                let file = match std::fs::OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .append(*append)
+                    .truncate(!*append)
+                    .open(path)
+                {
+                    Ok(f) => f,
+                    Err(e) => {
+                        self.report_error(
+                            ExitStatus::Io,
+                            &t!("cannot-write"),
+                            &format!("{} ({})", path.display(), e),
+                        );
+                        return Ok(true);
+                    }
+                };
+                (Box::new(io::BufWriter::new(file)), None)
+            }
+            Redirect::Pipe(pipeline) => {
+                let mut shell = shell_command(pipeline).stdin(Stdio::piped()).spawn()?;
+                let shell_stdin = shell.stdin.take().expect("shell stdin is piped");
+                (Box::new(shell_stdin), Some(shell))
+            }
+        };
+
+        let colors = theme::colors_enabled();
+        theme::set_colors_enabled(false);
+        // The pipe to the shell is closed when this returns, so the shell
+        // sees the end of its input before it's waited on
+        let result = output::redirect_stdout(to, || run(self));
+        theme::set_colors_enabled(colors);
+        if let Some(mut shell) = shell {
+            shell.wait()?;
+        }
+        result
+    }
+
+    /// Run already-split arguments, expanding aliases and macros. A macro's
+    /// commands stop at the first one that fails. Parse errors are reported
+    /// (prefixed with `location`, if given) and set the usage status.
+    pub fn execute_args(
+        &mut self,
+        args: &[String],
+        location: Option<&str>,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<bool> {
+        let commands = match expand_args(args, &self.user_config) {
+            Ok(commands) => commands,
+            Err(e) => {
+                self.report_usage(location, &e);
+                return Ok(true);
+            }
+        };
+
+        for parts in commands {
+            let cmd = match Command::from_args(&parts) {
+                None => continue,
+                Some(Err(e)) => {
+                    self.report_usage(location, &e);
+                    return Ok(true);
+                }
+                Some(Ok(cmd)) => cmd,
+            };
+            let result = if self.pages(&cmd) {
+                self.run_redirected(&Redirect::Pipe(pager_command()), |repl| {
+                    repl.execute(cmd, rl)
+                })
+            } else {
+                self.execute(cmd, rl)
+            };
+            match result {
+                Ok(true) => {}
+                Ok(false) => return Ok(false),
+                // Ctrl+C during a long operation: stop the rest of the line too
+                Err(DromosError::Cancelled) => self.report_cancelled(),
+                Err(e) => self.report_failure(location.unwrap_or(&t!("error")), &e.to_string(), &e),
+            }
+            if self.last_status() != ExitStatus::Success {
+                break;
+            }
+        }
+        Ok(true)
+    }
+
+    /// True if `cmd` should run through the pager: `set pager on`, output
+    /// to a terminal, and a listing command that never prompts.
+    fn pages(&self, cmd: &Command) -> bool {
+        self.pager
+            && self.source_depth == 0
+            && io::stdout().is_terminal()
+            && !output::is_redirected()
+            && matches!(
+                cmd,
+                Command::List { .. }
+                    | Command::Search { .. }
+                    | Command::Sql { .. }
+                    | Command::Recent { .. }
+                    | Command::Status
+                    | Command::Help { .. }
+            )
+    }
+
+    /// Refuse something that would use stdin or stdout, which carry `--rpc`
+    /// requests and replies.
+    fn report_stdin_in_use(&self, location: Option<&str>, what: &str) {
+        let message = t!("not-with-rpc", what = what);
+        match location {
+            Some(location) => self.report_error(ExitStatus::Usage, location, &message),
+            None => self.report_error(ExitStatus::Usage, &t!("not-available"), &message),
+        }
+    }
+
+    fn report_usage(&self, location: Option<&str>, message: &str) {
+        match location {
+            Some(location) => self.report_error(ExitStatus::Usage, location, message),
+            None => {
+                self.status.set(ExitStatus::Usage);
+                if self.json() {
+                    output::print_json(&json!({ "error": message }));
+                } else {
+                    eprintln!("{}", theme::error(message));
+                }
+            }
+        }
+    }
+
+    pub fn execute(
+        &mut self,
+        cmd: Command,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<bool> {
+        // Scripts report timings for each command they run, not as a whole
+        let timed = timings::enabled() && !matches!(cmd, Command::Source { .. });
+        if !timed {
+            return self.dispatch(cmd, rl);
+        }
+
+        timings::reset();
+        let start = Instant::now();
+        let result = self.dispatch(cmd, rl);
+        print_timings(start.elapsed());
+        result
+    }
+
+    fn dispatch(
+        &mut self,
+        cmd: Command,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<bool> {
+        self.status.set(ExitStatus::Success);
+        output::set_prompts_on_stderr(self.json());
+        if self.rpc {
+            let reader = match &cmd {
+                Command::Add {
+                    from_stdin: true, ..
+                }
+                | Command::Hash {
+                    from_stdin: true, ..
+                } => Some(t!("rpc-paths-from-stdin")),
+                Command::Watch { .. } => Some("watch".to_string()),
+                Command::Serve { .. } => Some("serve".to_string()),
+                Command::Share { .. } => Some("share".to_string()),
+                _ => None,
+            };
+            if let Some(reader) = reader {
+                self.report_stdin_in_use(None, &reader);
+                return Ok(true);
+            }
+        }
+        match cmd {
+            Command::Quit => return Ok(false),
+            Command::Help { topic } => self.print_help(topic.as_deref()),
+            Command::Hash { files, from_stdin } => self.cmd_hash(&files, from_stdin)?,
+            Command::Check { file } => self.cmd_check(&file)?,
+            Command::ValidateAll { sources } => self.cmd_validate_all(&sources)?,
+            Command::CheckDiffs => self.cmd_check_diffs()?,
+            Command::Compare { a, b } => self.cmd_compare(&a, &b)?,
+            Command::Inspect { a, b, sources, all } => {
+                self.cmd_inspect(&a, &b, &sources, all, rl)?
+            }
+            Command::Banks { a, b, sources } => self.cmd_banks(&a, &b, &sources, rl)?,
+            Command::DatImport { file } => self.cmd_dat_import(&file)?,
+            Command::DatExport {
+                file,
+                component,
+                name,
+                query,
+            } => self.cmd_dat_export(&file, component.as_deref(), name, &query, rl)?,
+            Command::Add { files, from_stdin } => self.cmd_add(&files, from_stdin, rl)?,
+            Command::Attach {
+                file,
+                target,
+                linked,
+            } => self.cmd_attach(&file, &target, linked.as_deref(), rl)?,
+            Command::Detach {
+                name,
+                target,
+                linked,
+            } => self.cmd_detach(&name, &target, linked.as_deref(), rl)?,
+            Command::CollectionCreate { name } => self.cmd_collection_create(&name)?,
+            Command::CollectionDelete { name } => self.cmd_collection_delete(&name)?,
+            Command::CollectionAdd { name, targets } => {
+                self.cmd_collection_change(&name, &targets, true, rl)?
+            }
+            Command::CollectionRemove { name, targets } => {
+                self.cmd_collection_change(&name, &targets, false, rl)?
+            }
+            Command::CollectionList { name } => self.cmd_collection_list(name.as_deref())?,
+            Command::BuildAll {
+                target,
+                query,
+                dir,
+                sources,
+            } => self.cmd_build_all(target.as_deref(), query.as_deref(), &dir, &sources, rl)?,
+            Command::Build {
+                source,
+                target,
+                output,
+                overwrite,
+            } => {
+                if let Some(target) = self.target_or_pick(target, rl) {
+                    self.cmd_build(&source, &target, output.as_deref(), overwrite, rl)?
+                }
+            }
+            Command::Edit { target, edits } => {
+                if let Some(target) = self.target_or_pick(target, rl) {
+                    self.cmd_edit(&target, &edits, rl)?
+                }
+            }
+            Command::EditCsv { file, dry_run } => self.cmd_edit_csv(&file, dry_run)?,
+            Command::BulkEdit {
+                query,
+                edits,
+                all,
+                dry_run,
+            } => self.cmd_bulk_edit(&query, &edits, all, dry_run)?,
+            Command::Wish { hash, name, edits } => self.cmd_wish(&hash, &name, &edits)?,
+            Command::WishDat { query, dry_run } => self.cmd_wish_dat(&query, dry_run)?,
+            Command::Export {
+                hash_prefix,
+                output,
+                html,
+                git,
+                plugin,
+                collection,
+            } => {
+                let output = match &self.export_dir {
+                    Some(dir) if output.is_relative() => dir.join(output),
+                    _ => output,
+                };
+                let filter = NodeFilter {
+                    collections: collection.into_iter().collect(),
+                    ..Default::default()
+                };
+                if let Some(plugin) = plugin {
+                    self.cmd_export_plugin(hash_prefix.as_deref(), &output, &plugin, &filter, rl)?
+                } else if html {
+                    self.cmd_export_html(hash_prefix.as_deref(), &output, &filter, rl)?
+                } else {
+                    let git = git || self.export_git;
+                    self.cmd_export(hash_prefix.as_deref(), &output, git, &filter, rl)?
+                }
+            }
+            Command::Import {
+                input,
+                component,
+                tags,
+            } => match input.to_str().filter(|s| s.starts_with("http://")) {
+                Some(url) => self.cmd_import_remote(url, component, tags, rl)?,
+                None => self.cmd_import(&input, rl)?,
+            },
+            Command::Info { target } => self.cmd_info(&target, rl)?,
+            Command::Link { files, note } => self.cmd_link(&files, note.as_deref(), rl)?,
+            Command::Links { target } => {
+                if let Some(target) = self.target_or_pick(target, rl) {
+                    self.cmd_links(&target, rl)?
+                }
+            }
+            Command::List {
+                columns,
+                sort,
+                reverse,
+                query,
+                all,
+            } => self.cmd_list(columns, sort, reverse, &query, all)?,
+            Command::Open { target } => {
+                if let Some(target) = self.target_or_pick(target, rl) {
+                    self.cmd_open(&target, rl)?
+                }
+            }
+            Command::Fav { target } => {
+                if let Some(target) = self.target_or_pick(target, rl) {
+                    self.cmd_fav(&target, rl)?
+                }
+            }
+            Command::Archive { target, archived } => {
+                if let Some(target) = self.target_or_pick(target, rl) {
+                    self.cmd_archive(&target, archived, rl)?
+                }
+            }
+            Command::Play { source, target } => {
+                if let Some(target) = self.target_or_pick(target, rl) {
+                    self.cmd_play(&source, &target, rl)?
+                }
+            }
+            Command::Rm { mut targets } => {
+                if targets.is_empty()
+                    && let Some(target) = self.target_or_pick(None, rl)
+                {
+                    targets.push(target);
+                }
+                if !targets.is_empty() {
+                    self.cmd_rm(&targets, rl)?
+                }
+            }
+            Command::Scan {
+                dir,
+                prompt_ambiguous,
+            } => self.cmd_scan(&dir, prompt_ambiguous, rl)?,
+            Command::Locate { dir, record } => self.cmd_locate(&dir, record)?,
+            Command::Checksums { dir, format } => self.cmd_checksums(&dir, format)?,
+            Command::Dedupe { query } => self.cmd_dedupe(&query)?,
+            Command::Merge { keep, dup, sources } => self.cmd_merge(&keep, &dup, &sources, rl)?,
+            Command::SdCard {
+                dir,
+                sources,
+                layout,
+                query,
+            } => self.cmd_sdcard(&dir, &sources, layout, &query)?,
+            Command::Source {
+                file,
+                continue_on_error,
+            } => self.cmd_source(&file, continue_on_error, rl)?,
+            Command::Status => self.cmd_status()?,
+            Command::Version => self.cmd_version(),
+            Command::Search { query, regex, all } => self.cmd_search(&query, regex, all)?,
+            Command::Recent { count } => self.cmd_recent(count)?,
+            Command::Report {
+                file,
+                format,
+                columns,
+                sort,
+                reverse,
+                query,
+            } => self.cmd_report(&file, format, columns, sort, reverse, &query)?,
+            Command::Sql { query } => self.cmd_sql(&query),
+            Command::Set {
+                option,
+                value,
+                save,
+            } => self.cmd_set(option.as_deref(), value.as_deref(), save),
+            Command::Watch { dir, link_base } => self.cmd_watch(&dir, link_base.as_deref())?,
+            Command::Serve {
+                listen,
+                stdio,
+                grpc,
+            } => {
+                if grpc {
+                    self.cmd_serve_grpc(&listen)?
+                } else {
+                    self.cmd_serve(&listen, stdio)?
+                }
+            }
+            Command::Sync { remote, dry_run } => self.cmd_sync(&remote, dry_run)?,
+            Command::Share {
+                component,
+                tags,
+                name,
+                listen,
+            } => self.cmd_share(component, tags, name, &listen)?,
+            Command::Fetch { name } => self.cmd_fetch(name.as_deref(), rl)?,
+        }
+        Ok(true)
+    }
+
+    fn print_help(&self, topic: Option<&str>) {
+        if let Some(topic) = topic {
+            match catalog::find(topic) {
+                Some(command) => self.print_command_help(command),
+                None => self.report_error(ExitStatus::Usage, &t!("unknown-command"), topic),
+            }
+            return;
+        }
+
+        if self.json() {
+            let commands: Vec<_> = catalog::COMMANDS
+                .iter()
+                .map(|c| json!({ "usage": c.usage(), "description": c.summary }))
+                .collect();
+            output::print_json(&json!({ "commands": commands }));
+            return;
+        }
+
+        let usages: Vec<String> = catalog::COMMANDS.iter().map(|c| c.usage()).collect();
+        let width = usages.iter().map(String::len).max().unwrap_or(0) + 2;
+        outln!("{}", theme::header(&t!("help-commands")));
+        for (usage, command) in usages.iter().zip(catalog::COMMANDS) {
+            outln!("  {:<width$}{}", usage, command.summary);
+        }
+        outln!();
+        outln!("{}", t!("help-details"));
+    }
+
+    fn print_command_help(&self, command: &catalog::CommandInfo) {
+        let usage = format!("{} {}", command.name(), command.args);
+        let usage = usage.trim_end();
+        if self.json() {
+            output::print_json(&json!({
+                "name": command.name(),
+                "aliases": &command.names[1..],
+                "usage": usage,
+                "description": command.summary,
+                "details": command.details,
+                "examples": command.examples,
+                "related": command.related,
+            }));
+            return;
+        }
+
+        outln!("{} {}", theme::header(&t!("usage")), usage);
+        if command.names.len() > 1 {
+            outln!(
+                "{} {}",
+                theme::header(&t!("help-aliases")),
+                command.names[1..].join(", ")
+            );
+        }
+        outln!();
+        outln!("{}", command.summary);
+        outln!("{}", command.details);
+        if !command.examples.is_empty() {
+            outln!();
+            outln!("{}", theme::header(&t!("help-examples")));
+            for example in command.examples {
+                outln!("  {}", example);
+            }
+        }
+        if !command.related.is_empty() {
+            outln!();
+            outln!(
+                "{} {}",
+                theme::header(&t!("help-related")),
+                command.related.join(", ")
+            );
+        }
+    }
+
+    fn cmd_set(&mut self, option: Option<&str>, value: Option<&str>, save: bool) {
+        let Some(option) = option else {
+            self.print_settings();
+            return;
+        };
+        let option = option.to_lowercase();
+        let Some(&(name, values)) = SET_OPTIONS.iter().find(|(name, _)| *name == option) else {
+            self.report_error(ExitStatus::Usage, &t!("unknown-option"), &option);
+            return;
+        };
+        let Some(value) = value else {
+            self.report_error(
+                ExitStatus::Usage,
+                &t!("usage"),
+                &format!("set {} {}", name, values),
+            );
+            return;
+        };
+
+        let switch = match value.to_lowercase().as_str() {
+            "on" => Some(true),
+            "off" => Some(false),
+            _ => None,
+        };
+        let applied = match (name, switch) {
+            ("output", _) => value
+                .parse::<OutputFormat>()
+                .map(|format| self.output = format),
+            ("confirm", Some(on)) => {
+                self.assume_yes = !on;
+                Ok(())
+            }
+            ("color", Some(on)) => {
+                theme::set_colors_enabled(on);
+                Ok(())
+            }
+            ("pager", Some(on)) => {
+                self.pager = on;
+                Ok(())
+            }
+            ("export_dir", _) if value.eq_ignore_ascii_case("none") => {
+                self.export_dir = None;
+                Ok(())
+            }
+            ("export_dir", _) => std::path::absolute(value)
+                .map(|dir| self.export_dir = Some(dir))
+                .map_err(|e| e.to_string()),
+            ("export_git", Some(on)) => {
+                self.export_git = on;
+                Ok(())
+            }
+            ("strict", Some(on)) => {
+                self.storage.set_strict(on);
+                Ok(())
+            }
+            ("plain", Some(on)) => {
+                theme::set_plain(on);
+                Ok(())
+            }
+            _ => Err(t!("expected-values", values = values)),
+        };
+        if let Err(e) = applied {
+            self.report_error(ExitStatus::Usage, &t!("invalid-value"), &e);
+            return;
+        }
+
+        if save {
+            self.save_setting(name);
+        }
+    }
+
+    /// Current value of a `set` option, as `set` shows and saves it.
+    fn setting_value(&self, name: &str) -> String {
+        let switch = |on: bool| if on { "on" } else { "off" }.to_string();
+        match name {
+            "output" => self.output.to_string(),
+            "confirm" => switch(!self.assume_yes),
+            "color" => switch(theme::colors_enabled()),
+            "pager" => switch(self.pager),
+            "export_dir" => self
+                .export_dir
+                .as_ref()
+                .map_or("none".to_string(), |dir| dir.display().to_string()),
+            "export_git" => switch(self.export_git),
+            "strict" => switch(self.storage.strict()),
+            "plain" => switch(theme::plain()),
+            _ => unreachable!("not a set option: {}", name),
+        }
+    }
+
+    fn print_settings(&self) {
+        if self.json() {
+            let settings: serde_json::Map<_, _> = SET_OPTIONS
+                .iter()
+                .map(|(name, _)| (name.to_string(), json!(self.setting_value(name))))
+                .collect();
+            output::print_json(&serde_json::Value::Object(settings));
+            return;
+        }
+        for (name, _) in SET_OPTIONS {
+            outln!("{:<12}{}", name, theme::meta(&self.setting_value(name)));
+        }
+    }
+
+    /// Write the current value of a `set` option to the config file.
+    fn save_setting(&self, name: &str) {
+        let Some(path) = config_file_path() else {
+            self.report_error(
+                ExitStatus::Io,
+                &t!("cannot-save"),
+                &t!("no-config-directory"),
+            );
+            return;
+        };
+        let value = self.setting_value(name);
+        // Quote so folders with leading or trailing spaces survive
+        let saved = if name == "export_dir" && value != "none" {
+            format!("\"{}\"", value)
+        } else {
+            value
+        };
+        match save_setting(&path, name, &saved) {
+            Ok(()) => {
+                if self.narrate() {
+                    outln!(
+                        "{} {}",
+                        theme::success(&t!("saved")),
+                        t!(
+                            "setting-saved-in",
+                            name = name,
+                            value = saved,
+                            path = path.display(),
+                        )
+                    );
+                }
+            }
+            Err(e) => self.report_error(
+                ExitStatus::Io,
+                &t!("cannot-write"),
+                &format!("{} ({})", path.display(), e),
+            ),
+        }
+    }
+
+    /// Expand glob patterns in file arguments, then append any paths read
+    /// from stdin (one per line, taken literally). Reports a pattern that
+    /// matches nothing and returns None.
+    fn expand_files(&self, args: &[PathBuf], from_stdin: bool) -> Result<Option<Vec<PathBuf>>> {
+        let Some(mut files) = self.expand_patterns(args) else {
+            return Ok(None);
+        };
+        if from_stdin {
+            for line in io::stdin().lines() {
+                let line = line?;
+                let trimmed = line.trim();
+                if !trimmed.is_empty() {
+                    files.push(PathBuf::from(trimmed));
+                }
+            }
+        }
+        Ok(Some(self.convert_with_plugins(files)))
+    }
+
+    /// The folder converted files go in, made the first time it's needed.
+    fn converted_dir(&self) -> io::Result<&Path> {
+        if self.converted_dir.get().is_none() {
+            let dir = tempfile::Builder::new()
+                .prefix("dromos-converted-")
+                .tempdir()?;
+            let _ = self.converted_dir.set(dir);
+        }
+        Ok(self.converted_dir.get().expect("set above").path())
+    }
+
+    /// Replace files a plugin converts with the NES ROMs it converts them
+    /// to, kept in a temporary folder. Files that fail to convert are
+    /// reported and left out.
+    fn convert_with_plugins(&self, files: Vec<PathBuf>) -> Vec<PathBuf> {
+        if self.plugins.is_empty() {
+            return files;
+        }
+        files
+            .into_iter()
+            .filter_map(|file| {
+                if is_rom_file(&file) {
+                    return Some(file);
+                }
+                let converted = plugin::find_converter(&self.plugins, &file).and_then(|found| {
+                    found
+                        .map(|p| {
+                            p.convert(&file, self.converted_dir()?)
+                                .map(|path| (p, path))
+                        })
+                        .transpose()
+                });
+                match converted {
+                    Ok(Some((plugin, path))) => {
+                        if self.narrate() {
+                            outln!(
+                                "{} {} -> {} ({})",
+                                theme::info(&t!("converted")),
+                                file.display(),
+                                path.display(),
+                                plugin.name()
+                            );
+                        }
+                        Some(path)
+                    }
+                    Ok(None) => Some(file),
+                    Err(e) => {
+                        self.report_failure(
+                            &t!("failed-to-convert"),
+                            &format!("{} ({})", file.display(), e),
+                            &e,
+                        );
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn expand_patterns(&self, args: &[PathBuf]) -> Option<Vec<PathBuf>> {
+        match expand_file_patterns(args) {
+            Ok(files) => Some(files),
+            Err(DromosError::FileNotFound { path }) => {
+                self.report_error(
+                    ExitStatus::NotFound,
+                    &t!("no-files-match"),
+                    &path.display().to_string(),
+                );
+                None
+            }
+            Err(e) => {
+                self.report_error(
+                    ExitStatus::Usage,
+                    &t!("invalid-file-argument"),
+                    &e.to_string(),
+                );
+                None
+            }
+        }
+    }
+
+    fn cmd_hash(&self, args: &[PathBuf], from_stdin: bool) -> Result<()> {
+        let Some(files) = self.expand_files(args, from_stdin)? else {
+            return Ok(());
+        };
+        if let [file] = files.as_slice()
+            && !from_stdin
+        {
+            return self.hash_one(file);
+        }
+
+        let mut results = Vec::new();
+        for (i, file) in files.iter().enumerate() {
+            let metadata = match hash_rom_file(file) {
+                Ok(m) => m,
+                Err(e) => {
+                    self.report_failure(
+                        &t!("failed-to-hash"),
+                        &format!("{} ({})", file.display(), e),
+                        &e,
+                    );
+                    continue;
+                }
+            };
+            if self.json() {
+                results.push(json!({
+                    "path": file.display().to_string(),
+                    "file": RomFileJson::from(&metadata),
+                }));
+                continue;
+            }
+            if i > 0 {
+                outln!();
+            }
+            outln!("{} {}", t!("field-file"), file.display());
+            print_hash_details(&metadata);
+        }
+
+        if self.json() {
+            output::print_json(&results);
+        }
+        Ok(())
+    }
+
+    fn hash_one(&self, file: &Path) -> Result<()> {
+        let metadata = hash_rom_file(file)?;
+
+        if self.json() {
+            output::print_json(&RomFileJson::from(&metadata));
+            return Ok(());
+        }
+
+        print_hash_details(&metadata);
+        Ok(())
+    }
+
+    fn cmd_dat_import(&mut self, file: &Path) -> Result<()> {
+        if !file.is_file() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &file.display().to_string(),
+            );
+            return Ok(());
+        }
+
+        let import = match self.storage.import_dat(file) {
+            Ok(import) => import,
+            Err(e) => {
+                self.report_failure(&t!("dat-import-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+
+        if self.json() {
+            output::print_json(&json!({
+                "dat_name": import.dat_name,
+                "version": import.version,
+                "entries": import.entries,
+                "verified": import.verified,
+            }));
+            return Ok(());
+        }
+        if self.quiet {
+            return Ok(());
+        }
+
+        let version = import
+            .version
+            .map(|v| format!(" ({})", v))
+            .unwrap_or_default();
+        outln!(
+            "{} {}{}: {}",
+            theme::success(&t!("imported-dat")),
+            import.dat_name,
+            version,
+            counted!(import.entries, "entries-one", "entries-other"),
+        );
+        outln!(
+            "{} {}",
+            theme::info(&t!("verified")),
+            t!(
+                "roms-in-library",
+                roms = counted!(import.verified, "roms-one", "roms-other"),
+            ),
+        );
+        Ok(())
+    }
+
+    fn cmd_dat_export(
+        &self,
+        file: &Path,
+        component: Option<&str>,
+        name: Option<String>,
+        query: &str,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let filter = match NodeFilter::parse(query) {
+            Ok(filter) => filter,
+            Err(e) => {
+                self.report_error(ExitStatus::Usage, &t!("invalid-search"), &e);
+                return Ok(());
+            }
+        };
+        let component_hash = match component {
+            Some(prefix) => {
+                let Some(node) = self.node_by_hash_prefix(prefix, &t!("rom-not-found"), rl) else {
+                    return Ok(());
+                };
+                Some(node.sha256)
+            }
+            None => None,
+        };
+        // Name the DAT after its file unless told otherwise
+        let name = name.unwrap_or_else(|| {
+            file.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "dromos".to_string())
+        });
+
+        let dat = self
+            .storage
+            .plan_dat(&name, component_hash.as_ref(), &filter)?;
+        if dat.entries.is_empty() {
+            self.report_error(ExitStatus::NotFound, &t!("no-roms-match"), query);
+            return Ok(());
+        }
+        if file.exists() && !self.confirm(&t!("overwrite-file", path = file.display()))? {
+            self.report_cancelled();
+            return Ok(());
+        }
+        if let Err(e) = dat::write_dat(file, &dat) {
+            self.report_failure(&t!("dat-export-failed"), &e.to_string(), &e);
+            return Ok(());
+        }
+
+        // Nodes added before SHA-1s were kept can't be audited by most tools
+        let without_sha1 = dat.entries.iter().filter(|e| e.sha1.is_none()).count();
+        if self.json() {
+            output::print_json(&json!({
+                "file": file.display().to_string(),
+                "dat_name": dat.name,
+                "entries": dat.entries.len(),
+                "without_sha1": without_sha1,
+            }));
+            return Ok(());
+        }
+        if self.quiet {
+            return Ok(());
+        }
+
+        outln!(
+            "{} {}",
+            theme::success(&t!("exported-dat")),
+            t!(
+                "roms-to",
+                roms = counted!(dat.entries.len(), "roms-one", "roms-other"),
+                path = file.display(),
+            ),
+        );
+        if without_sha1 > 0 {
+            outln!(
+                "{} {}",
+                theme::warning(&t!("warning")),
+                counted!(without_sha1, "without-sha1-one", "without-sha1-other"),
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_check(&self, file: &Path) -> Result<()> {
+        // Check if file exists
+        if !file.exists() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &file.display().to_string(),
+            );
+            return Ok(());
+        }
+
+        // Hash the file and get metadata
+        let metadata = hash_rom_file(file)?;
+        let hash_str = format_hash(&metadata.sha256);
+        self.storage.record_filename(&metadata)?;
+
+        let wanted = self
+            .storage
+            .get_node_row_by_hash(&metadata.sha256)?
+            .is_some_and(|row| row.missing);
+        let header_problems = match metadata.nes_header {
+            Some(_) => nes_file_problems(file)?,
+            None => Vec::new(),
+        };
+        if self.json() {
+            let node = self.node_json(&metadata.sha256);
+            let header = match (&node, &metadata.source_file_header) {
+                _ if wanted => None,
+                (Some(_), Some(file_header)) => self
+                    .storage
+                    .get_node_row_by_hash(&metadata.sha256)?
+                    .map(|row| match &row.source_file_header {
+                        Some(stored) if stored == file_header => "matches",
+                        Some(_) => "differs",
+                        None => "unknown",
+                    }),
+                _ => None,
+            };
+            if node.is_none() {
+                self.status.set(ExitStatus::NotFound);
+            } else if header == Some("differs") {
+                self.status.set(ExitStatus::VerificationFailed);
+            }
+            output::print_json(&json!({
+                "file": RomFileJson::from(&metadata),
+                "found": node.is_some(),
+                "wanted": wanted,
+                "node": node,
+                "header": header,
+                "header_problems": header_problems
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+            }));
+            return Ok(());
+        }
+
+        // Print the hash
+        outln!("{} {}", t!("field-hash"), hash_str);
+        outln!("{} {}", t!("field-type"), metadata.rom_type);
+        for problem in &header_problems {
+            outln!(
+                "{} {}",
+                theme::warning(&t!("inconsistent")),
+                t!("header-problem", problem = problem)
+            );
+        }
+
+        // Look up in database
+        match self.storage.get_node_by_hash(&metadata.sha256) {
+            Some(node) => {
+                // Found in database - show title/version
+                let display_title = format_display_title(&node.title, node.version.as_deref());
+                outln!("{} {}", theme::success(&t!("found")), display_title);
+                if wanted {
+                    outln!(
+                        "{} {}",
+                        theme::warning(&t!("wanted")),
+                        t!("wanted-add-file")
+                    );
+                }
+
+                // Compare headers if file has one
+                if let Some(ref file_header) = metadata.source_file_header
+                    && !wanted
+                {
+                    // Get full NodeRow from database (has stored header)
+                    if let Ok(Some(node_row)) = self.storage.get_node_row_by_hash(&metadata.sha256)
+                    {
+                        match &node_row.source_file_header {
+                            Some(stored_header) if stored_header == file_header => {
+                                outln!("{} {}", t!("field-header"), t!("header-matches"));
+                            }
+                            Some(_) => {
+                                self.status.set(ExitStatus::VerificationFailed);
+                                outln!("{} {}", t!("field-header"), t!("header-differs"));
+                            }
+                            None => {
+                                outln!("{} {}", t!("field-header"), t!("header-not-stored"));
+                            }
+                        }
+                    }
+                }
+            }
+            None => {
+                self.status.set(ExitStatus::NotFound);
+                outln!("{} {}", t!("field-status"), t!("not-in-database"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ensure a ROM file is in the database, prompting for metadata if new.
+    /// Returns None if file doesn't exist (error already printed).
+    /// Returns AddResult with newly_added=false if ROM already exists.
+    /// Returns AddResult with newly_added=true if ROM was added.
+    fn ensure_rom_added(
+        &mut self,
+        file: &Path,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<Option<AddResult>> {
+        // Check if file exists
+        if !file.exists() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &file.display().to_string(),
+            );
+            return Ok(None);
+        }
+
+        // Hash the file
+        let metadata = hash_rom_file(file)?;
+
+        // Check if ROM already exists
+        if self.storage.node_exists(&metadata.sha256) {
+            let found = self.record_known_file(&metadata)?;
+            let node = self.storage.get_node_by_hash(&metadata.sha256).unwrap();
+            return Ok(Some(AddResult {
+                title: node.title.clone(),
+                version: node.version.clone(),
+                hash: metadata.sha256,
+                newly_added: false,
+                found,
+            }));
+        }
+
+        // ROM doesn't exist - refuse it before prompting if strict mode would
+        self.storage.check_strict(file)?;
+
+        // Prompt for metadata and add
+        let filename = file.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+        if self.narrate() {
+            outln!("{} {}", theme::info(&t!("adding-file")), filename);
+        }
+
+        let suggested = self.suggest_metadata(&metadata, name_from_filename(file));
+        let node_metadata = self.prompt_metadata(rl, &suggested)?;
+
+        // Add to database
+        let metadata = self.storage.add_node(file, &node_metadata)?;
+
+        if self.narrate() {
+            let display_title =
+                format_display_title(&node_metadata.title, node_metadata.version.as_deref());
+            outln!(
+                "{} {} ({})",
+                theme::success(&t!("added")),
+                display_title,
+                theme::styled_hash(&format_hash(&metadata.sha256)[..16])
+            );
+        }
+
+        Ok(Some(AddResult {
+            title: node_metadata.title,
+            version: node_metadata.version,
+            hash: metadata.sha256,
+            newly_added: true,
+            found: false,
+        }))
+    }
+
+    fn cmd_add(
+        &mut self,
+        args: &[PathBuf],
+        from_stdin: bool,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let Some(files) = self.expand_files(args, from_stdin)? else {
+            return Ok(());
+        };
+        if let [file] = files.as_slice()
+            && !from_stdin
+        {
+            return self.add_one(file, rl);
+        }
+
+        let mut added = 0;
+        let mut found = 0;
+        let mut existing = 0;
+        let mut failed = 0;
+        let mut first_failure = None;
+        let mut results = Vec::new();
+        for file in &files {
+            // stdin is busy supplying paths, so titles come from filenames
+            let added_result = if from_stdin {
+                self.add_with_derived_title(file)
+            } else {
+                self.ensure_rom_added(file, rl)
+            };
+            let result = match added_result {
+                Ok(Some(r)) => r,
+                Ok(None) => {
+                    // Missing file, error already reported
+                    failed += 1;
+                    first_failure.get_or_insert(self.last_status());
+                    continue;
+                }
+                Err(e) => {
+                    failed += 1;
+                    first_failure.get_or_insert(ExitStatus::from_error(&e));
+                    self.report_failure(
+                        &t!("failed-to-add"),
+                        &format!("{} ({})", file.display(), e),
+                        &e,
+                    );
+                    continue;
+                }
+            };
+
+            results.push(json!({
+                "path": file.display().to_string(),
+                "status": result.status(),
+                "node": self.node_json(&result.hash),
+            }));
+            if result.newly_added {
+                added += 1;
+                self.last_added = Some(LastAdded {
+                    hash: result.hash,
+                    title: result.title,
+                    version: result.version,
+                });
+            } else if result.found {
+                found += 1;
+            } else {
+                existing += 1;
+                if self.narrate() {
+                    let display_title =
+                        format_display_title(&result.title, result.version.as_deref());
+                    outln!(
+                        "{} {} ({})",
+                        theme::info(&t!("rom-already-exists")),
+                        display_title,
+                        theme::styled_hash(&format_hash(&result.hash)[..16])
+                    );
+                }
+            }
+        }
+
+        self.status
+            .set(first_failure.unwrap_or(ExitStatus::Success));
+        if self.json() {
+            output::print_json(&json!({
+                "results": results,
+                "added": added,
+                "found": found,
+                "existing": existing,
+                "failed": failed,
+            }));
+        } else if !self.quiet {
+            let summary = if found > 0 {
+                t!(
+                    "add-summary-found",
+                    added = added,
+                    found = found,
+                    existing = existing,
+                    failed = failed,
+                )
+            } else {
+                t!(
+                    "add-summary",
+                    added = added,
+                    existing = existing,
+                    failed = failed,
+                )
+            };
+            outln!("{} {}", theme::header(&t!("summary")), summary);
+        }
+        Ok(())
+    }
+
+    /// Metadata for a new ROM: its release from the game database, if it's
+    /// listed there, or else what the first plugin that knows it suggests,
+    /// otherwise what its file `name` says.
+    fn suggest_metadata(&self, metadata: &RomMetadata, name: FileName) -> NodeMetadata {
+        let found = self.game_db.as_ref().and_then(|db| {
+            db.lookup(metadata).unwrap_or_else(|e| {
+                warn(t!("game-db-lookup-failed", error = e));
+                None
+            })
+        });
+        if let Some(info) = found {
+            let db_regions = info
+                .region
+                .as_deref()
+                .and_then(|r| region::parse_list(r).ok())
+                .unwrap_or_default();
+            return NodeMetadata {
+                title: info.title,
+                version: info.region,
+                release_date: info.release_date.or(name.release_date),
+                tags: name.tags,
+                regions: if db_regions.is_empty() {
+                    name.regions
+                } else {
+                    db_regions
+                },
+                languages: name.languages,
+                ..Default::default()
+            };
+        }
+        let mut suggested = self
+            .plugins
+            .iter()
+            .find_map(|plugin| {
+                plugin
+                    .metadata(metadata, self.lookup_cache.as_ref())
+                    .unwrap_or_else(|e| {
+                        warn(&e);
+                        None
+                    })
+            })
+            .unwrap_or(NodeMetadata {
+                title: name.title,
+                ..Default::default()
+            });
+        // No-Intro, GoodTools, and TOSEC names fill in what the plugin left out
+        if suggested.version.is_none() {
+            suggested.version = name.version;
+        }
+        if suggested.release_date.is_none() {
+            suggested.release_date = name.release_date;
+        }
+        if suggested.tags.is_empty() {
+            suggested.tags = name.tags;
+        }
+        if suggested.regions.is_empty() {
+            suggested.regions = name.regions;
+        }
+        if suggested.languages.is_empty() {
+            suggested.languages = name.languages;
+        }
+        suggested
+    }
+
+    /// Like `ensure_rom_added`, but titles a new ROM from its filename
+    /// instead of prompting for metadata.
+    fn add_with_derived_title(&mut self, file: &Path) -> Result<Option<AddResult>> {
+        if !file.exists() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &file.display().to_string(),
+            );
+            return Ok(None);
+        }
+
+        let metadata = hash_rom_file(file)?;
+        if self.storage.node_exists(&metadata.sha256) {
+            let found = self.record_known_file(&metadata)?;
+            let node = self.storage.get_node_by_hash(&metadata.sha256).unwrap();
+            return Ok(Some(AddResult {
+                title: node.title.clone(),
+                version: node.version.clone(),
+                hash: metadata.sha256,
+                newly_added: false,
+                found,
+            }));
+        }
+
+        let node_metadata = self.suggest_metadata(&metadata, name_from_filename(file));
+        self.storage.add_node(file, &node_metadata)?;
+        if self.narrate() {
+            outln!(
+                "{} {} ({})",
+                theme::success(&t!("added")),
+                node_metadata.title,
+                theme::styled_hash(&format_hash(&metadata.sha256)[..16])
+            );
+        }
+
+        Ok(Some(AddResult {
+            title: node_metadata.title,
+            version: node_metadata.version,
+            hash: metadata.sha256,
+            newly_added: true,
+            found: false,
+        }))
+    }
+
+    /// Record the freshly hashed file of a ROM already in the library (see
+    /// [`StorageManager::record_file`]), announcing it if it was on the
+    /// wishlist. Returns true if it was.
+    fn record_known_file(&mut self, metadata: &RomMetadata) -> Result<bool> {
+        let found = self.storage.record_file(metadata)?;
+        if found
+            && self.narrate()
+            && let Some(node) = self.storage.get_node_by_hash(&metadata.sha256)
+        {
+            outln!(
+                "{} {} ({})",
+                theme::success(&t!("found-wishlist-rom")),
+                format_display_title(&node.title, node.version.as_deref()),
+                theme::styled_hash(&format_hash(&metadata.sha256)[..16])
+            );
+        }
+        Ok(found)
+    }
+
+    fn add_one(
+        &mut self,
+        file: &Path,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let result = match self.ensure_rom_added(file, rl)? {
+            Some(r) => r,
+            None => return Ok(()), // File not found, error already printed
+        };
+
+        if self.json() {
+            output::print_json(&json!({
+                "status": result.status(),
+                "node": self.node_json(&result.hash),
+            }));
+        }
+
+        if result.found {
+            return Ok(());
+        }
+        if !result.newly_added {
+            self.status.set(ExitStatus::AlreadyExists);
+            if !self.narrate() {
+                return Ok(());
+            }
+            let display_title = format_display_title(&result.title, result.version.as_deref());
+            outln!(
+                "{} {} ({})",
+                theme::info(&t!("rom-already-exists")),
+                display_title,
+                theme::styled_hash(&format_hash(&result.hash)[..16])
+            );
+            return Ok(());
+        }
+
+        // Update last added
+        self.last_added = Some(LastAdded {
+            hash: result.hash,
+            title: result.title,
+            version: result.version,
+        });
+
+        Ok(())
+    }
+
+    fn cmd_build(
+        &self,
+        source: &Path,
+        target: &str,
+        output: Option<&Path>,
+        overwrite: bool,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        // Refuse to clobber an explicit output path before doing any work
+        if let Some(path) = output
+            && path.exists()
+            && !overwrite
+        {
+            self.report_error(
+                ExitStatus::AlreadyExists,
+                &t!("output-file-already-exists"),
+                &t!("use-overwrite", path = path.display()),
+            );
+            return Ok(());
+        }
+
+        // Validate source exists
+        if !source.exists() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &source.display().to_string(),
+            );
+            return Ok(());
+        }
+
+        // Find target node
+        let Some(target_node) = self.resolve_target(target, rl) else {
+            return Ok(());
+        };
+        let target_hash = target_node.sha256;
+        let target_title = target_node.title.clone();
+        let target_version = target_node.version.clone();
+        let target_type = target_node.rom_type;
+
+        // Build the ROM
+        let display_title = format_display_title(&target_title, target_version.as_deref());
+        if self.narrate() {
+            outln!("{} {}...", theme::info(&t!("building")), display_title);
+        }
+        let result = match self.storage.build_rom(
+            source,
+            &target_hash,
+            &mut self.progress_line(),
+            interrupt::arm().token(),
+        ) {
+            Ok(r) => r,
+            Err(e) => {
+                self.report_failure(&t!("build-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        let steps = result.steps;
+        if self.narrate() {
+            outln!(
+                "{} {}",
+                theme::info(&t!("applied")),
+                counted!(steps, "diffs-one", "diffs-other")
+            );
+        }
+
+        // Use the explicit output path, or prompt for a filename
+        let output_path = match output {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let default_name = sanitize_filename(&target_title);
+                let filename =
+                    prompt_with_initial(rl, &t!("prompt-output-filename"), &default_name)?;
+                PathBuf::from(ensure_extension(&filename, target_type))
+            }
+        };
+
+        let final_bytes = self.file_bytes(result);
+
+        // Write to disk
+        std::fs::write(&output_path, &final_bytes)?;
+        if self.json() {
+            output::print_json(&json!({
+                "target": self.node_json(&target_hash),
+                "steps": steps,
+                "output": output_path.display().to_string(),
+                "bytes": final_bytes.len(),
+            }));
+        } else if self.narrate() {
+            outln!(
+                "{} {}",
+                theme::success(&t!("wrote-file")),
+                t!(
+                    "bytes-to",
+                    count = final_bytes.len(),
+                    path = output_path.display(),
+                )
+            );
+        }
+
+        Ok(())
+    }
+
+    /// A built ROM's file contents, with its header restored for NES files.
+    fn file_bytes(&self, result: BuildResult) -> Vec<u8> {
+        if result.target_row.rom_type != RomType::Nes {
+            return result.bytes;
+        }
+        match result.target_row.source_file_header {
+            Some(ref raw_header) => reconstruct_nes_file_raw(raw_header, &result.bytes),
+            None => {
+                if !self.json() {
+                    warn(t!("no-header-metadata"));
+                }
+                result.bytes
+            }
+        }
+    }
+
+    fn cmd_play(
+        &self,
+        source: &Path,
+        target: &str,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        if !source.exists() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &source.display().to_string(),
+            );
+            return Ok(());
+        }
+        let Some(target_node) = self.node_by_hash_prefix(target, &t!("target-rom-not-found"), rl)
+        else {
+            return Ok(());
+        };
+        let rom_type = target_node.rom_type;
+        let Some(emulator) = self.user_config.emulators.get(rom_type.extension()) else {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("no-emulator-configured-for"),
+                &t!(
+                    "emulator-setting",
+                    rom_type = rom_type,
+                    extension = rom_type.extension(),
+                    path = config_file_path()
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_else(|| "dromos.conf".to_string()),
+                ),
+            );
+            return Ok(());
+        };
+
+        // There's no build cache yet, so every play builds from the source
+        let display_title =
+            format_display_title(&target_node.title, target_node.version.as_deref());
+        if self.narrate() {
+            outln!("{} {}...", theme::info(&t!("building")), display_title);
+        }
+        let result = match self.storage.build_rom(
+            source,
+            &target_node.sha256,
+            &mut self.progress_line(),
+            interrupt::arm().token(),
+        ) {
+            Ok(r) => r,
+            Err(e) => {
+                self.report_failure(&t!("build-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        let final_bytes = self.file_bytes(result);
+
+        // Named after the title, since emulators show it and key saves by it
+        let temp_dir = tempfile::Builder::new().prefix("dromos-play-").tempdir()?;
+        let rom_path = temp_dir.path().join(ensure_extension(
+            &sanitize_filename(&target_node.title),
+            rom_type,
+        ));
+        let status = std::fs::write(&rom_path, &final_bytes).and_then(|()| {
+            let args = emulator_args(emulator, &rom_path);
+            let (program, args) = args.split_first().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, t!("empty-emulator-command"))
+            })?;
+            if self.narrate() {
+                outln!(
+                    "{} {}",
+                    theme::info(&t!("playing")),
+                    t!("title-in", title = display_title, program = program)
+                );
+            }
+            // Ctrl+C reaches the emulator too; armed, dromos outlives it and
+            // removes the ROM
+            let _interrupt = interrupt::arm();
+            process::Command::new(program).args(args).status()
+        });
+        drop(temp_dir);
+
+        match status {
+            Ok(status) => {
+                if self.json() {
+                    output::print_json(&json!({
+                        "target": self.node_json(&target_node.sha256),
+                        "emulator": emulator,
+                        "exit_code": status.code(),
+                    }));
+                } else if !status.success() && !self.quiet {
+                    warn(t!("emulator-exited", status = status));
+                }
+            }
+            Err(e) => self.report_error(
+                ExitStatus::Io,
+                &t!("could-not-run-emulator"),
+                &e.to_string(),
+            ),
+        }
+        Ok(())
+    }
+
+    fn cmd_link(
+        &mut self,
+        files: &[std::path::PathBuf],
+        note: Option<&str>,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        match files.len() {
+            1 => self.link_to_last(&files[0], rl),
+            2 => self.link_two_files(&files[0], &files[1], note, rl),
+            _ => {
+                self.report_error(
+                    ExitStatus::Usage,
+                    &t!("usage"),
+                    "link <file1> [file2] [--note <text>]",
+                );
+                Ok(())
+            }
+        }
+    }
+
+    fn link_to_last(
+        &mut self,
+        file: &Path,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let last = match &self.last_added {
+            Some(last) => last.clone(),
+            None => {
+                self.report_error(
+                    ExitStatus::NotFound,
+                    &t!("no-previous-rom"),
+                    &t!("link-two-files-instead"),
+                );
+                return Ok(());
+            }
+        };
+
+        // Confirm link to last added
+        if !self.assume_yes && !self.rpc {
+            let last_display = format_display_title(&last.title, last.version.as_deref());
+            let prompt = t!("link-to-last", title = last_display);
+            output::write_prompt(format_args!("{} {}: ", prompt, t!("confirm-choices-yes")))?;
+
+            let mut input = String::new();
+            io::stdin().read_line(&mut input)?;
+            if is_answer(&t!("confirm-no"), &input) {
+                self.report_cancelled();
+                return Ok(());
+            }
+        }
+
+        // Add ROM if needed (with full metadata prompting)
+        let result = match self.ensure_rom_added(file, rl)? {
+            Some(r) => r,
+            None => return Ok(()), // File not found, error already printed
+        };
+
+        // Need to find the file for the last_added ROM
+        // For now, require the user to have the file accessible
+        // This is a limitation - we'd need to store original file paths
+        eprintln!("{}", t!("link-needs-both-files"));
+        if self.json() {
+            output::print_json(&json!({
+                "status": result.status(),
+                "node": self.node_json(&result.hash),
+                "linked": false,
+            }));
+        }
+
+        // Update last added
+        self.last_added = Some(LastAdded {
+            hash: result.hash,
+            title: result.title,
+            version: result.version,
+        });
+
+        Ok(())
+    }
+
+    fn link_two_files(
+        &mut self,
+        file_a: &Path,
+        file_b: &Path,
+        note: Option<&str>,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        // Add first file if needed (with full metadata prompting)
+        let result_a = match self.ensure_rom_added(file_a, rl)? {
+            Some(r) => r,
+            None => return Ok(()), // File not found, error already printed
+        };
+
+        // Add second file if needed (with full metadata prompting)
+        let result_b = match self.ensure_rom_added(file_b, rl)? {
+            Some(r) => r,
+            None => return Ok(()), // File not found, error already printed
+        };
+
+        // Create bidirectional links
+        let (size_ab, size_ba) = self.storage.link_nodes(
+            file_a,
+            file_b,
+            note,
+            &mut self.progress_line(),
+            interrupt::arm().token(),
+        )?;
+        if self.json() {
+            output::print_json(&json!({
+                "linked": [self.node_json(&result_a.hash), self.node_json(&result_b.hash)],
+                "diff_sizes": [size_ab, size_ba],
+                "note": note,
+            }));
+        } else if self.narrate() {
+            let display_a = format_display_title(&result_a.title, result_a.version.as_deref());
+            let display_b = format_display_title(&result_b.title, result_b.version.as_deref());
+            outln!(
+                "{} {} <-> {}",
+                theme::success(&t!("linked")),
+                display_a,
+                display_b
+            );
+        }
+
+        // Update last added to the second file
+        self.last_added = Some(LastAdded {
+            hash: result_b.hash,
+            title: result_b.title,
+            version: result_b.version,
+        });
+
+        Ok(())
+    }
+
+    fn cmd_list(
+        &self,
+        columns: Option<Vec<ListColumn>>,
+        sort: Option<ListColumn>,
+        reverse: bool,
+        query: &str,
+        all: bool,
+    ) -> Result<()> {
+        let mut filter = match NodeFilter::parse(query) {
+            Ok(filter) => filter,
+            Err(e) => {
+                self.report_error(ExitStatus::Usage, &t!("invalid-search"), &e);
+                return Ok(());
+            }
+        };
+        if !all {
+            filter.archived.get_or_insert(false);
+        }
+        // Options given on the command line override the configured layout
+        let layout = &self.list_layout;
+        let columns = columns.unwrap_or_else(|| layout.columns.clone());
+        let (sort, reverse) = match sort {
+            Some(sort) => (sort, reverse),
+            None => (layout.sort, layout.reverse != reverse),
+        };
+
+        let mut dat_matches = self.storage.dat_matches()?;
+        let mut entries: Vec<ListEntry> = self
+            .storage
+            .search(&filter)?
+            .into_iter()
+            .map(|row| ListEntry {
+                size: row.data_size(),
+                links: self.storage.link_count(&row.sha256),
+                dat: dat_matches.remove(&row.sha256),
+                row,
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            let order = a.compare(b, sort);
+            let order = if reverse { order.reverse() } else { order };
+            order.then_with(|| a.compare(b, ListColumn::Title))
+        });
+
+        if self.json() {
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|e| {
+                    json!({
+                        "node": self.node_json(&e.row.sha256),
+                        "links": e.links,
+                        "size": e.size,
+                        "dat": e.dat.as_ref().map(dat_match_json),
+                        "rating": e.row.rating,
+                        "play_status": e.row.play_status.as_str(),
+                        "regions": display_strings(&e.row.regions),
+                        "languages": display_strings(&e.row.languages),
+                        "favorite": e.row.favorite,
+                        "archived": e.row.archived,
+                        "wanted": e.row.missing,
+                    })
+                })
+                .collect();
+            output::print_json(&entries);
+            return Ok(());
+        }
+
+        if entries.is_empty() {
+            let message = if query.trim().is_empty() && self.storage.counts().0 > 0 {
+                t!("every-rom-archived")
+            } else if query.trim().is_empty() {
+                t!("no-roms-in-database")
+            } else {
+                t!("no-matches-for", query = query)
+            };
+            outln!("{}", theme::dim(&message));
+            return Ok(());
+        }
+
+        // Leave out columns with nothing to show, rather than a blank gap
+        let columns: Vec<ListColumn> = columns
+            .into_iter()
+            .filter(|c| entries.iter().any(|e| !e.cell(*c).is_empty()))
+            .collect();
+        let cells: Vec<Vec<String>> = entries
+            .iter()
+            .map(|e| columns.iter().map(|c| e.cell(*c)).collect())
+            .collect();
+        if theme::plain() {
+            // One labeled line per ROM, which reads better than columns
+            for row in &cells {
+                let fields: Vec<String> = row
+                    .iter()
+                    .zip(&columns)
+                    .filter(|(cell, _)| !cell.is_empty())
+                    .map(|(cell, column)| format!("{}: {}", column, cell))
+                    .collect();
+                outln!("{}", fields.join(", "));
+            }
+            return Ok(());
+        }
+        let widths = column_widths(&cells);
+        for row in &cells {
+            let mut line = String::new();
+            for ((cell, column), width) in row.iter().zip(&columns).zip(&widths) {
+                if !line.is_empty() {
+                    line.push_str("  ");
+                }
+                if !cell.is_empty() {
+                    line.push_str(&style_list_cell(*column, cell));
+                }
+                let padding = width.saturating_sub(cell.chars().count());
+                line.push_str(&" ".repeat(padding));
+            }
+            outln!("{}", line.trim_end());
+        }
+        Ok(())
+    }
+
+    fn cmd_report(
+        &self,
+        file: &Path,
+        format: ReportFormat,
+        columns: Option<Vec<ListColumn>>,
+        sort: Option<ListColumn>,
+        reverse: bool,
+        query: &str,
+    ) -> Result<()> {
+        let filter = match NodeFilter::parse(query) {
+            Ok(filter) => filter,
+            Err(e) => {
+                self.report_error(ExitStatus::Usage, &t!("invalid-search"), &e);
+                return Ok(());
+            }
+        };
+        let columns = columns.unwrap_or_else(|| REPORT_COLUMNS.to_vec());
+        let sort = sort.unwrap_or(ListColumn::Title);
+
+        let mut dat_matches = self.storage.dat_matches()?;
+        let mut entries: Vec<ListEntry> = self
+            .storage
+            .search(&filter)?
+            .into_iter()
+            .map(|row| ListEntry {
+                size: row.data_size(),
+                links: self.storage.link_count(&row.sha256),
+                dat: dat_matches.remove(&row.sha256),
+                row,
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            let order = a.compare(b, sort);
+            let order = if reverse { order.reverse() } else { order };
+            order.then_with(|| a.compare(b, ListColumn::Title))
+        });
+        let rows: Vec<Vec<String>> = entries
+            .iter()
+            .map(|e| columns.iter().map(|c| e.value(*c)).collect())
+            .collect();
+        let table = format.format(&columns, &rows);
+
+        // `-` writes the table itself to standard output, for piping
+        if file == Path::new("-") {
+            // Under `--rpc` standard output carries replies, so it goes in one
+            if self.rpc {
+                output::print_json(&json!({ "report": table }));
+            } else {
+                out!("{}", table);
+            }
+            return Ok(());
+        }
+        if file.exists() && !self.confirm(&t!("overwrite-file", path = file.display()))? {
+            self.report_cancelled();
+            return Ok(());
+        }
+        if let Err(e) = std::fs::write(file, table) {
+            let e = DromosError::file(file)(e);
+            self.report_failure(&t!("report-failed"), &e.to_string(), &e);
+            return Ok(());
+        }
+
+        if self.json() {
+            output::print_json(&json!({
+                "file": file.display().to_string(),
+                "format": format.to_string(),
+                "rows": rows.len(),
+            }));
+        } else if !self.quiet {
+            outln!(
+                "{} {}",
+                theme::success(&t!("reported")),
+                t!(
+                    "roms-to",
+                    roms = counted!(rows.len(), "roms-one", "roms-other"),
+                    path = file.display(),
+                )
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_open(&self, target: &str, rl: &mut Editor<DromosHelper, DefaultHistory>) -> Result<()> {
+        let Some(node) = self.resolve_node(target, rl) else {
+            return Ok(());
+        };
+        let display_title = format_display_title(&node.title, node.version.as_deref());
+        let Some(row) = self.storage.get_node_row_by_hash(&node.sha256)? else {
+            self.report_rom_not_found(&t!("rom-not-found"), target);
+            return Ok(());
+        };
+
+        let Some(url) = row.source_url.filter(|u| !u.trim().is_empty()) else {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("no-source-url-for"),
+                &display_title,
+            );
+            return Ok(());
+        };
+        // Only hand web links to the OS opener, never local paths or commands
+        // and never anything that could split into more arguments
+        let lower = url.to_lowercase();
+        if !(lower.starts_with("http://") || lower.starts_with("https://"))
+            || url
+                .chars()
+                .any(|c| c.is_whitespace() || c.is_control() || c == '"')
+        {
+            self.report_error(ExitStatus::Failure, &t!("not-a-web-url"), &url);
+            return Ok(());
+        }
+
+        if let Err(e) = open_in_browser(&url) {
+            self.report_error(
+                ExitStatus::Io,
+                &t!("could-not-open-browser"),
+                &e.to_string(),
+            );
+            return Ok(());
+        }
+
+        if self.json() {
+            output::print_json(&json!({ "opened": url }));
+        } else if self.narrate() {
+            outln!("{} {}", theme::success(&t!("opened")), url);
+        }
+        Ok(())
+    }
+
+    fn cmd_fav(
+        &mut self,
+        target: &str,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let Some(node) = self.resolve_node(target, rl) else {
+            return Ok(());
+        };
+        let sha256 = node.sha256;
+        let display_title = format_display_title(&node.title, node.version.as_deref());
+        let Some(row) = self.storage.get_node_row_by_hash(&sha256)? else {
+            self.report_rom_not_found(&t!("rom-not-found"), target);
+            return Ok(());
+        };
+        let favorite = !row.favorite;
+        self.storage.set_favorite(&sha256, favorite)?;
+
+        if self.json() {
+            output::print_json(&json!({
+                "node": self.node_json(&sha256),
+                "favorite": favorite,
+            }));
+        } else if self.narrate() {
+            let label = if favorite {
+                t!("favorited")
+            } else {
+                t!("unfavorited")
+            };
+            outln!(
+                "{} {}",
+                theme::success(&label),
+                theme::title(&display_title)
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_archive(
+        &mut self,
+        target: &str,
+        archived: bool,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let Some(node) = self.resolve_node(target, rl) else {
+            return Ok(());
+        };
+        let sha256 = node.sha256;
+        let display_title = format_display_title(&node.title, node.version.as_deref());
+        self.storage.set_archived(&sha256, archived)?;
+
+        if self.json() {
+            output::print_json(&json!({
+                "node": self.node_json(&sha256),
+                "archived": archived,
+            }));
+        } else if self.narrate() {
+            let label = if archived {
+                t!("archived")
+            } else {
+                t!("unarchived")
+            };
+            outln!(
+                "{} {}",
+                theme::success(&label),
+                theme::title(&display_title)
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_attach(
+        &mut self,
+        file: &Path,
+        target: &str,
+        linked: Option<&str>,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        if !file.is_file() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &file.display().to_string(),
+            );
+            return Ok(());
+        }
+        let Some((node, linked)) = self.resolve_attachment_place(target, linked, rl) else {
+            return Ok(());
+        };
+
+        match self.storage.attach_file(file, &node, linked.as_ref()) {
+            Ok(attachment) => {
+                if self.json() {
+                    output::print_json(&json!({ "attached": self.attachment_json(&attachment) }));
+                } else if self.narrate() {
+                    outln!(
+                        "{} {}",
+                        theme::success(&t!("attached")),
+                        t!(
+                            "attached-to",
+                            name = attachment.name,
+                            size = format_size(attachment.size),
+                            place = self.attachment_place(&attachment),
+                        )
+                    );
+                }
+            }
+            Err(e) => self.report_failure(&t!("attach-failed"), &e.to_string(), &e),
+        }
+        Ok(())
+    }
+
+    fn cmd_detach(
+        &mut self,
+        name: &str,
+        target: &str,
+        linked: Option<&str>,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let Some((node, linked)) = self.resolve_attachment_place(target, linked, rl) else {
+            return Ok(());
+        };
+
+        match self.storage.detach_file(&node, linked.as_ref(), name) {
+            Ok(attachment) => {
+                if self.json() {
+                    output::print_json(&json!({ "detached": self.attachment_json(&attachment) }));
+                } else if self.narrate() {
+                    outln!(
+                        "{} {}",
+                        theme::success(&t!("detached")),
+                        t!(
+                            "detached-from",
+                            name = attachment.name,
+                            place = self.attachment_place(&attachment),
+                        )
+                    );
+                }
+            }
+            Err(e) => self.report_failure(&t!("detach-failed"), &e.to_string(), &e),
+        }
+        Ok(())
+    }
+
+    fn cmd_collection_create(&mut self, name: &str) -> Result<()> {
+        match self.storage.create_collection(name) {
+            Ok(collection) => {
+                if self.json() {
+                    output::print_json(&json!({ "created": collection_json(&collection) }));
+                } else if self.narrate() {
+                    outln!(
+                        "{} {}",
+                        theme::success(&t!("created")),
+                        t!("collection-name", name = collection.name)
+                    );
+                }
+            }
+            Err(e) => self.report_failure(&t!("create-failed"), &e.to_string(), &e),
+        }
+        Ok(())
+    }
+
+    fn cmd_collection_delete(&mut self, name: &str) -> Result<()> {
+        let collection = match self.storage.collection(name) {
+            Ok(collection) => collection,
+            Err(e) => {
+                self.report_failure(&t!("delete-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        if collection.nodes > 0
+            && !self.confirm(&t!(
+                "delete-collection",
+                name = collection.name,
+                roms = counted!(collection.nodes, "roms-one", "roms-other"),
+            ))?
+        {
+            self.report_cancelled();
+            return Ok(());
+        }
+        match self.storage.delete_collection(&collection.name) {
+            Ok(collection) => {
+                if self.json() {
+                    output::print_json(&json!({ "deleted": collection_json(&collection) }));
+                } else if self.narrate() {
+                    outln!(
+                        "{} {}",
+                        theme::success(&t!("deleted")),
+                        t!("collection-name", name = collection.name)
+                    );
+                }
+            }
+            Err(e) => self.report_failure(&t!("delete-failed"), &e.to_string(), &e),
+        }
+        Ok(())
+    }
+
+    /// Add ROMs to a collection, or with `add` false, take them out.
+    fn cmd_collection_change(
+        &mut self,
+        name: &str,
+        targets: &[String],
+        add: bool,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let collection = match self.storage.collection(name) {
+            Ok(collection) => collection,
+            Err(e) => {
+                self.report_failure(&t!("collection-not-changed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        let mut hashes: Vec<[u8; 32]> = Vec::new();
+        for target in targets {
+            let Some(node) = self.resolve_node(target, rl) else {
+                return Ok(());
+            };
+            if !hashes.contains(&node.sha256) {
+                hashes.push(node.sha256);
+            }
+        }
+
+        let changed = if add {
+            self.storage.add_to_collection(&collection.name, &hashes)
+        } else {
+            self.storage
+                .remove_from_collection(&collection.name, &hashes)
+        };
+        let changed = match changed {
+            Ok(changed) => changed,
+            Err(e) => {
+                self.report_failure(&t!("collection-not-changed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+
+        if self.json() {
+            let nodes: Vec<_> = changed.iter().filter_map(|h| self.node_json(h)).collect();
+            let key = if add { "added" } else { "removed" };
+            output::print_json(&json!({ "collection": collection.name, key: nodes }));
+            return Ok(());
+        }
+        if !self.narrate() {
+            return Ok(());
+        }
+        for sha256 in &changed {
+            if let Some(node) = self.storage.get_node_by_hash(sha256) {
+                let title = format_display_title(&node.title, node.version.as_deref());
+                let (label, change) = if add {
+                    (
+                        t!("added"),
+                        t!("title-to", title = title, name = collection.name),
+                    )
+                } else {
+                    (
+                        t!("removed"),
+                        t!("title-from", title = title, name = collection.name),
+                    )
+                };
+                outln!("{} {}", theme::success(&label), change);
+            }
+        }
+        let unchanged = hashes.len() - changed.len();
+        if unchanged > 0 {
+            let roms = counted!(unchanged, "roms-one", "roms-other");
+            let state = if add {
+                t!("roms-already-in", roms = roms, name = collection.name)
+            } else {
+                t!("roms-not-in", roms = roms, name = collection.name)
+            };
+            outln!("{}", theme::dim(&state));
+        }
+        Ok(())
+    }
+
+    /// List the collections, or with `name`, the ROMs in that one.
+    fn cmd_collection_list(&self, name: Option<&str>) -> Result<()> {
+        let Some(name) = name else {
+            let collections = self.storage.collections()?;
+            if self.json() {
+                let collections: Vec<_> = collections.iter().map(collection_json).collect();
+                output::print_json(&collections);
+            } else if collections.is_empty() {
+                outln!("{}", theme::dim(&t!("no-collections")));
+            } else {
+                let width = collections
+                    .iter()
+                    .map(|c| c.name.chars().count())
+                    .max()
+                    .unwrap_or(0);
+                for collection in &collections {
+                    let padding = width - collection.name.chars().count();
+                    outln!(
+                        "{}{}  {}",
+                        theme::title(&collection.name),
+                        " ".repeat(padding),
+                        theme::meta(&format!(
+                            "[{}]",
+                            counted!(collection.nodes, "roms-one", "roms-other")
+                        ))
+                    );
+                }
+            }
+            return Ok(());
+        };
+
+        let filter = NodeFilter {
+            collections: vec![name.to_string()],
+            ..Default::default()
+        };
+        let rows = match self.storage.search(&filter) {
+            Ok(rows) => rows,
+            Err(e) => {
+                self.report_failure(&t!("list-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        if self.json() {
+            let nodes: Vec<_> = rows
+                .iter()
+                .filter_map(|row| self.node_json(&row.sha256))
+                .collect();
+            output::print_json(&nodes);
+            return Ok(());
+        }
+        if rows.is_empty() {
+            outln!("{}", theme::dim(&t!("no-roms-in", name = name)));
+            return Ok(());
+        }
+        for row in rows {
+            outln!(
+                "{}  {}  {}",
+                format_display_title(&row.title, row.version.as_deref()),
+                theme::styled_hash(&format_hash(&row.sha256)[..16]),
+                row.rom_type
+            );
+        }
+        Ok(())
+    }
+
+    /// Resolve the ROM, and the other end of the link if given, that an
+    /// attachment is on
+    fn resolve_attachment_place(
+        &self,
+        target: &str,
+        linked: Option<&str>,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Option<([u8; 32], Option<[u8; 32]>)> {
+        let node = self.resolve_node(target, rl)?.sha256;
+        match linked {
+            Some(linked) => Some((node, Some(self.resolve_node(linked, rl)?.sha256))),
+            None => Some((node, None)),
+        }
+    }
+
+    /// The ROM's title, or the link's titles, that an attachment is on
+    fn attachment_place(&self, attachment: &AttachmentRow) -> String {
+        let title = |sha256: &[u8; 32]| match self.storage.get_node_by_hash(sha256) {
+            Some(n) => format_display_title(&n.title, n.version.as_deref()),
+            None => format_hash(sha256)[..16].to_string(),
+        };
+        match &attachment.linked_node {
+            Some(linked) => t!(
+                "link-between",
+                a = title(&attachment.node),
+                b = title(linked),
+            ),
+            None => title(&attachment.node),
+        }
+    }
+
+    fn attachment_json(&self, attachment: &AttachmentRow) -> serde_json::Value {
+        json!({
+            "name": attachment.name,
+            "size": attachment.size,
+            "sha256": format_hash(&attachment.sha256),
+            "node": self.node_json(&attachment.node),
+            "linked_node": attachment.linked_node.and_then(|h| self.node_json(&h)),
+            "path": self.storage.attachment_path(attachment).display().to_string(),
+            "created_at": attachment.created_at,
+        })
+    }
+
+    fn cmd_info(&self, target: &str, rl: &mut Editor<DromosHelper, DefaultHistory>) -> Result<()> {
+        let Some(node) = self.resolve_node(target, rl) else {
+            return Ok(());
+        };
+        let Some(row) = self.storage.get_node_row_by_hash(&node.sha256)? else {
+            self.report_rom_not_found(&t!("rom-not-found"), target);
+            return Ok(());
+        };
+        let neighbors = self.storage.get_neighbors(&row.sha256).unwrap_or_default();
+
+        // Header details come from the stored raw header bytes
+        let raw_header: Option<[u8; 16]> = row
+            .source_file_header
+            .as_deref()
+            .and_then(|h| h.get(..16))
+            .and_then(|h| h.try_into().ok());
+        let header = raw_header.as_ref().and_then(parse_nes_header_bytes);
+        let nes2 = raw_header.as_ref().and_then(parse_nes2_details);
+        let dat = self.storage.dat_match(&row.sha256)?;
+        let attachments = self.storage.attachments(&row.sha256)?;
+        let collections = self.storage.node_collections(&row.sha256)?;
+        let aliases = self.storage.filename_aliases(&row.sha256)?;
+        let locations = self.storage.locations(&row.sha256)?;
+
+        if self.json() {
+            let nes2_json = nes2.as_ref().map(|d| {
+                json!({
+                    "prg_ram_size": d.prg_ram_size,
+                    "prg_nvram_size": d.prg_nvram_size,
+                    "chr_ram_size": d.chr_ram_size,
+                    "chr_nvram_size": d.chr_nvram_size,
+                    "timing": d.timing.to_string(),
+                })
+            });
+            let links: Vec<_> = neighbors.iter().map(|(n, e)| link_json(n, e)).collect();
+            output::print_json(&json!({
+                "node": NodeJson::from(node),
+                "created_at": row.created_at,
+                "updated_at": row.updated_at,
+                "content_size": row.content_size,
+                "source_url": row.source_url,
+                "release_date": row.release_date,
+                "tags": row.tags,
+                "description": row.description,
+                "rating": row.rating,
+                "play_status": row.play_status.as_str(),
+                "regions": display_strings(&row.regions),
+                "languages": display_strings(&row.languages),
+                "favorite": row.favorite,
+                "archived": row.archived,
+                "wanted": row.missing,
+                "filename_aliases": aliases,
+                "locations": locations
+                    .iter()
+                    .map(|l| json!({
+                        "path": l.path,
+                        "header_matches": l.header_matches,
+                        "last_seen": l.last_seen,
+                    }))
+                    .collect::<Vec<_>>(),
+                "collections": collections,
+                "header": header.as_ref().map(HeaderJson::from),
+                "nes2": nes2_json,
+                "dat": dat.as_ref().map(dat_match_json),
+                "links": links,
+                "attachments": attachments
+                    .iter()
+                    .map(|a| self.attachment_json(a))
+                    .collect::<Vec<_>>(),
+            }));
+            return Ok(());
+        }
+
+        outln!(
+            "{}",
+            theme::title(&format_display_title(&row.title, row.version.as_deref()))
+        );
+        print_field(&t!("field-sha-256"), &format_hash(&row.sha256));
+        print_field(&t!("field-type"), &theme::label(&row.rom_type.to_string()));
+        if let Some(size) = row.content_size {
+            print_field(
+                &t!("field-size"),
+                &t!("size-bytes", size = format_size(size as i64), count = size),
+            );
+        }
+        print_field(
+            &t!("field-filename"),
+            row.filename.as_deref().unwrap_or("-"),
+        );
+        let other_names: Vec<&str> = aliases
+            .iter()
+            .map(String::as_str)
+            .filter(|name| Some(*name) != row.filename.as_deref())
+            .collect();
+        if !other_names.is_empty() {
+            print_field(&t!("field-also-seen-as"), &other_names.join(", "));
+        }
+        if row.missing {
+            print_field(&t!("field-wanted"), &theme::warning(&t!("on-wishlist")));
+        }
+        if let Some(m) = &dat {
+            print_field(
+                &t!("field-verified"),
+                &format!(
+                    "{} ({})",
+                    theme::success(&format!("{} {}", verified_mark(), m.game)),
+                    m.dat_name
+                ),
+            );
+        }
+        print_field(&t!("field-added"), &row.created_at);
+        if let Some(updated_at) = &row.updated_at {
+            print_field(&t!("field-edited"), updated_at);
+        }
+        print_field(
+            &t!("field-source-url"),
+            row.source_url.as_deref().unwrap_or("-"),
+        );
+        print_field(
+            &t!("field-released"),
+            row.release_date.as_deref().unwrap_or("-"),
+        );
+        let tags = if row.tags.is_empty() {
+            "-".to_string()
+        } else {
+            row.tags.join(", ")
+        };
+        print_field(&t!("field-tags"), &tags);
+        let rating = row
+            .rating
+            .map(|r| format!("{}/{}", r, MAX_RATING))
+            .unwrap_or_else(|| "-".to_string());
+        print_field(&t!("field-rating"), &rating);
+        print_field(&t!("field-status"), row.play_status.as_str());
+        let or_dash = |s: String| if s.is_empty() { "-".to_string() } else { s };
+        print_field(
+            &t!("field-region"),
+            &or_dash(region::join_list(&row.regions)),
+        );
+        print_field(
+            &t!("field-language"),
+            &or_dash(region::join_list(&row.languages)),
+        );
+        if row.favorite {
+            let yes = t!("yes");
+            let favorite = if theme::plain() {
+                yes
+            } else {
+                format!("★ {}", yes)
+            };
+            print_field(&t!("field-favorite"), &favorite);
+        }
+        if row.archived {
+            print_field(&t!("field-archived"), &t!("archived-hidden"));
+        }
+        if !collections.is_empty() {
+            print_field(&t!("field-collections"), &collections.join(", "));
+        }
+        match row.description.as_deref() {
+            Some(desc) if !desc.is_empty() => {
+                outln!("  {}", t!("field-description"));
+                for line in desc.lines() {
+                    outln!("    {}", line);
+                }
+            }
+            _ => print_field(&t!("field-description"), "-"),
+        }
+
+        if let Some(h) = &header {
+            outln!("{}", theme::header(&t!("field-header")));
+            print_field(
+                &t!("field-format"),
+                if h.is_nes2 { "NES 2.0" } else { "iNES" },
+            );
+            let mapper = match (mapper_name(h.mapper), h.submapper) {
+                (Some(name), Some(sub)) => t!(
+                    "mapper-named-submapper",
+                    mapper = h.mapper,
+                    name = name,
+                    submapper = sub,
+                ),
+                (Some(name), None) => format!("{} ({})", h.mapper, name),
+                (None, Some(sub)) => t!("mapper-submapper", mapper = h.mapper, submapper = sub),
+                (None, None) => h.mapper.to_string(),
+            };
+            print_field(&t!("field-mapper"), &mapper);
+            print_field(&t!("field-prg-rom"), &format_size(h.prg_rom_size as i64));
+            print_field(&t!("field-chr-rom"), &format_size(h.chr_rom_size as i64));
+            print_field(&t!("field-mirroring"), &format!("{:?}", h.mirroring));
+            print_field(&t!("field-battery"), &yes_no(h.has_battery));
+            print_field(&t!("field-trainer"), &yes_no(h.has_trainer));
+            if let Some(d) = &nes2 {
+                print_field(&t!("field-prg-ram"), &format_size(d.prg_ram_size as i64));
+                print_field(
+                    &t!("field-prg-nvram"),
+                    &format_size(d.prg_nvram_size as i64),
+                );
+                print_field(&t!("field-chr-ram"), &format_size(d.chr_ram_size as i64));
+                print_field(
+                    &t!("field-chr-nvram"),
+                    &format_size(d.chr_nvram_size as i64),
+                );
+                print_field(&t!("field-timing"), &d.timing.to_string());
+            }
+        }
+
+        outln!("{} {}", theme::header(&t!("links")), neighbors.len());
+        for (neighbor, edge) in &neighbors {
+            outln!(
+                "  -> {}  {}  ({})",
+                format_display_title(&neighbor.title, neighbor.version.as_deref()),
+                theme::styled_hash(&format_hash(&neighbor.sha256)[..16]),
+                format_size(edge.diff_size)
+            );
+            print_link_note(edge);
+        }
+
+        if !attachments.is_empty() {
+            outln!(
+                "{} {}",
+                theme::header(&t!("attachments")),
+                attachments.len()
+            );
+            for attachment in &attachments {
+                let other = attachment
+                    .link_end_from(&row.sha256)
+                    .and_then(|other| self.storage.get_node_by_hash(&other));
+                let link = match other {
+                    Some(other) => format!(
+                        "  {}",
+                        t!(
+                            "link-with",
+                            title = format_display_title(&other.title, other.version.as_deref()),
+                        )
+                    ),
+                    None => String::new(),
+                };
+                outln!(
+                    "  {}  ({}){}  {}",
+                    attachment.name,
+                    format_size(attachment.size),
+                    link,
+                    theme::dim(
+                        &self
+                            .storage
+                            .attachment_path(attachment)
+                            .display()
+                            .to_string()
+                    )
+                );
+            }
+        }
+
+        if !locations.is_empty() {
+            outln!("{} {}", theme::header(&t!("located-at")), locations.len());
+            for location in &locations {
+                let header = if location.header_matches {
+                    String::new()
+                } else {
+                    format!("  {}", theme::warning(&t!("different-header")))
+                };
+                outln!(
+                    "  {}{}  {}",
+                    location.path,
+                    header,
+                    theme::dim(&t!("seen-at", time = location.last_seen))
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn cmd_links(&self, target: &str, rl: &mut Editor<DromosHelper, DefaultHistory>) -> Result<()> {
+        // Try to find node: first as file, then as hash prefix
+        let node = if std::path::Path::new(target).exists() {
+            // It's a file path - hash it and look up
+            let metadata = hash_rom_file(std::path::Path::new(target))?;
+            let node = self.storage.get_node_by_hash(&metadata.sha256);
+            if node.is_none() {
+                self.report_rom_not_found(&t!("rom-not-found"), target);
+            }
+            node
+        } else {
+            self.node_by_hash_prefix(target, &t!("rom-not-found"), rl)
+        };
+        let Some(node) = node else {
+            return Ok(());
+        };
+
+        let neighbors = self.storage.get_neighbors(&node.sha256);
+
+        if self.json() {
+            let links: Vec<_> = neighbors
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(n, e)| link_json(n, e))
+                .collect();
+            output::print_json(&json!({ "node": NodeJson::from(node), "links": links }));
+            return Ok(());
+        }
+
+        let display_title = format_display_title(&node.title, node.version.as_deref());
+        outln!(
+            "{}  ({})",
+            display_title,
+            theme::styled_hash(&format_hash(&node.sha256)[..16])
+        );
+
+        match neighbors {
+            Some(links) if !links.is_empty() => {
+                for (neighbor, edge) in links {
+                    let neighbor_display =
+                        format_display_title(&neighbor.title, neighbor.version.as_deref());
+                    outln!(
+                        "  -> {}  ({})",
+                        neighbor_display,
+                        format_size(edge.diff_size)
+                    );
+                    print_link_note(edge);
+                }
+            }
+            _ => {
+                outln!("  {}", theme::dim(&t!("no-links")));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn cmd_rm(
+        &mut self,
+        targets: &[String],
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        // Resolve every target before removing anything
+        let mut hashes: Vec<[u8; 32]> = Vec::new();
+        for target in targets {
+            let Some(node) = self.resolve_node(target, rl) else {
+                return Ok(());
+            };
+            if !hashes.contains(&node.sha256) {
+                hashes.push(node.sha256);
+            }
+        }
+
+        // Links to other ROMs being removed are listed (and counted) once
+        let mut removed_json = Vec::new();
+        let mut titles = Vec::new();
+        let mut listing = Vec::new();
+        let mut link_count = 0;
+        for (i, sha256) in hashes.iter().enumerate() {
+            let Some(node) = self.storage.get_node_by_hash(sha256) else {
+                continue;
+            };
+            let display_title = format_display_title(&node.title, node.version.as_deref());
+            listing.push(format!(
+                "  {}  {}",
+                theme::title(&display_title),
+                theme::styled_hash(&format_hash(sha256)[..16])
+            ));
+            for (neighbor, _) in self.storage.get_neighbors(sha256).unwrap_or_default() {
+                if hashes[..i].contains(&neighbor.sha256) {
+                    continue;
+                }
+                link_count += 1;
+                listing.push(format!(
+                    "    {} {}",
+                    theme::dim(&t!("link-to")),
+                    format_display_title(&neighbor.title, neighbor.version.as_deref())
+                ));
+            }
+            removed_json.push(NodeJson::from(node));
+            titles.push(display_title);
+        }
+
+        // One confirmation covers everything
+        let links = counted!(link_count, "links-one", "links-other");
+        let question = if titles.len() == 1 {
+            t!("remove-rom", title = titles[0], links = links)
+        } else {
+            if !self.assume_yes {
+                output::write_prompt(format_args!("{}\n", t!("this-will-remove")))?;
+                for line in &listing {
+                    output::write_prompt(format_args!("{}\n", line))?;
+                }
+            }
+            t!("remove-roms", count = titles.len(), links = links)
+        };
+        if !self.confirm(&question)? {
+            self.report_cancelled();
+            return Ok(());
+        }
+
+        let mut edges_removed = 0;
+        let mut diff_files_removed = 0;
+        let mut attachments_removed = 0;
+        for (sha256, display_title) in hashes.iter().zip(&titles) {
+            let result = self.storage.remove_node(sha256)?;
+            edges_removed += result.edges_removed;
+            diff_files_removed += result.diff_files_removed;
+            attachments_removed += result.attachments_removed;
+            for (path, reason) in &result.diff_files_failed {
+                warn(t!(
+                    "diff-file-not-deleted",
+                    path = path.display(),
+                    reason = reason,
+                ));
+            }
+
+            if self.narrate() {
+                outln!(
+                    "{} {} ({}, {})",
+                    theme::success(&t!("removed")),
+                    display_title,
+                    counted!(result.edges_removed, "edges-one", "edges-other"),
+                    counted!(
+                        result.diff_files_removed,
+                        "diff-files-one",
+                        "diff-files-other"
+                    ),
+                );
+            }
+
+            // Clear last_added if it was a removed node
+            if let Some(ref last) = self.last_added
+                && last.hash == *sha256
+            {
+                self.last_added = None;
+            }
+        }
+
+        if self.json() {
+            output::print_json(&json!({
+                "removed": removed_json,
+                "edges_removed": edges_removed,
+                "diff_files_removed": diff_files_removed,
+                "attachments_removed": attachments_removed,
+            }));
+        }
+
+        Ok(())
+    }
+
+    fn cmd_dedupe(&self, query: &str) -> Result<()> {
+        let filter = match NodeFilter::parse(query) {
+            Ok(filter) => filter,
+            Err(e) => {
+                self.report_error(ExitStatus::Usage, &t!("invalid-search"), &e);
+                return Ok(());
+            }
+        };
+        let rows = match self.storage.search(&filter) {
+            Ok(rows) => rows,
+            Err(e) => {
+                self.report_failure(&t!("search-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        let groups = dedupe::find_duplicates(&rows);
+
+        if self.json() {
+            let groups: Vec<_> = groups
+                .iter()
+                .map(|group| {
+                    json!({
+                        "exact": group.exact,
+                        "nodes": group
+                            .nodes
+                            .iter()
+                            .filter_map(|row| self.node_json(&row.sha256))
+                            .collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            output::print_json(&groups);
+            return Ok(());
+        }
+
+        if groups.is_empty() {
+            outln!("{}", t!("no-duplicates"));
+            return Ok(());
+        }
+        for (i, group) in groups.iter().enumerate() {
+            if i > 0 {
+                outln!();
+            }
+            let label = if group.exact {
+                t!("same-title")
+            } else {
+                t!("similar-titles")
+            };
+            outln!("{}", theme::header(&label));
+            for row in &group.nodes {
+                let links = self.storage.link_count(&row.sha256);
+                outln!(
+                    "  {}  {}{}",
+                    theme::title(&format_display_title(&row.title, row.version.as_deref())),
+                    theme::styled_hash(&format_hash(&row.sha256)[..16]),
+                    match links {
+                        0 => String::new(),
+                        n =>
+                            theme::dim(&format!("  [{}]", counted!(n, "links-one", "links-other"))),
+                    }
+                );
+            }
+        }
+        if !self.quiet {
+            outln!();
+            outln!(
+                "{}",
+                theme::dim(&t!(
+                    "duplicate-groups-found",
+                    groups = counted!(groups.len(), "groups-one", "groups-other"),
+                ))
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_merge(
+        &mut self,
+        keep: &str,
+        dup: &str,
+        sources: &[PathBuf],
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let Some(keep) = self.resolve_node(keep, rl).map(|n| n.sha256) else {
+            return Ok(());
+        };
+        let Some(dup) = self.resolve_node(dup, rl).map(|n| n.sha256) else {
+            return Ok(());
+        };
+        let (Some(keep_row), Some(dup_row)) = (
+            self.storage.get_node_row_by_hash(&keep)?,
+            self.storage.get_node_row_by_hash(&dup)?,
+        ) else {
+            return Ok(());
+        };
+        let keep_title = format_display_title(&keep_row.title, keep_row.version.as_deref());
+        let dup_title = format_display_title(&dup_row.title, dup_row.version.as_deref());
+        if keep == dup {
+            self.report_error(
+                ExitStatus::Usage,
+                &t!("cannot-merge-into-itself"),
+                &keep_title,
+            );
+            return Ok(());
+        }
+        if let Some(missing) = sources.iter().find(|s| !s.is_file()) {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &missing.display().to_string(),
+            );
+            return Ok(());
+        }
+
+        // Show what the merge will do before asking
+        let relinks = self.storage.merge_relinks(&keep, &dup);
+        if self.narrate() && !self.assume_yes {
+            let merged = dedupe::merge_metadata(&keep_row, &dup_row);
+            let kept = NodeMetadata {
+                title: keep_row.title.clone(),
+                source_url: keep_row.source_url.clone(),
+                version: keep_row.version.clone(),
+                release_date: keep_row.release_date.clone(),
+                tags: keep_row.tags.clone(),
+                description: keep_row.description.clone(),
+                rating: keep_row.rating,
+                play_status: keep_row.play_status,
+                regions: keep_row.regions.clone(),
+                languages: keep_row.languages.clone(),
+            };
+            let fields = |metadata: &NodeMetadata| {
+                [
+                    (
+                        t!("field-version"),
+                        metadata.version.clone().unwrap_or_default(),
+                    ),
+                    (
+                        t!("field-source-url"),
+                        metadata.source_url.clone().unwrap_or_default(),
+                    ),
+                    (
+                        t!("field-released"),
+                        metadata.release_date.clone().unwrap_or_default(),
+                    ),
+                    (t!("field-tags"), metadata.tags.join(", ")),
+                    (
+                        t!("field-rating"),
+                        metadata.rating.map(|r| r.to_string()).unwrap_or_default(),
+                    ),
+                    (t!("field-status"), metadata.play_status.to_string()),
+                    (t!("field-region"), region::join_list(&metadata.regions)),
+                    (t!("field-language"), region::join_list(&metadata.languages)),
+                ]
+            };
+            outln!(
+                "{} {}",
+                theme::header(&t!("merging")),
+                t!(
+                    "title-into",
+                    from = theme::title(&dup_title),
+                    into = theme::title(&keep_title),
+                )
+            );
+            for ((label, old), (_, new)) in fields(&kept).into_iter().zip(fields(&merged)) {
+                if old != new {
+                    let old = if old.is_empty() { "-".to_string() } else { old };
+                    print_field(
+                        &label,
+                        &format!("{} {} {}", theme::dim(&old), theme::dim("->"), new),
+                    );
+                }
+            }
+            if merged.description != kept.description {
+                print_field(&t!("field-description"), &t!("from-the-duplicate"));
+            }
+            for sha256 in &relinks {
+                if let Some(node) = self.storage.get_node_by_hash(sha256) {
+                    print_field(
+                        &t!("field-link-to"),
+                        &format_display_title(&node.title, node.version.as_deref()),
+                    );
+                }
+            }
+        }
+        if !self.confirm(&t!("merge-prompt", from = dup_title, into = keep_title))? {
+            self.report_cancelled();
+            return Ok(());
+        }
+
+        let removed = self.node_json(&dup);
+        let result = match self.storage.merge_nodes(
+            &keep,
+            &dup,
+            sources,
+            &mut self.progress_line(),
+            interrupt::arm().token(),
+        ) {
+            Ok(r) => r,
+            Err(e) => {
+                self.report_failure(&t!("merge-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        if let Some(ref last) = self.last_added
+            && last.hash == dup
+        {
+            self.last_added = None;
+        }
+        for (path, reason) in &result.removed.diff_files_failed {
+            warn(t!(
+                "diff-file-not-deleted",
+                path = path.display(),
+                reason = reason,
+            ));
+        }
+
+        if self.json() {
+            output::print_json(&json!({
+                "kept": self.node_json(&keep),
+                "removed": removed,
+                "relinked": result
+                    .relinked
+                    .iter()
+                    .filter_map(|sha256| self.node_json(sha256))
+                    .collect::<Vec<_>>(),
+                "collections": result.collections,
+                "attachments_moved": result.attachments_moved,
+                "edges_removed": result.removed.edges_removed,
+            }));
+        } else if self.narrate() {
+            outln!(
+                "{} {}",
+                theme::success(&t!("merged")),
+                t!(
+                    "merged-into",
+                    from = dup_title,
+                    into = format_display_title(
+                        &result.metadata.title,
+                        result.metadata.version.as_deref()
+                    ),
+                    links = counted!(result.relinked.len(), "links-one", "links-other"),
+                )
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_build_all(
+        &self,
+        target: Option<&str>,
+        query: Option<&str>,
+        dir: &Path,
+        sources: &[PathBuf],
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let rows = match (target, query) {
+            (Some(target), _) => {
+                let Some(node) = self.resolve_node(target, rl).map(|n| n.sha256) else {
+                    return Ok(());
+                };
+                let mut rows = Vec::new();
+                for sha256 in self.storage.connected_component_hashes(&node) {
+                    rows.extend(self.storage.get_node_row_by_hash(&sha256)?);
+                }
+                rows.sort_by(|a, b| compare_titles(&a.title, &b.title));
+                rows
+            }
+            (None, query) => match NodeFilter::parse(query.unwrap_or_default()) {
+                Ok(filter) => self.storage.search(&filter)?,
+                Err(e) => {
+                    self.report_error(ExitStatus::Usage, &t!("invalid-search"), &e);
+                    return Ok(());
+                }
+            },
+        };
+        if let Some(missing) = sources.iter().find(|s| !s.is_file()) {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &missing.display().to_string(),
+            );
+            return Ok(());
+        }
+        let has_files = std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some());
+        if has_files && !self.confirm(&t!("replace-roms-in-folder", path = dir.display()))? {
+            self.report_cancelled();
+            return Ok(());
+        }
+
+        let builds = match self.storage.build_all(
+            &rows,
+            dir,
+            sources,
+            &mut self.progress_line(),
+            interrupt::arm().token(),
+        ) {
+            Ok(builds) => builds,
+            Err(e) => {
+                self.report_failure(&t!("build-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        let failed = builds.iter().filter(|b| b.error.is_some()).count();
+        if failed > 0 {
+            self.status.set(ExitStatus::Failure);
+        }
+
+        if self.json() {
+            let builds: Vec<_> = builds
+                .iter()
+                .map(|b| {
+                    json!({
+                        "node": self.node_json(&b.sha256),
+                        "path": dir.join(&b.path).display().to_string(),
+                        "error": b.error,
+                    })
+                })
+                .collect();
+            output::print_json(&json!({ "builds": builds }));
+            return Ok(());
+        }
+
+        let title_of = |sha256: &[u8; 32]| {
+            self.storage
+                .get_node_by_hash(sha256)
+                .map(|n| format_display_title(&n.title, n.version.as_deref()))
+                .unwrap_or_default()
+        };
+        for build in &builds {
+            match &build.error {
+                None if !self.quiet => outln!(
+                    "  {}   {}  {}",
+                    theme::success(&t!("status-built")),
+                    theme::title(&title_of(&build.sha256)),
+                    theme::dim(&build.path.display().to_string())
+                ),
+                None => {}
+                Some(error) => outln!(
+                    "  {}  {}  {}",
+                    theme::error(&t!("status-failed")),
+                    theme::title(&title_of(&build.sha256)),
+                    theme::dim(error)
+                ),
+            }
+        }
+        let written = builds.len() - failed;
+        if failed > 0 {
+            outln!(
+                "{} {}",
+                theme::error(&t!("failed")),
+                t!(
+                    "builds-failed",
+                    roms = counted!(failed, "roms-one", "roms-other"),
+                    total = builds.len(),
+                    written = written,
+                    path = dir.display(),
+                )
+            );
+        } else if self.narrate() {
+            outln!(
+                "{} {}",
+                theme::success(&t!("built")),
+                t!(
+                    "roms-to",
+                    roms = counted!(written, "roms-one", "roms-other"),
+                    path = dir.display(),
+                )
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_sdcard(
+        &self,
+        dir: &Path,
+        sources: &[PathBuf],
+        layout: SdLayout,
+        query: &str,
+    ) -> Result<()> {
+        let filter = match NodeFilter::parse(query) {
+            Ok(filter) => filter,
+            Err(e) => {
+                self.report_error(ExitStatus::Usage, &t!("invalid-search"), &e);
+                return Ok(());
+            }
+        };
+        if let Some(missing) = sources.iter().find(|s| !s.is_file()) {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &missing.display().to_string(),
+            );
+            return Ok(());
+        }
+        let has_files = std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some());
+        if has_files && !self.confirm(&t!("replace-roms-in-folder", path = dir.display()))? {
+            self.report_cancelled();
+            return Ok(());
+        }
+
+        let result = match self.storage.export_sd_card(
+            dir,
+            sources,
+            &filter,
+            layout,
+            &mut self.progress_line(),
+            interrupt::arm().token(),
+        ) {
+            Ok(r) => r,
+            Err(e) => {
+                self.report_failure(&t!("sdcard-export-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+
+        if self.json() {
+            let unreachable: Vec<_> = result
+                .unreachable
+                .iter()
+                .filter_map(|row| self.node_json(&row.sha256))
+                .collect();
+            output::print_json(&json!({
+                "written": result.written,
+                "unreachable": unreachable,
+            }));
+            return Ok(());
+        }
+        if self.quiet {
+            return Ok(());
+        }
+
+        outln!(
+            "{} {}",
+            theme::success(&t!("built")),
+            t!(
+                "roms-to",
+                roms = counted!(result.written.len(), "roms-one", "roms-other"),
+                path = dir.display(),
+            ),
+        );
+        if !result.unreachable.is_empty() {
+            outln!(
+                "{} {}",
+                theme::warning(&t!("skipped")),
+                t!(
+                    "roms-not-linked",
+                    roms = counted!(result.unreachable.len(), "roms-one", "roms-other"),
+                ),
+            );
+            for row in &result.unreachable {
+                outln!(
+                    "  {}  {}",
+                    format_display_title(&row.title, row.version.as_deref()),
+                    theme::styled_hash(&format_hash(&row.sha256)[..16])
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn cmd_validate_all(&self, sources: &[PathBuf]) -> Result<()> {
+        if let Some(missing) = sources.iter().find(|s| !s.is_file()) {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &missing.display().to_string(),
+            );
+            return Ok(());
+        }
+        let result = match self.storage.validate_all(
+            sources,
+            &mut self.progress_line(),
+            interrupt::arm().token(),
+        ) {
+            Ok(r) => r,
+            Err(e) => {
+                self.report_failure(&t!("validation-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        if !result.broken.is_empty() {
+            self.status.set(ExitStatus::VerificationFailed);
+        }
+
+        if self.json() {
+            let rows_json = |rows: &[NodeRow]| -> Vec<_> {
+                rows.iter()
+                    .filter_map(|row| self.node_json(&row.sha256))
+                    .collect()
+            };
+            let broken: Vec<_> = result
+                .broken
+                .iter()
+                .map(|edge| {
+                    json!({
+                        "from": self.node_json(&edge.from),
+                        "to": self.node_json(&edge.to),
+                        "diff_path": edge.diff_path,
+                        "reason": edge.reason,
+                    })
+                })
+                .collect();
+            output::print_json(&json!({
+                "verified": result.verified.len(),
+                "edges_checked": result.edges_checked,
+                "broken": broken,
+                "unbuilt": rows_json(&result.unbuilt),
+                "unreachable": rows_json(&result.unreachable),
+            }));
+            return Ok(());
+        }
+
+        let list_rows = |rows: &[NodeRow]| {
+            for row in rows {
+                outln!(
+                    "  {}  {}",
+                    format_display_title(&row.title, row.version.as_deref()),
+                    theme::styled_hash(&format_hash(&row.sha256)[..16])
+                );
+            }
+        };
+        if !result.broken.is_empty() {
+            outln!(
+                "{} {}",
+                theme::error(&t!("broken")),
+                t!(
+                    "links-failed-to-rebuild",
+                    links = counted!(result.broken.len(), "links-one", "links-other"),
+                ),
+            );
+            for edge in &result.broken {
+                self.print_link_problem(&edge.from, &edge.to, &edge.diff_path, &edge.reason);
+            }
+        }
+        if !result.unbuilt.is_empty() {
+            outln!(
+                "{} {}",
+                theme::error(&t!("unbuilt")),
+                t!(
+                    "roms-behind-broken-links",
+                    roms = counted!(result.unbuilt.len(), "roms-one", "roms-other"),
+                ),
+            );
+            list_rows(&result.unbuilt);
+        }
+        if !result.unreachable.is_empty() && !self.quiet {
+            outln!(
+                "{} {}",
+                theme::warning(&t!("skipped")),
+                t!(
+                    "roms-not-linked",
+                    roms = counted!(result.unreachable.len(), "roms-one", "roms-other"),
+                ),
+            );
+            list_rows(&result.unreachable);
+        }
+        if result.broken.is_empty() && !self.quiet {
+            outln!(
+                "{} {}",
+                theme::success(&t!("valid")),
+                t!(
+                    "roms-verified-through",
+                    roms = counted!(result.verified.len(), "roms-one", "roms-other"),
+                    links = counted!(result.edges_checked, "links-one", "links-other"),
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    /// Resolve two ROMs and get their data from `sources` or recorded
+    /// locations, building through links where needed. None once a problem
+    /// has been reported, under `failure` if getting the data failed.
+    fn load_node_pair(
+        &self,
+        a: &str,
+        b: &str,
+        sources: &[PathBuf],
+        failure: &str,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<Option<NodePair>> {
+        let Some(a) = self.resolve_node(a, rl).map(|n| n.sha256) else {
+            return Ok(None);
+        };
+        let Some(b) = self.resolve_node(b, rl).map(|n| n.sha256) else {
+            return Ok(None);
+        };
+        if let Some(missing) = sources.iter().find(|s| !s.is_file()) {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &missing.display().to_string(),
+            );
+            return Ok(None);
+        }
+        let data = |sha256: &[u8; 32]| {
+            self.storage.node_bytes(
+                sha256,
+                sources,
+                &mut self.progress_line(),
+                interrupt::arm().token(),
+            )
+        };
+        let (data_a, data_b) = match data(&a).and_then(|data_a| Ok((data_a, data(&b)?))) {
+            Ok(data) => data,
+            Err(e) => {
+                self.report_failure(failure, &e.to_string(), &e);
+                return Ok(None);
+            }
+        };
+        let header = self
+            .storage
+            .get_node_row_by_hash(&a)?
+            .and_then(|row| row.source_file_header)
+            .and_then(|h| parse_nes_header_bytes(h.get(..16)?.try_into().ok()?));
+        Ok(Some(NodePair {
+            a,
+            b,
+            data_a,
+            data_b,
+            header,
+        }))
+    }
+
+    /// Print which two ROMs are being compared, as `A:` and `B:` fields.
+    fn print_node_pair(&self, a: &[u8; 32], b: &[u8; 32]) {
+        for (label, sha256) in [(t!("field-a"), a), (t!("field-b"), b)] {
+            if let Some(node) = self.storage.get_node_by_hash(sha256) {
+                print_field(
+                    &label,
+                    &format!(
+                        "{}  {}",
+                        theme::title(&format_display_title(&node.title, node.version.as_deref())),
+                        theme::styled_hash(&format_hash(sha256)[..16])
+                    ),
+                );
+            }
+        }
+    }
+
+    fn cmd_inspect(
+        &self,
+        a: &str,
+        b: &str,
+        sources: &[PathBuf],
+        all: bool,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        const ROW: usize = 8;
+        const MAX_ROWS: usize = 64;
+        let Some(NodePair {
+            a,
+            b,
+            data_a,
+            data_b,
+            header,
+        }) = self.load_node_pair(a, b, sources, &t!("inspect-failed"), rl)?
+        else {
+            return Ok(());
+        };
+        let ranges = compare::changed_ranges(&data_a, &data_b);
+
+        if self.json() {
+            let hex_of = |data: &[u8], range: &std::ops::Range<usize>| {
+                hex::encode(
+                    data.get(range.start..range.end.min(data.len()))
+                        .unwrap_or(&[]),
+                )
+            };
+            let regions: Vec<_> = ranges
+                .iter()
+                .map(|range| {
+                    json!({
+                        "start": range.start,
+                        "end": range.end,
+                        "area": compare::RomArea::at(range.start, header.as_ref()).to_string(),
+                        "a": hex_of(&data_a, range),
+                        "b": hex_of(&data_b, range),
+                    })
+                })
+                .collect();
+            output::print_json(&json!({
+                "a": self.node_json(&a),
+                "b": self.node_json(&b),
+                "len_a": data_a.len(),
+                "len_b": data_b.len(),
+                "regions": regions,
+            }));
+            return Ok(());
+        }
+
+        self.print_node_pair(&a, &b);
+        if ranges.is_empty() {
+            outln!(
+                "{} {}",
+                theme::success(&t!("identical")),
+                t!("same-rom-data")
+            );
+            return Ok(());
+        }
+        let rows: Vec<Vec<usize>> = ranges
+            .iter()
+            .map(|range| (range.start / ROW * ROW..range.end).step_by(ROW).collect())
+            .collect();
+        let total_rows: usize = rows.iter().map(Vec::len).sum();
+        outln!(
+            "{} {}, {}",
+            theme::info(&t!("differs")),
+            counted!(ranges.len(), "regions-one", "regions-other"),
+            counted!(total_rows, "rows-one", "rows-other"),
+        );
+
+        let hex_row = |data: &[u8], other: &[u8], start: usize| {
+            (start..start + ROW)
+                .map(|i| match (data.get(i), other.get(i)) {
+                    (Some(byte), Some(o)) if byte == o => format!("{:02x}", byte),
+                    (Some(byte), _) => theme::warning(&format!("{:02x}", byte)),
+                    (None, _) => "  ".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        outln!(
+            "  {}",
+            theme::dim(&format!(
+                "{:<8}  {:<11}  {:<w$}  {}",
+                t!("column-offset"),
+                t!("column-area"),
+                "A",
+                "B",
+                w = ROW * 3 - 1
+            ))
+        );
+        let mut shown = 0;
+        for (i, region) in rows.iter().enumerate() {
+            if i > 0 {
+                outln!("  {}", theme::dim("..."));
+            }
+            for &start in region {
+                if shown == MAX_ROWS && !all {
+                    outln!("  {}", t!("more-rows", count = total_rows - shown));
+                    return Ok(());
+                }
+                outln!(
+                    "  {:08x}  {:<11}  {}  {}",
+                    start,
+                    compare::RomArea::at(start, header.as_ref()).to_string(),
+                    hex_row(&data_a, &data_b, start),
+                    hex_row(&data_b, &data_a, start),
+                );
+                shown += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn cmd_banks(
+        &self,
+        a: &str,
+        b: &str,
+        sources: &[PathBuf],
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        /// Banks per heatmap line
+        const WIDTH: usize = 32;
+        let Some(pair) = self.load_node_pair(a, b, sources, &t!("banks-failed"), rl)? else {
+            return Ok(());
+        };
+        let summaries = compare::area_summaries(&pair.data_a, &pair.data_b, pair.header.as_ref());
+
+        if self.json() {
+            let areas: Vec<_> = summaries
+                .iter()
+                .map(|s| {
+                    json!({
+                        "area": s.area.to_string(),
+                        "len": s.len,
+                        "changed_bytes": s.changed_bytes,
+                    })
+                })
+                .collect();
+            output::print_json(&json!({
+                "a": self.node_json(&pair.a),
+                "b": self.node_json(&pair.b),
+                "len_a": pair.data_a.len(),
+                "len_b": pair.data_b.len(),
+                "areas": areas,
+            }));
+            return Ok(());
+        }
+
+        self.print_node_pair(&pair.a, &pair.b);
+        let changed: Vec<_> = summaries.iter().filter(|s| s.changed_bytes > 0).collect();
+        if changed.is_empty() {
+            outln!(
+                "{} {}",
+                theme::success(&t!("identical")),
+                t!("same-rom-data")
+            );
+            return Ok(());
+        }
+
+        if !theme::plain() {
+            // One cell per bank, shaded by how much of it changed
+            let cell = |s: &compare::AreaSummary| match s.changed_fraction() {
+                0.0 => theme::dim("."),
+                f if f <= 0.25 => theme::warning("░"),
+                f if f <= 0.5 => theme::warning("▒"),
+                f if f <= 0.75 => theme::warning("▓"),
+                _ => theme::warning("█"),
+            };
+            let kind = |area: compare::RomArea| match area {
+                compare::RomArea::Prg(_) => "PRG",
+                compare::RomArea::Chr(_) => "CHR",
+                compare::RomArea::Block(_) => "Other",
+            };
+            for group in summaries.chunk_by(|x, y| kind(x.area) == kind(y.area)) {
+                for (i, line) in group.chunks(WIDTH).enumerate() {
+                    let label = match (i, kind(group[0].area)) {
+                        (0, "Other") => t!("area-other"),
+                        (0, kind) => kind.to_string(),
+                        _ => String::new(),
+                    };
+                    outln!(
+                        "  {:<6}{}",
+                        theme::label(&label),
+                        line.iter().map(cell).collect::<String>()
+                    );
+                }
+            }
+            outln!("  {}", theme::dim(&t!("heatmap-key")));
+        }
+
+        let changed_bytes: usize = changed.iter().map(|s| s.changed_bytes).sum();
+        print_field(
+            &t!("field-changed"),
+            &t!(
+                "bytes-in-banks",
+                bytes = counted!(changed_bytes, "bytes-one", "bytes-other"),
+                count = changed.len(),
+                banks = counted!(summaries.len(), "banks-one", "banks-other"),
+            ),
+        );
+        for s in &changed {
+            let percent = s.changed_fraction() * 100.0;
+            let percent = if percent < 0.1 {
+                "<0.1%".to_string()
+            } else {
+                format!("{:.1}%", percent)
+            };
+            outln!(
+                "    {:<12}{:>7} {}  {}",
+                s.area.to_string(),
+                s.changed_bytes,
+                t!("changed"),
+                theme::dim(&percent)
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_compare(&self, a: &Path, b: &Path) -> Result<()> {
+        if let Some(missing) = [a, b].into_iter().find(|f| !f.is_file()) {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("file-not-found"),
+                &missing.display().to_string(),
+            );
+            return Ok(());
+        }
+        let comparison = match compare::compare_files(a, b) {
+            Ok(c) => c,
+            Err(e) => {
+                self.report_failure(&t!("compare-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+
+        if self.json() {
+            let file_json = |path: &Path, metadata: &RomMetadata| {
+                json!({
+                    "path": path.display().to_string(),
+                    "sha256": format_hash(&metadata.sha256),
+                    "node": self.node_json(&metadata.sha256),
+                })
+            };
+            let areas: Vec<_> = comparison
+                .areas
+                .iter()
+                .map(|c| {
+                    json!({
+                        "area": c.area.to_string(),
+                        "changed_bytes": c.changed_bytes,
+                        "first": c.first,
+                        "last": c.last,
+                    })
+                })
+                .collect();
+            output::print_json(&json!({
+                "a": file_json(a, &comparison.a),
+                "b": file_json(b, &comparison.b),
+                "identical": comparison.identical(),
+                "headers_match": comparison.headers_match(),
+                "len_a": comparison.len_a,
+                "len_b": comparison.len_b,
+                "changed_bytes": comparison.changed_bytes,
+                "changed_ranges": comparison.changed_ranges,
+                "areas": areas,
+                "diff_size": comparison.diff_size,
+                "likely_related": comparison.likely_related(),
+            }));
+            return Ok(());
+        }
+
+        for (label, path, metadata) in [
+            (t!("field-a"), a, &comparison.a),
+            (t!("field-b"), b, &comparison.b),
+        ] {
+            let known = self
+                .storage
+                .get_node_by_hash(&metadata.sha256)
+                .map(|n| {
+                    format!(
+                        "  {}",
+                        theme::title(&format_display_title(&n.title, n.version.as_deref()))
+                    )
+                })
+                .unwrap_or_default();
+            print_field(
+                &label,
+                &format!(
+                    "{}  {}{}",
+                    path.display(),
+                    theme::styled_hash(&format_hash(&metadata.sha256)[..16]),
+                    known
+                ),
+            );
+        }
+        if comparison.identical() {
+            let data = if comparison.headers_match() {
+                t!("same-rom-data-same-header")
+            } else {
+                t!("same-rom-data-different-header")
+            };
+            outln!("{} {}", theme::success(&t!("identical")), data);
+            return Ok(());
+        }
+
+        let delta = comparison.len_b as i64 - comparison.len_a as i64;
+        print_field(
+            &t!("field-size"),
+            &match delta {
+                0 => t!("size-same", size = format_size(comparison.len_a as i64)),
+                _ => format!(
+                    "{} -> {} ({}{})",
+                    format_size(comparison.len_a as i64),
+                    format_size(comparison.len_b as i64),
+                    if delta > 0 { "+" } else { "-" },
+                    format_size(delta.abs())
+                ),
+            },
+        );
+        let header = if comparison.headers_match() {
+            t!("same")
+        } else {
+            t!("different")
+        };
+        print_field(&t!("field-header"), &header);
+        print_field(
+            &t!("field-changed"),
+            &t!(
+                "bytes-in-ranges",
+                bytes = counted!(comparison.changed_bytes, "bytes-one", "bytes-other"),
+                ranges = counted!(comparison.changed_ranges, "ranges-one", "ranges-other"),
+            ),
+        );
+        for changes in &comparison.areas {
+            outln!(
+                "    {:<12}{:>7} {}  {}",
+                changes.area.to_string(),
+                changes.changed_bytes,
+                t!("changed"),
+                theme::dim(&format!("0x{:06X}-0x{:06X}", changes.first, changes.last))
+            );
+        }
+        let percent = comparison.diff_size as f64 * 100.0 / comparison.len_b.max(1) as f64;
+        print_field(
+            &t!("field-diff-size"),
+            &t!(
+                "diff-size-of-b",
+                size = format_size(comparison.diff_size as i64),
+                percent = format!("{:.1}", percent),
+            ),
+        );
+        if comparison.likely_related() {
+            outln!(
+                "{} {}",
+                theme::success(&t!("related")),
+                t!("likely-related")
+            );
+        } else {
+            outln!(
+                "{} {}",
+                theme::warning(&t!("unrelated")),
+                t!("likely-unrelated")
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_check_diffs(&self) -> Result<()> {
+        let result = match self
+            .storage
+            .check_diffs(&mut self.progress_line(), interrupt::arm().token())
+        {
+            Ok(r) => r,
+            Err(e) => {
+                self.report_failure(&t!("diff-check-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        if !result.problems.is_empty() {
+            self.status.set(ExitStatus::VerificationFailed);
+        }
+
+        if self.json() {
+            let problems: Vec<_> = result
+                .problems
+                .iter()
+                .map(|p| {
+                    json!({
+                        "from": self.node_json(&p.from),
+                        "to": self.node_json(&p.to),
+                        "diff_path": p.diff_path,
+                        "problem": p.problem,
+                    })
+                })
+                .collect();
+            output::print_json(&json!({
+                "checked": result.checked,
+                "checksums_recorded": result.checksums_recorded,
+                "problems": problems,
+            }));
+            return Ok(());
+        }
+
+        if !result.problems.is_empty() {
+            outln!(
+                "{} {}",
+                theme::error(&t!("damaged")),
+                t!(
+                    "diff-files-of",
+                    files = counted!(result.problems.len(), "diff-files-one", "diff-files-other"),
+                    total = result.checked,
+                ),
+            );
+            for p in &result.problems {
+                self.print_link_problem(&p.from, &p.to, &p.diff_path, &p.problem);
+            }
+        } else if !self.quiet {
+            outln!(
+                "{} {}",
+                theme::success(&t!("healthy")),
+                t!(
+                    "diff-files-checked",
+                    files = counted!(result.checked, "diff-files-one", "diff-files-other"),
+                ),
+            );
+        }
+        if result.checksums_recorded > 0 && !self.quiet {
+            outln!(
+                "{}",
+                counted!(
+                    result.checksums_recorded,
+                    "checksums-recorded-one",
+                    "checksums-recorded-other"
+                )
+            );
+        }
+        Ok(())
+    }
+
+    /// Print a link by its ROMs' titles, with its diff file and what's wrong
+    fn print_link_problem(&self, from: &[u8; 32], to: &[u8; 32], diff_path: &str, problem: &str) {
+        let title_of = |sha256: &[u8; 32]| {
+            self.storage
+                .get_node_by_hash(sha256)
+                .map(|n| format_display_title(&n.title, n.version.as_deref()))
+                .unwrap_or_else(|| format_hash(sha256)[..16].to_string())
+        };
+        outln!(
+            "  {} -> {}  {}",
+            title_of(from),
+            title_of(to),
+            theme::dim(diff_path)
+        );
+        outln!("    {}", problem);
+    }
+
+    fn cmd_checksums(&self, dir: &Path, format: ChecksumFormat) -> Result<()> {
+        if !dir.is_dir() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("folder-not-found"),
+                &dir.display().to_string(),
+            );
+            return Ok(());
+        }
+        let path = dir.join(format.file_name());
+        if path.exists() && !self.confirm(&t!("replace-file", path = path.display()))? {
+            self.report_cancelled();
+            return Ok(());
+        }
+        let entries = match checksums::checksum_folder(
+            dir,
+            format,
+            &mut self.progress_line(),
+            interrupt::arm().token(),
+        ) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.report_failure(&t!("checksums-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        if let Err(e) = std::fs::write(&path, checksums::render(&entries, format))
+            .map_err(DromosError::file(&path))
+        {
+            self.report_failure(&t!("checksums-failed"), &e.to_string(), &e);
+            return Ok(());
+        }
+
+        if self.json() {
+            output::print_json(&json!({
+                "path": path.display().to_string(),
+                "format": format.to_string(),
+                "files": entries
+                    .iter()
+                    .map(|e| json!({ "path": e.path, "checksum": e.checksum }))
+                    .collect::<Vec<_>>(),
+            }));
+        } else if self.narrate() {
+            outln!(
+                "{} {} ({})",
+                theme::success(&t!("wrote")),
+                path.display(),
+                counted!(entries.len(), "files-one", "files-other")
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_locate(&self, dir: &Path, record: bool) -> Result<()> {
+        if !dir.is_dir() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("folder-not-found"),
+                &dir.display().to_string(),
+            );
+            return Ok(());
+        }
+        let located = match self.storage.locate(
+            dir,
+            record,
+            &mut self.progress_line(),
+            interrupt::arm().token(),
+        ) {
+            Ok(located) => located,
+            Err(e) => {
+                self.report_failure(&t!("locate-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+
+        if self.json() {
+            let files: Vec<_> = located
+                .iter()
+                .map(|f| {
+                    json!({
+                        "path": f.path.display().to_string(),
+                        "match": match f.matched {
+                            LocateMatch::Exact => "exact",
+                            LocateMatch::HeaderDiffers => "header_differs",
+                            LocateMatch::Unknown => "unknown",
+                            LocateMatch::Unreadable => "unreadable",
+                        },
+                        "sha256": f.sha256.as_ref().map(format_hash),
+                        "node": f.node.as_ref().and_then(|n| self.node_json(&n.sha256)),
+                        "error": f.error,
+                    })
+                })
+                .collect();
+            output::print_json(&json!({ "files": files, "recorded": record }));
+            return Ok(());
+        }
+
+        let count = |matched: LocateMatch| located.iter().filter(|f| f.matched == matched).count();
+        let recorded = count(LocateMatch::Exact) + count(LocateMatch::HeaderDiffers);
+        outln!(
+            "{} {}",
+            theme::info(&t!("located")),
+            t!(
+                "located-summary",
+                exact = count(LocateMatch::Exact),
+                header = count(LocateMatch::HeaderDiffers),
+                unknown = count(LocateMatch::Unknown),
+                unreadable = count(LocateMatch::Unreadable),
+            ),
+        );
+        for file in &located {
+            let path = theme::dim(&file.path.display().to_string());
+            let title = file
+                .node
+                .as_ref()
+                .map(|n| theme::title(&format_display_title(&n.title, n.version.as_deref())));
+            let hash = file
+                .sha256
+                .map(|h| theme::styled_hash(&format_hash(&h)[..16]));
+            match (file.matched, title, hash) {
+                (LocateMatch::Exact, Some(title), Some(hash)) => {
+                    outln!(
+                        "  {}    {}  {}  {}",
+                        theme::success(&t!("status-exact")),
+                        title,
+                        hash,
+                        path
+                    )
+                }
+                (LocateMatch::HeaderDiffers, Some(title), Some(hash)) => {
+                    outln!(
+                        "  {}   {}  {}  {}",
+                        theme::warning(&t!("status-header")),
+                        title,
+                        hash,
+                        path
+                    )
+                }
+                (LocateMatch::Unknown, _, Some(hash)) => {
+                    outln!(
+                        "  {}  {}  {}",
+                        theme::dim(&t!("status-unknown")),
+                        hash,
+                        path
+                    )
+                }
+                _ => outln!(
+                    "  {}    {}  {}",
+                    theme::error(&t!("status-error")),
+                    file.path.display(),
+                    theme::dim(file.error.as_deref().unwrap_or_default())
+                ),
+            }
+        }
+        if record && recorded > 0 && self.narrate() {
+            outln!(
+                "{} {}",
+                theme::success(&t!("recorded")),
+                t!(
+                    "locations-recorded",
+                    locations = counted!(recorded, "locations-one", "locations-other"),
+                )
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_scan(
+        &mut self,
+        dir: &Path,
+        prompt_ambiguous: bool,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        if !dir.is_dir() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("folder-not-found"),
+                &dir.display().to_string(),
+            );
+            return Ok(());
+        }
+
+        // Classify every ROM file as known, found (a wishlist ROM, recorded as
+        // it's seen), new, duplicate (same content as an earlier new file in
+        // this scan), or unreadable
+        let files = find_rom_files(dir)?;
+        let mut known: Vec<(PathBuf, [u8; 32])> = Vec::new();
+        let mut found: Vec<(PathBuf, [u8; 32])> = Vec::new();
+        let mut new: Vec<ScanCandidate> = Vec::new();
+        let mut duplicates: Vec<PathBuf> = Vec::new();
+        let mut failed: Vec<(PathBuf, String)> = Vec::new();
+        let mut seen: HashSet<[u8; 32]> = HashSet::new();
+
+        let hashed = hash_rom_files(&files, &mut self.progress_line(), interrupt::arm().token())?;
+        for (file, hashed) in files.into_iter().zip(hashed) {
+            match hashed {
+                Ok(metadata) if self.storage.node_exists(&metadata.sha256) => {
+                    if self.storage.record_file(&metadata)? {
+                        found.push((file, metadata.sha256));
+                    } else {
+                        known.push((file, metadata.sha256));
+                    }
+                }
+                Ok(metadata) => {
+                    if seen.insert(metadata.sha256) {
+                        let name = name_from_filename(&file);
+                        new.push(ScanCandidate {
+                            path: file,
+                            metadata,
+                            name,
+                            ambiguous: false,
+                        });
+                    } else {
+                        duplicates.push(file);
+                    }
+                }
+                Err(e) => failed.push((file, e.to_string())),
+            }
+        }
+
+        // A derived title is ambiguous if it collides with a title already in
+        // the library or with another new file from this scan, version included
+        let (nodes, _) = self.storage.list();
+        let existing_titles: HashSet<String> = nodes
+            .iter()
+            .map(|n| format_display_title(&n.title, n.version.as_deref()).to_lowercase())
+            .collect();
+        let display_title =
+            |name: &FileName| format_display_title(&name.title, name.version.as_deref());
+        let new_titles: Vec<String> = new
+            .iter()
+            .map(|c| display_title(&c.name).to_lowercase())
+            .collect();
+        for candidate in &mut new {
+            let lower = display_title(&candidate.name).to_lowercase();
+            candidate.ambiguous = existing_titles.contains(&lower)
+                || new_titles.iter().filter(|t| **t == lower).count() > 1;
+        }
+
+        if !self.json() {
+            let duplicates_text = counted!(duplicates.len(), "duplicates-one", "duplicates-other");
+            let summary = if found.is_empty() {
+                t!(
+                    "scan-summary",
+                    known = known.len(),
+                    new = new.len(),
+                    duplicates = duplicates_text,
+                    unreadable = failed.len(),
+                )
+            } else {
+                t!(
+                    "scan-summary-found",
+                    known = known.len(),
+                    found = found.len(),
+                    new = new.len(),
+                    duplicates = duplicates_text,
+                    unreadable = failed.len(),
+                )
+            };
+            outln!("{} {}", theme::info(&t!("scanned")), summary);
+            for (path, hash) in &known {
+                let title = self
+                    .storage
+                    .get_node_by_hash(hash)
+                    .map(|n| format_display_title(&n.title, n.version.as_deref()))
+                    .unwrap_or_default();
+                outln!(
+                    "  {}  {}  {}  {}",
+                    theme::dim(&t!("status-known")),
+                    theme::title(&title),
+                    theme::styled_hash(&format_hash(hash)[..16]),
+                    theme::dim(&path.display().to_string())
+                );
+            }
+            for (path, hash) in &found {
+                let title = self
+                    .storage
+                    .get_node_by_hash(hash)
+                    .map(|n| format_display_title(&n.title, n.version.as_deref()))
+                    .unwrap_or_default();
+                outln!(
+                    "  {}  {}  {}  {}",
+                    theme::success(&t!("status-found")),
+                    theme::title(&title),
+                    theme::styled_hash(&format_hash(hash)[..16]),
+                    theme::dim(&path.display().to_string())
+                );
+            }
+            for candidate in &new {
+                outln!(
+                    "  {}    {}{}  {}  {}",
+                    theme::success(&t!("status-new")),
+                    theme::title(&format_display_title(
+                        &candidate.name.title,
+                        candidate.name.version.as_deref()
+                    )),
+                    if candidate.ambiguous {
+                        format!("  {}", theme::warning(&t!("ambiguous-title-note")))
+                    } else {
+                        String::new()
+                    },
+                    theme::styled_hash(&format_hash(&candidate.metadata.sha256)[..16]),
+                    theme::dim(&candidate.path.display().to_string())
+                );
+            }
+            for path in &duplicates {
+                outln!(
+                    "  {}    {}",
+                    theme::dim(&t!("status-dup")),
+                    theme::dim(&path.display().to_string())
+                );
+            }
+            for (path, error) in &failed {
+                outln!(
+                    "  {}  {}  {}",
+                    theme::error(&t!("status-error")),
+                    path.display(),
+                    theme::dim(error)
+                );
+            }
+        }
+
+        let mut added = 0;
+        if !new.is_empty() {
+            if !self.confirm(&counted!(
+                new.len(),
+                "add-new-roms-one",
+                "add-new-roms-other"
+            ))? {
+                self.report_cancelled();
+                return Ok(());
+            }
+
+            for candidate in &new {
+                let suggested = self.suggest_metadata(&candidate.metadata, candidate.name.clone());
+                let node_metadata = if candidate.ambiguous && prompt_ambiguous {
+                    output::write_prompt(format_args!(
+                        "{} {}\n",
+                        theme::info(&t!("adding-file")),
+                        candidate.path.display()
+                    ))?;
+                    self.prompt_metadata(rl, &suggested)?
+                } else {
+                    suggested
+                };
+
+                match self.storage.add_node(&candidate.path, &node_metadata) {
+                    Ok(_) => {
+                        added += 1;
+                        self.last_added = Some(LastAdded {
+                            hash: candidate.metadata.sha256,
+                            title: node_metadata.title,
+                            version: node_metadata.version,
+                        });
+                    }
+                    Err(e) => {
+                        self.report_failure(
+                            &t!("failed-to-add"),
+                            &format!("{} ({})", candidate.path.display(), e),
+                            &e,
+                        );
+                    }
+                }
+            }
+        }
+
+        if self.json() {
+            let known_json: Vec<_> = known
+                .iter()
+                .map(|(path, hash)| {
+                    json!({ "path": path.display().to_string(), "node": self.node_json(hash) })
+                })
+                .collect();
+            let found_json: Vec<_> = found
+                .iter()
+                .map(|(path, hash)| {
+                    json!({ "path": path.display().to_string(), "node": self.node_json(hash) })
+                })
+                .collect();
+            let new_json: Vec<_> = new
+                .iter()
+                .map(|c| {
+                    json!({
+                        "path": c.path.display().to_string(),
+                        "sha256": format_hash(&c.metadata.sha256),
+                        "title": c.name.title,
+                        "version": c.name.version,
+                        "ambiguous": c.ambiguous,
+                    })
+                })
+                .collect();
+            let failed_json: Vec<_> = failed
+                .iter()
+                .map(|(path, error)| json!({ "path": path.display().to_string(), "error": error }))
+                .collect();
+            let duplicates_json: Vec<String> =
+                duplicates.iter().map(|p| p.display().to_string()).collect();
+            output::print_json(&json!({
+                "known": known_json,
+                "found": found_json,
+                "new": new_json,
+                "duplicates": duplicates_json,
+                "errors": failed_json,
+                "added": added,
+            }));
+        } else if self.narrate() && added > 0 {
+            outln!(
+                "{} {}",
+                theme::success(&t!("added")),
+                counted!(added, "roms-one", "roms-other")
+            );
+        }
+
+        Ok(())
+    }
+
+    fn cmd_watch(&mut self, dir: &Path, link_base: Option<&Path>) -> Result<()> {
+        if !dir.is_dir() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("folder-not-found"),
+                &dir.display().to_string(),
+            );
+            return Ok(());
+        }
+
+        // New ROMs can only be linked to a base that is already in the library
+        if let Some(base) = link_base {
+            if !base.exists() {
+                self.report_error(
+                    ExitStatus::NotFound,
+                    &t!("file-not-found"),
+                    &base.display().to_string(),
+                );
+                return Ok(());
+            }
+            let base_metadata = hash_rom_file(base)?;
+            if !self.storage.node_exists(&base_metadata.sha256) {
+                self.report_error(
+                    ExitStatus::NotFound,
+                    &t!("base-rom-not-in-database"),
+                    &base.display().to_string(),
+                );
+                return Ok(());
+            }
+        }
+
+        let mut watcher = FolderWatcher::start(dir)?;
+        #[cfg(feature = "server")]
+        let webhooks = self.start_webhooks();
+        if self.narrate() {
+            outln!(
+                "{} {} {}",
+                theme::info(&t!("watching")),
+                dir.display(),
+                t!("press-enter-to-stop")
+            );
+        }
+
+        let mut added = 0;
+        while let Some(batch) = watcher.next_batch() {
+            for path in batch {
+                let metadata = match hash_rom_file(&path) {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        self.report_failure(
+                            &t!("failed-to-hash"),
+                            &format!("{} ({})", path.display(), e),
+                            &e,
+                        );
+                        continue;
+                    }
+                };
+
+                if self.storage.node_exists(&metadata.sha256) {
+                    let found = self.storage.record_file(&metadata)?;
+                    if self.json() {
+                        let key = if found { "found" } else { "known" };
+                        output::print_json(&json!({
+                            "path": path.display().to_string(),
+                            key: self.node_json(&metadata.sha256),
+                        }));
+                    } else if found && self.narrate() {
+                        let title = self
+                            .storage
+                            .get_node_by_hash(&metadata.sha256)
+                            .map(|n| format_display_title(&n.title, n.version.as_deref()))
+                            .unwrap_or_default();
+                        outln!(
+                            "  {}  {}  {}",
+                            theme::success(&t!("status-found")),
+                            theme::title(&title),
+                            theme::dim(&path.display().to_string())
+                        );
+                    } else if self.narrate() {
+                        outln!(
+                            "  {}  {}",
+                            theme::dim(&t!("status-known")),
+                            theme::dim(&path.display().to_string())
+                        );
+                    }
+                    continue;
+                }
+
+                let node_metadata = self.suggest_metadata(&metadata, name_from_filename(&path));
+                if let Err(e) = self.storage.add_node(&path, &node_metadata) {
+                    self.report_failure(
+                        &t!("failed-to-add"),
+                        &format!("{} ({})", path.display(), e),
+                        &e,
+                    );
+                    continue;
+                }
+                added += 1;
+                self.last_added = Some(LastAdded {
+                    hash: metadata.sha256,
+                    title: node_metadata.title.clone(),
+                    version: node_metadata.version.clone(),
+                });
+
+                let mut linked = false;
+                if let Some(base) = link_base {
+                    match self.storage.link_nodes(
+                        base,
+                        &path,
+                        None,
+                        &mut self.progress_line(),
+                        interrupt::arm().token(),
+                    ) {
+                        Ok(_) => linked = true,
+                        Err(e) => self.report_failure(
+                            &t!("failed-to-link"),
+                            &format!("{} ({})", path.display(), e),
+                            &e,
+                        ),
+                    }
+                }
+
+                if self.json() {
+                    output::print_json(&json!({
+                        "path": path.display().to_string(),
+                        "added": self.node_json(&metadata.sha256),
+                        "linked": linked,
+                    }));
+                } else if !self.quiet {
+                    outln!(
+                        "  {}  {}  {}{}",
+                        theme::success(&t!("status-added")),
+                        theme::title(&node_metadata.title),
+                        theme::styled_hash(&format_hash(&metadata.sha256)[..16]),
+                        if linked {
+                            format!("  {}", theme::dim(&t!("linked-note")))
+                        } else {
+                            String::new()
+                        }
+                    );
+                }
+            }
+            #[cfg(feature = "server")]
+            if let Some((webhooks, _)) = &webhooks {
+                report_webhook_failures(&webhooks.failures());
+            }
+        }
+        #[cfg(feature = "server")]
+        self.stop_webhooks(webhooks);
+
+        if self.narrate() {
+            outln!(
+                "{} {}",
+                theme::info(&t!("stopped")),
+                t!(
+                    "added-while-watching",
+                    roms = counted!(added, "roms-one", "roms-other"),
+                )
+            );
+        }
+
+        Ok(())
+    }
+
+    fn cmd_source(
+        &mut self,
+        file: &Path,
+        continue_on_error: bool,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let script = match std::fs::read_to_string(file) {
+            Ok(s) => s,
+            Err(e) => {
+                let status = ExitStatus::from_error(&e.into());
+                self.report_error(
+                    status,
+                    &t!("cannot-read-script"),
+                    &file.display().to_string(),
+                );
+                return Ok(());
+            }
+        };
+        if self.source_depth >= MAX_SOURCE_DEPTH {
+            self.report_error(
+                ExitStatus::Failure,
+                &t!("scripts-nested-too-deeply"),
+                &file.display().to_string(),
+            );
+            return Ok(());
+        }
+
+        // The script's outcome is that of its first failing command
+        let mut first_failure: Option<ExitStatus> = None;
+        self.source_depth += 1;
+        for (i, line) in script.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if self.narrate() {
+                outln!("{}", theme::dim(&format!("> {}", trimmed)));
+            }
+
+            let location = format!("{}:{}:", file.display(), i + 1);
+            let keep_going = match self.run_line(trimmed, Some(&location), rl) {
+                Ok(keep_going) => keep_going,
+                Err(e) => {
+                    self.report_failure(&location, &e.to_string(), &e);
+                    true
+                }
+            };
+
+            let status = self.last_status();
+            if status != ExitStatus::Success && first_failure.is_none() {
+                first_failure = Some(status);
+            }
+            // `quit` ends the script, not the session
+            if !keep_going || (status != ExitStatus::Success && !continue_on_error) {
+                break;
+            }
+        }
+        self.source_depth -= 1;
+
+        self.status
+            .set(first_failure.unwrap_or(ExitStatus::Success));
+        Ok(())
+    }
+
+    fn cmd_sql(&self, query: &str) {
+        let result = match self.storage.query_readonly(query) {
+            Ok(result) => result,
+            Err(e) => {
+                self.report_error(ExitStatus::Failure, &t!("sql-error"), &e.to_string());
+                return;
+            }
+        };
+
+        if self.json() {
+            let rows: Vec<Vec<serde_json::Value>> = result
+                .rows
+                .iter()
+                .map(|row| row.iter().map(sql_value_json).collect())
+                .collect();
+            output::print_json(&json!({ "columns": result.columns, "rows": rows }));
+            return;
+        }
+
+        let cells: Vec<Vec<String>> = result
+            .rows
+            .iter()
+            .map(|row| row.iter().map(sql_value_text).collect())
+            .collect();
+        let widths: Vec<usize> = result
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                cells
+                    .iter()
+                    .map(|row| row[i].chars().count())
+                    .chain([name.chars().count()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let pad = |text: &str, width: usize| {
+            format!("{}{}", text, " ".repeat(width - text.chars().count()))
+        };
+        let header: Vec<String> = result
+            .columns
+            .iter()
+            .zip(&widths)
+            .map(|(name, w)| pad(name, *w))
+            .collect();
+        outln!("{}", theme::header(header.join("  ").trim_end()));
+        let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+        outln!("{}", theme::dim(&rule.join("  ")));
+        for row in &cells {
+            let line: Vec<String> = row.iter().zip(&widths).map(|(c, w)| pad(c, *w)).collect();
+            outln!("{}", line.join("  ").trim_end());
+        }
+        if self.narrate() {
+            outln!(
+                "{}",
+                theme::dim(&format!(
+                    "({})",
+                    counted!(cells.len(), "rows-one", "rows-other")
+                ))
+            );
+        }
+    }
+
+    fn cmd_recent(&self, count: usize) -> Result<()> {
+        let rows = self.storage.recent_changes(count)?;
+
+        if self.json() {
+            let recent: Vec<_> = rows
+                .iter()
+                .map(|row| {
+                    json!({
+                        "node": self.node_json(&row.sha256),
+                        "created_at": row.created_at,
+                        "updated_at": row.updated_at,
+                    })
+                })
+                .collect();
+            output::print_json(&json!({ "recent": recent }));
+            return Ok(());
+        }
+
+        if rows.is_empty() {
+            outln!("{}", t!("no-roms-in-database"));
+            return Ok(());
+        }
+        let (edited, added) = (t!("recent-edited"), t!("recent-added"));
+        let width = edited.chars().count().max(added.chars().count());
+        for row in &rows {
+            let (when, change) = match &row.updated_at {
+                Some(updated_at) if *updated_at >= row.created_at => (updated_at, &edited),
+                _ => (&row.created_at, &added),
+            };
+            outln!(
+                "{}  {}  {}  {}",
+                theme::dim(when),
+                theme::meta(&format!("{:<width$}", change)),
+                theme::title(&format_display_title(&row.title, row.version.as_deref())),
+                theme::styled_hash(&format_hash(&row.sha256)[..16])
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_status(&self) -> Result<()> {
+        let status = self.storage.status(STATUS_RECENT_COUNT)?;
+
+        if self.json() {
+            let recent: Vec<_> = status
+                .recent
+                .iter()
+                .map(|row| json!({ "node": self.node_json(&row.sha256), "created_at": row.created_at }))
+                .collect();
+            let unlinked: Vec<NodeJson> = status.unlinked.iter().map(NodeJson::from).collect();
+            output::print_json(&json!({
+                "nodes": status.node_count,
+                "diffs": status.diff_count,
+                "diff_bytes": status.total_diff_bytes,
+                "recent": recent,
+                "unlinked": unlinked,
+                "missing_diffs": status.missing_diffs,
+                "last_export_at": status.last_export_at,
+                "last_import_at": status.last_import_at,
+            }));
+            return Ok(());
+        }
+
+        outln!(
+            "{} {}, {} ({})",
+            theme::header(&t!("library")),
+            counted!(status.node_count, "roms-one", "roms-other"),
+            counted!(status.diff_count, "diffs-one", "diffs-other"),
+            format_size(status.total_diff_bytes)
+        );
+        let never = t!("never");
+        outln!(
+            "{} {}",
+            theme::header(&t!("last-export")),
+            status.last_export_at.as_deref().unwrap_or(&never)
+        );
+        outln!(
+            "{} {}",
+            theme::header(&t!("last-import")),
+            status.last_import_at.as_deref().unwrap_or(&never)
+        );
+
+        if !status.recent.is_empty() {
+            outln!();
+            outln!("{}", theme::header(&t!("recently-added")));
+            for row in &status.recent {
+                outln!(
+                    "  {}  {}  {}",
+                    theme::title(&format_display_title(&row.title, row.version.as_deref())),
+                    theme::styled_hash(&format_hash(&row.sha256)[..16]),
+                    theme::dim(&row.created_at)
+                );
+            }
+        }
+
+        if !status.unlinked.is_empty() {
+            outln!();
+            outln!(
+                "{} {}",
+                theme::warning(&t!("unlinked-roms")),
+                status.unlinked.len()
+            );
+            for node in &status.unlinked {
+                outln!(
+                    "  {}  {}",
+                    theme::title(&format_display_title(&node.title, node.version.as_deref())),
+                    theme::styled_hash(&format_hash(&node.sha256)[..16])
+                );
+            }
+        }
+
+        if !status.missing_diffs.is_empty() {
+            outln!();
+            outln!(
+                "{} {}",
+                theme::error(&t!("missing-diff-files")),
+                status.missing_diffs.len()
+            );
+            for path in &status.missing_diffs {
+                outln!("  {}", path);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn cmd_version(&self) {
+        let config = self.storage.config();
+        let (nodes, edges) = self.storage.counts();
+
+        if self.json() {
+            output::print_json(&json!({
+                "version": crate::VERSION,
+                "build_time": crate::BUILD_TIME,
+                "data_revision": DATA_REVISION,
+                "database": config.db_path.display().to_string(),
+                "diffs_dir": config.diffs_dir.display().to_string(),
+                "attachments_dir": config.attachments_dir.display().to_string(),
+                "nodes": nodes,
+                "edges": edges,
+            }));
+            return;
+        }
+
+        outln!("{}", theme::header("dromos"));
+        print_field(&t!("field-version"), crate::VERSION);
+        print_field(&t!("field-built"), crate::BUILD_TIME);
+        print_field(&t!("field-data-rev"), &DATA_REVISION.to_string());
+        print_field(&t!("field-database"), &config.db_path.display().to_string());
+        print_field(&t!("field-diffs"), &config.diffs_dir.display().to_string());
+        print_field(
+            &t!("field-attachments"),
+            &config.attachments_dir.display().to_string(),
+        );
+        print_field(&t!("field-roms"), &nodes.to_string());
+        print_field(&t!("field-edges"), &edges.to_string());
+    }
+
+    fn cmd_search(&self, query: &str, regex: bool, all: bool) -> Result<()> {
+        let matches = if regex {
+            match RegexBuilder::new(query).case_insensitive(true).build() {
+                Ok(pattern) => {
+                    let mut matches = self.storage.search_regex(&pattern)?;
+                    if !all {
+                        matches.retain(|row| !row.archived);
+                    }
+                    matches
+                }
+                Err(e) => {
+                    self.report_error(ExitStatus::Usage, &t!("invalid-regex"), &e.to_string());
+                    return Ok(());
+                }
+            }
+        } else {
+            match NodeFilter::parse(query) {
+                Ok(mut filter) => {
+                    if !all {
+                        filter.archived.get_or_insert(false);
+                    }
+                    self.storage.search(&filter)?
+                }
+                Err(e) => {
+                    self.report_error(ExitStatus::Usage, &t!("invalid-search"), &e);
+                    return Ok(());
+                }
+            }
+        };
+
+        if self.json() {
+            let nodes: Vec<_> = matches
+                .iter()
+                .filter_map(|row| self.node_json(&row.sha256))
+                .collect();
+            output::print_json(&nodes);
+            return Ok(());
+        }
+
+        if matches.is_empty() {
+            outln!("{}", theme::dim(&t!("no-matches-for", query = query)));
+            return Ok(());
+        }
+
+        for node in matches {
+            let display_title = format_display_title(&node.title, node.version.as_deref());
+            outln!(
+                "{}  {}  {}",
+                display_title,
+                theme::styled_hash(&format_hash(&node.sha256)[..16]),
+                node.rom_type
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_edit(
+        &mut self,
+        target: &str,
+        edits: &MetadataEdits,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        // Find node by hash prefix
+        let Some(node) = self.node_by_hash_prefix(target, &t!("rom-not-found"), rl) else {
+            return Ok(());
+        };
+
+        let sha256 = node.sha256;
+
+        // Get full NodeRow from database
+        let node_row = match self.storage.get_node_row_by_hash(&sha256)? {
+            Some(r) => r,
+            None => {
+                self.report_error(
+                    ExitStatus::NotFound,
+                    &t!("rom-not-found-in-database"),
+                    target,
+                );
+                return Ok(());
+            }
+        };
+
+        // Apply flag edits directly, or prompt for updated metadata
+        let mut metadata = NodeMetadata {
+            title: node_row.title,
+            source_url: node_row.source_url,
+            version: node_row.version,
+            release_date: node_row.release_date,
+            tags: node_row.tags,
+            description: node_row.description,
+            rating: node_row.rating,
+            play_status: node_row.play_status,
+            regions: node_row.regions,
+            languages: node_row.languages,
+        };
+        let node_metadata = if edits.is_empty() {
+            if self.rpc {
+                self.report_error(
+                    ExitStatus::Usage,
+                    &t!("nothing-to-edit"),
+                    &t!("give-a-field"),
+                );
+                return Ok(());
+            }
+            let mut edited = prompt_metadata_edit(rl, &metadata)?;
+            edited.rating = prompt_rating(rl, metadata.rating)?;
+            edited.play_status = prompt_play_status(rl, metadata.play_status)?;
+            edited.regions = prompt_list(rl, &t!("prompt-regions"), &metadata.regions)?;
+            edited.languages = prompt_list(rl, &t!("prompt-languages"), &metadata.languages)?;
+            edited
+        } else {
+            if let Err(e) = edits.apply(&mut metadata) {
+                self.report_error(ExitStatus::Usage, &t!("invalid-edit"), &e);
+                return Ok(());
+            }
+            metadata
+        };
+
+        // Update in storage
+        self.storage.update_node_metadata(&sha256, &node_metadata)?;
+
+        if self.json() {
+            output::print_json(&json!({ "updated": self.node_json(&sha256) }));
+            return Ok(());
+        }
+        if self.quiet {
+            return Ok(());
+        }
+
+        let display_title =
+            format_display_title(&node_metadata.title, node_metadata.version.as_deref());
+        outln!(
+            "{} {} ({})",
+            theme::success(&t!("updated")),
+            display_title,
+            theme::styled_hash(&format_hash(&sha256)[..16])
+        );
+
+        Ok(())
+    }
+
+    fn cmd_edit_csv(&mut self, file: &Path, dry_run: bool) -> Result<()> {
+        let edits = match self.storage.plan_csv_edits(file) {
+            Ok(edits) => edits,
+            Err(e) => {
+                self.report_failure(&t!("csv-edit-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+
+        // Show the changes before asking, or instead of making them
+        if !self.json() && (dry_run || !self.quiet) {
+            self.print_csv_edits(&edits);
+        }
+        let apply = !dry_run && !edits.changes.is_empty();
+        if apply
+            && !self.confirm(&t!(
+                "update-roms",
+                roms = counted!(edits.changes.len(), "roms-one", "roms-other"),
+            ))?
+        {
+            self.report_cancelled();
+            return Ok(());
+        }
+        if apply {
+            self.storage.apply_metadata_changes(&edits.changes)?;
+        }
+
+        if self.json() {
+            let changes: Vec<_> = edits
+                .changes
+                .iter()
+                .map(|change| self.metadata_change_json(change))
+                .collect();
+            let skipped: Vec<_> = edits
+                .skipped
+                .iter()
+                .map(|s| json!({ "line": s.line, "reason": s.reason }))
+                .collect();
+            output::print_json(&json!({
+                "file": file.display().to_string(),
+                "changes": changes,
+                "unchanged": edits.unchanged,
+                "skipped": skipped,
+                "applied": apply,
+            }));
+            return Ok(());
+        }
+        if self.quiet {
+            return Ok(());
+        }
+        let roms = counted!(edits.changes.len(), "roms-one", "roms-other");
+        if apply {
+            outln!("{} {}", theme::success(&t!("updated")), roms);
+        } else if !edits.changes.is_empty() {
+            outln!("{}", t!("dry-run-would-change", roms = roms));
+        } else {
+            outln!("{}", theme::dim(&t!("nothing-to-change")));
+        }
+        Ok(())
+    }
+
+    /// A planned metadata change, with each field's old and new value.
+    fn metadata_change_json(&self, change: &MetadataChange) -> serde_json::Value {
+        let fields: Vec<_> = change
+            .fields()
+            .into_iter()
+            .map(|(field, from, to)| json!({ "field": field, "from": from, "to": to }))
+            .collect();
+        json!({
+            "line": change.line,
+            "node": self.node_json(&change.sha256),
+            "fields": fields,
+        })
+    }
+
+    fn cmd_bulk_edit(
+        &mut self,
+        query: &str,
+        edits: &MetadataEdits,
+        all: bool,
+        dry_run: bool,
+    ) -> Result<()> {
+        let mut filter = match NodeFilter::parse(query) {
+            Ok(filter) => filter,
+            Err(e) => {
+                self.report_error(ExitStatus::Usage, &t!("invalid-search"), &e);
+                return Ok(());
+            }
+        };
+        if !all {
+            filter.archived.get_or_insert(false);
+        }
+
+        let rows = self.storage.search(&filter)?;
+        let mut changes = Vec::new();
+        for row in &rows {
+            let before = NodeMetadata {
+                title: row.title.clone(),
+                source_url: row.source_url.clone(),
+                version: row.version.clone(),
+                release_date: row.release_date.clone(),
+                tags: row.tags.clone(),
+                description: row.description.clone(),
+                rating: row.rating,
+                play_status: row.play_status,
+                regions: row.regions.clone(),
+                languages: row.languages.clone(),
+            };
+            let mut after = before.clone();
+            if let Err(e) = edits.apply(&mut after) {
+                self.report_error(ExitStatus::Usage, &t!("invalid-edit"), &e);
+                return Ok(());
+            }
+            if after != before {
+                changes.push(MetadataChange {
+                    sha256: row.sha256,
+                    line: None,
+                    before,
+                    after,
+                });
+            }
+        }
+        let unchanged = rows.len() - changes.len();
+
+        if !self.json() && (dry_run || !self.quiet) {
+            print_metadata_changes(&changes);
+        }
+        let apply = !dry_run && !changes.is_empty();
+        if apply
+            && !self.confirm(&t!(
+                "update-roms",
+                roms = counted!(changes.len(), "roms-one", "roms-other"),
+            ))?
+        {
+            self.report_cancelled();
+            return Ok(());
+        }
+        if apply {
+            self.storage.apply_metadata_changes(&changes)?;
+        }
+
+        if self.json() {
+            let changes: Vec<_> = changes
+                .iter()
+                .map(|change| self.metadata_change_json(change))
+                .collect();
+            output::print_json(&json!({
+                "query": query,
+                "changes": changes,
+                "unchanged": unchanged,
+                "applied": apply,
+            }));
+            return Ok(());
+        }
+        if self.quiet {
+            return Ok(());
+        }
+        let roms = counted!(changes.len(), "roms-one", "roms-other");
+        if rows.is_empty() {
+            outln!("{}", theme::dim(&t!("no-matches-for", query = query)));
+        } else if apply {
+            outln!("{} {}", theme::success(&t!("updated")), roms);
+        } else if !changes.is_empty() {
+            outln!("{}", t!("dry-run-would-change", roms = roms));
+        } else {
+            outln!(
+                "{}",
+                theme::dim(&t!(
+                    "nothing-to-change-up-to-date",
+                    roms = counted!(unchanged, "matching-roms-one", "matching-roms-other"),
+                ))
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_wish(&mut self, hash: &str, name: &str, edits: &MetadataEdits) -> Result<()> {
+        let Some(sha256) = parse_hash(hash) else {
+            self.report_error(ExitStatus::Usage, &t!("not-a-full-sha256"), hash);
+            return Ok(());
+        };
+        if let Some(node) = self.storage.get_node_by_hash(&sha256) {
+            let display_title = format_display_title(&node.title, node.version.as_deref());
+            self.report_error(
+                ExitStatus::AlreadyExists,
+                &t!("rom-already-exists"),
+                &display_title,
+            );
+            return Ok(());
+        }
+        let mut node_metadata = metadata_from_name(filename::parse(name));
+        if let Err(e) = edits.apply(&mut node_metadata) {
+            self.report_error(ExitStatus::Usage, &t!("invalid-edit"), &e);
+            return Ok(());
+        }
+        self.storage
+            .add_wanted(&wanted_rom(sha256, None, None), &node_metadata)?;
+
+        if self.json() {
+            output::print_json(&json!({ "node": self.node_json(&sha256) }));
+        } else if self.narrate() {
+            outln!(
+                "{} {} ({})",
+                theme::success(&t!("wished-for")),
+                format_display_title(&node_metadata.title, node_metadata.version.as_deref()),
+                theme::styled_hash(&format_hash(&sha256)[..16])
+            );
+        }
+        Ok(())
+    }
+
+    fn cmd_wish_dat(&mut self, query: &str, dry_run: bool) -> Result<()> {
+        let entries = self.storage.dat_entries_named(query)?;
+        // Several DATs may list the same ROM, and nodes need its SHA-256
+        let mut unhashed = 0;
+        let mut known = 0;
+        let mut wanted: Vec<(dat::DatEntry, NodeMetadata)> = Vec::new();
+        for entry in entries.iter() {
+            let Some(sha256) = entry.sha256 else {
+                unhashed += 1;
+                continue;
+            };
+            if self.storage.node_exists(&sha256) {
+                known += 1;
+            } else if !wanted.iter().any(|(e, _)| e.sha256 == entry.sha256) {
+                let metadata = metadata_from_name(filename::parse(&entry.game));
+                wanted.push((entry.clone(), metadata));
+            }
+        }
+
+        if !self.json() && (dry_run || !self.quiet) {
+            for (entry, metadata) in &wanted {
+                outln!(
+                    "  {}  {}  {}  {}",
+                    theme::success(&t!("status-wish")),
+                    theme::title(&format_display_title(
+                        &metadata.title,
+                        metadata.version.as_deref()
+                    )),
+                    theme::styled_hash(&format_hash(&entry.sha256.unwrap_or_default())[..16]),
+                    theme::dim(&entry.game)
+                );
+            }
+        }
+        let apply = !dry_run && !wanted.is_empty();
+        if apply
+            && !self.confirm(&t!(
+                "add-to-wishlist",
+                roms = counted!(wanted.len(), "roms-one", "roms-other"),
+            ))?
+        {
+            self.report_cancelled();
+            return Ok(());
+        }
+        if apply {
+            for (entry, metadata) in &wanted {
+                let sha256 = entry.sha256.unwrap_or_default();
+                self.storage
+                    .add_wanted(&wanted_rom(sha256, entry.sha1, entry.crc32), metadata)?;
+            }
+        }
+
+        if self.json() {
+            let wanted: Vec<_> = wanted
+                .iter()
+                .map(|(entry, metadata)| {
+                    json!({
+                        "sha256": format_hash(&entry.sha256.unwrap_or_default()),
+                        "title": metadata.title,
+                        "version": metadata.version,
+                        "game": entry.game,
+                    })
+                })
+                .collect();
+            output::print_json(&json!({
+                "query": query,
+                "wanted": wanted,
+                "known": known,
+                "unhashed": unhashed,
+                "added": apply,
+            }));
+            return Ok(());
+        }
+        if self.quiet {
+            return Ok(());
+        }
+        let roms = counted!(wanted.len(), "roms-one", "roms-other");
+        if entries.is_empty() {
+            outln!("{}", theme::dim(&t!("no-dat-game-like", query = query)));
+        } else if apply {
+            outln!(
+                "{} {}",
+                theme::success(&t!("added")),
+                t!("roms-to-wishlist", roms = roms)
+            );
+        } else if !wanted.is_empty() {
+            outln!("{}", t!("dry-run-would-add", roms = roms));
+        } else {
+            outln!(
+                "{}",
+                theme::dim(&t!("nothing-to-add", known = known, unhashed = unhashed,))
+            );
+        }
+        Ok(())
+    }
+
+    /// Show each change a CSV makes and each row it skips.
+    fn print_csv_edits(&self, edits: &CsvEdits) {
+        print_metadata_changes(&edits.changes);
+        for skipped in &edits.skipped {
+            outln!(
+                "{} {}",
+                theme::warning(&t!("skipped")),
+                t!("line-reason", line = skipped.line, reason = skipped.reason)
+            );
+        }
+        if edits.unchanged > 0 {
+            outln!(
+                "{}",
+                theme::dim(&t!(
+                    "rows-up-to-date",
+                    rows = counted!(edits.unchanged, "rows-one", "rows-other"),
+                ))
+            );
+        }
+    }
+
+    fn cmd_export_html(
+        &self,
+        hash_prefix: Option<&str>,
+        output: &Path,
+        filter: &NodeFilter,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let component_hash = match hash_prefix {
+            Some(prefix) => {
+                let Some(node) = self.node_by_hash_prefix(prefix, &t!("rom-not-found"), rl) else {
+                    return Ok(());
+                };
+                Some(node.sha256)
+            }
+            None => None,
         };
+        let has_files = std::fs::read_dir(output).is_ok_and(|mut entries| entries.next().is_some());
+        if has_files && !self.confirm(&t!("replace-catalog-in-folder", path = output.display()))? {
+            self.report_cancelled();
+            return Ok(());
+        }
 
-        // Confirm link to last added
-        let last_display = format_display_title(&last.title, last.version.as_deref());
-        let prompt = format!("Link to \"{}\"? [Y/n]", last_display);
-        print!("{}: ", prompt);
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim().to_lowercase();
+        // Title the catalog after its folder
+        let name = output
+            .file_name()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "dromos".to_string());
+        let nodes = match self.storage.export_site(
+            output,
+            &name,
+            component_hash.as_ref(),
+            filter,
+            &mut self.progress_line(),
+            interrupt::arm().token(),
+        ) {
+            Ok(n) => n,
+            Err(e) => {
+                self.report_failure(&t!("catalog-export-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
 
-        if input == "n" || input == "no" {
-            println!("Cancelled.");
+        if self.json() {
+            output::print_json(&json!({
+                "output": output.display().to_string(),
+                "nodes": nodes,
+                "index": output.join("index.html").display().to_string(),
+            }));
             return Ok(());
         }
+        if !self.quiet {
+            outln!(
+                "{} {}",
+                theme::success(&t!("exported")),
+                t!(
+                    "catalog-of-roms",
+                    roms = counted!(nodes, "roms-one", "roms-other"),
+                    path = output.join("index.html").display(),
+                )
+            );
+        }
+        Ok(())
+    }
 
-        // Add ROM if needed (with full metadata prompting)
-        let result = match self.ensure_rom_added(file, rl)? {
-            Some(r) => r,
-            None => return Ok(()), // File not found, error already printed
+    #[cfg(feature = "server")]
+    fn cmd_serve(&mut self, listen: &str, stdio: bool) -> Result<()> {
+        if stdio {
+            // Standard output carries the replies, so nothing else goes there
+            let result = server::serve_stdio(
+                &mut self.storage,
+                &mut std::io::stdin().lock(),
+                &mut std::io::stdout().lock(),
+            );
+            if let Err(e) = result {
+                self.status.set(ExitStatus::from_error(&e));
+                eprintln!("{} {}", theme::error(&t!("server-failed")), e);
+            }
+            return Ok(());
+        }
+        let server = match Server::bind(listen) {
+            Ok(server) => server,
+            Err(e) => {
+                self.report_failure(&t!("cannot-listen"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        // Beyond this machine, requests need the token, which the web UI
+        // takes from its address
+        let url = match server.token() {
+            Some(token) => format!("http://{}/?token={}", server.address(), token),
+            None => format!("http://{}/", server.address()),
         };
+        if self.json() {
+            output::print_json(&json!({ "listening": url, "token": server.token() }));
+        } else if !self.quiet {
+            outln!(
+                "{} {} {}",
+                theme::info(&t!("serving")),
+                url,
+                t!("press-ctrl-c-to-stop")
+            );
+        }
 
-        // Need to find the file for the last_added ROM
-        // For now, require the user to have the file accessible
-        // This is a limitation - we'd need to store original file paths
-        eprintln!(
-            "Note: To create links, you need both ROM files. Use 'link <file1> <file2>' with both files."
+        let webhooks = self.start_webhooks();
+        let (json, narrate) = (self.json(), self.narrate());
+        let result = server.run(
+            &mut self.storage,
+            interrupt::arm().token(),
+            &mut |request| {
+                log_request(request, json, narrate);
+                if let Some((webhooks, _)) = &webhooks {
+                    report_webhook_failures(&webhooks.failures());
+                }
+            },
         );
+        self.stop_webhooks(webhooks);
+        match result {
+            Ok(()) | Err(DromosError::Cancelled) => {
+                if self.narrate() {
+                    outln!("{}", theme::info(&t!("stopped-serving")));
+                }
+            }
+            Err(e) => self.report_failure(&t!("server-failed"), &e.to_string(), &e),
+        }
+        Ok(())
+    }
 
-        // Update last added
-        self.last_added = Some(LastAdded {
-            hash: result.hash,
-            title: result.title,
-            version: result.version,
-        });
-
+    #[cfg(not(feature = "server"))]
+    fn cmd_serve(&mut self, _listen: &str, _stdio: bool) -> Result<()> {
+        self.report_error(
+            ExitStatus::Failure,
+            &t!("not-available"),
+            &t!("built-without", feature = "server"),
+        );
         Ok(())
     }
 
-    fn link_two_files(
-        &mut self,
-        file_a: &Path,
-        file_b: &Path,
-        rl: &mut Editor<DromosHelper, DefaultHistory>,
-    ) -> Result<()> {
-        // Add first file if needed (with full metadata prompting)
-        let result_a = match self.ensure_rom_added(file_a, rl)? {
-            Some(r) => r,
-            None => return Ok(()), // File not found, error already printed
+    #[cfg(feature = "grpc")]
+    fn cmd_serve_grpc(&mut self, listen: &str) -> Result<()> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let listener = match runtime.block_on(crate::grpc::Listener::bind(listen)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                self.report_failure(&t!("cannot-listen"), &e.to_string(), &e);
+                return Ok(());
+            }
         };
+        let address = listener.address()?;
+        if self.json() {
+            output::print_json(&json!({
+                "listening": address,
+                "protocol": "grpc",
+                "token": listener.token(),
+            }));
+        } else if !self.quiet {
+            outln!(
+                "{} {} {}",
+                theme::info(&t!("serving")),
+                t!("grpc-on", address = address),
+                t!("press-ctrl-c-to-stop")
+            );
+            // Beyond this machine, calls need the token
+            if let Some(token) = listener.token() {
+                outln!(
+                    "{} authorization: Bearer {}",
+                    theme::info(&t!("calls-need")),
+                    token
+                );
+            }
+        }
 
-        // Add second file if needed (with full metadata prompting)
-        let result_b = match self.ensure_rom_added(file_b, rl)? {
-            Some(r) => r,
-            None => return Ok(()), // File not found, error already printed
-        };
+        // Calls go through their own connection to the library; the shell's
+        // is reopened afterwards so it sees what they changed
+        let config = self.storage.config().clone();
+        let tag_rules = self.storage.tag_rules().to_vec();
+        let mut library = StorageManager::open(config.clone())?;
+        library.set_tag_rules(tag_rules.clone());
+        let webhooks = (!self.user_config.webhooks.is_empty())
+            .then(|| Webhooks::start(self.user_config.webhooks.clone()));
+        if let Some(webhooks) = &webhooks {
+            library.add_observer(webhooks.observer());
+        }
+        let result = runtime.block_on(crate::grpc::serve(
+            library.into(),
+            listener,
+            interrupt::arm().token().clone(),
+        ));
+        if let Some(webhooks) = webhooks {
+            report_webhook_failures(&webhooks.finish());
+        }
+        self.storage = StorageManager::open(config)?;
+        self.storage.set_tag_rules(tag_rules);
+        match result {
+            Ok(()) | Err(DromosError::Cancelled) => {
+                if self.narrate() {
+                    outln!("{}", theme::info(&t!("stopped-serving")));
+                }
+            }
+            Err(e) => self.report_failure(&t!("server-failed"), &e.to_string(), &e),
+        }
+        Ok(())
+    }
 
-        // Create bidirectional links
-        self.storage.link_nodes(file_a, file_b)?;
-        let display_a = format_display_title(&result_a.title, result_a.version.as_deref());
-        let display_b = format_display_title(&result_b.title, result_b.version.as_deref());
-        println!(
-            "{} {} <-> {}",
-            theme::success("Linked:"),
-            display_a,
-            display_b
+    #[cfg(not(feature = "grpc"))]
+    fn cmd_serve_grpc(&mut self, _listen: &str) -> Result<()> {
+        self.report_error(
+            ExitStatus::Failure,
+            &t!("not-available"),
+            &t!("built-without", feature = "grpc"),
         );
-
-        // Update last added to the second file
-        self.last_added = Some(LastAdded {
-            hash: result_b.hash,
-            title: result_b.title,
-            version: result_b.version,
-        });
-
         Ok(())
     }
 
-    fn cmd_list(&self) {
-        let (nodes, _edges) = self.storage.list();
-
-        if nodes.is_empty() {
-            println!("{}", theme::dim("No ROMs in database."));
-            return;
+    /// Start sending library changes to the config file's webhooks, for
+    /// `serve` and `watch`; none without any.
+    #[cfg(feature = "server")]
+    fn start_webhooks(&mut self) -> Option<(Webhooks, ObserverId)> {
+        if self.user_config.webhooks.is_empty() {
+            return None;
         }
+        let webhooks = Webhooks::start(self.user_config.webhooks.clone());
+        let observer = self.storage.add_observer(webhooks.observer());
+        Some((webhooks, observer))
+    }
 
-        // Sort by title
-        let mut sorted_nodes: Vec<&RomNode> = nodes.clone();
-        sorted_nodes.sort_by(|a, b| a.title.to_lowercase().cmp(&b.title.to_lowercase()));
-
-        for node in sorted_nodes {
-            let link_count = self.storage.link_count(&node.sha256);
-            let link_info = if link_count > 0 {
-                let text = format!(
-                    "[{} link{}]",
-                    link_count,
-                    if link_count == 1 { "" } else { "s" }
-                );
-                format!("  {}", theme::meta(&text))
-            } else {
-                String::new()
-            };
-            let styled_title = theme::title(&node.title);
-            let version_part = match node.version.as_deref() {
-                Some(v) if !v.is_empty() => format!(" {}", theme::meta(&format!("[{}]", v))),
-                _ => String::new(),
-            };
-            println!(
-                "{}{}  {}  {}{}",
-                styled_title,
-                version_part,
-                theme::styled_hash(&format_hash(&node.sha256)[..16]),
-                theme::label(&node.rom_type.to_string()),
-                link_info
-            );
+    /// Send what [`Self::start_webhooks`] still has queued, and stop.
+    #[cfg(feature = "server")]
+    fn stop_webhooks(&mut self, webhooks: Option<(Webhooks, ObserverId)>) {
+        if let Some((webhooks, observer)) = webhooks {
+            self.storage.remove_observer(observer);
+            report_webhook_failures(&webhooks.finish());
         }
     }
 
-    fn cmd_links(&self, target: &str) -> Result<()> {
-        // Try to find node: first as file, then as hash prefix
-        let node = if std::path::Path::new(target).exists() {
-            // It's a file path - hash it and look up
-            let metadata = hash_rom_file(std::path::Path::new(target))?;
-            self.storage.get_node_by_hash(&metadata.sha256)
-        } else {
-            // Try as hash prefix
-            self.storage.find_node_by_hash_prefix(target)
+    #[cfg(feature = "lan")]
+    fn cmd_share(
+        &mut self,
+        component: Option<String>,
+        tags: Vec<String>,
+        name: Option<String>,
+        listen: &str,
+    ) -> Result<()> {
+        let selection = Selection { component, tags };
+        let manifest = match server::selected_manifest(&self.storage, &selection) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                self.report_failure(&t!("cannot-share"), &e.to_string(), &e);
+                return Ok(());
+            }
         };
-
-        let node = match node {
-            Some(n) => n,
-            None => {
-                eprintln!("{} {}", theme::error("ROM not found:"), target);
+        if manifest.files.is_empty() {
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("nothing-to-share"),
+                &t!("no-roms-tagged", tags = selection.tags.join(", ")),
+            );
+            return Ok(());
+        }
+        // What's shared is for anyone who finds it, so no token
+        let server = match Server::bind(listen) {
+            Ok(server) => server.without_token(),
+            Err(e) => {
+                self.report_failure(&t!("cannot-listen"), &e.to_string(), &e);
                 return Ok(());
             }
         };
 
-        let neighbors = self.storage.get_neighbors(&node.sha256);
+        // Described by what was picked, for `fetch` to list
+        let mut description = Vec::new();
+        if let Some(prefix) = &selection.component {
+            let prefix = prefix.to_lowercase();
+            if let Some(file) = manifest
+                .files
+                .iter()
+                .find(|f| f.sha256.starts_with(&prefix))
+            {
+                description.push(format_display_title(&file.title, file.version.as_deref()));
+            }
+        }
+        if !selection.tags.is_empty() {
+            description.push(t!("tagged", tags = selection.tags.join(", ")));
+        }
+        let share = ShareInfo {
+            name: name.unwrap_or_else(lan::host_name),
+            port: server.port().unwrap_or_default(),
+            description: description.join("; "),
+            nodes: manifest.files.len(),
+            revision: manifest.dromos_export.data_revision,
+        };
+        let advertisement = match Advertisement::start(&share) {
+            Ok(advertisement) => advertisement,
+            Err(e) => {
+                self.report_failure(&t!("cannot-advertise"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
+        if self.json() {
+            output::print_json(&json!({
+                "sharing": share.name,
+                "listening": format!("http://{}/", server.address()),
+                "description": share.description,
+                "nodes": share.nodes,
+            }));
+        } else if !self.quiet {
+            outln!(
+                "{} {} {}",
+                theme::info(&t!("sharing")),
+                t!(
+                    "sharing-as",
+                    roms = counted!(share.nodes, "roms-one", "roms-other"),
+                    description = share.description,
+                    name = theme::title(&share.name),
+                    port = share.port,
+                ),
+                t!("press-ctrl-c-to-stop")
+            );
+        }
 
-        let display_title = format_display_title(&node.title, node.version.as_deref());
-        println!(
-            "{}  ({})",
-            display_title,
-            theme::styled_hash(&format_hash(&node.sha256)[..16])
+        let (json, narrate) = (self.json(), self.narrate());
+        let storage = &self.storage;
+        let result = server.run_with(
+            interrupt::arm().token(),
+            &mut |request| log_request(request, json, narrate),
+            &mut |method, url, _body| server::share(storage, &manifest, method, url),
         );
-
-        match neighbors {
-            Some(links) if !links.is_empty() => {
-                for (neighbor, diff_size) in links {
-                    let neighbor_display =
-                        format_display_title(&neighbor.title, neighbor.version.as_deref());
-                    println!("  -> {}  ({})", neighbor_display, format_size(diff_size));
+        drop(advertisement);
+        match result {
+            Ok(()) | Err(DromosError::Cancelled) => {
+                if self.narrate() {
+                    outln!("{}", theme::info(&t!("stopped-sharing")));
                 }
             }
-            _ => {
-                println!("  {}", theme::dim("(no links)"));
-            }
+            Err(e) => self.report_failure(&t!("server-failed"), &e.to_string(), &e),
         }
-
         Ok(())
     }
 
-    fn cmd_rm(&mut self, target: &str) -> Result<()> {
-        // Try to find node by hash prefix
-        let node = self.storage.find_node_by_hash_prefix(target);
+    #[cfg(not(feature = "lan"))]
+    fn cmd_share(
+        &mut self,
+        _component: Option<String>,
+        _tags: Vec<String>,
+        _name: Option<String>,
+        _listen: &str,
+    ) -> Result<()> {
+        self.report_error(
+            ExitStatus::Failure,
+            &t!("not-available"),
+            &t!("built-without", feature = "lan"),
+        );
+        Ok(())
+    }
 
-        let node = match node {
-            Some(n) => n,
-            None => {
-                eprintln!("{} {}", theme::error("ROM not found:"), target);
+    #[cfg(feature = "lan")]
+    fn cmd_fetch(
+        &mut self,
+        name: Option<&str>,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        if self.narrate() {
+            outln!("{}", theme::info(&t!("looking-for-shares")));
+        }
+        let peers = match lan::browse(lan::BROWSE_WAIT, interrupt::arm().token()) {
+            Ok(peers) => peers,
+            Err(e) => {
+                self.report_failure(&t!("cannot-search-network"), &e.to_string(), &e);
                 return Ok(());
             }
         };
+        let Some(name) = name else {
+            if self.json() {
+                output::print_json(&json!({ "shares": peers }));
+            } else if peers.is_empty() {
+                outln!("{}", theme::dim(&t!("no-shares-found")));
+            } else {
+                for peer in &peers {
+                    outln!(
+                        "  {}  {}  {}  {}",
+                        theme::title(&peer.name),
+                        counted!(peer.nodes, "roms-one", "roms-other"),
+                        peer.description,
+                        theme::dim(&peer.url)
+                    );
+                }
+            }
+            return Ok(());
+        };
+        match peers
+            .iter()
+            .find(|peer| peer.name.eq_ignore_ascii_case(name))
+        {
+            Some(peer) => {
+                let url = peer.url.clone();
+                self.cmd_import_remote(&url, None, Vec::new(), rl)
+            }
+            None => {
+                self.report_error(ExitStatus::NotFound, &t!("share-not-found"), name);
+                Ok(())
+            }
+        }
+    }
 
-        let sha256 = node.sha256;
-        let display_title = format_display_title(&node.title, node.version.as_deref());
-        let link_count = self.storage.link_count(&sha256);
-
-        // Prompt for confirmation
-        let link_text = if link_count == 1 { "link" } else { "links" };
-        print!(
-            "Remove '{}' and {} {}? [y/N]: ",
-            display_title, link_count, link_text
+    #[cfg(not(feature = "lan"))]
+    fn cmd_fetch(
+        &mut self,
+        _name: Option<&str>,
+        _rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        self.report_error(
+            ExitStatus::Failure,
+            &t!("not-available"),
+            &t!("built-without", feature = "lan"),
         );
-        io::stdout().flush()?;
+        Ok(())
+    }
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim().to_lowercase();
+    #[cfg(feature = "server")]
+    fn cmd_sync(&mut self, remote: &str, dry_run: bool) -> Result<()> {
+        if self.narrate() {
+            outln!("{} {}...", theme::info(&t!("comparing-with")), remote);
+        }
+        // Phase 1: Compare the libraries
+        let planned = Remote::connect(remote)
+            .and_then(|mut remote| Ok((remote.plan(&self.storage)?, remote)));
+        let (plan, mut remote) = match planned {
+            Ok(planned) => planned,
+            Err(e) => {
+                self.report_failure(&t!("sync-failed"), &e.to_string(), &e);
+                return Ok(());
+            }
+        };
 
-        if input != "y" && input != "yes" {
-            println!("Cancelled.");
+        if dry_run {
+            if self.json() {
+                let counts = |m: &ExportManifest| {
+                    json!({
+                        "nodes": m.files.len(),
+                        "diffs": m.diffs.len(),
+                        "attachments": m.attachments.len(),
+                    })
+                };
+                output::print_json(&json!({
+                    "pull": counts(&plan.pull),
+                    "push": counts(&plan.push),
+                    "conflicts": plan.conflicts,
+                }));
+            } else if !self.quiet {
+                for (label, m) in [
+                    (t!("would-pull"), &plan.pull),
+                    (t!("would-push"), &plan.push),
+                ] {
+                    let counts = (m.files.len(), m.diffs.len(), m.attachments.len());
+                    outln!("{} {}", theme::info(&label), sync_counts(counts));
+                }
+                self.print_sync_conflicts(&plan.conflicts);
+            }
             return Ok(());
         }
 
-        // Perform the removal
-        let result = self.storage.remove_node(&sha256)?;
-
-        println!(
-            "{} {} ({} edge{}, {} diff file{})",
-            theme::success("Removed:"),
-            display_title,
-            result.edges_removed,
-            if result.edges_removed == 1 { "" } else { "s" },
-            result.diff_files_removed,
-            if result.diff_files_removed == 1 {
-                ""
-            } else {
-                "s"
+        // Phase 2: Copy what each side is missing
+        let up_to_date = plan.is_empty();
+        let mut progress = self.progress_line();
+        let result = match remote.sync(
+            &mut self.storage,
+            plan,
+            &mut progress,
+            interrupt::arm().token(),
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                self.report_failure(&t!("sync-failed"), &e.to_string(), &e);
+                return Ok(());
             }
-        );
+        };
 
-        // Clear last_added if it was the removed node
-        if let Some(ref last) = self.last_added
-            && last.hash == sha256
-        {
-            self.last_added = None;
+        if self.json() {
+            output::print_json(&serde_json::to_value(&result)?);
+            return Ok(());
         }
-
+        warn_rejected(&result.pulled.rejected);
+        warn_rejected(&result.pushed.rejected);
+        if self.quiet {
+            return Ok(());
+        }
+        if up_to_date {
+            outln!(
+                "{} {}",
+                theme::success(&t!("up-to-date")),
+                t!("libraries-match")
+            );
+        } else {
+            for (label, r) in [
+                (t!("pulled"), &result.pulled),
+                (t!("pushed"), &result.pushed),
+            ] {
+                let counts = (r.nodes_added, r.diffs_copied, r.attachments_added);
+                outln!("{} {}", theme::success(&label), sync_counts(counts));
+            }
+        }
+        self.print_sync_conflicts(&result.conflicts);
         Ok(())
     }
 
-    fn cmd_search(&self, query: &str) {
-        let (nodes, _) = self.storage.list();
-        let query_lower = query.to_lowercase();
-
-        let matches: Vec<&RomNode> = nodes
-            .into_iter()
-            .filter(|n| n.title.to_lowercase().contains(&query_lower))
-            .collect();
+    #[cfg(not(feature = "server"))]
+    fn cmd_sync(&mut self, _remote: &str, _dry_run: bool) -> Result<()> {
+        self.report_error(
+            ExitStatus::Failure,
+            &t!("not-available"),
+            &t!("built-without", feature = "server"),
+        );
+        Ok(())
+    }
 
-        if matches.is_empty() {
-            println!(
-                "{}",
-                theme::dim(&format!("No matches found for \"{}\"", query))
-            );
+    /// List the ROMs whose metadata differs between two synced libraries.
+    #[cfg(feature = "server")]
+    fn print_sync_conflicts(&self, conflicts: &[String]) {
+        if conflicts.is_empty() {
             return;
         }
-
-        for node in matches {
-            let display_title = format_display_title(&node.title, node.version.as_deref());
-            println!(
-                "{}  {}  {}",
-                display_title,
-                theme::styled_hash(&format_hash(&node.sha256)[..16]),
-                node.rom_type
+        outln!(
+            "{} {}",
+            theme::warning(&t!("conflicts")),
+            t!(
+                "sync-conflicts",
+                roms = counted!(conflicts.len(), "roms-one", "roms-other")
+            )
+        );
+        for hash in conflicts {
+            let title = crate::rom::parse_hash(hash)
+                .and_then(|sha256| self.storage.get_node_by_hash(&sha256))
+                .map(|node| format_display_title(&node.title, node.version.as_deref()))
+                .unwrap_or_default();
+            outln!(
+                "  {} ({})",
+                theme::title(&title),
+                theme::styled_hash(&hash[..16])
             );
         }
     }
 
-    fn cmd_edit(
-        &mut self,
-        target: &str,
+    fn cmd_export_plugin(
+        &self,
+        hash_prefix: Option<&str>,
+        output: &Path,
+        name: &str,
+        filter: &NodeFilter,
         rl: &mut Editor<DromosHelper, DefaultHistory>,
     ) -> Result<()> {
-        // Find node by hash prefix
-        let node = match self.storage.find_node_by_hash_prefix(target) {
-            Some(n) => n,
-            None => {
-                eprintln!("{} {}", theme::error("ROM not found:"), target);
-                return Ok(());
+        let Some(plugin) = self
+            .plugins
+            .iter()
+            .find(|p| p.name() == name.to_lowercase())
+        else {
+            let hint = match config_file_path() {
+                Some(path) => t!("set-plugin-in", name = name, path = path.display()),
+                None => name.to_string(),
+            };
+            self.report_error(ExitStatus::NotFound, &t!("no-plugin-named"), &hint);
+            return Ok(());
+        };
+        let component_hash = match hash_prefix {
+            Some(prefix) => {
+                let Some(node) = self.node_by_hash_prefix(prefix, &t!("rom-not-found"), rl) else {
+                    return Ok(());
+                };
+                Some(node.sha256)
             }
+            None => None,
         };
 
-        let sha256 = node.sha256;
-
-        // Get full NodeRow from database
-        let node_row = match self.storage.get_node_row_by_hash(&sha256)? {
-            Some(r) => r,
-            None => {
-                eprintln!("{} {}", theme::error("ROM not found in database:"), target);
+        // Title the export after its folder, as for catalogs
+        let export_name = output
+            .file_name()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "dromos".to_string());
+        let nodes = match self.storage.export_with_plugin(
+            plugin,
+            output,
+            &export_name,
+            component_hash.as_ref(),
+            filter,
+        ) {
+            Ok(n) => n,
+            Err(e) => {
+                self.report_failure(&t!("export-failed"), &e.to_string(), &e);
                 return Ok(());
             }
         };
 
-        // Prompt for updated metadata
-        let node_metadata = prompt_metadata_from_row(rl, &node_row)?;
-
-        // Update in storage
-        self.storage.update_node_metadata(&sha256, &node_metadata)?;
-
-        let display_title =
-            format_display_title(&node_metadata.title, node_metadata.version.as_deref());
-        println!(
-            "{} {} ({})",
-            theme::success("Updated:"),
-            display_title,
-            theme::styled_hash(&format_hash(&sha256)[..16])
-        );
-
+        if self.json() {
+            output::print_json(&json!({
+                "output": output.display().to_string(),
+                "nodes": nodes,
+                "plugin": plugin.name(),
+            }));
+        } else if !self.quiet {
+            outln!(
+                "{} {}",
+                theme::success(&t!("exported")),
+                t!(
+                    "exported-with-plugin",
+                    roms = counted!(nodes, "roms-one", "roms-other"),
+                    path = output.display(),
+                    plugin = plugin.name(),
+                )
+            );
+        }
         Ok(())
     }
 
-    fn cmd_export(&self, hash_prefix: Option<&str>, output: &Path) -> Result<()> {
+    fn cmd_export(
+        &self,
+        hash_prefix: Option<&str>,
+        output: &Path,
+        git: bool,
+        filter: &NodeFilter,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
         let component_hash = match hash_prefix {
             Some(prefix) => {
-                let node = match self.storage.find_node_by_hash_prefix(prefix) {
-                    Some(n) => n,
-                    None => {
-                        eprintln!("{} {}", theme::error("ROM not found:"), prefix);
-                        return Ok(());
-                    }
+                let Some(node) = self.node_by_hash_prefix(prefix, &t!("rom-not-found"), rl) else {
+                    return Ok(());
                 };
                 Some(node.sha256)
             }
             None => None,
         };
 
+        // A collection export is planned up front, as that's what counts it
+        let mut planned = None;
+        if !filter.collections.is_empty() {
+            match self
+                .storage
+                .plan_filtered_export(component_hash.as_ref(), filter)
+            {
+                Ok(plan) => planned = Some(plan),
+                Err(e) => {
+                    self.report_failure(&t!("export-failed"), &e.to_string(), &e);
+                    return Ok(());
+                }
+            }
+        }
+
         // Count nodes that will be exported
-        let node_count = match &component_hash {
-            Some(h) => self.storage.connected_component_count(h).unwrap_or(0),
-            None => self.storage.list().0.len(),
+        let node_count = match (&planned, &component_hash) {
+            (Some(plan), _) => plan.manifest.files.len(),
+            (None, Some(h)) => self.storage.connected_component_count(h).unwrap_or(0),
+            (None, None) => self.storage.list().0.len(),
         };
 
         // Confirm before creating the folder
-        print!(
-            "Export {} node{} to folder \"{}\"? [y/N]: ",
-            node_count,
-            if node_count == 1 { "" } else { "s" },
-            output.display()
-        );
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim().to_lowercase();
-
-        if input != "y" && input != "yes" {
-            println!("Cancelled.");
+        if !self.confirm(&t!(
+            "export-to-folder",
+            nodes = counted!(node_count, "nodes-one", "nodes-other"),
+            path = output.display(),
+        ))? {
+            self.report_cancelled();
             return Ok(());
         }
 
-        // Warn if folder already exists
-        if output.is_dir() {
-            print!(
-                "{} Folder \"{}\" already exists. Continue? [y/N]: ",
-                theme::warning("Warning:"),
-                output.display()
-            );
-            io::stdout().flush()?;
+        // Warn if folder already exists. Git keeps what an export replaces,
+        // so a Git export updates the folder without asking.
+        if output.is_dir()
+            && !git
+            && !self.confirm(&format!(
+                "{} {}",
+                theme::warning(&t!("warning")),
+                t!("folder-exists-continue", path = output.display())
+            ))?
+        {
+            self.report_cancelled();
+            return Ok(());
+        }
 
+        // Ask about each file that would be replaced before writing anything
+        let plan = match planned {
+            Some(plan) => plan,
+            None => self.storage.plan_export(component_hash.as_ref())?,
+        };
+        let mut overwrite = HashSet::new();
+        for path in plan.existing_files(output) {
+            if self.assume_yes || git {
+                overwrite.insert(path);
+                continue;
+            }
+            if self.rpc {
+                continue;
+            }
+            output::write_prompt(format_args!(
+                "{} {}: ",
+                t!("overwrite-file", path = path.display()),
+                t!("confirm-choices-abort")
+            ))?;
             let mut input = String::new();
             io::stdin().read_line(&mut input)?;
-            let input = input.trim().to_lowercase();
-
-            if input != "y" && input != "yes" {
-                println!("Cancelled.");
+            if is_answer(&t!("confirm-yes"), &input) {
+                overwrite.insert(path);
+            } else if is_answer(&t!("confirm-abort"), &input) {
+                self.status.set(ExitStatus::Cancelled);
+                if self.json() {
+                    output::print_json(&json!({
+                        "output": output.display().to_string(),
+                        "nodes": plan.manifest.files.len(),
+                        "edges": plan.manifest.diffs.len(),
+                        "aborted": true,
+                    }));
+                } else {
+                    outln!("{}", t!("export-aborted"));
+                }
                 return Ok(());
             }
         }
 
-        // Export with per-file conflict handling
-        let mut on_conflict = |path: &Path| -> Result<OverwriteAction> {
-            print!("Overwrite \"{}\"? [y/N/a]: ", path.display());
-            io::stdout().flush()?;
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
-            match input.trim().to_lowercase().as_str() {
-                "y" | "yes" => Ok(OverwriteAction::Overwrite),
-                "a" | "abort" => Ok(OverwriteAction::Abort),
-                _ => Ok(OverwriteAction::Skip),
+        let stats = self.storage.export(
+            output,
+            &plan,
+            &overwrite,
+            &mut self.progress_line(),
+            interrupt::arm().token(),
+        )?;
+
+        let mut summary = format!(
+            "{}, {}",
+            counted!(stats.nodes, "nodes-one", "nodes-other"),
+            counted!(stats.edges, "edges-one", "edges-other")
+        );
+        if stats.attachments > 0 {
+            summary += ", ";
+            summary += &counted!(stats.attachments, "attachments-one", "attachments-other");
+        }
+        let commit = git.then(|| {
+            let message = format!(
+                "Export {}\n\nExported by dromos {}",
+                summary,
+                crate::VERSION
+            );
+            // The manifest's export time alone isn't a change worth a commit
+            super::git::commit_folder(output, &message, "\"exported_at\"")
+        });
+
+        if self.json() {
+            output::print_json(&json!({
+                "output": output.display().to_string(),
+                "nodes": stats.nodes,
+                "edges": stats.edges,
+                "attachments": stats.attachments,
+                "skipped": stats.skipped,
+                "aborted": false,
+                "commit": commit.as_ref().and_then(|c| c.as_ref().ok()),
+            }));
+        } else if !self.quiet {
+            outln!(
+                "{} {}",
+                theme::success(&t!("exported")),
+                t!("summary-to", summary = summary, path = output.display())
+            );
+            match &commit {
+                Some(Ok(Some(hash))) => outln!("{} {}", theme::success(&t!("committed")), hash),
+                Some(Ok(None)) => outln!("{}", theme::dim(&t!("no-changes-to-commit"))),
+                _ => {}
             }
-        };
+        }
+        if let Some(Err(e)) = &commit {
+            self.report_error(ExitStatus::Failure, &t!("git-commit-failed"), e);
+            return Ok(());
+        }
+        if self.json() || self.quiet {
+            return Ok(());
+        }
+        if stats.skipped > 0 {
+            outln!(
+                "{}",
+                counted!(
+                    stats.skipped,
+                    "kept-existing-files-one",
+                    "kept-existing-files-other"
+                )
+            );
+        }
 
-        let stats = self
-            .storage
-            .export(output, component_hash.as_ref(), &mut on_conflict)?;
+        Ok(())
+    }
 
-        if stats.aborted {
-            println!("Export aborted.");
-            return Ok(());
+    /// Download part of a library from `dromos serve` at `url` as an export
+    /// folder, then import it like any other.
+    #[cfg(feature = "server")]
+    fn cmd_import_remote(
+        &mut self,
+        url: &str,
+        component: Option<String>,
+        tags: Vec<String>,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        let selection = Selection { component, tags };
+        let local: HashSet<String> = self
+            .storage
+            .node_rows()?
+            .iter()
+            .map(|row| format_hash(&row.sha256))
+            .collect();
+        let folder = tempfile::Builder::new()
+            .prefix("dromos-import-")
+            .tempdir()?;
+        if self.narrate() {
+            outln!("{} {}...", theme::info(&t!("downloading-from")), url);
+        }
+        let mut progress = self.progress_line();
+        let downloaded = Remote::connect(url).and_then(|mut remote| {
+            remote.download_export(
+                &selection,
+                &local,
+                folder.path(),
+                &mut progress,
+                interrupt::arm().token(),
+            )
+        });
+        match downloaded {
+            Ok(_) => self.cmd_import(folder.path(), rl),
+            Err(e) => {
+                self.report_failure(&t!("import-failed"), &e.to_string(), &e);
+                Ok(())
+            }
         }
+    }
 
-        println!(
-            "{} {} node{}, {} edge{} to {}",
-            theme::success("Exported:"),
-            stats.nodes,
-            if stats.nodes == 1 { "" } else { "s" },
-            stats.edges,
-            if stats.edges == 1 { "" } else { "s" },
-            output.display()
+    #[cfg(not(feature = "server"))]
+    fn cmd_import_remote(
+        &mut self,
+        _url: &str,
+        _component: Option<String>,
+        _tags: Vec<String>,
+        _rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
+        self.report_error(
+            ExitStatus::Failure,
+            &t!("not-available"),
+            &t!("built-without", feature = "server"),
         );
-
         Ok(())
     }
 
-    fn cmd_import(&mut self, input: &Path) -> Result<()> {
+    fn cmd_import(
+        &mut self,
+        input: &Path,
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<()> {
         if !input.is_dir() {
-            eprintln!("{} {}", theme::error("Folder not found:"), input.display());
+            self.report_error(
+                ExitStatus::NotFound,
+                &t!("folder-not-found"),
+                &input.display().to_string(),
+            );
             return Ok(());
         }
 
@@ -740,89 +6807,283 @@ impl ReplState {
         let (manifest, conflicts) = match self.storage.analyze_import(input) {
             Ok(r) => r,
             Err(e) => {
-                eprintln!("{} {}", theme::error("Import failed:"), e);
+                self.report_failure(&t!("import-failed"), &e.to_string(), &e);
                 return Ok(());
             }
         };
 
-        println!(
-            "{} {} node{}, {} diff{}",
-            theme::info("Folder contains:"),
-            manifest.files.len(),
-            if manifest.files.len() == 1 { "" } else { "s" },
-            manifest.diffs.len(),
-            if manifest.diffs.len() == 1 { "" } else { "s" },
-        );
-
-        // Show conflicts
-        let overwrite = if !conflicts.is_empty() {
-            println!(
-                "\n{} {} node{} with different metadata:",
-                theme::warning("Conflicts:"),
-                conflicts.len(),
-                if conflicts.len() == 1 { "" } else { "s" },
-            );
-            for conflict in &conflicts {
-                println!(
-                    "  {} ({})",
-                    theme::title(&conflict.title),
-                    theme::styled_hash(&conflict.sha256[..16])
-                );
-                for diff in &conflict.diffs {
-                    println!(
-                        "    {}: {} -> {}",
-                        theme::meta(&diff.field),
-                        theme::dim(if diff.local_value.is_empty() {
-                            "(empty)"
-                        } else {
-                            &diff.local_value
-                        }),
-                        &diff.import_value
-                    );
-                }
-            }
-
-            print!("\nOverwrite local metadata with imported values? [y/N]: ");
-            io::stdout().flush()?;
+        if self.narrate() {
+            outln!(
+                "{} {}, {}",
+                theme::info(&t!("folder-contains")),
+                counted!(manifest.files.len(), "nodes-one", "nodes-other"),
+                counted!(manifest.diffs.len(), "diffs-one", "diffs-other")
+            );
+        }
 
-            let mut answer = String::new();
-            io::stdin().read_line(&mut answer)?;
-            let answer = answer.trim().to_lowercase();
-            if answer != "y" && answer != "yes" {
-                // Still import but skip overwrites
-                false
-            } else {
-                true
-            }
+        let resolutions = if conflicts.is_empty() {
+            HashMap::new()
         } else {
-            false
+            self.resolve_conflicts(&conflicts, rl)?
         };
 
         // Phase 2: Execute
-        let result = match self.storage.execute_import(input, &manifest, overwrite) {
+        let result = match self.storage.execute_import(
+            input,
+            &manifest,
+            &resolutions,
+            &mut self.progress_line(),
+            interrupt::arm().token(),
+        ) {
             Ok(r) => r,
             Err(e) => {
-                eprintln!("{} {}", theme::error("Import failed:"), e);
+                self.report_failure(&t!("import-failed"), &e.to_string(), &e);
                 return Ok(());
             }
         };
 
-        println!(
-            "{} {} added, {} skipped, {} overwritten, {} edge{} added, {} edge{} skipped, {} diff{} copied",
-            theme::success("Imported:"),
-            result.nodes_added,
-            result.nodes_skipped,
-            result.nodes_overwritten,
-            result.edges_added,
-            if result.edges_added == 1 { "" } else { "s" },
-            result.edges_skipped,
-            if result.edges_skipped == 1 { "" } else { "s" },
-            result.diffs_copied,
-            if result.diffs_copied == 1 { "" } else { "s" },
+        if self.json() {
+            output::print_json(&json!({
+                "conflicts": conflicts.len(),
+                "nodes_added": result.nodes_added,
+                "nodes_skipped": result.nodes_skipped,
+                "nodes_overwritten": result.nodes_overwritten,
+                "edges_added": result.edges_added,
+                "edges_skipped": result.edges_skipped,
+                "diffs_copied": result.diffs_copied,
+                "attachments_added": result.attachments_added,
+                "attachments_skipped": result.attachments_skipped,
+                "rejected": result.rejected.iter().map(|node| json!({
+                    "sha256": format_hash(&node.sha256),
+                    "title": node.title,
+                    "reason": node.reason,
+                })).collect::<Vec<_>>(),
+            }));
+            return Ok(());
+        }
+        warn_rejected(&result.rejected);
+        if self.quiet {
+            return Ok(());
+        }
+
+        outln!(
+            "{} {}",
+            theme::success(&t!("imported")),
+            t!(
+                "import-summary",
+                added = result.nodes_added,
+                skipped = result.nodes_skipped,
+                overwritten = result.nodes_overwritten,
+                edges_added = counted!(result.edges_added, "edges-one", "edges-other"),
+                edges_skipped = counted!(result.edges_skipped, "edges-one", "edges-other"),
+                diffs = counted!(result.diffs_copied, "diffs-one", "diffs-other"),
+            )
         );
+        if result.attachments_added + result.attachments_skipped > 0 {
+            outln!(
+                "{} {}",
+                theme::success(&t!("attached")),
+                t!(
+                    "attachments-summary",
+                    added = counted!(
+                        result.attachments_added,
+                        "attachments-one",
+                        "attachments-other"
+                    ),
+                    skipped = result.attachments_skipped,
+                )
+            );
+        }
 
         Ok(())
     }
+
+    /// Show each conflicting node's differences and ask whether to accept the
+    /// imported metadata, keep the local metadata, or edit it by hand.
+    /// With `--yes`, every conflict takes the imported values.
+    fn resolve_conflicts(
+        &self,
+        conflicts: &[NodeConflict],
+        rl: &mut Editor<DromosHelper, DefaultHistory>,
+    ) -> Result<HashMap<String, ConflictResolution>> {
+        // Without a prompt, conflicts keep the local metadata (the default answer)
+        if self.rpc {
+            let resolution = if self.assume_yes {
+                ConflictResolution::Import
+            } else {
+                ConflictResolution::KeepLocal
+            };
+            return Ok(conflicts
+                .iter()
+                .map(|c| (c.sha256.clone(), resolution.clone()))
+                .collect());
+        }
+        outln!(
+            "\n{} {}",
+            theme::warning(&t!("conflicts")),
+            t!(
+                "import-conflicts",
+                nodes = counted!(conflicts.len(), "nodes-one", "nodes-other")
+            )
+        );
+
+        let mut resolutions = HashMap::new();
+        // Set by "all" answers, and applied to the remaining conflicts
+        let mut remaining: Option<ConflictResolution> =
+            self.assume_yes.then_some(ConflictResolution::Import);
+        for conflict in conflicts {
+            outln!(
+                "\n  {} ({})",
+                theme::title(&conflict.title),
+                theme::styled_hash(&conflict.sha256[..16])
+            );
+            for diff in &conflict.diffs {
+                outln!(
+                    "    {}: {} -> {}",
+                    theme::meta(&diff.field),
+                    theme::dim(&if diff.local_value.is_empty() {
+                        t!("empty-value")
+                    } else {
+                        diff.local_value.clone()
+                    }),
+                    &diff.import_value
+                );
+            }
+
+            let resolution = match &remaining {
+                Some(resolution) => resolution.clone(),
+                None => loop {
+                    let answer = match rl.readline(&format!("  {}: ", t!("resolve-conflict"))) {
+                        Ok(line) => line.trim().to_string(),
+                        // Ctrl-C/Ctrl-D keep the local metadata
+                        Err(_) => "k".to_string(),
+                    };
+                    match answer.as_str() {
+                        "a" => break ConflictResolution::Import,
+                        "" | "k" => break ConflictResolution::KeepLocal,
+                        "e" => {
+                            let edited = prompt_metadata_edit(rl, &conflict.import_metadata)?;
+                            break ConflictResolution::Edited(edited);
+                        }
+                        "A" | "K" => {
+                            let resolution = if answer == "A" {
+                                ConflictResolution::Import
+                            } else {
+                                ConflictResolution::KeepLocal
+                            };
+                            remaining = Some(resolution.clone());
+                            break resolution;
+                        }
+                        _ => outln!("{}", theme::warning(&t!("resolve-conflict-answers"))),
+                    }
+                },
+            };
+            resolutions.insert(conflict.sha256.clone(), resolution);
+        }
+        outln!();
+
+        Ok(resolutions)
+    }
+}
+
+/// How deeply `source` scripts may source other scripts.
+const MAX_SOURCE_DEPTH: usize = 16;
+
+/// Options `set` can change, with the values each accepts.
+const SET_OPTIONS: &[(&str, &str)] = &[
+    ("output", "<text|json>"),
+    ("confirm", "<on|off>"),
+    ("color", "<on|off>"),
+    ("pager", "<on|off>"),
+    ("export_dir", "<folder|none>"),
+    ("export_git", "<on|off>"),
+    ("strict", "<on|off>"),
+    ("plain", "<on|off>"),
+];
+
+/// Number of recently added ROMs shown by `status`.
+const STATUS_RECENT_COUNT: usize = 5;
+
+/// "2 ROMs, 4 diffs, 1 attachment", from counts of each.
+#[cfg(feature = "server")]
+fn sync_counts((roms, diffs, attachments): (usize, usize, usize)) -> String {
+    format!(
+        "{}, {}, {}",
+        counted!(roms, "roms-one", "roms-other"),
+        counted!(diffs, "diffs-one", "diffs-other"),
+        counted!(attachments, "attachments-one", "attachments-other")
+    )
+}
+
+/// Whether `input` is one of `answers`, a comma-separated list such as
+/// `confirm-yes`.
+fn is_answer(answers: &str, input: &str) -> bool {
+    let input = input.trim().to_lowercase();
+    answers
+        .split(',')
+        .any(|answer| answer.trim().to_lowercase() == input)
+}
+
+/// Print a warning on stderr, recording it in the log file too when logging
+/// is on.
+fn warn(message: impl fmt::Display) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!("{}", message);
+    eprintln!("{} {}", theme::warning(&t!("warning")), message);
+}
+
+/// Warn about the nodes an import left alone because their metadata
+/// couldn't be stored, so they can be fixed where they came from.
+fn warn_rejected(rejected: &[RejectedNode]) {
+    for node in rejected {
+        warn(t!(
+            "metadata-not-imported",
+            title = node.title,
+            hash = &format_hash(&node.sha256)[..16],
+            reason = node.reason,
+        ));
+    }
+}
+
+/// Warn about webhook requests that failed.
+#[cfg(feature = "server")]
+fn report_webhook_failures(failures: &[Failure]) {
+    for failure in failures {
+        warn(t!(
+            "webhook-failed",
+            name = failure.name,
+            event = failure.event,
+            reason = failure.reason,
+        ));
+    }
+}
+
+/// Print a line about a request `serve` or `share` answered.
+#[cfg(feature = "server")]
+fn log_request(request: &server::RequestLog, json: bool, narrate: bool) {
+    let ms = request.duration.as_millis();
+    if json {
+        output::print_json(&json!({
+            "method": request.method,
+            "url": request.url,
+            "status": request.status,
+            "ms": ms,
+        }));
+    } else if narrate {
+        let status = request.status.to_string();
+        let status = if request.status < 400 {
+            theme::success(&status)
+        } else {
+            theme::error(&status)
+        };
+        outln!(
+            "  {} {} {} {}",
+            status,
+            request.method,
+            request.url,
+            theme::dim(&format!("{}ms", ms))
+        );
+    }
 }
 
 /// Format a title with optional version for display.
@@ -881,8 +7142,8 @@ fn prompt_tags(
     existing: &[String],
 ) -> Result<Vec<String>> {
     let initial = existing.join(", ");
-    let prompt_str = "Tags (comma-separated): ";
-    match rl.readline_with_initial(prompt_str, (&initial, "")) {
+    let prompt_str = format!("{}: ", t!("prompt-tags"));
+    match rl.readline_with_initial(&prompt_str, (&initial, "")) {
         Ok(line) => {
             let trimmed = line.trim();
             if trimmed.is_empty() {
@@ -905,8 +7166,8 @@ fn prompt_date(
     existing: Option<&str>,
 ) -> Result<Option<String>> {
     let initial = existing.unwrap_or("");
-    let prompt_str = "Release Date (YYYY-MM-DD): ";
-    match rl.readline_with_initial(prompt_str, (initial, "")) {
+    let prompt_str = format!("{}: ", t!("prompt-release-date"));
+    match rl.readline_with_initial(&prompt_str, (initial, "")) {
         Ok(line) => {
             let trimmed = line.trim();
             if trimmed.is_empty() {
@@ -917,8 +7178,9 @@ fn prompt_date(
                     Ok(Some(date.format("%Y-%m-%d").to_string()))
                 } else {
                     eprintln!(
-                        "{} expected YYYY-MM-DD",
-                        theme::error("Invalid date format,")
+                        "{} {}",
+                        theme::error(&t!("invalid-date-format")),
+                        t!("expected-values", values = "YYYY-MM-DD")
                     );
                     Ok(existing.map(String::from))
                 }
@@ -931,13 +7193,19 @@ fn prompt_date(
 /// Prompt for multi-line description.
 fn prompt_description(existing: Option<&str>) -> Result<Option<String>> {
     let initial = existing.unwrap_or("");
+    let editor = external_editor();
+    if theme::plain() {
+        return prompt_description_line(existing, editor.as_deref());
+    }
 
     // Ask if user wants to enter/edit description
-    print!(
-        "Description (press Enter to {}): ",
-        if initial.is_empty() { "skip" } else { "edit" }
-    );
-    io::stdout().flush()?;
+    let prompt = match (&editor, initial.is_empty()) {
+        (None, true) => t!("prompt-description-skip"),
+        (None, false) => t!("prompt-description-edit"),
+        (Some(editor), true) => t!("prompt-description-skip-editor", editor = editor),
+        (Some(editor), false) => t!("prompt-description-edit-editor", editor = editor),
+    };
+    output::write_prompt(format_args!("{}: ", prompt))?;
 
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
@@ -951,26 +7219,67 @@ fn prompt_description(existing: Option<&str>) -> Result<Option<String>> {
         return Ok(Some(initial.to_string()));
     }
 
-    // Open multi-line editor
-    match edit_multiline("Description:", initial)? {
+    let edited = match editor {
+        Some(editor) if input.trim().eq_ignore_ascii_case("e") => edit_external(&editor, initial)?,
+        _ => edit_multiline(&t!("field-description"), initial)?,
+    };
+    match edited {
+        Some(text) if text.trim().is_empty() => Ok(None),
+        Some(text) => Ok(Some(text)),
+        None => Ok(existing.map(String::from)),
+    }
+}
+
+/// Prompt for a description on one line, for plain mode, where the
+/// multi-line editor's redrawing gets in the way of screen readers. The
+/// current description is read out first; Enter keeps it and `-` clears it.
+fn prompt_description_line(existing: Option<&str>, editor: Option<&str>) -> Result<Option<String>> {
+    if let Some(current) = existing.filter(|d| !d.is_empty()) {
+        output::write_prompt(format_args!(
+            "{}\n",
+            t!(
+                "current-description",
+                description = current.replace('\n', " ")
+            )
+        ))?;
+    }
+    let prompt = match editor {
+        None => t!("prompt-description-keep"),
+        Some(editor) => t!("prompt-description-keep-editor", editor = editor),
+    };
+    output::write_prompt(format_args!("{}: ", prompt))?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let input = input.trim();
+    let edited = match (input, editor) {
+        ("", _) => return Ok(existing.map(String::from)),
+        ("-", _) => return Ok(None),
+        (e, Some(editor)) if e.eq_ignore_ascii_case("e") => {
+            edit_external(editor, existing.unwrap_or(""))?
+        }
+        (text, _) => Some(text.to_string()),
+    };
+    match edited {
         Some(text) if text.trim().is_empty() => Ok(None),
         Some(text) => Ok(Some(text)),
         None => Ok(existing.map(String::from)),
     }
 }
 
-/// Prompt for all metadata fields when adding a new ROM.
-fn prompt_metadata(
+/// Prompt for all metadata fields, starting from existing values
+/// (a new ROM's suggested values, editing a ROM, or resolving an import
+/// conflict by hand).
+fn prompt_metadata_edit(
     rl: &mut Editor<DromosHelper, DefaultHistory>,
-    default_title: &str,
-    _existing: Option<&crate::db::NodeRow>,
+    existing: &NodeMetadata,
 ) -> Result<NodeMetadata> {
-    let title = prompt_with_initial(rl, "Title", default_title)?;
-    let source_url = prompt_optional(rl, "Source URL", None)?;
-    let version = prompt_optional(rl, "Version", None)?;
-    let release_date = prompt_date(rl, None)?;
-    let tags = prompt_tags(rl, &[])?;
-    let description = prompt_description(None)?;
+    let title = prompt_with_initial(rl, &t!("prompt-title"), &existing.title)?;
+    let source_url = prompt_optional(rl, &t!("prompt-source-url"), existing.source_url.as_deref())?;
+    let version = prompt_optional(rl, &t!("prompt-version"), existing.version.as_deref())?;
+    let release_date = prompt_date(rl, existing.release_date.as_deref())?;
+    let tags = prompt_tags(rl, &existing.tags)?;
+    let description = prompt_description(existing.description.as_deref())?;
 
     Ok(NodeMetadata {
         title,
@@ -979,29 +7288,91 @@ fn prompt_metadata(
         release_date,
         tags,
         description,
+        rating: existing.rating,
+        play_status: existing.play_status,
+        regions: existing.regions.clone(),
+        languages: existing.languages.clone(),
     })
 }
 
-/// Prompt for all metadata fields when editing an existing ROM.
-fn prompt_metadata_from_row(
+/// Prompt for a rating from 1 to 5, blank for none.
+fn prompt_rating(
     rl: &mut Editor<DromosHelper, DefaultHistory>,
-    row: &crate::db::NodeRow,
-) -> Result<NodeMetadata> {
-    let title = prompt_with_initial(rl, "Title", &row.title)?;
-    let source_url = prompt_optional(rl, "Source URL", row.source_url.as_deref())?;
-    let version = prompt_optional(rl, "Version", row.version.as_deref())?;
-    let release_date = prompt_date(rl, row.release_date.as_deref())?;
-    let tags = prompt_tags(rl, &row.tags)?;
-    let description = prompt_description(row.description.as_deref())?;
+    existing: Option<u8>,
+) -> Result<Option<u8>> {
+    let initial = existing.map(|r| r.to_string()).unwrap_or_default();
+    let prompt_str = format!("{}: ", t!("prompt-rating", max = MAX_RATING));
+    match rl.readline_with_initial(&prompt_str, (&initial, "")) {
+        Ok(line) => {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                return Ok(None);
+            }
+            match trimmed
+                .parse()
+                .map_err(|_| String::new())
+                .and_then(check_rating)
+            {
+                Ok(rating) => Ok(Some(rating)),
+                Err(_) => {
+                    eprintln!(
+                        "{} {}",
+                        theme::error(&t!("invalid-rating")),
+                        t!("expected-range", min = 1, max = MAX_RATING)
+                    );
+                    Ok(existing)
+                }
+            }
+        }
+        Err(_) => Ok(existing),
+    }
+}
 
-    Ok(NodeMetadata {
-        title,
-        source_url,
-        version,
-        release_date,
-        tags,
-        description,
-    })
+/// Prompt for a comma-separated list of regions or languages, such as
+/// `Japan, USA`; blank for none.
+fn prompt_list<T>(
+    rl: &mut Editor<DromosHelper, DefaultHistory>,
+    prompt: &str,
+    existing: &[T],
+) -> Result<Vec<T>>
+where
+    T: Clone + fmt::Display + FromStr<Err = String> + PartialEq,
+{
+    let initial = region::join_list(existing);
+    let prompt_str = format!("{}: ", prompt);
+    match rl.readline_with_initial(&prompt_str, (&initial, "")) {
+        Ok(line) => Ok(region::parse_list(&line).unwrap_or_else(|_| existing.to_vec())),
+        Err(_) => Ok(existing.to_vec()),
+    }
+}
+
+/// Prompt for a play status, keeping the existing one if left blank.
+fn prompt_play_status(
+    rl: &mut Editor<DromosHelper, DefaultHistory>,
+    existing: PlayStatus,
+) -> Result<PlayStatus> {
+    let names: Vec<&str> = PlayStatus::ALL.iter().map(|s| s.as_str()).collect();
+    let prompt_str = format!("{}: ", t!("prompt-status", statuses = names.join("/")));
+    match rl.readline_with_initial(&prompt_str, (existing.as_str(), "")) {
+        Ok(line) => {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                return Ok(existing);
+            }
+            match trimmed.parse() {
+                Ok(status) => Ok(status),
+                Err(_) => {
+                    eprintln!(
+                        "{} {}",
+                        theme::error(&t!("invalid-status")),
+                        t!("expected-values", values = names.join(", "))
+                    );
+                    Ok(existing)
+                }
+            }
+        }
+        Err(_) => Ok(existing),
+    }
 }
 
 /// Known ROM file extensions to strip from titles.
@@ -1010,6 +7381,40 @@ const ROM_EXTENSIONS: &[&str] = &[
     ".sms", ".gg", ".pce", ".bin", ".iso", ".cue", ".zip", ".7z",
 ];
 
+/// What a file's name says about a ROM (see [`filename`]), without known
+/// ROM extensions.
+fn name_from_filename(path: &Path) -> FileName {
+    filename::parse(&title_from_filename(path))
+}
+
+/// Metadata for a ROM titled by a name with file name codes, such as a DAT's
+/// game name.
+fn metadata_from_name(name: FileName) -> NodeMetadata {
+    NodeMetadata {
+        title: name.title,
+        version: name.version,
+        release_date: name.release_date,
+        tags: name.tags,
+        regions: name.regions,
+        languages: name.languages,
+        ..Default::default()
+    }
+}
+
+/// A wishlist ROM's hashes, with nothing known about its file.
+fn wanted_rom(sha256: [u8; 32], sha1: Option<[u8; 20]>, crc32: Option<u32>) -> RomMetadata {
+    RomMetadata {
+        rom_type: RomType::Nes,
+        sha256,
+        sha1,
+        crc32,
+        content_size: None,
+        filename: None,
+        nes_header: None,
+        source_file_header: None,
+    }
+}
+
 /// Extract a title from a filename, stripping known ROM extensions.
 fn title_from_filename(path: &Path) -> String {
     let filename = path
@@ -1027,7 +7432,100 @@ fn title_from_filename(path: &Path) -> String {
     filename.to_string()
 }
 
+/// Color a `list` cell according to its column.
+fn style_list_cell(column: ListColumn, text: &str) -> String {
+    match column {
+        ListColumn::Title => theme::title(text),
+        ListColumn::Hash => theme::styled_hash(text),
+        ListColumn::Type => theme::label(text),
+        ListColumn::Date | ListColumn::Added => theme::dim(text),
+        ListColumn::Dat => theme::success(text),
+        ListColumn::Version
+        | ListColumn::Size
+        | ListColumn::Links
+        | ListColumn::Tags
+        | ListColumn::Rating
+        | ListColumn::Status
+        | ListColumn::Region
+        | ListColumn::Language => theme::meta(text),
+    }
+}
+
+/// Mark before a DAT match's game name: a check mark, or a word in plain
+/// mode.
+fn verified_mark() -> &'static str {
+    if theme::plain() { "verified:" } else { "✓" }
+}
+
+/// Each item's display text, for JSON lists of regions and languages
+fn display_strings<T: fmt::Display>(items: &[T]) -> Vec<String> {
+    items.iter().map(|item| item.to_string()).collect()
+}
+
+/// JSON for a link to `node`, as `links` and `info` show it
+fn link_json(node: &RomNode, edge: &DiffEdge) -> serde_json::Value {
+    json!({ "node": NodeJson::from(node), "diff_size": edge.diff_size, "note": edge.note })
+}
+
+/// Print a link's note, if it has one, under the line naming the link.
+fn print_link_note(edge: &DiffEdge) {
+    if let Some(note) = edge.note.as_deref().filter(|n| !n.is_empty()) {
+        outln!("       {}", theme::dim(note));
+    }
+}
+
+/// JSON for a collection, as `collection` and `info` show it
+fn collection_json(collection: &CollectionRow) -> serde_json::Value {
+    json!({
+        "name": collection.name,
+        "nodes": collection.nodes,
+        "created_at": collection.created_at,
+    })
+}
+
+/// JSON for the DAT entry a verified node matches
+fn dat_match_json(m: &DatMatch) -> serde_json::Value {
+    json!({ "dat_name": m.dat_name, "game": m.game, "rom_name": m.rom_name })
+}
+
 /// Format a byte size in a human-readable way.
+/// Show each planned metadata change, field by field.
+fn print_metadata_changes(changes: &[MetadataChange]) {
+    for change in changes {
+        let title = theme::title(&format_display_title(
+            &change.before.title,
+            change.before.version.as_deref(),
+        ));
+        let hash = theme::styled_hash(&format_hash(&change.sha256)[..16]);
+        match change.line {
+            Some(line) => outln!(
+                "{} {} ({})",
+                theme::dim(&t!("line-number", line = line)),
+                title,
+                hash
+            ),
+            None => outln!("{} ({})", title, hash),
+        }
+        for (field, from, to) in change.fields() {
+            let show = |text: &str| match text {
+                "" => theme::dim(&t!("none-value")),
+                text => format!("\"{}\"", text),
+            };
+            outln!(
+                "  {}: {} -> {}",
+                theme::label(field),
+                show(&from),
+                show(&to)
+            );
+        }
+    }
+}
+
+/// `yes` or `no`, for report fields.
+fn yes_no(value: bool) -> String {
+    if value { t!("yes") } else { t!("no") }
+}
+
 fn format_size(bytes: i64) -> String {
     let bytes = bytes as f64;
     if bytes < 1024.0 {
@@ -1039,6 +7537,158 @@ fn format_size(bytes: i64) -> String {
     }
 }
 
+/// Print the hash, type, and header summary for a hashed file.
+fn print_hash_details(metadata: &RomMetadata) {
+    outln!("{} {}", t!("field-hash"), format_hash(&metadata.sha256));
+    if let (Some(sha1), Some(crc32)) = (metadata.sha1, metadata.crc32) {
+        outln!(
+            "{} {}  {} {:08x}",
+            t!("field-sha1"),
+            hex::encode(sha1),
+            t!("field-crc32"),
+            crc32
+        );
+    }
+    outln!("{} {}", t!("field-type"), metadata.rom_type);
+
+    if let Some(header) = &metadata.nes_header {
+        outln!("{} {} KB", t!("field-prg-rom"), header.prg_rom_size / 1024);
+        outln!("{} {} KB", t!("field-chr-rom"), header.chr_rom_size / 1024);
+        outln!("{} {}", t!("field-trainer"), yes_no(header.has_trainer));
+    }
+}
+
+/// Print per-phase timings for the last command to stderr.
+fn print_timings(total: Duration) {
+    let phases = timings::snapshot();
+    eprintln!(
+        "{} {}",
+        theme::info(&t!("timings")),
+        t!(
+            "ms-total",
+            ms = format_args!("{:.1}", total.as_secs_f64() * 1000.0)
+        )
+    );
+    for p in &phases {
+        eprintln!(
+            "  {:<18}{:>10.1} ms  {}",
+            p.phase.to_string(),
+            p.total.as_secs_f64() * 1000.0,
+            theme::dim(&format!(
+                "({})",
+                counted!(p.calls, "calls-one", "calls-other")
+            ))
+        );
+    }
+    let measured: Duration = phases.iter().map(|p| p.total).sum();
+    eprintln!(
+        "  {:<18}{:>10.1} ms  {}",
+        t!("timing-other"),
+        total.saturating_sub(measured).as_secs_f64() * 1000.0,
+        theme::dim(&t!("timing-other-phases"))
+    );
+}
+
+/// A ROM as offered by tab completion and the picker.
+fn node_completion(node: &RomNode) -> NodeCompletion {
+    NodeCompletion {
+        hash: format_hash(&node.sha256),
+        title: format_display_title(&node.title, node.version.as_deref()),
+        favorite: false,
+    }
+}
+
+/// Fill in a prompt template: `{count}` is the number of ROMs in the library
+/// and `{db}` the database path.
+fn render_prompt(template: &str, node_count: usize, db_path: &str) -> String {
+    template
+        .replace("{count}", &node_count.to_string())
+        .replace("{db}", db_path)
+}
+
+/// Longest cell `sql` prints before truncating (outside plain mode).
+const SQL_CELL_WIDTH: usize = 48;
+
+/// Render a SQL value as a table cell.
+fn sql_value_text(value: &rusqlite::types::Value) -> String {
+    use rusqlite::types::Value;
+    let text = match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) => f.to_string(),
+        Value::Text(s) => s.replace('\n', " "),
+        Value::Blob(b) => format!("<{} bytes>", b.len()),
+    };
+    if text.chars().count() > SQL_CELL_WIDTH && !theme::plain() {
+        let truncated: String = text.chars().take(SQL_CELL_WIDTH - 3).collect();
+        format!("{}...", truncated)
+    } else {
+        text
+    }
+}
+
+/// Convert a SQL value to JSON; blobs become hex strings.
+fn sql_value_json(value: &rusqlite::types::Value) -> serde_json::Value {
+    use rusqlite::types::Value;
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Integer(i) => json!(i),
+        Value::Real(f) => json!(f),
+        Value::Text(s) => json!(s),
+        Value::Blob(b) => json!(hex::encode(b)),
+    }
+}
+
+/// Pager command line: `$PAGER`, or `less` (`more` on Windows).
+fn pager_command() -> String {
+    match std::env::var("PAGER") {
+        Ok(pager) if !pager.trim().is_empty() => pager,
+        _ if cfg!(target_os = "windows") => "more".to_string(),
+        // -F quits if the output fits on one screen, -X keeps it on screen after
+        _ => "less -FRX".to_string(),
+    }
+}
+
+/// A command that runs `pipeline` in the platform shell.
+fn shell_command(pipeline: &str) -> process::Command {
+    if cfg!(target_os = "windows") {
+        let mut c = process::Command::new("cmd");
+        c.args(["/C", pipeline]);
+        c
+    } else {
+        let mut c = process::Command::new("sh");
+        c.args(["-c", pipeline]);
+        c
+    }
+}
+
+/// Open `url` with the platform's default handler.
+fn open_in_browser(url: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        // Not through `cmd /C start`, which would run whatever follows an
+        // `&` or `|` in the URL as another command
+        let mut c = std::process::Command::new("rundll32");
+        c.arg("url.dll,FileProtocolHandler");
+        c
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    command
+        .arg(url)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Print an indented "Label: value" line with aligned values.
+fn print_field(label: &str, value: &str) {
+    outln!("  {:<14}{}", label, value);
+}
+
 /// Sanitize a string for use as a filename.
 fn sanitize_filename(title: &str) -> String {
     title
@@ -1055,10 +7705,8 @@ fn sanitize_filename(title: &str) -> String {
 
 /// Ensure filename has the correct extension for the ROM type.
 fn ensure_extension(filename: &str, rom_type: RomType) -> String {
-    let ext = match rom_type {
-        RomType::Nes => ".nes",
-    };
-    if filename.to_lowercase().ends_with(ext) {
+    let ext = format!(".{}", rom_type.extension());
+    if filename.to_lowercase().ends_with(&ext) {
         filename.to_string()
     } else {
         format!("{}{}", filename, ext)
@@ -0,0 +1,240 @@
+//! `dromos --rpc`: shell commands over newline-delimited JSON-RPC 2.0.
+//!
+//! Each line on stdin is a request naming a command as its method, with the
+//! command's arguments as string params:
+//!
+//! ```text
+//! {"jsonrpc":"2.0","id":1,"method":"search","params":["tag:hack"]}
+//! ```
+//!
+//! Commands run as with `--json`, and the reply lists the documents they
+//! emitted:
+//!
+//! ```text
+//! {"jsonrpc":"2.0","id":1,"result":{"output":[{"nodes":[...]}]}}
+//! ```
+//!
+//! A command that fails replies with an error whose code is its exit status
+//! (see [`ExitStatus`]), with its documents under `data`. Requests without an
+//! id run without a reply, and `quit` ends the session after replying.
+
+use std::io::{BufRead, Write};
+
+use rustyline::Editor;
+use rustyline::history::DefaultHistory;
+use serde_json::{Value, json};
+
+use crate::error::Result;
+
+use super::completer::DromosHelper;
+use super::output::{self, OutputFormat};
+use super::repl::ReplState;
+use super::status::ExitStatus;
+
+/// The line isn't valid JSON.
+const PARSE_ERROR: i64 = -32700;
+/// The JSON isn't a request object.
+const INVALID_REQUEST: i64 = -32600;
+/// The params aren't an array of strings.
+const INVALID_PARAMS: i64 = -32602;
+
+/// Answer requests read from `input` until it ends or a `quit` request,
+/// writing one reply line per request to `output`.
+pub fn serve(
+    state: &mut ReplState,
+    rl: &mut Editor<DromosHelper, DefaultHistory>,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<()> {
+    state.rpc = true;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (reply, keep_going) = match serde_json::from_str::<Value>(&line) {
+            Err(e) => (
+                Some(error_reply(Value::Null, PARSE_ERROR, &e.to_string(), None)),
+                true,
+            ),
+            Ok(Value::Array(batch)) if batch.is_empty() => (
+                Some(error_reply(
+                    Value::Null,
+                    INVALID_REQUEST,
+                    "Empty batch",
+                    None,
+                )),
+                true,
+            ),
+            // A batch gets one array of replies, once all its requests have run
+            Ok(Value::Array(batch)) => {
+                let mut replies = Vec::new();
+                let mut keep_going = true;
+                for request in batch {
+                    let (reply, more) = handle(state, rl, &request);
+                    replies.extend(reply);
+                    keep_going = more;
+                    if !keep_going {
+                        break;
+                    }
+                }
+                (
+                    (!replies.is_empty()).then_some(Value::Array(replies)),
+                    keep_going,
+                )
+            }
+            Ok(request) => handle(state, rl, &request),
+        };
+        if let Some(reply) = reply {
+            serde_json::to_writer(&mut *output, &reply)?;
+            output.write_all(b"\n")?;
+            output.flush()?;
+        }
+        if !keep_going {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Run one request. Returns its reply (none for a notification) and false
+/// if it asked to quit.
+fn handle(
+    state: &mut ReplState,
+    rl: &mut Editor<DromosHelper, DefaultHistory>,
+    request: &Value,
+) -> (Option<Value>, bool) {
+    let id = request.get("id").cloned();
+    let args = match command_args(request) {
+        Ok(args) => args,
+        Err((code, message)) => {
+            let id = id.unwrap_or(Value::Null);
+            return (Some(error_reply(id, code, &message, None)), true);
+        }
+    };
+
+    // `set output text` can't take effect here; replies are always JSON
+    state.output = OutputFormat::Json;
+    let (result, documents) = output::capture_json(|| state.execute_args(&args, None, rl));
+    let (status, message, keep_going) = match result {
+        Ok(keep_going) => (state.last_status(), None, keep_going),
+        Err(e) => (ExitStatus::from_error(&e), Some(e.to_string()), true),
+    };
+    let Some(id) = id else {
+        return (None, keep_going);
+    };
+    let reply = if status == ExitStatus::Success {
+        json!({ "jsonrpc": "2.0", "id": id, "result": { "output": documents } })
+    } else {
+        let message = message
+            .or_else(|| {
+                documents
+                    .iter()
+                    .rev()
+                    .find_map(|d| d["error"].as_str().map(String::from))
+            })
+            .unwrap_or_else(|| match status {
+                ExitStatus::Cancelled => "Cancelled".to_string(),
+                _ => "Command failed".to_string(),
+            });
+        error_reply(
+            id,
+            i64::from(status.code()),
+            &message,
+            Some(json!({ "output": documents })),
+        )
+    };
+    (Some(reply), keep_going)
+}
+
+/// The command line a request asks for: its method, then its params.
+fn command_args(request: &Value) -> std::result::Result<Vec<String>, (i64, String)> {
+    let invalid = |message: &str| (INVALID_REQUEST, message.to_string());
+    if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err(invalid("Expected \"jsonrpc\": \"2.0\""));
+    }
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return Err(invalid("Expected a \"method\" string"));
+    };
+
+    let mut args = vec![method.to_string()];
+    match request.get("params") {
+        None | Some(Value::Null) => {}
+        Some(Value::Array(params)) => {
+            for param in params {
+                let Some(param) = param.as_str() else {
+                    return Err((
+                        INVALID_PARAMS,
+                        "Params must be an array of strings".to_string(),
+                    ));
+                };
+                args.push(param.to_string());
+            }
+        }
+        Some(_) => {
+            return Err((
+                INVALID_PARAMS,
+                "Params must be an array of strings".to_string(),
+            ));
+        }
+    }
+    Ok(args)
+}
+
+fn error_reply(id: Value, code: i64, message: &str, data: Option<Value>) -> Value {
+    let mut error = json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StorageConfig;
+
+    #[test]
+    fn test_rpc_session() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = StorageConfig {
+            db_path: temp_dir.path().join("dromos.db"),
+            diffs_dir: temp_dir.path().join("diffs"),
+            attachments_dir: temp_dir.path().join("attachments"),
+        };
+        let mut state = ReplState::new(config).unwrap();
+        let mut rl = Editor::new().unwrap();
+        let input = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"status"}"#,
+            "",
+            "not json",
+            r#"{"jsonrpc":"2.0","method":"status"}"#,
+            r#"{"jsonrpc":"2.0","id":"a","method":"info","params":["ffff"]}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"list","params":[1]}"#,
+            r#"[{"jsonrpc":"2.0","id":3,"method":"rm","params":["ffff"]},{"id":4}]"#,
+            r#"{"jsonrpc":"2.0","id":5,"method":"quit"}"#,
+            r#"{"jsonrpc":"2.0","id":6,"method":"status"}"#,
+        ]
+        .join("\n");
+        let mut output = Vec::new();
+        serve(&mut state, &mut rl, &mut input.as_bytes(), &mut output).unwrap();
+
+        let replies: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(replies.len(), 6);
+        assert_eq!(replies[0]["id"], 1);
+        assert!(replies[0]["result"]["output"][0].is_object());
+        assert_eq!(replies[1]["error"]["code"], PARSE_ERROR);
+        assert_eq!(replies[2]["id"], "a");
+        assert_eq!(replies[2]["error"]["code"], 3);
+        assert!(replies[2]["error"]["data"]["output"][0]["error"].is_string());
+        assert_eq!(replies[3]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(replies[4][0]["error"]["code"], 3);
+        assert_eq!(replies[4][1]["error"]["code"], INVALID_REQUEST);
+        assert_eq!(replies[5]["id"], 5);
+        assert!(replies[5]["result"].is_object());
+    }
+}
@@ -0,0 +1,100 @@
+//! What a shell session changed, summed up when it ends.
+//!
+//! [`SessionSummary`] is kept up to date by a storage observer, so every
+//! command that saves a change is counted, including ones run by `source`
+//! scripts, macros, imports, and `watch`.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use crate::storage::{StorageEvent, StorageObserver};
+
+/// Changes saved during a session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionSummary {
+    pub added: usize,
+    pub removed: usize,
+    /// ROMs whose metadata changed, counted once each
+    pub edited: HashSet<[u8; 32]>,
+    /// Linked pairs, lowest hash first, counted once however many diffs
+    /// were written between them
+    pub links: HashSet<([u8; 32], [u8; 32])>,
+    /// Diffs written, and their total size in bytes
+    pub diffs: usize,
+    pub diff_bytes: i64,
+}
+
+impl SessionSummary {
+    pub fn record(&mut self, event: &StorageEvent) {
+        match event {
+            StorageEvent::NodeAdded { .. } => self.added += 1,
+            StorageEvent::NodeRemoved { .. } => self.removed += 1,
+            StorageEvent::MetadataChanged { sha256, .. } => {
+                self.edited.insert(*sha256);
+            }
+            StorageEvent::EdgeCreated {
+                source,
+                target,
+                diff_size,
+            } => {
+                self.links
+                    .insert((*source.min(target), *source.max(target)));
+                self.diffs += 1;
+                self.diff_bytes += diff_size;
+            }
+        }
+    }
+
+    /// Whether nothing was changed
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed == 0 && self.edited.is_empty() && self.diffs == 0
+    }
+
+    /// A storage observer recording into `summary`.
+    pub fn observer(summary: &Arc<Mutex<SessionSummary>>) -> Box<dyn StorageObserver> {
+        let summary = Arc::clone(summary);
+        Box::new(move |event: &StorageEvent| {
+            summary
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .record(event)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut summary = SessionSummary::default();
+        assert!(summary.is_empty());
+        let (a, b) = ([1; 32], [2; 32]);
+        let title = "Zelda".to_string();
+        summary.record(&StorageEvent::NodeAdded {
+            sha256: b,
+            title: title.clone(),
+        });
+        // Linking writes a diff each way
+        for (source, target, diff_size) in [(a, b, 100), (b, a, 120)] {
+            summary.record(&StorageEvent::EdgeCreated {
+                source,
+                target,
+                diff_size,
+            });
+        }
+        for _ in 0..2 {
+            summary.record(&StorageEvent::MetadataChanged {
+                sha256: a,
+                title: title.clone(),
+            });
+        }
+        assert_eq!(summary.added, 1);
+        assert_eq!(summary.links.len(), 1);
+        assert_eq!((summary.diffs, summary.diff_bytes), (2, 220));
+        assert_eq!(summary.edited.len(), 1);
+        assert_eq!(summary.removed, 0);
+        assert!(!summary.is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+//! Command outcomes and their process exit codes.
+//!
+//! When dromos runs a single command from the command line, the outcome of
+//! that command becomes the process exit code so shell scripts can branch on
+//! it. These values are part of the documented CLI contract; don't renumber.
+
+use crate::error::DromosError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExitStatus {
+    /// 0: the command completed.
+    #[default]
+    Success = 0,
+    /// 1: unexpected failure not covered by a more specific code.
+    Failure = 1,
+    /// 2: the command line could not be parsed.
+    Usage = 2,
+    /// 3: a ROM, file, folder, or build path was not found.
+    NotFound = 3,
+    /// 4: the ROM or link is already in the library.
+    AlreadyExists = 4,
+    /// 5: a hash, header, or checksum did not match.
+    VerificationFailed = 5,
+    /// 6: reading or writing a file failed.
+    Io = 6,
+    /// 7: the user declined a confirmation prompt or pressed Ctrl+C.
+    Cancelled = 7,
+}
+
+impl ExitStatus {
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// The status for a process exit code (e.g. from a child `dromos`).
+    pub fn from_code(code: i32) -> ExitStatus {
+        match code {
+            0 => ExitStatus::Success,
+            2 => ExitStatus::Usage,
+            3 => ExitStatus::NotFound,
+            4 => ExitStatus::AlreadyExists,
+            5 => ExitStatus::VerificationFailed,
+            6 => ExitStatus::Io,
+            7 => ExitStatus::Cancelled,
+            _ => ExitStatus::Failure,
+        }
+    }
+
+    /// Classify a library error into an exit status.
+    pub fn from_error(error: &DromosError) -> ExitStatus {
+        match error {
+            DromosError::RomNotFound { .. }
+            | DromosError::FileNotFound { .. }
+            | DromosError::NoPath { .. }
+            | DromosError::SourceNeeded { .. }
+            | DromosError::AttachmentNotFound { .. }
+            | DromosError::NotLinked { .. }
+            | DromosError::CollectionNotFound { .. } => ExitStatus::NotFound,
+            DromosError::BuildStep { source, .. } => ExitStatus::from_error(source),
+            DromosError::RomAlreadyExists { .. }
+            | DromosError::DiffAlreadyExists(..)
+            | DromosError::CollectionExists { .. } => ExitStatus::AlreadyExists,
+            DromosError::Io(e) if e.kind() == std::io::ErrorKind::NotFound => ExitStatus::NotFound,
+            DromosError::FileAccess { source, .. }
+                if source.kind() == std::io::ErrorKind::NotFound =>
+            {
+                ExitStatus::NotFound
+            }
+            DromosError::Io(_) | DromosError::FileAccess { .. } => ExitStatus::Io,
+            DromosError::DiffApplication(_)
+            | DromosError::DiffChecksumMismatch { .. }
+            | DromosError::DiffOutputSize { .. }
+            | DromosError::InvalidNesFile { .. }
+            | DromosError::InconsistentHeader { .. } => ExitStatus::VerificationFailed,
+            DromosError::Cancelled => ExitStatus::Cancelled,
+            _ => ExitStatus::Failure,
+        }
+    }
+}
+
+impl From<ExitStatus> for std::process::ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        std::process::ExitCode::from(status.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_stable() {
+        assert_eq!(ExitStatus::Success.code(), 0);
+        assert_eq!(ExitStatus::Failure.code(), 1);
+        assert_eq!(ExitStatus::Usage.code(), 2);
+        assert_eq!(ExitStatus::NotFound.code(), 3);
+        assert_eq!(ExitStatus::AlreadyExists.code(), 4);
+        assert_eq!(ExitStatus::VerificationFailed.code(), 5);
+        assert_eq!(ExitStatus::Io.code(), 6);
+        assert_eq!(ExitStatus::Cancelled.code(), 7);
+
+        for code in 0..=7 {
+            assert_eq!(ExitStatus::from_code(code).code() as i32, code);
+        }
+        assert_eq!(ExitStatus::from_code(101), ExitStatus::Failure);
+    }
+
+    #[test]
+    fn test_from_error() {
+        let err = DromosError::RomNotFound {
+            hash: "abc".to_string(),
+        };
+        assert_eq!(ExitStatus::from_error(&err), ExitStatus::NotFound);
+
+        let err = DromosError::RomAlreadyExists {
+            hash: "abc".to_string(),
+        };
+        assert_eq!(ExitStatus::from_error(&err), ExitStatus::AlreadyExists);
+
+        let err = DromosError::Io(std::io::Error::other("disk full"));
+        assert_eq!(ExitStatus::from_error(&err), ExitStatus::Io);
+    }
+}
@@ -1,15 +1,30 @@
 //! Color theming for CLI output.
 //!
-//! Respects `NO_COLOR` environment variable and TTY detection.
+//! Respects `NO_COLOR` environment variable and TTY detection. Colors come
+//! from a [`Palette`] of named roles, chosen by the `theme` preset and
+//! `color.<role>` overrides in `dromos.conf`.
+//!
+//! Plain mode (`--plain`, `plain = on`) is for screen readers and braille
+//! displays: besides turning colors off, it tells the shell to print labeled
+//! lines instead of aligned columns and symbols, and to read input a line at
+//! a time.
 
+use std::collections::BTreeMap;
 use std::io::IsTerminal;
+use std::sync::RwLock;
 use std::sync::atomic::{AtomicBool, Ordering};
 
-use crossterm::style::Stylize;
+use crossterm::style::{Color, Stylize};
 
 /// Global flag for whether colors are enabled.
 static COLORS_ENABLED: AtomicBool = AtomicBool::new(false);
 
+/// Global flag for plain, screen-reader-friendly output.
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Active palette; the dark preset until `set_palette` is called.
+static PALETTE: RwLock<Palette> = RwLock::new(Palette::DARK);
+
 /// Initialize color support detection.
 /// Call this once at startup before any themed output.
 pub fn init() {
@@ -18,133 +33,295 @@ pub fn init() {
 }
 
 /// Check if colors are currently enabled.
-fn colors_enabled() -> bool {
+pub fn colors_enabled() -> bool {
     COLORS_ENABLED.load(Ordering::Relaxed)
 }
 
-// ─── Semantic Functions ─────────────────────────────────────────────────────
+/// Turn colors on or off, overriding terminal detection (`set color`).
+pub fn set_colors_enabled(enabled: bool) {
+    COLORS_ENABLED.store(enabled, Ordering::Relaxed);
+}
 
-/// Format text as an error (red).
-pub fn error(text: &str) -> String {
-    if colors_enabled() {
-        text.red().to_string()
-    } else {
-        text.to_string()
+/// Check if plain output is on.
+pub fn plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// Turn plain output on or off (`set plain`). Turning it on also turns
+/// colors off.
+pub fn set_plain(enabled: bool) {
+    PLAIN.store(enabled, Ordering::Relaxed);
+    if enabled {
+        set_colors_enabled(false);
     }
 }
 
-/// Format text as a warning (yellow).
-pub fn warning(text: &str) -> String {
-    if colors_enabled() {
-        text.yellow().to_string()
-    } else {
-        text.to_string()
+// ─── Palette ────────────────────────────────────────────────────────────────
+
+/// A color plus optional bold, used for one role.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoleStyle {
+    pub color: Color,
+    pub bold: bool,
+}
+
+impl RoleStyle {
+    const fn plain(color: Color) -> Self {
+        RoleStyle { color, bold: false }
+    }
+
+    const fn bold(color: Color) -> Self {
+        RoleStyle { color, bold: true }
+    }
+
+    /// Parse `<color>` or `bold <color>`, using crossterm color names
+    /// (e.g. `red`, `dark_grey`, `bold blue`).
+    pub fn parse(value: &str) -> Option<RoleStyle> {
+        let value = value.trim();
+        let (bold, color) = match value.strip_prefix("bold ") {
+            Some(rest) => (true, rest.trim()),
+            None => (false, value),
+        };
+        let color = Color::try_from(color).ok()?;
+        Some(RoleStyle { color, bold })
     }
 }
 
-/// Format text as success (green).
-pub fn success(text: &str) -> String {
-    if colors_enabled() {
-        text.green().to_string()
-    } else {
-        text.to_string()
+/// Styles for every semantic role used in CLI output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub error: RoleStyle,
+    pub warning: RoleStyle,
+    pub success: RoleStyle,
+    pub info: RoleStyle,
+    pub title: RoleStyle,
+    pub label: RoleStyle,
+    pub meta: RoleStyle,
+    pub prompt: RoleStyle,
+    pub dim: RoleStyle,
+    pub header: RoleStyle,
+    pub logo: RoleStyle,
+    pub build_version: RoleStyle,
+    pub build_date: RoleStyle,
+    pub hash: RoleStyle,
+    pub hash_suffix: RoleStyle,
+}
+
+impl Palette {
+    /// Default palette for dark terminal backgrounds.
+    pub const DARK: Palette = Palette {
+        error: RoleStyle::plain(Color::Red),
+        warning: RoleStyle::plain(Color::Yellow),
+        success: RoleStyle::plain(Color::Green),
+        info: RoleStyle::plain(Color::Cyan),
+        title: RoleStyle::plain(Color::White),
+        label: RoleStyle::plain(Color::Yellow),
+        meta: RoleStyle::plain(Color::Cyan),
+        prompt: RoleStyle::bold(Color::Blue),
+        dim: RoleStyle::plain(Color::DarkGrey),
+        header: RoleStyle::bold(Color::Reset),
+        logo: RoleStyle::plain(Color::Blue),
+        build_version: RoleStyle::plain(Color::DarkGreen),
+        build_date: RoleStyle::plain(Color::DarkRed),
+        hash: RoleStyle::plain(Color::Blue),
+        hash_suffix: RoleStyle::plain(Color::DarkBlue),
+    };
+
+    /// Palette for light terminal backgrounds: no white or bright yellow/cyan text.
+    pub const LIGHT: Palette = Palette {
+        error: RoleStyle::plain(Color::DarkRed),
+        warning: RoleStyle::plain(Color::DarkYellow),
+        success: RoleStyle::plain(Color::DarkGreen),
+        info: RoleStyle::plain(Color::DarkCyan),
+        title: RoleStyle::plain(Color::Black),
+        label: RoleStyle::plain(Color::DarkMagenta),
+        meta: RoleStyle::plain(Color::DarkCyan),
+        prompt: RoleStyle::bold(Color::DarkBlue),
+        dim: RoleStyle::plain(Color::DarkGrey),
+        header: RoleStyle::bold(Color::Reset),
+        logo: RoleStyle::plain(Color::DarkBlue),
+        build_version: RoleStyle::plain(Color::DarkGreen),
+        build_date: RoleStyle::plain(Color::DarkRed),
+        hash: RoleStyle::plain(Color::DarkBlue),
+        hash_suffix: RoleStyle::plain(Color::Blue),
+    };
+
+    /// Terminal default color for everything; only bold is kept.
+    pub const PLAIN: Palette = Palette {
+        error: RoleStyle::plain(Color::Reset),
+        warning: RoleStyle::plain(Color::Reset),
+        success: RoleStyle::plain(Color::Reset),
+        info: RoleStyle::plain(Color::Reset),
+        title: RoleStyle::plain(Color::Reset),
+        label: RoleStyle::plain(Color::Reset),
+        meta: RoleStyle::plain(Color::Reset),
+        prompt: RoleStyle::bold(Color::Reset),
+        dim: RoleStyle::plain(Color::Reset),
+        header: RoleStyle::bold(Color::Reset),
+        logo: RoleStyle::plain(Color::Reset),
+        build_version: RoleStyle::plain(Color::Reset),
+        build_date: RoleStyle::plain(Color::Reset),
+        hash: RoleStyle::plain(Color::Reset),
+        hash_suffix: RoleStyle::plain(Color::Reset),
+    };
+
+    /// Names accepted by `theme = ...` in the config file.
+    pub const PRESETS: [&'static str; 3] = ["dark", "light", "plain"];
+
+    pub fn preset(name: &str) -> Option<Palette> {
+        match name.to_lowercase().as_str() {
+            "dark" => Some(Palette::DARK),
+            "light" => Some(Palette::LIGHT),
+            "plain" => Some(Palette::PLAIN),
+            _ => None,
+        }
+    }
+
+    fn role_mut(&mut self, role: &str) -> Option<&mut RoleStyle> {
+        Some(match role {
+            "error" => &mut self.error,
+            "warning" => &mut self.warning,
+            "success" => &mut self.success,
+            "info" => &mut self.info,
+            "title" => &mut self.title,
+            "label" => &mut self.label,
+            "meta" => &mut self.meta,
+            "prompt" => &mut self.prompt,
+            "dim" => &mut self.dim,
+            "header" => &mut self.header,
+            "logo" => &mut self.logo,
+            "build_version" => &mut self.build_version,
+            "build_date" => &mut self.build_date,
+            "hash" => &mut self.hash,
+            "hash_suffix" => &mut self.hash_suffix,
+            _ => return None,
+        })
+    }
+
+    /// Build a palette from a preset name (default: dark) and per-role overrides.
+    pub fn from_config(
+        preset: Option<&str>,
+        colors: &BTreeMap<String, String>,
+    ) -> Result<Palette, String> {
+        let mut palette = match preset {
+            Some(name) => Palette::preset(name).ok_or_else(|| {
+                format!(
+                    "unknown theme `{}` (expected one of: {})",
+                    name,
+                    Palette::PRESETS.join(", ")
+                )
+            })?,
+            None => Palette::DARK,
+        };
+
+        for (role, value) in colors {
+            let style = RoleStyle::parse(value)
+                .ok_or_else(|| format!("unknown color `{}` for `{}`", value, role))?;
+            *palette
+                .role_mut(role)
+                .ok_or_else(|| format!("unknown color role `{}`", role))? = style;
+        }
+        Ok(palette)
     }
 }
 
-/// Format text as info (cyan).
-pub fn info(text: &str) -> String {
-    if colors_enabled() {
-        text.cyan().to_string()
+/// Replace the active palette.
+pub fn set_palette(palette: Palette) {
+    *PALETTE.write().unwrap_or_else(|e| e.into_inner()) = palette;
+}
+
+fn palette() -> Palette {
+    *PALETTE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Apply the style chosen by `role` if colors are enabled.
+fn paint(text: &str, role: fn(&Palette) -> RoleStyle) -> String {
+    if !colors_enabled() {
+        return text.to_string();
+    }
+    let style = role(&palette());
+    let mut styled = text.stylize();
+    if style.color != Color::Reset {
+        styled = styled.with(style.color);
+    }
+    if style.bold {
+        styled.bold().to_string()
     } else {
-        text.to_string()
+        styled.to_string()
     }
 }
 
+// ─── Semantic Functions ─────────────────────────────────────────────────────
+
+/// Format text as an error.
+pub fn error(text: &str) -> String {
+    paint(text, |p| p.error)
+}
+
+/// Format text as a warning.
+pub fn warning(text: &str) -> String {
+    paint(text, |p| p.warning)
+}
+
+/// Format text as success.
+pub fn success(text: &str) -> String {
+    paint(text, |p| p.success)
+}
+
+/// Format text as info.
+pub fn info(text: &str) -> String {
+    paint(text, |p| p.info)
+}
+
 // ─── Data Display Functions ────────────────────────────────────────────────
 
-/// Format a title (bright white).
+/// Format a title.
 pub fn title(text: &str) -> String {
-    if colors_enabled() {
-        text.white().to_string()
-    } else {
-        text.to_string()
-    }
+    paint(text, |p| p.title)
 }
 
-/// Format a categorical label like ROM type (yellow).
+/// Format a categorical label like ROM type.
 pub fn label(text: &str) -> String {
-    if colors_enabled() {
-        text.yellow().to_string()
-    } else {
-        text.to_string()
-    }
+    paint(text, |p| p.label)
 }
 
-/// Format secondary metadata like version or link count (cyan).
+/// Format secondary metadata like version or link count.
 pub fn meta(text: &str) -> String {
-    if colors_enabled() {
-        text.cyan().to_string()
-    } else {
-        text.to_string()
-    }
+    paint(text, |p| p.meta)
 }
 
 // ─── Chrome Functions ───────────────────────────────────────────────────────
 
-/// Format text as a prompt (bright blue, bold).
+/// Format text as a prompt.
 pub fn prompt(text: &str) -> String {
-    if colors_enabled() {
-        text.blue().bold().to_string()
-    } else {
-        text.to_string()
-    }
+    paint(text, |p| p.prompt)
 }
 
-/// Format text as dim/secondary (dark grey).
+/// Format text as dim/secondary.
 pub fn dim(text: &str) -> String {
-    if colors_enabled() {
-        text.dark_grey().to_string()
-    } else {
-        text.to_string()
-    }
+    paint(text, |p| p.dim)
 }
 
-/// Format text as a header (bold white).
+/// Format text as a header.
 pub fn header(text: &str) -> String {
-    if colors_enabled() {
-        text.bold().to_string()
-    } else {
-        text.to_string()
-    }
+    paint(text, |p| p.header)
 }
 
 // ─── Banner Functions ──────────────────────────────────────────────────────
 
-/// Format the ASCII logo (bright blue).
+/// Format the ASCII logo.
 pub fn logo(text: &str) -> String {
-    if colors_enabled() {
-        text.blue().to_string()
-    } else {
-        text.to_string()
-    }
+    paint(text, |p| p.logo)
 }
 
-/// Format the build version in banner (dark green).
+/// Format the build version in banner.
 pub fn build_version(text: &str) -> String {
-    if colors_enabled() {
-        text.dark_green().to_string()
-    } else {
-        text.to_string()
-    }
+    paint(text, |p| p.build_version)
 }
 
-/// Format the build date in banner (dark red).
+/// Format the build date in banner.
 pub fn build_date(text: &str) -> String {
-    if colors_enabled() {
-        text.dark_red().to_string()
-    } else {
-        text.to_string()
-    }
+    paint(text, |p| p.build_date)
 }
 
 const LOGO: [&str; 5] = [
@@ -155,23 +332,55 @@ const LOGO: [&str; 5] = [
     r" \__,_|_|  \___/|_| |_| |_|\___/|___/",
 ];
 
-/// Print the startup banner with version and build time.
+/// Print the startup banner with version and build time; a single line
+/// in plain mode.
 pub fn print_banner(version: &str, build_time: &str) {
-    println!("{}", logo(LOGO[0]));
-    println!("{}", logo(LOGO[1]));
-    println!("{}", logo(LOGO[2]));
-    println!("{}  {}", logo(LOGO[3]), build_version(version));
-    println!("{}  {}", logo(LOGO[4]), build_date(build_time));
+    if plain() {
+        outln!("dromos {}, built {}", version, build_time);
+        return;
+    }
+    outln!("{}", logo(LOGO[0]));
+    outln!("{}", logo(LOGO[1]));
+    outln!("{}", logo(LOGO[2]));
+    outln!("{}  {}", logo(LOGO[3]), build_version(version));
+    outln!("{}  {}", logo(LOGO[4]), build_date(build_time));
 }
 
 // ─── Helper Functions ───────────────────────────────────────────────────────
 
 /// Format a hash with a styled suffix ("...").
-/// Takes the short hash prefix (e.g., first 16 chars) and appends green "...".
+/// Takes the short hash prefix (e.g., first 16 chars) and appends a styled "...".
+/// In plain mode the prefix is returned alone.
 pub fn styled_hash(short_hash: &str) -> String {
-    if colors_enabled() {
-        format!("{}{}", short_hash.blue(), "...".dark_blue())
-    } else {
-        format!("{}...", short_hash)
+    if plain() {
+        return short_hash.to_string();
+    }
+    format!(
+        "{}{}",
+        paint(short_hash, |p| p.hash),
+        paint("...", |p| p.hash_suffix)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_from_config() {
+        let mut colors = BTreeMap::new();
+        assert_eq!(Palette::from_config(None, &colors), Ok(Palette::DARK));
+
+        colors.insert("title".to_string(), "bold dark_magenta".to_string());
+        let palette = Palette::from_config(Some("Light"), &colors).unwrap();
+        assert_eq!(palette.title, RoleStyle::bold(Color::DarkMagenta));
+        assert_eq!(palette.error, Palette::LIGHT.error);
+
+        assert!(Palette::from_config(Some("solarized"), &BTreeMap::new()).is_err());
+        colors.insert("title".to_string(), "chartreuse".to_string());
+        assert!(Palette::from_config(None, &colors).is_err());
+        colors.clear();
+        colors.insert("banner".to_string(), "red".to_string());
+        assert!(Palette::from_config(None, &colors).is_err());
     }
 }
@@ -0,0 +1,99 @@
+//! Folder watching for the `watch` command.
+//!
+//! Wraps a `notify` watcher and debounces its events so a ROM file is only
+//! reported once it has stopped changing (i.e. the copy has finished).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::error::{DromosError, Result};
+use crate::rom::is_rom_file;
+
+/// How long a file must go without new events before it is reported.
+const SETTLE_TIME: Duration = Duration::from_millis(1000);
+
+/// How often to check for settled files and the stop signal.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+enum WatchMessage {
+    Changed(PathBuf),
+    Stop,
+}
+
+pub struct FolderWatcher {
+    // Kept alive for the lifetime of the watch; dropping it stops events
+    _watcher: RecommendedWatcher,
+    rx: Receiver<WatchMessage>,
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl FolderWatcher {
+    /// Start watching `dir` recursively. Pressing Enter on stdin stops the watch.
+    pub fn start(dir: &Path) -> Result<Self> {
+        let (tx, rx) = mpsc::channel();
+
+        let fs_tx = tx.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res
+                && matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+            {
+                for path in event.paths {
+                    let _ = fs_tx.send(WatchMessage::Changed(path));
+                }
+            }
+        })
+        .map_err(|e| DromosError::Io(std::io::Error::other(e)))?;
+        watcher
+            .watch(dir, RecursiveMode::Recursive)
+            .map_err(|e| DromosError::Io(std::io::Error::other(e)))?;
+
+        std::thread::spawn(move || {
+            let mut line = String::new();
+            let _ = std::io::stdin().read_line(&mut line);
+            let _ = tx.send(WatchMessage::Stop);
+        });
+
+        Ok(FolderWatcher {
+            _watcher: watcher,
+            rx,
+            pending: HashMap::new(),
+        })
+    }
+
+    /// Block until one or more ROM files have settled.
+    /// Returns None once the user stops the watch.
+    pub fn next_batch(&mut self) -> Option<Vec<PathBuf>> {
+        loop {
+            match self.rx.recv_timeout(POLL_INTERVAL) {
+                Ok(WatchMessage::Changed(path)) => {
+                    if is_rom_file(&path) {
+                        self.pending.insert(path, Instant::now());
+                    }
+                }
+                Ok(WatchMessage::Stop) | Err(RecvTimeoutError::Disconnected) => return None,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+
+            let settled: Vec<PathBuf> = self
+                .pending
+                .iter()
+                .filter(|(_, last)| last.elapsed() >= SETTLE_TIME)
+                .map(|(path, _)| path.clone())
+                .collect();
+            if !settled.is_empty() {
+                for path in &settled {
+                    self.pending.remove(path);
+                }
+                let mut ready: Vec<PathBuf> = settled.into_iter().filter(|p| p.is_file()).collect();
+                ready.sort();
+                if !ready.is_empty() {
+                    return Some(ready);
+                }
+            }
+        }
+    }
+}
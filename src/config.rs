@@ -1,10 +1,13 @@
 use directories::ProjectDirs;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub struct StorageConfig {
     pub db_path: PathBuf,
     pub diffs_dir: PathBuf,
+    /// Attached files, each named by the SHA-256 of its contents
+    pub attachments_dir: PathBuf,
 }
 
 impl StorageConfig {
@@ -15,6 +18,7 @@ impl StorageConfig {
         Some(StorageConfig {
             db_path: data_dir.join("dromos.db"),
             diffs_dir: data_dir.join("diffs"),
+            attachments_dir: data_dir.join("attachments"),
         })
     }
 
@@ -23,6 +27,436 @@ impl StorageConfig {
             std::fs::create_dir_all(parent)?;
         }
         std::fs::create_dir_all(&self.diffs_dir)?;
+        std::fs::create_dir_all(&self.attachments_dir)?;
         Ok(())
     }
 }
+
+/// Shell prompt used when the config file doesn't set one.
+pub const DEFAULT_PROMPT: &str = "dromos> ";
+
+/// Path of the user's config file (e.g. `~/.config/dromos/dromos.conf`).
+pub fn config_file_path() -> Option<PathBuf> {
+    let proj_dirs = ProjectDirs::from("", "", "dromos")?;
+    Some(proj_dirs.config_dir().join("dromos.conf"))
+}
+
+/// Folder of translations of shell messages, next to the config file (see
+/// [`crate::cli::i18n`]).
+pub fn locales_dir() -> Option<PathBuf> {
+    let proj_dirs = ProjectDirs::from("", "", "dromos")?;
+    Some(proj_dirs.config_dir().join("locales"))
+}
+
+/// Path of the log file `--log` writes (e.g. `~/.local/share/dromos/dromos.log`).
+pub fn log_file_path() -> Option<PathBuf> {
+    let proj_dirs = ProjectDirs::from("", "", "dromos")?;
+    Some(proj_dirs.data_dir().join("dromos.log"))
+}
+
+/// User preferences from `dromos.conf`.
+///
+/// The file is line-based; blank lines and lines starting with `#` are ignored:
+///
+/// ```text
+/// alias b = build
+/// macro ingest = add $1; link $1 $2
+/// history_size = 5000
+/// history_ignore_space = on
+/// theme = light
+/// color.title = bold black
+/// prompt = "{count} ROMs> "
+/// list_columns = title,version,type,size,links
+/// list_sort = size
+/// output = json
+/// confirm = off
+/// color = on
+/// plain = on
+/// pager = on
+/// export_dir = ~/dromos-exports
+/// export_git = on
+/// strict = on
+/// log = on
+/// game_db = /home/me/openvgdb.sqlite
+/// metadata_cache_days = 30
+/// emulator.nes = mesen {rom}
+/// plugin.unif = python3 /home/me/unif-plugin.py
+/// webhook.discord = https://discord.com/api/webhooks/123/abc
+/// autotag.MMC3 = mapper:4
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserConfig {
+    /// Command name -> replacement text
+    pub aliases: BTreeMap<String, String>,
+    /// Macro name -> `;`-separated command templates using `$1`..`$9` and `$*`
+    pub macros: BTreeMap<String, String>,
+    /// Maximum number of shell history entries kept; 0 disables saved history
+    pub history_size: usize,
+    /// Don't record lines that start with a space (for sensitive commands)
+    pub history_ignore_space: bool,
+    /// Color preset name (`dark`, `light`, or `plain`)
+    pub theme: Option<String>,
+    /// Role name -> color override, e.g. `title` -> `bold black`
+    pub colors: BTreeMap<String, String>,
+    /// Shell prompt template; `{count}` and `{db}` are filled in
+    pub prompt: String,
+    /// Default `list` columns, comma-separated (e.g. `title,size,links`)
+    pub list_columns: Option<String>,
+    /// Default `list` sort column; a leading `-` sorts descending
+    pub list_sort: Option<String>,
+    /// Default output format (`text` or `json`)
+    pub output: Option<String>,
+    /// Ask before destructive or bulk operations
+    pub confirm: bool,
+    /// Force colors on or off; None detects the terminal
+    pub color: Option<bool>,
+    /// Plain, screen-reader-friendly output: no colors, columns, symbols,
+    /// or multi-line editor
+    pub plain: bool,
+    /// Page long output through `$PAGER`
+    pub pager: bool,
+    /// Folder that relative `export` paths are placed in
+    pub export_dir: Option<PathBuf>,
+    /// Commit each export folder to Git after writing it
+    pub export_git: bool,
+    /// Refuse to add ROMs with inconsistent headers
+    pub strict: bool,
+    /// Write operations, warnings, and errors to the log file
+    pub log: bool,
+    /// OpenVGDB database used to suggest titles, regions, and release dates
+    /// for new ROMs
+    pub game_db: Option<PathBuf>,
+    /// Days cached metadata lookups are used before fetching again; 0
+    /// turns the cache off
+    pub metadata_cache_days: u64,
+    /// ROM type -> emulator command line for `play`, e.g. `nes` ->
+    /// `mesen {rom}`
+    pub emulators: BTreeMap<String, String>,
+    /// Plugin name -> command line (see [`crate::plugin`])
+    pub plugins: BTreeMap<String, String>,
+    /// Webhook name -> URL that `serve` and `watch` POST library changes to
+    pub webhooks: BTreeMap<String, String>,
+    /// Tag -> search query; new ROMs matching the query get the tag
+    pub autotags: BTreeMap<String, String>,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            aliases: BTreeMap::new(),
+            macros: BTreeMap::new(),
+            history_size: 1000,
+            history_ignore_space: false,
+            theme: None,
+            colors: BTreeMap::new(),
+            prompt: DEFAULT_PROMPT.to_string(),
+            list_columns: None,
+            list_sort: None,
+            output: None,
+            confirm: true,
+            color: None,
+            plain: false,
+            pager: false,
+            export_dir: None,
+            export_git: false,
+            strict: false,
+            log: false,
+            game_db: None,
+            metadata_cache_days: 30,
+            emulators: BTreeMap::new(),
+            plugins: BTreeMap::new(),
+            webhooks: BTreeMap::new(),
+            autotags: BTreeMap::new(),
+        }
+    }
+}
+
+impl UserConfig {
+    /// Load the config file, returning defaults if it doesn't exist.
+    pub fn load(path: &Path) -> Result<UserConfig, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => UserConfig::parse(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(UserConfig::default()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<UserConfig, String> {
+        let mut config = UserConfig::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = |msg: &str| format!("line {}: {}", i + 1, msg);
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| err("expected `key = value`"))?;
+            let value = value.trim().to_string();
+            let mut key_parts = key.split_whitespace();
+
+            match (key_parts.next(), key_parts.next(), key_parts.next()) {
+                (Some("alias"), Some(name), None) => {
+                    config.aliases.insert(name.to_lowercase(), value);
+                }
+                (Some("macro"), Some(name), None) => {
+                    config.macros.insert(name.to_lowercase(), value);
+                }
+                (Some("history_size"), None, None) => {
+                    config.history_size = value
+                        .parse()
+                        .map_err(|_| err("history_size must be a number"))?;
+                }
+                (Some("history_ignore_space"), None, None) => {
+                    config.history_ignore_space =
+                        parse_switch(&value).ok_or_else(|| err("expected `on` or `off`"))?;
+                }
+                (Some("theme"), None, None) => config.theme = Some(value),
+                (Some("prompt"), None, None) => config.prompt = unquote(&value).to_string(),
+                (Some("list_columns"), None, None) => config.list_columns = Some(value),
+                (Some("list_sort"), None, None) => config.list_sort = Some(value),
+                (Some("output"), None, None) => config.output = Some(value),
+                (Some("confirm"), None, None) => {
+                    config.confirm =
+                        parse_switch(&value).ok_or_else(|| err("expected `on` or `off`"))?;
+                }
+                (Some("color"), None, None) => {
+                    config.color =
+                        Some(parse_switch(&value).ok_or_else(|| err("expected `on` or `off`"))?);
+                }
+                (Some("plain"), None, None) => {
+                    config.plain =
+                        parse_switch(&value).ok_or_else(|| err("expected `on` or `off`"))?;
+                }
+                (Some("pager"), None, None) => {
+                    config.pager =
+                        parse_switch(&value).ok_or_else(|| err("expected `on` or `off`"))?;
+                }
+                (Some("export_dir"), None, None) => {
+                    config.export_dir = match unquote(&value) {
+                        "" | "none" => None,
+                        dir => Some(PathBuf::from(dir)),
+                    };
+                }
+                (Some("export_git"), None, None) => {
+                    config.export_git =
+                        parse_switch(&value).ok_or_else(|| err("expected `on` or `off`"))?;
+                }
+                (Some("strict"), None, None) => {
+                    config.strict =
+                        parse_switch(&value).ok_or_else(|| err("expected `on` or `off`"))?;
+                }
+                (Some("log"), None, None) => {
+                    config.log =
+                        parse_switch(&value).ok_or_else(|| err("expected `on` or `off`"))?;
+                }
+                (Some("game_db"), None, None) => {
+                    config.game_db = match unquote(&value) {
+                        "" | "none" => None,
+                        path => Some(PathBuf::from(path)),
+                    };
+                }
+                (Some("metadata_cache_days"), None, None) => {
+                    config.metadata_cache_days = value
+                        .parse()
+                        .map_err(|_| err("metadata_cache_days must be a number"))?;
+                }
+                (Some(key), None, None) if key.starts_with("emulator.") => {
+                    config
+                        .emulators
+                        .insert(key["emulator.".len()..].to_lowercase(), value);
+                }
+                (Some(key), None, None) if key.starts_with("plugin.") => {
+                    config
+                        .plugins
+                        .insert(key["plugin.".len()..].to_lowercase(), value);
+                }
+                (Some(key), None, None) if key.starts_with("webhook.") => {
+                    config.webhooks.insert(
+                        key["webhook.".len()..].to_lowercase(),
+                        unquote(&value).to_string(),
+                    );
+                }
+                // Tags keep their case, unlike the names above
+                (Some(key), None, None) if key.starts_with("autotag.") => {
+                    config
+                        .autotags
+                        .insert(key["autotag.".len()..].to_string(), value);
+                }
+                (Some(key), None, None) if key.starts_with("color.") => {
+                    config
+                        .colors
+                        .insert(key["color.".len()..].to_lowercase(), value);
+                }
+                _ => return Err(err(&format!("unknown setting `{}`", key.trim()))),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Set `key = value` in the config file, replacing an existing line for the
+/// key or appending one. Comments and other settings are kept as written.
+pub fn save_setting(path: &Path, key: &str, value: &str) -> std::io::Result<()> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, replace_setting(&text, key, value))
+}
+
+/// Config text with the line for `key` replaced by `key = value` (appended
+/// if the key isn't set).
+fn replace_setting(text: &str, key: &str, value: &str) -> String {
+    let setting = format!("{} = {}", key, value);
+    let mut lines: Vec<&str> = text.lines().collect();
+    let existing = lines.iter().position(|line| {
+        line.split_once('=')
+            .is_some_and(|(k, _)| !line.trim_start().starts_with('#') && k.trim() == key)
+    });
+    match existing {
+        Some(i) => lines[i] = &setting,
+        None => lines.push(&setting),
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Strip one pair of surrounding double quotes, so values can keep
+/// leading or trailing spaces.
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "on" | "true" | "yes" => Some(true),
+        "off" | "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_user_config() {
+        let config = UserConfig::parse(
+            "# my shortcuts\n\nalias b = build\nmacro Ingest = add $1; link $1 $2\n",
+        )
+        .unwrap();
+        assert_eq!(config.aliases.get("b").map(String::as_str), Some("build"));
+        assert_eq!(
+            config.macros.get("ingest").map(String::as_str),
+            Some("add $1; link $1 $2")
+        );
+
+        assert_eq!(config.history_size, 1000);
+        assert!(!config.history_ignore_space);
+
+        let config = UserConfig::parse("history_size = 50\nhistory_ignore_space = on").unwrap();
+        assert_eq!(config.history_size, 50);
+        assert!(config.history_ignore_space);
+
+        let config = UserConfig::parse("theme = light\ncolor.Title = bold black").unwrap();
+        assert_eq!(config.theme.as_deref(), Some("light"));
+        assert_eq!(
+            config.colors.get("title").map(String::as_str),
+            Some("bold black")
+        );
+
+        let config = UserConfig::parse("prompt = \"{count}> \"").unwrap();
+        assert_eq!(config.prompt, "{count}> ");
+        assert_eq!(UserConfig::default().prompt, DEFAULT_PROMPT);
+
+        let config = UserConfig::parse("list_columns = title,size\nlist_sort = -size").unwrap();
+        assert_eq!(config.list_columns.as_deref(), Some("title,size"));
+        assert_eq!(config.list_sort.as_deref(), Some("-size"));
+
+        let config =
+            UserConfig::parse("output = json\nconfirm = off\ncolor = off\npager = on").unwrap();
+        assert_eq!(config.output.as_deref(), Some("json"));
+        assert!(!config.confirm);
+        assert_eq!(config.color, Some(false));
+        assert!(config.pager);
+        assert_eq!(UserConfig::default().color, None);
+        assert!(UserConfig::parse("plain = on").unwrap().plain);
+        assert!(!UserConfig::default().plain);
+
+        let config = UserConfig::parse("export_dir = \"/tmp/my exports\"").unwrap();
+        assert_eq!(config.export_dir, Some(PathBuf::from("/tmp/my exports")));
+        assert_eq!(
+            UserConfig::parse("export_dir = none").unwrap().export_dir,
+            None
+        );
+        assert!(UserConfig::parse("export_git = on").unwrap().export_git);
+        assert!(!UserConfig::default().export_git);
+        assert!(UserConfig::parse("strict = yes").unwrap().strict);
+        assert!(!UserConfig::default().strict);
+        assert!(UserConfig::parse("log = on").unwrap().log);
+        let config = UserConfig::parse("game_db = /data/openvgdb.sqlite").unwrap();
+        assert_eq!(config.game_db, Some(PathBuf::from("/data/openvgdb.sqlite")));
+        assert_eq!(UserConfig::default().metadata_cache_days, 30);
+        let config = UserConfig::parse("metadata_cache_days = 0").unwrap();
+        assert_eq!(config.metadata_cache_days, 0);
+        let config = UserConfig::parse("emulator.NES = fceux --fullscreen 1").unwrap();
+        assert_eq!(
+            config.emulators.get("nes").map(String::as_str),
+            Some("fceux --fullscreen 1")
+        );
+        let config = UserConfig::parse("plugin.UNIF = python3 \"unif plugin.py\"").unwrap();
+        assert_eq!(
+            config.plugins.get("unif").map(String::as_str),
+            Some("python3 \"unif plugin.py\"")
+        );
+        let config = UserConfig::parse("webhook.Indexer = http://nas:9000/dromos").unwrap();
+        assert_eq!(
+            config.webhooks.get("indexer").map(String::as_str),
+            Some("http://nas:9000/dromos")
+        );
+        let config =
+            UserConfig::parse("autotag.MMC3 = mapper:4\nautotag.translation = filename:*[T+*")
+                .unwrap();
+        assert_eq!(
+            config.autotags.get("MMC3").map(String::as_str),
+            Some("mapper:4")
+        );
+        assert_eq!(config.autotags.len(), 2);
+
+        assert!(UserConfig::parse("history_size = lots").is_err());
+        assert!(UserConfig::parse("pager = sometimes").is_err());
+        assert!(UserConfig::parse("alias b build").is_err());
+        assert!(UserConfig::parse("colour = red").is_err());
+    }
+
+    #[test]
+    fn test_replace_setting() {
+        let text = "# settings\npager = off\ntheme = light\n";
+        assert_eq!(
+            replace_setting(text, "pager", "on"),
+            "# settings\npager = on\ntheme = light\n"
+        );
+        assert_eq!(
+            replace_setting(text, "output", "json"),
+            "# settings\npager = off\ntheme = light\noutput = json\n"
+        );
+        assert_eq!(replace_setting("", "confirm", "off"), "confirm = off\n");
+        // Commented-out settings are left alone
+        assert_eq!(
+            replace_setting("# pager = on\n", "pager", "off"),
+            "# pager = on\npager = off\n"
+        );
+    }
+}
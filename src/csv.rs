@@ -0,0 +1,505 @@
+//! Bulk metadata edits from a CSV file, such as a `report` corrected in a
+//! spreadsheet.
+//!
+//! The first row names the columns. A `hash` (or `sha256`) column picks
+//! each row's ROM by full hash or unique prefix; without one, `title` does,
+//! matching a single ROM's title. The other columns replace the field they
+//! name, and an empty cell clears it (an empty `status` resets it to
+//! unplayed). Columns `report` writes that can't be
+//! edited (type, size, links, added, dat) are ignored.
+
+use std::path::Path;
+
+use crate::db::{MAX_RATING, NodeMetadata, NodeRow, PlayStatus, check_rating};
+use crate::error::{DromosError, Result};
+use crate::rom::{format_hash, parse_hash, region};
+
+/// A metadata field a CSV column can set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Hash,
+    Title,
+    Version,
+    Tags,
+    Date,
+    Source,
+    Description,
+    Rating,
+    Status,
+    Region,
+    Language,
+    /// Read-only values, as written by `report`
+    Ignored,
+}
+
+impl Column {
+    fn parse(name: &str) -> Option<Column> {
+        Some(match name.trim().to_lowercase().as_str() {
+            "hash" | "sha256" => Column::Hash,
+            "title" => Column::Title,
+            "version" => Column::Version,
+            "tags" => Column::Tags,
+            "date" | "release_date" => Column::Date,
+            "source" | "url" | "source_url" => Column::Source,
+            "description" => Column::Description,
+            "rating" => Column::Rating,
+            "status" | "play_status" => Column::Status,
+            "region" => Column::Region,
+            "lang" | "language" => Column::Language,
+            "type" | "size" | "links" | "added" | "dat" => Column::Ignored,
+            _ => return None,
+        })
+    }
+}
+
+/// The new metadata for one ROM, with what it replaces.
+#[derive(Debug, Clone)]
+pub struct MetadataChange {
+    pub sha256: [u8; 32],
+    /// Line of the CSV the change came from, or None for a `bulk-edit`
+    pub line: Option<usize>,
+    pub before: NodeMetadata,
+    pub after: NodeMetadata,
+}
+
+impl MetadataChange {
+    /// Each field that differs: its name, old value, and new value.
+    pub fn fields(&self) -> Vec<(&'static str, String, String)> {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        let rating = |value: Option<u8>| value.map(|r| r.to_string()).unwrap_or_default();
+        let (a, b) = (&self.before, &self.after);
+        [
+            ("title", a.title.clone(), b.title.clone()),
+            ("version", text(&a.version), text(&b.version)),
+            ("tags", a.tags.join(", "), b.tags.join(", ")),
+            ("date", text(&a.release_date), text(&b.release_date)),
+            ("source", text(&a.source_url), text(&b.source_url)),
+            ("description", text(&a.description), text(&b.description)),
+            ("rating", rating(a.rating), rating(b.rating)),
+            (
+                "status",
+                a.play_status.to_string(),
+                b.play_status.to_string(),
+            ),
+            (
+                "region",
+                region::join_list(&a.regions),
+                region::join_list(&b.regions),
+            ),
+            (
+                "lang",
+                region::join_list(&a.languages),
+                region::join_list(&b.languages),
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .collect()
+    }
+}
+
+/// A CSV row that wasn't applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedRow {
+    pub line: usize,
+    pub reason: String,
+}
+
+/// What applying a CSV would change.
+#[derive(Debug, Clone, Default)]
+pub struct CsvEdits {
+    pub changes: Vec<MetadataChange>,
+    /// Rows matching a ROM whose metadata they already have
+    pub unchanged: usize,
+    pub skipped: Vec<SkippedRow>,
+}
+
+/// Read a metadata CSV and work out its changes to `rows`.
+pub fn read_csv_edits(path: &Path, rows: &[NodeRow]) -> Result<CsvEdits> {
+    let text = std::fs::read_to_string(path).map_err(DromosError::file(path))?;
+    plan_csv_edits(&text, rows).map_err(|reason| DromosError::InvalidCsv {
+        path: path.to_path_buf(),
+        reason,
+    })
+}
+
+/// Work out the changes a metadata CSV makes to `rows`. Rows that can't be
+/// applied are skipped with a reason; only a bad header fails the whole file.
+pub fn plan_csv_edits(text: &str, rows: &[NodeRow]) -> std::result::Result<CsvEdits, String> {
+    let mut records = parse_csv(text)?.into_iter();
+    let Some((_, header)) = records.next() else {
+        return Err("the file is empty".to_string());
+    };
+    let columns = header
+        .iter()
+        .map(|name| Column::parse(name).ok_or_else(|| format!("unknown column `{}`", name.trim())))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    for (i, column) in columns.iter().enumerate() {
+        if *column != Column::Ignored && columns[..i].contains(column) {
+            return Err(format!("column `{}` appears twice", header[i].trim()));
+        }
+    }
+    let key = if columns.contains(&Column::Hash) {
+        Column::Hash
+    } else if columns.contains(&Column::Title) {
+        Column::Title
+    } else {
+        return Err("no `hash` or `title` column to match ROMs by".to_string());
+    };
+
+    let mut edits = CsvEdits::default();
+    // Line each ROM was applied from, to catch repeats
+    let mut seen: Vec<([u8; 32], usize)> = Vec::new();
+    for (line, record) in records {
+        if record.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+        let key_cell = columns
+            .iter()
+            .position(|c| *c == key)
+            .and_then(|i| record.get(i))
+            .map(|cell| cell.trim())
+            .unwrap_or("");
+        let row = match find_row(rows, key, key_cell) {
+            Ok(row) => row,
+            Err(reason) => {
+                edits.skipped.push(SkippedRow { line, reason });
+                continue;
+            }
+        };
+        if let Some((_, first)) = seen.iter().find(|(hash, _)| *hash == row.sha256) {
+            let reason = format!("same ROM as line {}", first);
+            edits.skipped.push(SkippedRow { line, reason });
+            continue;
+        }
+
+        let before = row.metadata();
+        match apply_record(&columns, &record, key, before.clone()) {
+            Ok(after) => {
+                seen.push((row.sha256, line));
+                if after == before {
+                    edits.unchanged += 1;
+                } else {
+                    edits.changes.push(MetadataChange {
+                        sha256: row.sha256,
+                        line: Some(line),
+                        before,
+                        after,
+                    });
+                }
+            }
+            Err(reason) => edits.skipped.push(SkippedRow { line, reason }),
+        }
+    }
+    Ok(edits)
+}
+
+/// The ROM a row's key cell picks out.
+fn find_row<'a>(
+    rows: &'a [NodeRow],
+    key: Column,
+    cell: &str,
+) -> std::result::Result<&'a NodeRow, String> {
+    if cell.is_empty() {
+        let name = if key == Column::Hash { "hash" } else { "title" };
+        return Err(format!("no {}", name));
+    }
+    let matches: Vec<&NodeRow> = if key == Column::Hash {
+        match parse_hash(cell) {
+            Some(hash) => rows.iter().filter(|r| r.sha256 == hash).collect(),
+            None => {
+                let prefix = cell.to_lowercase();
+                rows.iter()
+                    .filter(|r| format_hash(&r.sha256).starts_with(&prefix))
+                    .collect()
+            }
+        }
+    } else {
+        rows.iter()
+            .filter(|r| r.title.eq_ignore_ascii_case(cell))
+            .collect()
+    };
+    match matches.as_slice() {
+        [row] => Ok(row),
+        [] => Err(format!("no ROM matches `{}`", cell)),
+        _ => Err(format!("`{}` matches {} ROMs", cell, matches.len())),
+    }
+}
+
+/// `metadata` with the record's cells applied.
+fn apply_record(
+    columns: &[Column],
+    record: &[String],
+    key: Column,
+    mut metadata: NodeMetadata,
+) -> std::result::Result<NodeMetadata, String> {
+    let stored = metadata.clone();
+    for (column, cell) in columns.iter().zip(record) {
+        let cell = cell.trim();
+        let value = (!cell.is_empty()).then(|| cell.to_string());
+        match column {
+            Column::Hash | Column::Ignored => {}
+            // A title used to find the ROM can't also change it
+            Column::Title if key == Column::Title => {}
+            Column::Title => match value {
+                Some(title) => metadata.title = title,
+                None => return Err("title can't be empty".to_string()),
+            },
+            Column::Version => metadata.version = value,
+            Column::Tags => {
+                metadata.tags = Vec::new();
+                for tag in cell.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+                    if !metadata.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                        metadata.tags.push(tag.to_string());
+                    }
+                }
+            }
+            Column::Date => {
+                metadata.release_date = value
+                    .map(|date| {
+                        chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                            .map(|d| d.format("%Y-%m-%d").to_string())
+                            .map_err(|_| format!("invalid date `{}` (expected YYYY-MM-DD)", date))
+                    })
+                    .transpose()?;
+            }
+            Column::Source => metadata.source_url = value,
+            Column::Description => metadata.description = value,
+            Column::Rating => {
+                metadata.rating = value
+                    .map(|rating| {
+                        rating
+                            .parse()
+                            .map_err(|_| {
+                                format!(
+                                    "invalid rating `{}` (expected 1 to {})",
+                                    rating, MAX_RATING
+                                )
+                            })
+                            .and_then(check_rating)
+                    })
+                    .transpose()?;
+            }
+            Column::Status => {
+                metadata.play_status = match value {
+                    Some(status) => status.parse::<PlayStatus>()?,
+                    None => PlayStatus::default(),
+                };
+            }
+            Column::Region => {
+                metadata.regions = region::parse_list(value.as_deref().unwrap_or_default())?;
+            }
+            Column::Language => {
+                metadata.languages = region::parse_list(value.as_deref().unwrap_or_default())?;
+            }
+        }
+    }
+    metadata.normalize_edit(&stored)?;
+    Ok(metadata)
+}
+
+/// Split CSV text (RFC 4180) into records of fields, each with the line it
+/// starts on. Quoted fields may hold commas, quotes (doubled), and line
+/// breaks. A leading byte order mark, as spreadsheets write, is skipped.
+pub fn parse_csv(text: &str) -> std::result::Result<Vec<(usize, Vec<String>)>, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut start = 1;
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                c => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut field));
+                records.push((start, std::mem::take(&mut record)));
+                line += 1;
+                start = line;
+            }
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("unclosed quote in the record on line {}", start));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((start, record));
+    }
+    Ok(records)
+}
+
+/// Quote a CSV field if it holds a separator, quote, or line break.
+pub fn quote_field(text: &str) -> String {
+    if text.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rom::RomType;
+
+    fn row(byte: u8, title: &str) -> NodeRow {
+        NodeRow {
+            id: byte as i64,
+            sha256: [byte; 32],
+            filename: None,
+            title: title.to_string(),
+            rom_type: RomType::Nes,
+            source_url: None,
+            version: Some("1.0".to_string()),
+            release_date: None,
+            tags: vec!["hack".to_string()],
+            description: None,
+            source_file_header: None,
+            created_at: String::new(),
+            updated_at: None,
+            sha1: None,
+            crc32: None,
+            rating: None,
+            play_status: PlayStatus::Unplayed,
+            regions: Vec::new(),
+            languages: Vec::new(),
+            favorite: false,
+            archived: false,
+            missing: false,
+            content_size: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let text = "\u{feff}title,description\r\n\"Tom, \"\"Jerry\"\"\",\"two\nlines\"\r\nx,\n";
+        assert_eq!(
+            parse_csv(text).unwrap(),
+            vec![
+                (1, vec!["title".to_string(), "description".to_string()]),
+                (
+                    2,
+                    vec!["Tom, \"Jerry\"".to_string(), "two\nlines".to_string()]
+                ),
+                (4, vec!["x".to_string(), String::new()]),
+            ]
+        );
+        assert!(parse_csv("a,\"b\n").is_err());
+        assert_eq!(quote_field("a \"b\", c"), "\"a \"\"b\"\", c\"");
+        assert_eq!(quote_field("plain"), "plain");
+    }
+
+    #[test]
+    fn test_plan_csv_edits() {
+        let rows = vec![row(0xaa, "Alpha"), row(0xbb, "Beta"), row(0xbc, "Beta")];
+        let text = format!(
+            "hash,title,version,tags,date,links\n\
+             {},Alpha DX,1.0,\"hack, fr\",2024-02-01,3\n\
+             {},Beta,1.0,hack,,0\n\
+             b,Gamma,,,,\n\
+             cc,Gamma,,,,\n\
+             bc,,,,,\n\
+             bcbc,Beta,,,31/01/2024,\n\
+             aaaa,Alpha,,,,\n\
+             bc,Beta,,bad tag,,\n",
+            format_hash(&[0xaa; 32]),
+            format_hash(&[0xbb; 32])
+        );
+        let edits = plan_csv_edits(&text, &rows).unwrap();
+        assert_eq!(edits.changes.len(), 1);
+        let change = &edits.changes[0];
+        assert_eq!(change.sha256, [0xaa; 32]);
+        assert_eq!(change.line, Some(2));
+        assert_eq!(change.after.tags, vec!["hack", "fr"]);
+        assert_eq!(
+            change.fields(),
+            vec![
+                ("title", "Alpha".to_string(), "Alpha DX".to_string()),
+                ("tags", "hack".to_string(), "hack, fr".to_string()),
+                ("date", String::new(), "2024-02-01".to_string()),
+            ]
+        );
+        assert_eq!(edits.unchanged, 1);
+        let reasons: Vec<_> = edits
+            .skipped
+            .iter()
+            .map(|s| (s.line, s.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (4, "`b` matches 2 ROMs"),
+                (5, "no ROM matches `cc`"),
+                (6, "title can't be empty"),
+                (7, "invalid date `31/01/2024` (expected YYYY-MM-DD)"),
+                (8, "same ROM as line 2"),
+                (
+                    9,
+                    "tag `bad tag` can't contain spaces (use letters, digits, and - _ . +)"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_title_keyed_csv() {
+        let rows = vec![row(1, "Alpha"), row(2, "Beta"), row(3, "Beta")];
+        let edits = plan_csv_edits("Title,Version\nalpha,\nBeta,2.0\n", &rows).unwrap();
+        assert_eq!(edits.changes.len(), 1);
+        assert_eq!(edits.changes[0].after.title, "Alpha");
+        assert_eq!(edits.changes[0].after.version, None);
+        assert_eq!(edits.skipped[0].reason, "`Beta` matches 2 ROMs");
+
+        assert!(plan_csv_edits("version,tags\n1.0,x\n", &rows).is_err());
+        assert!(plan_csv_edits("hash,colour\n", &rows).is_err());
+        assert!(plan_csv_edits("hash,tags,tags\n", &rows).is_err());
+        assert!(plan_csv_edits("", &rows).is_err());
+    }
+
+    #[test]
+    fn test_rating_and_status_columns() {
+        let rows = vec![row(1, "Alpha"), row(2, "Beta"), row(3, "Gamma")];
+        let text = "title,rating,status
+Alpha,4,Finished
+Beta,9,
+Gamma,,ongoing
+";
+        let edits = plan_csv_edits(text, &rows).unwrap();
+        assert_eq!(edits.changes.len(), 1);
+        let after = &edits.changes[0].after;
+        assert_eq!(after.rating, Some(4));
+        assert_eq!(after.play_status, PlayStatus::Finished);
+        assert_eq!(
+            edits.changes[0].fields(),
+            vec![
+                ("rating", String::new(), "4".to_string()),
+                ("status", "unplayed".to_string(), "finished".to_string()),
+            ]
+        );
+        assert_eq!(edits.skipped.len(), 2);
+        assert_eq!(edits.skipped[0].line, 3);
+        assert_eq!(edits.skipped[1].line, 4);
+    }
+}
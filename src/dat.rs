@@ -0,0 +1,298 @@
+//! No-Intro and Redump DAT files: the canonical names and hashes of known
+//! good dumps, in the Logiqx XML format both projects publish.
+//!
+//! Library ROMs are hashed without their file header, so they match
+//! entries from headerless DATs (e.g. No-Intro's "Nintendo - Nintendo
+//! Entertainment System (Headerless)"). DATs written here are headerless
+//! too, and name the header skipper ROM managers should apply.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use roxmltree::{Document, Node, ParsingOptions};
+
+use crate::db::NodeRow;
+use crate::error::{DromosError, Result};
+
+/// A parsed DAT file.
+#[derive(Debug, Clone)]
+pub struct DatFile {
+    /// The DAT's own name from its header, e.g. "Nintendo - Nintendo
+    /// Entertainment System (Headerless)"
+    pub name: String,
+    pub version: Option<String>,
+    /// clrmamepro header skipper to apply before hashing files, e.g.
+    /// "No-Intro_NES.xml" to skip iNES headers
+    pub header_skipper: Option<String>,
+    pub entries: Vec<DatEntry>,
+}
+
+/// One ROM listed in a DAT file. Hashes the DAT leaves out are None.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatEntry {
+    /// Canonical name of the game, e.g. "Super Mario Bros. (World)"
+    pub game: String,
+    /// File name of the ROM within the game
+    pub rom_name: String,
+    pub size: Option<u64>,
+    pub crc32: Option<u32>,
+    pub sha1: Option<[u8; 20]>,
+    pub sha256: Option<[u8; 32]>,
+}
+
+/// Read and parse a DAT file.
+pub fn read_dat(path: &Path) -> Result<DatFile> {
+    let text = std::fs::read_to_string(path).map_err(DromosError::file(path))?;
+    parse_dat(&text).map_err(|reason| DromosError::InvalidDat {
+        path: path.to_path_buf(),
+        reason,
+    })
+}
+
+/// Parse a Logiqx XML DAT. ROMs listing no CRC, SHA-1, or SHA-256 are
+/// skipped, since nothing could match them.
+pub fn parse_dat(text: &str) -> std::result::Result<DatFile, String> {
+    // No-Intro DATs declare the Logiqx DTD
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(text, options).map_err(|e| e.to_string())?;
+    let root = doc.root_element();
+    if !root.has_tag_name("datafile") {
+        return Err(format!(
+            "expected a <datafile> element, found <{}>",
+            root.tag_name().name()
+        ));
+    }
+
+    let header = child(root, "header");
+    let name = header
+        .and_then(|h| child_text(h, "name"))
+        .ok_or("missing header name")?;
+    let version = header.and_then(|h| child_text(h, "version"));
+    let header_skipper = header
+        .and_then(|h| child(h, "clrmamepro"))
+        .and_then(|c| c.attribute("header"))
+        .map(str::to_string);
+
+    let mut entries = Vec::new();
+    let games = root
+        .children()
+        .filter(|n| n.has_tag_name("game") || n.has_tag_name("machine"));
+    for game in games {
+        let game_name = game.attribute("name").ok_or("game without a name")?;
+        for rom in game.children().filter(|n| n.has_tag_name("rom")) {
+            let entry = DatEntry {
+                game: game_name.to_string(),
+                rom_name: rom.attribute("name").unwrap_or(game_name).to_string(),
+                size: rom.attribute("size").and_then(|s| s.parse().ok()),
+                crc32: rom
+                    .attribute("crc")
+                    .and_then(|c| u32::from_str_radix(c, 16).ok()),
+                sha1: rom.attribute("sha1").and_then(parse_hex),
+                sha256: rom.attribute("sha256").and_then(parse_hex),
+            };
+            if entry.crc32.is_some() || entry.sha1.is_some() || entry.sha256.is_some() {
+                entries.push(entry);
+            }
+        }
+    }
+
+    Ok(DatFile {
+        name,
+        version,
+        header_skipper,
+        entries,
+    })
+}
+
+/// Write `dat` as a Logiqx XML DAT file.
+pub fn write_dat(path: &Path, dat: &DatFile) -> Result<()> {
+    std::fs::write(path, format_dat(dat)).map_err(DromosError::file(path))
+}
+
+/// Format `dat` as Logiqx XML, one `<game>` per entry.
+pub fn format_dat(dat: &DatFile) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\"?>\n\
+         <!DOCTYPE datafile PUBLIC \"-//Logiqx//DTD ROM Management Datafile//EN\" \
+         \"http://www.logiqx.com/dats/datafile.dtd\">\n\
+         <datafile>\n\t<header>\n",
+    );
+    let name = escape(&dat.name);
+    let _ = writeln!(xml, "\t\t<name>{}</name>", name);
+    let _ = writeln!(xml, "\t\t<description>{}</description>", name);
+    if let Some(version) = &dat.version {
+        let _ = writeln!(xml, "\t\t<version>{}</version>", escape(version));
+    }
+    if let Some(skipper) = &dat.header_skipper {
+        let _ = writeln!(xml, "\t\t<clrmamepro header=\"{}\"/>", escape(skipper));
+    }
+    xml.push_str("\t</header>\n");
+
+    for entry in &dat.entries {
+        let game = escape(&entry.game);
+        let _ = writeln!(xml, "\t<game name=\"{}\">", game);
+        let _ = writeln!(xml, "\t\t<description>{}</description>", game);
+        let _ = write!(xml, "\t\t<rom name=\"{}\"", escape(&entry.rom_name));
+        if let Some(size) = entry.size {
+            let _ = write!(xml, " size=\"{}\"", size);
+        }
+        if let Some(crc32) = entry.crc32 {
+            let _ = write!(xml, " crc=\"{:08x}\"", crc32);
+        }
+        if let Some(sha1) = &entry.sha1 {
+            let _ = write!(xml, " sha1=\"{}\"", hex::encode(sha1));
+        }
+        if let Some(sha256) = &entry.sha256 {
+            let _ = write!(xml, " sha256=\"{}\"", hex::encode(sha256));
+        }
+        xml.push_str("/>\n\t</game>\n");
+    }
+    xml.push_str("</datafile>\n");
+    xml
+}
+
+/// Escape text for an XML (or HTML) attribute or element.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Header skipper that makes clrmamepro and RomVault hash NES files
+/// without their iNES header, as the library does.
+pub const NES_HEADER_SKIPPER: &str = "No-Intro_NES.xml";
+
+/// A DAT entry for each library node, named "Title (Version)" as No-Intro
+/// names its games. Names that would repeat get the start of the hash.
+pub fn library_entries(rows: &[NodeRow]) -> Vec<DatEntry> {
+    let base_names: Vec<String> = rows.iter().map(game_name).collect();
+    rows.iter()
+        .zip(&base_names)
+        .map(|(row, name)| {
+            let repeated = base_names.iter().filter(|n| *n == name).count() > 1;
+            let game = if repeated {
+                format!("{} [{}]", name, &hex::encode(row.sha256)[..8])
+            } else {
+                name.clone()
+            };
+            DatEntry {
+                rom_name: format!("{}.{}", game, row.rom_type.extension()),
+                game,
+                size: row.data_size().map(|s| s as u64),
+                crc32: row.crc32,
+                sha1: row.sha1,
+                sha256: Some(row.sha256),
+            }
+        })
+        .collect()
+}
+
+/// A node's title and version as a game name, with characters that aren't
+/// allowed in file names replaced.
+pub(crate) fn game_name(row: &NodeRow) -> String {
+    let name = match row.version.as_deref() {
+        Some(v) if !v.is_empty() => format!("{} ({})", row.title, v),
+        _ => row.title.clone(),
+    };
+    name.replace(": ", " - ")
+        .replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(tag))
+}
+
+fn child_text(node: Node, tag: &str) -> Option<String> {
+    child(node, tag)
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+fn parse_hex<const N: usize>(text: &str) -> Option<[u8; N]> {
+    hex::decode(text).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE datafile PUBLIC "-//Logiqx//DTD ROM Management Datafile//EN" "http://www.logiqx.com/dats/datafile.dtd">
+<datafile>
+	<header>
+		<name>Nintendo - Nintendo Entertainment System (Headerless)</name>
+		<version>20240101-000000</version>
+	</header>
+	<game name="Example Game (USA)">
+		<description>Example Game (USA)</description>
+		<rom name="Example Game (USA).nes" size="40960" crc="3D564757" sha1="0123456789ABCDEF0123456789ABCDEF01234567"/>
+	</game>
+	<game name="No Hashes (Japan)">
+		<rom name="No Hashes (Japan).nes" size="16"/>
+	</game>
+</datafile>
+"#;
+
+    #[test]
+    fn test_parse_dat() {
+        let dat = parse_dat(SAMPLE).unwrap();
+        assert_eq!(
+            dat.name,
+            "Nintendo - Nintendo Entertainment System (Headerless)"
+        );
+        assert_eq!(dat.version.as_deref(), Some("20240101-000000"));
+        assert_eq!(
+            dat.entries,
+            vec![DatEntry {
+                game: "Example Game (USA)".to_string(),
+                rom_name: "Example Game (USA).nes".to_string(),
+                size: Some(40960),
+                crc32: Some(0x3D564757),
+                sha1: parse_hex("0123456789abcdef0123456789abcdef01234567"),
+                sha256: None,
+            }]
+        );
+
+        assert!(parse_dat("<html/>").is_err());
+        assert!(parse_dat("<datafile><game name='x'/></datafile>").is_err());
+        assert!(parse_dat("not xml").is_err());
+    }
+
+    #[test]
+    fn test_format_dat_round_trips() {
+        let dat = DatFile {
+            name: "dromos <library>".to_string(),
+            version: Some("20260101-000000".to_string()),
+            header_skipper: Some("No-Intro_NES.xml".to_string()),
+            entries: vec![DatEntry {
+                game: "Tom & Jerry (\"Hack\")".to_string(),
+                rom_name: "Tom & Jerry ('Hack').nes".to_string(),
+                size: Some(40960),
+                crc32: Some(0x0000_1234),
+                sha1: Some([0xAB; 20]),
+                sha256: Some([0xCD; 32]),
+            }],
+        };
+        let xml = format_dat(&dat);
+        assert!(xml.contains("crc=\"00001234\""));
+
+        let parsed = parse_dat(&xml).unwrap();
+        assert_eq!(parsed.name, dat.name);
+        assert_eq!(parsed.version, dat.version);
+        assert_eq!(parsed.header_skipper, dat.header_skipper);
+        assert_eq!(parsed.entries, dat.entries);
+    }
+}
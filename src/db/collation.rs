@@ -0,0 +1,156 @@
+//! The order titles are listed in.
+//!
+//! Titles compare the way people read them rather than byte by byte: runs
+//! of digits by their value, so "Mega Man 2" comes before "Mega Man 10",
+//! and letters without regard to case or accents, so "Pokémon" sorts with
+//! "Pokemon" rather than after "Zelda". Titles that differ only in case,
+//! accents, or leading zeros fall back to a plain comparison, so the order
+//! is still total.
+//!
+//! The same comparison is registered with SQLite as the `TITLE` collation,
+//! so queries can `ORDER BY title COLLATE TITLE`.
+
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use rusqlite::Connection;
+
+/// Name of the SQLite collation registered by [`register`]
+pub const TITLE_COLLATION: &str = "TITLE";
+
+/// Make the `TITLE` collation available on `conn`.
+pub fn register(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_collation(TITLE_COLLATION, compare_titles)
+}
+
+/// Compare two titles in natural, case- and accent-insensitive order.
+pub fn compare_titles(a: &str, b: &str) -> Ordering {
+    natural_cmp(&fold(a), &fold(b)).then_with(|| a.cmp(b))
+}
+
+/// Compare with runs of ASCII digits ordered by value.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let ordering = match (a.peek(), b.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, y) = (take_number(&mut a), take_number(&mut b));
+                // Without leading zeros, a longer number is a larger one
+                x.len().cmp(&y.len()).then_with(|| x.cmp(&y))
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.cmp(y);
+                a.next();
+                b.next();
+                ordering
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// The run of digits at the front of `chars`, without leading zeros.
+fn take_number(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        if !(digits.is_empty() && c == '0') {
+            digits.push(c);
+        }
+    }
+    digits
+}
+
+/// Lowercase `text` and strip the accents from Latin letters, spelling out
+/// ligatures (`æ` as `ae`, `ß` as `ss`).
+fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let base = match c {
+            'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+            'æ' => "ae",
+            'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+            'ď' | 'đ' | 'ð' => "d",
+            'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+            'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+            'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+            'ĺ' | 'ļ' | 'ľ' | 'ł' => "l",
+            'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+            'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+            'œ' => "oe",
+            'ŕ' | 'ŗ' | 'ř' => "r",
+            'ß' => "ss",
+            'ś' | 'ŝ' | 'ş' | 'š' => "s",
+            'ţ' | 'ť' => "t",
+            'þ' => "th",
+            'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+            'ý' | 'ÿ' | 'ŷ' => "y",
+            'ź' | 'ż' | 'ž' => "z",
+            _ => {
+                folded.push(c);
+                continue;
+            }
+        };
+        folded.push_str(base);
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_titles() {
+        let mut titles = vec![
+            "Mega Man 10",
+            "zelda",
+            "Mega Man 2",
+            "Pokémon Red",
+            "Mega Man",
+            "Zelda",
+            "Pokemon Blue",
+            "Mega Man 02",
+            "Ærith",
+        ];
+        titles.sort_by(|a, b| compare_titles(a, b));
+        assert_eq!(
+            titles,
+            [
+                "Ærith",
+                "Mega Man",
+                "Mega Man 02",
+                "Mega Man 2",
+                "Mega Man 10",
+                "Pokemon Blue",
+                "Pokémon Red",
+                "Zelda",
+                "zelda",
+            ]
+        );
+        assert_eq!(compare_titles("Zelda", "Zelda"), Ordering::Equal);
+        assert_eq!(fold("Straße Ñandú"), "strasse nandu");
+    }
+
+    #[test]
+    fn test_sql_collation() {
+        let conn = Connection::open_in_memory().unwrap();
+        register(&conn).unwrap();
+        let titles: Vec<String> = conn
+            .prepare(
+                "SELECT t FROM (SELECT 'Mega Man 10' AS t UNION SELECT 'Mega Man 9'
+                 UNION SELECT 'élan') ORDER BY t COLLATE TITLE",
+            )
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(titles, ["élan", "Mega Man 9", "Mega Man 10"]);
+    }
+}
@@ -0,0 +1,433 @@
+//! Structured node filters for `search`.
+//!
+//! A query like `tag:translation mapper:4 type:nes version:1.* mario` is
+//! parsed into a [`NodeFilter`], which compiles to a SQL `WHERE` clause.
+//! Words without a known qualifier are matched against the title.
+//!
+//! `rating:4` matches one rating, and `rating:>=4` (or `>`, `<`, `<=`) a
+//! range; unrated ROMs match neither. `status:playing` matches a play status,
+//! and `collection:<name>` the ROMs in a collection. `region:J` and `lang:en`
+//! match one of a ROM's regions or languages, by any name
+//! [`Region`] and [`Language`] accept. `is:fav` matches the ROMs pinned
+//! with `fav`, `is:archived` the ROMs hidden with `archive`, and
+//! `is:wanted` the wishlist ROMs added with `wish` whose file hasn't been
+//! seen.
+//! `filename:*[T+Eng]*` matches the name of the file a ROM was added from,
+//! or any other name its file has been seen under, with the same wildcards
+//! as `version:`.
+//!
+//! `added:2024-03-01` matches the ROMs added on a day, and `added:>=2024-01-01`
+//! (or `>`, `<`, `<=`) the ones added after or before it. An age such as
+//! `30d` stands for that long ago, so `added:30d` (or `added:>=30d`) matches
+//! the ROMs added in the last 30 days and `added:<1y` the ones added over a
+//! year ago; ages count hours (`h`), days (`d`), weeks (`w`), months (`m`),
+//! or years (`y`). Dates are UTC days, as `created_at` is stored. These
+//! compare `created_at` directly, which is indexed, so they stay fast on
+//! large libraries.
+
+use rusqlite::types::Value;
+
+use super::repository::{PlayStatus, check_rating};
+use crate::rom::{Language, Region, RomType};
+
+/// Conditions a node must meet to match a search. Every condition must hold.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeFilter {
+    /// Case-insensitive substring of the title
+    pub title: Option<String>,
+    /// Tags the node must have (case-insensitive)
+    pub tags: Vec<String>,
+    /// iNES mapper numbers, decoded from the stored header
+    pub mappers: Vec<u16>,
+    pub rom_types: Vec<RomType>,
+    /// Version patterns; `*` matches any run of characters and `?` one character
+    pub versions: Vec<String>,
+    /// File name patterns, with the same wildcards as versions
+    pub filenames: Vec<String>,
+    /// Rating comparisons, e.g. (`>=`, 4)
+    pub ratings: Vec<(&'static str, u8)>,
+    pub play_statuses: Vec<PlayStatus>,
+    /// Collections the node must be in (names in any case)
+    pub collections: Vec<String>,
+    pub regions: Vec<Region>,
+    pub languages: Vec<Language>,
+    /// Only favorites
+    pub favorite: bool,
+    /// Only archived nodes (`Some(true)`) or only unarchived ones
+    /// (`Some(false)`); `None` matches both
+    pub archived: Option<bool>,
+    /// Only wishlist nodes, whose file hasn't been seen
+    pub missing: bool,
+    /// When the node was added, e.g. (`>=`, 30 days ago)
+    pub added: Vec<(&'static str, AddedAt)>,
+}
+
+/// A point in time `added:` compares with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddedAt {
+    /// A `YYYY-MM-DD` day
+    Date(String),
+    /// That long before now, as an SQLite date modifier like `-30 days`
+    Ago(String),
+}
+
+impl NodeFilter {
+    /// Parse a search query. Returns Err for a malformed qualifier value.
+    pub fn parse(query: &str) -> Result<NodeFilter, String> {
+        let mut filter = NodeFilter::default();
+        let mut title_words = Vec::new();
+
+        for word in query.split_whitespace() {
+            let Some((key, value)) = word.split_once(':') else {
+                title_words.push(word);
+                continue;
+            };
+            match key.to_lowercase().as_str() {
+                "tag" => filter.tags.push(value.to_string()),
+                "mapper" => filter.mappers.push(
+                    value
+                        .parse()
+                        .map_err(|_| format!("mapper must be a number: {}", value))?,
+                ),
+                "type" => filter.rom_types.push(
+                    value
+                        .parse()
+                        .map_err(|_| format!("unknown ROM type: {}", value))?,
+                ),
+                "version" => filter.versions.push(value.to_string()),
+                "filename" | "file" => filter.filenames.push(value.to_string()),
+                "rating" => filter.ratings.push(parse_rating(value)?),
+                "added" => filter.added.push(parse_added(value)?),
+                "status" => filter.play_statuses.push(value.parse()?),
+                "collection" => filter.collections.push(value.to_string()),
+                "region" => filter.regions.push(value.parse()?),
+                "lang" | "language" => filter.languages.push(value.parse()?),
+                "is" => match value.to_lowercase().as_str() {
+                    "fav" | "favorite" => filter.favorite = true,
+                    "archived" => filter.archived = Some(true),
+                    "wanted" | "missing" => filter.missing = true,
+                    "" => {}
+                    other => {
+                        return Err(format!(
+                            "unknown is: value: {} (expected fav, archived, or wanted)",
+                            other
+                        ));
+                    }
+                },
+                // Titles can contain colons, e.g. "Zelda II: The Adventure of Link"
+                _ => {
+                    title_words.push(word);
+                    continue;
+                }
+            }
+            if value.is_empty() {
+                return Err(format!("missing value for {}:", key));
+            }
+        }
+
+        if !title_words.is_empty() {
+            filter.title = Some(title_words.join(" "));
+        }
+        Ok(filter)
+    }
+
+    /// SQL condition and parameters for the conditions that can be checked in
+    /// the database. Mapper numbers are checked separately with
+    /// [`NodeFilter::matches_mapper`], since they're packed into header bits.
+    pub fn to_sql(&self) -> (String, Vec<Value>) {
+        let mut conditions = vec!["1".to_string()];
+        let mut params = Vec::new();
+
+        if let Some(title) = &self.title {
+            conditions.push("title LIKE ? ESCAPE '\\'".to_string());
+            params.push(Value::Text(format!("%{}%", escape_like(title))));
+        }
+        for tag in &self.tags {
+            conditions.push(
+                "EXISTS (SELECT 1 FROM json_each(nodes.tags) WHERE lower(value) = lower(?))"
+                    .to_string(),
+            );
+            params.push(Value::Text(tag.clone()));
+        }
+        for rom_type in &self.rom_types {
+            conditions.push("rom_type = ?".to_string());
+            params.push(Value::Text(rom_type.as_str().to_string()));
+        }
+        for version in &self.versions {
+            conditions.push("version LIKE ? ESCAPE '\\'".to_string());
+            params.push(Value::Text(glob_to_like(version)));
+        }
+        for filename in &self.filenames {
+            conditions.push(
+                "(filename LIKE ? ESCAPE '\\' OR EXISTS (SELECT 1 FROM filename_aliases fa
+                 WHERE fa.node_id = nodes.id AND fa.filename LIKE ? ESCAPE '\\'))"
+                    .to_string(),
+            );
+            params.push(Value::Text(glob_to_like(filename)));
+            params.push(Value::Text(glob_to_like(filename)));
+        }
+        for (operator, rating) in &self.ratings {
+            conditions.push(format!("rating {} ?", operator));
+            params.push(Value::Integer(*rating as i64));
+        }
+        for status in &self.play_statuses {
+            conditions.push("play_status = ?".to_string());
+            params.push(Value::Text(status.as_str().to_string()));
+        }
+        for collection in &self.collections {
+            conditions.push(
+                "EXISTS (SELECT 1 FROM collection_nodes cn
+                 JOIN collections c ON c.id = cn.collection_id
+                 WHERE cn.node_id = nodes.id AND c.name = ?)"
+                    .to_string(),
+            );
+            params.push(Value::Text(collection.clone()));
+        }
+        // Lists are stored comma-separated, e.g. "Japan,USA"
+        for region in &self.regions {
+            conditions.push("',' || COALESCE(region, '') || ',' LIKE ? ESCAPE '\\'".to_string());
+            params.push(Value::Text(format!("%,{},%", escape_like(region.name()))));
+        }
+        for language in &self.languages {
+            conditions.push("',' || COALESCE(language, '') || ',' LIKE ? ESCAPE '\\'".to_string());
+            params.push(Value::Text(format!("%,{},%", escape_like(language.code()))));
+        }
+        if self.favorite {
+            conditions.push("favorite = 1".to_string());
+        }
+        if let Some(archived) = self.archived {
+            conditions.push("archived = ?".to_string());
+            params.push(Value::Integer(archived as i64));
+        }
+        if self.missing {
+            conditions.push("missing = 1".to_string());
+        }
+        for (operator, at) in &self.added {
+            match (at, *operator) {
+                (AddedAt::Date(date), "=") => {
+                    conditions
+                        .push("created_at >= ? AND created_at < date(?, '+1 day')".to_string());
+                    params.push(Value::Text(date.clone()));
+                    params.push(Value::Text(date.clone()));
+                }
+                // After or up to the end of the day
+                (AddedAt::Date(date), ">" | "<=") => {
+                    let operator = if *operator == ">" { ">=" } else { "<" };
+                    conditions.push(format!("created_at {} date(?, '+1 day')", operator));
+                    params.push(Value::Text(date.clone()));
+                }
+                (AddedAt::Date(date), _) => {
+                    conditions.push(format!("created_at {} ?", operator));
+                    params.push(Value::Text(date.clone()));
+                }
+                (AddedAt::Ago(modifier), _) => {
+                    let operator = if *operator == "=" { ">=" } else { operator };
+                    conditions.push(format!("created_at {} datetime('now', ?)", operator));
+                    params.push(Value::Text(modifier.clone()));
+                }
+            }
+        }
+
+        (conditions.join(" AND "), params)
+    }
+
+    /// True if the decoded mapper number satisfies every `mapper:` condition.
+    pub fn matches_mapper(&self, mapper: Option<u16>) -> bool {
+        self.mappers.iter().all(|m| mapper == Some(*m))
+    }
+}
+
+/// Parse a `rating:` value: a rating, optionally after a comparison.
+fn parse_rating(value: &str) -> Result<(&'static str, u8), String> {
+    let (operator, number) = [">=", "<=", ">", "<", "="]
+        .into_iter()
+        .find_map(|op| value.strip_prefix(op).map(|rest| (op, rest)))
+        .unwrap_or(("=", value));
+    let rating = number
+        .parse()
+        .map_err(|_| format!("rating must be a number: {}", value))?;
+    Ok((operator, check_rating(rating)?))
+}
+
+/// Parse an `added:` value: a date or an age, optionally after a
+/// comparison.
+fn parse_added(value: &str) -> Result<(&'static str, AddedAt), String> {
+    let (operator, rest) = [">=", "<=", ">", "<", "="]
+        .into_iter()
+        .find_map(|op| value.strip_prefix(op).map(|rest| (op, rest)))
+        .unwrap_or(("=", value));
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(rest, "%Y-%m-%d") {
+        return Ok((operator, AddedAt::Date(date.format("%Y-%m-%d").to_string())));
+    }
+    let invalid = || {
+        format!(
+            "added: expects a date (YYYY-MM-DD) or an age like 30d: {}",
+            value
+        )
+    };
+    let (count, unit) = rest.split_at(rest.len() - rest.chars().last().map_or(0, char::len_utf8));
+    let count: u32 = count.parse().map_err(|_| invalid())?;
+    let modifier = match unit.to_lowercase().as_str() {
+        "h" => format!("-{} hours", count),
+        "d" => format!("-{} days", count),
+        "w" => format!("-{} days", u64::from(count) * 7),
+        "m" => format!("-{} months", count),
+        "y" => format!("-{} years", count),
+        _ => return Err(invalid()),
+    };
+    Ok((operator, AddedAt::Ago(modifier)))
+}
+
+/// Escape LIKE's own wildcards so they match literally.
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Convert a `*`/`?` glob into a LIKE pattern.
+fn glob_to_like(pattern: &str) -> String {
+    escape_like(pattern).replace('*', "%").replace('?', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_filter() {
+        let filter =
+            NodeFilter::parse("tag:translation mapper:4 type:nes version:1.* super mario").unwrap();
+        assert_eq!(filter.title.as_deref(), Some("super mario"));
+        assert_eq!(filter.tags, vec!["translation"]);
+        assert_eq!(filter.mappers, vec![4]);
+        assert_eq!(filter.rom_types, vec![RomType::Nes]);
+        assert_eq!(filter.versions, vec!["1.*"]);
+
+        // Unknown qualifiers are part of the title
+        let filter = NodeFilter::parse("Zelda II: Link").unwrap();
+        assert_eq!(filter.title.as_deref(), Some("Zelda II: Link"));
+
+        assert!(NodeFilter::parse("mapper:mmc3").is_err());
+        assert!(NodeFilter::parse("type:snes").is_err());
+        assert!(NodeFilter::parse("tag:").is_err());
+        assert!(NodeFilter::parse("collection:").is_err());
+    }
+
+    #[test]
+    fn test_parse_rating_and_status() {
+        let filter = NodeFilter::parse("rating:>=4 rating:5 status:Finished").unwrap();
+        assert_eq!(filter.ratings, vec![(">=", 4), ("=", 5)]);
+        assert_eq!(filter.play_statuses, vec![PlayStatus::Finished]);
+        assert_eq!(filter.title, None);
+        let (sql, params) = filter.to_sql();
+        assert_eq!(sql, "1 AND rating >= ? AND rating = ? AND play_status = ?");
+        assert_eq!(params.len(), 3);
+
+        assert!(NodeFilter::parse("rating:6").is_err());
+        assert!(NodeFilter::parse("rating:>good").is_err());
+        assert!(NodeFilter::parse("status:beaten").is_err());
+    }
+
+    #[test]
+    fn test_parse_region_and_language() {
+        let filter = NodeFilter::parse("region:J lang:English mario").unwrap();
+        assert_eq!(filter.regions, vec![Region::Japan]);
+        assert_eq!(filter.languages, vec![Language::English]);
+        assert_eq!(filter.title.as_deref(), Some("mario"));
+        let (sql, params) = filter.to_sql();
+        assert!(sql.ends_with("AND ',' || COALESCE(language, '') || ',' LIKE ? ESCAPE '\\'"));
+        assert_eq!(params[1], Value::Text("%,Japan,%".to_string()));
+        assert_eq!(params[2], Value::Text("%,en,%".to_string()));
+
+        assert!(NodeFilter::parse("region:").is_err());
+    }
+
+    #[test]
+    fn test_parse_favorite() {
+        let filter = NodeFilter::parse("is:fav zelda").unwrap();
+        assert!(filter.favorite);
+        assert!(filter.to_sql().0.ends_with("AND favorite = 1"));
+        assert!(NodeFilter::parse("is:Favorite").unwrap().favorite);
+        assert!(!NodeFilter::parse("zelda").unwrap().favorite);
+        assert!(NodeFilter::parse("is:hidden").is_err());
+        assert!(NodeFilter::parse("is:").is_err());
+    }
+
+    #[test]
+    fn test_parse_filename() {
+        let filter = NodeFilter::parse("filename:*[T+Eng]*").unwrap();
+        assert_eq!(filter.filenames, vec!["*[T+Eng]*"]);
+        let (sql, params) = filter.to_sql();
+        assert!(sql.starts_with("1 AND (filename LIKE ? ESCAPE '\\' OR EXISTS"));
+        assert_eq!(params, vec![Value::Text("%[T+Eng]%".to_string()); 2]);
+        assert!(NodeFilter::parse("file:").is_err());
+    }
+
+    #[test]
+    fn test_parse_archived() {
+        let filter = NodeFilter::parse("is:archived").unwrap();
+        assert_eq!(filter.archived, Some(true));
+        let (sql, params) = filter.to_sql();
+        assert_eq!(sql, "1 AND archived = ?");
+        assert_eq!(params, vec![Value::Integer(1)]);
+        assert_eq!(NodeFilter::parse("zelda").unwrap().archived, None);
+    }
+
+    #[test]
+    fn test_parse_wanted() {
+        let filter = NodeFilter::parse("is:wanted").unwrap();
+        assert!(filter.missing);
+        assert_eq!(filter.to_sql().0, "1 AND missing = 1");
+        assert!(NodeFilter::parse("is:missing").unwrap().missing);
+    }
+
+    #[test]
+    fn test_parse_added() {
+        let filter = NodeFilter::parse("added:>=2024-01-01 added:30d zelda").unwrap();
+        assert_eq!(
+            filter.added,
+            vec![
+                (">=", AddedAt::Date("2024-01-01".to_string())),
+                ("=", AddedAt::Ago("-30 days".to_string())),
+            ]
+        );
+        let (sql, params) = filter.to_sql();
+        assert!(sql.ends_with("AND created_at >= ? AND created_at >= datetime('now', ?)"));
+        assert_eq!(
+            params[1..],
+            [
+                Value::Text("2024-01-01".to_string()),
+                Value::Text("-30 days".to_string())
+            ]
+        );
+
+        let (sql, params) = NodeFilter::parse("added:2024-03-01 added:<=2024-06-30 added:<2w")
+            .unwrap()
+            .to_sql();
+        assert_eq!(
+            sql,
+            "1 AND created_at >= ? AND created_at < date(?, '+1 day') \
+             AND created_at < date(?, '+1 day') AND created_at < datetime('now', ?)"
+        );
+        assert_eq!(params.len(), 4);
+        assert_eq!(params[3], Value::Text("-14 days".to_string()));
+
+        assert!(NodeFilter::parse("added:yesterday").is_err());
+        assert!(NodeFilter::parse("added:2024-13-01").is_err());
+        assert!(NodeFilter::parse("added:30x").is_err());
+        assert!(NodeFilter::parse("added:").is_err());
+    }
+
+    #[test]
+    fn test_glob_to_like() {
+        assert_eq!(glob_to_like("1.*"), "1.%");
+        assert_eq!(glob_to_like("rev?"), "rev_");
+        assert_eq!(glob_to_like("100%_a"), "100\\%\\_a");
+    }
+}
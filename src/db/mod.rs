@@ -1,7 +1,14 @@
+pub mod collation;
+pub mod filter;
 pub mod repository;
 pub mod schema;
 
-pub use repository::{EdgeRow, NodeMetadata, NodeRow, Repository};
+pub use filter::NodeFilter;
+pub use repository::{
+    AttachmentRow, CollectionRow, Cursor, DatMatch, EdgeRow, LocationRow, MAX_RATING, NodeMetadata,
+    NodeRow, PlayStatus, QueryResult, Repository, check_rating,
+};
 pub use schema::{
-    DATA_REVISION, get_stored_data_revision, has_existing_data, run_migrations, set_data_revision,
+    DATA_REVISION, get_stored_data_revision, has_existing_data, migrations_pending, run_migrations,
+    set_data_revision,
 };
@@ -1,10 +1,18 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use rusqlite::types::Value;
 use rusqlite::{Connection, OptionalExtension, Row, params};
 
+use super::filter::NodeFilter;
+use crate::dat::DatEntry;
 use crate::error::{DromosError, Result};
-use crate::rom::{RomMetadata, RomType, format_hash};
+use crate::rom::{Language, Region, RomMetadata, RomType, format_hash, parse_nes_header_bytes};
 
 /// Metadata for a ROM node (user-editable fields)
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeMetadata {
     pub title: String,
     pub source_url: Option<String>,
@@ -12,10 +20,227 @@ pub struct NodeMetadata {
     pub release_date: Option<String>,
     pub tags: Vec<String>,
     pub description: Option<String>,
+    /// 1 to 5 stars; None if unrated
+    pub rating: Option<u8>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub play_status: PlayStatus,
+    /// Regions the ROM was released for
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub regions: Vec<Region>,
+    /// Languages its text is in
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub languages: Vec<Language>,
+}
+
+impl NodeMetadata {
+    /// Check the fields before they're stored, tidying them on the way:
+    /// text is trimmed and blank text cleared, a version's leading `v` is
+    /// dropped (`v1.1` is `1.1`), and repeated tags and blank regions and
+    /// languages are removed. Fails on a source URL that isn't
+    /// `scheme://host...`, a release date that isn't a YYYY-MM-DD from 1970
+    /// on, a tag with characters other than letters, digits, and `-_.+`, a
+    /// rating outside 1 to [`MAX_RATING`], or a region or language with a
+    /// comma.
+    pub fn normalize(&mut self) -> std::result::Result<(), String> {
+        self.normalize_against(None)
+    }
+
+    /// Like [`NodeMetadata::normalize`] for an edit of `stored`, the node's
+    /// metadata as it's stored, checking only the fields the edit changes:
+    /// values saved before a check existed don't hold up the rest. The
+    /// title can't be changed to a blank one.
+    pub fn normalize_edit(&mut self, stored: &NodeMetadata) -> std::result::Result<(), String> {
+        self.normalize_against(Some(stored))
+    }
+
+    fn normalize_against(
+        &mut self,
+        stored: Option<&NodeMetadata>,
+    ) -> std::result::Result<(), String> {
+        let blank_to_none = |value: &mut Option<String>| {
+            *value = value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(String::from);
+        };
+
+        self.title = self.title.trim().to_string();
+        if self.title.is_empty() && stored.is_some_and(|s| s.title.trim() != self.title) {
+            return Err("title cannot be empty".to_string());
+        }
+
+        blank_to_none(&mut self.source_url);
+        if let Some(url) = &self.source_url
+            && stored.is_none_or(|s| s.source_url.as_ref() != Some(url))
+        {
+            check_url(url)?;
+        }
+
+        blank_to_none(&mut self.version);
+        if let Some(version) = &self.version {
+            let version = version.split_whitespace().collect::<Vec<_>>().join(" ");
+            let number = version
+                .strip_prefix(['v', 'V'])
+                .filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+            self.version = Some(number.map_or(version.clone(), String::from));
+        }
+
+        blank_to_none(&mut self.release_date);
+        if let Some(date) = &self.release_date
+            && stored.is_none_or(|s| s.release_date.as_ref() != Some(date))
+        {
+            self.release_date = Some(check_release_date(date)?);
+        }
+
+        let mut tags: Vec<String> = Vec::new();
+        for tag in &self.tags {
+            let tag = tag.trim();
+            if tag.is_empty() || tags.iter().any(|t| t == tag) {
+                continue;
+            }
+            let unchanged = stored.is_some_and(|s| s.tags.iter().any(|t| t.trim() == tag));
+            if let Some(c) = tag
+                .chars()
+                .find(|c| !c.is_alphanumeric() && !matches!(c, '-' | '_' | '.' | '+'))
+                .filter(|_| !unchanged)
+            {
+                let c = if c.is_whitespace() {
+                    "spaces".to_string()
+                } else {
+                    format!("`{}`", c)
+                };
+                return Err(format!(
+                    "tag `{}` can't contain {} (use letters, digits, and - _ . +)",
+                    tag, c
+                ));
+            }
+            tags.push(tag.to_string());
+        }
+        self.tags = tags;
+
+        blank_to_none(&mut self.description);
+        if let Some(rating) = self.rating
+            && stored.is_none_or(|s| s.rating != self.rating)
+        {
+            check_rating(rating)?;
+        }
+
+        // Stored comma-separated, so blanks are dropped and commas refused
+        self.regions.retain(|r| !r.name().trim().is_empty());
+        self.languages.retain(|l| !l.code().trim().is_empty());
+        let commas = self.regions.iter().any(|r| r.name().contains(','))
+            || self.languages.iter().any(|l| l.code().contains(','));
+        let changed =
+            stored.is_none_or(|s| s.regions != self.regions || s.languages != self.languages);
+        if commas && changed {
+            return Err("regions and languages cannot contain commas".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Check that `url` has a scheme and host, as in `https://example.com/rom`.
+fn check_url(url: &str) -> std::result::Result<(), String> {
+    let invalid = || format!("invalid source URL: {} (expected e.g. https://...)", url);
+    let (scheme, rest) = url.split_once("://").ok_or_else(invalid)?;
+    let scheme_ok = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+    if !scheme_ok || host.is_empty() || url.contains(char::is_whitespace) {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// `date` as YYYY-MM-DD, if it's a valid date from 1970 on.
+fn check_release_date(date: &str) -> std::result::Result<String, String> {
+    let parsed = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("invalid date: {} (expected YYYY-MM-DD)", date))?;
+    let earliest = chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap_or_default();
+    if parsed < earliest {
+        return Err(format!(
+            "release date {} is out of range (1970-01-01 on)",
+            date
+        ));
+    }
+    Ok(parsed.format("%Y-%m-%d").to_string())
+}
+
+/// Highest rating a node can have; ratings run from 1 up to it.
+pub const MAX_RATING: u8 = 5;
+
+/// Where a ROM stands in the user's backlog, in the order `list` sorts by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum PlayStatus {
+    #[default]
+    Unplayed,
+    Playing,
+    Finished,
+    Abandoned,
+}
+
+impl PlayStatus {
+    pub const ALL: [PlayStatus; 4] = [
+        PlayStatus::Unplayed,
+        PlayStatus::Playing,
+        PlayStatus::Finished,
+        PlayStatus::Abandoned,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlayStatus::Unplayed => "unplayed",
+            PlayStatus::Playing => "playing",
+            PlayStatus::Finished => "finished",
+            PlayStatus::Abandoned => "abandoned",
+        }
+    }
+}
+
+impl fmt::Display for PlayStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PlayStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        PlayStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "unknown play status: {} (expected unplayed, playing, finished, or abandoned)",
+                    s
+                )
+            })
+    }
+}
+
+/// Check that `rating` is from 1 to [`MAX_RATING`].
+pub fn check_rating(rating: u8) -> std::result::Result<u8, String> {
+    if (1..=MAX_RATING).contains(&rating) {
+        Ok(rating)
+    } else {
+        Err(format!(
+            "rating must be from 1 to {}: {}",
+            MAX_RATING, rating
+        ))
+    }
 }
 
 /// Map a database row to NodeRow. Expects columns in order:
-/// id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header
+/// id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header, created_at, updated_at, sha1, crc32, rating, play_status, region, language, favorite, archived, missing, content_size
 fn map_row_to_node_row(row: &Row) -> rusqlite::Result<NodeRow> {
     let hash_str: String = row.get(1)?;
     let sha256 = hex::decode(&hash_str)
@@ -26,6 +251,7 @@ fn map_row_to_node_row(row: &Row) -> rusqlite::Result<NodeRow> {
     let rom_type_str: String = row.get(4)?;
     let rom_type = rom_type_str.parse().unwrap_or(RomType::Nes);
     let filename: Option<String> = row.get(2)?;
+    let sha1: Option<String> = row.get(13)?;
 
     // Parse tags from JSON array
     let tags_json: Option<String> = row.get(8)?;
@@ -36,6 +262,8 @@ fn map_row_to_node_row(row: &Row) -> rusqlite::Result<NodeRow> {
     Ok(NodeRow {
         id: row.get(0)?,
         sha256,
+        sha1: sha1.and_then(|s| hex::decode(s).ok()?.try_into().ok()),
+        crc32: row.get(14)?,
         title: title.unwrap_or_else(|| filename.clone().unwrap_or_default()),
         filename,
         rom_type,
@@ -45,13 +273,50 @@ fn map_row_to_node_row(row: &Row) -> rusqlite::Result<NodeRow> {
         tags,
         description: row.get(9)?,
         source_file_header: row.get(10)?,
+        created_at: row.get(11)?,
+        updated_at: row.get(12)?,
+        rating: row.get(15)?,
+        play_status: row.get::<_, String>(16)?.parse().unwrap_or_default(),
+        regions: load_list(row.get(17)?),
+        languages: load_list(row.get(18)?),
+        favorite: row.get(19)?,
+        archived: row.get(20)?,
+        missing: row.get(21)?,
+        content_size: row.get::<_, Option<i64>>(22)?.map(|s| s as u64),
+    })
+}
+
+/// Store a region or language list as comma-separated text, or NULL if empty.
+fn store_list<T: fmt::Display>(items: &[T]) -> Option<String> {
+    if items.is_empty() {
+        return None;
+    }
+    let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+    Some(items.join(","))
+}
+
+fn load_list<T: From<String>>(text: Option<String>) -> Vec<T> {
+    text.map(|text| {
+        text.split(',')
+            .filter(|item| !item.is_empty())
+            .map(|item| T::from(item.to_string()))
+            .collect()
     })
+    .unwrap_or_default()
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeRow {
     pub id: i64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
     pub sha256: [u8; 32],
+    /// Recorded when the node was added or last linked; None for nodes
+    /// from before SHA-1s were kept
+    #[cfg_attr(feature = "serde", serde(default, with = "crate::wire::optional_hex"))]
+    pub sha1: Option<[u8; 20]>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub crc32: Option<u32>,
     pub filename: Option<String>,
     pub title: String,
     pub rom_type: RomType,
@@ -61,17 +326,305 @@ pub struct NodeRow {
     pub release_date: Option<String>,
     pub tags: Vec<String>,
     pub description: Option<String>,
+    /// 1 to 5 stars; None if unrated
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rating: Option<u8>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub play_status: PlayStatus,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub regions: Vec<Region>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub languages: Vec<Language>,
+    /// Pinned with `fav`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub favorite: bool,
+    /// Hidden from `list` and `search` unless `--all` is given
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub archived: bool,
+    /// On the wishlist: known by hash, but no file has been seen yet
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub missing: bool,
     /// Raw file header bytes for byte-identical reconstruction
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::optional_bytes"))]
     pub source_file_header: Option<Vec<u8>>,
+    /// Length of the ROM data, headers excluded, as hashed when the node
+    /// was added; None for nodes from before it was recorded
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub content_size: Option<u64>,
+    /// When the node was added (SQLite `datetime('now')`, UTC)
+    pub created_at: String,
+    /// When the metadata was last edited, if ever (same format as `created_at`)
+    pub updated_at: Option<String>,
+}
+
+impl NodeRow {
+    /// The node's user-editable metadata.
+    pub fn metadata(&self) -> NodeMetadata {
+        NodeMetadata {
+            title: self.title.clone(),
+            source_url: self.source_url.clone(),
+            version: self.version.clone(),
+            release_date: self.release_date.clone(),
+            tags: self.tags.clone(),
+            description: self.description.clone(),
+            rating: self.rating,
+            play_status: self.play_status,
+            regions: self.regions.clone(),
+            languages: self.languages.clone(),
+        }
+    }
+
+    /// ROM data size: the recorded content size, or else PRG + CHR from
+    /// the stored header, if it has one
+    pub fn data_size(&self) -> Option<usize> {
+        if let Some(size) = self.content_size {
+            return Some(size as usize);
+        }
+        let raw: &[u8; 16] = self
+            .source_file_header
+            .as_deref()?
+            .get(..16)?
+            .try_into()
+            .ok()?;
+        parse_nes_header_bytes(raw).map(|h| h.prg_rom_size + h.chr_rom_size)
+    }
+}
+
+/// The DAT entry a node's hashes match: the node is a verified good dump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DatMatch {
+    /// Name of the DAT file the entry came from
+    pub dat_name: String,
+    /// Canonical name of the game
+    pub game: String,
+    pub rom_name: String,
+}
+
+/// Joins nodes to the DAT entries their SHA-256 or SHA-1 match, first
+/// imported entry first. Selects the node's sha256 and the DatMatch fields.
+const DAT_MATCH_SQL: &str = "SELECT n.sha256, d.dat_name, d.game, d.rom_name
+     FROM nodes n JOIN dat_entries d ON d.sha256 = n.sha256 OR d.sha1 = n.sha1";
+
+/// A file attached to a node, or to the link between two nodes. Its contents
+/// are stored once per SHA-256, however many times it's attached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttachmentRow {
+    pub id: i64,
+    /// The node, or one end of the link
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
+    pub node: [u8; 32],
+    /// The link's other end; None when attached to the node itself
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::optional_hex"))]
+    pub linked_node: Option<[u8; 32]>,
+    /// File name, without any folders
+    pub name: String,
+    /// SHA-256 of the contents
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
+    pub sha256: [u8; 32],
+    pub size: i64,
+    pub created_at: String,
+}
+
+impl AttachmentRow {
+    /// True if this is attached to `node` itself, or with `linked`, to the
+    /// link between them
+    pub fn is_on(&self, node: &[u8; 32], linked: Option<&[u8; 32]>) -> bool {
+        match (self.linked_node, linked) {
+            (None, None) => self.node == *node,
+            (Some(other), Some(linked)) => {
+                (self.node == *node && other == *linked) || (self.node == *linked && other == *node)
+            }
+            _ => false,
+        }
+    }
+
+    /// For an attachment on a link, the end that isn't `node`
+    pub fn link_end_from(&self, node: &[u8; 32]) -> Option<[u8; 32]> {
+        let linked = self.linked_node?;
+        Some(if linked == *node { self.node } else { linked })
+    }
+}
+
+/// A named collection of nodes, with how many it holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CollectionRow {
+    pub id: i64,
+    pub name: String,
+    pub nodes: usize,
+    pub created_at: String,
+}
+
+/// A place on disk where a node's file was found by `locate --record`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LocationRow {
+    pub path: String,
+    /// Whether the file there had the header stored with the node
+    pub header_matches: bool,
+    pub last_seen: String,
+}
+
+/// Selects the AttachmentRow fields, with the hashes of the nodes attached to
+const ATTACHMENT_SQL: &str =
+    "SELECT a.id, n.sha256, l.sha256, a.name, a.sha256, a.size, a.created_at
+     FROM attachments a JOIN nodes n ON n.id = a.node_id
+     LEFT JOIN nodes l ON l.id = a.linked_node_id";
+
+/// Column names and rows from an ad-hoc query
+#[derive(Debug, Clone)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EdgeRow {
     pub id: i64,
     pub source_id: i64,
     pub target_id: i64,
     pub diff_path: String,
     pub diff_size: i64,
+    /// What changed between the two ROMs, as given when they were linked
+    pub note: Option<String>,
+    /// SHA-256 of the diff file, in hex; None if linked before it was kept
+    pub diff_sha256: Option<String>,
+    /// Size of the ROM data the diff builds; None if linked before it was kept
+    pub output_size: Option<i64>,
+}
+
+/// Map a database row to EdgeRow. Expects columns in order:
+/// id, source_id, target_id, diff_path, diff_size, note, diff_sha256,
+/// output_size, output_size
+fn map_row_to_edge_row(row: &Row) -> rusqlite::Result<EdgeRow> {
+    Ok(EdgeRow {
+        id: row.get(0)?,
+        source_id: row.get(1)?,
+        target_id: row.get(2)?,
+        diff_path: row.get(3)?,
+        diff_size: row.get(4)?,
+        note: row.get(5)?,
+        diff_sha256: row.get(6)?,
+        output_size: row.get(7)?,
+    })
+}
+
+/// Map a [`DAT_MATCH_SQL`] row to the node's hash and its match
+fn map_row_to_dat_match(row: &Row) -> rusqlite::Result<([u8; 32], DatMatch)> {
+    let hash_str: String = row.get(0)?;
+    let sha256 = hex::decode(&hash_str)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .unwrap_or([0u8; 32]);
+    Ok((
+        sha256,
+        DatMatch {
+            dat_name: row.get(1)?,
+            game: row.get(2)?,
+            rom_name: row.get(3)?,
+        },
+    ))
+}
+
+/// Map an [`ATTACHMENT_SQL`] row to AttachmentRow
+fn map_row_to_attachment_row(row: &Row) -> rusqlite::Result<AttachmentRow> {
+    let hash = |text: Option<String>| {
+        text.and_then(|t| hex::decode(t).ok())
+            .and_then(|b| b.try_into().ok())
+    };
+    Ok(AttachmentRow {
+        id: row.get(0)?,
+        node: hash(row.get(1)?).unwrap_or([0u8; 32]),
+        linked_node: hash(row.get(2)?),
+        name: row.get(3)?,
+        sha256: hash(row.get(4)?).unwrap_or([0u8; 32]),
+        size: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+/// iNES mapper number decoded from a node's stored header, if it has one
+fn header_mapper(row: &NodeRow) -> Option<u16> {
+    row.source_file_header
+        .as_deref()
+        .and_then(|h| h.get(..16))
+        .and_then(|h| h.try_into().ok())
+        .and_then(parse_nes_header_bytes)
+        .map(|h| h.mapper)
+}
+
+/// Rows fetched per query by a [`Cursor`] unless set with [`Cursor::page_size`]
+const DEFAULT_PAGE_SIZE: usize = 256;
+
+/// Streams rows in id order, querying one page at a time so memory stays
+/// bounded however large the library is. Rows added or removed while
+/// iterating may or may not be seen.
+pub struct Cursor<'a, T> {
+    conn: &'a Connection,
+    /// Query ending in `id > ? ORDER BY id LIMIT ?`, filled in per page
+    sql: String,
+    params: Vec<Value>,
+    map: fn(&Row) -> rusqlite::Result<T>,
+    id_of: fn(&T) -> i64,
+    /// Conditions checked in Rust after each page is read
+    keep: Box<dyn Fn(&T) -> bool + 'a>,
+    page_size: usize,
+    after_id: i64,
+    page: std::vec::IntoIter<T>,
+    done: bool,
+}
+
+impl<'a, T> Cursor<'a, T> {
+    /// Number of rows to read per query.
+    pub fn page_size(mut self, rows: usize) -> Self {
+        self.page_size = rows.max(1);
+        self
+    }
+
+    fn fetch_page(&mut self) -> Result<()> {
+        let mut stmt = self.conn.prepare(&self.sql)?;
+        let params = self.params.iter().cloned().chain([
+            Value::Integer(self.after_id),
+            Value::Integer(self.page_size as i64),
+        ]);
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), self.map)?;
+
+        let mut page = Vec::new();
+        let mut fetched = 0;
+        for row in rows {
+            let row = row?;
+            fetched += 1;
+            self.after_id = (self.id_of)(&row);
+            if (self.keep)(&row) {
+                page.push(row);
+            }
+        }
+        self.done = fetched < self.page_size;
+        self.page = page.into_iter();
+        Ok(())
+    }
+}
+
+impl<T> Iterator for Cursor<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        loop {
+            if let Some(row) = self.page.next() {
+                return Some(Ok(row));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(e) = self.fetch_page() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
 }
 
 pub struct Repository<'a> {
@@ -99,8 +652,8 @@ impl<'a> Repository<'a> {
         };
 
         self.conn.execute(
-            "INSERT INTO nodes (sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            "INSERT INTO nodes (sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header, sha1, crc32, rating, play_status, region, language, content_size)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
             params![
                 hash_hex,
                 metadata.filename.as_deref(),
@@ -112,6 +665,13 @@ impl<'a> Repository<'a> {
                 &tags_json,
                 &node_metadata.description,
                 &metadata.source_file_header,
+                metadata.sha1.map(hex::encode),
+                metadata.crc32,
+                node_metadata.rating,
+                node_metadata.play_status.as_str(),
+                store_list(&node_metadata.regions),
+                store_list(&node_metadata.languages),
+                metadata.content_size.map(|s| s as i64),
             ],
         )?;
 
@@ -124,6 +684,7 @@ impl<'a> Repository<'a> {
         target_id: i64,
         diff_path: &str,
         diff_size: i64,
+        note: Option<&str>,
     ) -> Result<i64> {
         // Check if edge already exists
         let exists: bool = self.conn.query_row(
@@ -140,9 +701,9 @@ impl<'a> Repository<'a> {
         }
 
         self.conn.execute(
-            "INSERT INTO edges (source_id, target_id, diff_path, diff_size)
-             VALUES (?1, ?2, ?3, ?4)",
-            params![source_id, target_id, diff_path, diff_size],
+            "INSERT INTO edges (source_id, target_id, diff_path, diff_size, note)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![source_id, target_id, diff_path, diff_size, note],
         )?;
 
         Ok(self.conn.last_insert_rowid())
@@ -154,7 +715,7 @@ impl<'a> Repository<'a> {
         let result = self
             .conn
             .query_row(
-                "SELECT id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header
+                "SELECT id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header, created_at, updated_at, sha1, crc32, rating, play_status, region, language, favorite, archived, missing, content_size
                  FROM nodes WHERE sha256 = ?1",
                 params![hash_hex],
                 map_row_to_node_row,
@@ -168,7 +729,7 @@ impl<'a> Repository<'a> {
         let result = self
             .conn
             .query_row(
-                "SELECT id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header
+                "SELECT id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header, created_at, updated_at, sha1, crc32, rating, play_status, region, language, favorite, archived, missing, content_size
                  FROM nodes WHERE id = ?1",
                 params![id],
                 map_row_to_node_row,
@@ -180,7 +741,7 @@ impl<'a> Repository<'a> {
 
     pub fn load_all_nodes(&self) -> Result<Vec<NodeRow>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header
+            "SELECT id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header, created_at, updated_at, sha1, crc32, rating, play_status, region, language, favorite, archived, missing, content_size
              FROM nodes ORDER BY id",
         )?;
 
@@ -195,19 +756,11 @@ impl<'a> Repository<'a> {
 
     pub fn load_all_edges(&self) -> Result<Vec<EdgeRow>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, source_id, target_id, diff_path, diff_size
+            "SELECT id, source_id, target_id, diff_path, diff_size, note, diff_sha256, output_size
              FROM edges ORDER BY id",
         )?;
 
-        let rows = stmt.query_map([], |row| {
-            Ok(EdgeRow {
-                id: row.get(0)?,
-                source_id: row.get(1)?,
-                target_id: row.get(2)?,
-                diff_path: row.get(3)?,
-                diff_size: row.get(4)?,
-            })
-        })?;
+        let rows = stmt.query_map([], map_row_to_edge_row)?;
 
         let mut edges = Vec::new();
         for row in rows {
@@ -219,19 +772,11 @@ impl<'a> Repository<'a> {
     /// Get all edges involving a node (as source or target)
     pub fn get_edges_for_node(&self, node_id: i64) -> Result<Vec<EdgeRow>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, source_id, target_id, diff_path, diff_size
+            "SELECT id, source_id, target_id, diff_path, diff_size, note, diff_sha256, output_size
              FROM edges WHERE source_id = ?1 OR target_id = ?1",
         )?;
 
-        let rows = stmt.query_map(params![node_id], |row| {
-            Ok(EdgeRow {
-                id: row.get(0)?,
-                source_id: row.get(1)?,
-                target_id: row.get(2)?,
-                diff_path: row.get(3)?,
-                diff_size: row.get(4)?,
-            })
-        })?;
+        let rows = stmt.query_map(params![node_id], map_row_to_edge_row)?;
 
         let mut edges = Vec::new();
         for row in rows {
@@ -240,13 +785,203 @@ impl<'a> Repository<'a> {
         Ok(edges)
     }
 
+    /// Load the most recently added nodes, newest first
+    pub fn load_recent_nodes(&self, limit: usize) -> Result<Vec<NodeRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header, created_at, updated_at, sha1, crc32, rating, play_status, region, language, favorite, archived, missing, content_size
+             FROM nodes ORDER BY created_at DESC, id DESC LIMIT ?1",
+        )?;
+
+        let rows = stmt.query_map(params![limit as i64], map_row_to_node_row)?;
+
+        let mut nodes = Vec::new();
+        for row in rows {
+            nodes.push(row?);
+        }
+        Ok(nodes)
+    }
+
+    /// Load the most recently added or edited nodes, newest change first
+    pub fn load_recently_changed_nodes(&self, limit: usize) -> Result<Vec<NodeRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header, created_at, updated_at, sha1, crc32, rating, play_status, region, language, favorite, archived, missing, content_size
+             FROM nodes ORDER BY COALESCE(updated_at, created_at) DESC, id DESC LIMIT ?1",
+        )?;
+
+        let rows = stmt.query_map(params![limit as i64], map_row_to_node_row)?;
+
+        let mut nodes = Vec::new();
+        for row in rows {
+            nodes.push(row?);
+        }
+        Ok(nodes)
+    }
+
+    /// Load the nodes matching a search filter, ordered by title
+    pub fn search_nodes(&self, filter: &NodeFilter) -> Result<Vec<NodeRow>> {
+        let (condition, values) = filter.to_sql();
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header, created_at, updated_at, sha1, crc32, rating, play_status, region, language, favorite, archived, missing, content_size
+             FROM nodes WHERE {} ORDER BY title COLLATE TITLE, id",
+            condition
+        ))?;
+
+        let rows = stmt.query_map(rusqlite::params_from_iter(values), map_row_to_node_row)?;
+
+        let mut nodes = Vec::new();
+        for row in rows {
+            let row = row?;
+            if filter.matches_mapper(header_mapper(&row)) {
+                nodes.push(row);
+            }
+        }
+        Ok(nodes)
+    }
+
+    /// Whether the node with `node_id` matches a search filter.
+    pub fn node_matches(&self, node_id: i64, filter: &NodeFilter) -> Result<bool> {
+        let (condition, mut values) = filter.to_sql();
+        values.insert(0, Value::Integer(node_id));
+        let row = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header, created_at, updated_at, sha1, crc32, rating, play_status, region, language, favorite, archived, missing, content_size
+                     FROM nodes WHERE id = ? AND {}",
+                    condition
+                ),
+                rusqlite::params_from_iter(values),
+                map_row_to_node_row,
+            )
+            .optional()?;
+        Ok(row.is_some_and(|row| filter.matches_mapper(header_mapper(&row))))
+    }
+
+    /// Stream the nodes matching a search filter, in the order they were added.
+    pub fn iter_nodes_filtered(&self, filter: &NodeFilter) -> Cursor<'a, NodeRow> {
+        let (condition, params) = filter.to_sql();
+        let filter = filter.clone();
+        Cursor {
+            conn: self.conn,
+            sql: format!(
+                "SELECT id, sha256, filename, title, rom_type, source_url, version, release_date, tags, description, source_file_header, created_at, updated_at, sha1, crc32, rating, play_status, region, language, favorite, archived, missing, content_size
+                 FROM nodes WHERE {} AND id > ? ORDER BY id LIMIT ?",
+                condition
+            ),
+            params,
+            map: map_row_to_node_row,
+            id_of: |row| row.id,
+            keep: Box::new(move |row| filter.matches_mapper(header_mapper(row))),
+            page_size: DEFAULT_PAGE_SIZE,
+            after_id: 0,
+            page: Vec::new().into_iter(),
+            done: false,
+        }
+    }
+
+    /// Stream the edges involving a node (as source or target).
+    pub fn iter_edges_for(&self, node_id: i64) -> Cursor<'a, EdgeRow> {
+        Cursor {
+            conn: self.conn,
+            sql: "SELECT id, source_id, target_id, diff_path, diff_size, note, diff_sha256, output_size
+                  FROM edges WHERE (source_id = ? OR target_id = ?) AND id > ? ORDER BY id LIMIT ?"
+                .to_string(),
+            params: vec![Value::Integer(node_id), Value::Integer(node_id)],
+            map: map_row_to_edge_row,
+            id_of: |row| row.id,
+            keep: Box::new(|_| true),
+            page_size: DEFAULT_PAGE_SIZE,
+            after_id: 0,
+            page: Vec::new().into_iter(),
+            done: false,
+        }
+    }
+
+    /// Run a single read-only statement (e.g. a SELECT) and collect every row.
+    /// Statements that could modify the database are rejected before running.
+    pub fn query_readonly(&self, sql: &str) -> Result<QueryResult> {
+        // SQLite counts BEGIN, ATTACH, and most PRAGMAs as read-only, so
+        // only queries are let through
+        if !matches!(
+            leading_keyword(sql).as_str(),
+            "select" | "with" | "values" | "explain"
+        ) {
+            return Err(DromosError::QueryNotReadOnly);
+        }
+        let mut stmt = self.conn.prepare(sql)?;
+        if !stmt.readonly() {
+            return Err(DromosError::QueryNotReadOnly);
+        }
+        let result = Self::collect_rows(&mut stmt);
+        if !self.conn.is_autocommit() {
+            self.conn.execute_batch("ROLLBACK")?;
+            return Err(DromosError::QueryNotReadOnly);
+        }
+        result
+    }
+
+    fn collect_rows(stmt: &mut rusqlite::Statement) -> Result<QueryResult> {
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let column_count = columns.len();
+        let rows = stmt.query_map([], |row| {
+            (0..column_count)
+                .map(|i| row.get::<_, Value>(i))
+                .collect::<rusqlite::Result<Vec<Value>>>()
+        })?;
+
+        let mut result = QueryResult {
+            columns,
+            rows: Vec::new(),
+        };
+        for row in rows {
+            result.rows.push(row?);
+        }
+        Ok(result)
+    }
+
+    /// Read a value from the dromos_meta key/value table
+    pub fn get_meta(&self, key: &str) -> Result<Option<String>> {
+        let value = self
+            .conn
+            .query_row(
+                "SELECT value FROM dromos_meta WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
+    /// Write a value to the dromos_meta key/value table
+    pub fn set_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO dromos_meta (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
     /// Delete all edges where source_id or target_id matches, then delete the node
     pub fn delete_node(&self, node_id: i64) -> Result<()> {
-        // Delete all edges involving this node
+        // Delete all edges and attachments involving this node
         self.conn.execute(
             "DELETE FROM edges WHERE source_id = ?1 OR target_id = ?1",
             params![node_id],
         )?;
+        self.conn.execute(
+            "DELETE FROM attachments WHERE node_id = ?1 OR linked_node_id = ?1",
+            params![node_id],
+        )?;
+        self.conn.execute(
+            "DELETE FROM collection_nodes WHERE node_id = ?1",
+            params![node_id],
+        )?;
+        self.conn.execute(
+            "DELETE FROM filename_aliases WHERE node_id = ?1",
+            params![node_id],
+        )?;
+        self.conn
+            .execute("DELETE FROM locations WHERE node_id = ?1", params![node_id])?;
 
         // Delete the node itself
         self.conn
@@ -255,6 +990,305 @@ impl<'a> Repository<'a> {
         Ok(())
     }
 
+    /// Fill in a node's SHA-1, CRC32, and content size from freshly hashed
+    /// metadata, if it was added before they were recorded.
+    pub fn record_checksums(&self, metadata: &RomMetadata) -> Result<()> {
+        if let Some(size) = metadata.content_size {
+            self.conn.execute(
+                "UPDATE nodes SET content_size = ?1 WHERE sha256 = ?2 AND content_size IS NULL",
+                params![size as i64, format_hash(&metadata.sha256)],
+            )?;
+        }
+        if let Some(sha1) = metadata.sha1 {
+            self.conn.execute(
+                "UPDATE nodes SET sha1 = ?1, crc32 = ?2 WHERE sha256 = ?3 AND sha1 IS NULL",
+                params![
+                    hex::encode(sha1),
+                    metadata.crc32,
+                    format_hash(&metadata.sha256)
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Replace the entries loaded from the DAT named `dat_name` with
+    /// `entries`. Run inside a transaction to make the swap atomic.
+    pub fn replace_dat_entries(&self, dat_name: &str, entries: &[DatEntry]) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM dat_entries WHERE dat_name = ?1",
+            params![dat_name],
+        )?;
+        let mut stmt = self.conn.prepare(
+            "INSERT INTO dat_entries (dat_name, game, rom_name, size, crc32, sha1, sha256)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for entry in entries {
+            stmt.execute(params![
+                dat_name,
+                &entry.game,
+                &entry.rom_name,
+                entry.size.map(|s| s as i64),
+                entry.crc32,
+                entry.sha1.map(hex::encode),
+                entry.sha256.as_ref().map(format_hash),
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// The DAT entry matching a node, if any
+    pub fn dat_match(&self, sha256: &[u8; 32]) -> Result<Option<DatMatch>> {
+        let sql = format!(
+            "{} WHERE n.sha256 = ?1 ORDER BY d.id LIMIT 1",
+            DAT_MATCH_SQL
+        );
+        let found = self
+            .conn
+            .query_row(&sql, params![format_hash(sha256)], |row| {
+                map_row_to_dat_match(row)
+            })
+            .optional()?;
+        Ok(found.map(|(_, m)| m))
+    }
+
+    /// Loaded DAT entries whose game name contains `text`, ignoring case,
+    /// in the order they were imported.
+    pub fn dat_entries_named(&self, text: &str) -> Result<Vec<DatEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT game, rom_name, size, crc32, sha1, sha256 FROM dat_entries
+             WHERE instr(lower(game), lower(?1)) > 0 ORDER BY id",
+        )?;
+        let entries = stmt
+            .query_map(params![text], |row| {
+                let hex_bytes = |hex: Option<String>| hex.and_then(|h| hex::decode(h).ok());
+                Ok(DatEntry {
+                    game: row.get(0)?,
+                    rom_name: row.get(1)?,
+                    size: row.get::<_, Option<i64>>(2)?.map(|s| s as u64),
+                    crc32: row.get(3)?,
+                    sha1: hex_bytes(row.get(4)?).and_then(|b| b.try_into().ok()),
+                    sha256: hex_bytes(row.get(5)?).and_then(|b| b.try_into().ok()),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    /// The DAT entry matching each node that has one, by node hash
+    pub fn dat_matches(&self) -> Result<HashMap<[u8; 32], DatMatch>> {
+        let sql = format!("{} ORDER BY d.id", DAT_MATCH_SQL);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut matches = HashMap::new();
+        for found in stmt.query_map([], map_row_to_dat_match)? {
+            let (sha256, dat_match) = found?;
+            matches.entry(sha256).or_insert(dat_match);
+        }
+        Ok(matches)
+    }
+
+    /// Attach a file to a node, or to its link with `linked_node_id`,
+    /// replacing any attachment with the same name there. Returns its ID.
+    pub fn insert_attachment(
+        &self,
+        node_id: i64,
+        linked_node_id: Option<i64>,
+        name: &str,
+        sha256: &[u8; 32],
+        size: i64,
+    ) -> Result<i64> {
+        // Links are stored with the lower node ID first
+        let (node_id, linked_node_id) = match linked_node_id {
+            Some(other) if other < node_id => (other, Some(node_id)),
+            other => (node_id, other),
+        };
+        self.conn.execute(
+            "DELETE FROM attachments
+             WHERE node_id = ?1 AND linked_node_id IS ?2 AND name = ?3",
+            params![node_id, linked_node_id, name],
+        )?;
+        self.conn.execute(
+            "INSERT INTO attachments (node_id, linked_node_id, name, sha256, size)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![node_id, linked_node_id, name, format_hash(sha256), size],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Files attached to a node and to its links, the node's own first,
+    /// each group ordered by name
+    pub fn get_attachments_for_node(&self, node_id: i64) -> Result<Vec<AttachmentRow>> {
+        let sql = format!(
+            "{} WHERE a.node_id = ?1 OR a.linked_node_id = ?1
+             ORDER BY a.linked_node_id IS NOT NULL, a.name, a.id",
+            ATTACHMENT_SQL
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![node_id], map_row_to_attachment_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn load_all_attachments(&self) -> Result<Vec<AttachmentRow>> {
+        let sql = format!("{} ORDER BY a.id", ATTACHMENT_SQL);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([], map_row_to_attachment_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn delete_attachment(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM attachments WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// True if any attachment has contents with this SHA-256
+    pub fn attachment_contents_in_use(&self, sha256: &[u8; 32]) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM attachments WHERE sha256 = ?1)",
+            params![format_hash(sha256)],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Create an empty collection. Returns its ID.
+    pub fn insert_collection(&self, name: &str) -> Result<i64> {
+        self.conn
+            .execute("INSERT INTO collections (name) VALUES (?1)", params![name])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// The ID of the collection with this name, in any case
+    pub fn collection_id(&self, name: &str) -> Result<Option<i64>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id FROM collections WHERE name = ?1",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Every collection, ordered by name
+    pub fn load_collections(&self) -> Result<Vec<CollectionRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.name, COUNT(cn.node_id), c.created_at
+             FROM collections c LEFT JOIN collection_nodes cn ON cn.collection_id = c.id
+             GROUP BY c.id ORDER BY c.name, c.id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(CollectionRow {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                nodes: row.get::<_, i64>(2)? as usize,
+                created_at: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Names of the collections holding a node, in order
+    pub fn collections_for_node(&self, node_id: i64) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.name FROM collections c
+             JOIN collection_nodes cn ON cn.collection_id = c.id
+             WHERE cn.node_id = ?1 ORDER BY c.name",
+        )?;
+        let names = stmt.query_map(params![node_id], |row| row.get(0))?;
+        Ok(names.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Give node `to` the collections, file names, and locations of node
+    /// `from`, for merging duplicates. Returns the names of the collections
+    /// `to` joined, in order.
+    pub fn move_node_records(&self, from: i64, to: i64) -> Result<Vec<String>> {
+        let held = self.collections_for_node(to)?;
+        let joined = self
+            .collections_for_node(from)?
+            .into_iter()
+            .filter(|name| !held.contains(name))
+            .collect();
+        self.conn.execute(
+            "INSERT OR IGNORE INTO collection_nodes (collection_id, node_id)
+             SELECT collection_id, ?2 FROM collection_nodes WHERE node_id = ?1",
+            params![from, to],
+        )?;
+        self.conn.execute(
+            "INSERT OR IGNORE INTO filename_aliases (node_id, filename, first_seen)
+             SELECT ?2, filename, first_seen FROM filename_aliases WHERE node_id = ?1",
+            params![from, to],
+        )?;
+        self.conn.execute(
+            "INSERT OR IGNORE INTO locations (node_id, path, header_matches, last_seen)
+             SELECT ?2, path, header_matches, last_seen FROM locations WHERE node_id = ?1",
+            params![from, to],
+        )?;
+        Ok(joined)
+    }
+
+    /// Remember that the node with this hash was found at `path`, updating
+    /// when it was last seen there. Returns false if there's no such node.
+    pub fn record_location(
+        &self,
+        sha256: &[u8; 32],
+        path: &str,
+        header_matches: bool,
+    ) -> Result<bool> {
+        let recorded = self.conn.execute(
+            "INSERT OR REPLACE INTO locations (node_id, path, header_matches)
+             SELECT id, ?2, ?3 FROM nodes WHERE sha256 = ?1",
+            params![format_hash(sha256), path, header_matches],
+        )?;
+        Ok(recorded > 0)
+    }
+
+    /// Every place a node's file was found, by path
+    pub fn locations(&self, node_id: i64) -> Result<Vec<LocationRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, header_matches, last_seen FROM locations WHERE node_id = ?1
+             ORDER BY path",
+        )?;
+        let rows = stmt.query_map(params![node_id], |row| {
+            Ok(LocationRow {
+                path: row.get(0)?,
+                header_matches: row.get(1)?,
+                last_seen: row.get(2)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Add a node to a collection. Returns false if it was already there.
+    pub fn add_to_collection(&self, collection_id: i64, node_id: i64) -> Result<bool> {
+        let added = self.conn.execute(
+            "INSERT OR IGNORE INTO collection_nodes (collection_id, node_id) VALUES (?1, ?2)",
+            params![collection_id, node_id],
+        )?;
+        Ok(added > 0)
+    }
+
+    /// Take a node out of a collection. Returns false if it wasn't there.
+    pub fn remove_from_collection(&self, collection_id: i64, node_id: i64) -> Result<bool> {
+        let removed = self.conn.execute(
+            "DELETE FROM collection_nodes WHERE collection_id = ?1 AND node_id = ?2",
+            params![collection_id, node_id],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Delete a collection, leaving the nodes it held alone
+    pub fn delete_collection(&self, collection_id: i64) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM collection_nodes WHERE collection_id = ?1",
+            params![collection_id],
+        )?;
+        self.conn.execute(
+            "DELETE FROM collections WHERE id = ?1",
+            params![collection_id],
+        )?;
+        Ok(())
+    }
+
     /// Update metadata fields for a node
     pub fn update_node_metadata(&self, node_id: i64, metadata: &NodeMetadata) -> Result<()> {
         // Serialize tags to JSON
@@ -265,7 +1299,7 @@ impl<'a> Repository<'a> {
         };
 
         self.conn.execute(
-            "UPDATE nodes SET title = ?1, source_url = ?2, version = ?3, release_date = ?4, tags = ?5, description = ?6 WHERE id = ?7",
+            "UPDATE nodes SET title = ?1, source_url = ?2, version = ?3, release_date = ?4, tags = ?5, description = ?6, rating = ?7, play_status = ?8, region = ?9, language = ?10, updated_at = datetime('now') WHERE id = ?11",
             params![
                 &metadata.title,
                 &metadata.source_url,
@@ -273,12 +1307,139 @@ impl<'a> Repository<'a> {
                 &metadata.release_date,
                 &tags_json,
                 &metadata.description,
+                metadata.rating,
+                metadata.play_status.as_str(),
+                store_list(&metadata.regions),
+                store_list(&metadata.languages),
                 node_id,
             ],
         )?;
 
         Ok(())
     }
+
+    /// Pin or unpin a node. Not an edit, so `updated_at` is left alone.
+    pub fn set_favorite(&self, node_id: i64, favorite: bool) -> Result<()> {
+        self.conn.execute(
+            "UPDATE nodes SET favorite = ?1 WHERE id = ?2",
+            params![favorite, node_id],
+        )?;
+        Ok(())
+    }
+
+    /// Replace a node's tags without counting it as an edit, for tags added
+    /// automatically.
+    pub fn set_tags(&self, node_id: i64, tags: &[String]) -> Result<()> {
+        let tags_json = if tags.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(tags).unwrap_or_default())
+        };
+        self.conn.execute(
+            "UPDATE nodes SET tags = ?1 WHERE id = ?2",
+            params![tags_json, node_id],
+        )?;
+        Ok(())
+    }
+
+    /// Archive or unarchive a node, leaving `updated_at` alone like
+    /// [`Repository::set_favorite`].
+    pub fn set_archived(&self, node_id: i64, archived: bool) -> Result<()> {
+        self.conn.execute(
+            "UPDATE nodes SET archived = ?1 WHERE id = ?2",
+            params![archived, node_id],
+        )?;
+        Ok(())
+    }
+
+    /// Mark a node as on the wishlist, with no file seen yet.
+    pub fn set_missing(&self, node_id: i64, missing: bool) -> Result<()> {
+        self.conn.execute(
+            "UPDATE nodes SET missing = ?1 WHERE id = ?2",
+            params![missing, node_id],
+        )?;
+        Ok(())
+    }
+
+    /// Record the file of a wishlist node from freshly hashed `metadata`:
+    /// its name, header, and checksums. Returns false, changing nothing, if
+    /// the node isn't missing its file.
+    pub fn record_found(&self, metadata: &RomMetadata) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE nodes SET filename = ?1, source_file_header = ?2, sha1 = ?3, crc32 = ?4,
+                 rom_type = ?5, content_size = ?6, missing = 0
+             WHERE sha256 = ?7 AND missing = 1",
+            params![
+                metadata.filename.as_deref(),
+                &metadata.source_file_header,
+                metadata.sha1.map(hex::encode),
+                metadata.crc32,
+                metadata.rom_type.as_str(),
+                metadata.content_size.map(|s| s as i64),
+                format_hash(&metadata.sha256)
+            ],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Store the SHA-256 (in hex) of the diff file at `diff_path`
+    pub fn set_diff_checksum(&self, diff_path: &str, sha256: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE edges SET diff_sha256 = ?1 WHERE diff_path = ?2",
+            params![sha256, diff_path],
+        )?;
+        Ok(())
+    }
+
+    /// Store the size of the ROM data the diff at `diff_path` builds
+    pub fn set_diff_output_size(&self, diff_path: &str, size: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE edges SET output_size = ?1 WHERE diff_path = ?2",
+            params![size, diff_path],
+        )?;
+        Ok(())
+    }
+
+    /// Remember that the node with this hash was seen in a file named
+    /// `filename`. Returns false if the name was already known, or there's
+    /// no such node.
+    pub fn record_filename_alias(&self, sha256: &[u8; 32], filename: &str) -> Result<bool> {
+        let added = self.conn.execute(
+            "INSERT OR IGNORE INTO filename_aliases (node_id, filename)
+             SELECT id, ?2 FROM nodes WHERE sha256 = ?1",
+            params![format_hash(sha256), filename],
+        )?;
+        Ok(added > 0)
+    }
+
+    /// Every file name a node has been seen under, oldest first
+    pub fn filename_aliases(&self, node_id: i64) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT filename FROM filename_aliases WHERE node_id = ?1
+             ORDER BY first_seen, rowid",
+        )?;
+        let names = stmt.query_map(params![node_id], |row| row.get(0))?;
+        Ok(names.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+/// The first word of `sql`, lowercased, after any whitespace and comments
+fn leading_keyword(sql: &str) -> String {
+    let mut rest = sql;
+    loop {
+        rest = rest.trim_start();
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.split_once('\n').map_or("", |(_, after)| after);
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, after)| after);
+        } else {
+            break;
+        }
+    }
+    rest.chars()
+        .take_while(char::is_ascii_alphabetic)
+        .collect::<String>()
+        .to_ascii_lowercase()
 }
 
 #[cfg(test)]
@@ -290,6 +1451,7 @@ mod tests {
     fn setup_test_db() -> Connection {
         let mut conn = Connection::open_in_memory().unwrap();
         run_migrations(&mut conn).unwrap();
+        crate::db::collation::register(&conn).unwrap();
         conn
     }
 
@@ -308,6 +1470,9 @@ mod tests {
         RomMetadata {
             rom_type: RomType::Nes,
             sha256,
+            sha1: None,
+            crc32: None,
+            content_size: None,
             filename: Some(filename.to_string()),
             nes_header: Some(NesHeader {
                 prg_rom_size: 32 * 1024,
@@ -421,8 +1586,19 @@ mod tests {
             .insert_node(&meta_b, &make_node_metadata("ROM B"))
             .unwrap();
 
-        let edge_id = repo.insert_edge(id_a, id_b, "a_to_b.bsdiff", 1234).unwrap();
+        let edge_id = repo
+            .insert_edge(id_a, id_b, "a_to_b.bsdiff", 1234, Some("retranslation"))
+            .unwrap();
         assert!(edge_id > 0);
+        repo.insert_edge(id_b, id_a, "b_to_a.bsdiff", 1234, None)
+            .unwrap();
+        let notes: Vec<Option<String>> = repo
+            .load_all_edges()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.note)
+            .collect();
+        assert_eq!(notes, [Some("retranslation".to_string()), None]);
     }
 
     #[test]
@@ -440,10 +1616,11 @@ mod tests {
             .insert_node(&meta_b, &make_node_metadata("ROM B"))
             .unwrap();
 
-        repo.insert_edge(id_a, id_b, "a_to_b.bsdiff", 1234).unwrap();
+        repo.insert_edge(id_a, id_b, "a_to_b.bsdiff", 1234, None)
+            .unwrap();
 
         // Second insert should fail
-        let result = repo.insert_edge(id_a, id_b, "a_to_b_v2.bsdiff", 5678);
+        let result = repo.insert_edge(id_a, id_b, "a_to_b_v2.bsdiff", 5678, None);
         assert!(result.is_err());
         match result.unwrap_err() {
             DromosError::DiffAlreadyExists(_, _) => {}
@@ -485,8 +1662,10 @@ mod tests {
             .insert_node(&meta_b, &make_node_metadata("ROM B"))
             .unwrap();
 
-        repo.insert_edge(id_a, id_b, "a_to_b.bsdiff", 1000).unwrap();
-        repo.insert_edge(id_b, id_a, "b_to_a.bsdiff", 2000).unwrap();
+        repo.insert_edge(id_a, id_b, "a_to_b.bsdiff", 1000, None)
+            .unwrap();
+        repo.insert_edge(id_b, id_a, "b_to_a.bsdiff", 2000, None)
+            .unwrap();
 
         let edges = repo.load_all_edges().unwrap();
         assert_eq!(edges.len(), 2);
@@ -513,9 +1692,12 @@ mod tests {
             .insert_node(&meta_c, &make_node_metadata("ROM C"))
             .unwrap();
 
-        repo.insert_edge(id_a, id_b, "a_to_b.bsdiff", 1000).unwrap();
-        repo.insert_edge(id_b, id_a, "b_to_a.bsdiff", 1000).unwrap();
-        repo.insert_edge(id_b, id_c, "b_to_c.bsdiff", 1000).unwrap();
+        repo.insert_edge(id_a, id_b, "a_to_b.bsdiff", 1000, None)
+            .unwrap();
+        repo.insert_edge(id_b, id_a, "b_to_a.bsdiff", 1000, None)
+            .unwrap();
+        repo.insert_edge(id_b, id_c, "b_to_c.bsdiff", 1000, None)
+            .unwrap();
 
         // Delete node B
         repo.delete_node(id_b).unwrap();
@@ -532,6 +1714,91 @@ mod tests {
         assert!(repo.get_node_by_id(id_c).unwrap().is_some());
     }
 
+    #[test]
+    fn test_attachments() {
+        let conn = setup_test_db();
+        let repo = Repository::new(&conn);
+        let (meta_a, meta_b) = (make_metadata(0xAA, "a.nes"), make_metadata(0xBB, "b.nes"));
+        let id_a = repo
+            .insert_node(&meta_a, &make_node_metadata("ROM A"))
+            .unwrap();
+        let id_b = repo
+            .insert_node(&meta_b, &make_node_metadata("ROM B"))
+            .unwrap();
+
+        repo.insert_attachment(id_a, None, "readme.txt", &[1; 32], 10)
+            .unwrap();
+        repo.insert_attachment(id_b, Some(id_a), "notes.txt", &[2; 32], 20)
+            .unwrap();
+        // Attaching under an existing name replaces the file
+        repo.insert_attachment(id_a, None, "readme.txt", &[3; 32], 30)
+            .unwrap();
+
+        let attached = repo.get_attachments_for_node(id_a).unwrap();
+        assert_eq!(attached.len(), 2);
+        assert_eq!(attached[0].name, "readme.txt");
+        assert_eq!(attached[0].sha256, [3; 32]);
+        assert_eq!(attached[0].linked_node, None);
+        // Links are stored lower ID first, whichever end they were attached from
+        assert_eq!(attached[1].node, meta_a.sha256);
+        assert_eq!(attached[1].linked_node, Some(meta_b.sha256));
+        assert_eq!(repo.get_attachments_for_node(id_b).unwrap().len(), 1);
+
+        assert!(!repo.attachment_contents_in_use(&[1; 32]).unwrap());
+        assert!(repo.attachment_contents_in_use(&[2; 32]).unwrap());
+        repo.delete_attachment(attached[0].id).unwrap();
+        assert_eq!(repo.load_all_attachments().unwrap().len(), 1);
+
+        // Deleting either end of a link deletes its attachments
+        repo.delete_node(id_b).unwrap();
+        assert!(repo.load_all_attachments().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_collections() {
+        let conn = setup_test_db();
+        let repo = Repository::new(&conn);
+        let id_a = repo
+            .insert_node(&make_metadata(0xAA, "a.nes"), &make_node_metadata("ROM A"))
+            .unwrap();
+        let id_b = repo
+            .insert_node(&make_metadata(0xBB, "b.nes"), &make_node_metadata("ROM B"))
+            .unwrap();
+
+        let faves = repo.insert_collection("Faves").unwrap();
+        let queue = repo.insert_collection("queue").unwrap();
+        assert!(repo.insert_collection("FAVES").is_err());
+        assert_eq!(repo.collection_id("faves").unwrap(), Some(faves));
+        assert_eq!(repo.collection_id("missing").unwrap(), None);
+
+        assert!(repo.add_to_collection(faves, id_a).unwrap());
+        assert!(!repo.add_to_collection(faves, id_a).unwrap());
+        assert!(repo.add_to_collection(faves, id_b).unwrap());
+        assert!(repo.add_to_collection(queue, id_a).unwrap());
+        assert_eq!(
+            repo.collections_for_node(id_a).unwrap(),
+            vec!["Faves", "queue"]
+        );
+
+        let collections = repo.load_collections().unwrap();
+        let counts: Vec<_> = collections
+            .iter()
+            .map(|c| (c.name.as_str(), c.nodes))
+            .collect();
+        assert_eq!(counts, vec![("Faves", 2), ("queue", 1)]);
+
+        let filter = NodeFilter::parse("collection:FAVES").unwrap();
+        assert_eq!(repo.search_nodes(&filter).unwrap().len(), 2);
+
+        assert!(repo.remove_from_collection(faves, id_b).unwrap());
+        assert!(!repo.remove_from_collection(faves, id_b).unwrap());
+        // Deleting a node takes it out of its collections
+        repo.delete_node(id_a).unwrap();
+        assert!(repo.search_nodes(&filter).unwrap().is_empty());
+        repo.delete_collection(queue).unwrap();
+        assert_eq!(repo.load_collections().unwrap().len(), 1);
+    }
+
     #[test]
     fn test_get_edges_for_node() {
         let conn = setup_test_db();
@@ -551,10 +1818,14 @@ mod tests {
             .insert_node(&meta_c, &make_node_metadata("ROM C"))
             .unwrap();
 
-        repo.insert_edge(id_a, id_b, "a_to_b.bsdiff", 1000).unwrap();
-        repo.insert_edge(id_b, id_a, "b_to_a.bsdiff", 1000).unwrap();
-        repo.insert_edge(id_b, id_c, "b_to_c.bsdiff", 1000).unwrap();
-        repo.insert_edge(id_c, id_b, "c_to_b.bsdiff", 1000).unwrap();
+        repo.insert_edge(id_a, id_b, "a_to_b.bsdiff", 1000, None)
+            .unwrap();
+        repo.insert_edge(id_b, id_a, "b_to_a.bsdiff", 1000, None)
+            .unwrap();
+        repo.insert_edge(id_b, id_c, "b_to_c.bsdiff", 1000, None)
+            .unwrap();
+        repo.insert_edge(id_c, id_b, "c_to_b.bsdiff", 1000, None)
+            .unwrap();
 
         // Get edges for node B (should include all 4)
         let edges_b = repo.get_edges_for_node(id_b).unwrap();
@@ -578,6 +1849,10 @@ mod tests {
             release_date: Some("2024-01-15".to_string()),
             tags: vec!["action".to_string(), "platformer".to_string()],
             description: Some("A test ROM description".to_string()),
+            rating: Some(4),
+            play_status: PlayStatus::Playing,
+            regions: vec![Region::Japan, Region::Other("Mars".to_string())],
+            languages: vec![Language::Japanese, Language::English],
         };
         repo.insert_node(&metadata, &node_meta).unwrap();
 
@@ -592,6 +1867,13 @@ mod tests {
         assert_eq!(node.release_date, Some("2024-01-15".to_string()));
         assert_eq!(node.tags, vec!["action", "platformer"]);
         assert_eq!(node.description, Some("A test ROM description".to_string()));
+        assert_eq!(node.rating, Some(4));
+        assert_eq!(node.play_status, PlayStatus::Playing);
+        assert_eq!(
+            node.regions,
+            vec![Region::Japan, Region::Other("Mars".to_string())]
+        );
+        assert_eq!(node.languages, vec![Language::Japanese, Language::English]);
     }
 
     #[test]
@@ -614,6 +1896,10 @@ mod tests {
         assert!(node.release_date.is_none());
         assert!(node.tags.is_empty());
         assert!(node.description.is_none());
+        assert!(node.rating.is_none());
+        assert_eq!(node.play_status, PlayStatus::Unplayed);
+        assert!(node.regions.is_empty());
+        assert!(node.languages.is_empty());
     }
 
     #[test]
@@ -633,6 +1919,10 @@ mod tests {
             release_date: Some("2024-06-01".to_string()),
             tags: vec!["rpg".to_string()],
             description: Some("Updated description".to_string()),
+            rating: Some(2),
+            play_status: PlayStatus::Abandoned,
+            regions: vec![Region::Europe],
+            languages: Vec::new(),
         };
         repo.update_node_metadata(id, &updated_meta).unwrap();
 
@@ -641,6 +1931,73 @@ mod tests {
         assert_eq!(node.source_url, Some("https://new-url.com".to_string()));
         assert_eq!(node.version, Some("2.0".to_string()));
         assert_eq!(node.tags, vec!["rpg"]);
+        assert_eq!(node.rating, Some(2));
+        assert_eq!(node.play_status, PlayStatus::Abandoned);
+    }
+
+    #[test]
+    fn test_normalize_metadata() {
+        let mut metadata = NodeMetadata {
+            title: "  Zelda ".to_string(),
+            source_url: Some("https://example.com/zelda".to_string()),
+            version: Some(" v1.1 ".to_string()),
+            release_date: Some("1986-02-21".to_string()),
+            tags: vec!["rpg".into(), " rpg".into(), "".into(), "hack+fix".into()],
+            description: Some("   ".to_string()),
+            ..Default::default()
+        };
+        metadata.normalize().unwrap();
+        assert_eq!(metadata.title, "Zelda");
+        assert_eq!(metadata.version.as_deref(), Some("1.1"));
+        assert_eq!(metadata.tags, vec!["rpg", "hack+fix"]);
+        assert_eq!(metadata.description, None);
+        // Versions that aren't numbers keep their v
+        metadata.version = Some("vs".to_string());
+        metadata.normalize().unwrap();
+        assert_eq!(metadata.version.as_deref(), Some("vs"));
+
+        let invalid = |edit: fn(&mut NodeMetadata)| {
+            let mut bad = metadata.clone();
+            edit(&mut bad);
+            bad.normalize().unwrap_err()
+        };
+        assert!(invalid(|m| m.source_url = Some("example.com".into())).contains("URL"));
+        assert!(invalid(|m| m.source_url = Some("https:///rom".into())).contains("URL"));
+        assert!(invalid(|m| m.release_date = Some("1986-13-01".into())).contains("date"));
+        assert!(invalid(|m| m.release_date = Some("1969-12-31".into())).contains("range"));
+        assert!(invalid(|m| m.tags = vec!["two words".into()]).contains("two words"));
+        assert!(invalid(|m| m.tags = vec!["a,b".into()]).contains(','));
+        assert!(invalid(|m| m.rating = Some(9)).contains("rating"));
+        assert!(invalid(|m| m.regions = vec![Region::from("a,b".to_string())]).contains("commas"));
+        // Any date from 1970 on, whatever the clock says
+        let mut future = metadata.clone();
+        future.release_date = Some("2999-01-01".to_string());
+        future.normalize().unwrap();
+
+        // Edits check only what they change
+        let stored = NodeMetadata {
+            title: "Zelda".to_string(),
+            source_url: Some("not a url".to_string()),
+            release_date: Some("1986-2-21".to_string()),
+            tags: vec!["fan translation".into()],
+            ..Default::default()
+        };
+        let mut edited = stored.clone();
+        edited.rating = Some(4);
+        edited.tags.push("rpg".into());
+        edited.normalize_edit(&stored).unwrap();
+        assert_eq!(edited.tags, vec!["fan translation", "rpg"]);
+        assert_eq!(edited.release_date.as_deref(), Some("1986-2-21"));
+        edited.tags.push("two words".into());
+        assert!(
+            edited
+                .normalize_edit(&stored)
+                .unwrap_err()
+                .contains("two words")
+        );
+        let mut blank = stored.clone();
+        blank.title = " ".to_string();
+        assert!(blank.normalize_edit(&stored).unwrap_err().contains("title"));
     }
 
     #[test]
@@ -706,6 +2063,204 @@ mod tests {
         assert_eq!(&header[0..4], b"NES\x1a");
     }
 
+    #[test]
+    fn test_content_size() {
+        let conn = setup_test_db();
+        let repo = Repository::new(&conn);
+
+        // Without a recorded size, the header's PRG + CHR stands in
+        let mut metadata = make_metadata(0xAA, "test.nes");
+        repo.insert_node(&metadata, &make_node_metadata("Test ROM"))
+            .unwrap();
+        let node = repo.get_node_by_hash(&metadata.sha256).unwrap().unwrap();
+        assert_eq!(node.content_size, None);
+        assert_eq!(node.data_size(), Some(40 * 1024));
+
+        // Hashing the file again records it, once
+        metadata.content_size = Some(40 * 1024 + 7);
+        repo.record_checksums(&metadata).unwrap();
+        metadata.content_size = Some(1);
+        repo.record_checksums(&metadata).unwrap();
+        let node = repo.get_node_by_hash(&metadata.sha256).unwrap().unwrap();
+        assert_eq!(node.content_size, Some(40 * 1024 + 7));
+        assert_eq!(node.data_size(), Some(40 * 1024 + 7));
+
+        let mut metadata = make_metadata(0xBB, "other.nes");
+        metadata.content_size = Some(123);
+        repo.insert_node(&metadata, &make_node_metadata("Other"))
+            .unwrap();
+        let node = repo.get_node_by_hash(&metadata.sha256).unwrap().unwrap();
+        assert_eq!(node.content_size, Some(123));
+    }
+
+    #[test]
+    fn test_load_recent_nodes() {
+        let conn = setup_test_db();
+        let repo = Repository::new(&conn);
+
+        for i in 1..=3u8 {
+            repo.insert_node(
+                &make_metadata(i, &format!("rom{}.nes", i)),
+                &make_node_metadata(&format!("ROM {}", i)),
+            )
+            .unwrap();
+        }
+
+        // Same created_at second for all rows, so newest id comes first
+        let recent = repo.load_recent_nodes(2).unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].title, "ROM 3");
+        assert_eq!(recent[1].title, "ROM 2");
+        assert!(!recent[0].created_at.is_empty());
+    }
+
+    #[test]
+    fn test_load_recently_changed_nodes() {
+        let conn = setup_test_db();
+        let repo = Repository::new(&conn);
+
+        let mut ids = Vec::new();
+        for i in 1..=3u8 {
+            ids.push(
+                repo.insert_node(
+                    &make_metadata(i, &format!("rom{}.nes", i)),
+                    &make_node_metadata(&format!("ROM {}", i)),
+                )
+                .unwrap(),
+            );
+        }
+        let recent = repo.load_recently_changed_nodes(3).unwrap();
+        assert_eq!(recent[0].title, "ROM 3");
+        assert!(recent.iter().all(|n| n.updated_at.is_none()));
+
+        // Editing sets updated_at; backdate creation so the edit is newer
+        conn.execute("UPDATE nodes SET created_at = '2000-01-01 00:00:00'", [])
+            .unwrap();
+        repo.update_node_metadata(ids[0], &make_node_metadata("ROM 1 (edited)"))
+            .unwrap();
+
+        let recent = repo.load_recently_changed_nodes(2).unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].title, "ROM 1 (edited)");
+        assert!(recent[0].updated_at.is_some());
+        assert_eq!(recent[1].title, "ROM 3");
+    }
+
+    #[test]
+    fn test_query_readonly() {
+        let conn = setup_test_db();
+        let repo = Repository::new(&conn);
+        repo.insert_node(&make_metadata(1, "rom1.nes"), &make_node_metadata("ROM 1"))
+            .unwrap();
+
+        let result = repo
+            .query_readonly("SELECT title, version FROM nodes")
+            .unwrap();
+        assert_eq!(result.columns, vec!["title", "version"]);
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Text("ROM 1".to_string()), Value::Null]]
+        );
+
+        assert!(matches!(
+            repo.query_readonly("DELETE FROM nodes"),
+            Err(DromosError::QueryNotReadOnly)
+        ));
+        assert!(repo.query_readonly("SELECT 1; DELETE FROM nodes").is_err());
+        let attached = tempfile::tempdir().unwrap().path().join("other.db");
+        for sql in [
+            "BEGIN".to_string(),
+            "SAVEPOINT s".to_string(),
+            format!("ATTACH '{}' AS other", attached.display()),
+            "PRAGMA foreign_keys = OFF".to_string(),
+            "pragma journal_mode".to_string(),
+        ] {
+            assert!(
+                matches!(
+                    repo.query_readonly(&sql),
+                    Err(DromosError::QueryNotReadOnly)
+                ),
+                "{}",
+                sql
+            );
+        }
+        assert!(conn.is_autocommit());
+        assert!(!attached.exists());
+        assert!(
+            repo.query_readonly("-- count\n/* all */ select count(*) FROM nodes")
+                .is_ok()
+        );
+        let count = repo.query_readonly("SELECT COUNT(*) FROM nodes").unwrap();
+        assert_eq!(count.rows[0][0], Value::Integer(1));
+    }
+
+    #[test]
+    fn test_search_nodes() {
+        let conn = setup_test_db();
+        let repo = Repository::new(&conn);
+        let tagged = NodeMetadata {
+            title: "Super Game".to_string(),
+            version: Some("1.1".to_string()),
+            tags: vec!["Translation".to_string()],
+            regions: vec![Region::Japan, Region::Usa],
+            languages: vec![Language::English],
+            ..Default::default()
+        };
+        repo.insert_node(&make_metadata(1, "rom1.nes"), &tagged)
+            .unwrap();
+        let other = repo
+            .insert_node(
+                &make_metadata(2, "rom2.nes"),
+                &make_node_metadata("Other Game"),
+            )
+            .unwrap();
+
+        let titles = |query: &str| -> Vec<String> {
+            repo.search_nodes(&NodeFilter::parse(query).unwrap())
+                .unwrap()
+                .into_iter()
+                .map(|n| n.title)
+                .collect()
+        };
+        assert_eq!(titles("game"), vec!["Other Game", "Super Game"]);
+        assert_eq!(titles("tag:translation"), vec!["Super Game"]);
+        assert_eq!(titles("version:1.* game"), vec!["Super Game"]);
+        assert_eq!(titles("version:2.*"), Vec::<String>::new());
+        assert_eq!(titles("mapper:4 type:nes").len(), 2);
+        assert!(titles("mapper:1").is_empty());
+        assert_eq!(titles("region:J lang:english"), vec!["Super Game"]);
+        assert_eq!(titles("region:usa"), vec!["Super Game"]);
+        assert!(titles("region:Europe").is_empty());
+        assert!(titles("lang:ja").is_empty());
+
+        repo.set_archived(other, true).unwrap();
+        assert_eq!(titles("is:archived"), vec!["Other Game"]);
+        let unarchived = NodeFilter {
+            archived: Some(false),
+            ..Default::default()
+        };
+        let rows = repo.search_nodes(&unarchived).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].title, "Super Game");
+        assert_eq!(titles("game").len(), 2);
+    }
+
+    #[test]
+    fn test_meta_roundtrip() {
+        let conn = setup_test_db();
+        let repo = Repository::new(&conn);
+
+        assert_eq!(repo.get_meta("last_export_at").unwrap(), None);
+        repo.set_meta("last_export_at", "2024-01-01T00:00:00Z")
+            .unwrap();
+        repo.set_meta("last_export_at", "2024-02-01T00:00:00Z")
+            .unwrap();
+        assert_eq!(
+            repo.get_meta("last_export_at").unwrap().as_deref(),
+            Some("2024-02-01T00:00:00Z")
+        );
+    }
+
     #[test]
     fn test_source_file_header_none() {
         let conn = setup_test_db();
@@ -717,6 +2272,9 @@ mod tests {
         let metadata = RomMetadata {
             rom_type: RomType::Nes,
             sha256,
+            sha1: None,
+            crc32: None,
+            content_size: None,
             filename: Some("test.nes".to_string()),
             nes_header: None,
             source_file_header: None,
@@ -731,4 +2289,49 @@ mod tests {
 
         assert!(node.source_file_header.is_none());
     }
+
+    #[test]
+    fn test_iter_nodes_and_edges_across_pages() {
+        let conn = setup_test_db();
+        let repo = Repository::new(&conn);
+        let mut ids = Vec::new();
+        for i in 1..=5 {
+            let title = if i % 2 == 0 { "Even" } else { "Odd" };
+            ids.push(
+                repo.insert_node(
+                    &make_metadata(i, &format!("rom{}.nes", i)),
+                    &make_node_metadata(title),
+                )
+                .unwrap(),
+            );
+        }
+        for &target in &ids[1..] {
+            repo.insert_edge(ids[0], target, &format!("{}.bsdiff", target), 10, None)
+                .unwrap();
+        }
+
+        let filter = NodeFilter::parse("odd").unwrap();
+        let odd: Vec<i64> = repo
+            .iter_nodes_filtered(&filter)
+            .page_size(2)
+            .map(|n| n.unwrap().id)
+            .collect();
+        assert_eq!(odd, vec![ids[0], ids[2], ids[4]]);
+
+        // Mapper conditions are checked as each page is read
+        let count = |query: &str| {
+            let filter = NodeFilter::parse(query).unwrap();
+            repo.iter_nodes_filtered(&filter).page_size(1).count()
+        };
+        assert_eq!(count("mapper:4"), 5);
+        assert_eq!(count("mapper:1"), 0);
+
+        let edges: Vec<i64> = repo
+            .iter_edges_for(ids[0])
+            .page_size(3)
+            .map(|e| e.unwrap().target_id)
+            .collect();
+        assert_eq!(edges, ids[1..].to_vec());
+        assert_eq!(repo.iter_edges_for(ids[3]).count(), 1);
+    }
 }
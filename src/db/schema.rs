@@ -7,15 +7,38 @@ use crate::error::Result;
 /// When incrementing, also collapse all migrations into 001_initial.sql.
 pub const DATA_REVISION: u32 = 2;
 
-pub fn run_migrations(conn: &mut Connection) -> Result<()> {
-    let migrations = Migrations::new(vec![M::up(include_str!(
-        "../../migrations/001_initial.sql"
-    ))]);
+fn migrations() -> Migrations<'static> {
+    Migrations::new(vec![
+        M::up(include_str!("../../migrations/001_initial.sql")),
+        M::up(include_str!("../../migrations/002_node_updated_at.sql")),
+        M::up(include_str!("../../migrations/003_dat_entries.sql")),
+        M::up(include_str!("../../migrations/004_attachments.sql")),
+        M::up(include_str!("../../migrations/005_ratings.sql")),
+        M::up(include_str!("../../migrations/006_collections.sql")),
+        M::up(include_str!("../../migrations/007_edge_notes.sql")),
+        M::up(include_str!("../../migrations/008_regions.sql")),
+        M::up(include_str!("../../migrations/009_favorites.sql")),
+        M::up(include_str!("../../migrations/010_archived.sql")),
+        M::up(include_str!("../../migrations/011_wishlist.sql")),
+        M::up(include_str!("../../migrations/012_filename_aliases.sql")),
+        M::up(include_str!("../../migrations/013_diff_checksums.sql")),
+        M::up(include_str!("../../migrations/014_edge_output_size.sql")),
+        M::up(include_str!("../../migrations/015_locations.sql")),
+        M::up(include_str!("../../migrations/016_created_at_index.sql")),
+        M::up(include_str!("../../migrations/017_node_content_size.sql")),
+    ])
+}
 
-    migrations.to_latest(conn)?;
+pub fn run_migrations(conn: &mut Connection) -> Result<()> {
+    migrations().to_latest(conn)?;
     Ok(())
 }
 
+/// Check whether the schema is behind this build, without changing it.
+pub fn migrations_pending(conn: &Connection) -> Result<bool> {
+    Ok(migrations().pending_migrations(conn)? > 0)
+}
+
 /// Get the stored data revision from dromos_meta table.
 /// Returns None if table doesn't exist or key not found.
 pub fn get_stored_data_revision(conn: &Connection) -> Option<u32> {
@@ -0,0 +1,295 @@
+//! Finding ROMs that are probably the same game entered twice, as bulk
+//! ingests tend to leave behind, and combining their metadata for `merge`.
+//!
+//! Titles are compared after dropping case, punctuation, bracketed tags such
+//! as `(USA)` or `[!]`, and a leading or trailing "The". Titles that still
+//! differ count as near-identical when they're at least 90% the same by edit
+//! distance and have the same numbers in them, so sequels such as
+//! "Mega Man 2" and "Mega Man 3" are never flagged.
+
+use std::collections::HashMap;
+
+use crate::db::{NodeMetadata, NodeRow, PlayStatus};
+
+/// ROMs that look like duplicates of each other.
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    /// Sorted by title, then hash
+    pub nodes: Vec<NodeRow>,
+    /// Every title and version is exactly the same
+    pub exact: bool,
+}
+
+/// Groups of two or more ROMs with identical or near-identical titles and
+/// the same version, ordered by title.
+pub fn find_duplicates(rows: &[NodeRow]) -> Vec<DuplicateGroup> {
+    let keys: Vec<(String, String)> = rows
+        .iter()
+        .map(|row| {
+            (
+                normalize_title(&row.title),
+                normalize_version(row.version.as_deref()),
+            )
+        })
+        .collect();
+
+    // Union-find over every similar pair
+    let mut parent: Vec<usize> = (0..rows.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..rows.len() {
+        for j in i + 1..rows.len() {
+            if keys[i].1 == keys[j].1 && similar_titles(&keys[i].0, &keys[j].0) {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..rows.len() {
+        let r = root(&mut parent, i);
+        groups.entry(r).or_default().push(i);
+    }
+    let mut groups: Vec<DuplicateGroup> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let mut nodes: Vec<NodeRow> = members.iter().map(|&i| rows[i].clone()).collect();
+            nodes.sort_by(|a, b| {
+                a.title
+                    .to_lowercase()
+                    .cmp(&b.title.to_lowercase())
+                    .then(a.sha256.cmp(&b.sha256))
+            });
+            let exact = nodes
+                .iter()
+                .all(|n| n.title == nodes[0].title && n.version == nodes[0].version);
+            DuplicateGroup { nodes, exact }
+        })
+        .collect();
+    groups.sort_by_key(|g| normalize_title(&g.nodes[0].title));
+    groups
+}
+
+/// Metadata for `keep` after merging `dup` into it: `keep`'s fields win,
+/// `dup` fills the ones it's missing, and tags, regions, and languages are
+/// combined.
+pub fn merge_metadata(keep: &NodeRow, dup: &NodeRow) -> NodeMetadata {
+    let mut tags = keep.tags.clone();
+    for tag in &dup.tags {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.clone());
+        }
+    }
+    let mut regions = keep.regions.clone();
+    regions.extend(
+        dup.regions
+            .iter()
+            .filter(|r| !keep.regions.contains(r))
+            .cloned(),
+    );
+    let mut languages = keep.languages.clone();
+    languages.extend(
+        dup.languages
+            .iter()
+            .filter(|l| !keep.languages.contains(l))
+            .cloned(),
+    );
+    NodeMetadata {
+        title: keep.title.clone(),
+        source_url: keep.source_url.clone().or_else(|| dup.source_url.clone()),
+        version: keep.version.clone().or_else(|| dup.version.clone()),
+        release_date: keep
+            .release_date
+            .clone()
+            .or_else(|| dup.release_date.clone()),
+        tags,
+        description: keep
+            .description
+            .clone()
+            .filter(|d| !d.is_empty())
+            .or_else(|| dup.description.clone()),
+        rating: keep.rating.or(dup.rating),
+        play_status: if keep.play_status == PlayStatus::Unplayed {
+            dup.play_status
+        } else {
+            keep.play_status
+        },
+        regions,
+        languages,
+    }
+}
+
+/// `title` reduced to lowercase words, without bracketed tags or "The".
+pub fn normalize_title(title: &str) -> String {
+    let mut plain = String::new();
+    let mut depth = 0usize;
+    for c in title.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ if depth > 0 => {}
+            c if c.is_alphanumeric() => plain.extend(c.to_lowercase()),
+            // Apostrophes join words ("Link's"); other punctuation splits them
+            '\'' | '’' => {}
+            _ => plain.push(' '),
+        }
+    }
+    let words: Vec<&str> = plain.split_whitespace().collect();
+    let words = match words.as_slice() {
+        ["the", rest @ ..] if !rest.is_empty() => rest,
+        [rest @ .., "the"] if !rest.is_empty() => rest,
+        all => all,
+    };
+    words.join(" ")
+}
+
+fn normalize_version(version: Option<&str>) -> String {
+    let version = version.unwrap_or_default().trim().to_lowercase();
+    version.strip_prefix('v').unwrap_or(&version).to_string()
+}
+
+/// Whether two normalized titles name the same game.
+fn similar_titles(a: &str, b: &str) -> bool {
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    if a == b {
+        return true;
+    }
+    if numbers(a) != numbers(b) {
+        return false;
+    }
+    let longest = a.chars().count().max(b.chars().count());
+    edit_distance(a, b) * 10 <= longest
+}
+
+/// The numbers in a normalized title, Roman numerals included.
+fn numbers(title: &str) -> Vec<&str> {
+    const ROMAN: [&str; 10] = ["i", "ii", "iii", "iv", "v", "vi", "vii", "viii", "ix", "x"];
+    title
+        .split(' ')
+        .filter(|w| w.chars().all(|c| c.is_ascii_digit()) || ROMAN.contains(w))
+        .collect()
+}
+
+/// Levenshtein distance, by character.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rom::{Region, RomType};
+
+    fn make_row(byte: u8, title: &str, version: Option<&str>) -> NodeRow {
+        NodeRow {
+            id: byte as i64,
+            sha256: [byte; 32],
+            sha1: None,
+            crc32: None,
+            filename: None,
+            title: title.to_string(),
+            rom_type: RomType::Nes,
+            source_url: None,
+            version: version.map(String::from),
+            release_date: None,
+            tags: Vec::new(),
+            description: None,
+            source_file_header: None,
+            created_at: String::new(),
+            updated_at: None,
+            rating: None,
+            play_status: PlayStatus::Unplayed,
+            regions: Vec::new(),
+            languages: Vec::new(),
+            favorite: false,
+            archived: false,
+            missing: false,
+            content_size: None,
+        }
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let rows = vec![
+            make_row(1, "Legend of Zelda, The (USA)", None),
+            make_row(2, "The Legend of Zelda", None),
+            make_row(3, "Mega Man 2", None),
+            make_row(4, "Mega Man 3", None),
+            make_row(5, "Castlevania", Some("1.0")),
+            make_row(6, "Castlevania", Some("v1.0")),
+            make_row(7, "Castlevania", Some("1.1")),
+            make_row(8, "Final Fantasy II", None),
+            make_row(9, "Final Fantasy III", None),
+            make_row(10, "Teenage Mutant Ninja Turtles", None),
+            make_row(11, "Teenage Mutant Ninja Turtle", None),
+            make_row(12, "Tetris", None),
+            make_row(13, "Tetris", None),
+        ];
+        let groups = find_duplicates(&rows);
+        let ids: Vec<Vec<i64>> = groups
+            .iter()
+            .map(|g| g.nodes.iter().map(|n| n.id).collect())
+            .collect();
+        assert_eq!(
+            ids,
+            vec![vec![5, 6], vec![1, 2], vec![11, 10], vec![12, 13]]
+        );
+        let exact: Vec<bool> = groups.iter().map(|g| g.exact).collect();
+        assert_eq!(exact, vec![false, false, false, true]);
+    }
+
+    #[test]
+    fn test_merge_metadata() {
+        let mut keep = make_row(1, "Zelda", None);
+        keep.tags = vec!["Hack".to_string()];
+        keep.regions = vec![Region::Japan];
+        let mut dup = make_row(2, "Zelda (J)", Some("1.1"));
+        dup.tags = vec!["hack".to_string(), "translation".to_string()];
+        dup.regions = vec![Region::Japan, Region::Usa];
+        dup.rating = Some(4);
+        dup.play_status = PlayStatus::Finished;
+        dup.description = Some("English".to_string());
+
+        let merged = merge_metadata(&keep, &dup);
+        assert_eq!(merged.title, "Zelda");
+        assert_eq!(merged.version.as_deref(), Some("1.1"));
+        assert_eq!(merged.tags, vec!["Hack", "translation"]);
+        assert_eq!(merged.regions, vec![Region::Japan, Region::Usa]);
+        assert_eq!(merged.rating, Some(4));
+        assert_eq!(merged.play_status, PlayStatus::Finished);
+        assert_eq!(merged.description.as_deref(), Some("English"));
+    }
+
+    #[test]
+    fn test_normalize_title() {
+        assert_eq!(
+            normalize_title("Legend of Zelda, The (USA) [!]"),
+            "legend of zelda"
+        );
+        assert_eq!(
+            normalize_title("Zelda II - Link's Adventure"),
+            "zelda ii links adventure"
+        );
+        assert_eq!(normalize_title("The"), "the");
+    }
+}
@@ -1,5 +1,5 @@
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Write};
+use std::io::{BufWriter, Read, Write};
 use std::path::Path;
 
 use bzip2::Compression;
@@ -7,22 +7,64 @@ use bzip2::read::BzDecoder;
 use bzip2::write::BzEncoder;
 
 use crate::error::{DromosError, Result};
+use crate::timings::{self, Phase};
 
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    level = "debug",
+    skip_all,
+    fields(path = %diff_path.display(), old_len = old.len(), new_len = new.len()),
+))]
 pub fn create_diff(old: &[u8], new: &[u8], diff_path: &Path) -> Result<u64> {
+    let file = File::create(diff_path).map_err(DromosError::file(diff_path))?;
+    write_diff(old, new, BufWriter::new(file))?;
+
+    let size = std::fs::metadata(diff_path)?.len();
+    #[cfg(feature = "tracing")]
+    tracing::debug!(size, "diff written");
+    Ok(size)
+}
+
+/// The size of the diff [`create_diff`] would write, computed in memory.
+pub fn diff_size(old: &[u8], new: &[u8]) -> Result<u64> {
+    Ok(write_diff(old, new, Vec::new())?.len() as u64)
+}
+
+/// Write the bzip2-compressed bsdiff patch from `old` to `new` to `out`.
+fn write_diff<W: Write>(old: &[u8], new: &[u8], out: W) -> Result<W> {
+    let _span = timings::span(Phase::DiffCreation);
     let mut patch = Vec::new();
     bsdiff::diff(old, new, &mut patch).map_err(|e| DromosError::DiffCreation(e.to_string()))?;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(patch_len = patch.len(), "patch created");
 
-    let file = File::create(diff_path)?;
-    let mut encoder = BzEncoder::new(BufWriter::new(file), Compression::best());
+    let mut encoder = BzEncoder::new(out, Compression::best());
     encoder.write_all(&patch)?;
-    encoder.finish()?;
+    Ok(encoder.finish()?)
+}
 
-    Ok(std::fs::metadata(diff_path)?.len())
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    level = "debug",
+    skip_all,
+    fields(path = %diff_path.display()),
+))]
+/// Apply a diff file to `old`. If the link records the size of the ROM it
+/// builds, a result of any other size is an error, as from a damaged diff.
+pub fn apply_diff(old: &[u8], diff_path: &Path, expected_len: Option<u64>) -> Result<Vec<u8>> {
+    let diff = std::fs::read(diff_path).map_err(DromosError::file(diff_path))?;
+    let new = apply_diff_bytes(old, &diff)?;
+    match expected_len {
+        Some(expected) if new.len() as u64 != expected => Err(DromosError::DiffOutputSize {
+            expected,
+            actual: new.len() as u64,
+        }),
+        _ => Ok(new),
+    }
 }
 
-pub fn apply_diff(old: &[u8], diff_path: &Path) -> Result<Vec<u8>> {
-    let file = File::open(diff_path)?;
-    let mut decoder = BzDecoder::new(BufReader::new(file));
+/// Apply a diff file's contents (bzip2-compressed bsdiff) already in memory.
+pub fn apply_diff_bytes(old: &[u8], diff: &[u8]) -> Result<Vec<u8>> {
+    let _span = timings::span(Phase::DiffApplication);
+    let mut decoder = BzDecoder::new(diff);
     let mut patch = Vec::new();
     decoder.read_to_end(&mut patch)?;
 
@@ -30,9 +72,67 @@ pub fn apply_diff(old: &[u8], diff_path: &Path) -> Result<Vec<u8>> {
     bsdiff::patch(old, &mut patch.as_slice(), &mut new)
         .map_err(|e| DromosError::DiffApplication(e.to_string()))?;
 
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        diff_len = diff.len(),
+        old_len = old.len(),
+        new_len = new.len(),
+        "diff applied"
+    );
     Ok(new)
 }
 
+/// Check that a diff file's contents (bzip2-compressed bsdiff) are a
+/// well-formed patch, without the ROM it applies to: that it decompresses,
+/// and that every block's lengths fit in the patch and never seek before
+/// the start of the old ROM. Returns the size of the ROM it builds.
+pub fn check_diff_bytes(diff: &[u8]) -> Result<u64> {
+    let mut decoder = BzDecoder::new(diff);
+    let mut patch = Vec::new();
+    decoder.read_to_end(&mut patch)?;
+
+    let invalid = |at: usize, what: &str| {
+        Err(DromosError::DiffApplication(format!(
+            "{} at byte {} of the patch",
+            what, at
+        )))
+    };
+    let mut pos = 0;
+    let mut old_pos: i64 = 0;
+    let mut new_len = 0;
+    while pos < patch.len() {
+        let Some(control) = patch.get(pos..pos + 24) else {
+            return invalid(pos, "truncated control block");
+        };
+        let field = |i: usize| u64::from_le_bytes(control[i..i + 8].try_into().unwrap());
+        let (mix_len, copy_len, seek) = (field(0), field(8), field(16));
+        // The seek is sign-magnitude; the lengths can't be negative
+        let seek = if seek & (1 << 63) == 0 {
+            seek as i64
+        } else {
+            -((seek & !(1 << 63)) as i64)
+        };
+        let data_len = mix_len
+            .checked_add(copy_len)
+            .and_then(|n| usize::try_from(n).ok());
+        let Some(end) = data_len.and_then(|n| (pos + 24).checked_add(n)) else {
+            return invalid(pos, "impossible block length");
+        };
+        if end > patch.len() {
+            return invalid(pos, "block runs past the end");
+        }
+        // The block fits in the patch, so its lengths fit in an i64
+        let moved = (mix_len as i64).checked_add(seek);
+        old_pos = match moved.and_then(|m| old_pos.checked_add(m)) {
+            Some(p) if p >= 0 => p,
+            _ => return invalid(pos, "seek before the start of the ROM"),
+        };
+        new_len += mix_len + copy_len;
+        pos = end;
+    }
+    Ok(new_len)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -51,9 +151,39 @@ mod tests {
         assert!(diff_size > 0);
         assert!(diff_path.exists());
 
+        assert_eq!(super::diff_size(old, new).unwrap(), diff_size);
+
         // Apply diff
-        let result = apply_diff(old, &diff_path).unwrap();
+        let result = apply_diff(old, &diff_path, None).unwrap();
         assert_eq!(result, new);
+        let expected = Some(new.len() as u64);
+        assert_eq!(apply_diff(old, &diff_path, expected).unwrap(), new);
+        let wrong = Some(new.len() as u64 + 1);
+        assert!(matches!(
+            apply_diff(old, &diff_path, wrong),
+            Err(DromosError::DiffOutputSize { .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_diff_bytes() {
+        let temp_dir = tempdir().unwrap();
+        let diff_path = temp_dir.path().join("check.bsdiff");
+        create_diff(b"Hello, World!", b"Hello, Rust World!", &diff_path).unwrap();
+        let diff = std::fs::read(&diff_path).unwrap();
+        assert_eq!(check_diff_bytes(&diff).unwrap(), 18);
+
+        assert!(check_diff_bytes(b"not a diff").is_err());
+        // A patch cut short inside a block
+        let mut patch = Vec::new();
+        BzDecoder::new(diff.as_slice())
+            .read_to_end(&mut patch)
+            .unwrap();
+        patch.truncate(patch.len() - 1);
+        let mut encoder = BzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&patch).unwrap();
+        let truncated = encoder.finish().unwrap();
+        assert!(check_diff_bytes(&truncated).is_err());
     }
 
     #[test]
@@ -67,7 +197,7 @@ mod tests {
         // Just verify the diff was created and can be applied
         assert!(diff_size > 0);
 
-        let result = apply_diff(data, &diff_path).unwrap();
+        let result = apply_diff(data, &diff_path, None).unwrap();
         assert_eq!(result, data);
     }
 
@@ -82,7 +212,7 @@ mod tests {
         let diff_size = create_diff(&old, &new, &diff_path).unwrap();
         assert!(diff_size > 0);
 
-        let result = apply_diff(&old, &diff_path).unwrap();
+        let result = apply_diff(&old, &diff_path, None).unwrap();
         assert_eq!(result, new);
     }
 
@@ -95,7 +225,7 @@ mod tests {
         let new = b"Some new content";
 
         create_diff(old, new, &diff_path).unwrap();
-        let result = apply_diff(old, &diff_path).unwrap();
+        let result = apply_diff(old, &diff_path, None).unwrap();
         assert_eq!(result, new);
     }
 
@@ -108,7 +238,7 @@ mod tests {
         let new = b"";
 
         create_diff(old, new, &diff_path).unwrap();
-        let result = apply_diff(old, &diff_path).unwrap();
+        let result = apply_diff(old, &diff_path, None).unwrap();
         assert_eq!(result, new);
     }
 
@@ -130,7 +260,7 @@ mod tests {
         assert!(diff_size > 0);
 
         // Verify the diff applies correctly - this is the important part
-        let result = apply_diff(&old, &diff_path).unwrap();
+        let result = apply_diff(&old, &diff_path, None).unwrap();
         assert_eq!(result, new);
     }
 }
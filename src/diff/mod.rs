@@ -1,3 +1,24 @@
 pub mod bsdiff;
 
-pub use bsdiff::{apply_diff, create_diff};
+use std::path::Path;
+
+use crate::error::Result;
+
+pub use bsdiff::{apply_diff, apply_diff_bytes, check_diff_bytes, create_diff, diff_size};
+
+/// How new diffs are created. Bsdiff with bzip2 compression is the only
+/// engine so far, and the format every stored and exported diff uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffEngine {
+    #[default]
+    Bsdiff,
+}
+
+impl DiffEngine {
+    /// Write a diff from `old` to `new` to `diff_path`, returning its size.
+    pub fn create_diff(self, old: &[u8], new: &[u8], diff_path: &Path) -> Result<u64> {
+        match self {
+            DiffEngine::Bsdiff => bsdiff::create_diff(old, new, diff_path),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use serde_json::{Map, Value};
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -6,21 +7,45 @@ pub enum DromosError {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// An I/O error reading or writing a particular file
+    #[error("{}: {source}", path.display())]
+    FileAccess {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[cfg(feature = "native")]
     #[error("Database error: {0}")]
     Database(#[from] rusqlite::Error),
 
+    #[cfg(feature = "native")]
     #[error("Migration error: {0}")]
     Migration(#[from] rusqlite_migration::Error),
 
     #[error("Invalid NES file: {}", path.display())]
     InvalidNesFile { path: PathBuf },
 
+    /// A ROM refused in strict mode because its header contradicts itself
+    /// or the file
+    #[error("{}: inconsistent header: {}", path.display(), problems.join("; "))]
+    InconsistentHeader {
+        path: PathBuf,
+        problems: Vec<String>,
+    },
+
     #[error("Unsupported ROM type: {extension}")]
     UnsupportedRomType { extension: String },
 
     #[error("ROM not found: {hash}")]
     RomNotFound { hash: String },
 
+    /// A hash prefix or title matched more than one ROM
+    #[error("{query} matches {}", candidates.join(", "))]
+    RomNotFoundAmbiguous {
+        query: String,
+        candidates: Vec<String>,
+    },
+
     #[error("ROM already exists: {hash}")]
     RomAlreadyExists { hash: String },
 
@@ -30,6 +55,18 @@ pub enum DromosError {
     #[error("File not found: {}", path.display())]
     FileNotFound { path: PathBuf },
 
+    #[error("Invalid glob pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("Only read-only queries are allowed")]
+    QueryNotReadOnly,
+
+    #[error("Library is open read-only")]
+    ReadOnly,
+
+    #[error("Library was written by an older version; open it writable to upgrade it")]
+    UpgradeRequired,
+
     #[error("Invalid hash format: {hash}")]
     InvalidHashFormat { hash: String },
 
@@ -39,6 +76,26 @@ pub enum DromosError {
     #[error("Diff application failed: {0}")]
     DiffApplication(String),
 
+    /// A diff failed to apply partway through a build
+    #[error("step {step} of {steps} ({diff_path}): {source}")]
+    BuildStep {
+        step: usize,
+        steps: usize,
+        diff_path: String,
+        source: Box<DromosError>,
+    },
+
+    #[error("SHA-256 mismatch for {diff_path}: expected {expected}, got {actual}")]
+    DiffChecksumMismatch {
+        diff_path: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// A diff built a ROM of a different size than its link records
+    #[error("Diff built {actual} bytes, but the link expects {expected}")]
+    DiffOutputSize { expected: u64, actual: u64 },
+
     #[error("No path from {from} to {to}")]
     NoPath { from: String, to: String },
 
@@ -50,6 +107,201 @@ pub enum DromosError {
 
     #[error("Import error: {0}")]
     Import(String),
+
+    #[error("{}: not a valid DAT file: {reason}", path.display())]
+    InvalidDat { path: PathBuf, reason: String },
+
+    #[error("{}: not a valid metadata CSV: {reason}", path.display())]
+    InvalidCsv { path: PathBuf, reason: String },
+
+    #[error("Plugin {name}: {reason}")]
+    Plugin { name: String, reason: String },
+
+    #[error("{remote}: {reason}")]
+    Sync { remote: String, reason: String },
+
+    #[error("No attachment named {name}")]
+    AttachmentNotFound { name: String },
+
+    #[error("{from} and {to} aren't linked")]
+    NotLinked { from: String, to: String },
+
+    #[error("No collection named {name}")]
+    CollectionNotFound { name: String },
+
+    #[error("A collection named {name} already exists")]
+    CollectionExists { name: String },
+
+    #[error("Can't merge {hash} into itself")]
+    MergeIntoItself { hash: String },
+
+    #[error("No source file reaches {title} ({hash}); give a ROM file linked to it")]
+    SourceNeeded { hash: String, title: String },
+
+    #[error("Invalid metadata: {0}")]
+    InvalidMetadata(String),
+
+    #[error("Cancelled")]
+    Cancelled,
 }
 
 pub type Result<T> = std::result::Result<T, DromosError>;
+
+impl DromosError {
+    /// Attach the file being read or written to an I/O error.
+    pub fn file(path: impl Into<PathBuf>) -> impl FnOnce(std::io::Error) -> DromosError {
+        let path = path.into();
+        move |source| DromosError::FileAccess { path, source }
+    }
+
+    /// A stable identifier for the kind of error, reported as `code` in
+    /// `--json` output. Codes are part of the documented CLI contract;
+    /// don't rename them.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DromosError::Io(_) | DromosError::FileAccess { .. } => "io",
+            #[cfg(feature = "native")]
+            DromosError::Database(_) => "database",
+            #[cfg(feature = "native")]
+            DromosError::Migration(_) => "migration",
+            DromosError::InvalidNesFile { .. } => "invalid_nes_file",
+            DromosError::InconsistentHeader { .. } => "inconsistent_header",
+            DromosError::UnsupportedRomType { .. } => "unsupported_rom_type",
+            DromosError::RomNotFound { .. } => "rom_not_found",
+            DromosError::RomNotFoundAmbiguous { .. } => "rom_ambiguous",
+            DromosError::RomAlreadyExists { .. } => "rom_already_exists",
+            DromosError::DiffAlreadyExists(..) => "link_already_exists",
+            DromosError::FileNotFound { .. } => "file_not_found",
+            DromosError::InvalidPattern(_) => "invalid_pattern",
+            DromosError::QueryNotReadOnly => "query_not_read_only",
+            DromosError::ReadOnly => "read_only",
+            DromosError::UpgradeRequired => "upgrade_required",
+            DromosError::InvalidHashFormat { .. } => "invalid_hash",
+            DromosError::DiffCreation(_) => "diff_creation_failed",
+            DromosError::DiffApplication(_) => "diff_application_failed",
+            DromosError::BuildStep { .. } => "build_step_failed",
+            DromosError::DiffChecksumMismatch { .. } => "checksum_mismatch",
+            DromosError::DiffOutputSize { .. } => "output_size_mismatch",
+            DromosError::NoPath { .. } => "no_path",
+            DromosError::Json(_) => "json",
+            DromosError::Export(_) => "export_failed",
+            DromosError::Import(_) => "import_failed",
+            DromosError::InvalidDat { .. } => "invalid_dat",
+            DromosError::InvalidCsv { .. } => "invalid_csv",
+            DromosError::Plugin { .. } => "plugin_failed",
+            DromosError::Sync { .. } => "sync_failed",
+            DromosError::AttachmentNotFound { .. } => "attachment_not_found",
+            DromosError::NotLinked { .. } => "not_linked",
+            DromosError::CollectionNotFound { .. } => "collection_not_found",
+            DromosError::CollectionExists { .. } => "collection_exists",
+            DromosError::MergeIntoItself { .. } => "merge_into_itself",
+            DromosError::SourceNeeded { .. } => "source_needed",
+            DromosError::InvalidMetadata(_) => "invalid_metadata",
+            DromosError::Cancelled => "cancelled",
+        }
+    }
+
+    /// The data the error is about (file, hash, diff, build step), for
+    /// `--json` output. Empty for errors that carry only a message.
+    pub fn context(&self) -> Map<String, Value> {
+        let fields: Vec<(&str, Value)> = match self {
+            DromosError::FileAccess { path, .. }
+            | DromosError::InvalidNesFile { path }
+            | DromosError::FileNotFound { path }
+            | DromosError::InvalidDat { path, .. }
+            | DromosError::InvalidCsv { path, .. } => {
+                vec![("path", path.display().to_string().into())]
+            }
+            DromosError::InconsistentHeader { path, problems } => vec![
+                ("path", path.display().to_string().into()),
+                ("problems", problems.clone().into()),
+            ],
+            DromosError::UnsupportedRomType { extension } => {
+                vec![("extension", extension.as_str().into())]
+            }
+            DromosError::RomNotFound { hash }
+            | DromosError::RomAlreadyExists { hash }
+            | DromosError::InvalidHashFormat { hash }
+            | DromosError::MergeIntoItself { hash }
+            | DromosError::SourceNeeded { hash, .. } => vec![("hash", hash.as_str().into())],
+            DromosError::RomNotFoundAmbiguous { query, candidates } => vec![
+                ("query", query.as_str().into()),
+                ("candidates", candidates.clone().into()),
+            ],
+            DromosError::AttachmentNotFound { name }
+            | DromosError::Plugin { name, .. }
+            | DromosError::CollectionNotFound { name }
+            | DromosError::CollectionExists { name } => vec![("name", name.as_str().into())],
+            DromosError::Sync { remote, .. } => vec![("remote", remote.as_str().into())],
+            DromosError::DiffAlreadyExists(source, target) => vec![
+                ("source", source.as_str().into()),
+                ("target", target.as_str().into()),
+            ],
+            DromosError::NoPath { from, to } | DromosError::NotLinked { from, to } => {
+                vec![("from", from.as_str().into()), ("to", to.as_str().into())]
+            }
+            DromosError::BuildStep {
+                step,
+                steps,
+                diff_path,
+                source,
+            } => vec![
+                ("step", (*step).into()),
+                ("steps", (*steps).into()),
+                ("diff_path", diff_path.as_str().into()),
+                ("cause", source.code().into()),
+            ],
+            DromosError::DiffChecksumMismatch {
+                diff_path,
+                expected,
+                actual,
+            } => vec![
+                ("diff_path", diff_path.as_str().into()),
+                ("expected", expected.as_str().into()),
+                ("actual", actual.as_str().into()),
+            ],
+            DromosError::DiffOutputSize { expected, actual } => vec![
+                ("expected", (*expected).into()),
+                ("actual", (*actual).into()),
+            ],
+            _ => Vec::new(),
+        };
+        fields
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_and_context() {
+        let err = DromosError::RomNotFound {
+            hash: "abc".to_string(),
+        };
+        assert_eq!(err.code(), "rom_not_found");
+        assert_eq!(err.context()["hash"], "abc");
+
+        let err = DromosError::BuildStep {
+            step: 2,
+            steps: 3,
+            diff_path: "a_b.bsdiff".to_string(),
+            source: Box::new(DromosError::DiffApplication("bad patch".to_string())),
+        };
+        assert_eq!(err.code(), "build_step_failed");
+        assert_eq!(err.context()["step"], 2);
+        assert_eq!(err.context()["cause"], "diff_application_failed");
+        assert_eq!(
+            err.to_string(),
+            "step 2 of 3 (a_b.bsdiff): Diff application failed: bad patch"
+        );
+
+        let err = DromosError::file("roms/a.nes")(std::io::Error::other("denied"));
+        assert_eq!(err.code(), "io");
+        assert_eq!(err.context()["path"], "roms/a.nes");
+        assert!(DromosError::Cancelled.context().is_empty());
+    }
+}
@@ -1,69 +1,76 @@
 use std::collections::HashSet;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use sha2::{Digest, Sha256};
 
+use crate::cancel::CancelToken;
 use crate::db::{DATA_REVISION, Repository};
 use crate::error::{DromosError, Result};
 use crate::graph::RomGraph;
+use crate::progress::{Progress, ProgressSink, Stage};
 use crate::rom::format_hash;
 
-use super::format::{ExportEdge, ExportHeader, ExportManifest, ExportNode};
+use super::format::{ExportAttachment, ExportEdge, ExportHeader, ExportManifest, ExportNode};
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExportStats {
     pub nodes: usize,
     pub edges: usize,
-    pub aborted: bool,
+    pub attachments: usize,
+    /// Existing files left in place because they weren't chosen for overwriting
+    pub skipped: usize,
 }
 
-pub enum OverwriteAction {
-    Overwrite,
-    Skip,
-    Abort,
+/// Everything an export will write, read from the library up front so the
+/// caller can check for existing files before anything is written.
+pub struct ExportPlan {
+    pub manifest: ExportManifest,
+    /// Diff filename and contents for each exported edge with a diff on disk
+    diffs: Vec<(String, Vec<u8>)>,
+    /// Stored filename and contents of each exported attachment, once per
+    /// SHA-256
+    attachments: Vec<(String, Vec<u8>)>,
 }
 
-enum WriteResult {
-    Written,
-    Skipped,
-    Aborted,
-}
-
-/// Write bytes to a file, calling `on_conflict` if the file already exists.
-fn write_with_conflict_check(
-    path: &Path,
-    bytes: &[u8],
-    on_conflict: &mut impl FnMut(&Path) -> Result<OverwriteAction>,
-) -> Result<WriteResult> {
-    if path.exists() {
-        match on_conflict(path)? {
-            OverwriteAction::Overwrite => {
-                std::fs::write(path, bytes)?;
-                Ok(WriteResult::Written)
-            }
-            OverwriteAction::Skip => Ok(WriteResult::Skipped),
-            OverwriteAction::Abort => Ok(WriteResult::Aborted),
-        }
-    } else {
-        std::fs::write(path, bytes)?;
-        Ok(WriteResult::Written)
+impl ExportPlan {
+    /// Files under `output_path` that this export would replace.
+    pub fn existing_files(&self, output_path: &Path) -> Vec<PathBuf> {
+        self.files(output_path)
+            .into_iter()
+            .filter(|path| path.exists())
+            .collect()
+    }
+    /// Every file the export writes: `index.json`, the diffs, then the
+    /// attachments.
+    fn files(&self, output_path: &Path) -> Vec<PathBuf> {
+        let diffs_dir = output_path.join("diffs");
+        let attachments_dir = output_path.join("attachments");
+        std::iter::once(output_path.join("index.json"))
+            .chain(self.diffs.iter().map(|(name, _)| diffs_dir.join(name)))
+            .chain(
+                self.attachments
+                    .iter()
+                    .map(|(name, _)| attachments_dir.join(name)),
+            )
+            .collect()
     }
 }
 
-/// Export nodes/edges to a folder.
+/// Gather the nodes, edges, and diffs to export.
 ///
 /// If `component_hash` is provided, exports only the connected component
 /// containing that node. Otherwise exports all nodes.
-///
-/// The `on_conflict` callback is called when a destination file already exists,
-/// letting the caller decide whether to overwrite, skip, or abort.
-pub fn write_folder(
-    output_path: &Path,
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(component = component_hash.map(format_hash)),
+))]
+pub fn plan_export(
     repo: &Repository,
     graph: &RomGraph,
     diffs_dir: &Path,
+    attachments_dir: &Path,
     component_hash: Option<&[u8; 32]>,
-    on_conflict: &mut impl FnMut(&Path) -> Result<OverwriteAction>,
-) -> Result<ExportStats> {
+) -> Result<ExportPlan> {
     // Determine which nodes to export
     let node_hashes: HashSet<[u8; 32]> = match component_hash {
         Some(hash) => {
@@ -78,7 +85,17 @@ pub fn write_folder(
         }
         None => graph.iter_nodes().map(|(_, n)| n.sha256).collect(),
     };
+    plan_export_of(repo, diffs_dir, attachments_dir, &node_hashes)
+}
 
+/// Gather the nodes in `node_hashes`, and the edges, diffs, and attachments
+/// among them, to export.
+pub fn plan_export_of(
+    repo: &Repository,
+    diffs_dir: &Path,
+    attachments_dir: &Path,
+    node_hashes: &HashSet<[u8; 32]>,
+) -> Result<ExportPlan> {
     // Load full NodeRows from DB for selected nodes
     let all_nodes = repo.load_all_nodes()?;
     let selected_nodes: Vec<_> = all_nodes
@@ -110,7 +127,7 @@ pub fn write_folder(
 
     // Read source diffs and compute SHA-256 hashes (without writing yet)
     let mut export_edges: Vec<ExportEdge> = Vec::new();
-    let mut diff_data: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut diffs: Vec<(String, Vec<u8>)> = Vec::new();
     for e in &selected_edges {
         let diff_file_path = diffs_dir.join(&e.diff_path);
         let diff_sha256 = if diff_file_path.exists() {
@@ -118,7 +135,7 @@ pub fn write_folder(
             let mut hasher = Sha256::new();
             hasher.update(&diff_bytes);
             let hash_hex = hex::encode(hasher.finalize());
-            diff_data.push((e.diff_path.clone(), diff_bytes));
+            diffs.push((e.diff_path.clone(), diff_bytes));
             hash_hex
         } else {
             String::new()
@@ -132,6 +149,23 @@ pub fn write_folder(
         ));
     }
 
+    // Attachments of the selected nodes, and of links between them
+    let selected = |hash: &[u8; 32]| node_hashes.contains(hash);
+    let mut export_attachments = Vec::new();
+    let mut attachments: Vec<(String, Vec<u8>)> = Vec::new();
+    for a in repo.load_all_attachments()? {
+        if !selected(&a.node) || !a.linked_node.as_ref().is_none_or(selected) {
+            continue;
+        }
+        let stored_name = format_hash(&a.sha256);
+        if !attachments.iter().any(|(name, _)| *name == stored_name) {
+            let path = attachments_dir.join(&stored_name);
+            let bytes = std::fs::read(&path).map_err(DromosError::file(&path))?;
+            attachments.push((stored_name, bytes));
+        }
+        export_attachments.push(ExportAttachment::from_attachment_row(&a));
+    }
+
     let manifest = ExportManifest {
         dromos_export: ExportHeader {
             version: 1,
@@ -140,11 +174,41 @@ pub fn write_folder(
         },
         files: export_nodes,
         diffs: export_edges,
+        attachments: export_attachments,
     };
 
-    let node_count = manifest.files.len();
-    let edge_count = manifest.diffs.len();
-    let json = serde_json::to_string_pretty(&manifest)?;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        nodes = manifest.files.len(),
+        edges = manifest.diffs.len(),
+        diff_files = diffs.len(),
+        attachments = manifest.attachments.len(),
+        "export planned"
+    );
+    Ok(ExportPlan {
+        manifest,
+        diffs,
+        attachments,
+    })
+}
+
+/// Write a planned export to a folder.
+///
+/// Files that already exist are replaced only if they are in `overwrite`;
+/// the rest are left as they are and counted as skipped. On failure or
+/// cancellation, the files it created are removed.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(output = %output_path.display()),
+))]
+pub fn write_folder(
+    output_path: &Path,
+    plan: &ExportPlan,
+    overwrite: &HashSet<PathBuf>,
+    progress: &mut dyn ProgressSink,
+    cancel: &CancelToken,
+) -> Result<ExportStats> {
+    let json = serde_json::to_string_pretty(&plan.manifest)?;
 
     // Create output directory structure
     std::fs::create_dir_all(output_path).map_err(|e| {
@@ -154,41 +218,55 @@ pub fn write_folder(
             e
         ))
     })?;
-    let output_diffs_dir = output_path.join("diffs");
-    std::fs::create_dir_all(&output_diffs_dir)
+    std::fs::create_dir_all(output_path.join("diffs"))
         .map_err(|e| DromosError::Export(format!("Failed to create diffs directory: {}", e)))?;
-
-    // Write index.json
-    let index_path = output_path.join("index.json");
-    if matches!(
-        write_with_conflict_check(&index_path, json.as_bytes(), on_conflict)?,
-        WriteResult::Aborted
-    ) {
-        return Ok(ExportStats {
-            nodes: node_count,
-            edges: edge_count,
-            aborted: true,
-        });
+    if !plan.attachments.is_empty() {
+        std::fs::create_dir_all(output_path.join("attachments")).map_err(|e| {
+            DromosError::Export(format!("Failed to create attachments directory: {}", e))
+        })?;
     }
 
-    // Copy diff files
-    for (filename, bytes) in &diff_data {
-        let dest = output_diffs_dir.join(filename);
-        if matches!(
-            write_with_conflict_check(&dest, bytes, on_conflict)?,
-            WriteResult::Aborted
-        ) {
-            return Ok(ExportStats {
-                nodes: node_count,
-                edges: edge_count,
-                aborted: true,
+    let contents = std::iter::once(json.as_bytes())
+        .chain(plan.diffs.iter().map(|(_, b)| b.as_slice()))
+        .chain(plan.attachments.iter().map(|(_, b)| b.as_slice()));
+    let files = plan.files(output_path);
+    let mut skipped = 0;
+    // If writing fails or is cancelled, remove the files this export created
+    // so no partial export is left behind; overwritten files stay replaced
+    let mut created = Vec::new();
+    for (i, (path, bytes)) in files.iter().zip(contents).enumerate() {
+        let exists = path.exists();
+        if exists && !overwrite.contains(path) {
+            skipped += 1;
+            continue;
+        }
+        let written = cancel.check().and_then(|_| {
+            progress.progress(&Progress {
+                stage: Stage::Writing,
+                item: &path.display().to_string(),
+                current: i + 1,
+                total: files.len(),
+                bytes: bytes.len() as u64,
             });
+            if !exists {
+                created.push(path);
+            }
+            Ok(std::fs::write(path, bytes)?)
+        });
+        if let Err(e) = written {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %e, removed = created.len(), "export stopped");
+            for path in &created {
+                let _ = std::fs::remove_file(path);
+            }
+            return Err(e);
         }
     }
 
     Ok(ExportStats {
-        nodes: node_count,
-        edges: edge_count,
-        aborted: false,
+        nodes: plan.manifest.files.len(),
+        edges: plan.manifest.diffs.len(),
+        attachments: plan.manifest.attachments.len(),
+        skipped,
     })
 }
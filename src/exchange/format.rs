@@ -1,17 +1,22 @@
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 use serde::{Deserialize, Serialize};
 
-use crate::db::{NodeRow, repository::EdgeRow};
+#[cfg(feature = "native")]
+use crate::db::{AttachmentRow, NodeRow, repository::EdgeRow};
 use crate::rom::format_hash;
+use crate::rom::hash::hash_bytes;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportManifest {
     pub dromos_export: ExportHeader,
     pub files: Vec<ExportNode>,
     pub diffs: Vec<ExportEdge>,
+    /// Missing from exports made before attachments were supported
+    #[serde(default)]
+    pub attachments: Vec<ExportAttachment>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportHeader {
     pub version: u32,
     pub data_revision: u32,
@@ -30,6 +35,20 @@ pub struct ExportNode {
     pub tags: Vec<String>,
     pub description: Option<String>,
     pub source_file_header: Option<String>,
+    /// 1 to 5; missing from exports made before ratings were supported
+    #[serde(default)]
+    pub rating: Option<u8>,
+    /// `unplayed`, `playing`, `finished`, or `abandoned`; missing (meaning
+    /// unplayed) from exports made before play status was supported
+    #[serde(default)]
+    pub play_status: Option<String>,
+    /// Region names, e.g. `USA`; missing from exports made before regions
+    /// were supported
+    #[serde(default)]
+    pub regions: Vec<String>,
+    /// Language codes, e.g. `en`
+    #[serde(default)]
+    pub languages: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,9 +58,57 @@ pub struct ExportEdge {
     pub diff_path: String,
     pub diff_size: i64,
     pub sha256: String,
+    /// Missing from exports made before links had notes
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Size of the ROM data the diff builds; missing from exports made
+    /// before links recorded it
+    #[serde(default)]
+    pub output_size: Option<i64>,
+}
+
+/// A file attached to a ROM or link, stored in the export's `attachments`
+/// folder under its SHA-256.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportAttachment {
+    pub node_sha256: String,
+    /// The link's other end; None when attached to the ROM itself
+    pub linked_sha256: Option<String>,
+    pub name: String,
+    pub size: i64,
+    /// SHA-256 of the contents
+    pub sha256: String,
+}
+
+impl ExportManifest {
+    /// True if the manifest lists no nodes, diffs, or attachments.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.diffs.is_empty() && self.attachments.is_empty()
+    }
+
+    /// The file entry for a ROM hash, if the manifest lists it.
+    pub fn find_file(&self, sha256: &[u8; 32]) -> Option<&ExportNode> {
+        let hash = format_hash(sha256);
+        self.files.iter().find(|f| f.sha256 == hash)
+    }
+
+    /// The diff that turns `source` into `target`, if the manifest has one.
+    pub fn find_diff(&self, source: &[u8; 32], target: &[u8; 32]) -> Option<&ExportEdge> {
+        let (source, target) = (format_hash(source), format_hash(target));
+        self.diffs
+            .iter()
+            .find(|d| d.source_sha256 == source && d.target_sha256 == target)
+    }
 }
 
 impl ExportNode {
+    /// Raw file header bytes for rebuilding the original file, if recorded
+    /// and valid base64.
+    pub fn header_bytes(&self) -> Option<Vec<u8>> {
+        BASE64.decode(self.source_file_header.as_ref()?).ok()
+    }
+
+    #[cfg(feature = "native")]
     pub fn from_node_row(row: &NodeRow) -> Self {
         ExportNode {
             sha256: format_hash(&row.sha256),
@@ -54,12 +121,40 @@ impl ExportNode {
             tags: row.tags.clone(),
             description: row.description.clone(),
             source_file_header: row.source_file_header.as_ref().map(|h| BASE64.encode(h)),
+            rating: row.rating,
+            play_status: Some(row.play_status.to_string()),
+            regions: row.regions.iter().map(|r| r.to_string()).collect(),
+            languages: row.languages.iter().map(|l| l.to_string()).collect(),
+        }
+    }
+}
+
+impl ExportAttachment {
+    /// True if `contents` are the file this entry describes.
+    pub fn verify(&self, contents: &[u8]) -> bool {
+        hex::encode(hash_bytes(contents)) == self.sha256
+    }
+
+    #[cfg(feature = "native")]
+    pub fn from_attachment_row(row: &AttachmentRow) -> Self {
+        ExportAttachment {
+            node_sha256: format_hash(&row.node),
+            linked_sha256: row.linked_node.as_ref().map(format_hash),
+            name: row.name.clone(),
+            size: row.size,
+            sha256: format_hash(&row.sha256),
         }
     }
 }
 
 impl ExportEdge {
+    /// True if `diff` is the diff file this entry describes.
+    pub fn verify(&self, diff: &[u8]) -> bool {
+        hex::encode(hash_bytes(diff)) == self.sha256
+    }
+
     /// Create from an EdgeRow, resolving DB IDs to hash strings.
+    #[cfg(feature = "native")]
     pub fn from_edge_row(
         edge: &EdgeRow,
         source_hash: &str,
@@ -72,6 +167,51 @@ impl ExportEdge {
             diff_path: edge.diff_path.clone(),
             diff_size: edge.diff_size,
             sha256: diff_sha256.to_string(),
+            note: edge.note.clone(),
+            output_size: edge.output_size,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_and_verify_diff() {
+        let (a, b) = ([0xAA; 32], [0xBB; 32]);
+        let diff = b"patch bytes";
+        let manifest = ExportManifest {
+            dromos_export: ExportHeader {
+                version: 1,
+                data_revision: 2,
+                exported_at: String::new(),
+            },
+            files: Vec::new(),
+            diffs: vec![ExportEdge {
+                source_sha256: format_hash(&a),
+                target_sha256: format_hash(&b),
+                diff_path: "a_to_b.bsdiff".to_string(),
+                diff_size: diff.len() as i64,
+                sha256: hex::encode(hash_bytes(diff)),
+                note: None,
+                output_size: None,
+            }],
+            attachments: Vec::new(),
+        };
+
+        let edge = manifest.find_diff(&a, &b).unwrap();
+        assert!(edge.verify(diff));
+        assert!(!edge.verify(b"tampered"));
+        assert!(manifest.find_diff(&b, &a).is_none());
+        assert!(manifest.find_file(&a).is_none());
+
+        // Exports from before attachments still read
+        let old: ExportManifest = serde_json::from_str(
+            r#"{"dromos_export": {"version": 1, "data_revision": 2, "exported_at": ""},
+                "files": [], "diffs": []}"#,
+        )
+        .unwrap();
+        assert!(old.attachments.is_empty());
+    }
+}
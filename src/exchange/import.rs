@@ -1,16 +1,21 @@
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 use sha2::{Digest, Sha256};
 
+use crate::cancel::CancelToken;
 use crate::db::{DATA_REVISION, NodeMetadata, Repository};
 use crate::error::{DromosError, Result};
 use crate::graph::{DiffEdge, RomGraph, RomNode};
-use crate::rom::{RomMetadata, RomType, parse_hash};
+use crate::progress::{Progress, ProgressSink, Stage};
+use crate::rom::{Language, Region, RomMetadata, RomType, parse_hash, region};
 
-use super::format::{ExportManifest, ExportNode};
+use super::format::{ExportAttachment, ExportEdge, ExportManifest, ExportNode};
 
 /// Describes a field that differs between local and import data.
 #[derive(Debug)]
@@ -26,8 +31,23 @@ pub struct NodeConflict {
     pub sha256: String,
     pub title: String,
     pub diffs: Vec<FieldDiff>,
+    /// The complete metadata from the import, as a starting point for edits
+    pub import_metadata: NodeMetadata,
 }
 
+/// How to settle one node's conflict. Conflicting nodes without a
+/// resolution keep their local metadata.
+#[derive(Debug, Clone)]
+pub enum ConflictResolution {
+    /// Replace local metadata with the imported values
+    Import,
+    KeepLocal,
+    /// Replace local metadata with values edited by the user
+    Edited(NodeMetadata),
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ImportResult {
     pub nodes_added: usize,
     pub nodes_skipped: usize,
@@ -35,9 +55,48 @@ pub struct ImportResult {
     pub edges_added: usize,
     pub edges_skipped: usize,
     pub diffs_copied: usize,
+    pub attachments_added: usize,
+    /// Attachments left out because one with the same name was already there
+    pub attachments_skipped: usize,
+    /// Hashes of the nodes added
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hashes"))]
+    pub added: Vec<[u8; 32]>,
+    /// Hashes of existing nodes whose metadata was replaced
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hashes"))]
+    pub overwritten: Vec<[u8; 32]>,
+    pub linked: Vec<ImportedLink>,
+    /// Nodes whose imported metadata didn't pass [`NodeMetadata::normalize`]:
+    /// new ones were left out, existing ones kept their local metadata
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rejected: Vec<RejectedNode>,
+}
+
+/// A node an import left alone because of its metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RejectedNode {
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
+    pub sha256: [u8; 32],
+    pub title: String,
+    pub reason: String,
+}
+
+/// An edge added by an import
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImportedLink {
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
+    pub source: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
+    pub target: [u8; 32],
+    pub diff_size: i64,
 }
 
 /// Phase 1: Analyze a folder and identify conflicts.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(folder = %folder_path.display()),
+))]
 pub fn analyze_import(
     folder_path: &Path,
     repo: &Repository,
@@ -65,36 +124,47 @@ pub fn analyze_import(
         })?;
 
         if let Some(local_row) = repo.get_node_by_hash(&hash)? {
+            // Metadata that can't be stored is reported by the import instead
+            let Ok(import_metadata) =
+                node_metadata_from_export(import_node, Some(&local_row.metadata()))
+            else {
+                continue;
+            };
             let mut diffs = Vec::new();
 
-            compare_field(&mut diffs, "title", &local_row.title, &import_node.title);
+            compare_field(
+                &mut diffs,
+                "title",
+                &local_row.title,
+                &import_metadata.title,
+            );
             compare_optional(
                 &mut diffs,
                 "version",
                 &local_row.version,
-                &import_node.version,
+                &import_metadata.version,
             );
             compare_optional(
                 &mut diffs,
                 "source_url",
                 &local_row.source_url,
-                &import_node.source_url,
+                &import_metadata.source_url,
             );
             compare_optional(
                 &mut diffs,
                 "release_date",
                 &local_row.release_date,
-                &import_node.release_date,
+                &import_metadata.release_date,
             );
             compare_optional(
                 &mut diffs,
                 "description",
                 &local_row.description,
-                &import_node.description,
+                &import_metadata.description,
             );
 
             let local_tags = local_row.tags.join(", ");
-            let import_tags = import_node.tags.join(", ");
+            let import_tags = import_metadata.tags.join(", ");
             if local_tags != import_tags {
                 diffs.push(FieldDiff {
                     field: "tags".to_string(),
@@ -103,27 +173,70 @@ pub fn analyze_import(
                 });
             }
 
+            let rating = |r: Option<u8>| r.map(|r| r.to_string()).unwrap_or_default();
+            compare_field(
+                &mut diffs,
+                "rating",
+                &rating(local_row.rating),
+                &rating(import_metadata.rating),
+            );
+            compare_field(
+                &mut diffs,
+                "play_status",
+                local_row.play_status.as_str(),
+                import_metadata.play_status.as_str(),
+            );
+            compare_field(
+                &mut diffs,
+                "region",
+                &region::join_list(&local_row.regions),
+                &region::join_list(&import_metadata.regions),
+            );
+            compare_field(
+                &mut diffs,
+                "language",
+                &region::join_list(&local_row.languages),
+                &region::join_list(&import_metadata.languages),
+            );
+
             if !diffs.is_empty() {
                 conflicts.push(NodeConflict {
                     sha256: import_node.sha256.clone(),
                     title: import_node.title.clone(),
                     diffs,
+                    import_metadata,
                 });
             }
         }
     }
 
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        nodes = manifest.files.len(),
+        edges = manifest.diffs.len(),
+        conflicts = conflicts.len(),
+        "import analyzed"
+    );
     Ok((manifest, conflicts))
 }
 
 /// Phase 2: Execute the import, inserting nodes/edges and copying diffs.
+/// Existing nodes are updated according to `resolutions` (keyed by hash).
+#[allow(clippy::too_many_arguments)]
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(folder = %folder_path.display()),
+))]
 pub fn execute_import(
     folder_path: &Path,
     manifest: &ExportManifest,
-    overwrite: bool,
+    resolutions: &HashMap<String, ConflictResolution>,
     repo: &Repository,
     graph: &mut RomGraph,
     diffs_dir: &Path,
+    attachments_dir: &Path,
+    progress: &mut dyn ProgressSink,
+    cancel: &CancelToken,
 ) -> Result<ImportResult> {
     let mut result = ImportResult {
         nodes_added: 0,
@@ -132,20 +245,57 @@ pub fn execute_import(
         edges_added: 0,
         edges_skipped: 0,
         diffs_copied: 0,
+        attachments_added: 0,
+        attachments_skipped: 0,
+        added: Vec::new(),
+        overwritten: Vec::new(),
+        linked: Vec::new(),
+        rejected: Vec::new(),
     };
 
     // Build hash -> DB ID map for edge insertion
     let mut hash_to_db_id: HashMap<String, i64> = HashMap::new();
 
     // Process nodes
-    for import_node in &manifest.files {
+    for (i, import_node) in manifest.files.iter().enumerate() {
+        cancel.check()?;
+        progress.progress(&Progress {
+            stage: Stage::Importing,
+            item: &import_node.title,
+            current: i + 1,
+            total: manifest.files.len(),
+            bytes: 0,
+        });
         let hash = parse_hash(&import_node.sha256)
             .ok_or_else(|| DromosError::Import(format!("Invalid hash: {}", import_node.sha256)))?;
 
+        let mut reject = |reason: String| {
+            result.rejected.push(RejectedNode {
+                sha256: hash,
+                title: import_node.title.clone(),
+                reason,
+            })
+        };
+
         if let Some(existing) = repo.get_node_by_hash(&hash)? {
-            if overwrite {
+            let stored = existing.metadata();
+            let node_meta = match resolutions.get(&import_node.sha256) {
+                Some(ConflictResolution::Import) => {
+                    node_metadata_from_export(import_node, Some(&stored))
+                        .map_err(&mut reject)
+                        .ok()
+                }
+                Some(ConflictResolution::Edited(edited)) => {
+                    let mut edited = edited.clone();
+                    edited.normalize_edit(&stored).map_err(|reason| {
+                        DromosError::Import(format!("{}: {}", import_node.sha256, reason))
+                    })?;
+                    Some(edited)
+                }
+                Some(ConflictResolution::KeepLocal) | None => None,
+            };
+            if let Some(node_meta) = node_meta {
                 // Update metadata for conflicting nodes
-                let node_meta = node_metadata_from_export(import_node);
                 repo.update_node_metadata(existing.id, &node_meta)?;
 
                 // Update in-memory graph
@@ -157,14 +307,21 @@ pub fn execute_import(
                 }
 
                 result.nodes_overwritten += 1;
+                result.overwritten.push(hash);
             } else {
                 result.nodes_skipped += 1;
             }
             hash_to_db_id.insert(import_node.sha256.clone(), existing.id);
         } else {
-            // New node: insert
+            // New node: insert, unless its metadata can't be stored
             let rom_meta = rom_metadata_from_export(import_node)?;
-            let node_meta = node_metadata_from_export(import_node);
+            let node_meta = match node_metadata_from_export(import_node, None) {
+                Ok(node_meta) => node_meta,
+                Err(reason) => {
+                    reject(reason);
+                    continue;
+                }
+            };
 
             let db_id = repo.insert_node(&rom_meta, &node_meta)?;
 
@@ -179,11 +336,13 @@ pub fn execute_import(
 
             hash_to_db_id.insert(import_node.sha256.clone(), db_id);
             result.nodes_added += 1;
+            result.added.push(hash);
         }
     }
 
     // Process edges
     for import_edge in &manifest.diffs {
+        cancel.check()?;
         let source_id = match hash_to_db_id.get(&import_edge.source_sha256) {
             Some(id) => *id,
             None => {
@@ -217,8 +376,16 @@ pub fn execute_import(
             target_id,
             &import_edge.diff_path,
             import_edge.diff_size,
+            import_edge.note.as_deref(),
         ) {
             Ok(edge_db_id) => {
+                if !import_edge.sha256.is_empty() {
+                    repo.set_diff_checksum(&import_edge.diff_path, &import_edge.sha256)?;
+                }
+                if let Some(size) = import_edge.output_size {
+                    repo.set_diff_output_size(&import_edge.diff_path, size)?;
+                }
+
                 // Update in-memory graph
                 let source_hash = parse_hash(&import_edge.source_sha256).unwrap();
                 let target_hash = parse_hash(&import_edge.target_sha256).unwrap();
@@ -234,11 +401,18 @@ pub fn execute_import(
                             db_id: edge_db_id,
                             diff_path: import_edge.diff_path.clone(),
                             diff_size: import_edge.diff_size,
+                            note: import_edge.note.clone(),
+                            output_size: import_edge.output_size,
                         },
                     );
                 }
 
                 result.edges_added += 1;
+                result.linked.push(ImportedLink {
+                    source: source_hash,
+                    target: target_hash,
+                    diff_size: import_edge.diff_size,
+                });
             }
             Err(DromosError::DiffAlreadyExists(_, _)) => {
                 result.edges_skipped += 1;
@@ -247,40 +421,267 @@ pub fn execute_import(
         }
     }
 
-    // Copy diff files from folder, verifying SHA-256
+    // Attach files where they were exported from, unless a file with the
+    // same name is already attached there
+    let mut attached = Vec::new();
+    for import_attachment in &manifest.attachments {
+        cancel.check()?;
+        let node = parse_import_hash(&import_attachment.node_sha256)?;
+        let linked = match &import_attachment.linked_sha256 {
+            Some(hash) => Some(parse_import_hash(hash)?),
+            None => None,
+        };
+        let Some(node_id) = node_db_id(&hash_to_db_id, repo, &node)? else {
+            continue;
+        };
+        let linked_id = match &linked {
+            Some(linked) => match node_db_id(&hash_to_db_id, repo, linked)? {
+                Some(id) => Some(id),
+                None => continue,
+            },
+            None => None,
+        };
+        let contents = parse_import_hash(&import_attachment.sha256)?;
+
+        let exists = repo
+            .get_attachments_for_node(node_id)?
+            .iter()
+            .any(|a| a.name == import_attachment.name && a.is_on(&node, linked.as_ref()));
+        if exists {
+            result.attachments_skipped += 1;
+            continue;
+        }
+        repo.insert_attachment(
+            node_id,
+            linked_id,
+            &import_attachment.name,
+            &contents,
+            import_attachment.size,
+        )?;
+        attached.push(import_attachment);
+        result.attachments_added += 1;
+    }
+
+    // Copy diff and attachment files from folder, verifying SHA-256. If
+    // copying fails or is cancelled, remove the files already copied so none
+    // are left orphaned.
+    let mut copied = Vec::new();
+    let copy = copy_diffs(
+        folder_path,
+        manifest,
+        diffs_dir,
+        &mut copied,
+        progress,
+        cancel,
+    )
+    .and_then(|_| {
+        result.diffs_copied = copied.len();
+        copy_attachments(
+            folder_path,
+            &attached,
+            attachments_dir,
+            &mut copied,
+            progress,
+            cancel,
+        )
+    });
+    if let Err(e) = copy {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(error = %e, removed = copied.len(), "diff copy stopped");
+        for path in &copied {
+            let _ = fs::remove_file(path);
+        }
+        return Err(e);
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        nodes_added = result.nodes_added,
+        nodes_overwritten = result.nodes_overwritten,
+        edges_added = result.edges_added,
+        diffs_copied = result.diffs_copied,
+        "import applied"
+    );
+    Ok(result)
+}
+
+/// A message from a [`copy_diffs`] worker to the thread reporting progress.
+enum CopyEvent<'a> {
+    /// The worker started on this diff
+    Started(&'a ExportEdge),
+    /// The worker is about to write this file, so remove it if the import fails
+    Writing(PathBuf),
+    /// The worker finished its diff, or failed
+    Finished(Result<()>),
+}
+
+/// Copy the manifest's diff files that aren't already present locally,
+/// recording each file written in `copied`. Diffs are read, checksummed and
+/// written on several threads at once; progress is reported from this one.
+/// The first failure stops the other workers and is returned once they exit.
+fn copy_diffs(
+    folder_path: &Path,
+    manifest: &ExportManifest,
+    diffs_dir: &Path,
+    copied: &mut Vec<PathBuf>,
+    progress: &mut dyn ProgressSink,
+    cancel: &CancelToken,
+) -> Result<()> {
     let import_diffs_dir = folder_path.join("diffs");
+    let mut jobs = Vec::new();
     for import_edge in &manifest.diffs {
-        let source_diff_path = import_diffs_dir.join(&import_edge.diff_path);
+        cancel.check()?;
+        // Skip files that already exist locally or are missing from the import
         let local_diff_path = diffs_dir.join(&import_edge.diff_path);
-
-        // Skip if file already exists locally
-        if local_diff_path.exists() {
-            continue;
+        if !local_diff_path.exists() && import_diffs_dir.join(&import_edge.diff_path).exists() {
+            jobs.push(import_edge);
         }
+    }
+    if jobs.is_empty() {
+        return Ok(());
+    }
 
-        // Copy from import folder
-        if source_diff_path.exists() {
-            let bytes = fs::read(&source_diff_path)?;
-
-            // Verify SHA-256 if checksum is present
-            if !import_edge.sha256.is_empty() {
-                let mut hasher = Sha256::new();
-                hasher.update(&bytes);
-                let computed = hex::encode(hasher.finalize());
-                if computed != import_edge.sha256 {
-                    return Err(DromosError::Import(format!(
-                        "SHA-256 mismatch for {}: expected {}, got {}",
-                        import_edge.diff_path, import_edge.sha256, computed
-                    )));
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(jobs.len());
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (events, received) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let events = events.clone();
+            let (jobs, next, stop) = (&jobs, &next, &stop);
+            let import_diffs_dir = &import_diffs_dir;
+            scope.spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    let Some(import_edge) = jobs.get(next.fetch_add(1, Ordering::SeqCst)) else {
+                        break;
+                    };
+                    let _ = events.send(CopyEvent::Started(import_edge));
+                    let copy = cancel
+                        .check()
+                        .and_then(|_| copy_diff(import_diffs_dir, diffs_dir, import_edge, &events));
+                    let _ = events.send(CopyEvent::Finished(copy));
+                }
+            });
+        }
+        drop(events);
+
+        let mut started = 0;
+        let mut failure = None;
+        for event in received {
+            match event {
+                CopyEvent::Started(import_edge) => {
+                    started += 1;
+                    if failure.is_none() {
+                        progress.progress(&Progress {
+                            stage: Stage::Copying,
+                            item: &import_edge.diff_path,
+                            current: started,
+                            total: jobs.len(),
+                            bytes: import_edge.diff_size as u64,
+                        });
+                    }
+                }
+                CopyEvent::Writing(path) => copied.push(path),
+                CopyEvent::Finished(Ok(())) => {}
+                CopyEvent::Finished(Err(e)) => {
+                    stop.store(true, Ordering::SeqCst);
+                    failure.get_or_insert(e);
                 }
             }
+        }
+        failure.map_or(Ok(()), Err)
+    })
+}
 
-            fs::write(&local_diff_path, &bytes)?;
-            result.diffs_copied += 1;
+/// Copy one diff from the import's `diffs/` folder, verifying its SHA-256
+/// when the manifest has one.
+fn copy_diff(
+    import_diffs_dir: &Path,
+    diffs_dir: &Path,
+    import_edge: &ExportEdge,
+    events: &mpsc::Sender<CopyEvent>,
+) -> Result<()> {
+    let bytes = fs::read(import_diffs_dir.join(&import_edge.diff_path))?;
+
+    // Verify SHA-256 if checksum is present
+    if !import_edge.sha256.is_empty() {
+        let mut hasher = Sha256::new();
+        hasher.update(&bytes);
+        let computed = hex::encode(hasher.finalize());
+        if computed != import_edge.sha256 {
+            return Err(DromosError::DiffChecksumMismatch {
+                diff_path: import_edge.diff_path.clone(),
+                expected: import_edge.sha256.clone(),
+                actual: computed,
+            });
         }
     }
 
-    Ok(result)
+    let local_diff_path = diffs_dir.join(&import_edge.diff_path);
+    let _ = events.send(CopyEvent::Writing(local_diff_path.clone()));
+    fs::write(&local_diff_path, &bytes)?;
+    Ok(())
+}
+
+/// Copy the contents of newly added attachments that aren't already stored
+/// locally, recording each file written in `copied`.
+fn copy_attachments(
+    folder_path: &Path,
+    attachments: &[&ExportAttachment],
+    attachments_dir: &Path,
+    copied: &mut Vec<PathBuf>,
+    progress: &mut dyn ProgressSink,
+    cancel: &CancelToken,
+) -> Result<()> {
+    for (i, attachment) in attachments.iter().enumerate() {
+        cancel.check()?;
+        let local_path = attachments_dir.join(&attachment.sha256);
+        if local_path.exists() || copied.contains(&local_path) {
+            continue;
+        }
+        progress.progress(&Progress {
+            stage: Stage::Copying,
+            item: &attachment.name,
+            current: i + 1,
+            total: attachments.len(),
+            bytes: attachment.size as u64,
+        });
+        let relative = format!("attachments/{}", attachment.sha256);
+        let source_path = folder_path.join(&relative);
+        let bytes = fs::read(&source_path).map_err(DromosError::file(&source_path))?;
+        if !attachment.verify(&bytes) {
+            let mut hasher = Sha256::new();
+            hasher.update(&bytes);
+            return Err(DromosError::DiffChecksumMismatch {
+                diff_path: relative,
+                expected: attachment.sha256.clone(),
+                actual: hex::encode(hasher.finalize()),
+            });
+        }
+
+        fs::create_dir_all(attachments_dir).map_err(DromosError::file(attachments_dir))?;
+        copied.push(local_path.clone());
+        fs::write(&local_path, &bytes)?;
+    }
+    Ok(())
+}
+
+fn parse_import_hash(hash: &str) -> Result<[u8; 32]> {
+    parse_hash(hash).ok_or_else(|| DromosError::Import(format!("Invalid hash: {}", hash)))
+}
+
+/// The database ID of a node added by this import or already in the library.
+fn node_db_id(
+    hash_to_db_id: &HashMap<String, i64>,
+    repo: &Repository,
+    sha256: &[u8; 32],
+) -> Result<Option<i64>> {
+    match hash_to_db_id.get(&hex::encode(sha256)) {
+        Some(id) => Ok(Some(*id)),
+        None => Ok(repo.get_node_by_hash(sha256)?.map(|row| row.id)),
+    }
 }
 
 fn compare_field(diffs: &mut Vec<FieldDiff>, field: &str, local: &str, import: &str) {
@@ -310,15 +711,41 @@ fn compare_optional(
     }
 }
 
-fn node_metadata_from_export(node: &ExportNode) -> NodeMetadata {
-    NodeMetadata {
+/// An exported node's metadata, checked and tidied as if it were added here,
+/// or as an edit of `stored` for a node the library already has.
+fn node_metadata_from_export(
+    node: &ExportNode,
+    stored: Option<&NodeMetadata>,
+) -> std::result::Result<NodeMetadata, String> {
+    let mut metadata = NodeMetadata {
         title: node.title.clone(),
         source_url: node.source_url.clone(),
         version: node.version.clone(),
         release_date: node.release_date.clone(),
         tags: node.tags.clone(),
         description: node.description.clone(),
+        rating: node.rating,
+        play_status: node
+            .play_status
+            .as_deref()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default(),
+        regions: node
+            .regions
+            .iter()
+            .map(|r| Region::from(r.clone()))
+            .collect(),
+        languages: node
+            .languages
+            .iter()
+            .map(|l| Language::from(l.clone()))
+            .collect(),
+    };
+    match stored {
+        Some(stored) => metadata.normalize_edit(stored)?,
+        None => metadata.normalize()?,
     }
+    Ok(metadata)
 }
 
 fn rom_metadata_from_export(node: &ExportNode) -> Result<RomMetadata> {
@@ -338,6 +765,9 @@ fn rom_metadata_from_export(node: &ExportNode) -> Result<RomMetadata> {
     Ok(RomMetadata {
         rom_type,
         sha256,
+        sha1: None,
+        crc32: None,
+        content_size: None,
         filename: node.filename.clone(),
         nes_header: None, // Not serialized in export format
         source_file_header,
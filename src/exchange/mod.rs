@@ -1,7 +1,18 @@
+#[cfg(feature = "native")]
 pub mod export;
 pub mod format;
+#[cfg(feature = "native")]
 pub mod import;
+#[cfg(feature = "native")]
+pub mod sync;
 
-pub use export::{ExportStats, OverwriteAction, write_folder};
-pub use format::{ExportEdge, ExportHeader, ExportManifest, ExportNode};
-pub use import::{ImportResult, NodeConflict, analyze_import, execute_import};
+#[cfg(feature = "native")]
+pub use export::{ExportPlan, ExportStats, plan_export, plan_export_of, write_folder};
+pub use format::{ExportAttachment, ExportEdge, ExportHeader, ExportManifest, ExportNode};
+#[cfg(feature = "native")]
+pub use import::{
+    ConflictResolution, ImportResult, ImportedLink, NodeConflict, RejectedNode, analyze_import,
+    execute_import,
+};
+#[cfg(feature = "native")]
+pub use sync::{SyncPlan, plan_sync};
@@ -0,0 +1,262 @@
+//! Comparing two libraries for `sync`: which nodes, links, and attachments
+//! each one is missing, as manifests of the entries to send each way. The
+//! files they refer to travel one at a time.
+
+use std::collections::{BTreeMap, HashSet};
+
+use super::format::{ExportAttachment, ExportEdge, ExportManifest, ExportNode};
+
+/// What a sync would copy in each direction.
+#[derive(Debug, Clone)]
+pub struct SyncPlan {
+    /// Entries the remote library has and the local one doesn't
+    pub pull: ExportManifest,
+    /// Entries the local library has and the remote one doesn't
+    pub push: ExportManifest,
+    /// Hashes of nodes in both libraries whose metadata differs, which sync
+    /// leaves as they are
+    pub conflicts: Vec<String>,
+}
+
+impl SyncPlan {
+    /// True if the libraries already have the same nodes, links, and
+    /// attachments.
+    pub fn is_empty(&self) -> bool {
+        self.pull.is_empty() && self.push.is_empty()
+    }
+}
+
+/// Compare the manifests of the whole local and remote libraries.
+pub fn plan_sync(local: &ExportManifest, remote: &ExportManifest) -> SyncPlan {
+    let remote_nodes: BTreeMap<&str, &ExportNode> = remote
+        .files
+        .iter()
+        .map(|n| (n.sha256.as_str(), n))
+        .collect();
+    let conflicts = local
+        .files
+        .iter()
+        .filter(|node| {
+            remote_nodes
+                .get(node.sha256.as_str())
+                .is_some_and(|other| !same_metadata(node, other))
+        })
+        .map(|node| node.sha256.clone())
+        .collect();
+    SyncPlan {
+        pull: select(remote, local, false),
+        push: select(local, remote, false),
+        conflicts,
+    }
+}
+
+/// The entries of `manifest` that are (`present`) or aren't in `other`.
+pub fn select(manifest: &ExportManifest, other: &ExportManifest, present: bool) -> ExportManifest {
+    let nodes: HashSet<&str> = other.files.iter().map(|n| n.sha256.as_str()).collect();
+    let diffs: HashSet<(&str, &str)> = other.diffs.iter().map(diff_key).collect();
+    let attachments: HashSet<(&str, Option<&str>, &str)> =
+        other.attachments.iter().map(attachment_key).collect();
+    ExportManifest {
+        dromos_export: manifest.dromos_export.clone(),
+        files: manifest
+            .files
+            .iter()
+            .filter(|n| nodes.contains(n.sha256.as_str()) == present)
+            .cloned()
+            .collect(),
+        diffs: manifest
+            .diffs
+            .iter()
+            .filter(|d| diffs.contains(&diff_key(d)) == present)
+            .cloned()
+            .collect(),
+        attachments: manifest
+            .attachments
+            .iter()
+            .filter(|a| attachments.contains(&attachment_key(a)) == present)
+            .cloned()
+            .collect(),
+    }
+}
+
+/// The nodes of `manifest` with any of `tags`, with the diffs and
+/// attachments that involve them.
+pub fn tagged(manifest: &ExportManifest, tags: &[String]) -> ExportManifest {
+    let files: Vec<ExportNode> = manifest
+        .files
+        .iter()
+        .filter(|n| {
+            n.tags
+                .iter()
+                .any(|t| tags.iter().any(|w| w.eq_ignore_ascii_case(t)))
+        })
+        .cloned()
+        .collect();
+    let selected: HashSet<&str> = files.iter().map(|n| n.sha256.as_str()).collect();
+    let involved =
+        |a: &str, b: Option<&str>| selected.contains(a) || b.is_some_and(|b| selected.contains(b));
+    ExportManifest {
+        dromos_export: manifest.dromos_export.clone(),
+        diffs: manifest
+            .diffs
+            .iter()
+            .filter(|d| involved(&d.source_sha256, Some(&d.target_sha256)))
+            .cloned()
+            .collect(),
+        attachments: manifest
+            .attachments
+            .iter()
+            .filter(|a| involved(&a.node_sha256, a.linked_sha256.as_deref()))
+            .cloned()
+            .collect(),
+        files,
+    }
+}
+
+/// `manifest` without the diffs and attachments that refer to nodes that
+/// are neither in it nor in `present`, which importing it would skip.
+pub fn without_dangling(manifest: &ExportManifest, present: &HashSet<String>) -> ExportManifest {
+    let listed: HashSet<&str> = manifest.files.iter().map(|n| n.sha256.as_str()).collect();
+    let known = |hash: &str| present.contains(hash) || listed.contains(hash);
+    let mut kept = manifest.clone();
+    kept.diffs
+        .retain(|d| known(&d.source_sha256) && known(&d.target_sha256));
+    kept.attachments
+        .retain(|a| known(&a.node_sha256) && a.linked_sha256.as_deref().is_none_or(known));
+    kept
+}
+
+fn diff_key(edge: &ExportEdge) -> (&str, &str) {
+    (&edge.source_sha256, &edge.target_sha256)
+}
+
+/// Imports skip attachments with the same name in the same place, so that's
+/// what identifies one.
+fn attachment_key(attachment: &ExportAttachment) -> (&str, Option<&str>, &str) {
+    (
+        &attachment.node_sha256,
+        attachment.linked_sha256.as_deref(),
+        &attachment.name,
+    )
+}
+
+fn same_metadata(a: &ExportNode, b: &ExportNode) -> bool {
+    a.title == b.title
+        && a.version == b.version
+        && a.source_url == b.source_url
+        && a.release_date == b.release_date
+        && a.tags == b.tags
+        && a.description == b.description
+        && a.rating == b.rating
+        && a.play_status.as_deref().unwrap_or("unplayed")
+            == b.play_status.as_deref().unwrap_or("unplayed")
+        && a.regions == b.regions
+        && a.languages == b.languages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::ExportHeader;
+
+    fn node(sha256: &str, title: &str) -> ExportNode {
+        ExportNode {
+            sha256: sha256.to_string(),
+            filename: None,
+            title: title.to_string(),
+            rom_type: "nes".to_string(),
+            version: None,
+            source_url: None,
+            release_date: None,
+            tags: Vec::new(),
+            description: None,
+            source_file_header: None,
+            rating: None,
+            play_status: None,
+            regions: Vec::new(),
+            languages: Vec::new(),
+        }
+    }
+
+    fn edge(source: &str, target: &str) -> ExportEdge {
+        ExportEdge {
+            source_sha256: source.to_string(),
+            target_sha256: target.to_string(),
+            diff_path: format!("{}_{}.bsdiff", source, target),
+            diff_size: 1,
+            sha256: String::new(),
+            note: None,
+            output_size: None,
+        }
+    }
+
+    fn manifest(files: Vec<ExportNode>, diffs: Vec<ExportEdge>) -> ExportManifest {
+        ExportManifest {
+            dromos_export: ExportHeader {
+                version: 1,
+                data_revision: 1,
+                exported_at: String::new(),
+            },
+            files,
+            diffs,
+            attachments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_plan_sync() {
+        let desktop = manifest(
+            vec![
+                node("aa", "Base"),
+                node("bb", "Hack"),
+                node("cc", "Desktop"),
+            ],
+            vec![edge("aa", "bb"), edge("bb", "aa"), edge("aa", "cc")],
+        );
+        let laptop = manifest(
+            vec![
+                node("aa", "Base"),
+                node("bb", "Hack v2"),
+                node("dd", "Laptop"),
+            ],
+            vec![edge("aa", "bb"), edge("bb", "aa"), edge("aa", "dd")],
+        );
+
+        let plan = plan_sync(&desktop, &laptop);
+        let hashes = |m: &ExportManifest| -> Vec<String> {
+            m.files.iter().map(|n| n.sha256.clone()).collect()
+        };
+        assert_eq!(hashes(&plan.push), ["cc"]);
+        assert_eq!(hashes(&plan.pull), ["dd"]);
+        assert_eq!(plan.push.diffs.len(), 1);
+        assert_eq!(plan.pull.diffs[0].target_sha256, "dd");
+        assert_eq!(plan.conflicts, ["bb"]);
+        assert!(!plan.is_empty());
+
+        assert!(plan_sync(&desktop, &desktop).is_empty());
+        let wanted = select(&desktop, &plan.push, true);
+        assert_eq!(hashes(&wanted), ["cc"]);
+    }
+
+    #[test]
+    fn test_tagged_selection() {
+        let mut hack = node("bb", "Hack");
+        hack.tags = vec!["Translation".to_string()];
+        let library = manifest(
+            vec![node("aa", "Base"), hack, node("cc", "Other")],
+            vec![edge("aa", "bb"), edge("bb", "aa"), edge("aa", "cc")],
+        );
+
+        let selected = tagged(&library, &["translation".to_string()]);
+        assert_eq!(selected.files.len(), 1);
+        assert_eq!(selected.diffs.len(), 2);
+        // Without the base here, its links can't be imported
+        assert!(
+            without_dangling(&selected, &HashSet::new())
+                .diffs
+                .is_empty()
+        );
+        let present = HashSet::from(["aa".to_string()]);
+        assert_eq!(without_dangling(&selected, &present).diffs.len(), 2);
+    }
+}
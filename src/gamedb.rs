@@ -0,0 +1,156 @@
+//! Offline lookup of original releases in an OpenVGDB database
+//! (`openvgdb.sqlite`), used to suggest a new ROM's title, region, and
+//! release date.
+//!
+//! ROMs are matched by SHA-1, or by CRC32 when no SHA-1 matches, of their
+//! data without the file header.
+
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
+
+use crate::error::Result;
+use crate::rom::RomMetadata;
+
+/// A release found for a ROM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameInfo {
+    /// Canonical title, e.g. "Super Mario Bros."
+    pub title: String,
+    /// Region name, e.g. "USA"
+    pub region: Option<String>,
+    /// Release date as YYYY-MM-DD, when the database gives a full date
+    pub release_date: Option<String>,
+}
+
+/// An open OpenVGDB database, read-only.
+pub struct GameDb {
+    conn: Connection,
+}
+
+const LOOKUP_SQL: &str = "SELECT r.releaseTitleName, g.regionName, r.releaseDate
+     FROM ROMs m
+     JOIN RELEASES r ON r.romID = m.romID
+     LEFT JOIN REGIONS g ON g.regionID = m.regionID
+     WHERE m.romHashSHA1 = ?1 OR m.romHashCRC = ?2
+     ORDER BY m.romHashSHA1 = ?1 DESC, r.releaseID
+     LIMIT 1";
+
+impl GameDb {
+    /// Open the database at `path`, checking it has OpenVGDB's tables.
+    pub fn open(path: &Path) -> Result<GameDb> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        conn.prepare(LOOKUP_SQL)?;
+        Ok(GameDb { conn })
+    }
+
+    /// The release matching a hashed ROM, if any. ROMs hashed without a
+    /// SHA-1 or CRC32 never match.
+    pub fn lookup(&self, metadata: &RomMetadata) -> Result<Option<GameInfo>> {
+        if metadata.sha1.is_none() && metadata.crc32.is_none() {
+            return Ok(None);
+        }
+        // OpenVGDB stores hashes as uppercase hex
+        let sha1 = metadata.sha1.map(hex::encode_upper);
+        let crc32 = metadata.crc32.map(|c| format!("{:08X}", c));
+        let found = self
+            .conn
+            .query_row(LOOKUP_SQL, params![sha1, crc32], |row| {
+                Ok(GameInfo {
+                    title: row.get(0)?,
+                    region: row.get(1)?,
+                    release_date: row
+                        .get::<_, Option<String>>(2)?
+                        .as_deref()
+                        .and_then(parse_date),
+                })
+            })
+            .optional()?;
+        Ok(found)
+    }
+}
+
+/// Parse the date formats OpenVGDB uses, e.g. "Sep 13, 1985", to
+/// YYYY-MM-DD. Partial dates like "1985" give None.
+fn parse_date(text: &str) -> Option<String> {
+    ["%b %d, %Y", "%B %d, %Y", "%Y-%m-%d"]
+        .iter()
+        .find_map(|format| chrono::NaiveDate::parse_from_str(text.trim(), format).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rom::RomType;
+
+    fn make_metadata(sha1: Option<[u8; 20]>, crc32: Option<u32>) -> RomMetadata {
+        RomMetadata {
+            rom_type: RomType::Nes,
+            sha256: [0; 32],
+            sha1,
+            crc32,
+            content_size: None,
+            filename: None,
+            nes_header: None,
+            source_file_header: None,
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("openvgdb.sqlite");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE ROMs (romID INTEGER PRIMARY KEY, regionID INTEGER, romHashCRC TEXT, romHashSHA1 TEXT);
+             CREATE TABLE RELEASES (releaseID INTEGER PRIMARY KEY, romID INTEGER, releaseTitleName TEXT, releaseDate TEXT);
+             CREATE TABLE REGIONS (regionID INTEGER PRIMARY KEY, regionName TEXT);
+             INSERT INTO REGIONS VALUES (1, 'USA'), (2, 'Japan');
+             INSERT INTO ROMs VALUES (1, 1, '3337EC46', 'FACEE9C577A5262DBE33AC4930BB0B58C8C037F7');
+             INSERT INTO ROMs VALUES (2, 2, 'ABCDEF01', NULL);
+             INSERT INTO RELEASES VALUES (1, 1, 'Super Mario Bros.', 'Sep 13, 1985');
+             INSERT INTO RELEASES VALUES (2, 2, 'Some Game', '1987');",
+        )
+        .unwrap();
+        drop(conn);
+
+        let db = GameDb::open(&path).unwrap();
+        let sha1 = hex::decode("facee9c577a5262dbe33ac4930bb0b58c8c037f7").unwrap();
+        let smb = db
+            .lookup(&make_metadata(sha1.try_into().ok(), Some(0)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            smb,
+            GameInfo {
+                title: "Super Mario Bros.".to_string(),
+                region: Some("USA".to_string()),
+                release_date: Some("1985-09-13".to_string()),
+            }
+        );
+
+        // CRC32 alone is enough; a bare year isn't a release date
+        let by_crc = db
+            .lookup(&make_metadata(None, Some(0xABCDEF01)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(by_crc.title, "Some Game");
+        assert_eq!(by_crc.region.as_deref(), Some("Japan"));
+        assert_eq!(by_crc.release_date, None);
+
+        assert!(db.lookup(&make_metadata(None, Some(1))).unwrap().is_none());
+        assert!(db.lookup(&make_metadata(None, None)).unwrap().is_none());
+
+        // Other SQLite databases are refused
+        let other = temp_dir.path().join("other.sqlite");
+        Connection::open(&other)
+            .unwrap()
+            .execute_batch("CREATE TABLE t (x)")
+            .unwrap();
+        assert!(GameDb::open(&other).is_err());
+    }
+}
@@ -4,10 +4,13 @@ use petgraph::visit::EdgeRef;
 use std::collections::{HashMap, HashSet, VecDeque};
 
 use crate::rom::RomType;
+use crate::timings::{self, Phase};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RomNode {
     pub db_id: i64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
     pub sha256: [u8; 32],
     pub filename: Option<String>,
     pub title: String,
@@ -20,6 +23,10 @@ pub struct DiffEdge {
     pub db_id: i64,
     pub diff_path: String,
     pub diff_size: i64,
+    /// What changed between the two ROMs
+    pub note: Option<String>,
+    /// Size of the ROM data the diff builds, if known
+    pub output_size: Option<i64>,
 }
 
 /// A step in a path from source to target node.
@@ -30,6 +37,7 @@ pub struct PathStep {
     pub edge: Option<DiffEdge>,
 }
 
+#[derive(Clone)]
 pub struct RomGraph {
     graph: StableGraph<RomNode, DiffEdge>,
     hash_to_node: HashMap<[u8; 32], NodeIndex>,
@@ -124,7 +132,13 @@ impl RomGraph {
 
     /// Find shortest path from source to target using BFS.
     /// Returns None if no path exists.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        level = "debug",
+        skip(self),
+        fields(source = source.index(), target = target.index()),
+    ))]
     pub fn find_path(&self, source: NodeIndex, target: NodeIndex) -> Option<Vec<PathStep>> {
+        let _span = timings::span(Phase::PathFinding);
         if source == target {
             return Some(vec![PathStep {
                 node_idx: source,
@@ -145,11 +159,20 @@ impl RomGraph {
                 }
                 visited.insert(neighbor, (current, edge_ref.weight().clone()));
                 if neighbor == target {
-                    return Some(self.reconstruct_path(source, target, &visited));
+                    let path = self.reconstruct_path(source, target, &visited);
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(
+                        steps = path.len() - 1,
+                        visited = visited.len(),
+                        "path found"
+                    );
+                    return Some(path);
                 }
                 queue.push_back(neighbor);
             }
         }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(visited = visited.len(), "no path");
         None
     }
 
@@ -234,6 +257,8 @@ mod tests {
             db_id,
             diff_path: diff_path.to_string(),
             diff_size: 100,
+            note: None,
+            output_size: None,
         }
     }
 
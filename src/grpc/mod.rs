@@ -0,0 +1,671 @@
+//! gRPC service over a library (`dromos serve --grpc`), for integrators who
+//! want a typed contract: the `dromos.v1.Dromos` service in
+//! `proto/dromos.proto`. It covers the same ground as the HTTP API in
+//! [`crate::server`], with builds and imports streaming their progress.
+//!
+//! | Method | Returns |
+//! |---|---|
+//! | `GetStatus` | node and link counts, last export and import |
+//! | `ListNodes` | nodes matching a `search` query (all for an empty one) |
+//! | `GetNode` | one node, by hash or unique prefix, with its links |
+//! | `UpdateNode` | replaces the node's metadata, then the node |
+//! | `RemoveNode` | removes the node, its links, and its attachments |
+//! | `Build` | a stream of progress for each diff applied, the built file's name and size, then its contents in chunks |
+//! | `Import` | a stream of progress for each node and diff, then the counts of what was imported |
+//!
+//! As with the HTTP server, calls must be addressed to localhost unless
+//! [`Listener`] is bound beyond this machine, when they must carry its token
+//! as `authorization: Bearer <token>` metadata instead.
+//!
+//! Failed calls carry [`DromosError::code`] in their `dromos-code`
+//! metadata. Unlike the HTTP server, calls are handled concurrently, though
+//! the library still runs one operation at a time (see
+//! [`AsyncStorageManager`]). Enabled with the `grpc` feature.
+
+pub mod proto;
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::path::PathBuf;
+
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::codegen::{Body, BoxFuture, Context, Future, Poll, Service, StdError, http};
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Request, Response, Status};
+use tonic_prost::ProstCodec;
+
+use crate::cancel::CancelToken;
+use crate::db::{NodeFilter, NodeMetadata};
+use crate::error::{DromosError, Result};
+use crate::exchange::ConflictResolution;
+use crate::progress::{self, ProgressSink};
+use crate::rom::format_hash;
+use crate::server;
+use crate::storage::AsyncStorageManager;
+
+use proto::{build_event, import_event};
+
+/// Full name of the service, which prefixes each method's path.
+pub const SERVICE_NAME: &str = "dromos.v1.Dromos";
+
+/// Size of the pieces a built ROM is streamed in.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Stream messages buffered before a slow client holds up the work.
+const STREAM_BUFFER: usize = 16;
+
+type Reply<T> = std::result::Result<Response<T>, Status>;
+type EventStream<T> = ReceiverStream<std::result::Result<T, Status>>;
+
+/// A socket for [`serve`] to answer calls on.
+pub struct Listener {
+    listener: TcpListener,
+    access: server::Access,
+}
+
+impl Listener {
+    /// Listen on `address`, e.g. `127.0.0.1:50051`. Unless that's on this
+    /// machine only, calls must carry a new random token.
+    pub async fn bind(address: &str) -> Result<Listener> {
+        let listener = TcpListener::bind(address)
+            .await
+            .map_err(|e| DromosError::Io(std::io::Error::other(format!("{}: {}", address, e))))?;
+        let access = server::Access::for_address(listener.local_addr().ok())?;
+        Ok(Listener { listener, access })
+    }
+
+    /// The token calls must carry, if any.
+    pub fn token(&self) -> Option<&str> {
+        self.access.token()
+    }
+
+    /// The address the server listens on, e.g. `127.0.0.1:50051`.
+    pub fn address(&self) -> Result<String> {
+        Ok(self.listener.local_addr()?.to_string())
+    }
+}
+
+/// Answer calls on `listener` until `cancel` stops the server.
+pub async fn serve(
+    library: AsyncStorageManager,
+    listener: Listener,
+    cancel: CancelToken,
+) -> Result<()> {
+    let stopped = async move {
+        while cancel.check().is_ok() {
+            tokio::time::sleep(server::POLL_INTERVAL).await;
+        }
+    };
+    let service = DromosServer {
+        library,
+        access: Some(listener.access),
+    };
+    tonic::transport::Server::builder()
+        .add_service(service)
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener.listener), stopped)
+        .await
+        .map_err(|e| DromosError::Io(std::io::Error::other(e)))
+}
+
+/// The `Dromos` service, for adding to a [`tonic::transport::Server`]
+/// alongside an application's own services, which then decides who may
+/// call it.
+#[derive(Clone)]
+pub struct DromosServer {
+    library: AsyncStorageManager,
+    /// Checked before each call, when served by [`serve`]
+    access: Option<server::Access>,
+}
+
+impl DromosServer {
+    pub fn new(library: AsyncStorageManager) -> Self {
+        DromosServer {
+            library,
+            access: None,
+        }
+    }
+}
+
+impl tonic::server::NamedService for DromosServer {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+impl<B> Service<http::Request<B>> for DromosServer
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<std::result::Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        if let Some(status) = self
+            .access
+            .as_ref()
+            .and_then(|access| denied(access, &request))
+        {
+            return Box::pin(async { Ok(status.into_http()) });
+        }
+        let library = self.library.clone();
+        let method = request
+            .uri()
+            .path()
+            .strip_prefix('/')
+            .and_then(|path| path.strip_prefix(SERVICE_NAME))
+            .and_then(|path| path.strip_prefix('/'))
+            .unwrap_or_default();
+        match method {
+            "GetStatus" => unary(library, request, get_status),
+            "ListNodes" => unary(library, request, list_nodes),
+            "GetNode" => unary(library, request, get_node),
+            "UpdateNode" => unary(library, request, update_node),
+            "RemoveNode" => unary(library, request, remove_node),
+            "Build" => streaming(library, request, build),
+            "Import" => streaming(library, request, import),
+            _ => Box::pin(async { Ok(Status::unimplemented("no such method").into_http()) }),
+        }
+    }
+}
+
+/// The status turning `request` away, if `access` doesn't allow it.
+fn denied<B>(access: &server::Access, request: &http::Request<B>) -> Option<Status> {
+    let header = |name: &str| {
+        let value = request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        // HTTP/2 names the host in the request's authority instead
+        match value {
+            None if name.eq_ignore_ascii_case("Host") => request
+                .uri()
+                .authority()
+                .map(|authority| authority.to_string()),
+            value => value,
+        }
+    };
+    let denial = access.denial(request.uri().query().unwrap_or_default(), &header)?;
+    let code = match denial.status {
+        401 => Code::Unauthenticated,
+        _ => Code::PermissionDenied,
+    };
+    let mut status = Status::new(code, denial.message);
+    status
+        .metadata_mut()
+        .insert("dromos-code", MetadataValue::from_static(denial.code));
+    Some(status)
+}
+
+/// Decode a request, answer it with `handler`, and encode the reply.
+fn unary<B, M, R, F, Fut>(
+    library: AsyncStorageManager,
+    request: http::Request<B>,
+    handler: F,
+) -> BoxFuture<http::Response<tonic::body::Body>, Infallible>
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+    M: prost::Message + Default + Send + 'static,
+    R: prost::Message + Send + 'static,
+    F: Fn(AsyncStorageManager, Request<M>) -> Fut + Send + 'static,
+    Fut: Future<Output = Reply<R>> + Send + 'static,
+{
+    Box::pin(async move {
+        let service = tower::service_fn(move |request| handler(library.clone(), request));
+        let mut grpc = tonic::server::Grpc::new(ProstCodec::default());
+        Ok(grpc.unary(service, request).await)
+    })
+}
+
+/// Like [`unary`], for methods that reply with a stream of messages.
+fn streaming<B, M, R, F, Fut>(
+    library: AsyncStorageManager,
+    request: http::Request<B>,
+    handler: F,
+) -> BoxFuture<http::Response<tonic::body::Body>, Infallible>
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+    M: prost::Message + Default + Send + 'static,
+    R: prost::Message + Send + 'static,
+    F: Fn(AsyncStorageManager, Request<M>) -> Fut + Send + 'static,
+    Fut: Future<Output = Reply<EventStream<R>>> + Send + 'static,
+{
+    Box::pin(async move {
+        let service = tower::service_fn(move |request| handler(library.clone(), request));
+        let mut grpc = tonic::server::Grpc::new(ProstCodec::default());
+        Ok(grpc.server_streaming(service, request).await)
+    })
+}
+
+async fn get_status(
+    library: AsyncStorageManager,
+    _request: Request<proto::GetStatusRequest>,
+) -> Reply<proto::LibraryStatus> {
+    let status = library.status(0).await.map_err(status_from)?;
+    Ok(Response::new(proto::LibraryStatus {
+        nodes: status.node_count as u64,
+        links: status.diff_count as u64,
+        diff_bytes: status.total_diff_bytes,
+        last_export_at: status.last_export_at,
+        last_import_at: status.last_import_at,
+    }))
+}
+
+async fn list_nodes(
+    library: AsyncStorageManager,
+    request: Request<proto::ListNodesRequest>,
+) -> Reply<proto::ListNodesResponse> {
+    let filter =
+        NodeFilter::parse(&request.into_inner().query).map_err(Status::invalid_argument)?;
+    let rows = library.search(filter).await.map_err(status_from)?;
+    Ok(Response::new(proto::ListNodesResponse {
+        nodes: rows.iter().map(proto::Node::from).collect(),
+    }))
+}
+
+async fn get_node(
+    library: AsyncStorageManager,
+    request: Request<proto::GetNodeRequest>,
+) -> Reply<proto::NodeDetail> {
+    let sha256 = resolve(&library, request.into_inner().hash).await?;
+    node_detail(&library, sha256).await.map(Response::new)
+}
+
+async fn update_node(
+    library: AsyncStorageManager,
+    request: Request<proto::UpdateNodeRequest>,
+) -> Reply<proto::Node> {
+    let request = request.into_inner();
+    let metadata = request
+        .metadata
+        .ok_or_else(|| Status::invalid_argument("metadata is required"))?;
+    let metadata = NodeMetadata::try_from(metadata).map_err(Status::invalid_argument)?;
+    let sha256 = resolve(&library, request.hash).await?;
+    library
+        .update_node_metadata(sha256, metadata)
+        .await
+        .map_err(status_from)?;
+    let detail = node_detail(&library, sha256).await?;
+    Ok(Response::new(detail.node.unwrap_or_default()))
+}
+
+async fn remove_node(
+    library: AsyncStorageManager,
+    request: Request<proto::RemoveNodeRequest>,
+) -> Reply<proto::RemoveNodeResponse> {
+    let sha256 = resolve(&library, request.into_inner().hash).await?;
+    let removed = library.remove_node(sha256).await.map_err(status_from)?;
+    Ok(Response::new(proto::RemoveNodeResponse {
+        title: removed.title,
+        links_removed: removed.edges_removed as u64,
+        attachments_removed: removed.attachments_removed as u64,
+    }))
+}
+
+async fn build(
+    library: AsyncStorageManager,
+    request: Request<proto::BuildRequest>,
+) -> Reply<EventStream<proto::BuildEvent>> {
+    let request = request.into_inner();
+    if request.source.is_empty() {
+        return Err(Status::invalid_argument("send the source ROM"));
+    }
+    let target = resolve(&library, request.hash).await?;
+
+    // Builds read their source from a file
+    let source = tempfile::Builder::new()
+        .prefix("dromos-grpc-")
+        .suffix(".nes")
+        .tempfile()
+        .map_err(|e| status_from(DromosError::Io(e)))?;
+    tokio::fs::write(source.path(), &request.source)
+        .await
+        .map_err(|e| status_from(DromosError::file(source.path())(e)))?;
+    // Removed when the build is done with it
+    let source = source.into_temp_path();
+
+    let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+    tokio::spawn(async move {
+        let cancel = CancelToken::new();
+        let progress = forward_progress(sender.clone(), &cancel, |progress| proto::BuildEvent {
+            event: Some(build_event::Event::Progress(progress)),
+        });
+        let built = library
+            .build_rom(source.to_path_buf(), target, progress, cancel)
+            .await;
+        drop(source);
+        let built = match built {
+            Ok(built) => built,
+            Err(e) => {
+                let _ = sender.send(Err(status_from(e))).await;
+                return;
+            }
+        };
+
+        let target = proto::Node::from(&built.target_row);
+        let steps = built.steps as u32;
+        let (filename, contents) = server::built_file(built);
+        let event = |event| Ok(proto::BuildEvent { event: Some(event) });
+        let header = build_event::Event::Built(proto::BuiltFile {
+            target: Some(target),
+            filename,
+            size: contents.len() as u64,
+            steps,
+        });
+        if sender.send(event(header)).await.is_err() {
+            return;
+        }
+        for chunk in contents.chunks(CHUNK_BYTES) {
+            let data = build_event::Event::Data(chunk.to_vec());
+            if sender.send(event(data)).await.is_err() {
+                return;
+            }
+        }
+    });
+    Ok(Response::new(ReceiverStream::new(receiver)))
+}
+
+async fn import(
+    library: AsyncStorageManager,
+    request: Request<proto::ImportRequest>,
+) -> Reply<EventStream<proto::ImportEvent>> {
+    let request = request.into_inner();
+    let resolution = match proto::ConflictPolicy::try_from(request.conflicts) {
+        Ok(proto::ConflictPolicy::Keep) => ConflictResolution::KeepLocal,
+        Ok(proto::ConflictPolicy::Import) => ConflictResolution::Import,
+        Err(_) => return Err(Status::invalid_argument("unknown conflict policy")),
+    };
+    let folder = PathBuf::from(request.folder);
+    let (manifest, conflicts) = library
+        .analyze_import(folder.clone())
+        .await
+        .map_err(status_from)?;
+    let resolutions: HashMap<String, ConflictResolution> = conflicts
+        .into_iter()
+        .map(|conflict| (conflict.sha256, resolution.clone()))
+        .collect();
+
+    let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+    tokio::spawn(async move {
+        let cancel = CancelToken::new();
+        let progress = forward_progress(sender.clone(), &cancel, |progress| proto::ImportEvent {
+            event: Some(import_event::Event::Progress(progress)),
+        });
+        let result = library
+            .execute_import(folder, manifest, resolutions, progress, cancel)
+            .await
+            .map(|result| proto::ImportEvent {
+                event: Some(import_event::Event::Result((&result).into())),
+            })
+            .map_err(status_from);
+        let _ = sender.send(result).await;
+    });
+    Ok(Response::new(ReceiverStream::new(receiver)))
+}
+
+/// A progress sink sending each update down `sender` as the message `wrap`
+/// makes of it. If the client has gone away, it cancels the operation.
+fn forward_progress<T: Send + 'static>(
+    sender: mpsc::Sender<std::result::Result<T, Status>>,
+    cancel: &CancelToken,
+    wrap: impl Fn(proto::Progress) -> T + Send + 'static,
+) -> impl ProgressSink + Send + 'static {
+    let cancel = cancel.clone();
+    // Library operations report progress from the blocking thread pool
+    move |progress: &progress::Progress| {
+        if sender.blocking_send(Ok(wrap(progress.into()))).is_err() {
+            cancel.cancel();
+        }
+    }
+}
+
+/// The node a hash or unique prefix refers to.
+async fn resolve(
+    library: &AsyncStorageManager,
+    prefix: String,
+) -> std::result::Result<[u8; 32], Status> {
+    library
+        .with(move |m| server::resolve(m, &prefix))
+        .await
+        .map_err(status_from)
+}
+
+async fn node_detail(
+    library: &AsyncStorageManager,
+    sha256: [u8; 32],
+) -> std::result::Result<proto::NodeDetail, Status> {
+    library
+        .with(move |m| {
+            let row = m
+                .get_node_row_by_hash(&sha256)?
+                .ok_or_else(|| DromosError::RomNotFound {
+                    hash: format_hash(&sha256),
+                })?;
+            let links = m
+                .get_neighbors(&sha256)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(node, edge)| proto::Link {
+                    sha256: format_hash(&node.sha256),
+                    title: node.title.clone(),
+                    version: node.version.clone(),
+                    diff_size: edge.diff_size as u64,
+                    note: edge.note.clone(),
+                })
+                .collect();
+            Ok(proto::NodeDetail {
+                node: Some(proto::Node::from(&row)),
+                links,
+            })
+        })
+        .await
+        .map_err(status_from)
+}
+
+/// The gRPC status for a library error, with its code in `dromos-code`.
+fn status_from(error: DromosError) -> Status {
+    let code = match &error {
+        DromosError::RomNotFound { .. }
+        | DromosError::FileNotFound { .. }
+        | DromosError::NoPath { .. } => Code::NotFound,
+        DromosError::FileAccess { source, .. } if source.kind() == std::io::ErrorKind::NotFound => {
+            Code::NotFound
+        }
+        DromosError::RomNotFoundAmbiguous { .. }
+        | DromosError::InvalidHashFormat { .. }
+        | DromosError::Import(_)
+        | DromosError::InvalidMetadata(_)
+        | DromosError::InvalidNesFile { .. }
+        | DromosError::UnsupportedRomType { .. } => Code::InvalidArgument,
+        DromosError::ReadOnly => Code::PermissionDenied,
+        DromosError::Cancelled => Code::Cancelled,
+        _ => Code::Internal,
+    };
+    let mut status = Status::new(code, error.to_string());
+    status
+        .metadata_mut()
+        .insert("dromos-code", MetadataValue::from_static(error.code()));
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::NoProgress;
+    use crate::storage::StorageManager;
+    use crate::test_support::write_rom;
+    use tokio_stream::StreamExt;
+
+    #[test]
+    fn test_grpc_methods() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let mut storage = StorageManager::builder()
+            .in_memory()
+            .diffs_dir(dir.join("diffs"))
+            .open()
+            .unwrap();
+        let (path_a, path_b) = (dir.join("a.nes"), dir.join("b.nes"));
+        let rom_a = write_rom(&path_a, 0xEA);
+        let rom_b = write_rom(&path_b, 0x60);
+        for (path, title) in [(&path_a, "Alpha"), (&path_b, "Beta")] {
+            let metadata = NodeMetadata {
+                title: title.to_string(),
+                ..Default::default()
+            };
+            storage.add_node(path, &metadata).unwrap();
+        }
+        storage
+            .link_nodes(&path_a, &path_b, None, &mut NoProgress, &CancelToken::new())
+            .unwrap();
+        let hash_b = format_hash(&storage.find_nodes_by_title("Beta")[0].sha256);
+        let library = AsyncStorageManager::from(storage);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let status = get_status(library.clone(), Request::new(proto::GetStatusRequest {}))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!((status.nodes, status.links), (2, 2));
+
+            let query = proto::ListNodesRequest {
+                query: "beta".to_string(),
+            };
+            let listed = list_nodes(library.clone(), Request::new(query))
+                .await
+                .unwrap();
+            assert_eq!(listed.into_inner().nodes[0].sha256, hash_b);
+
+            let request = proto::GetNodeRequest {
+                hash: hash_b[..12].to_string(),
+            };
+            let detail = get_node(library.clone(), Request::new(request))
+                .await
+                .unwrap();
+            assert_eq!(detail.into_inner().links[0].title, "Alpha");
+
+            let request = proto::GetNodeRequest {
+                hash: "ffffffff".to_string(),
+            };
+            let missing = get_node(library.clone(), Request::new(request))
+                .await
+                .unwrap_err();
+            assert_eq!(missing.code(), Code::NotFound);
+            assert_eq!(
+                missing.metadata().get("dromos-code").unwrap(),
+                "rom_not_found"
+            );
+
+            let mut metadata = proto::Metadata {
+                title: " ".to_string(),
+                ..Default::default()
+            };
+            let request = |metadata: &proto::Metadata| proto::UpdateNodeRequest {
+                hash: hash_b.clone(),
+                metadata: Some(metadata.clone()),
+            };
+            let blank = update_node(library.clone(), Request::new(request(&metadata))).await;
+            assert_eq!(blank.unwrap_err().code(), Code::InvalidArgument);
+            metadata.title = "Beta DX".to_string();
+            metadata.rating = Some(6);
+            let bad_rating = update_node(library.clone(), Request::new(request(&metadata))).await;
+            assert_eq!(bad_rating.unwrap_err().code(), Code::InvalidArgument);
+            metadata.rating = Some(3);
+            metadata.release_date = Some("2024-1-5".to_string());
+            let updated = update_node(library.clone(), Request::new(request(&metadata)))
+                .await
+                .unwrap()
+                .into_inner();
+            let metadata = updated.metadata.unwrap();
+            assert_eq!(metadata.title, "Beta DX");
+            assert_eq!(metadata.release_date.as_deref(), Some("2024-01-05"));
+            assert_eq!(metadata.rating, Some(3));
+            assert_eq!(metadata.play_status, "unplayed");
+
+            let request = proto::BuildRequest {
+                hash: hash_b.clone(),
+                source: rom_a,
+            };
+            let mut events = build(library.clone(), Request::new(request))
+                .await
+                .unwrap()
+                .into_inner();
+            let (mut stages, mut built, mut contents) = (Vec::new(), None, Vec::new());
+            while let Some(event) = events.next().await {
+                match event.unwrap().event.unwrap() {
+                    build_event::Event::Progress(p) => stages.push(p.stage),
+                    build_event::Event::Built(file) => built = Some(file),
+                    build_event::Event::Data(data) => contents.extend(data),
+                }
+            }
+            assert!(stages.iter().any(|stage| stage == "patching"));
+            let built = built.unwrap();
+            assert_eq!(built.filename, "Beta DX.nes");
+            assert_eq!(built.size, rom_b.len() as u64);
+            assert_eq!(contents, rom_b);
+
+            let request = proto::ImportRequest {
+                folder: dir.join("nowhere").display().to_string(),
+                conflicts: 0,
+            };
+            assert!(
+                import(library.clone(), Request::new(request))
+                    .await
+                    .is_err()
+            );
+
+            let request = proto::RemoveNodeRequest { hash: hash_b };
+            let removed = remove_node(library.clone(), Request::new(request))
+                .await
+                .unwrap();
+            assert_eq!(removed.into_inner().title, "Beta DX");
+        });
+    }
+
+    #[test]
+    fn test_grpc_access() {
+        let call = |access: &server::Access, uri: &str, headers: &[(&str, &str)]| {
+            let mut request = http::Request::builder().uri(uri);
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            denied(access, &request.body(()).unwrap()).map(|status| status.code())
+        };
+        let remove = "/dromos.v1.Dromos/RemoveNode";
+
+        let local = server::Access::for_address(Some("127.0.0.1:50051".parse().unwrap())).unwrap();
+        assert_eq!(local.token(), None);
+        let uri = format!("http://127.0.0.1:50051{}", remove);
+        assert_eq!(call(&local, &uri, &[]), None);
+        // DNS rebinding, and pages on other sites
+        let uri = format!("http://evil.example:50051{}", remove);
+        assert_eq!(call(&local, &uri, &[]), Some(Code::PermissionDenied));
+        let uri = format!("http://localhost:50051{}", remove);
+        let cross_site = [("origin", "http://evil.example")];
+        assert_eq!(
+            call(&local, &uri, &cross_site),
+            Some(Code::PermissionDenied)
+        );
+
+        let remote = server::Access::for_address(Some("0.0.0.0:50051".parse().unwrap())).unwrap();
+        let token = remote.token().unwrap().to_string();
+        let uri = format!("http://desktop:50051{}", remove);
+        assert_eq!(call(&remote, &uri, &[]), Some(Code::Unauthenticated));
+        let wrong = [("authorization", "Bearer wrong")];
+        assert_eq!(call(&remote, &uri, &wrong), Some(Code::Unauthenticated));
+        let bearer = format!("Bearer {}", token);
+        assert_eq!(call(&remote, &uri, &[("authorization", &bearer)]), None);
+    }
+}
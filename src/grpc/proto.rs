@@ -0,0 +1,328 @@
+//! Messages of `proto/dromos.proto`, written out by hand in the form
+//! `prost-build` generates so that building needs no `protoc`. Field tags
+//! must match the `.proto` file.
+
+use crate::db::{MAX_RATING, NodeMetadata, NodeRow, PlayStatus, check_rating};
+use crate::exchange::ImportResult as LibraryImportResult;
+use crate::rom::{Language, Region, format_hash};
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct GetStatusRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LibraryStatus {
+    #[prost(uint64, tag = "1")]
+    pub nodes: u64,
+    #[prost(uint64, tag = "2")]
+    pub links: u64,
+    #[prost(int64, tag = "3")]
+    pub diff_bytes: i64,
+    #[prost(string, optional, tag = "4")]
+    pub last_export_at: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub last_import_at: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListNodesRequest {
+    #[prost(string, tag = "1")]
+    pub query: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListNodesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Metadata {
+    #[prost(string, tag = "1")]
+    pub title: String,
+    #[prost(string, optional, tag = "2")]
+    pub version: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub source_url: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub release_date: Option<String>,
+    #[prost(string, repeated, tag = "5")]
+    pub tags: Vec<String>,
+    #[prost(string, optional, tag = "6")]
+    pub description: Option<String>,
+    #[prost(uint32, optional, tag = "7")]
+    pub rating: Option<u32>,
+    #[prost(string, tag = "8")]
+    pub play_status: String,
+    #[prost(string, repeated, tag = "9")]
+    pub regions: Vec<String>,
+    #[prost(string, repeated, tag = "10")]
+    pub languages: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Node {
+    #[prost(string, tag = "1")]
+    pub sha256: String,
+    #[prost(string, optional, tag = "2")]
+    pub sha1: Option<String>,
+    #[prost(uint32, optional, tag = "3")]
+    pub crc32: Option<u32>,
+    #[prost(string, optional, tag = "4")]
+    pub filename: Option<String>,
+    #[prost(string, tag = "5")]
+    pub rom_type: String,
+    #[prost(message, optional, tag = "6")]
+    pub metadata: Option<Metadata>,
+    #[prost(string, tag = "7")]
+    pub created_at: String,
+    #[prost(string, optional, tag = "8")]
+    pub updated_at: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetNodeRequest {
+    #[prost(string, tag = "1")]
+    pub hash: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Link {
+    #[prost(string, tag = "1")]
+    pub sha256: String,
+    #[prost(string, tag = "2")]
+    pub title: String,
+    #[prost(string, optional, tag = "3")]
+    pub version: Option<String>,
+    #[prost(uint64, tag = "4")]
+    pub diff_size: u64,
+    #[prost(string, optional, tag = "5")]
+    pub note: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct NodeDetail {
+    #[prost(message, optional, tag = "1")]
+    pub node: Option<Node>,
+    #[prost(message, repeated, tag = "2")]
+    pub links: Vec<Link>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct UpdateNodeRequest {
+    #[prost(string, tag = "1")]
+    pub hash: String,
+    #[prost(message, optional, tag = "2")]
+    pub metadata: Option<Metadata>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RemoveNodeRequest {
+    #[prost(string, tag = "1")]
+    pub hash: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RemoveNodeResponse {
+    #[prost(string, tag = "1")]
+    pub title: String,
+    #[prost(uint64, tag = "2")]
+    pub links_removed: u64,
+    #[prost(uint64, tag = "3")]
+    pub attachments_removed: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Progress {
+    #[prost(string, tag = "1")]
+    pub stage: String,
+    #[prost(string, tag = "2")]
+    pub item: String,
+    #[prost(uint64, tag = "3")]
+    pub current: u64,
+    #[prost(uint64, tag = "4")]
+    pub total: u64,
+    #[prost(uint64, tag = "5")]
+    pub bytes: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BuildRequest {
+    #[prost(string, tag = "1")]
+    pub hash: String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub source: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BuiltFile {
+    #[prost(message, optional, tag = "1")]
+    pub target: Option<Node>,
+    #[prost(string, tag = "2")]
+    pub filename: String,
+    #[prost(uint64, tag = "3")]
+    pub size: u64,
+    #[prost(uint32, tag = "4")]
+    pub steps: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BuildEvent {
+    #[prost(oneof = "build_event::Event", tags = "1, 2, 3")]
+    pub event: Option<build_event::Event>,
+}
+
+pub mod build_event {
+    // Sent a handful of times per build, so the size of `Built` doesn't matter
+    #[allow(clippy::large_enum_variant)]
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "1")]
+        Progress(super::Progress),
+        #[prost(message, tag = "2")]
+        Built(super::BuiltFile),
+        #[prost(bytes = "vec", tag = "3")]
+        Data(Vec<u8>),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum ConflictPolicy {
+    Keep = 0,
+    Import = 1,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ImportRequest {
+    #[prost(string, tag = "1")]
+    pub folder: String,
+    #[prost(enumeration = "ConflictPolicy", tag = "2")]
+    pub conflicts: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ImportResult {
+    #[prost(uint64, tag = "1")]
+    pub nodes_added: u64,
+    #[prost(uint64, tag = "2")]
+    pub nodes_skipped: u64,
+    #[prost(uint64, tag = "3")]
+    pub nodes_overwritten: u64,
+    #[prost(uint64, tag = "4")]
+    pub edges_added: u64,
+    #[prost(uint64, tag = "5")]
+    pub edges_skipped: u64,
+    #[prost(uint64, tag = "6")]
+    pub diffs_copied: u64,
+    #[prost(uint64, tag = "7")]
+    pub attachments_added: u64,
+    #[prost(uint64, tag = "8")]
+    pub attachments_skipped: u64,
+    #[prost(string, repeated, tag = "9")]
+    pub added: Vec<String>,
+    #[prost(string, repeated, tag = "10")]
+    pub overwritten: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ImportEvent {
+    #[prost(oneof = "import_event::Event", tags = "1, 2")]
+    pub event: Option<import_event::Event>,
+}
+
+pub mod import_event {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "1")]
+        Progress(super::Progress),
+        #[prost(message, tag = "2")]
+        Result(super::ImportResult),
+    }
+}
+
+impl From<&NodeRow> for Node {
+    fn from(row: &NodeRow) -> Self {
+        Node {
+            sha256: format_hash(&row.sha256),
+            sha1: row.sha1.map(hex::encode),
+            crc32: row.crc32,
+            filename: row.filename.clone(),
+            rom_type: row.rom_type.as_str().to_string(),
+            metadata: Some(Metadata {
+                title: row.title.clone(),
+                version: row.version.clone(),
+                source_url: row.source_url.clone(),
+                release_date: row.release_date.clone(),
+                tags: row.tags.clone(),
+                description: row.description.clone(),
+                rating: row.rating.map(u32::from),
+                play_status: row.play_status.to_string(),
+                regions: row.regions.iter().map(|r| r.to_string()).collect(),
+                languages: row.languages.iter().map(|l| l.to_string()).collect(),
+            }),
+            created_at: row.created_at.clone(),
+            updated_at: row.updated_at.clone(),
+        }
+    }
+}
+
+impl TryFrom<Metadata> for NodeMetadata {
+    type Error = String;
+
+    fn try_from(metadata: Metadata) -> Result<Self, Self::Error> {
+        let rating = metadata
+            .rating
+            .map(|r| {
+                u8::try_from(r)
+                    .map_err(|_| String::new())
+                    .and_then(check_rating)
+            })
+            .transpose()
+            .map_err(|_| format!("rating must be from 1 to {}", MAX_RATING))?;
+        let play_status = match metadata.play_status.as_str() {
+            "" => PlayStatus::default(),
+            status => status.parse()?,
+        };
+        Ok(NodeMetadata {
+            title: metadata.title,
+            source_url: metadata.source_url,
+            version: metadata.version,
+            release_date: metadata.release_date,
+            tags: metadata.tags,
+            description: metadata.description,
+            rating,
+            play_status,
+            regions: metadata.regions.into_iter().map(Region::from).collect(),
+            languages: metadata.languages.into_iter().map(Language::from).collect(),
+        })
+    }
+}
+
+impl From<&crate::progress::Progress<'_>> for Progress {
+    fn from(progress: &crate::progress::Progress) -> Self {
+        Progress {
+            stage: progress.stage.to_string(),
+            item: progress.item.to_string(),
+            current: progress.current as u64,
+            total: progress.total as u64,
+            bytes: progress.bytes,
+        }
+    }
+}
+
+impl From<&LibraryImportResult> for ImportResult {
+    fn from(result: &LibraryImportResult) -> Self {
+        ImportResult {
+            nodes_added: result.nodes_added as u64,
+            nodes_skipped: result.nodes_skipped as u64,
+            nodes_overwritten: result.nodes_overwritten as u64,
+            edges_added: result.edges_added as u64,
+            edges_skipped: result.edges_skipped as u64,
+            diffs_copied: result.diffs_copied as u64,
+            attachments_added: result.attachments_added as u64,
+            attachments_skipped: result.attachments_skipped as u64,
+            added: result.added.iter().map(format_hash).collect(),
+            overwritten: result.overwritten.iter().map(format_hash).collect(),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Finding other libraries on the local network. `dromos share` serves part
+//! of a library (see [`server::share`](crate::server::share)) and advertises
+//! it over mDNS as a `_dromos._tcp` service; `dromos fetch` browses for
+//! those services and imports from one as `import http://...` would.
+//!
+//! Each advertisement carries TXT properties describing the share: what was
+//! selected (`description`), how many ROMs it holds (`nodes`), and the
+//! sharing library's data revision (`revision`).
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::Serialize;
+
+use crate::cancel::CancelToken;
+use crate::error::{DromosError, Result};
+use crate::server::POLL_INTERVAL;
+
+/// The mDNS service type shares are advertised as.
+pub const SERVICE_TYPE: &str = "_dromos._tcp.local.";
+
+/// How long `fetch` listens for shares to answer.
+pub const BROWSE_WAIT: Duration = Duration::from_secs(3);
+
+/// A share found on the network.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Peer {
+    /// Instance name, the sharing machine's host name unless `share --name`
+    /// gave another
+    pub name: String,
+    /// `http://address:port` of its server, for [`Remote::connect`](crate::sync::Remote::connect)
+    pub url: String,
+    /// What it shares, e.g. `tag:hack`
+    pub description: String,
+    /// ROMs it shares
+    pub nodes: usize,
+    /// Data revision of the sharing library
+    pub revision: Option<u32>,
+}
+
+/// A share being advertised, until dropped.
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+/// What a share is advertised with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShareInfo {
+    pub name: String,
+    pub port: u16,
+    pub description: String,
+    pub nodes: usize,
+    pub revision: u32,
+}
+
+impl Advertisement {
+    /// Announce `share` on every network interface, answering queries for
+    /// it until dropped.
+    pub fn start(share: &ShareInfo) -> Result<Advertisement> {
+        let daemon = ServiceDaemon::new().map_err(mdns_error)?;
+        let host = format!("{}.local.", host_name());
+        let properties = [
+            ("description", share.description.clone()),
+            ("nodes", share.nodes.to_string()),
+            ("revision", share.revision.to_string()),
+        ];
+        let info = ServiceInfo::new(
+            SERVICE_TYPE,
+            &share.name,
+            &host,
+            "",
+            share.port,
+            &properties[..],
+        )
+        .map_err(mdns_error)?
+        .enable_addr_auto();
+        let fullname = info.get_fullname().to_string();
+        daemon.register(info).map_err(mdns_error)?;
+        Ok(Advertisement { daemon, fullname })
+    }
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        // Say goodbye so browsers drop the share now rather than at expiry
+        if let Ok(done) = self.daemon.unregister(&self.fullname) {
+            let _ = done.recv_timeout(Duration::from_secs(1));
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// This machine's name, without any `.local` suffix, as shares are named
+/// by default.
+pub fn host_name() -> String {
+    let name = gethostname::gethostname().to_string_lossy().into_owned();
+    let name = name.trim_end_matches('.').trim_end_matches(".local");
+    if name.is_empty() {
+        "dromos".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// The shares that answer within `wait`, sorted by name. Stops early,
+/// returning [`DromosError::Cancelled`], if `cancel` says so.
+pub fn browse(wait: Duration, cancel: &CancelToken) -> Result<Vec<Peer>> {
+    let daemon = ServiceDaemon::new().map_err(mdns_error)?;
+    let events = daemon.browse(SERVICE_TYPE).map_err(mdns_error)?;
+    let deadline = Instant::now() + wait;
+    let mut peers = BTreeMap::new();
+    let result = loop {
+        if let Err(e) = cancel.check() {
+            break Err(e);
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break Ok(());
+        }
+        match events.recv_timeout(left.min(POLL_INTERVAL)) {
+            Ok(ServiceEvent::ServiceResolved(info)) => {
+                if let Some(peer) = peer(&info) {
+                    peers.insert(info.get_fullname().to_string(), peer);
+                }
+            }
+            Ok(ServiceEvent::ServiceRemoved(_, fullname)) => {
+                peers.remove(&fullname);
+            }
+            _ => {}
+        }
+    };
+    let _ = daemon.shutdown();
+    result?;
+    let mut peers: Vec<Peer> = peers.into_values().collect();
+    peers.sort_by_key(|peer| peer.name.to_lowercase());
+    Ok(peers)
+}
+
+/// The share `info` advertises, if it has an address to reach it at.
+fn peer(info: &ServiceInfo) -> Option<Peer> {
+    let suffix = format!(".{}", SERVICE_TYPE);
+    let fullname = info.get_fullname();
+    let name = fullname.strip_suffix(&suffix).unwrap_or(fullname);
+    // IPv4 first, since link-local IPv6 addresses need an interface to work
+    let address = info
+        .get_addresses()
+        .iter()
+        .min_by_key(|ip| (ip.is_ipv6(), **ip))?;
+    let url = match address {
+        IpAddr::V4(ip) => format!("http://{}:{}", ip, info.get_port()),
+        IpAddr::V6(ip) => format!("http://[{}]:{}", ip, info.get_port()),
+    };
+    let property = |key| info.get_property_val_str(key);
+    Some(Peer {
+        name: name.to_string(),
+        url,
+        description: property("description").unwrap_or_default().to_string(),
+        nodes: property("nodes").and_then(|n| n.parse().ok()).unwrap_or(0),
+        revision: property("revision").and_then(|n| n.parse().ok()),
+    })
+}
+
+fn mdns_error(e: mdns_sd::Error) -> DromosError {
+    DromosError::Io(std::io::Error::other(format!("mDNS: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_from_service() {
+        let properties = [
+            ("description", "tag:hack"),
+            ("nodes", "3"),
+            ("revision", "7"),
+        ];
+        let info = ServiceInfo::new(
+            SERVICE_TYPE,
+            "den",
+            "den.local.",
+            "fe80::1,192.168.1.5",
+            8080,
+            &properties[..],
+        )
+        .unwrap();
+        assert_eq!(
+            peer(&info),
+            Some(Peer {
+                name: "den".to_string(),
+                url: "http://192.168.1.5:8080".to_string(),
+                description: "tag:hack".to_string(),
+                nodes: 3,
+                revision: Some(7),
+            })
+        );
+
+        let info = ServiceInfo::new(SERVICE_TYPE, "den", "den.local.", "", 8080, None).unwrap();
+        assert_eq!(peer(&info), None);
+    }
+}
@@ -1,11 +1,64 @@
+#[cfg(feature = "native")]
+pub mod cache;
+pub mod cancel;
+#[cfg(feature = "native")]
+pub mod checksums;
+#[cfg(feature = "native")]
 pub mod cli;
+#[cfg(feature = "native")]
 pub mod config;
+#[cfg(feature = "native")]
+pub mod csv;
+#[cfg(feature = "native")]
+pub mod dat;
+#[cfg(feature = "native")]
 pub mod db;
+#[cfg(feature = "native")]
+pub mod dedupe;
 pub mod diff;
 pub mod error;
 pub mod exchange;
+#[cfg(feature = "native")]
+pub mod gamedb;
+#[cfg(feature = "native")]
 pub mod graph;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "lan")]
+pub mod lan;
+#[cfg(feature = "native")]
+pub mod logfile;
+#[cfg(feature = "native")]
+pub mod plugin;
+pub mod progress;
 pub mod rom;
+#[cfg(feature = "native")]
+pub mod sdcard;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "native")]
+pub mod site;
+#[cfg(feature = "native")]
 pub mod storage;
+#[cfg(feature = "server")]
+pub mod sync;
+#[cfg(all(test, feature = "native"))]
+mod test_support;
+pub mod timings;
+#[cfg(feature = "server")]
+pub mod webhook;
+#[cfg(feature = "serde")]
+mod wire;
 
 pub use error::{DromosError, Result};
+
+use git_version::git_version;
+
+/// Version string from git tags (falls back to the crate version).
+pub const VERSION: &str = git_version!(
+    args = ["--tags", "--always", "--dirty=-modified"],
+    fallback = env!("CARGO_PKG_VERSION")
+);
+
+/// UTC time the binary was built, set by build.rs.
+pub const BUILD_TIME: &str = env!("BUILD_TIMESTAMP");
@@ -0,0 +1,116 @@
+//! The log file `--log` (or `log = on` in `dromos.conf`) keeps in the data
+//! folder, so problems can be looked into after the fact.
+//!
+//! The file is rotated by size: once writing to `dromos.log` would take it
+//! past the limit, it becomes `dromos.log.1`, an older `dromos.log.1`
+//! becomes `dromos.log.2`, and so on, keeping [`KEPT_LOGS`] old files.
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Size a log file is rotated at
+pub const MAX_LOG_SIZE: u64 = 1024 * 1024;
+/// Number of rotated log files kept besides the current one
+pub const KEPT_LOGS: usize = 3;
+
+/// A log file that rotates itself as it's written to.
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    /// Size of the current file
+    written: u64,
+    max_size: u64,
+}
+
+impl RotatingFile {
+    /// Open `path` for appending, creating it and its folder if needed, and
+    /// rotate it first if it's already at `max_size`.
+    pub fn open(path: &Path, max_size: u64) -> io::Result<RotatingFile> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let existing = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if existing >= max_size {
+            rotate(path)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(RotatingFile {
+            path: path.to_path_buf(),
+            written: file.metadata()?.len(),
+            file,
+            max_size,
+        })
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_size {
+            self.file.flush()?;
+            rotate(&self.path)?;
+            self.file = File::create(&self.path)?;
+            self.written = 0;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Shift `path` to `path.1`, `path.1` to `path.2`, and so on, dropping the
+/// oldest past [`KEPT_LOGS`].
+fn rotate(path: &Path) -> io::Result<()> {
+    for n in (1..KEPT_LOGS).rev() {
+        let older = numbered(path, n);
+        if older.exists() {
+            fs::rename(&older, numbered(path, n + 1))?;
+        }
+    }
+    if path.exists() {
+        fs::rename(path, numbered(path, 1))?;
+    }
+    Ok(())
+}
+
+/// `path` with `.n` appended
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotating_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("logs/dromos.log");
+        let mut log = RotatingFile::open(&path, 10).unwrap();
+        log.write_all(b"first\n").unwrap();
+        log.write_all(b"second\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second\n");
+        assert_eq!(fs::read(numbered(&path, 1)).unwrap(), b"first\n");
+
+        for i in 0..KEPT_LOGS + 2 {
+            log.write_all(format!("line {}\n", i).as_bytes()).unwrap();
+        }
+        assert!(numbered(&path, KEPT_LOGS).exists());
+        assert!(!numbered(&path, KEPT_LOGS + 1).exists());
+
+        // A full file is rotated on opening
+        drop(log);
+        fs::write(&path, b"0123456789").unwrap();
+        RotatingFile::open(&path, 10).unwrap();
+        assert_eq!(fs::read(numbered(&path, 1)).unwrap(), b"0123456789");
+        assert_eq!(fs::read(&path).unwrap(), b"");
+    }
+}
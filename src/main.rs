@@ -1,30 +1,179 @@
 use std::process::ExitCode;
+use std::time::Duration;
 
-use git_version::git_version;
-use rustyline::Editor;
+use clap::Parser;
 use rustyline::error::ReadlineError;
+use rustyline::{Config, Editor};
 
-const VERSION: &str = git_version!(
-    args = ["--tags", "--always", "--dirty=-modified"],
-    fallback = env!("CARGO_PKG_VERSION")
-);
-const BUILD_TIME: &str = env!("BUILD_TIMESTAMP");
+use dromos::cache::LookupCache;
+use dromos::cli::commands::parse_quoted_args;
+use dromos::cli::listing::ListLayout;
+use dromos::cli::theme::Palette;
+use dromos::cli::{DromosHelper, ExitStatus, OutputFormat, ReplState, i18n, theme};
+use dromos::config::{StorageConfig, UserConfig, config_file_path};
+use dromos::gamedb::GameDb;
+#[cfg(feature = "tracing")]
+use dromos::logfile::{MAX_LOG_SIZE, RotatingFile};
+use dromos::plugin::Plugin;
+use dromos::storage::TagRule;
+use dromos::{BUILD_TIME, VERSION};
 
-use dromos::cli::{Command, DromosHelper, ReplState, theme};
-use dromos::config::StorageConfig;
+/// Manage ROM images through a graph of binary diffs.
+///
+/// With no command, starts the interactive shell. Otherwise runs a single
+/// shell command (e.g. `dromos --json list`) and exits.
+///
+/// Exit codes: 0 success, 1 failure, 2 usage error, 3 not found,
+/// 4 already exists, 5 verification failed, 6 I/O error, 7 cancelled.
+#[derive(Parser)]
+#[command(name = "dromos", version = VERSION)]
+struct Cli {
+    /// Emit structured JSON on stdout instead of colored text
+    #[arg(long)]
+    json: bool,
+
+    /// Only print results and errors (no progress or confirmation messages)
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Report wall time spent hashing, diffing, and path finding per command
+    #[arg(long)]
+    timings: bool,
+
+    /// Answer yes to all confirmation prompts (remove, export, import)
+    #[arg(short, long)]
+    yes: bool,
+
+    /// Plain output for screen readers: labeled lines instead of colors,
+    /// columns, and symbols, and one-line prompts
+    #[arg(long)]
+    plain: bool,
+
+    /// Refuse to add ROMs whose headers contradict themselves or the file
+    #[arg(long)]
+    strict: bool,
+
+    /// Trace library operations and their durations on stderr (-vv for
+    /// diff, path finding, and export/import details)
+    #[cfg(feature = "tracing")]
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Also write operations, warnings, and errors to dromos.log in the data
+    /// folder, at the -v level chosen (rotated at 1 MB)
+    #[cfg(feature = "tracing")]
+    #[arg(long)]
+    log: bool,
+
+    /// Serve shell commands as newline-delimited JSON-RPC 2.0 on stdin and
+    /// stdout, for editors and other tools
+    #[arg(long, conflicts_with = "command")]
+    rpc: bool,
+
+    /// Shell command to run non-interactively
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
 
 fn main() -> ExitCode {
+    let cli = Cli::parse();
     theme::init();
+    let translation = i18n::init();
+    let user_config = config_file_path().map(|path| {
+        let loaded = UserConfig::load(&path);
+        (path, loaded)
+    });
+    #[cfg(feature = "tracing")]
+    {
+        let log = cli.log
+            || user_config
+                .as_ref()
+                .is_some_and(|(_, loaded)| loaded.as_ref().is_ok_and(|c| c.log));
+        if cli.verbose > 0 || log {
+            init_tracing(cli.verbose, log);
+        }
+    }
+    if let Err(e) = translation {
+        warn(e);
+    }
+    if let Err(e) = dromos::cli::interrupt::install() {
+        warn(format_args!("Ctrl+C handler not installed: {}", e));
+    }
 
-    if let Err(e) = run() {
-        eprintln!("{} {}", theme::error("Error:"), e);
-        return ExitCode::FAILURE;
+    match run(cli, user_config) {
+        Ok(status) => status.into(),
+        Err(e) => {
+            #[cfg(feature = "tracing")]
+            tracing::error!(code = e.code(), "{}", e);
+            eprintln!("{} {}", theme::error(&i18n::message("error", &[])), e);
+            ExitStatus::from_error(&e).into()
+        }
     }
+}
 
-    ExitCode::SUCCESS
+/// Print a warning on stderr, recording it in the log file too when logging
+/// is on.
+fn warn(message: impl std::fmt::Display) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!("{}", message);
+    eprintln!(
+        "{} {}",
+        theme::warning(&i18n::message("warning", &[])),
+        message
+    );
 }
 
-fn run() -> dromos::Result<()> {
+/// Print spans and events on stderr with -v, and write them to the log file
+/// with `log`, with each span's duration when it closes. The log file gets
+/// operations, warnings, and errors, plus details with -vv.
+#[cfg(feature = "tracing")]
+fn init_tracing(verbose: u8, log: bool) {
+    use std::sync::Mutex;
+    use tracing_subscriber::filter::LevelFilter;
+    use tracing_subscriber::fmt::{self, format::FmtSpan};
+    use tracing_subscriber::prelude::*;
+
+    let level = if verbose > 1 {
+        LevelFilter::DEBUG
+    } else {
+        LevelFilter::INFO
+    };
+    let stderr = (verbose > 0).then(|| {
+        fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_ansi(theme::colors_enabled())
+            .with_filter(level)
+    });
+    let file = dromos::config::log_file_path()
+        .filter(|_| log)
+        .and_then(|path| match RotatingFile::open(&path, MAX_LOG_SIZE) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                warn(i18n::message(
+                    "log-file-failed",
+                    &[("path", &path.display()), ("error", &e)],
+                ));
+                None
+            }
+        })
+        .map(|file| {
+            fmt::layer()
+                .with_writer(Mutex::new(file))
+                .with_span_events(FmtSpan::CLOSE)
+                .with_ansi(false)
+                .with_filter(level)
+        });
+    tracing_subscriber::registry()
+        .with(stderr)
+        .with(file)
+        .init();
+}
+
+fn run(
+    cli: Cli,
+    user_config: Option<(std::path::PathBuf, Result<UserConfig, String>)>,
+) -> dromos::Result<ExitStatus> {
     let config = StorageConfig::default_paths().ok_or_else(|| {
         dromos::DromosError::Io(std::io::Error::new(
             std::io::ErrorKind::NotFound,
@@ -33,35 +182,159 @@ fn run() -> dromos::Result<()> {
     })?;
 
     let mut state = ReplState::new(config)?;
-    let mut rl = Editor::new().expect("Failed to initialize readline");
+    if let Some(wipe) = state.storage.data_wipe() {
+        eprintln!(
+            "{} Data revision changed (stored: {}, current: {}). Wiped database and diffs.",
+            theme::warning(&i18n::message("warning", &[])),
+            wipe.stored_revision
+                .map(|r| r.to_string())
+                .unwrap_or_else(|| "none".to_string()),
+            wipe.current_revision
+        );
+    }
+    state.quiet = cli.quiet;
+    dromos::timings::set_enabled(cli.timings);
+    if let Some((path, loaded)) = user_config {
+        match loaded {
+            Ok(user_config) => {
+                match Palette::from_config(user_config.theme.as_deref(), &user_config.colors) {
+                    Ok(palette) => theme::set_palette(palette),
+                    Err(e) => warn(format_args!("{}: {}", path.display(), e)),
+                }
+                match ListLayout::from_config(
+                    user_config.list_columns.as_deref(),
+                    user_config.list_sort.as_deref(),
+                ) {
+                    Ok(layout) => state.list_layout = layout,
+                    Err(e) => warn(format_args!("{}: {}", path.display(), e)),
+                }
+                if let Some(output) = &user_config.output {
+                    match output.parse::<OutputFormat>() {
+                        Ok(format) => state.output = format,
+                        Err(e) => warn(format_args!("{}: {}", path.display(), e)),
+                    }
+                }
+                // NO_COLOR wins over the config file
+                if let Some(color) = user_config.color
+                    && std::env::var_os("NO_COLOR").is_none()
+                {
+                    theme::set_colors_enabled(color);
+                }
+                if user_config.plain {
+                    theme::set_plain(true);
+                }
+                state.assume_yes = !user_config.confirm;
+                state.pager = user_config.pager;
+                state.export_dir = user_config.export_dir.clone();
+                state.export_git = user_config.export_git;
+                if let Some(game_db) = &user_config.game_db {
+                    match GameDb::open(game_db) {
+                        Ok(db) => state.game_db = Some(db),
+                        Err(e) => warn(format_args!(
+                            "{}: game_db {}: {}",
+                            path.display(),
+                            game_db.display(),
+                            e
+                        )),
+                    }
+                }
+                state.plugins = user_config
+                    .plugins
+                    .iter()
+                    .map(|(name, command)| Plugin::new(name, parse_quoted_args(command)))
+                    .collect();
+                let mut tag_rules = Vec::new();
+                for (tag, query) in &user_config.autotags {
+                    match TagRule::new(tag, query) {
+                        Ok(rule) => tag_rules.push(rule),
+                        Err(e) => warn(format_args!("{}: {}", path.display(), e)),
+                    }
+                }
+                state.storage.set_tag_rules(tag_rules);
+                state.storage.set_strict(user_config.strict);
+                if user_config.metadata_cache_days > 0 {
+                    let days = user_config.metadata_cache_days;
+                    state.lookup_cache = LookupCache::default_location(Duration::from_secs(
+                        days.saturating_mul(24 * 60 * 60),
+                    ));
+                }
+                state.user_config = user_config;
+            }
+            Err(e) => warn(format_args!("{}: {}", path.display(), e)),
+        }
+    }
+    // Command-line switches override the config file
+    if cli.json {
+        state.output = OutputFormat::Json;
+    }
+    state.assume_yes |= cli.yes;
+    if cli.strict {
+        state.storage.set_strict(true);
+    }
+    if cli.plain {
+        theme::set_plain(true);
+    }
+    let history_size = state.user_config.history_size;
+    let rl_config = Config::builder()
+        .max_history_size(history_size.max(1))
+        .expect("history size is at least 1")
+        .history_ignore_space(state.user_config.history_ignore_space)
+        .build();
+    let mut rl = Editor::with_config(rl_config).expect("Failed to initialize readline");
     rl.set_helper(Some(DromosHelper::new()));
 
-    // Try to load history (ignore errors)
-    let history_path = dirs_history_path();
+    if cli.rpc {
+        dromos::cli::rpc::serve(
+            &mut state,
+            &mut rl,
+            &mut std::io::stdin().lock(),
+            &mut std::io::stdout().lock(),
+        )?;
+        return Ok(ExitStatus::Success);
+    }
+
+    // One-shot mode: run the command given on the command line and exit
+    if !cli.command.is_empty() {
+        state.execute_args(&cli.command, None, &mut rl)?;
+        return Ok(state.last_status());
+    }
+
+    // Try to load history (ignore errors); a size of 0 turns saved history off
+    let history_path = dirs_history_path().filter(|_| history_size > 0);
     if let Some(path) = &history_path {
         let _ = rl.load_history(path);
     }
 
-    theme::print_banner(VERSION, BUILD_TIME);
-    println!();
-    println!("  - type a command, e.g. \"help\" or \"exit\"");
-    println!("  - press tab for autocomplete, and up/down for history");
-
-    let prompt_str = "\ndromos> ";
+    if !cli.quiet {
+        theme::print_banner(VERSION, BUILD_TIME);
+        println!();
+        println!("  - {}", i18n::message("banner-hint-command", &[]));
+        println!("  - {}", i18n::message("banner-hint-keys", &[]));
+        let db_path = theme::dim(&state.storage.config().db_path.display().to_string());
+        println!(
+            "  - {}",
+            i18n::message("banner-library", &[("path", &db_path)])
+        );
+    }
+    state.start_diff_check();
 
     loop {
-        match rl.readline(prompt_str) {
+        state.report_diff_check();
+        // Keep hash/title completions and the prompt in sync with the library
+        let prompt_str = format!("\n{}", state.prompt());
+        if let Some(helper) = rl.helper_mut() {
+            helper.set_nodes(state.completion_nodes());
+            helper.set_main_prompt(&prompt_str);
+        }
+
+        match rl.readline(&prompt_str) {
             Ok(line) => {
                 let _ = rl.add_history_entry(&line);
 
-                match Command::parse(&line) {
-                    None => continue, // Empty line
-                    Some(Err(e)) => eprintln!("{}", theme::error(&e)),
-                    Some(Ok(cmd)) => match state.execute(cmd, &mut rl) {
-                        Ok(true) => {}      // Continue
-                        Ok(false) => break, // Quit requested
-                        Err(e) => eprintln!("{} {}", theme::error("Error:"), e),
-                    },
+                match state.execute_line(&line, &mut rl) {
+                    Ok(true) => {}      // Continue
+                    Ok(false) => break, // Quit requested
+                    Err(e) => eprintln!("{} {}", theme::error(&i18n::message("error", &[])), e),
                 }
             }
             Err(ReadlineError::Interrupted) => {
@@ -72,18 +345,20 @@ fn run() -> dromos::Result<()> {
                 break;
             }
             Err(e) => {
-                eprintln!("{} {}", theme::error("Error:"), e);
+                eprintln!("{} {}", theme::error(&i18n::message("error", &[])), e);
                 break;
             }
         }
     }
 
+    state.print_session_summary();
+
     // Save history
     if let Some(path) = &history_path {
         let _ = rl.save_history(path);
     }
 
-    Ok(())
+    Ok(ExitStatus::Success)
 }
 
 fn dirs_history_path() -> Option<std::path::PathBuf> {
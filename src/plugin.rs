@@ -0,0 +1,437 @@
+//! External plugins: programs named in `dromos.conf` (`plugin.<name> =
+//! <command>`) that add ROM formats, metadata sources, and export formats
+//! without changes to dromos itself.
+//!
+//! dromos runs the command once per request, writing one JSON object to
+//! its stdin and reading one JSON value from its stdout. The object's
+//! `request` field says what is asked:
+//!
+//! - `describe`: what the plugin handles. The reply is an object like
+//!   `{"extensions": ["unf"], "metadata": true, "export": true}`; missing
+//!   fields mean no.
+//! - `convert`: `{"path": ...}`, a file with one of the plugin's
+//!   extensions. The reply is `{"nes": ...}`, the ROM as an iNES file in
+//!   base64, which dromos hashes and stores like any other NES ROM.
+//! - `metadata`: `{"filename", "sha256", "sha1", "crc32"}` of a ROM being
+//!   added. The reply suggests its `title`, `version`, `release_date`,
+//!   `source_url`, and `description`, or is `null` if the ROM is unknown.
+//!   Replies are kept in a [`LookupCache`], since plugins may fetch them
+//!   over the network.
+//! - `export`: `{"output", "name", "nodes", "edges"}`, the ROMs and links to
+//!   write into the `output` folder. The reply is ignored.
+//!
+//! A command that exits unsuccessfully fails the request with its stderr.
+//! Converted ROMs are always NES ROMs, since those are the only ROMs the
+//! library can store.
+
+use std::cell::OnceCell;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::cache::LookupCache;
+use crate::db::{NodeMetadata, NodeRow};
+use crate::error::{DromosError, Result};
+use crate::rom::{RomMetadata, format_hash, parse_nes_header_bytes};
+use crate::site::SiteEdge;
+
+/// What a plugin handles, from its `describe` reply.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Capabilities {
+    /// File extensions it converts to NES ROMs, without the dot
+    pub extensions: Vec<String>,
+    /// Whether it suggests metadata for new ROMs
+    pub metadata: bool,
+    /// Whether it writes exports
+    pub export: bool,
+}
+
+/// A configured plugin command.
+#[derive(Debug)]
+pub struct Plugin {
+    name: String,
+    command: Vec<String>,
+    capabilities: OnceCell<Capabilities>,
+}
+
+/// A `metadata` reply.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SuggestedMetadata {
+    title: Option<String>,
+    version: Option<String>,
+    release_date: Option<String>,
+    source_url: Option<String>,
+    description: Option<String>,
+}
+
+/// A `convert` reply.
+#[derive(Debug, Deserialize)]
+struct Converted {
+    nes: String,
+}
+
+/// A library ROM as sent with an `export` request.
+#[derive(Debug, Serialize)]
+struct ExportNode<'a> {
+    sha256: String,
+    sha1: Option<String>,
+    crc32: Option<String>,
+    title: &'a str,
+    version: Option<&'a str>,
+    rom_type: &'static str,
+    filename: Option<&'a str>,
+    release_date: Option<&'a str>,
+    source_url: Option<&'a str>,
+    tags: &'a [String],
+    description: Option<&'a str>,
+}
+
+impl Plugin {
+    /// A plugin run as `command` (the program followed by its arguments).
+    pub fn new(name: impl Into<String>, command: Vec<String>) -> Plugin {
+        Plugin {
+            name: name.into(),
+            command,
+            capabilities: OnceCell::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// What the plugin handles, asked once and then remembered.
+    pub fn capabilities(&self) -> Result<&Capabilities> {
+        if let Some(capabilities) = self.capabilities.get() {
+            return Ok(capabilities);
+        }
+        let capabilities = self.request(json!({ "request": "describe" }))?;
+        Ok(self.capabilities.get_or_init(|| capabilities))
+    }
+
+    /// Whether the plugin converts files with `path`'s extension.
+    pub fn converts(&self, path: &Path) -> Result<bool> {
+        let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+            return Ok(false);
+        };
+        Ok(self
+            .capabilities()?
+            .extensions
+            .iter()
+            .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(extension)))
+    }
+
+    /// Convert `path` to an NES ROM written into `dir`, named after the
+    /// original file. Returns the new file's path.
+    pub fn convert(&self, path: &Path, dir: &Path) -> Result<PathBuf> {
+        let reply: Converted = self.request(json!({
+            "request": "convert",
+            "path": path.display().to_string(),
+        }))?;
+        let data = BASE64
+            .decode(reply.nes.as_bytes())
+            .map_err(|e| self.error(format!("convert reply isn't base64: {}", e)))?;
+        let header: Option<&[u8; 16]> = data.get(..16).and_then(|h| h.try_into().ok());
+        if header.and_then(parse_nes_header_bytes).is_none() {
+            return Err(self.error("convert reply isn't an NES ROM".to_string()));
+        }
+
+        let stem = path.file_stem().unwrap_or(path.as_os_str());
+        let converted = dir.join(stem).with_extension("nes");
+        std::fs::create_dir_all(dir).map_err(DromosError::file(dir))?;
+        std::fs::write(&converted, data).map_err(DromosError::file(&converted))?;
+        Ok(converted)
+    }
+
+    /// Metadata the plugin suggests for a new ROM, or None if it doesn't
+    /// know it (or doesn't suggest metadata).
+    ///
+    /// A fresh reply in `cache` is used without running the plugin. If the
+    /// plugin fails (e.g. while offline), a stale cached reply is used
+    /// instead of the error.
+    pub fn metadata(
+        &self,
+        rom: &RomMetadata,
+        cache: Option<&LookupCache>,
+    ) -> Result<Option<NodeMetadata>> {
+        let key = format_hash(&rom.sha256);
+        let cached = cache.and_then(|c| c.get(&self.name, &key));
+        if let Some(cached) = &cached
+            && cached.fresh
+        {
+            return self.suggested_metadata(cached.reply.clone());
+        }
+
+        let fetched = self.capabilities().and_then(|capabilities| {
+            if !capabilities.metadata {
+                return Ok(None);
+            }
+            self.request::<Value>(json!({
+                "request": "metadata",
+                "filename": rom.filename,
+                "sha256": key,
+                "sha1": rom.sha1.map(hex::encode),
+                "crc32": rom.crc32.map(|c| format!("{:08x}", c)),
+            }))
+            .map(Some)
+        });
+        match (fetched, cached) {
+            (Ok(None), _) => Ok(None),
+            (Ok(Some(reply)), _) => {
+                if let Some(cache) = cache
+                    && let Err(_e) = cache.put(&self.name, &key, &reply)
+                {
+                    // Only costs a refetch next time
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(error = %_e, plugin = %self.name, "can't cache lookup");
+                }
+                self.suggested_metadata(reply)
+            }
+            (Err(_), Some(stale)) => self.suggested_metadata(stale.reply),
+            (Err(e), None) => Err(e),
+        }
+    }
+
+    /// Node metadata from a `metadata` reply; replies without a title
+    /// suggest nothing.
+    fn suggested_metadata(&self, reply: Value) -> Result<Option<NodeMetadata>> {
+        let suggested: Option<SuggestedMetadata> = serde_json::from_value(reply)
+            .map_err(|e| self.error(format!("invalid reply: {}", e)))?;
+        Ok(suggested.and_then(|s| {
+            Some(NodeMetadata {
+                title: s.title.filter(|t| !t.trim().is_empty())?,
+                version: s.version,
+                release_date: s.release_date,
+                source_url: s.source_url,
+                description: s.description,
+                tags: Vec::new(),
+                ..Default::default()
+            })
+        }))
+    }
+
+    /// Have the plugin write `nodes` and the `edges` between them into
+    /// `output`, as an export called `name`.
+    pub fn export(
+        &self,
+        output: &Path,
+        name: &str,
+        nodes: &[NodeRow],
+        edges: &[SiteEdge],
+    ) -> Result<()> {
+        if !self.capabilities()?.export {
+            return Err(self.error("doesn't write exports".to_string()));
+        }
+        let nodes: Vec<ExportNode> = nodes
+            .iter()
+            .map(|row| ExportNode {
+                sha256: format_hash(&row.sha256),
+                sha1: row.sha1.map(hex::encode),
+                crc32: row.crc32.map(|c| format!("{:08x}", c)),
+                title: &row.title,
+                version: row.version.as_deref(),
+                rom_type: row.rom_type.as_str(),
+                filename: row.filename.as_deref(),
+                release_date: row.release_date.as_deref(),
+                source_url: row.source_url.as_deref(),
+                tags: &row.tags,
+                description: row.description.as_deref(),
+            })
+            .collect();
+        let edges: Vec<Value> = edges
+            .iter()
+            .map(|(from, to, diff_size)| {
+                json!({
+                    "from": format_hash(from),
+                    "to": format_hash(to),
+                    "diff_size": diff_size,
+                })
+            })
+            .collect();
+        self.request::<Value>(json!({
+            "request": "export",
+            "output": output.display().to_string(),
+            "name": name,
+            "nodes": nodes,
+            "edges": edges,
+        }))?;
+        Ok(())
+    }
+
+    /// Send one request and parse the reply.
+    fn request<T: DeserializeOwned>(&self, request: Value) -> Result<T> {
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or_else(|| self.error("empty command".to_string()))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| self.error(format!("can't run {}: {}", program, e)))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A plugin that exits without reading its request still replies
+            let _ = stdin.write_all(request.to_string().as_bytes());
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = match stderr.trim() {
+                "" => format!("exited with {}", output.status),
+                message => message.to_string(),
+            };
+            return Err(self.error(reason));
+        }
+        serde_json::from_slice(&output.stdout)
+            .map_err(|e| self.error(format!("invalid reply: {}", e)))
+    }
+
+    fn error(&self, reason: String) -> DromosError {
+        DromosError::Plugin {
+            name: self.name.clone(),
+            reason,
+        }
+    }
+}
+
+/// The first of `plugins` that converts `path`, for files that aren't
+/// already a supported ROM type.
+pub fn find_converter<'a>(plugins: &'a [Plugin], path: &Path) -> Result<Option<&'a Plugin>> {
+    for plugin in plugins {
+        if plugin.converts(path)? {
+            return Ok(Some(plugin));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::rom::{hash_nes_bytes, hash_rom_file};
+    use crate::test_support::nes_rom;
+
+    /// A plugin that answers every request from a shell script.
+    fn script_plugin(dir: &Path, script: &str) -> Plugin {
+        let path = dir.join("plugin.sh");
+        std::fs::write(&path, script).unwrap();
+        Plugin::new("test", vec!["sh".to_string(), path.display().to_string()])
+    }
+
+    #[test]
+    fn test_plugin_requests() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let rom = nes_rom(0xEA);
+        let script = format!(
+            "read request\n\
+             case \"$request\" in\n\
+             *describe*) echo '{{\"extensions\": [\".UNF\"], \"metadata\": true}}' ;;\n\
+             *convert*) echo '{{\"nes\": \"{}\"}}' ;;\n\
+             *metadata*) echo '{{\"title\": \"Converted Game\", \"version\": \"USA\"}}' ;;\n\
+             *) echo 'unknown request' >&2; exit 1 ;;\n\
+             esac\n",
+            BASE64.encode(&rom)
+        );
+        let plugin = script_plugin(dir, &script);
+
+        let capabilities = plugin.capabilities().unwrap();
+        assert!(capabilities.metadata && !capabilities.export);
+        assert!(plugin.converts(Path::new("game.unf")).unwrap());
+        assert!(!plugin.converts(Path::new("game.nes")).unwrap());
+        let plugins = [plugin];
+        let plugin = find_converter(&plugins, Path::new("x/game.unf"))
+            .unwrap()
+            .unwrap();
+
+        let converted = plugin
+            .convert(Path::new("x/game.unf"), &dir.join("out"))
+            .unwrap();
+        assert_eq!(converted, dir.join("out/game.nes"));
+        let hashed = hash_rom_file(&converted).unwrap();
+
+        let suggested = plugin.metadata(&hashed, None).unwrap().unwrap();
+        assert_eq!(suggested.title, "Converted Game");
+        assert_eq!(suggested.version.as_deref(), Some("USA"));
+
+        match plugin.export(dir, "library", &[], &[]) {
+            Err(DromosError::Plugin { name, reason }) => {
+                assert_eq!(name, "test");
+                assert_eq!(reason, "doesn't write exports");
+            }
+            other => panic!("expected a plugin error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_plugin_failures() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let failing = script_plugin(temp_dir.path(), "echo 'no database' >&2\nexit 3\n");
+        assert!(matches!(
+            failing.capabilities(),
+            Err(DromosError::Plugin { reason, .. }) if reason == "no database"
+        ));
+
+        let garbled = script_plugin(temp_dir.path(), "echo 'not json'\n");
+        assert!(matches!(
+            garbled.capabilities(),
+            Err(DromosError::Plugin { reason, .. }) if reason.starts_with("invalid reply")
+        ));
+
+        let missing = Plugin::new("missing", vec!["/nonexistent/plugin".to_string()]);
+        assert!(missing.capabilities().is_err());
+    }
+
+    #[test]
+    fn test_cached_metadata() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let rom = hash_nes_bytes(
+            &[b'N', b'E', b'S', 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            None,
+        )
+        .unwrap();
+        // Counts its metadata requests, so cache hits show
+        let script = format!(
+            "read request\n\
+             case \"$request\" in\n\
+             *describe*) echo '{{\"metadata\": true}}' ;;\n\
+             *) echo x >> {count}; echo '{{\"title\": \"Online Title\"}}' ;;\n\
+             esac\n",
+            count = dir.join("count").display()
+        );
+        let online = script_plugin(dir, &script);
+        let cache = LookupCache::new(dir.join("cache"), crate::cache::DEFAULT_TTL);
+        let requests = || {
+            std::fs::read_to_string(dir.join("count"))
+                .unwrap()
+                .lines()
+                .count()
+        };
+
+        for _ in 0..2 {
+            let suggested = online.metadata(&rom, Some(&cache)).unwrap().unwrap();
+            assert_eq!(suggested.title, "Online Title");
+        }
+        assert_eq!(requests(), 1);
+
+        // Offline: an expired reply beats failing
+        let offline = Plugin::new("test", vec!["/nonexistent/plugin".to_string()]);
+        let expired = LookupCache::new(dir.join("cache"), std::time::Duration::ZERO);
+        let suggested = offline.metadata(&rom, Some(&expired)).unwrap().unwrap();
+        assert_eq!(suggested.title, "Online Title");
+        assert!(offline.metadata(&rom, None).is_err());
+
+        online.metadata(&rom, Some(&expired)).unwrap();
+        assert_eq!(requests(), 2);
+    }
+}
@@ -0,0 +1,75 @@
+//! Progress reporting for long operations.
+//!
+//! Hashing a folder of ROMs, linking, building, exporting, and importing
+//! take a [`ProgressSink`] and report each item as they start on it. The CLI
+//! turns these into a status line; a server or GUI can forward them to its
+//! own progress display. Pass [`NoProgress`] to ignore them.
+
+use std::fmt;
+
+/// The kind of work an item belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading and hashing a ROM file
+    Hashing,
+    /// Creating a diff between two ROMs
+    Diffing,
+    /// Applying a diff while building a ROM
+    Patching,
+    /// Writing an export file
+    Writing,
+    /// Adding or updating an imported node
+    Importing,
+    /// Copying an imported diff into the library
+    Copying,
+    /// Fetching a diff or attachment from another library
+    Downloading,
+    /// Sending a diff or attachment to another library
+    Uploading,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Hashing => write!(f, "hashing"),
+            Stage::Diffing => write!(f, "diffing"),
+            Stage::Patching => write!(f, "patching"),
+            Stage::Writing => write!(f, "writing"),
+            Stage::Importing => write!(f, "importing"),
+            Stage::Copying => write!(f, "copying"),
+            Stage::Downloading => write!(f, "downloading"),
+            Stage::Uploading => write!(f, "uploading"),
+        }
+    }
+}
+
+/// One item of work, reported as it starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress<'a> {
+    pub stage: Stage,
+    /// What is being worked on: a file path, diff name, or ROM title
+    pub item: &'a str,
+    /// 1-based position of this item within its stage
+    pub current: usize,
+    pub total: usize,
+    /// Size of the data this item reads or writes; 0 if not known up front
+    pub bytes: u64,
+}
+
+/// Receives [`Progress`] updates. Closures taking `&Progress` implement it.
+pub trait ProgressSink {
+    fn progress(&mut self, progress: &Progress);
+}
+
+impl<F: FnMut(&Progress)> ProgressSink for F {
+    fn progress(&mut self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+/// A sink that discards every update.
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn progress(&mut self, _progress: &Progress) {}
+}
@@ -0,0 +1,354 @@
+//! Comparing two ROM files, to see whether they're versions of the same game
+//! before linking them.
+//!
+//! Both files are compared as dromos stores them: NES files without their
+//! iNES header (and trainer), so a header edit alone doesn't count as a
+//! change. Offsets are into that headerless data, where PRG ROM comes first
+//! and CHR ROM after it.
+
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::diff;
+use crate::error::Result;
+
+use super::hash::{hash_rom_file, read_rom_bytes};
+use super::types::{NesHeader, RomMetadata};
+
+const PRG_BANK_SIZE: usize = 16 * 1024;
+const CHR_BANK_SIZE: usize = 8 * 1024;
+/// Size of the blocks changes are counted in for data without a NES header
+const BLOCK_SIZE: usize = 16 * 1024;
+/// Changed bytes this close together count as one range
+const RANGE_GAP: usize = 16;
+
+/// Part of a ROM's data that changes are counted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", content = "index", rename_all = "snake_case")
+)]
+pub enum RomArea {
+    /// A 16 KB PRG ROM bank
+    Prg(usize),
+    /// An 8 KB CHR ROM bank
+    Chr(usize),
+    /// A 16 KB block of data without a NES header, or past the banks the
+    /// header declares
+    Block(usize),
+}
+
+impl RomArea {
+    /// The area holding `offset`, given the header of the file it's in.
+    pub fn at(offset: usize, header: Option<&NesHeader>) -> RomArea {
+        match header {
+            Some(h) if offset < h.prg_rom_size => RomArea::Prg(offset / PRG_BANK_SIZE),
+            Some(h) if offset < h.prg_rom_size + h.chr_rom_size => {
+                RomArea::Chr((offset - h.prg_rom_size) / CHR_BANK_SIZE)
+            }
+            _ => RomArea::Block(offset / BLOCK_SIZE),
+        }
+    }
+
+    /// The offset just past this area, given the header it was found with.
+    fn end(self, header: Option<&NesHeader>) -> usize {
+        let (prg, chr) = header.map_or((0, 0), |h| (h.prg_rom_size, h.chr_rom_size));
+        match self {
+            RomArea::Prg(bank) => ((bank + 1) * PRG_BANK_SIZE).min(prg),
+            RomArea::Chr(bank) => prg + ((bank + 1) * CHR_BANK_SIZE).min(chr),
+            RomArea::Block(block) => (block + 1) * BLOCK_SIZE,
+        }
+    }
+}
+
+impl fmt::Display for RomArea {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomArea::Prg(bank) => write!(f, "PRG bank {}", bank),
+            RomArea::Chr(bank) => write!(f, "CHR bank {}", bank),
+            RomArea::Block(block) => write!(f, "block {}", block),
+        }
+    }
+}
+
+/// The bytes that differ within one [`RomArea`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AreaChanges {
+    pub area: RomArea,
+    pub changed_bytes: usize,
+    /// Offsets of the first and last changed byte
+    pub first: usize,
+    pub last: usize,
+}
+
+/// How many bytes of one [`RomArea`] differ, for every area of a ROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AreaSummary {
+    pub area: RomArea,
+    /// Bytes in the area
+    pub len: usize,
+    pub changed_bytes: usize,
+}
+
+impl AreaSummary {
+    /// Share of the area's bytes that changed, from 0 to 1
+    pub fn changed_fraction(&self) -> f64 {
+        self.changed_bytes as f64 / self.len.max(1) as f64
+    }
+}
+
+/// How two ROM files differ
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RomComparison {
+    pub a: RomMetadata,
+    pub b: RomMetadata,
+    /// Length of each file's data, headers excluded
+    pub len_a: usize,
+    pub len_b: usize,
+    /// Bytes that differ where both files have data
+    pub changed_bytes: usize,
+    /// Runs of changed bytes, counting ones up to 16 bytes apart as one
+    pub changed_ranges: usize,
+    /// Changed bytes per area, in order; areas only one file has aren't
+    /// listed
+    pub areas: Vec<AreaChanges>,
+    /// Size of the diff `link` would store from `a` to `b`
+    pub diff_size: u64,
+}
+
+impl RomComparison {
+    /// Whether the files hold the same ROM data
+    pub fn identical(&self) -> bool {
+        self.a.sha256 == self.b.sha256
+    }
+
+    /// Whether the files have the same header (or neither has one)
+    pub fn headers_match(&self) -> bool {
+        self.a.source_file_header == self.b.source_file_header
+    }
+
+    /// Whether the files look like versions of the same ROM: under half
+    /// the bytes both have differ, and the diff between them is under half
+    /// the size of `b`'s data.
+    pub fn likely_related(&self) -> bool {
+        self.changed_bytes.saturating_mul(2) < self.len_a.min(self.len_b)
+            && self.diff_size.saturating_mul(2) < self.len_b as u64
+    }
+}
+
+/// Compare two ROM files' data, headers excluded.
+pub fn compare_files(a: &Path, b: &Path) -> Result<RomComparison> {
+    let (meta_a, meta_b) = (hash_rom_file(a)?, hash_rom_file(b)?);
+    let (data_a, data_b) = (read_rom_bytes(a)?, read_rom_bytes(b)?);
+    let changes = compare_bytes(&data_a, &data_b, meta_a.nes_header.as_ref());
+    Ok(RomComparison {
+        len_a: data_a.len(),
+        len_b: data_b.len(),
+        changed_bytes: changes.changed_bytes,
+        changed_ranges: changes.changed_ranges,
+        areas: changes.areas,
+        diff_size: diff::diff_size(&data_a, &data_b)?,
+        a: meta_a,
+        b: meta_b,
+    })
+}
+
+/// The ranges of offsets where `a` and `b` differ, counting changed bytes up
+/// to 16 apart as one range. Data only one of them has is a range too.
+pub fn changed_ranges(a: &[u8], b: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (offset, _) in a.iter().zip(b).enumerate().filter(|(_, (x, y))| x != y) {
+        match ranges.last_mut() {
+            Some(range) if offset - range.end < RANGE_GAP => range.end = offset + 1,
+            _ => ranges.push(offset..offset + 1),
+        }
+    }
+    let (common, longest) = (a.len().min(b.len()), a.len().max(b.len()));
+    if common < longest {
+        match ranges.last_mut() {
+            Some(range) if common - range.end < RANGE_GAP => range.end = longest,
+            _ => ranges.push(common..longest),
+        }
+    }
+    ranges
+}
+
+/// What [`compare_bytes`] counts
+struct ByteChanges {
+    changed_bytes: usize,
+    changed_ranges: usize,
+    areas: Vec<AreaChanges>,
+}
+
+/// Count the bytes that differ between `a` and `b` where both have data,
+/// by area of `a` (laid out as `header` says).
+fn compare_bytes(a: &[u8], b: &[u8], header: Option<&NesHeader>) -> ByteChanges {
+    let mut areas: Vec<AreaChanges> = Vec::new();
+    let mut changed_bytes = 0;
+    let mut changed_ranges = 0;
+    let mut last_changed: Option<usize> = None;
+    for (offset, _) in a.iter().zip(b).enumerate().filter(|(_, (x, y))| x != y) {
+        changed_bytes += 1;
+        if last_changed.is_none_or(|last| offset - last > RANGE_GAP) {
+            changed_ranges += 1;
+        }
+        last_changed = Some(offset);
+
+        let area = RomArea::at(offset, header);
+        match areas.last_mut() {
+            Some(changes) if changes.area == area => {
+                changes.changed_bytes += 1;
+                changes.last = offset;
+            }
+            _ => areas.push(AreaChanges {
+                area,
+                changed_bytes: 1,
+                first: offset,
+                last: offset,
+            }),
+        }
+    }
+
+    ByteChanges {
+        changed_bytes,
+        changed_ranges,
+        areas,
+    }
+}
+
+/// Every area of the data in `a` and `b`, laid out as `a`'s `header` says,
+/// with how many of its bytes differ. Bytes only one of them has count as
+/// changed, so the banks an expansion adds show up in full.
+pub fn area_summaries(a: &[u8], b: &[u8], header: Option<&NesHeader>) -> Vec<AreaSummary> {
+    let longest = a.len().max(b.len());
+    let mut summaries = Vec::new();
+    let mut start = 0;
+    while start < longest {
+        let area = RomArea::at(start, header);
+        let end = area.end(header).min(longest);
+        let changed_bytes = (start..end).filter(|&i| a.get(i) != b.get(i)).count();
+        summaries.push(AreaSummary {
+            area,
+            len: end - start,
+            changed_bytes,
+        });
+        start = end;
+    }
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rom::Mirroring;
+
+    fn header(prg_banks: usize, chr_banks: usize) -> NesHeader {
+        NesHeader {
+            prg_rom_size: prg_banks * PRG_BANK_SIZE,
+            chr_rom_size: chr_banks * CHR_BANK_SIZE,
+            has_trainer: false,
+            mapper: 0,
+            mirroring: Mirroring::Horizontal,
+            has_battery: false,
+            is_nes2: false,
+            submapper: None,
+        }
+    }
+
+    #[test]
+    fn test_rom_area() {
+        let h = header(2, 1);
+        assert_eq!(RomArea::at(0, Some(&h)), RomArea::Prg(0));
+        assert_eq!(RomArea::at(PRG_BANK_SIZE, Some(&h)), RomArea::Prg(1));
+        assert_eq!(RomArea::at(2 * PRG_BANK_SIZE, Some(&h)), RomArea::Chr(0));
+        assert_eq!(
+            RomArea::at(2 * PRG_BANK_SIZE + CHR_BANK_SIZE, Some(&h)),
+            RomArea::Block(2)
+        );
+        assert_eq!(RomArea::at(BLOCK_SIZE + 1, None), RomArea::Block(1));
+        assert_eq!(RomArea::Chr(3).to_string(), "CHR bank 3");
+    }
+
+    #[test]
+    fn test_area_summaries() {
+        let h = header(2, 1);
+        let a = vec![0u8; 2 * PRG_BANK_SIZE + CHR_BANK_SIZE];
+        let mut b = a.clone();
+        b[PRG_BANK_SIZE + 1] = 1;
+        b[2 * PRG_BANK_SIZE] = 1;
+        b.extend_from_slice(&[0; 100]);
+
+        let summaries = area_summaries(&a, &b, Some(&h));
+        let counts: Vec<_> = summaries
+            .iter()
+            .map(|s| (s.area, s.len, s.changed_bytes))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                (RomArea::Prg(0), PRG_BANK_SIZE, 0),
+                (RomArea::Prg(1), PRG_BANK_SIZE, 1),
+                (RomArea::Chr(0), CHR_BANK_SIZE, 1),
+                (RomArea::Block(2), 100, 100),
+            ]
+        );
+        assert_eq!(summaries[3].changed_fraction(), 1.0);
+
+        let blocks = area_summaries(&[0; BLOCK_SIZE + 1], &[0; BLOCK_SIZE + 1], None);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].len, 1);
+    }
+
+    #[test]
+    fn test_changed_ranges() {
+        let a = vec![0u8; 100];
+        let mut b = a.clone();
+        b[10] = 1;
+        b[26] = 1;
+        b[60] = 1;
+        assert_eq!(changed_ranges(&a, &b), vec![10..27, 60..61]);
+        b.extend_from_slice(&[0; 8]);
+        b[99] = 1;
+        assert_eq!(changed_ranges(&a, &b), vec![10..27, 60..61, 99..108]);
+        assert!(changed_ranges(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn test_compare_bytes() {
+        let h = header(2, 1);
+        let a = vec![0u8; 2 * PRG_BANK_SIZE + CHR_BANK_SIZE];
+        let mut b = a.clone();
+        // Two nearby changes in PRG bank 0, one far off in it, one in CHR
+        b[10] = 1;
+        b[20] = 1;
+        b[1000] = 1;
+        b[2 * PRG_BANK_SIZE + 5] = 1;
+        b.extend_from_slice(&[9; 100]);
+
+        let comparison = compare_bytes(&a, &b, Some(&h));
+        assert_eq!(comparison.changed_bytes, 4);
+        assert_eq!(comparison.changed_ranges, 3);
+        assert_eq!(
+            comparison.areas,
+            vec![
+                AreaChanges {
+                    area: RomArea::Prg(0),
+                    changed_bytes: 3,
+                    first: 10,
+                    last: 1000,
+                },
+                AreaChanges {
+                    area: RomArea::Chr(0),
+                    changed_bytes: 1,
+                    first: 2 * PRG_BANK_SIZE + 5,
+                    last: 2 * PRG_BANK_SIZE + 5,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,444 @@
+//! Metadata from GoodTools and No-Intro style file names.
+//!
+//! `Super Mario Bros. 3 (U) (PRG1) [!].nes` names the game "Super Mario
+//! Bros. 3", revision PRG1, a verified good dump released in the USA. The
+//! codes are taken out of the title: regions and languages (see
+//! [`region::detect`]), revisions such as `(Rev A)`, `(v1.1)`, or `(PRG1)`,
+//! which become the version, and GoodTools dump codes (`[!]`, `[b1]`,
+//! `[h]`, `[T+Eng]`) and No-Intro flags (`(Beta)`, `(Unl)`), which become
+//! tags. Parenthesized text that isn't a known code, such as
+//! `(Hack by Someone)`, stays in the title.
+//!
+//! TOSEC names, common in home computer collections, are told apart by a
+//! release date as their first group: `Elite v1.1 (1985)(Acornsoft)(GB)
+//! (en)[cr Fairlight]` names "Elite", version 1.1, released in 1985 by
+//! Acornsoft in Great Britain, in English, cracked. Only a full date such as
+//! `(1986-03-12)` becomes the release date.
+
+use chrono::NaiveDate;
+
+use super::region::{self, Language, Region};
+
+/// What a file name says about a ROM.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileName {
+    /// The name without its codes
+    pub title: String,
+    pub version: Option<String>,
+    pub tags: Vec<String>,
+    pub regions: Vec<Region>,
+    pub languages: Vec<Language>,
+    /// YYYY-MM-DD, from a TOSEC name's date
+    pub release_date: Option<String>,
+    /// From a TOSEC name; None if it's unknown (`-`)
+    pub publisher: Option<String>,
+}
+
+/// Parse a file name, without its extension.
+pub fn parse(name: &str) -> FileName {
+    tosec(name).unwrap_or_else(|| good_name(name))
+}
+
+/// Parse a GoodTools or No-Intro name.
+fn good_name(name: &str) -> FileName {
+    let (regions, languages) = region::detect(name);
+    let mut parsed = FileName {
+        regions,
+        languages,
+        ..Default::default()
+    };
+
+    let mut title = String::new();
+    let mut rest = name;
+    while let Some(start) = rest.find(['(', '[']) {
+        let open = rest[start..].chars().next().unwrap_or('(');
+        let close = if open == '(' { ')' } else { ']' };
+        let Some(len) = rest[start + 1..].find(close) else {
+            break;
+        };
+        let group = &rest[start + 1..start + 1 + len];
+        title.push_str(&rest[..start]);
+        let known = if open == '[' {
+            // Every bracketed group is a GoodTools code, known or not
+            if let Some(tag) = dump_tag(group) {
+                push_tag(&mut parsed.tags, tag);
+            }
+            true
+        } else if let Some(version) = revision(group) {
+            parsed.version.get_or_insert(version);
+            true
+        } else if let Some(tag) = flag_tag(group) {
+            push_tag(&mut parsed.tags, tag);
+            true
+        } else {
+            region::group_regions(group).is_some() || region::group_languages(group).is_some()
+        };
+        if !known {
+            title.push_str(&rest[start..start + len + 2]);
+        }
+        rest = &rest[start + len + 2..];
+    }
+    title.push_str(rest);
+
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    parsed.title = if title.is_empty() {
+        name.trim().to_string()
+    } else {
+        title
+    };
+    parsed
+}
+
+/// The version a group like `Rev A`, `Rev 1`, `v1.1`, or `PRG1` names.
+fn revision(group: &str) -> Option<String> {
+    let lower = group.to_lowercase();
+    if let Some(rev) = lower.strip_prefix("rev ")
+        && !rev.is_empty()
+        && rev.chars().all(|c| c.is_ascii_alphanumeric() || c == '.')
+    {
+        return Some(format!("Rev {}", group[4..].trim().to_uppercase()));
+    }
+    if let Some(number) = lower.strip_prefix('v')
+        && number.starts_with(|c: char| c.is_ascii_digit())
+        && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+    {
+        return Some(number.to_string());
+    }
+    if let Some(number) = lower.strip_prefix("prg")
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit())
+    {
+        return Some(group.to_uppercase());
+    }
+    None
+}
+
+/// The tag for a GoodTools dump code, such as `!` or `b1`.
+fn dump_tag(code: &str) -> Option<&'static str> {
+    if code == "!" {
+        return Some("verified");
+    }
+    if code.starts_with("T+") || code.starts_with("T-") {
+        return Some("translation");
+    }
+    // The letter may be followed by a number or details, e.g. [b1] or [hM04]
+    let mut chars = code.chars();
+    let letter = chars.next()?;
+    let details = chars.as_str();
+    if !details.is_empty() && !details.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some(match letter {
+        'b' => "bad-dump",
+        'o' => "overdump",
+        'h' => "hack",
+        'a' => "alternate",
+        'f' => "fixed",
+        'p' => "pirate",
+        't' => "trainer",
+        _ => return None,
+    })
+}
+
+/// The tag for a No-Intro flag such as `(Beta)` or `(Unl)`.
+fn flag_tag(group: &str) -> Option<&'static str> {
+    let lower = group.to_lowercase();
+    // Numbered flags, e.g. (Beta 2) or (Proto 1)
+    let word = lower
+        .split_once(' ')
+        .filter(|(_, n)| n.chars().all(|c| c.is_ascii_digit()))
+        .map_or(lower.as_str(), |(word, _)| word);
+    Some(match word {
+        "beta" => "beta",
+        "proto" | "prototype" => "prototype",
+        "demo" | "sample" => "demo",
+        "unl" | "unlicensed" => "unlicensed",
+        "hack" => "hack",
+        "pd" => "public-domain",
+        _ => return None,
+    })
+}
+
+/// Parse a TOSEC name, if `name` is one: its first group, after an optional
+/// `(demo)`, is a date, and the one after that the publisher.
+fn tosec(name: &str) -> Option<FileName> {
+    let start = name.find('(')?;
+    let (title, version) = tosec_title(name[..start].trim());
+    let mut parsed = FileName {
+        version,
+        ..Default::default()
+    };
+
+    let mut extra = String::new();
+    let mut dated = false;
+    let mut published = false;
+    let mut translation = None;
+    let mut rest = &name[start..];
+    while let Some(start) = rest.find(['(', '[']) {
+        let open = rest[start..].chars().next().unwrap_or('(');
+        let close = if open == '(' { ')' } else { ']' };
+        let Some(len) = rest[start + 1..].find(close) else {
+            break;
+        };
+        let group = &rest[start + 1..start + 1 + len];
+        extra.push_str(&rest[..start]);
+        let known = if open == '[' {
+            // Bracketed groups are dump flags or notes, dropped like GoodTools codes
+            if let Some((tag, language)) = tosec_flag(group) {
+                push_tag(&mut parsed.tags, tag);
+                translation = translation.or(language);
+            }
+            true
+        } else if !dated {
+            if group.to_lowercase().starts_with("demo") && parsed.tags.is_empty() {
+                push_tag(&mut parsed.tags, "demo");
+            } else {
+                let date = tosec_date(group)?;
+                parsed.release_date = date;
+                dated = true;
+            }
+            true
+        } else if !published {
+            parsed.publisher = (group != "-").then(|| group.to_string());
+            published = true;
+            true
+        } else {
+            tosec_field(group, &mut parsed)
+        };
+        if !known {
+            extra.push_str(&rest[start..start + len + 2]);
+        }
+        rest = &rest[start + len + 2..];
+    }
+    if !dated {
+        return None;
+    }
+    extra.push_str(rest);
+
+    if let Some(language) = translation {
+        parsed.languages = vec![language];
+    } else if parsed.languages.is_empty() {
+        parsed
+            .languages
+            .extend(region::implied_language(&parsed.regions));
+    }
+    let title = format!("{} {}", title, extra);
+    parsed.title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    if parsed.title.is_empty() {
+        parsed.title = name.trim().to_string();
+    }
+    Some(parsed)
+}
+
+/// Split a trailing version, as in `Elite v1.1`, off a TOSEC title.
+fn tosec_title(title: &str) -> (&str, Option<String>) {
+    if let Some((name, last)) = title.rsplit_once(' ')
+        && let Some(number) = last.strip_prefix('v')
+        && number.starts_with(|c: char| c.is_ascii_digit())
+        && number
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.')
+    {
+        return (name.trim_end(), Some(number.to_string()));
+    }
+    (title, None)
+}
+
+/// Whether `group` is a TOSEC date such as `1986`, `198x`, `1986-03`, or
+/// `1986-03-12`, and if so the full date it names, if it names one.
+fn tosec_date(group: &str) -> Option<Option<String>> {
+    let parts: Vec<&str> = group.split('-').collect();
+    let widths_match = parts.len() <= 3
+        && parts
+            .iter()
+            .zip([4, 2, 2])
+            .all(|(part, width)| part.len() == width);
+    let digits_or_unknown = parts.iter().all(|p| {
+        p.chars()
+            .all(|c| c.is_ascii_digit() || c == 'x' || c == 'X')
+    });
+    if !widths_match || !digits_or_unknown || !group.starts_with(['1', '2']) {
+        return None;
+    }
+    Some(
+        NaiveDate::parse_from_str(group, "%Y-%m-%d")
+            .ok()
+            .map(|date| date.format("%Y-%m-%d").to_string()),
+    )
+}
+
+/// Record a TOSEC field after the publisher: a country such as `US` or
+/// `DE-FR`, a language such as `en` or `en-de`, or a copyright, video, or
+/// development status. Returns false if `group` is none of these.
+fn tosec_field(group: &str, parsed: &mut FileName) -> bool {
+    // Copyright statuses such as PD read like countries, so they go first
+    let tag = match group {
+        "PD" => Some("public-domain"),
+        "SW" | "SW-R" => Some("shareware"),
+        "FW" => Some("freeware"),
+        "alpha" => Some("alpha"),
+        "beta" => Some("beta"),
+        "preview" | "pre-release" => Some("prerelease"),
+        "proto" => Some("prototype"),
+        // Video standards and other copyright statuses say nothing to keep
+        "CGA" | "EGA" | "HGC" | "MCGA" | "MDA" | "NTSC" | "NTSC-PAL" | "PAL" | "PAL-60"
+        | "PAL-NTSC" | "SVGA" | "VGA" | "XGA" | "CW" | "CW-R" | "GW" | "GW-R" | "LW" => None,
+        _ => return tosec_locale(group, parsed),
+    };
+    if let Some(tag) = tag {
+        push_tag(&mut parsed.tags, tag);
+    }
+    true
+}
+
+/// Record a TOSEC country group such as `DE-FR` or language group such as
+/// `en-de`. Returns false if `group` is neither.
+fn tosec_locale(group: &str, parsed: &mut FileName) -> bool {
+    let codes = |upper: bool| {
+        group.split('-').all(|code| {
+            code.len() == 2
+                && code.chars().all(|c| {
+                    if upper {
+                        c.is_ascii_uppercase()
+                    } else {
+                        c.is_ascii_lowercase()
+                    }
+                })
+        })
+    };
+    if codes(true) {
+        for code in group.split('-') {
+            let found = Region::known(code).unwrap_or_else(|| Region::Other(code.to_string()));
+            if !parsed.regions.contains(&found) {
+                parsed.regions.push(found);
+            }
+        }
+        return true;
+    }
+    if codes(false) {
+        for code in group.split('-') {
+            let found = Language::known(code).unwrap_or_else(|| Language::Other(code.to_string()));
+            if !parsed.languages.contains(&found) {
+                parsed.languages.push(found);
+            }
+        }
+        return true;
+    }
+    false
+}
+
+/// The tag for a TOSEC dump flag such as `[cr Fairlight]` or `[a2]`, and
+/// for a translation like `[tr de]`, the language it's into.
+fn tosec_flag(group: &str) -> Option<(&'static str, Option<Language>)> {
+    if group == "!" {
+        return Some(("verified", None));
+    }
+    let (code, details) = group.split_once(' ').unwrap_or((group, ""));
+    // Flags may be numbered, e.g. [a2] or [cr2 Fairlight]
+    let code = code.trim_end_matches(|c: char| c.is_ascii_digit());
+    let tag = match code {
+        "a" => "alternate",
+        "b" => "bad-dump",
+        "cr" => "cracked",
+        "f" => "fixed",
+        "h" => "hack",
+        "m" => "modified",
+        "o" => "overdump",
+        "p" => "pirate",
+        "t" => "trainer",
+        "tr" => {
+            let language = details.split_whitespace().next().and_then(Language::known);
+            return Some(("translation", language));
+        }
+        "u" => "underdump",
+        "v" => "virus",
+        _ => return None,
+    };
+    Some((tag, None))
+}
+
+fn push_tag(tags: &mut Vec<String>, tag: &str) {
+    if !tags.iter().any(|t| t == tag) {
+        tags.push(tag.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_goodtools_name() {
+        let parsed = parse("Super Mario Bros. 3 (U) (PRG1) [!]");
+        assert_eq!(parsed.title, "Super Mario Bros. 3");
+        assert_eq!(parsed.version.as_deref(), Some("PRG1"));
+        assert_eq!(parsed.tags, vec!["verified"]);
+        assert_eq!(parsed.regions, vec![Region::Usa]);
+        assert_eq!(parsed.languages, vec![Language::English]);
+
+        let parsed = parse("Mario (E) [T+Fre1.0_Author] [b1]");
+        assert_eq!(parsed.title, "Mario");
+        assert_eq!(parsed.tags, vec!["translation", "bad-dump"]);
+        assert_eq!(parsed.languages, vec![Language::French]);
+
+        let parsed = parse("Kid Icarus (J) [hM04] (Hack by Someone)");
+        assert_eq!(parsed.title, "Kid Icarus (Hack by Someone)");
+        assert_eq!(parsed.tags, vec!["hack"]);
+    }
+
+    #[test]
+    fn test_parse_no_intro_name() {
+        let parsed = parse("Legend of Zelda, The (Japan, USA) (En,Ja) (Rev a) (Beta 2)");
+        assert_eq!(parsed.title, "Legend of Zelda, The");
+        assert_eq!(parsed.version.as_deref(), Some("Rev A"));
+        assert_eq!(parsed.tags, vec!["beta"]);
+        assert_eq!(parsed.regions, vec![Region::Japan, Region::Usa]);
+        assert_eq!(
+            parsed.languages,
+            vec![Language::English, Language::Japanese]
+        );
+
+        let parsed = parse("Tetris (USA) (v1.1) (Unl)");
+        assert_eq!(parsed.title, "Tetris");
+        assert_eq!(parsed.version.as_deref(), Some("1.1"));
+        assert_eq!(parsed.tags, vec!["unlicensed"]);
+    }
+
+    #[test]
+    fn test_parse_tosec_name() {
+        let parsed = parse("Elite v1.1 (1985)(Acornsoft)(GB)(en)[cr Fairlight][a2]");
+        assert_eq!(parsed.title, "Elite");
+        assert_eq!(parsed.version.as_deref(), Some("1.1"));
+        assert_eq!(parsed.release_date, None);
+        assert_eq!(parsed.publisher.as_deref(), Some("Acornsoft"));
+        assert_eq!(parsed.regions, vec![Region::Other("GB".into())]);
+        assert_eq!(parsed.languages, vec![Language::English]);
+        assert_eq!(parsed.tags, vec!["cracked", "alternate"]);
+
+        let parsed = parse("Turrican (demo) (1990-05-21)(-)(DE)(PD)(Disk 1 of 2)[tr en]");
+        assert_eq!(parsed.title, "Turrican (Disk 1 of 2)");
+        assert_eq!(parsed.release_date.as_deref(), Some("1990-05-21"));
+        assert_eq!(parsed.publisher, None);
+        assert_eq!(parsed.regions, vec![Region::Germany]);
+        assert_eq!(parsed.languages, vec![Language::English]);
+        assert_eq!(parsed.tags, vec!["demo", "public-domain", "translation"]);
+
+        // An implausible date isn't one
+        let parsed = parse("Game (1986-02-31)(Ocean)(US-EU)(PAL)(beta)");
+        assert_eq!(parsed.title, "Game");
+        assert_eq!(parsed.release_date, None);
+        assert_eq!(parsed.regions, vec![Region::Usa, Region::Europe]);
+        assert_eq!(parsed.tags, vec!["beta"]);
+    }
+
+    #[test]
+    fn test_parse_plain_name() {
+        assert_eq!(parse("my_hack").title, "my_hack");
+        assert_eq!(parse("Zelda (unfinished").title, "Zelda (unfinished");
+        // A name that is all codes keeps them rather than going blank
+        assert_eq!(parse("(U) [!]").title, "(U) [!]");
+        assert_eq!(parse("Game (Revolution)").title, "Game (Revolution)");
+        // A year that isn't the first group doesn't make a TOSEC name
+        assert_eq!(parse("Tetris (USA) (1989)").title, "Tetris (1989)");
+    }
+}
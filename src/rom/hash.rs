@@ -1,11 +1,15 @@
+use sha1::Sha1;
 use sha2::{Digest, Sha256};
 use std::fs::File;
 use std::io::{BufReader, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use crate::cancel::CancelToken;
 use crate::error::{DromosError, Result};
+use crate::progress::{Progress, ProgressSink, Stage};
 use crate::rom::nes::{parse_nes_header_bytes, skip_trainer_if_present};
 use crate::rom::types::{RomMetadata, RomType};
+use crate::timings::{self, Phase};
 
 /// Hash bytes directly using SHA-256. Pure function for testability.
 pub fn hash_bytes(data: &[u8]) -> [u8; 32] {
@@ -21,8 +25,100 @@ fn detect_rom_type(path: &Path) -> Option<RomType> {
     }
 }
 
-fn hash_remaining(reader: &mut impl Read) -> Result<[u8; 32]> {
-    let mut hasher = Sha256::new();
+/// Check whether a path has a supported ROM file extension.
+pub fn is_rom_file(path: &Path) -> bool {
+    detect_rom_type(path).is_some()
+}
+
+/// Recursively find all files with supported ROM extensions under `dir`.
+/// Results are sorted by path so scans are reproducible.
+pub fn find_rom_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file() && is_rom_file(&path) {
+                found.push(path);
+            }
+        }
+    }
+
+    found.sort();
+    Ok(found)
+}
+
+/// Expand glob patterns (e.g. `roms/*.nes`) in file arguments, since shells
+/// on Windows don't. Arguments without wildcards pass through unchanged so a
+/// missing file is still reported by whoever opens it, and so do files that
+/// exist, such as `Game (U) [!].nes`. A pattern that matches nothing is an
+/// error.
+#[cfg(feature = "native")]
+pub fn expand_file_patterns(args: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for arg in args {
+        let pattern = arg.to_string_lossy();
+        if !pattern.contains(['*', '?', '[']) || arg.is_file() {
+            files.push(arg.clone());
+            continue;
+        }
+
+        let paths = glob::glob(&pattern)
+            .map_err(|e| DromosError::InvalidPattern(format!("{} ({})", pattern, e)))?;
+        let mut matched: Vec<PathBuf> = paths
+            .filter_map(|p| p.ok())
+            .filter(|p| p.is_file())
+            .collect();
+        if matched.is_empty() {
+            return Err(DromosError::FileNotFound { path: arg.clone() });
+        }
+        matched.sort();
+        files.extend(matched);
+    }
+    Ok(files)
+}
+
+/// SHA-256, plus the SHA-1 and CRC32 that DAT files list, over the same data.
+#[derive(Default)]
+struct RomHasher {
+    sha256: Sha256,
+    sha1: Sha1,
+    crc32: crc32fast::Hasher,
+    len: u64,
+}
+
+struct RomHashes {
+    sha256: [u8; 32],
+    sha1: [u8; 20],
+    crc32: u32,
+    len: u64,
+}
+
+impl RomHasher {
+    fn update(&mut self, data: &[u8]) {
+        self.sha256.update(data);
+        self.sha1.update(data);
+        self.crc32.update(data);
+        self.len += data.len() as u64;
+    }
+
+    fn finish(self) -> RomHashes {
+        RomHashes {
+            sha256: self.sha256.finalize().into(),
+            sha1: self.sha1.finalize().into(),
+            crc32: self.crc32.finalize(),
+            len: self.len,
+        }
+    }
+}
+
+fn hash_remaining(reader: &mut impl Read) -> Result<RomHashes> {
+    let mut hasher = RomHasher::default();
     let mut buffer = [0u8; 8192];
 
     loop {
@@ -33,11 +129,12 @@ fn hash_remaining(reader: &mut impl Read) -> Result<[u8; 32]> {
         hasher.update(&buffer[..bytes_read]);
     }
 
-    Ok(hasher.finalize().into())
+    Ok(hasher.finish())
 }
 
 pub fn hash_rom_file(path: &Path) -> Result<RomMetadata> {
-    let file = File::open(path)?;
+    let _span = timings::span(Phase::Hashing);
+    let file = File::open(path).map_err(DromosError::file(path))?;
     let mut reader = BufReader::new(file);
 
     let filename = path.file_name().map(|s| s.to_string_lossy().into_owned());
@@ -51,11 +148,14 @@ pub fn hash_rom_file(path: &Path) -> Result<RomMetadata> {
             match parse_nes_header_bytes(&header_bytes) {
                 Some(header) => {
                     skip_trainer_if_present(&mut reader, &header)?;
-                    let sha256 = hash_remaining(&mut reader)?;
+                    let hashes = hash_remaining(&mut reader)?;
 
                     Ok(RomMetadata {
                         rom_type: RomType::Nes,
-                        sha256,
+                        sha256: hashes.sha256,
+                        sha1: Some(hashes.sha1),
+                        crc32: Some(hashes.crc32),
+                        content_size: Some(hashes.len),
                         filename,
                         nes_header: Some(header),
                         source_file_header: Some(header_bytes.to_vec()),
@@ -79,6 +179,54 @@ pub fn hash_rom_file(path: &Path) -> Result<RomMetadata> {
     }
 }
 
+/// Hash each of `files` in order, reporting progress per file. A file that
+/// can't be hashed gets its error in place, without stopping the rest;
+/// cancelling stops them all with [`DromosError::Cancelled`].
+pub fn hash_rom_files(
+    files: &[PathBuf],
+    progress: &mut dyn ProgressSink,
+    cancel: &CancelToken,
+) -> Result<Vec<Result<RomMetadata>>> {
+    files
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            cancel.check()?;
+            progress.progress(&Progress {
+                stage: Stage::Hashing,
+                item: &path.display().to_string(),
+                current: i + 1,
+                total: files.len(),
+                bytes: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+            });
+            Ok(hash_rom_file(path))
+        })
+        .collect()
+}
+
+/// Hash an in-memory NES file the same way [`hash_rom_file`] hashes one on
+/// disk, for ROMs that arrive as bytes (e.g. in a web page). Returns None if
+/// `data` doesn't start with a valid NES header.
+pub fn hash_nes_bytes(data: &[u8], filename: Option<String>) -> Option<RomMetadata> {
+    let _span = timings::span(Phase::Hashing);
+    let header_bytes: [u8; 16] = data.get(..16)?.try_into().ok()?;
+    let header = parse_nes_header_bytes(&header_bytes)?;
+    let rom_start = if header.has_trainer { 16 + 512 } else { 16 };
+    let mut hasher = RomHasher::default();
+    hasher.update(data.get(rom_start..).unwrap_or_default());
+    let hashes = hasher.finish();
+    Some(RomMetadata {
+        rom_type: RomType::Nes,
+        sha256: hashes.sha256,
+        sha1: Some(hashes.sha1),
+        crc32: Some(hashes.crc32),
+        content_size: Some(hashes.len),
+        filename,
+        nes_header: Some(header),
+        source_file_header: Some(header_bytes.to_vec()),
+    })
+}
+
 pub fn format_hash(hash: &[u8; 32]) -> String {
     hex::encode(hash)
 }
@@ -92,7 +240,7 @@ pub fn parse_hash(s: &str) -> Option<[u8; 32]> {
 }
 
 pub fn read_rom_bytes(path: &Path) -> Result<Vec<u8>> {
-    let file = File::open(path)?;
+    let file = File::open(path).map_err(DromosError::file(path))?;
     let mut reader = BufReader::new(file);
 
     match detect_rom_type(path) {
@@ -206,8 +354,8 @@ mod tests {
     #[test]
     fn test_format_parse_round_trip() {
         let mut original = [0u8; 32];
-        for i in 0..32 {
-            original[i] = i as u8;
+        for (i, byte) in original.iter_mut().enumerate() {
+            *byte = i as u8;
         }
 
         let formatted = format_hash(&original);
@@ -216,6 +364,72 @@ mod tests {
         assert_eq!(original, parsed);
     }
 
+    #[test]
+    fn test_find_rom_files_recursive() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let nested = temp_dir.path().join("hacks").join("deep");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(temp_dir.path().join("base.nes"), b"x").unwrap();
+        std::fs::write(nested.join("hack.NES"), b"x").unwrap();
+        std::fs::write(nested.join("readme.txt"), b"x").unwrap();
+
+        let found = find_rom_files(temp_dir.path()).unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().any(|p| p.ends_with("base.nes")));
+        assert!(found.iter().any(|p| p.ends_with("hack.NES")));
+    }
+
+    #[test]
+    fn test_hash_nes_bytes_matches_file() {
+        // Header with a trainer (flags 6 bit 2), which hashing skips
+        let mut data = vec![
+            b'N', b'E', b'S', 0x1A, 1, 1, 0x04, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        data.extend([0xEE; 512]);
+        data.extend((0..=255u8).cycle().take(0x6000));
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("game.nes");
+        std::fs::write(&path, &data).unwrap();
+
+        let from_file = hash_rom_file(&path).unwrap();
+        let from_bytes = hash_nes_bytes(&data, None).unwrap();
+        assert_eq!(from_bytes.sha256, from_file.sha256);
+        assert_eq!(from_bytes.source_file_header, from_file.source_file_header);
+        assert_eq!(from_file.content_size, Some(0x6000));
+        assert_eq!(from_bytes.content_size, Some(0x6000));
+
+        assert!(hash_nes_bytes(b"not a rom", None).is_none());
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_expand_file_patterns() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("b.nes"), b"x").unwrap();
+        std::fs::write(temp_dir.path().join("a.nes"), b"x").unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), b"x").unwrap();
+
+        let pattern = temp_dir.path().join("*.nes");
+        let plain = PathBuf::from("missing.nes");
+        let files = expand_file_patterns(&[pattern, plain.clone()]).unwrap();
+        assert_eq!(files.len(), 3);
+        assert!(files[0].ends_with("a.nes"));
+        assert!(files[1].ends_with("b.nes"));
+        assert_eq!(files[2], plain);
+
+        let bracketed = temp_dir.path().join("Game (U) [!].nes");
+        std::fs::write(&bracketed, b"x").unwrap();
+        let files = expand_file_patterns(std::slice::from_ref(&bracketed)).unwrap();
+        assert_eq!(files, vec![bracketed]);
+
+        let no_match = temp_dir.path().join("*.fds");
+        assert!(matches!(
+            expand_file_patterns(&[no_match]),
+            Err(DromosError::FileNotFound { .. })
+        ));
+    }
+
     #[test]
     fn test_detect_rom_type() {
         use std::path::Path;
@@ -1,7 +1,19 @@
+pub mod compare;
+pub mod filename;
 pub mod hash;
 pub mod nes;
+pub mod region;
 pub mod types;
 
-pub use hash::{format_hash, hash_rom_file, parse_hash, read_rom_bytes};
-pub use nes::{build_nes_header, reconstruct_nes_file, reconstruct_nes_file_raw};
-pub use types::{Mirroring, NesHeader, RomMetadata, RomType};
+#[cfg(feature = "native")]
+pub use hash::expand_file_patterns;
+pub use hash::{
+    find_rom_files, format_hash, hash_nes_bytes, hash_rom_file, hash_rom_files, is_rom_file,
+    parse_hash, read_rom_bytes,
+};
+pub use nes::{
+    HeaderProblem, build_nes_header, header_problems, mapper_name, nes_file_problems,
+    parse_nes_header_bytes, parse_nes2_details, reconstruct_nes_file, reconstruct_nes_file_raw,
+};
+pub use region::{Language, Region};
+pub use types::{Mirroring, Nes2Details, NesHeader, NesTiming, RomMetadata, RomType};
@@ -1,7 +1,10 @@
+use std::fmt;
+use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
 
-use crate::error::Result;
-use crate::rom::types::{Mirroring, NesHeader};
+use crate::error::{DromosError, Result};
+use crate::rom::types::{Mirroring, Nes2Details, NesHeader, NesTiming};
 
 /// Parse a 16-byte iNES/NES 2.0 header from raw bytes.
 /// Returns None if the magic bytes are invalid.
@@ -58,6 +61,195 @@ pub fn parse_nes_header_bytes(header: &[u8; 16]) -> Option<NesHeader> {
     })
 }
 
+/// Parse the NES 2.0-only fields (RAM sizes and timing) from a raw header.
+/// Returns None if the header is not a valid NES 2.0 header.
+pub fn parse_nes2_details(header: &[u8; 16]) -> Option<Nes2Details> {
+    if &header[0..4] != b"NES\x1a" || (header[7] & 0x0C) != 0x08 {
+        return None;
+    }
+
+    // RAM sizes are stored as shift counts: 0 means none, otherwise 64 << n bytes
+    let shift_size = |n: u8| if n == 0 { 0 } else { 64usize << n };
+
+    let timing = match header[12] & 0x03 {
+        0 => NesTiming::Ntsc,
+        1 => NesTiming::Pal,
+        2 => NesTiming::MultiRegion,
+        _ => NesTiming::Dendy,
+    };
+
+    Some(Nes2Details {
+        prg_ram_size: shift_size(header[10] & 0x0F),
+        prg_nvram_size: shift_size(header[10] >> 4),
+        chr_ram_size: shift_size(header[11] & 0x0F),
+        chr_nvram_size: shift_size(header[11] >> 4),
+        timing,
+    })
+}
+
+/// Common name for a mapper number, if it is a well-known board.
+pub fn mapper_name(mapper: u16) -> Option<&'static str> {
+    let name = match mapper {
+        0 => "NROM",
+        1 => "MMC1",
+        2 => "UxROM",
+        3 => "CNROM",
+        4 => "MMC3",
+        5 => "MMC5",
+        7 => "AxROM",
+        9 => "MMC2",
+        10 => "MMC4",
+        11 => "Color Dreams",
+        13 => "CPROM",
+        16 => "Bandai FCG",
+        19 => "Namco 163",
+        21 | 23 | 25 => "VRC4",
+        22 => "VRC2",
+        24 | 26 => "VRC6",
+        34 => "BNROM / NINA-001",
+        66 => "GxROM",
+        69 => "Sunsoft FME-7",
+        71 => "Camerica",
+        85 => "VRC7",
+        118 => "TxSROM",
+        119 => "TQROM",
+        206 => "DxROM",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// A way a NES header contradicts itself or the file it's in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderProblem {
+    /// The header declares no PRG ROM, which every cartridge has
+    NoPrgRom,
+    /// The ROM data after the header (and trainer) isn't the size the
+    /// header declares
+    SizeMismatch { declared: u64, actual: u64 },
+    /// Bytes an iNES 1.0 header leaves unused hold junk (such as a ripper's
+    /// "DiskDude!" tag), which garbles the upper bits of the mapper number
+    JunkInUnusedBytes,
+    /// A NES 2.0 submapper the mapper doesn't define
+    UndefinedSubmapper { mapper: u16, submapper: u8 },
+}
+
+impl fmt::Display for HeaderProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderProblem::NoPrgRom => write!(f, "declares no PRG ROM"),
+            HeaderProblem::SizeMismatch { declared, actual } => write!(
+                f,
+                "declares {} bytes of ROM data, but the file has {}",
+                declared, actual
+            ),
+            HeaderProblem::JunkInUnusedBytes => {
+                write!(
+                    f,
+                    "has junk in its unused bytes, garbling the mapper number"
+                )
+            }
+            HeaderProblem::UndefinedSubmapper { mapper, submapper } => write!(
+                f,
+                "gives submapper {}, which mapper {} doesn't define",
+                submapper, mapper
+            ),
+        }
+    }
+}
+
+/// Check a raw header against itself and the `data_len` bytes of ROM data
+/// that follow it (and its trainer). Returns nothing for bytes that aren't a
+/// NES header at all.
+pub fn header_problems(header: &[u8; 16], data_len: u64) -> Vec<HeaderProblem> {
+    let Some(parsed) = parse_nes_header_bytes(header) else {
+        return Vec::new();
+    };
+    let mut problems = Vec::new();
+
+    let (prg, chr) = declared_rom_sizes(header);
+    if prg == 0 {
+        problems.push(HeaderProblem::NoPrgRom);
+    }
+    // NES 2.0 headers can declare miscellaneous ROMs stored after CHR ROM
+    let has_misc_rom = parsed.is_nes2 && header[14] & 0x03 != 0;
+    let declared = prg.saturating_add(chr);
+    if data_len < declared || (data_len > declared && !has_misc_rom) {
+        problems.push(HeaderProblem::SizeMismatch {
+            declared,
+            actual: data_len,
+        });
+    }
+
+    let junk = match header[7] & 0x0C {
+        0x00 => header[12..].iter().any(|&b| b != 0),
+        0x08 => false,
+        _ => true,
+    };
+    if junk {
+        problems.push(HeaderProblem::JunkInUnusedBytes);
+    }
+
+    if let Some(submapper) = parsed.submapper
+        && defined_submappers(parsed.mapper).is_some_and(|defined| !defined.contains(&submapper))
+    {
+        problems.push(HeaderProblem::UndefinedSubmapper {
+            mapper: parsed.mapper,
+            submapper,
+        });
+    }
+    problems
+}
+
+/// [`header_problems`] for the NES file at `path`.
+pub fn nes_file_problems(path: &Path) -> Result<Vec<HeaderProblem>> {
+    let mut file = File::open(path).map_err(DromosError::file(path))?;
+    let len = file.metadata().map_err(DromosError::file(path))?.len();
+    let mut header = [0u8; 16];
+    if file.read_exact(&mut header).is_err() {
+        return Ok(Vec::new());
+    }
+    let trainer = if header[6] & 0x04 != 0 { 512 } else { 0 };
+    Ok(header_problems(&header, len.saturating_sub(16 + trainer)))
+}
+
+/// PRG and CHR ROM sizes in bytes as declared, counting NES 2.0's upper size
+/// bits and exponent-multiplier notation.
+fn declared_rom_sizes(header: &[u8; 16]) -> (u64, u64) {
+    let is_nes2 = (header[7] & 0x0C) == 0x08;
+    let size = |lsb: u8, msb: u8, unit: u64| {
+        if !is_nes2 {
+            lsb as u64 * unit
+        } else if msb == 0x0F {
+            // 2^E * (MM * 2 + 1), with E in the upper six bits
+            let multiplier = (lsb & 0x03) as u64 * 2 + 1;
+            (1u64 << (lsb >> 2)).saturating_mul(multiplier)
+        } else {
+            (((msb as u64) << 8) | lsb as u64) * unit
+        }
+    };
+    (
+        size(header[4], header[9] & 0x0F, 16 * 1024),
+        size(header[5], header[9] >> 4, 8 * 1024),
+    )
+}
+
+/// The submappers NES 2.0 defines for a mapper, for the mappers whose
+/// submappers are settled; None when any submapper is accepted.
+fn defined_submappers(mapper: u16) -> Option<&'static [u8]> {
+    match mapper {
+        // UxROM, CNROM, and AxROM: without and with AND-type bus conflicts
+        2 | 3 | 7 => Some(&[1, 2]),
+        // NINA-001 and BNROM
+        34 => Some(&[1, 2]),
+        // Fire Hawk
+        71 => Some(&[1]),
+        // Cosmo Carrier and Holy Diver
+        78 => Some(&[1, 3]),
+        _ => None,
+    }
+}
+
 /// Parse NES header from a reader. Thin I/O wrapper around parse_nes_header_bytes.
 pub fn parse_nes_header(reader: &mut impl Read) -> Result<Option<NesHeader>> {
     let mut header = [0u8; 16];
@@ -191,6 +383,75 @@ mod tests {
         assert_eq!(parsed.mapper, 0x201);
     }
 
+    #[test]
+    fn test_parse_nes2_details() {
+        let mut header = make_ines_header(2, 1, 0x12, 0x08);
+        header[10] = 0x70; // 8KB PRG-NVRAM, no PRG-RAM
+        header[11] = 0x07; // 8KB CHR-RAM
+        header[12] = 0x01; // PAL
+
+        let details = parse_nes2_details(&header).expect("Should parse NES 2.0 details");
+        assert_eq!(details.prg_ram_size, 0);
+        assert_eq!(details.prg_nvram_size, 8 * 1024);
+        assert_eq!(details.chr_ram_size, 8 * 1024);
+        assert_eq!(details.chr_nvram_size, 0);
+        assert_eq!(details.timing, NesTiming::Pal);
+
+        // iNES 1.0 headers have no NES 2.0 details
+        let header = make_ines_header(2, 1, 0x00, 0x00);
+        assert!(parse_nes2_details(&header).is_none());
+    }
+
+    #[test]
+    fn test_header_problems() {
+        // 2 PRG banks, 1 CHR bank
+        let header = make_ines_header(2, 1, 0x00, 0x00);
+        let size = 2 * 16 * 1024 + 8 * 1024;
+        assert!(header_problems(&header, size).is_empty());
+        assert_eq!(
+            header_problems(&header, size - 1),
+            vec![HeaderProblem::SizeMismatch {
+                declared: size,
+                actual: size - 1,
+            }]
+        );
+
+        let no_prg = make_ines_header(0, 1, 0x00, 0x00);
+        assert!(header_problems(&no_prg, 8 * 1024).contains(&HeaderProblem::NoPrgRom));
+
+        let mut disk_dude = header;
+        disk_dude[7..16].copy_from_slice(b"DiskDude!");
+        assert_eq!(
+            header_problems(&disk_dude, size),
+            vec![HeaderProblem::JunkInUnusedBytes]
+        );
+
+        // UxROM (mapper 2) defines submappers 1 and 2 only
+        let mut uxrom = make_ines_header(2, 1, 0x20, 0x08);
+        uxrom[8] = 0x20;
+        assert!(header_problems(&uxrom, size).is_empty());
+        uxrom[8] = 0x30;
+        assert_eq!(
+            header_problems(&uxrom, size),
+            vec![HeaderProblem::UndefinedSubmapper {
+                mapper: 2,
+                submapper: 3,
+            }]
+        );
+
+        // NES 2.0 upper size bits: 0x102 PRG banks
+        let mut large = make_ines_header(2, 0, 0x00, 0x08);
+        large[9] = 0x01;
+        assert!(header_problems(&large, 0x102 * 16 * 1024).is_empty());
+    }
+
+    #[test]
+    fn test_mapper_name() {
+        assert_eq!(mapper_name(0), Some("NROM"));
+        assert_eq!(mapper_name(4), Some("MMC3"));
+        assert_eq!(mapper_name(4000), None);
+    }
+
     #[test]
     fn test_parse_invalid_magic() {
         let mut header = [0u8; 16];
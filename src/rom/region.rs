@@ -0,0 +1,516 @@
+//! Regions a ROM was released for and languages its text is in.
+//!
+//! Both are enums of the common values with an `Other` variant for the
+//! rest, so anything a user types is kept. Users can name a region by name,
+//! GoodTools code, or country code (`Japan`, `J`, `JP`) and a language by
+//! code, name, or the three-letter code of translation tags (`en`,
+//! `English`, `Eng`), in any case.
+//!
+//! [`detect`] reads both from file naming conventions: No-Intro's
+//! `(Japan, USA)` and `(En,Fr,De)`, GoodTools' `(J)` and `(JU)`, and
+//! translation tags such as `[T+Eng]`.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A region a ROM was released for.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", from = "String")
+)]
+pub enum Region {
+    Japan,
+    Usa,
+    Europe,
+    World,
+    Asia,
+    Australia,
+    Brazil,
+    Canada,
+    China,
+    France,
+    Germany,
+    HongKong,
+    Italy,
+    Korea,
+    Netherlands,
+    Spain,
+    Sweden,
+    Taiwan,
+    Other(String),
+}
+
+type RegionEntry = (
+    Region,
+    &'static str,
+    &'static str,
+    &'static [&'static str],
+    Option<Language>,
+);
+
+/// Known regions: name, GoodTools code, other accepted spellings, and the
+/// language releases there are usually in.
+const REGIONS: &[RegionEntry] = &[
+    (
+        Region::Japan,
+        "Japan",
+        "J",
+        &["JP", "JPN"],
+        Some(Language::Japanese),
+    ),
+    (Region::Usa, "USA", "U", &["US"], Some(Language::English)),
+    (Region::Europe, "Europe", "E", &["EU", "EUR"], None),
+    (Region::World, "World", "W", &[], None),
+    (Region::Asia, "Asia", "As", &[], None),
+    (
+        Region::Australia,
+        "Australia",
+        "A",
+        &["AU"],
+        Some(Language::English),
+    ),
+    (
+        Region::Brazil,
+        "Brazil",
+        "B",
+        &["BR"],
+        Some(Language::Portuguese),
+    ),
+    (Region::Canada, "Canada", "Ca", &[], None),
+    (
+        Region::China,
+        "China",
+        "Ch",
+        &["C", "CN"],
+        Some(Language::Chinese),
+    ),
+    (
+        Region::France,
+        "France",
+        "F",
+        &["FR"],
+        Some(Language::French),
+    ),
+    (
+        Region::Germany,
+        "Germany",
+        "G",
+        &["DE"],
+        Some(Language::German),
+    ),
+    (
+        Region::HongKong,
+        "Hong Kong",
+        "HK",
+        &[],
+        Some(Language::Chinese),
+    ),
+    (
+        Region::Italy,
+        "Italy",
+        "I",
+        &["IT"],
+        Some(Language::Italian),
+    ),
+    (Region::Korea, "Korea", "K", &["KR"], Some(Language::Korean)),
+    (
+        Region::Netherlands,
+        "Netherlands",
+        "Nl",
+        &["NL"],
+        Some(Language::Dutch),
+    ),
+    (
+        Region::Spain,
+        "Spain",
+        "S",
+        &["ES"],
+        Some(Language::Spanish),
+    ),
+    (
+        Region::Sweden,
+        "Sweden",
+        "Sw",
+        &["SE"],
+        Some(Language::Swedish),
+    ),
+    (
+        Region::Taiwan,
+        "Taiwan",
+        "Tw",
+        &["TW"],
+        Some(Language::Chinese),
+    ),
+];
+
+impl Region {
+    /// The name regions are stored and shown by, e.g. `USA`.
+    pub fn name(&self) -> &str {
+        match self {
+            Region::Other(name) => name,
+            known => REGIONS
+                .iter()
+                .find(|(region, ..)| region == known)
+                .map_or("", |(_, name, ..)| name),
+        }
+    }
+
+    /// The known region `text` names, ignoring case.
+    pub fn known(text: &str) -> Option<Region> {
+        let text = text.trim();
+        REGIONS
+            .iter()
+            .find(|(_, name, code, aliases, _)| {
+                name.eq_ignore_ascii_case(text)
+                    || code.eq_ignore_ascii_case(text)
+                    || aliases.iter().any(|a| a.eq_ignore_ascii_case(text))
+            })
+            .map(|(region, ..)| region.clone())
+    }
+
+    /// The language releases for this region are usually in, if one is.
+    pub fn language(&self) -> Option<Language> {
+        REGIONS
+            .iter()
+            .find(|(region, ..)| region == self)
+            .and_then(|(.., language)| language.clone())
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("region can't be empty".to_string());
+        }
+        Ok(Region::known(s).unwrap_or_else(|| Region::Other(s.to_string())))
+    }
+}
+
+impl From<String> for Region {
+    fn from(s: String) -> Self {
+        s.parse().unwrap_or(Region::Other(s))
+    }
+}
+
+impl From<Region> for String {
+    fn from(region: Region) -> Self {
+        region.name().to_string()
+    }
+}
+
+/// A language a ROM's text is in.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", from = "String")
+)]
+pub enum Language {
+    English,
+    Japanese,
+    French,
+    German,
+    Spanish,
+    Italian,
+    Dutch,
+    Portuguese,
+    Swedish,
+    Danish,
+    Norwegian,
+    Finnish,
+    Polish,
+    Russian,
+    Korean,
+    Chinese,
+    Other(String),
+}
+
+/// Known languages: ISO 639-1 code, name, and other accepted spellings
+/// (the three-letter codes translation tags use).
+const LANGUAGES: &[(Language, &str, &str, &[&str])] = &[
+    (Language::English, "en", "English", &["eng"]),
+    (Language::Japanese, "ja", "Japanese", &["jpn", "jap"]),
+    (Language::French, "fr", "French", &["fre", "fra"]),
+    (Language::German, "de", "German", &["ger", "deu"]),
+    (Language::Spanish, "es", "Spanish", &["spa"]),
+    (Language::Italian, "it", "Italian", &["ita"]),
+    (Language::Dutch, "nl", "Dutch", &["dut", "nld"]),
+    (Language::Portuguese, "pt", "Portuguese", &["por", "bra"]),
+    (Language::Swedish, "sv", "Swedish", &["swe"]),
+    (Language::Danish, "da", "Danish", &["dan"]),
+    (Language::Norwegian, "no", "Norwegian", &["nor"]),
+    (Language::Finnish, "fi", "Finnish", &["fin"]),
+    (Language::Polish, "pl", "Polish", &["pol"]),
+    (Language::Russian, "ru", "Russian", &["rus"]),
+    (Language::Korean, "ko", "Korean", &["kor"]),
+    (Language::Chinese, "zh", "Chinese", &["chi", "zho"]),
+];
+
+impl Language {
+    /// The code languages are stored and shown by, e.g. `en`.
+    pub fn code(&self) -> &str {
+        match self {
+            Language::Other(code) => code,
+            known => LANGUAGES
+                .iter()
+                .find(|(language, ..)| language == known)
+                .map_or("", |(_, code, ..)| code),
+        }
+    }
+
+    /// The known language `text` names, ignoring case.
+    pub fn known(text: &str) -> Option<Language> {
+        let text = text.trim();
+        LANGUAGES
+            .iter()
+            .find(|(_, code, name, aliases)| {
+                code.eq_ignore_ascii_case(text)
+                    || name.eq_ignore_ascii_case(text)
+                    || aliases.iter().any(|a| a.eq_ignore_ascii_case(text))
+            })
+            .map(|(language, ..)| language.clone())
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("language can't be empty".to_string());
+        }
+        Ok(Language::known(s).unwrap_or_else(|| Language::Other(s.to_string())))
+    }
+}
+
+impl From<String> for Language {
+    fn from(s: String) -> Self {
+        s.parse().unwrap_or(Language::Other(s))
+    }
+}
+
+impl From<Language> for String {
+    fn from(language: Language) -> Self {
+        language.code().to_string()
+    }
+}
+
+/// Parse a comma-separated list, such as `Japan, USA` or `en,fr`, dropping
+/// repeats.
+pub fn parse_list<T: FromStr<Err = String> + PartialEq>(text: &str) -> Result<Vec<T>, String> {
+    let mut items = Vec::new();
+    for part in text.split(',').filter(|p| !p.trim().is_empty()) {
+        let item = part.parse()?;
+        if !items.contains(&item) {
+            items.push(item);
+        }
+    }
+    Ok(items)
+}
+
+/// Join a list the way it's stored and shown, e.g. `Japan, USA`.
+pub fn join_list<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The regions and languages a file name shows, as far as its naming
+/// conventions tell. Without a language tag, releases for one region (or
+/// regions sharing a language) are taken to be in that region's language.
+pub fn detect(filename: &str) -> (Vec<Region>, Vec<Language>) {
+    let mut regions = Vec::new();
+    let mut languages = Vec::new();
+    let mut translation = None;
+    for (open, group) in groups(filename) {
+        if open == '[' {
+            // GoodTools translation tags, e.g. [T+Eng1.0_Author] or [T-Fre]
+            let code = group
+                .strip_prefix("T+")
+                .or_else(|| group.strip_prefix("T-"))
+                .and_then(|rest| rest.get(..3));
+            if let Some(language) = code.and_then(Language::known) {
+                translation.get_or_insert(language);
+            }
+            continue;
+        }
+        if let Some(found) = group_regions(group) {
+            push_new(&mut regions, found);
+        } else if let Some(found) = group_languages(group) {
+            push_new(&mut languages, found);
+        }
+    }
+
+    if let Some(language) = translation {
+        languages = vec![language];
+    } else if languages.is_empty() {
+        languages.extend(implied_language(&regions));
+    }
+    (regions, languages)
+}
+
+/// The language all of `regions` share, if each has one and it's the same.
+pub(super) fn implied_language(regions: &[Region]) -> Option<Language> {
+    let mut implied: Option<Language> = None;
+    for region in regions {
+        let language = region.language()?;
+        if implied.as_ref().is_some_and(|l| *l != language) {
+            return None;
+        }
+        implied = Some(language);
+    }
+    implied
+}
+
+/// The bracketed groups of a file name, with the bracket each opens with.
+fn groups(filename: &str) -> Vec<(char, &str)> {
+    let mut groups = Vec::new();
+    let mut rest = filename;
+    while let Some(start) = rest.find(['(', '[']) {
+        let open = rest[start..].chars().next().unwrap_or('(');
+        let close = if open == '(' { ')' } else { ']' };
+        let Some(len) = rest[start + 1..].find(close) else {
+            break;
+        };
+        groups.push((open, &rest[start + 1..start + 1 + len]));
+        rest = &rest[start + 1 + len + 1..];
+    }
+    groups
+}
+
+/// Regions in a group like `Japan, USA`, `J`, or `JU`. Names and GoodTools
+/// codes must match exactly, so `(Es)` stays a language and `(Hack)` nothing.
+pub(super) fn group_regions(group: &str) -> Option<Vec<Region>> {
+    let by_name = |part: &str| {
+        REGIONS
+            .iter()
+            .find(|(_, name, code, ..)| *name == part || *code == part)
+            .map(|(region, ..)| region.clone())
+    };
+    let parts: Option<Vec<Region>> = group.split(',').map(|p| by_name(p.trim())).collect();
+    if parts.is_some() {
+        return parts;
+    }
+    // GoodTools combines one-letter codes, e.g. (JU) or (JUE)
+    if group.len() > 1 && group.len() <= 4 {
+        return group
+            .chars()
+            .map(|c| by_name(c.encode_utf8(&mut [0; 4])))
+            .collect();
+    }
+    None
+}
+
+/// Languages in a No-Intro group like `En,Fr,De`, written with a capital.
+pub(super) fn group_languages(group: &str) -> Option<Vec<Language>> {
+    group
+        .split([',', '+'])
+        .map(|part| {
+            let part = part.trim();
+            let mut chars = part.chars();
+            let capitalized = chars.next().is_some_and(|c| c.is_ascii_uppercase())
+                && chars.all(|c| c.is_ascii_lowercase());
+            LANGUAGES
+                .iter()
+                .find(|(_, code, ..)| capitalized && code.eq_ignore_ascii_case(part))
+                .map(|(language, ..)| language.clone())
+        })
+        .collect()
+}
+
+fn push_new<T: PartialEq>(list: &mut Vec<T>, items: Vec<T>) {
+    for item in items {
+        if !list.contains(&item) {
+            list.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_regions_and_languages() {
+        assert_eq!("j".parse(), Ok(Region::Japan));
+        assert_eq!("usa".parse(), Ok(Region::Usa));
+        assert_eq!("Hong Kong".parse(), Ok(Region::HongKong));
+        assert_eq!(
+            "Asia & Oceania".parse(),
+            Ok(Region::Other("Asia & Oceania".into()))
+        );
+        assert!("".parse::<Region>().is_err());
+        assert_eq!(Region::Usa.to_string(), "USA");
+
+        assert_eq!("EN".parse(), Ok(Language::English));
+        assert_eq!("Japanese".parse(), Ok(Language::Japanese));
+        assert_eq!("tlh".parse(), Ok(Language::Other("tlh".into())));
+        assert_eq!(Language::German.to_string(), "de");
+
+        assert_eq!(
+            parse_list::<Region>("Japan, u, JP"),
+            Ok(vec![Region::Japan, Region::Usa])
+        );
+        assert_eq!(join_list(&[Language::English, Language::French]), "en, fr");
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            detect("Super Mario Bros. (World)"),
+            (vec![Region::World], vec![])
+        );
+        assert_eq!(
+            detect("Final Fantasy (Japan) (Rev 1)"),
+            (vec![Region::Japan], vec![Language::Japanese])
+        );
+        assert_eq!(
+            detect("Zelda (Europe) (En,Fr,De)"),
+            (
+                vec![Region::Europe],
+                vec![Language::English, Language::French, Language::German]
+            )
+        );
+        assert_eq!(
+            detect("Mega Man (USA, Australia)"),
+            (
+                vec![Region::Usa, Region::Australia],
+                vec![Language::English]
+            )
+        );
+        assert_eq!(
+            detect("Tetris (JU) [!]"),
+            (vec![Region::Japan, Region::Usa], vec![])
+        );
+        assert_eq!(
+            detect("Mother (J) [T+Eng1.0_DemiForce]"),
+            (vec![Region::Japan], vec![Language::English])
+        );
+        assert_eq!(
+            detect("Mario (Hack) (PRG1) (Es)"),
+            (vec![], vec![Language::Spanish])
+        );
+        assert_eq!(detect("plain.nes"), (vec![], vec![]));
+        assert_eq!(detect("unclosed (Japan"), (vec![], vec![]));
+    }
+}
@@ -2,11 +2,14 @@ use std::fmt;
 use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RomType {
+    #[cfg_attr(feature = "serde", serde(rename = "NES"))]
     Nes,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Mirroring {
     Horizontal = 0,
     Vertical = 1,
@@ -54,9 +57,17 @@ impl RomType {
             RomType::Nes => "NES",
         }
     }
+
+    /// File extension for ROMs of this type, without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RomType::Nes => "nes",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NesHeader {
     pub prg_rom_size: usize,
     pub chr_rom_size: usize,
@@ -68,13 +79,56 @@ pub struct NesHeader {
     pub submapper: Option<u8>,
 }
 
+/// CPU/PPU timing mode from a NES 2.0 header (byte 12)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NesTiming {
+    Ntsc,
+    Pal,
+    MultiRegion,
+    Dendy,
+}
+
+impl fmt::Display for NesTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NesTiming::Ntsc => write!(f, "NTSC"),
+            NesTiming::Pal => write!(f, "PAL"),
+            NesTiming::MultiRegion => write!(f, "Multi-region"),
+            NesTiming::Dendy => write!(f, "Dendy"),
+        }
+    }
+}
+
+/// Extra fields only present in NES 2.0 headers (bytes 10-12)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nes2Details {
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+    pub timing: NesTiming,
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RomMetadata {
     pub rom_type: RomType,
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
     pub sha256: [u8; 32],
+    /// SHA-1 and CRC32 of the same data as `sha256`, for matching DAT
+    /// entries. None for ROMs added before they were recorded, or imported.
+    #[cfg_attr(feature = "serde", serde(default, with = "crate::wire::optional_hex"))]
+    pub sha1: Option<[u8; 20]>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub crc32: Option<u32>,
+    /// Length of the data the hashes cover, headers excluded. None for ROMs
+    /// added before it was recorded, or imported.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub content_size: Option<u64>,
     pub filename: Option<String>,
     pub nes_header: Option<NesHeader>,
     /// Raw file header bytes for byte-identical reconstruction
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::optional_bytes"))]
     pub source_file_header: Option<Vec<u8>>,
 }
 
@@ -126,4 +180,28 @@ mod tests {
         let parsed: RomType = as_str.parse().unwrap();
         assert_eq!(original, parsed);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_rom_metadata_wire_format() {
+        let metadata = RomMetadata {
+            rom_type: RomType::Nes,
+            sha256: [0xAB; 32],
+            sha1: None,
+            crc32: None,
+            content_size: None,
+            filename: Some("a.nes".to_string()),
+            nes_header: None,
+            source_file_header: Some(b"NES\x1a".to_vec()),
+        };
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(json["rom_type"], "NES");
+        assert_eq!(json["sha256"], "ab".repeat(32));
+        assert_eq!(json["source_file_header"], "TkVTGg==");
+
+        let back: RomMetadata = serde_json::from_value(json).unwrap();
+        assert_eq!(back.sha256, metadata.sha256);
+        assert_eq!(back.source_file_header, metadata.source_file_header);
+        assert!(serde_json::from_str::<RomMetadata>(r#"{"rom_type":"NES","sha256":"xyz","filename":null,"nes_header":null,"source_file_header":null}"#).is_err());
+    }
 }
@@ -0,0 +1,219 @@
+//! Folder layouts for copying built ROMs to a flash cart's SD card, such as
+//! an EverDrive's.
+//!
+//! File and folder names are kept safe for FAT32, which the carts read:
+//! ASCII only, none of the characters or device names FAT reserves, and
+//! short enough for the carts' menus.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::dat::game_name;
+use crate::db::NodeRow;
+
+/// Longest file or folder name written, extension included.
+const MAX_NAME_LEN: usize = 100;
+
+/// Names FAT reserves for devices, with or without an extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// How ROMs are grouped into folders on the card.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SdLayout {
+    /// Every ROM in the top folder
+    #[default]
+    Flat,
+    /// A folder per first letter of the title, and `#` for the rest
+    Letter,
+    /// A folder per ROM's first tag, and `Untagged` for ROMs without one
+    Tag,
+}
+
+impl FromStr for SdLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "flat" => Ok(SdLayout::Flat),
+            "letter" => Ok(SdLayout::Letter),
+            "tag" => Ok(SdLayout::Tag),
+            _ => Err(format!(
+                "unknown layout `{}` (expected one of: flat, letter, tag)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SdLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SdLayout::Flat => write!(f, "flat"),
+            SdLayout::Letter => write!(f, "letter"),
+            SdLayout::Tag => write!(f, "tag"),
+        }
+    }
+}
+
+/// Where each node goes on the card, relative to the card's folder, in the
+/// order given. Names are "Title (Version).nes"; ones that would repeat in a
+/// folder get the start of the hash.
+pub fn plan_card(rows: &[NodeRow], layout: SdLayout) -> Vec<(PathBuf, [u8; 32])> {
+    let mut used = HashSet::new();
+    rows.iter()
+        .map(|row| {
+            let folder = folder_for(row, layout);
+            let ext = row.rom_type.extension();
+            let stem = fat_safe_name(&game_name(row), MAX_NAME_LEN - ext.len() - 1);
+            let mut path = folder.join(format!("{}.{}", stem, ext));
+            if !used.insert(path.to_string_lossy().to_lowercase()) {
+                let hash = &hex::encode(row.sha256)[..8];
+                let stem = fat_safe_name(&stem, MAX_NAME_LEN - ext.len() - hash.len() - 4);
+                path = folder.join(format!("{} [{}].{}", stem, hash, ext));
+                used.insert(path.to_string_lossy().to_lowercase());
+            }
+            (path, row.sha256)
+        })
+        .collect()
+}
+
+fn folder_for(row: &NodeRow, layout: SdLayout) -> PathBuf {
+    match layout {
+        SdLayout::Flat => PathBuf::new(),
+        SdLayout::Letter => {
+            let first = row.title.trim_start().chars().next();
+            match first {
+                Some(c) if c.is_ascii_alphabetic() => c.to_ascii_uppercase().to_string().into(),
+                _ => "#".into(),
+            }
+        }
+        SdLayout::Tag => match row.tags.first() {
+            Some(tag) => fat_safe_name(tag, MAX_NAME_LEN).into(),
+            None => "Untagged".into(),
+        },
+    }
+}
+
+/// `name` made safe to use as a FAT file or folder name of at most `max_len`
+/// characters. Colons become dashes as in "Zelda II - The Adventure of
+/// Link"; other reserved or non-ASCII characters become underscores.
+pub fn fat_safe_name(name: &str, max_len: usize) -> String {
+    let name = name.replace(": ", " - ");
+    let safe: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_ascii_control() || !c.is_ascii() => '_',
+            c => c,
+        })
+        .take(max_len)
+        .collect();
+    // FAT drops trailing dots and spaces, so names differing only in them clash
+    let safe = safe.trim().trim_end_matches(['.', ' ']).to_string();
+
+    let stem = safe.split('.').next().unwrap_or_default();
+    if safe.is_empty() {
+        "_".to_string()
+    } else if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        format!("_{}", safe)
+    } else {
+        safe
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PlayStatus;
+    use crate::rom::RomType;
+
+    fn make_row(byte: u8, title: &str, tags: &[&str]) -> NodeRow {
+        NodeRow {
+            id: byte as i64,
+            sha256: [byte; 32],
+            sha1: None,
+            crc32: None,
+            filename: None,
+            title: title.to_string(),
+            rom_type: RomType::Nes,
+            source_url: None,
+            version: None,
+            release_date: None,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            description: None,
+            source_file_header: None,
+            created_at: String::new(),
+            updated_at: None,
+            rating: None,
+            play_status: PlayStatus::Unplayed,
+            regions: Vec::new(),
+            languages: Vec::new(),
+            favorite: false,
+            archived: false,
+            missing: false,
+            content_size: None,
+        }
+    }
+
+    #[test]
+    fn test_fat_safe_name() {
+        assert_eq!(
+            fat_safe_name("Zelda II: The Adventure of Link", 100),
+            "Zelda II - The Adventure of Link"
+        );
+        assert_eq!(fat_safe_name("AC/DC? \"Live\"", 100), "AC_DC_ _Live_");
+        assert_eq!(fat_safe_name("Pokémon...", 100), "Pok_mon");
+        assert_eq!(fat_safe_name("con", 100), "_con");
+        assert_eq!(fat_safe_name("Aux.nes", 100), "_Aux.nes");
+        assert_eq!(fat_safe_name(" . ", 100), "_");
+        assert_eq!(fat_safe_name("Long Title", 4), "Long");
+    }
+
+    #[test]
+    fn test_plan_card() {
+        let mut rows = vec![
+            make_row(0xAA, "Mario", &["hack", "platformer"]),
+            make_row(0xBB, "mario", &[]),
+            make_row(0xCC, "1942", &["shmup"]),
+        ];
+        rows[1].version = Some("Rev A".to_string());
+        let paths = |rows: &[NodeRow], layout| -> Vec<String> {
+            plan_card(rows, layout)
+                .into_iter()
+                .map(|(path, _)| path.to_string_lossy().replace('\\', "/"))
+                .collect()
+        };
+
+        assert_eq!(
+            paths(&rows, SdLayout::Flat),
+            vec!["Mario.nes", "mario (Rev A).nes", "1942.nes"]
+        );
+        assert_eq!(
+            paths(&rows, SdLayout::Letter),
+            vec!["M/Mario.nes", "M/mario (Rev A).nes", "#/1942.nes"]
+        );
+        assert_eq!(
+            paths(&rows, SdLayout::Tag),
+            vec![
+                "hack/Mario.nes",
+                "Untagged/mario (Rev A).nes",
+                "shmup/1942.nes"
+            ]
+        );
+
+        // Names that differ only in case would clash on FAT
+        rows[1].version = None;
+        assert_eq!(
+            paths(&rows, SdLayout::Flat),
+            vec!["Mario.nes", "mario [bbbbbbbb].nes", "1942.nes"]
+        );
+        assert_eq!("TAG".parse::<SdLayout>(), Ok(SdLayout::Tag));
+        assert!("alpha".parse::<SdLayout>().is_err());
+    }
+}
@@ -0,0 +1,1153 @@
+//! HTTP API over a library (`dromos serve`), for frontends and remote
+//! automation, and a small web UI on top of it at `/`. Responses are JSON,
+//! with hashes as lowercase hex, except builds, which send the built ROM
+//! file, and lineage drawings, which are SVG.
+//!
+//! | Request | Response |
+//! |---|---|
+//! | `GET /` | the web UI: a searchable table of nodes, and node pages to edit metadata, see lineage, and download builds |
+//! | `GET /api/status` | node and link counts, last export and import |
+//! | `GET /api/nodes?q=<query>` | nodes matching a `search` query (all without `q`) |
+//! | `GET /api/nodes/<hash>` | one node, by hash or unique prefix, with its links, attachments, and DAT match |
+//! | `PUT /api/nodes/<hash>` | replaces the node's metadata with `{"title", "version", "release_date", "source_url", "description", "tags", "rating", "play_status", "regions", "languages"}`, then the node |
+//! | `GET /api/nodes/<hash>/lineage.svg` | the drawing of the node's lineage from `export --html` |
+//! | `GET /api/graph` | every node and link |
+//! | `GET /api/components` | the hashes in each group of linked nodes, largest first |
+//! | `POST /api/nodes/<hash>/build` | the node built from the source ROM sent as the body |
+//! | `POST /api/import` | imports the export folder in `{"folder": ..., "conflicts": "keep" \| "import"}` |
+//! | `GET /api/sync/manifest?component=<hash>&tag=<tag>` | the export manifest of the whole library, for `sync`, or of the linked group of `component` and the nodes with any `tag` given, for `import` |
+//! | `GET /api/sync/diffs/<name>` | a diff file named in the manifest |
+//! | `GET /api/sync/attachments/<sha256>` | an attachment's contents |
+//! | `PUT /api/sync/incoming/diffs/<name>` | holds the diff file sent, for the next `POST /api/sync/import` |
+//! | `PUT /api/sync/incoming/attachments/<sha256>` | holds the attachment sent, likewise |
+//! | `POST /api/sync/import` | imports the export manifest sent, with the files held for it, keeping the metadata of nodes already here |
+//!
+//! Errors are `{"error": {"code", "message", ...}}` with the codes of
+//! [`DromosError::code`]. Requests are handled one at a time, so a build or
+//! import holds up the requests behind it.
+//!
+//! Other web pages the user has open can send requests to the server too,
+//! so it only answers ones a page on its own address could have sent: a
+//! server listening on this machine only answers requests addressed to
+//! `localhost` (or its IP address), an `Origin` must be the server's own,
+//! and `POST` and `PUT` bodies must say they're JSON (or, for builds and
+//! incoming sync files, binary), which browsers won't send to another site without asking it
+//! first. A server listening beyond this machine also requires the token
+//! [`Server::token`] gives, as `Authorization: Bearer <token>` or a
+//! `token=<token>` query parameter.
+//!
+//! [`serve_stdio`] answers the same requests over standard input and output
+//! (`dromos serve --stdio`), which is how `sync` reaches a library over SSH.
+//!
+//! [`share`] answers for `dromos share` instead: only the downloads of one
+//! selection of the library that `import http://...` makes, and nothing
+//! that changes it.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Read as _, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::cancel::CancelToken;
+use crate::dat;
+use crate::db::{NodeFilter, NodeMetadata};
+use crate::error::{DromosError, Result};
+use crate::exchange::{self, ConflictResolution, ExportManifest};
+use crate::progress::NoProgress;
+use crate::rom::{RomType, format_hash, reconstruct_nes_file_raw};
+use crate::site;
+use crate::storage::{BuildResult, StorageManager};
+use crate::sync::Selection;
+
+/// The web UI, a single page using the API.
+const UI_HTML: &str = include_str!("ui.html");
+
+/// How often the server checks for cancellation while idle.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Largest request body accepted (source ROMs for builds, and each file
+/// `sync` sends), and reply body `sync` reads.
+pub(crate) const MAX_BODY_BYTES: u64 = 64 * 1024 * 1024;
+
+/// A listening server.
+pub struct Server {
+    http: tiny_http::Server,
+    access: Access,
+}
+
+/// Which requests a [`Server`], or a gRPC server, answers.
+#[derive(Debug, Clone)]
+pub(crate) struct Access {
+    /// Whether the server listens on this machine only
+    loopback: bool,
+    /// Required of every request, when set
+    token: Option<String>,
+}
+
+/// Why [`Access`] turned a request away.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Denial {
+    /// 401 without the token, 403 otherwise
+    pub(crate) status: u16,
+    pub(crate) code: &'static str,
+    pub(crate) message: &'static str,
+}
+
+/// A handled request, for logging.
+#[derive(Debug, Clone)]
+pub struct RequestLog {
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    pub duration: Duration,
+}
+
+/// A response to send.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+    /// File name to download the body as, for builds
+    pub filename: Option<String>,
+}
+
+impl Reply {
+    fn json(status: u16, value: &Value) -> Reply {
+        Reply {
+            status,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+            filename: None,
+        }
+    }
+
+    fn text(content_type: &'static str, text: &str) -> Reply {
+        Reply {
+            status: 200,
+            content_type,
+            body: text.as_bytes().to_vec(),
+            filename: None,
+        }
+    }
+
+    fn error(error: &DromosError) -> Reply {
+        let status = match error {
+            DromosError::RomNotFound { .. }
+            | DromosError::FileNotFound { .. }
+            | DromosError::NoPath { .. } => 404,
+            DromosError::FileAccess { source, .. }
+                if source.kind() == std::io::ErrorKind::NotFound =>
+            {
+                404
+            }
+            DromosError::RomNotFoundAmbiguous { .. }
+            | DromosError::InvalidHashFormat { .. }
+            | DromosError::Import(_)
+            | DromosError::InvalidMetadata(_) => 400,
+            DromosError::InvalidNesFile { .. } | DromosError::UnsupportedRomType { .. } => 422,
+            DromosError::ReadOnly => 403,
+            _ => 500,
+        };
+        Reply::problem(status, error.code(), &error.to_string(), error.context())
+    }
+
+    fn problem(
+        status: u16,
+        code: &str,
+        message: &str,
+        context: serde_json::Map<String, Value>,
+    ) -> Reply {
+        let mut body = context;
+        body.insert("code".to_string(), code.into());
+        body.insert("message".to_string(), message.into());
+        Reply::json(status, &json!({ "error": body }))
+    }
+
+    fn bad_request(message: &str) -> Reply {
+        Reply::problem(400, "bad_request", message, Default::default())
+    }
+
+    fn not_found() -> Reply {
+        Reply::problem(404, "no_route", "no such endpoint", Default::default())
+    }
+}
+
+/// What precedes each request's body in [`serve_stdio`], as a line of JSON.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StdioRequest {
+    pub method: String,
+    pub url: String,
+    /// Length of the body in bytes
+    pub length: usize,
+}
+
+/// What precedes each reply's body in [`serve_stdio`], as a line of JSON.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StdioReply {
+    pub status: u16,
+    pub content_type: String,
+    /// Length of the body in bytes
+    pub length: usize,
+}
+
+/// Body of `POST /api/import`.
+#[derive(Deserialize)]
+struct ImportRequest {
+    folder: String,
+    /// `keep` (the default) or `import`, for nodes whose metadata differs
+    #[serde(default)]
+    conflicts: Option<String>,
+}
+
+impl Server {
+    /// Listen on `address`, e.g. `127.0.0.1:8080`. Unless that's on this
+    /// machine only, requests must carry a new random token.
+    pub fn bind(address: &str) -> Result<Server> {
+        let http = tiny_http::Server::http(address)
+            .map_err(|e| DromosError::Io(std::io::Error::other(format!("{}: {}", address, e))))?;
+        let access = Access::for_address(http.server_addr().to_ip())?;
+        Ok(Server { http, access })
+    }
+
+    /// Answer requests without a token, for servers that offer nothing
+    /// but downloads meant for anyone who can reach them.
+    pub fn without_token(mut self) -> Server {
+        self.access.token = None;
+        self
+    }
+
+    /// The token requests must carry, if any.
+    pub fn token(&self) -> Option<&str> {
+        self.access.token()
+    }
+
+    /// The address the server listens on, e.g. `127.0.0.1:8080`.
+    pub fn address(&self) -> String {
+        self.http.server_addr().to_string()
+    }
+
+    /// The port the server listens on, which `:0` in the address leaves to
+    /// the system to pick.
+    pub fn port(&self) -> Option<u16> {
+        self.http
+            .server_addr()
+            .to_ip()
+            .map(|address| address.port())
+    }
+
+    /// Answer requests until `cancel` stops the server, which
+    /// returns [`DromosError::Cancelled`]. `log` hears about each request.
+    pub fn run(
+        &self,
+        storage: &mut StorageManager,
+        cancel: &CancelToken,
+        log: &mut dyn FnMut(&RequestLog),
+    ) -> Result<()> {
+        self.run_with(cancel, log, &mut |method, url, body| {
+            handle(storage, method, url, body)
+        })
+    }
+
+    /// Like [`Server::run`], with `handler` replying to each request's
+    /// method, URL, and body in place of [`handle`].
+    pub fn run_with(
+        &self,
+        cancel: &CancelToken,
+        log: &mut dyn FnMut(&RequestLog),
+        handler: &mut dyn FnMut(&str, &str, &[u8]) -> Reply,
+    ) -> Result<()> {
+        loop {
+            cancel.check()?;
+            let Some(request) = self.http.recv_timeout(POLL_INTERVAL)? else {
+                continue;
+            };
+            let started = Instant::now();
+            let method = request.method().to_string();
+            let url = request.url().to_string();
+            let status = respond(handler, &self.access, request);
+            log(&RequestLog {
+                method,
+                url,
+                status,
+                duration: started.elapsed(),
+            });
+        }
+    }
+}
+
+impl Access {
+    /// The rules for a server listening on `address`: a new random token
+    /// unless that's on this machine only.
+    pub(crate) fn for_address(address: Option<std::net::SocketAddr>) -> Result<Access> {
+        let loopback = address.is_some_and(|address| address.ip().is_loopback());
+        let token = if loopback { None } else { Some(new_token()?) };
+        Ok(Access { loopback, token })
+    }
+
+    /// The token requests must carry, if any.
+    pub(crate) fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Why a request with the URL query `query`, whose headers `header`
+    /// looks up, is turned away, if it is.
+    pub(crate) fn denial(
+        &self,
+        query: &str,
+        header: &dyn Fn(&str) -> Option<String>,
+    ) -> Option<Denial> {
+        let forbidden = |message| {
+            Some(Denial {
+                status: 403,
+                code: "forbidden",
+                message,
+            })
+        };
+        let host = header("Host");
+        // A site can point a name of its own at this machine, so only
+        // this machine's names are answered to
+        if self.loopback
+            && !host
+                .as_deref()
+                .map(host_name)
+                .is_some_and(|name| matches!(name.as_str(), "localhost" | "127.0.0.1" | "[::1]"))
+        {
+            return forbidden("requests must be addressed to localhost");
+        }
+        if let Some(origin) = header("Origin")
+            && host.is_none_or(|host| origin != format!("http://{}", host))
+        {
+            return forbidden("requests from other sites aren't answered");
+        }
+
+        if let Some(token) = &self.token {
+            let given = header("Authorization")
+                .and_then(|value| value.strip_prefix("Bearer ").map(str::to_string))
+                .or_else(|| query_param(query, "token"));
+            if !given.is_some_and(|given| same_token(&given, token)) {
+                return Some(Denial {
+                    status: 401,
+                    code: "unauthorized",
+                    message: "requests need the token dromos serve printed",
+                });
+            }
+        }
+        None
+    }
+
+    /// The reply refusing an HTTP request with `method` and `url`, whose
+    /// headers `header` looks up, if it's refused.
+    fn refusal(
+        &self,
+        method: &str,
+        url: &str,
+        header: &dyn Fn(&str) -> Option<String>,
+    ) -> Option<Reply> {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        if let Some(denial) = self.denial(query, header) {
+            return Some(Reply::problem(
+                denial.status,
+                denial.code,
+                denial.message,
+                Default::default(),
+            ));
+        }
+
+        if matches!(method, "POST" | "PUT") {
+            let binary = path.trim_end_matches('/').ends_with("/build")
+                || path.starts_with("/api/sync/incoming/");
+            let expected = if binary {
+                "application/octet-stream"
+            } else {
+                "application/json"
+            };
+            let given = header("Content-Type");
+            let given = given.as_deref().and_then(|value| value.split(';').next());
+            if !given.is_some_and(|given| given.trim().eq_ignore_ascii_case(expected)) {
+                return Some(Reply::problem(
+                    415,
+                    "unsupported_media_type",
+                    &format!("send the body as {}", expected),
+                    Default::default(),
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// The name in a `Host` header, lowercased and without its port.
+fn host_name(host: &str) -> String {
+    let name = match host.find(']') {
+        Some(end) => &host[..=end],
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    name.to_ascii_lowercase()
+}
+
+/// Whether `given` is `token`, taking as long whichever bytes differ.
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |differs, (a, b)| differs | (a ^ b))
+            == 0
+}
+
+/// A random token for a server to require.
+fn new_token() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| DromosError::Io(std::io::Error::other(e.to_string())))?;
+    Ok(hex::encode(bytes))
+}
+
+/// Handle `request` and send the reply, returning its status.
+fn respond(
+    handler: &mut dyn FnMut(&str, &str, &[u8]) -> Reply,
+    access: &Access,
+    mut request: Request,
+) -> u16 {
+    let mut body = Vec::new();
+    let read = request
+        .as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body);
+    let reply = match read {
+        Err(e) => Reply::error(&DromosError::Io(e)),
+        Ok(_) if body.len() as u64 > MAX_BODY_BYTES => Reply::problem(
+            413,
+            "too_large",
+            "request body is too large",
+            Default::default(),
+        ),
+        Ok(_) => {
+            let method = match request.method() {
+                Method::Get | Method::Head => "GET",
+                Method::Post => "POST",
+                Method::Put => "PUT",
+                _ => "",
+            };
+            let header = |name: &str| {
+                request
+                    .headers()
+                    .iter()
+                    .find(|header| header.field.as_str().as_str().eq_ignore_ascii_case(name))
+                    .map(|header| header.value.as_str().to_string())
+            };
+            match access.refusal(method, request.url(), &header) {
+                Some(refusal) => refusal,
+                None => handler(method, request.url(), &body),
+            }
+        }
+    };
+
+    let status = reply.status;
+    let mut response = Response::from_data(reply.body).with_status_code(status);
+    let mut headers = vec![format!("Content-Type: {}", reply.content_type)];
+    if let Some(filename) = &reply.filename {
+        headers.push(format!(
+            "Content-Disposition: attachment; filename=\"{}\"",
+            filename.replace(['"', '\\'], "_")
+        ));
+    }
+    for header in headers {
+        if let Ok(header) = header.parse::<Header>() {
+            response.add_header(header);
+        }
+    }
+    // The client hanging up is its own business
+    let _ = request.respond(response);
+    status
+}
+
+/// The reply to `method` (`GET`, `POST`, or `PUT`) on `url`, with request
+/// `body`.
+pub fn handle(storage: &mut StorageManager, method: &str, url: &str, body: &[u8]) -> Reply {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let result = match (method, segments.as_slice()) {
+        ("GET", [""]) => Ok(Reply::text("text/html; charset=utf-8", UI_HTML)),
+        ("GET", ["api", "status"]) => status(storage),
+        ("GET", ["api", "nodes"]) => nodes(storage, query_param(query, "q").as_deref()),
+        ("GET", ["api", "nodes", hash]) => node(storage, hash),
+        ("PUT", ["api", "nodes", hash]) => match serde_json::from_slice::<NodeMetadata>(body) {
+            Ok(metadata) => edit(storage, hash, metadata),
+            Err(e) => return Reply::bad_request(&format!("invalid metadata: {}", e)),
+        },
+        ("GET", ["api", "nodes", hash, "lineage.svg"]) => lineage(storage, hash),
+        ("GET", ["api", "graph"]) => graph(storage),
+        ("GET", ["api", "components"]) => components(storage),
+        ("GET", ["api", "sync", "manifest"]) => sync_manifest(storage, query),
+        ("GET", ["api", "sync", "diffs", name]) => sync_file(&storage.config().diffs_dir, name),
+        ("GET", ["api", "sync", "attachments", sha256]) => {
+            sync_file(&storage.config().attachments_dir, sha256)
+        }
+        (
+            "PUT",
+            [
+                "api",
+                "sync",
+                "incoming",
+                kind @ ("diffs" | "attachments"),
+                name,
+            ],
+        ) => sync_receive(storage, kind, name, body),
+        ("POST", ["api", "sync", "import"]) => match serde_json::from_slice(body) {
+            Ok(manifest) => sync_import(storage, &manifest),
+            Err(e) => return Reply::bad_request(&format!("invalid manifest: {}", e)),
+        },
+        ("POST", ["api", "nodes", hash, "build"]) => return build(storage, hash, body),
+        ("POST", ["api", "import"]) => match serde_json::from_slice::<ImportRequest>(body) {
+            Ok(request) => import(storage, &request),
+            Err(e) => return Reply::bad_request(&format!("invalid import request: {}", e)),
+        },
+        _ => return Reply::not_found(),
+    };
+    match result {
+        Ok(reply) => reply,
+        Err(e) => Reply::error(&e),
+    }
+}
+
+/// The reply to `method` on `url` for `dromos share`, which offers only
+/// `manifest` (whatever selection a request asks for) and the diffs and
+/// attachments it lists.
+pub fn share(
+    storage: &StorageManager,
+    manifest: &ExportManifest,
+    method: &str,
+    url: &str,
+) -> Reply {
+    let path = url.split_once('?').map_or(url, |(path, _)| path);
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let result = match (method, segments.as_slice()) {
+        ("GET", ["api", "sync", "manifest"]) => serde_json::to_value(manifest)
+            .map(|manifest| Reply::json(200, &manifest))
+            .map_err(DromosError::from),
+        ("GET", ["api", "sync", "diffs", name])
+            if manifest
+                .diffs
+                .iter()
+                .any(|edge| crate::sync::plain_name(&edge.diff_path).is_ok_and(|n| n == *name)) =>
+        {
+            sync_file(&storage.config().diffs_dir, name)
+        }
+        ("GET", ["api", "sync", "attachments", sha256])
+            if manifest.attachments.iter().any(|a| a.sha256 == *sha256) =>
+        {
+            sync_file(&storage.config().attachments_dir, sha256)
+        }
+        _ => return Reply::not_found(),
+    };
+    match result {
+        Ok(reply) => reply,
+        Err(e) => Reply::error(&e),
+    }
+}
+
+fn status(storage: &StorageManager) -> Result<Reply> {
+    let status = storage.status(0)?;
+    Ok(Reply::json(
+        200,
+        &json!({
+            "nodes": status.node_count,
+            "links": status.diff_count,
+            "diff_bytes": status.total_diff_bytes,
+            "last_export_at": status.last_export_at,
+            "last_import_at": status.last_import_at,
+        }),
+    ))
+}
+
+fn nodes(storage: &StorageManager, query: Option<&str>) -> Result<Reply> {
+    let filter = match NodeFilter::parse(query.unwrap_or_default()) {
+        Ok(filter) => filter,
+        Err(e) => return Ok(Reply::bad_request(&e)),
+    };
+    let rows = storage.search(&filter)?;
+    Ok(Reply::json(200, &json!({ "nodes": rows })))
+}
+
+fn node(storage: &StorageManager, prefix: &str) -> Result<Reply> {
+    let sha256 = resolve(storage, prefix)?;
+    let row = storage
+        .get_node_row_by_hash(&sha256)?
+        .ok_or_else(|| DromosError::RomNotFound {
+            hash: prefix.to_string(),
+        })?;
+    let links: Vec<Value> = storage
+        .get_neighbors(&sha256)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(node, edge)| {
+            json!({
+                "sha256": format_hash(&node.sha256),
+                "title": node.title,
+                "version": node.version,
+                "diff_size": edge.diff_size,
+                "note": edge.note,
+            })
+        })
+        .collect();
+    Ok(Reply::json(
+        200,
+        &json!({
+            "node": row,
+            "links": links,
+            "attachments": storage.attachments(&sha256)?,
+            "dat": storage.dat_match(&sha256)?,
+        }),
+    ))
+}
+
+fn edit(storage: &mut StorageManager, prefix: &str, metadata: NodeMetadata) -> Result<Reply> {
+    let sha256 = resolve(storage, prefix)?;
+    storage.update_node_metadata(&sha256, &metadata)?;
+    node(storage, &format_hash(&sha256))
+}
+
+fn lineage(storage: &StorageManager, prefix: &str) -> Result<Reply> {
+    let sha256 = resolve(storage, prefix)?;
+    let rows = storage.node_rows()?;
+    let by_hash: HashMap<[u8; 32], _> = rows.iter().map(|r| (r.sha256, r)).collect();
+    let row = by_hash
+        .get(&sha256)
+        .ok_or_else(|| DromosError::RomNotFound {
+            hash: prefix.to_string(),
+        })?;
+    let svg = site::lineage_svg(row, &by_hash, &storage.edge_hashes());
+    Ok(Reply::text("image/svg+xml", &svg))
+}
+
+fn graph(storage: &StorageManager) -> Result<Reply> {
+    let (nodes, edges) = storage.list();
+    let edges: Vec<Value> = edges
+        .into_iter()
+        .map(|(from, to, diff_size)| json!({ "from": from, "to": to, "diff_size": diff_size }))
+        .collect();
+    Ok(Reply::json(200, &json!({ "nodes": nodes, "edges": edges })))
+}
+
+fn components(storage: &StorageManager) -> Result<Reply> {
+    let (nodes, _) = storage.list();
+    let hashes: Vec<[u8; 32]> = nodes.iter().map(|n| n.sha256).collect();
+    let mut seen = HashSet::new();
+    let mut components: Vec<Vec<String>> = Vec::new();
+    for hash in &hashes {
+        if seen.contains(hash) {
+            continue;
+        }
+        let mut members: Vec<[u8; 32]> = storage.connected_component_hashes(hash);
+        members.sort();
+        seen.extend(members.iter().copied());
+        components.push(members.iter().map(format_hash).collect());
+    }
+    components.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    let components: Vec<Value> = components
+        .into_iter()
+        .map(|nodes| json!({ "nodes": nodes }))
+        .collect();
+    Ok(Reply::json(200, &json!({ "components": components })))
+}
+
+fn build(storage: &StorageManager, prefix: &str, source: &[u8]) -> Reply {
+    let result = resolve(storage, prefix).and_then(|target| {
+        if source.is_empty() {
+            return Ok(None);
+        }
+        // Builds read their source from a file, removed when dropped
+        let mut file = tempfile::Builder::new()
+            .prefix("dromos-serve-")
+            .suffix(".nes")
+            .tempfile()?;
+        file.write_all(source)
+            .map_err(DromosError::file(file.path()))?;
+        storage
+            .build_rom(file.path(), &target, &mut NoProgress, &CancelToken::new())
+            .map(Some)
+    });
+    match result {
+        Ok(None) => Reply::bad_request("send the source ROM as the request body"),
+        Ok(Some(built)) => {
+            let (filename, body) = built_file(built);
+            Reply {
+                status: 200,
+                content_type: "application/octet-stream",
+                body,
+                filename: Some(filename),
+            }
+        }
+        Err(e) => Reply::error(&e),
+    }
+}
+
+/// The file a build produces: its name for downloading, and the ROM with its
+/// original file header restored.
+pub(crate) fn built_file(built: BuildResult) -> (String, Vec<u8>) {
+    let row = &built.target_row;
+    let filename = format!("{}.{}", dat::game_name(row), row.rom_type.extension());
+    let body = match (&row.source_file_header, row.rom_type) {
+        (Some(header), RomType::Nes) => reconstruct_nes_file_raw(header, &built.bytes),
+        _ => built.bytes,
+    };
+    (filename, body)
+}
+
+fn import(storage: &mut StorageManager, request: &ImportRequest) -> Result<Reply> {
+    let resolution = match request.conflicts.as_deref() {
+        None | Some("keep") => ConflictResolution::KeepLocal,
+        Some("import") => ConflictResolution::Import,
+        Some(other) => {
+            return Ok(Reply::bad_request(&format!(
+                "conflicts must be keep or import, not {}",
+                other
+            )));
+        }
+    };
+    let folder = std::path::Path::new(&request.folder);
+    let (manifest, conflicts) = storage.analyze_import(folder)?;
+    let resolutions: HashMap<String, ConflictResolution> = conflicts
+        .into_iter()
+        .map(|conflict| (conflict.sha256, resolution.clone()))
+        .collect();
+    let result = storage.execute_import(
+        folder,
+        &manifest,
+        &resolutions,
+        &mut NoProgress,
+        &CancelToken::new(),
+    )?;
+    Ok(Reply::json(200, &serde_json::to_value(result)?))
+}
+
+fn sync_manifest(storage: &StorageManager, query: &str) -> Result<Reply> {
+    let selection = Selection {
+        component: query_param(query, "component"),
+        tags: query_params(query, "tag"),
+    };
+    let manifest = selected_manifest(storage, &selection)?;
+    Ok(Reply::json(200, &serde_json::to_value(manifest)?))
+}
+
+/// The export manifest of `selection`: the linked group of its component
+/// (the whole library without one), narrowed to the nodes with any of its
+/// tags if it has some.
+pub fn selected_manifest(
+    storage: &StorageManager,
+    selection: &Selection,
+) -> Result<ExportManifest> {
+    let component = match &selection.component {
+        Some(prefix) => Some(resolve(storage, prefix)?),
+        None => None,
+    };
+    let mut manifest = storage.plan_export(component.as_ref())?.manifest;
+    if !selection.tags.is_empty() {
+        manifest = exchange::sync::tagged(&manifest, &selection.tags);
+    }
+    Ok(manifest)
+}
+
+/// The file `name` in `dir`, as long as it is a plain file name.
+fn sync_file(dir: &Path, name: &str) -> Result<Reply> {
+    let path = dir.join(crate::sync::plain_name(name)?);
+    let body = std::fs::read(&path).map_err(DromosError::file(&path))?;
+    Ok(Reply {
+        status: 200,
+        content_type: "application/octet-stream",
+        body,
+        filename: None,
+    })
+}
+
+/// Where files a sync sends wait for its import: an export folder beside
+/// the library's diffs.
+fn incoming_dir(storage: &StorageManager) -> PathBuf {
+    storage.config().diffs_dir.with_file_name("sync-incoming")
+}
+
+/// Hold a diff or attachment (`kind`) a sync sends, ahead of its import.
+fn sync_receive(storage: &StorageManager, kind: &str, name: &str, body: &[u8]) -> Result<Reply> {
+    storage.ensure_writable()?;
+    let dir = incoming_dir(storage).join(kind);
+    std::fs::create_dir_all(&dir).map_err(DromosError::file(&dir))?;
+    let path = dir.join(crate::sync::plain_name(name)?);
+    std::fs::write(&path, body).map_err(DromosError::file(&path))?;
+    Ok(Reply::json(200, &json!({ "received": name })))
+}
+
+/// Import `manifest` with the files sent ahead of it, then drop them.
+fn sync_import(storage: &mut StorageManager, manifest: &ExportManifest) -> Result<Reply> {
+    storage.ensure_writable()?;
+    let folder = incoming_dir(storage);
+    let index = folder.join("index.json");
+    let result = std::fs::create_dir_all(&folder)
+        .and_then(|_| std::fs::write(&index, serde_json::to_vec(manifest)?))
+        .map_err(DromosError::file(&index))
+        .and_then(|_| storage.import_synced(&folder, &mut NoProgress, &CancelToken::new()));
+    let _ = std::fs::remove_dir_all(&folder);
+    Ok(Reply::json(200, &serde_json::to_value(result?)?))
+}
+
+/// Answer requests read from `input` until it ends, writing the replies to
+/// `output`. Each request is a [`StdioRequest`] line followed by its body,
+/// and each reply a [`StdioReply`] line followed by its body.
+pub fn serve_stdio(
+    storage: &mut StorageManager,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<()> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let request: StdioRequest = serde_json::from_str(&line)?;
+        if request.length as u64 > MAX_BODY_BYTES {
+            return Err(DromosError::Io(std::io::Error::other(
+                "request body is too large",
+            )));
+        }
+        let mut body = vec![0; request.length];
+        input.read_exact(&mut body)?;
+        let reply = handle(storage, &request.method, &request.url, &body);
+        let header = StdioReply {
+            status: reply.status,
+            content_type: reply.content_type.to_string(),
+            length: reply.body.len(),
+        };
+        serde_json::to_writer(&mut *output, &header)?;
+        output.write_all(b"\n")?;
+        output.write_all(&reply.body)?;
+        output.flush()?;
+    }
+}
+
+/// The node a full hash or unique hash prefix names.
+pub(crate) fn resolve(storage: &StorageManager, prefix: &str) -> Result<[u8; 32]> {
+    let matches = storage.find_nodes_by_hash_prefix(prefix);
+    match matches.as_slice() {
+        [node] => Ok(node.sha256),
+        [] => Err(DromosError::RomNotFound {
+            hash: prefix.to_string(),
+        }),
+        _ => Err(DromosError::RomNotFoundAmbiguous {
+            query: prefix.to_string(),
+            candidates: matches.iter().map(|n| format_hash(&n.sha256)).collect(),
+        }),
+    }
+}
+
+/// The decoded value of `name` in a URL query string.
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query_params(query, name).into_iter().next()
+}
+
+/// The decoded values of `name` in a URL query string, which may repeat.
+fn query_params(query: &str, name: &str) -> Vec<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+        .collect()
+}
+
+/// Decode `%XX` escapes and `+` (a space in query strings).
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let escaped = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match escaped {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::NodeMetadata;
+    use crate::test_support::write_rom;
+
+    fn json_body(reply: &Reply) -> Value {
+        serde_json::from_slice(&reply.body).unwrap()
+    }
+
+    #[test]
+    fn test_handle_requests() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let mut storage = StorageManager::builder()
+            .in_memory()
+            .diffs_dir(dir.join("diffs"))
+            .open()
+            .unwrap();
+        let (path_a, path_b) = (dir.join("a.nes"), dir.join("b.nes"));
+        let rom_a = write_rom(&path_a, 0xEA);
+        let rom_b = write_rom(&path_b, 0x60);
+        for (path, title) in [(&path_a, "Alpha"), (&path_b, "Beta")] {
+            let metadata = NodeMetadata {
+                title: title.to_string(),
+                ..Default::default()
+            };
+            storage.add_node(path, &metadata).unwrap();
+        }
+        storage
+            .link_nodes(&path_a, &path_b, None, &mut NoProgress, &CancelToken::new())
+            .unwrap();
+        let hash_b = storage.find_nodes_by_title("Beta")[0].sha256;
+
+        let reply = handle(&mut storage, "GET", "/api/nodes", &[]);
+        assert_eq!(reply.status, 200);
+        assert_eq!(json_body(&reply)["nodes"].as_array().unwrap().len(), 2);
+        let reply = handle(&mut storage, "GET", "/api/nodes?q=bet%61", &[]);
+        assert_eq!(json_body(&reply)["nodes"][0]["title"], "Beta");
+
+        let prefix = &format_hash(&hash_b)[..12];
+        let reply = handle(&mut storage, "GET", &format!("/api/nodes/{}", prefix), &[]);
+        let detail = json_body(&reply);
+        assert_eq!(detail["node"]["sha256"], format_hash(&hash_b));
+        assert_eq!(detail["links"][0]["title"], "Alpha");
+
+        let reply = handle(&mut storage, "GET", "/api/components", &[]);
+        let components = json_body(&reply)["components"].clone();
+        assert_eq!(components[0]["nodes"].as_array().unwrap().len(), 2);
+        let reply = handle(&mut storage, "GET", "/api/graph", &[]);
+        assert_eq!(json_body(&reply)["edges"].as_array().unwrap().len(), 2);
+
+        let url = format!("/api/nodes/{}/build", prefix);
+        let reply = handle(&mut storage, "POST", &url, &rom_a);
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.body, rom_b);
+        assert_eq!(reply.filename.as_deref(), Some("Beta.nes"));
+        assert_eq!(handle(&mut storage, "POST", &url, &[]).status, 400);
+
+        let reply = handle(&mut storage, "GET", "/api/nodes/ffffffff", &[]);
+        assert_eq!(reply.status, 404);
+        assert_eq!(json_body(&reply)["error"]["code"], "rom_not_found");
+        assert_eq!(
+            handle(&mut storage, "GET", "/api/nodes?q=mapper:x", &[]).status,
+            400
+        );
+        assert_eq!(
+            handle(&mut storage, "POST", "/api/import", b"{}").status,
+            400
+        );
+        assert_eq!(handle(&mut storage, "GET", "/elsewhere", &[]).status, 404);
+    }
+
+    #[test]
+    fn test_share_requests() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let mut storage = StorageManager::builder()
+            .in_memory()
+            .diffs_dir(dir.join("diffs"))
+            .open()
+            .unwrap();
+        let paths = [dir.join("a.nes"), dir.join("b.nes"), dir.join("c.nes")];
+        for (path, fill) in paths.iter().zip([0xEA, 0x60, 0x00]) {
+            write_rom(path, fill);
+            let metadata = NodeMetadata {
+                title: path.display().to_string(),
+                ..Default::default()
+            };
+            storage.add_node(path, &metadata).unwrap();
+        }
+        for (from, to) in [(0, 1), (1, 2)] {
+            storage
+                .link_nodes(
+                    &paths[from],
+                    &paths[to],
+                    None,
+                    &mut NoProgress,
+                    &CancelToken::new(),
+                )
+                .unwrap();
+        }
+        let whole = storage.plan_export(None).unwrap().manifest;
+        let selection = Selection {
+            component: Some(whole.files[0].sha256[..12].to_string()),
+            tags: Vec::new(),
+        };
+        let mut manifest = selected_manifest(&storage, &selection).unwrap();
+        // Leave the rest of the diffs out of the share
+        let unshared = manifest.diffs.split_off(2);
+
+        let reply = share(&storage, &manifest, "GET", "/api/sync/manifest?tag=x");
+        assert_eq!(reply.status, 200);
+        assert_eq!(json_body(&reply)["diffs"].as_array().unwrap().len(), 2);
+        let url = |edge: &exchange::ExportEdge| format!("/api/sync/diffs/{}", edge.diff_path);
+        assert_eq!(
+            share(&storage, &manifest, "GET", &url(&manifest.diffs[0])).status,
+            200
+        );
+        assert_eq!(
+            share(&storage, &manifest, "GET", &url(&unshared[0])).status,
+            404
+        );
+        assert_eq!(share(&storage, &manifest, "GET", "/api/nodes").status, 404);
+        assert_eq!(
+            share(&storage, &manifest, "POST", "/api/sync/import").status,
+            404
+        );
+    }
+
+    #[test]
+    fn test_web_ui_requests() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let mut storage = StorageManager::builder()
+            .in_memory()
+            .diffs_dir(dir.join("diffs"))
+            .open()
+            .unwrap();
+        let path = dir.join("a.nes");
+        write_rom(&path, 0xEA);
+        let metadata = NodeMetadata {
+            title: "Alpha".to_string(),
+            ..Default::default()
+        };
+        storage.add_node(&path, &metadata).unwrap();
+        let hash = format_hash(&storage.find_nodes_by_title("Alpha")[0].sha256);
+
+        let reply = handle(&mut storage, "GET", "/", &[]);
+        assert_eq!(reply.status, 200);
+        assert!(reply.content_type.starts_with("text/html"));
+
+        let url = format!("/api/nodes/{}", hash);
+        let edited = br#"{"title": " Alpha DX ", "version": "1.1", "release_date": "2024-01-05",
+            "source_url": null, "description": null, "tags": ["hack"], "rating": 4,
+            "play_status": "finished"}"#;
+        let reply = handle(&mut storage, "PUT", &url, edited);
+        assert_eq!(reply.status, 200);
+        let node = json_body(&reply)["node"].clone();
+        assert_eq!(node["title"], "Alpha DX");
+        assert_eq!(node["tags"][0], "hack");
+        assert_eq!(node["rating"], 4);
+        assert_eq!(node["play_status"], "finished");
+
+        let bad_date = br#"{"title": "Alpha", "release_date": "soon", "tags": []}"#;
+        assert_eq!(handle(&mut storage, "PUT", &url, bad_date).status, 400);
+        let no_title = br#"{"title": "  ", "tags": []}"#;
+        assert_eq!(handle(&mut storage, "PUT", &url, no_title).status, 400);
+        let bad_rating = br#"{"title": "Alpha", "rating": 9, "tags": []}"#;
+        assert_eq!(handle(&mut storage, "PUT", &url, bad_rating).status, 400);
+        assert_eq!(handle(&mut storage, "PUT", &url, b"[]").status, 400);
+
+        let reply = handle(&mut storage, "GET", &format!("{}/lineage.svg", url), &[]);
+        assert_eq!(reply.content_type, "image/svg+xml");
+        assert!(String::from_utf8(reply.body).unwrap().contains("<svg"));
+    }
+
+    #[test]
+    fn test_access() {
+        let refused = |access: &Access, method: &str, url: &str, headers: &[(&str, &str)]| {
+            let header = |name: &str| {
+                headers
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.to_string())
+            };
+            access
+                .refusal(method, url, &header)
+                .map(|reply| reply.status)
+        };
+        let local = Access {
+            loopback: true,
+            token: None,
+        };
+        let json = ("Content-Type", "application/json");
+        assert_eq!(
+            refused(&local, "GET", "/api/status", &[("Host", "localhost:8080")]),
+            None
+        );
+        assert_eq!(refused(&local, "GET", "/", &[("Host", "[::1]:8080")]), None);
+        // DNS rebinding, and pages on other sites
+        assert_eq!(
+            refused(&local, "GET", "/", &[("Host", "evil.example:8080")]),
+            Some(403)
+        );
+        assert_eq!(refused(&local, "GET", "/", &[]), Some(403));
+        let cross_site = [
+            ("Host", "127.0.0.1:8080"),
+            ("Origin", "http://evil.example"),
+            json,
+        ];
+        assert_eq!(
+            refused(&local, "POST", "/api/import", &cross_site),
+            Some(403)
+        );
+        let same_site = [
+            ("Host", "127.0.0.1:8080"),
+            ("Origin", "http://127.0.0.1:8080"),
+            json,
+        ];
+        assert_eq!(refused(&local, "PUT", "/api/nodes/ab", &same_site), None);
+        // Bodies browsers send anywhere without asking
+        let text = [("Host", "localhost"), ("Content-Type", "text/plain")];
+        assert_eq!(refused(&local, "POST", "/api/import", &text), Some(415));
+        let rom = [
+            ("Host", "localhost"),
+            ("Content-Type", "application/octet-stream"),
+        ];
+        assert_eq!(refused(&local, "POST", "/api/nodes/ab/build", &rom), None);
+        assert_eq!(refused(&local, "POST", "/api/sync/import", &rom), Some(415));
+
+        let remote = Access {
+            loopback: false,
+            token: Some("secret".to_string()),
+        };
+        let host = ("Host", "desktop:8080");
+        assert_eq!(refused(&remote, "GET", "/api/status", &[host]), Some(401));
+        assert_eq!(
+            refused(&remote, "GET", "/api/status?token=wrong", &[host]),
+            Some(401)
+        );
+        assert_eq!(refused(&remote, "GET", "/?token=secret", &[host]), None);
+        let bearer = [host, ("Authorization", "Bearer secret"), json];
+        assert_eq!(refused(&remote, "POST", "/api/sync/import", &bearer), None);
+        let file = [
+            host,
+            ("Authorization", "Bearer secret"),
+            ("Content-Type", "application/octet-stream"),
+        ];
+        let incoming = "/api/sync/incoming/diffs/a.bsdiff";
+        assert_eq!(refused(&remote, "PUT", incoming, &file), None);
+        assert_eq!(refused(&remote, "PUT", incoming, &bearer), Some(415));
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("tag%3Ahack+mario"), "tag:hack mario");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%E2%9C%93"), "\u{2713}");
+        assert_eq!(query_param("a=1&q=x%20y", "q").as_deref(), Some("x y"));
+        assert_eq!(query_param("a=1", "q"), None);
+    }
+}
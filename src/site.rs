@@ -0,0 +1,465 @@
+//! A static HTML catalog of the library: an index page with a search box,
+//! and a page per ROM with its metadata and an SVG drawing of the ROMs it's
+//! linked to. The pages work from a plain folder or any static web host.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use crate::dat::escape;
+use crate::db::collation::compare_titles;
+use crate::db::{DatMatch, NodeRow};
+use crate::rom::format_hash;
+
+/// A diff from one ROM to another: source hash, target hash, diff size.
+pub type SiteEdge = ([u8; 32], [u8; 32], i64);
+
+/// Size of a ROM's box in the lineage drawing, and the space around it
+const BOX_WIDTH: i64 = 180;
+const BOX_HEIGHT: i64 = 36;
+const H_GAP: i64 = 20;
+const V_GAP: i64 = 48;
+/// Characters of a title that fit in a box
+const BOX_TITLE_LEN: usize = 24;
+
+const STYLE: &str =
+    "body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 960px; \
+padding: 0 1em; color: #222; }
+a { color: #1a5fb4; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; }
+input[type=search] { width: 100%; padding: 0.5em; margin-bottom: 1em; font-size: 1em; }
+dl { display: grid; grid-template-columns: max-content 1fr; gap: 0.3em 1em; }
+dt { font-weight: bold; }
+dd { margin: 0; overflow-wrap: anywhere; }
+.hash { font-family: monospace; }
+.verified { color: #26a269; }
+.description { white-space: pre-wrap; }
+svg { max-width: 100%; height: auto; }
+svg rect { fill: #f6f5f4; stroke: #77767b; }
+svg .current rect { fill: #dcebfc; stroke: #1a5fb4; stroke-width: 2; }
+svg line { stroke: #9a9996; stroke-width: 1.5; }
+svg text { font-size: 13px; fill: #222; }
+";
+
+/// Hides the index rows that don't contain every word typed in the search box
+const SEARCH_SCRIPT: &str = "const rows = document.querySelectorAll('tbody tr');
+document.getElementById('search').addEventListener('input', (e) => {
+  const words = e.target.value.toLowerCase().split(/\\s+/).filter(Boolean);
+  rows.forEach((row) => {
+    row.hidden = !words.every((w) => row.dataset.search.includes(w));
+  });
+});
+";
+
+/// Every file of the site, as paths relative to its folder and contents:
+/// `index.html`, `style.css`, and `roms/<sha256>.html` per ROM. `edges`
+/// outside `rows` are ignored.
+pub fn render_site(
+    name: &str,
+    rows: &[NodeRow],
+    edges: &[SiteEdge],
+    dat: &HashMap<[u8; 32], DatMatch>,
+) -> Vec<(PathBuf, String)> {
+    let by_hash: HashMap<[u8; 32], &NodeRow> = rows.iter().map(|r| (r.sha256, r)).collect();
+    let edges: Vec<SiteEdge> = edges
+        .iter()
+        .filter(|(a, b, _)| by_hash.contains_key(a) && by_hash.contains_key(b))
+        .copied()
+        .collect();
+
+    let mut sorted: Vec<&NodeRow> = rows.iter().collect();
+    sorted
+        .sort_by(|a, b| compare_titles(&a.title, &b.title).then_with(|| a.version.cmp(&b.version)));
+
+    let mut files = vec![
+        (PathBuf::from("style.css"), STYLE.to_string()),
+        (
+            PathBuf::from("index.html"),
+            index_page(name, &sorted, &edges, dat),
+        ),
+    ];
+    for row in &sorted {
+        files.push((
+            PathBuf::from("roms").join(format!("{}.html", format_hash(&row.sha256))),
+            node_page(name, row, &by_hash, &edges, dat),
+        ));
+    }
+    files
+}
+
+fn index_page(
+    name: &str,
+    rows: &[&NodeRow],
+    edges: &[SiteEdge],
+    dat: &HashMap<[u8; 32], DatMatch>,
+) -> String {
+    let mut html = page_start(name, "style.css");
+    let _ = writeln!(html, "<h1>{}</h1>", escape(name));
+    let _ = writeln!(
+        html,
+        "<p>{} ROM{}</p>",
+        rows.len(),
+        if rows.len() == 1 { "" } else { "s" }
+    );
+    html.push_str(
+        "<input id=\"search\" type=\"search\" placeholder=\"Search titles, versions, tags\" \
+         autofocus>\n<table>\n<thead><tr><th>Title</th><th>Version</th><th>Type</th>\
+         <th>Tags</th><th>Links</th></tr></thead>\n<tbody>\n",
+    );
+    for row in rows {
+        let links = edges.iter().filter(|(a, _, _)| *a == row.sha256).count();
+        let version = row.version.as_deref().unwrap_or("");
+        let search = [
+            row.title.as_str(),
+            version,
+            &row.tags.join(" "),
+            row.description.as_deref().unwrap_or(""),
+            dat.get(&row.sha256).map(|m| m.game.as_str()).unwrap_or(""),
+        ]
+        .join(" ")
+        .to_lowercase();
+        let _ = writeln!(
+            html,
+            "<tr data-search=\"{}\"><td><a href=\"roms/{}.html\">{}</a>{}</td><td>{}</td>\
+             <td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&search),
+            format_hash(&row.sha256),
+            escape(&row.title),
+            if dat.contains_key(&row.sha256) {
+                " <span class=\"verified\" title=\"Verified good dump\">✓</span>"
+            } else {
+                ""
+            },
+            escape(version),
+            row.rom_type,
+            escape(&row.tags.join(", ")),
+            links
+        );
+    }
+    html.push_str("</tbody>\n</table>\n<script>\n");
+    html.push_str(SEARCH_SCRIPT);
+    html.push_str("</script>\n");
+    page_end(html)
+}
+
+fn node_page(
+    name: &str,
+    row: &NodeRow,
+    by_hash: &HashMap<[u8; 32], &NodeRow>,
+    edges: &[SiteEdge],
+    dat: &HashMap<[u8; 32], DatMatch>,
+) -> String {
+    let title = display_title(row);
+    let mut html = page_start(&format!("{} - {}", title, name), "../style.css");
+    let _ = writeln!(
+        html,
+        "<p><a href=\"../index.html\">{}</a></p>\n<h1>{}</h1>\n<dl>",
+        escape(name),
+        escape(&title)
+    );
+    let mut field = |label: &str, value: String| {
+        let _ = writeln!(html, "<dt>{}</dt><dd>{}</dd>", label, value);
+    };
+    field(
+        "SHA-256",
+        format!("<span class=\"hash\">{}</span>", format_hash(&row.sha256)),
+    );
+    if let Some(sha1) = &row.sha1 {
+        field(
+            "SHA-1",
+            format!("<span class=\"hash\">{}</span>", hex::encode(sha1)),
+        );
+    }
+    if let Some(crc32) = row.crc32 {
+        field(
+            "CRC32",
+            format!("<span class=\"hash\">{:08x}</span>", crc32),
+        );
+    }
+    field("Type", row.rom_type.to_string());
+    if let Some(size) = row.data_size() {
+        field("Size", format!("{} bytes", size));
+    }
+    if let Some(m) = dat.get(&row.sha256) {
+        field(
+            "Verified",
+            format!(
+                "<span class=\"verified\">✓ {}</span> ({})",
+                escape(&m.game),
+                escape(&m.dat_name)
+            ),
+        );
+    }
+    if let Some(date) = &row.release_date {
+        field("Released", escape(date));
+    }
+    if !row.tags.is_empty() {
+        field("Tags", escape(&row.tags.join(", ")));
+    }
+    if let Some(url) = row.source_url.as_deref().filter(|u| !u.is_empty()) {
+        // Only web links are clickable
+        let lower = url.to_lowercase();
+        if lower.starts_with("http://") || lower.starts_with("https://") {
+            field(
+                "Source",
+                format!("<a href=\"{}\">{}</a>", escape(url), escape(url)),
+            );
+        } else {
+            field("Source", escape(url));
+        }
+    }
+    field("Added", escape(&row.created_at));
+    let _ = writeln!(html, "</dl>");
+    if let Some(description) = row.description.as_deref().filter(|d| !d.is_empty()) {
+        let _ = writeln!(
+            html,
+            "<h2>Description</h2>\n<p class=\"description\">{}</p>",
+            escape(description)
+        );
+    }
+
+    let mut links: Vec<(&NodeRow, i64)> = edges
+        .iter()
+        .filter(|(a, _, _)| *a == row.sha256)
+        .filter_map(|(_, b, size)| by_hash.get(b).map(|n| (*n, *size)))
+        .collect();
+    links.sort_by(|(a, _), (b, _)| compare_titles(&a.title, &b.title));
+    if !links.is_empty() {
+        let _ = writeln!(html, "<h2>Links</h2>\n<ul>");
+        for (other, diff_size) in &links {
+            let _ = writeln!(
+                html,
+                "<li><a href=\"{}.html\">{}</a> ({} byte diff)</li>",
+                format_hash(&other.sha256),
+                escape(&display_title(other)),
+                diff_size
+            );
+        }
+        let _ = writeln!(html, "</ul>\n<h2>Lineage</h2>");
+        html.push_str(&lineage_svg(row, by_hash, edges));
+    }
+    page_end(html)
+}
+
+/// Draw the ROMs linked to `row`, directly or through others, in rows by
+/// how many links away they are, with `row` at the top. Each box links to
+/// the ROM's page.
+pub fn lineage_svg(
+    row: &NodeRow,
+    by_hash: &HashMap<[u8; 32], &NodeRow>,
+    edges: &[SiteEdge],
+) -> String {
+    let mut neighbors: HashMap<[u8; 32], Vec<[u8; 32]>> = HashMap::new();
+    let mut lines: HashSet<([u8; 32], [u8; 32])> = HashSet::new();
+    for (a, b, _) in edges {
+        neighbors.entry(*a).or_default().push(*b);
+        neighbors.entry(*b).or_default().push(*a);
+        lines.insert(if a < b { (*a, *b) } else { (*b, *a) });
+    }
+
+    // Breadth-first from the page's ROM gives each ROM its row
+    let mut layers: Vec<Vec<[u8; 32]>> = Vec::new();
+    let mut depth: HashMap<[u8; 32], usize> = HashMap::from([(row.sha256, 0)]);
+    let mut queue = VecDeque::from([row.sha256]);
+    while let Some(hash) = queue.pop_front() {
+        let d = depth[&hash];
+        if layers.len() <= d {
+            layers.push(Vec::new());
+        }
+        layers[d].push(hash);
+        for next in neighbors.get(&hash).into_iter().flatten() {
+            if !depth.contains_key(next) {
+                depth.insert(*next, d + 1);
+                queue.push_back(*next);
+            }
+        }
+    }
+    let title_of = |hash: &[u8; 32]| by_hash.get(hash).map(|n| display_title(n));
+    for layer in &mut layers {
+        layer.sort_by(|a, b| {
+            compare_titles(
+                &title_of(a).unwrap_or_default(),
+                &title_of(b).unwrap_or_default(),
+            )
+        });
+    }
+
+    let widest = layers.iter().map(Vec::len).max().unwrap_or(1) as i64;
+    let width = widest * (BOX_WIDTH + H_GAP) + H_GAP;
+    let height = layers.len() as i64 * (BOX_HEIGHT + V_GAP) - V_GAP + 2 * H_GAP;
+    let mut position: HashMap<[u8; 32], (i64, i64)> = HashMap::new();
+    for (d, layer) in layers.iter().enumerate() {
+        let layer_width = layer.len() as i64 * (BOX_WIDTH + H_GAP) - H_GAP;
+        let left = (width - layer_width) / 2;
+        for (i, hash) in layer.iter().enumerate() {
+            let x = left + i as i64 * (BOX_WIDTH + H_GAP);
+            let y = H_GAP + d as i64 * (BOX_HEIGHT + V_GAP);
+            position.insert(*hash, (x, y));
+        }
+    }
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+         viewBox=\"0 0 {0} {1}\" role=\"img\" aria-label=\"Linked ROMs\">\n",
+        width, height
+    );
+    let mut sorted_lines: Vec<_> = lines.into_iter().collect();
+    sorted_lines.sort();
+    for (a, b) in sorted_lines {
+        if let (Some((ax, ay)), Some((bx, by))) = (position.get(&a), position.get(&b)) {
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>",
+                ax + BOX_WIDTH / 2,
+                ay + BOX_HEIGHT / 2,
+                bx + BOX_WIDTH / 2,
+                by + BOX_HEIGHT / 2
+            );
+        }
+    }
+    for hash in layers.iter().flatten() {
+        let (x, y) = position[hash];
+        let title = title_of(hash).unwrap_or_default();
+        let _ = writeln!(
+            svg,
+            "<a href=\"{}.html\"{}><title>{}</title>\
+             <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"6\"/>\
+             <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" dominant-baseline=\"middle\">{}</text></a>",
+            format_hash(hash),
+            if *hash == row.sha256 {
+                " class=\"current\""
+            } else {
+                ""
+            },
+            escape(&title),
+            x,
+            y,
+            BOX_WIDTH,
+            BOX_HEIGHT,
+            x + BOX_WIDTH / 2,
+            y + BOX_HEIGHT / 2,
+            escape(&truncate(&title, BOX_TITLE_LEN))
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn display_title(row: &NodeRow) -> String {
+    match row.version.as_deref() {
+        Some(v) if !v.is_empty() => format!("{} [{}]", row.title, v),
+        _ => row.title.clone(),
+    }
+}
+
+/// `text` cut to `max` characters, ending in an ellipsis if it was cut
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        let mut cut: String = text.chars().take(max - 1).collect();
+        cut.push('…');
+        cut
+    }
+}
+
+fn page_start(title: &str, stylesheet: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<link rel=\"stylesheet\" href=\"{}\">\n</head>\n<body>\n",
+        escape(title),
+        stylesheet
+    )
+}
+
+fn page_end(mut html: String) -> String {
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PlayStatus;
+    use crate::rom::RomType;
+
+    fn row(byte: u8, title: &str) -> NodeRow {
+        NodeRow {
+            id: byte as i64,
+            sha256: [byte; 32],
+            filename: None,
+            title: title.to_string(),
+            rom_type: RomType::Nes,
+            source_url: Some("javascript:alert(1)".to_string()),
+            version: None,
+            release_date: None,
+            tags: vec!["hack".to_string()],
+            description: None,
+            source_file_header: None,
+            created_at: "2026-01-01 00:00:00".to_string(),
+            updated_at: None,
+            sha1: None,
+            crc32: None,
+            rating: None,
+            play_status: PlayStatus::Unplayed,
+            regions: Vec::new(),
+            languages: Vec::new(),
+            favorite: false,
+            archived: false,
+            missing: false,
+            content_size: None,
+        }
+    }
+
+    #[test]
+    fn test_render_site() {
+        let rows = vec![row(1, "Base <Game>"), row(2, "Hack"), row(3, "Other")];
+        let edges = vec![([1; 32], [2; 32], 100), ([2; 32], [1; 32], 90)];
+        let files = render_site("My ROMs", &rows, &edges, &HashMap::new());
+        let paths: Vec<_> = files.iter().map(|(p, _)| p.clone()).collect();
+        assert_eq!(paths.len(), 5);
+        assert!(paths.contains(&PathBuf::from("index.html")));
+        assert!(
+            paths.contains(&PathBuf::from("roms").join(format!("{}.html", format_hash(&[2; 32]))))
+        );
+
+        let page = |hash: [u8; 32]| {
+            let path = PathBuf::from("roms").join(format!("{}.html", format_hash(&hash)));
+            files.iter().find(|(p, _)| *p == path).unwrap().1.clone()
+        };
+        let index = &files
+            .iter()
+            .find(|(p, _)| p.ends_with("index.html"))
+            .unwrap()
+            .1;
+        assert!(index.contains("Base &lt;Game&gt;"));
+        assert!(index.contains("data-search=\"base &lt;game&gt;  hack  \""));
+
+        // Linked ROMs get a lineage drawing with both boxes, unlinked ones don't
+        let base = page([1; 32]);
+        assert!(base.contains("<svg"));
+        assert!(base.contains("class=\"current\""));
+        assert!(base.contains(&format!("href=\"{}.html\"", format_hash(&[2; 32]))));
+        assert!(base.contains("(100 byte diff)"));
+        assert!(!page([3; 32]).contains("<svg"));
+        // Source URLs that aren't web links aren't clickable
+        assert!(!base.contains("href=\"javascript"));
+    }
+
+    #[test]
+    fn test_lineage_layers() {
+        let rows = [row(1, "A"), row(2, "B"), row(3, "C"), row(4, "Unlinked")];
+        let by_hash: HashMap<_, _> = rows.iter().map(|r| (r.sha256, r)).collect();
+        // A chain: A - B - C
+        let edges = vec![([1; 32], [2; 32], 1), ([2; 32], [3; 32], 1)];
+        let svg = lineage_svg(&rows[2], &by_hash, &edges);
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert_eq!(svg.matches("<line").count(), 2);
+        assert!(!svg.contains(">Unlinked<"));
+        // C is drawn first, at the top
+        assert!(svg.find(">C<").unwrap() < svg.find(">A<").unwrap());
+        assert_eq!(truncate("abcdef", 4), "abc…");
+    }
+}
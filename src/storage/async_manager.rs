@@ -0,0 +1,243 @@
+//! Async access to a [`StorageManager`] for tokio applications.
+//!
+//! SQLite calls, hashing, and bsdiff run on tokio's blocking thread pool, so
+//! a server or GUI can await library operations without stalling its
+//! runtime. The manager sits behind a mutex, so operations on one library
+//! run one at a time. Enabled with the `tokio` feature.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::cancel::CancelToken;
+use crate::config::StorageConfig;
+use crate::db::{NodeFilter, NodeMetadata, NodeRow};
+use crate::error::{DromosError, Result};
+use crate::exchange::{
+    ConflictResolution, ExportManifest, ExportPlan, ExportStats, ImportResult, NodeConflict,
+};
+use crate::progress::ProgressSink;
+use crate::rom::RomMetadata;
+
+use super::{BuildResult, LibraryStatus, RemoveResult, StorageManager};
+
+/// A shareable handle to a library. Clones refer to the same library.
+#[derive(Clone)]
+pub struct AsyncStorageManager {
+    inner: Arc<Mutex<StorageManager>>,
+}
+
+impl AsyncStorageManager {
+    /// Open the library, creating or wiping it as [`StorageManager::open`] does.
+    pub async fn open(config: StorageConfig) -> Result<Self> {
+        let manager = run_blocking(move || StorageManager::open(config)).await?;
+        Ok(Self::from(manager))
+    }
+
+    /// Run `f` with exclusive access to the manager on the blocking thread
+    /// pool, for operations without an async method of their own.
+    pub async fn with<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut StorageManager) -> Result<T> + Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+        run_blocking(move || f(&mut inner.lock().unwrap_or_else(|e| e.into_inner()))).await
+    }
+
+    pub async fn add_node(&self, path: PathBuf, metadata: NodeMetadata) -> Result<RomMetadata> {
+        self.with(move |m| m.add_node(&path, &metadata)).await
+    }
+
+    /// See [`StorageManager::link_nodes`]. `progress` is called from the
+    /// blocking thread pool; forward updates over a channel to use them here.
+    /// Dropping the future doesn't stop the work; cancel `cancel` instead.
+    pub async fn link_nodes(
+        &self,
+        path_a: PathBuf,
+        path_b: PathBuf,
+        note: Option<String>,
+        mut progress: impl ProgressSink + Send + 'static,
+        cancel: CancelToken,
+    ) -> Result<(u64, u64)> {
+        self.with(move |m| m.link_nodes(&path_a, &path_b, note.as_deref(), &mut progress, &cancel))
+            .await
+    }
+
+    /// See [`StorageManager::build_rom`].
+    pub async fn build_rom(
+        &self,
+        source_path: PathBuf,
+        target_hash: [u8; 32],
+        mut progress: impl ProgressSink + Send + 'static,
+        cancel: CancelToken,
+    ) -> Result<BuildResult> {
+        self.with(move |m| m.build_rom(&source_path, &target_hash, &mut progress, &cancel))
+            .await
+    }
+
+    /// Build a ROM and write it to `output_path`.
+    pub async fn build_rom_to_file(
+        &self,
+        source_path: PathBuf,
+        target_hash: [u8; 32],
+        output_path: PathBuf,
+        progress: impl ProgressSink + Send + 'static,
+        cancel: CancelToken,
+    ) -> Result<BuildResult> {
+        let result = self
+            .build_rom(source_path, target_hash, progress, cancel)
+            .await?;
+        tokio::fs::write(&output_path, &result.bytes).await?;
+        Ok(result)
+    }
+
+    pub async fn node_rows(&self) -> Result<Vec<NodeRow>> {
+        self.with(|m| m.node_rows()).await
+    }
+
+    pub async fn get_node_row_by_hash(&self, sha256: [u8; 32]) -> Result<Option<NodeRow>> {
+        self.with(move |m| m.get_node_row_by_hash(&sha256)).await
+    }
+
+    pub async fn search(&self, filter: NodeFilter) -> Result<Vec<NodeRow>> {
+        self.with(move |m| m.search(&filter)).await
+    }
+
+    pub async fn update_node_metadata(
+        &self,
+        sha256: [u8; 32],
+        metadata: NodeMetadata,
+    ) -> Result<()> {
+        self.with(move |m| m.update_node_metadata(&sha256, &metadata))
+            .await
+    }
+
+    pub async fn remove_node(&self, sha256: [u8; 32]) -> Result<RemoveResult> {
+        self.with(move |m| m.remove_node(&sha256)).await
+    }
+
+    pub async fn status(&self, recent_limit: usize) -> Result<LibraryStatus> {
+        self.with(move |m| m.status(recent_limit)).await
+    }
+
+    /// See [`StorageManager::plan_export`].
+    pub async fn plan_export(&self, component_hash: Option<[u8; 32]>) -> Result<ExportPlan> {
+        self.with(move |m| m.plan_export(component_hash.as_ref()))
+            .await
+    }
+
+    /// See [`StorageManager::export`]. The plan is handed back with the stats
+    /// so it can be written again, e.g. to a second folder.
+    pub async fn export(
+        &self,
+        output_path: PathBuf,
+        plan: ExportPlan,
+        overwrite: HashSet<PathBuf>,
+        mut progress: impl ProgressSink + Send + 'static,
+        cancel: CancelToken,
+    ) -> Result<(ExportPlan, ExportStats)> {
+        self.with(move |m| {
+            let stats = m.export(&output_path, &plan, &overwrite, &mut progress, &cancel)?;
+            Ok((plan, stats))
+        })
+        .await
+    }
+
+    /// See [`StorageManager::analyze_import`].
+    pub async fn analyze_import(
+        &self,
+        folder_path: PathBuf,
+    ) -> Result<(ExportManifest, Vec<NodeConflict>)> {
+        self.with(move |m| m.analyze_import(&folder_path)).await
+    }
+
+    /// See [`StorageManager::execute_import`].
+    pub async fn execute_import(
+        &self,
+        folder_path: PathBuf,
+        manifest: ExportManifest,
+        resolutions: HashMap<String, ConflictResolution>,
+        mut progress: impl ProgressSink + Send + 'static,
+        cancel: CancelToken,
+    ) -> Result<ImportResult> {
+        self.with(move |m| {
+            m.execute_import(
+                &folder_path,
+                &manifest,
+                &resolutions,
+                &mut progress,
+                &cancel,
+            )
+        })
+        .await
+    }
+}
+
+impl From<StorageManager> for AsyncStorageManager {
+    fn from(manager: StorageManager) -> Self {
+        AsyncStorageManager {
+            inner: Arc::new(Mutex::new(manager)),
+        }
+    }
+}
+
+/// Run `f` on the blocking thread pool. A panic in `f` resumes on the caller.
+async fn run_blocking<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(DromosError::Io(std::io::Error::other(e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rom::RomType;
+
+    #[test]
+    fn test_async_add_and_search() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = StorageManager::builder()
+            .in_memory()
+            .diffs_dir(temp_dir.path().join("diffs"))
+            .open()
+            .unwrap();
+        let library = AsyncStorageManager::from(manager);
+
+        runtime.block_on(async {
+            let metadata = RomMetadata {
+                rom_type: RomType::Nes,
+                sha256: [0xAA; 32],
+                sha1: None,
+                crc32: None,
+                content_size: None,
+                filename: Some("a.nes".to_string()),
+                nes_header: None,
+                source_file_header: None,
+            };
+            library
+                .with(move |m| m.add_node_from_metadata(&metadata, "Async ROM"))
+                .await
+                .unwrap();
+
+            let rows = library.node_rows().await.unwrap();
+            assert_eq!(rows.len(), 1);
+            let found = library
+                .search(NodeFilter::parse("async").unwrap())
+                .await
+                .unwrap();
+            assert_eq!(found[0].title, "Async ROM");
+            assert!(library.remove_node([0xAA; 32]).await.is_ok());
+            assert!(library.node_rows().await.unwrap().is_empty());
+        });
+    }
+}
@@ -0,0 +1,55 @@
+//! Tags added to new ROMs by rules, so bulk ingests come out organized.
+//!
+//! Each rule is a tag and a search query (see [`NodeFilter`]); a ROM that
+//! matches the query when it's added gets the tag. The CLI reads rules from
+//! `autotag.<tag> = <query>` lines in `dromos.conf`, e.g.
+//! `autotag.MMC3 = mapper:4` or `autotag.translation = filename:*[T+*`.
+
+use crate::db::{NodeFilter, Repository};
+use crate::error::Result;
+
+/// Tag ROMs matching a search query when they're added.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagRule {
+    pub tag: String,
+    pub filter: NodeFilter,
+}
+
+impl TagRule {
+    /// A rule adding `tag` to new ROMs matching `query`. Returns Err for an
+    /// empty tag or query, or a malformed query.
+    pub fn new(tag: &str, query: &str) -> std::result::Result<TagRule, String> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err("autotag needs a tag name".to_string());
+        }
+        if query.trim().is_empty() {
+            return Err(format!("autotag.{} needs a search query", tag));
+        }
+        let filter = NodeFilter::parse(query).map_err(|e| format!("autotag.{}: {}", tag, e))?;
+        Ok(TagRule {
+            tag: tag.to_string(),
+            filter,
+        })
+    }
+}
+
+/// `tags` plus the tag of each rule the node with `node_id` matches, or
+/// None if no rule adds a tag it doesn't already have (in any case).
+pub(super) fn apply_rules(
+    repo: &Repository,
+    node_id: i64,
+    tags: &[String],
+    rules: &[TagRule],
+) -> Result<Option<Vec<String>>> {
+    let mut tagged = tags.to_vec();
+    for rule in rules {
+        if tagged.iter().any(|t| t.eq_ignore_ascii_case(&rule.tag)) {
+            continue;
+        }
+        if repo.node_matches(node_id, &rule.filter)? {
+            tagged.push(rule.tag.clone());
+        }
+    }
+    Ok((tagged.len() > tags.len()).then_some(tagged))
+}
@@ -0,0 +1,144 @@
+//! Options for opening a [`StorageManager`].
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::config::StorageConfig;
+use crate::diff::DiffEngine;
+use crate::error::Result;
+
+use super::StorageManager;
+
+/// SQLite journal mode, set when the library is opened writable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+    Wal,
+    Off,
+}
+
+impl fmt::Display for JournalMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JournalMode::Delete => write!(f, "DELETE"),
+            JournalMode::Truncate => write!(f, "TRUNCATE"),
+            JournalMode::Persist => write!(f, "PERSIST"),
+            JournalMode::Memory => write!(f, "MEMORY"),
+            JournalMode::Wal => write!(f, "WAL"),
+            JournalMode::Off => write!(f, "OFF"),
+        }
+    }
+}
+
+/// Builds a [`StorageManager`]; see [`StorageManager::builder`].
+///
+/// Paths default to the user's data directory, except that attachments go
+/// next to a diffs directory that was set. Everything else defaults to
+/// what the CLI uses: writable, SQLite's own journal mode and cache size,
+/// and the bsdiff engine.
+#[derive(Debug, Clone, Default)]
+pub struct StorageManagerBuilder {
+    pub(super) db_path: Option<PathBuf>,
+    pub(super) diffs_dir: Option<PathBuf>,
+    pub(super) attachments_dir: Option<PathBuf>,
+    pub(super) in_memory: bool,
+    pub(super) read_only: bool,
+    pub(super) journal_mode: Option<JournalMode>,
+    pub(super) diff_engine: DiffEngine,
+    pub(super) cache_size_kib: Option<u32>,
+}
+
+impl StorageManagerBuilder {
+    /// Use every path from `config`.
+    pub fn paths(mut self, config: StorageConfig) -> Self {
+        self.db_path = Some(config.db_path);
+        self.diffs_dir = Some(config.diffs_dir);
+        self.attachments_dir = Some(config.attachments_dir);
+        self
+    }
+
+    pub fn db_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.db_path = Some(path.into());
+        self
+    }
+
+    pub fn diffs_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.diffs_dir = Some(path.into());
+        self
+    }
+
+    pub fn attachments_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.attachments_dir = Some(path.into());
+        self
+    }
+
+    /// Keep the database in memory, starting empty, e.g. for tests. Diffs are
+    /// still written to the diffs directory.
+    pub fn in_memory(mut self) -> Self {
+        self.in_memory = true;
+        self
+    }
+
+    /// Open without writing anything: no upgrade, no wipe, and every change
+    /// fails with [`DromosError::ReadOnly`](crate::DromosError::ReadOnly).
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn journal_mode(mut self, mode: JournalMode) -> Self {
+        self.journal_mode = Some(mode);
+        self
+    }
+
+    pub fn diff_engine(mut self, engine: DiffEngine) -> Self {
+        self.diff_engine = engine;
+        self
+    }
+
+    /// Size of SQLite's page cache, in KiB.
+    pub fn cache_size_kib(mut self, kib: u32) -> Self {
+        self.cache_size_kib = Some(kib);
+        self
+    }
+
+    /// Open the library, creating it, or wiping it if it was written by an
+    /// incompatible data revision (see [`StorageManager::data_wipe`]).
+    pub fn open(self) -> Result<StorageManager> {
+        StorageManager::open_with(self)
+    }
+
+    /// The configured paths, falling back to the user's data directory.
+    pub(super) fn config(&self) -> Result<StorageConfig> {
+        let defaults = StorageConfig::default_paths();
+        let db_path = match (&self.db_path, self.in_memory) {
+            (_, true) => Some(PathBuf::from(":memory:")),
+            (Some(path), false) => Some(path.clone()),
+            (None, false) => defaults.as_ref().map(|d| d.db_path.clone()),
+        };
+        let attachments_dir = match (&self.attachments_dir, &self.diffs_dir) {
+            (Some(path), _) => Some(path.clone()),
+            (None, Some(diffs_dir)) => Some(diffs_dir.with_file_name("attachments")),
+            (None, None) => defaults.as_ref().map(|d| d.attachments_dir.clone()),
+        };
+        let diffs_dir = self
+            .diffs_dir
+            .clone()
+            .or_else(|| defaults.map(|d| d.diffs_dir));
+        match (db_path, diffs_dir, attachments_dir) {
+            (Some(db_path), Some(diffs_dir), Some(attachments_dir)) => Ok(StorageConfig {
+                db_path,
+                diffs_dir,
+                attachments_dir,
+            }),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Could not determine data directory",
+            )
+            .into()),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Change notifications for code embedding a [`StorageManager`].
+//!
+//! Observers registered with [`StorageManager::add_observer`] are told about
+//! each change after it has been saved, so a GUI or sync layer can react
+//! without polling the database. Changes rolled back (e.g. a failed import)
+//! are never reported.
+//!
+//! [`StorageManager`]: super::StorageManager
+//! [`StorageManager::add_observer`]: super::StorageManager::add_observer
+
+/// A saved change to the library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageEvent {
+    NodeAdded {
+        sha256: [u8; 32],
+        title: String,
+    },
+    /// A diff from `source` to `target`; linking two ROMs creates one each way
+    EdgeCreated {
+        source: [u8; 32],
+        target: [u8; 32],
+        diff_size: i64,
+    },
+    /// The node and all of its edges were removed
+    NodeRemoved {
+        sha256: [u8; 32],
+        title: String,
+    },
+    MetadataChanged {
+        sha256: [u8; 32],
+        title: String,
+    },
+}
+
+/// Names an observer registered with [`StorageManager::add_observer`], for
+/// [`StorageManager::remove_observer`].
+///
+/// [`StorageManager::remove_observer`]: super::StorageManager::remove_observer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(pub(super) u64);
+
+/// Receives [`StorageEvent`]s. Closures taking `&StorageEvent` implement it.
+pub trait StorageObserver: Send {
+    fn on_event(&mut self, event: &StorageEvent);
+}
+
+impl<F: FnMut(&StorageEvent) + Send> StorageObserver for F {
+    fn on_event(&mut self, event: &StorageEvent) {
+        self(event)
+    }
+}
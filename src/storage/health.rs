@@ -0,0 +1,166 @@
+//! Checks of the stored diff files that need no ROM data, for `check-diffs`.
+//!
+//! [`check_diff`] only reads the diffs folder, so it can run on another
+//! thread while the library stays in use.
+
+use std::fs;
+use std::path::Path;
+
+use crate::diff;
+use crate::rom::hash::hash_bytes;
+
+/// A link's diff file as the database records it
+#[derive(Debug, Clone)]
+pub struct StoredDiff {
+    pub from: [u8; 32],
+    pub to: [u8; 32],
+    pub diff_path: String,
+    pub diff_size: i64,
+    /// SHA-256 in hex; None for diffs linked before checksums were kept
+    pub diff_sha256: Option<String>,
+    /// Size of the ROM data it builds; None for diffs linked before it was kept
+    pub output_size: Option<i64>,
+}
+
+/// What [`check_diff`] learned about a diff file that passed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthyDiff {
+    /// SHA-256 in hex
+    pub sha256: String,
+    /// Size of the ROM data the patch builds
+    pub output_size: u64,
+}
+
+/// A diff file that failed [`check_diff`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffProblem {
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
+    pub from: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
+    pub to: [u8; 32],
+    pub diff_path: String,
+    pub problem: String,
+}
+
+/// Result of checking every diff file in the library
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffCheck {
+    pub checked: usize,
+    pub problems: Vec<DiffProblem>,
+    /// Healthy diffs linked without a checksum or output size, which now
+    /// have them recorded
+    pub checksums_recorded: usize,
+}
+
+/// Check one diff file: that it exists, has the size and checksum stored
+/// for it, and is a well-formed bsdiff patch building a ROM of the size
+/// stored for it. Returns what it learned, or what's wrong with the diff.
+pub fn check_diff(diffs_dir: &Path, diff: &StoredDiff) -> Result<HealthyDiff, String> {
+    let path = diffs_dir.join(&diff.diff_path);
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err("file is missing".to_string());
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+    if bytes.len() as i64 != diff.diff_size {
+        return Err(format!(
+            "file is {} bytes, but {} were recorded",
+            bytes.len(),
+            diff.diff_size
+        ));
+    }
+    let sha256 = hex::encode(hash_bytes(&bytes));
+    if let Some(expected) = &diff.diff_sha256
+        && *expected != sha256
+    {
+        return Err(format!(
+            "SHA-256 is {}, but {} was recorded",
+            sha256, expected
+        ));
+    }
+    let output_size =
+        diff::check_diff_bytes(&bytes).map_err(|e| format!("not a valid bsdiff patch: {}", e))?;
+    if let Some(expected) = diff.output_size
+        && expected as u64 != output_size
+    {
+        return Err(format!(
+            "builds {} bytes, but {} were recorded",
+            output_size, expected
+        ));
+    }
+    Ok(HealthyDiff {
+        sha256,
+        output_size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_diff() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let size =
+            diff::create_diff(b"old ROM", b"new ROM", &temp_dir.path().join("a.bsdiff")).unwrap();
+        let mut stored = StoredDiff {
+            from: [1; 32],
+            to: [2; 32],
+            diff_path: "a.bsdiff".to_string(),
+            diff_size: size as i64,
+            diff_sha256: None,
+            output_size: None,
+        };
+        let healthy = check_diff(temp_dir.path(), &stored).unwrap();
+        assert_eq!(healthy.output_size, 7);
+        stored.diff_sha256 = Some(healthy.sha256.clone());
+        stored.output_size = Some(7);
+        assert_eq!(check_diff(temp_dir.path(), &stored), Ok(healthy));
+
+        stored.output_size = Some(8);
+        assert!(
+            check_diff(temp_dir.path(), &stored)
+                .unwrap_err()
+                .starts_with("builds 7")
+        );
+        stored.output_size = Some(7);
+
+        stored.diff_sha256 = Some("00".repeat(32));
+        assert!(
+            check_diff(temp_dir.path(), &stored)
+                .unwrap_err()
+                .starts_with("SHA-256 is")
+        );
+        stored.diff_size += 1;
+        assert!(
+            check_diff(temp_dir.path(), &stored)
+                .unwrap_err()
+                .contains("were recorded")
+        );
+
+        fs::write(temp_dir.path().join("b.bsdiff"), b"garbage").unwrap();
+        let garbage = StoredDiff {
+            diff_path: "b.bsdiff".to_string(),
+            diff_size: 7,
+            diff_sha256: None,
+            ..stored.clone()
+        };
+        assert!(
+            check_diff(temp_dir.path(), &garbage)
+                .unwrap_err()
+                .starts_with("not a valid")
+        );
+        let missing = StoredDiff {
+            diff_path: "c.bsdiff".to_string(),
+            ..garbage
+        };
+        assert_eq!(
+            check_diff(temp_dir.path(), &missing),
+            Err("file is missing".to_string())
+        );
+    }
+}
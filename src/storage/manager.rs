@@ -1,89 +1,364 @@
-use rusqlite::Connection;
+use regex::Regex;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use crate::cancel::CancelToken;
 use crate::config::StorageConfig;
+use crate::csv::{self, MetadataChange};
+use crate::dat;
+use crate::db::collation::{self, compare_titles};
 use crate::db::{
-    DATA_REVISION, NodeMetadata, NodeRow, Repository, get_stored_data_revision, has_existing_data,
-    run_migrations, set_data_revision,
+    AttachmentRow, CollectionRow, Cursor, DATA_REVISION, DatMatch, EdgeRow, LocationRow,
+    NodeFilter, NodeMetadata, NodeRow, QueryResult, Repository, get_stored_data_revision,
+    has_existing_data, migrations_pending, run_migrations, set_data_revision,
 };
-use crate::diff;
+use crate::dedupe;
+use crate::diff::{self, DiffEngine};
 use crate::error::{DromosError, Result};
 use crate::exchange;
 use crate::graph::{DiffEdge, PathStep, RomGraph, RomNode};
-use crate::rom::{RomMetadata, format_hash, hash_rom_file, read_rom_bytes};
+use crate::plugin::Plugin;
+use crate::progress::{Progress, ProgressSink, Stage};
+use crate::rom::hash::hash_bytes;
+use crate::rom::{
+    RomMetadata, find_rom_files, format_hash, hash_rom_file, hash_rom_files, nes_file_problems,
+    read_rom_bytes, reconstruct_nes_file_raw,
+};
+use crate::sdcard::{self, SdLayout};
+use crate::site;
+
+use super::autotag::{self, TagRule};
+use super::builder::StorageManagerBuilder;
+use super::events::{ObserverId, StorageEvent, StorageObserver};
+use super::health::{self, DiffCheck, DiffProblem, StoredDiff};
 
 /// Result of removing a node
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RemoveResult {
     pub title: String,
     pub edges_removed: usize,
     pub diff_files_removed: usize,
+    /// Files attached to the node or its links
+    pub attachments_removed: usize,
+    /// Diff files that couldn't be deleted, with the reason
+    pub diff_files_failed: Vec<(PathBuf, String)>,
+}
+
+/// Result of merging a duplicate node into another
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MergeResult {
+    /// The kept node's metadata, now including the duplicate's
+    pub metadata: NodeMetadata,
+    /// Nodes the duplicate was linked to that are now linked to the kept node
+    pub relinked: Vec<[u8; 32]>,
+    /// Collections the kept node was added to
+    pub collections: Vec<String>,
+    /// The duplicate's own attachments, now on the kept node
+    pub attachments_moved: usize,
+    /// Removing the duplicate
+    pub removed: RemoveResult,
+}
+
+/// The database and diffs were wiped on open because they were written by an
+/// incompatible data revision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataWipe {
+    /// Revision of the wiped data; None for a database from before revisions
+    pub stored_revision: Option<u32>,
+    pub current_revision: u32,
 }
 
 /// Result of building a ROM from diffs
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BuildResult {
+    /// The built ROM without its file header
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::bytes"))]
     pub bytes: Vec<u8>,
     pub target_row: NodeRow,
     pub steps: usize,
 }
 
+/// Result of importing a DAT file
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DatImport {
+    pub dat_name: String,
+    pub version: Option<String>,
+    /// ROM entries loaded from the DAT
+    pub entries: usize,
+    /// Library nodes that now match an entry in any imported DAT
+    pub verified: usize,
+}
+
+/// Result of building ROMs into a flash cart folder
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SdCardExport {
+    /// Files written, relative to the card folder
+    pub written: Vec<PathBuf>,
+    /// Selected nodes not linked to any of the source ROMs, so not built
+    pub unreachable: Vec<NodeRow>,
+}
+
+/// How building one node went in [`StorageManager::build_all`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeBuild {
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
+    pub sha256: [u8; 32],
+    /// The file it's written to, relative to the output folder
+    pub path: PathBuf,
+    /// Why it couldn't be built; None if it was written
+    pub error: Option<String>,
+}
+
+/// A link whose diff doesn't rebuild the ROM it leads to
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BrokenEdge {
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
+    pub from: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hash"))]
+    pub to: [u8; 32],
+    pub diff_path: String,
+    /// Why the diff failed: an error applying it, or the hash it built
+    pub reason: String,
+}
+
+/// How a file found by [`StorageManager::locate`] compares with the library
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum LocateMatch {
+    /// A node's ROM, with the header stored with the node (or no header)
+    Exact,
+    /// A node's ROM data under a different header
+    HeaderDiffers,
+    /// A ROM not in the library
+    Unknown,
+    /// A file that couldn't be hashed
+    Unreadable,
+}
+
+/// A ROM file found by [`StorageManager::locate`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LocatedFile {
+    pub path: PathBuf,
+    #[cfg_attr(feature = "serde", serde(rename = "match"))]
+    pub matched: LocateMatch,
+    /// The file's hash; None if it couldn't be hashed
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::optional_hex"))]
+    pub sha256: Option<[u8; 32]>,
+    /// The node it matches, for exact and header matches
+    pub node: Option<NodeRow>,
+    /// Why the file couldn't be hashed
+    pub error: Option<String>,
+}
+
+/// Result of rebuilding the whole library from source ROMs
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LibraryValidation {
+    /// Nodes rebuilt with the right hash, in the order they were reached,
+    /// the sources first
+    #[cfg_attr(feature = "serde", serde(with = "crate::wire::hashes"))]
+    pub verified: Vec<[u8; 32]>,
+    /// Diffs applied: every link out of every verified node
+    pub edges_checked: usize,
+    pub broken: Vec<BrokenEdge>,
+    /// Nodes linked to a source that no working link reaches
+    pub unbuilt: Vec<NodeRow>,
+    /// Nodes not linked to any of the sources, besides wishlist nodes
+    pub unreachable: Vec<NodeRow>,
+}
+
+/// Library health summary for the `status` command
+pub struct LibraryStatus {
+    pub node_count: usize,
+    pub diff_count: usize,
+    pub total_diff_bytes: i64,
+    /// Most recently added nodes, newest first
+    pub recent: Vec<NodeRow>,
+    /// Nodes with no links to any other node
+    pub unlinked: Vec<RomNode>,
+    /// Diff files referenced by edges but missing from the diffs directory
+    pub missing_diffs: Vec<String>,
+    pub last_export_at: Option<String>,
+    pub last_import_at: Option<String>,
+}
+
+/// dromos_meta keys recording when the library was last exported/imported
+const LAST_EXPORT_KEY: &str = "last_export_at";
+const LAST_IMPORT_KEY: &str = "last_import_at";
+
+/// Current UTC time in the same format as SQLite's `datetime('now')`
+fn now_timestamp() -> String {
+    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// `metadata` as it's stored, checked and tidied by [`NodeMetadata::normalize`].
+fn normalized(metadata: &NodeMetadata) -> Result<NodeMetadata> {
+    let mut metadata = metadata.clone();
+    metadata.normalize().map_err(DromosError::InvalidMetadata)?;
+    Ok(metadata)
+}
+
+/// A link's two diffs, written but not yet in the database.
+struct NewLink {
+    /// Database ID and hash of each end, A then B
+    nodes: [(i64, [u8; 32]); 2],
+    note: Option<String>,
+    /// A to B, then B to A
+    diffs: Vec<NewDiff>,
+}
+
+/// One diff of a [`NewLink`].
+struct NewDiff {
+    filename: String,
+    size: u64,
+    sha256: String,
+    /// Size of the ROM data the diff builds
+    output_size: usize,
+}
+
+impl NewLink {
+    /// Record both edges, with their checksums and output sizes.
+    fn insert(&self, repo: &Repository) -> Result<()> {
+        let [(id_a, _), (id_b, _)] = self.nodes;
+        for (diff, (source, target)) in self.diffs.iter().zip([(id_a, id_b), (id_b, id_a)]) {
+            repo.insert_edge(
+                source,
+                target,
+                &diff.filename,
+                diff.size as i64,
+                self.note.as_deref(),
+            )?;
+            repo.set_diff_checksum(&diff.filename, &diff.sha256)?;
+            repo.set_diff_output_size(&diff.filename, diff.output_size as i64)?;
+        }
+        Ok(())
+    }
+
+    /// Delete the diff files, for a link that won't be recorded.
+    fn remove_files(&self, diffs_dir: &Path) {
+        for diff in &self.diffs {
+            let _ = fs::remove_file(diffs_dir.join(&diff.filename));
+        }
+    }
+}
+
+/// A node deleted from the database, whose diff files, attachment
+/// contents, and place in the graph are still to go.
+struct DeletedNode {
+    row: NodeRow,
+    edges: Vec<EdgeRow>,
+    attachments: Vec<AttachmentRow>,
+}
+
+impl DeletedNode {
+    /// Delete `row`'s node with its edges, attachments, and other records.
+    fn delete(repo: &Repository, row: NodeRow) -> Result<DeletedNode> {
+        let edges = repo.get_edges_for_node(row.id)?;
+        let attachments = repo.get_attachments_for_node(row.id)?;
+        repo.delete_node(row.id)?;
+        Ok(DeletedNode {
+            row,
+            edges,
+            attachments,
+        })
+    }
+}
+
 pub struct StorageManager {
     conn: Connection,
     graph: RomGraph,
     config: StorageConfig,
+    /// Set when `open` wiped data from an older revision
+    data_wipe: Option<DataWipe>,
+    observers: Vec<(ObserverId, Box<dyn StorageObserver>)>,
+    /// Id of the next observer added
+    next_observer: u64,
+    read_only: bool,
+    diff_engine: DiffEngine,
+    /// Tags added to ROMs as they're added (see [`autotag`])
+    tag_rules: Vec<TagRule>,
+    /// Refuse to add ROMs with inconsistent headers
+    strict: bool,
 }
 
 impl StorageManager {
+    /// Options for opening a library; see [`StorageManagerBuilder`].
+    pub fn builder() -> StorageManagerBuilder {
+        StorageManagerBuilder::default()
+    }
+
+    /// Open the library at `config`'s paths with default options.
     pub fn open(config: StorageConfig) -> Result<Self> {
-        config.ensure_dirs_exist()?;
-
-        // Check if we need to wipe existing data due to revision change
-        let db_exists = config.db_path.exists();
-        if db_exists {
-            let conn = Connection::open(&config.db_path)?;
-            let stored_revision = get_stored_data_revision(&conn);
-            let has_data = has_existing_data(&conn);
-            drop(conn); // Close connection before potential delete
-
-            // Wipe if: revision mismatch OR (has data but no revision = legacy DB)
-            let needs_wipe = match stored_revision {
-                Some(rev) => rev < DATA_REVISION,
-                None => has_data, // Legacy DB without dromos_meta
-            };
+        Self::builder().paths(config).open()
+    }
 
-            if needs_wipe {
-                eprintln!(
-                    "Data revision changed (stored: {}, current: {}). Wiping database and diffs.",
-                    stored_revision
-                        .map(|r| r.to_string())
-                        .unwrap_or_else(|| "none".to_string()),
-                    DATA_REVISION
-                );
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "open",
+        skip_all,
+        fields(in_memory = options.in_memory, read_only = options.read_only),
+    ))]
+    pub(super) fn open_with(options: StorageManagerBuilder) -> Result<Self> {
+        let config = options.config()?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(db = %config.db_path.display(), diffs = %config.diffs_dir.display());
+        let mut data_wipe = None;
+        let conn = if options.in_memory {
+            if !options.read_only {
+                config.ensure_dirs_exist()?;
+            }
+            let mut conn = Connection::open_in_memory()?;
+            run_migrations(&mut conn)?;
+            conn
+        } else if options.read_only {
+            let conn = Connection::open_with_flags(
+                &config.db_path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )?;
+            let current =
+                matches!(get_stored_data_revision(&conn), Some(rev) if rev >= DATA_REVISION);
+            if !current || migrations_pending(&conn)? {
+                return Err(DromosError::UpgradeRequired);
+            }
+            conn
+        } else {
+            config.ensure_dirs_exist()?;
+            data_wipe = Self::wipe_if_outdated(&config)?;
 
-                // Delete database file
-                fs::remove_file(&config.db_path)?;
+            // Open (or create fresh) database
+            let mut conn = Connection::open(&config.db_path)?;
+            run_migrations(&mut conn)?;
+            set_data_revision(&conn, DATA_REVISION)?;
+            conn
+        };
+        collation::register(&conn)?;
 
-                // Delete all files in diffs directory
-                if config.diffs_dir.exists() {
-                    for entry in fs::read_dir(&config.diffs_dir)? {
-                        let entry = entry?;
-                        if entry.file_type()?.is_file() {
-                            fs::remove_file(entry.path())?;
-                        }
-                    }
-                }
-            }
+        if let Some(mode) = options.journal_mode
+            && !options.read_only
+        {
+            conn.pragma_update_and_check(None, "journal_mode", mode.to_string(), |_| Ok(()))?;
+        }
+        if let Some(kib) = options.cache_size_kib {
+            // A negative cache_size is in KiB rather than pages
+            conn.pragma_update(None, "cache_size", -i64::from(kib))?;
         }
-
-        // Open (or create fresh) database
-        let mut conn = Connection::open(&config.db_path)?;
-        run_migrations(&mut conn)?;
-        set_data_revision(&conn, DATA_REVISION)?;
 
         let mut manager = StorageManager {
             conn,
             graph: RomGraph::new(),
             config,
+            data_wipe,
+            observers: Vec::new(),
+            next_observer: 0,
+            read_only: options.read_only,
+            diff_engine: options.diff_engine,
+            tag_rules: Vec::new(),
+            strict: false,
         };
 
         manager.load_graph_from_db()?;
@@ -91,6 +366,63 @@ impl StorageManager {
         Ok(manager)
     }
 
+    /// Delete the database and diffs if they were written by an older data
+    /// revision, reporting what was wiped.
+    fn wipe_if_outdated(config: &StorageConfig) -> Result<Option<DataWipe>> {
+        if !config.db_path.exists() {
+            return Ok(None);
+        }
+        let conn = Connection::open(&config.db_path)?;
+        let stored_revision = get_stored_data_revision(&conn);
+        let has_data = has_existing_data(&conn);
+        drop(conn); // Close connection before potential delete
+
+        // Wipe if: revision mismatch OR (has data but no revision = legacy DB)
+        let needs_wipe = match stored_revision {
+            Some(rev) => rev < DATA_REVISION,
+            None => has_data, // Legacy DB without dromos_meta
+        };
+        if !needs_wipe {
+            return Ok(None);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            ?stored_revision,
+            current_revision = DATA_REVISION,
+            "wiping outdated library"
+        );
+
+        // Delete database file
+        fs::remove_file(&config.db_path)?;
+
+        // Delete all files in the diffs and attachments directories
+        for dir in [&config.diffs_dir, &config.attachments_dir] {
+            if dir.exists() {
+                for entry in fs::read_dir(dir)? {
+                    let entry = entry?;
+                    if entry.file_type()?.is_file() {
+                        fs::remove_file(entry.path())?;
+                    }
+                }
+            }
+        }
+
+        Ok(Some(DataWipe {
+            stored_revision,
+            current_revision: DATA_REVISION,
+        }))
+    }
+
+    /// Err(ReadOnly) if the library was opened read-only.
+    pub(crate) fn ensure_writable(&self) -> Result<()> {
+        if self.read_only {
+            Err(DromosError::ReadOnly)
+        } else {
+            Ok(())
+        }
+    }
+
     fn load_graph_from_db(&mut self) -> Result<()> {
         let repo = Repository::new(&self.conn);
 
@@ -121,20 +453,110 @@ impl StorageManager {
                         db_id: edge_row.id,
                         diff_path: edge_row.diff_path,
                         diff_size: edge_row.diff_size,
+                        note: edge_row.note,
+                        output_size: edge_row.output_size,
                     },
                 );
             }
         }
 
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            nodes = self.graph.node_count(),
+            edges = self.graph.edge_count(),
+            "graph loaded"
+        );
         Ok(())
     }
 
+    /// Replace the rules tagging ROMs as [`add_node`](Self::add_node) adds
+    /// them. Imports and syncs keep the tags they bring.
+    pub fn set_tag_rules(&mut self, rules: Vec<TagRule>) {
+        self.tag_rules = rules;
+    }
+
+    pub fn tag_rules(&self) -> &[TagRule] {
+        &self.tag_rules
+    }
+
+    /// Make [`add_node`](Self::add_node) refuse NES files whose headers
+    /// contradict themselves or the file (see
+    /// [`header_problems`](crate::rom::header_problems)), rather than storing
+    /// them as they are.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// In strict mode, Err(InconsistentHeader) if the NES file at `path`
+    /// has a header [`add_node`](Self::add_node) would refuse; Ok otherwise.
+    pub fn check_strict(&self, path: &Path) -> Result<()> {
+        if !self.strict {
+            return Ok(());
+        }
+        let problems = nes_file_problems(path)?;
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(DromosError::InconsistentHeader {
+            path: path.to_path_buf(),
+            problems: problems.iter().map(ToString::to_string).collect(),
+        })
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(path = %path.display()),
+    ))]
     pub fn add_node(&mut self, path: &Path, node_metadata: &NodeMetadata) -> Result<RomMetadata> {
+        self.ensure_writable()?;
+        let node_metadata = &normalized(node_metadata)?;
         let metadata = hash_rom_file(path)?;
+        if metadata.nes_header.is_some() {
+            self.check_strict(path)?;
+        }
+        self.insert_node(&metadata, node_metadata, false)?;
+        Ok(metadata)
+    }
 
-        let repo = Repository::new(&self.conn);
+    /// Add a wishlist node for a ROM known only by its hashes, such as one a
+    /// DAT lists, so it can be tracked before its file turns up. It's marked
+    /// missing until [`StorageManager::record_file`] sees the file.
+    pub fn add_wanted(
+        &mut self,
+        metadata: &RomMetadata,
+        node_metadata: &NodeMetadata,
+    ) -> Result<()> {
+        self.ensure_writable()?;
+        let node_metadata = &normalized(node_metadata)?;
+        self.insert_node(metadata, node_metadata, true)
+    }
 
-        let db_id = repo.insert_node(&metadata, node_metadata)?;
+    /// Insert a node, tagged by the auto-tag rules, and add it to the graph.
+    fn insert_node(
+        &mut self,
+        metadata: &RomMetadata,
+        node_metadata: &NodeMetadata,
+        missing: bool,
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let repo = Repository::new(&tx);
+        let db_id = repo.insert_node(metadata, node_metadata)?;
+        if missing {
+            repo.set_missing(db_id, true)?;
+        }
+        if let Some(filename) = &metadata.filename {
+            repo.record_filename_alias(&metadata.sha256, filename)?;
+        }
+        if let Some(tags) =
+            autotag::apply_rules(&repo, db_id, &node_metadata.tags, &self.tag_rules)?
+        {
+            repo.set_tags(db_id, &tags)?;
+        }
+        tx.commit()?;
 
         self.graph.add_node(RomNode {
             db_id,
@@ -145,7 +567,11 @@ impl StorageManager {
             rom_type: metadata.rom_type,
         });
 
-        Ok(metadata)
+        self.notify(StorageEvent::NodeAdded {
+            sha256: metadata.sha256,
+            title: node_metadata.title.clone(),
+        });
+        Ok(())
     }
 
     /// Get a node by hash, if it exists
@@ -160,16 +586,37 @@ impl StorageManager {
         self.graph.get_node_by_hash(sha256).is_some()
     }
 
-    /// Create bidirectional links between two ROMs using their file paths.
+    /// Create bidirectional links between two ROMs using their file paths,
+    /// with an optional note on what changed between them.
     /// Both ROMs must already exist in the database.
-    pub fn link_nodes(&mut self, path_a: &Path, path_b: &Path) -> Result<(u64, u64)> {
+    /// Cancelling stops the link, removing any diff already written.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(a = %path_a.display(), b = %path_b.display()),
+    ))]
+    pub fn link_nodes(
+        &mut self,
+        path_a: &Path,
+        path_b: &Path,
+        note: Option<&str>,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<(u64, u64)> {
+        self.ensure_writable()?;
         let bytes_a = read_rom_bytes(path_a)?;
         let bytes_b = read_rom_bytes(path_b)?;
 
-        let metadata_a = hash_rom_file(path_a)?;
-        let metadata_b = hash_rom_file(path_b)?;
+        let mut hashed = hash_rom_files(
+            &[path_a.to_path_buf(), path_b.to_path_buf()],
+            progress,
+            cancel,
+        )?;
+        let metadata_b = hashed.pop().unwrap()?;
+        let metadata_a = hashed.pop().unwrap()?;
 
         let repo = Repository::new(&self.conn);
+        repo.record_checksums(&metadata_a)?;
+        repo.record_checksums(&metadata_b)?;
 
         // Get both nodes from the database
         let node_a = repo
@@ -178,55 +625,130 @@ impl StorageManager {
         let node_b = repo
             .get_node_by_hash(&metadata_b.sha256)?
             .expect("Node B must exist in database");
+        self.link_rows(&node_a, &bytes_a, &node_b, &bytes_b, note, progress, cancel)
+    }
 
-        // Create A -> B diff
-        let diff_filename_ab = format!(
-            "{}_{}.bsdiff",
-            &format_hash(&metadata_a.sha256)[..16],
-            &format_hash(&metadata_b.sha256)[..16]
-        );
-        let diff_path_ab = self.config.diffs_dir.join(&diff_filename_ab);
-        let diff_size_ab = diff::create_diff(&bytes_a, &bytes_b, &diff_path_ab)?;
-
-        // Create B -> A diff
-        let diff_filename_ba = format!(
-            "{}_{}.bsdiff",
-            &format_hash(&metadata_b.sha256)[..16],
-            &format_hash(&metadata_a.sha256)[..16]
-        );
-        let diff_path_ba = self.config.diffs_dir.join(&diff_filename_ba);
-        let diff_size_ba = diff::create_diff(&bytes_b, &bytes_a, &diff_path_ba)?;
+    /// Diff two nodes' ROM data both ways and store the links, for
+    /// [`Self::link_nodes`].
+    #[allow(clippy::too_many_arguments)]
+    fn link_rows(
+        &mut self,
+        node_a: &NodeRow,
+        bytes_a: &[u8],
+        node_b: &NodeRow,
+        bytes_b: &[u8],
+        note: Option<&str>,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<(u64, u64)> {
+        let link = self.write_link(node_a, bytes_a, node_b, bytes_b, note, progress, cancel)?;
 
-        // Insert edges
-        repo.insert_edge(node_a.id, node_b.id, &diff_filename_ab, diff_size_ab as i64)?;
-        repo.insert_edge(node_b.id, node_a.id, &diff_filename_ba, diff_size_ba as i64)?;
+        // Insert both edges or neither
+        let tx = self.conn.unchecked_transaction()?;
+        link.insert(&Repository::new(&tx))?;
+        tx.commit()?;
+        Ok(self.add_link(link))
+    }
 
-        // Update in-memory graph
-        if let (Some(idx_a), Some(idx_b)) = (
-            self.graph.get_node_by_db_id(node_a.id),
-            self.graph.get_node_by_db_id(node_b.id),
-        ) {
-            self.graph.add_edge(
-                idx_a,
-                idx_b,
-                DiffEdge {
-                    db_id: 0,
-                    diff_path: diff_filename_ab,
-                    diff_size: diff_size_ab as i64,
-                },
-            );
-            self.graph.add_edge(
-                idx_b,
-                idx_a,
-                DiffEdge {
-                    db_id: 0,
-                    diff_path: diff_filename_ba,
-                    diff_size: diff_size_ba as i64,
-                },
-            );
+    /// Write the diffs between two nodes' ROM data, both ways, leaving
+    /// neither behind if writing either fails or is cancelled.
+    #[allow(clippy::too_many_arguments)]
+    fn write_link(
+        &self,
+        node_a: &NodeRow,
+        bytes_a: &[u8],
+        node_b: &NodeRow,
+        bytes_b: &[u8],
+        note: Option<&str>,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<NewLink> {
+        let filename = |from: &NodeRow, to: &NodeRow| {
+            format!(
+                "{}_{}.bsdiff",
+                &format_hash(&from.sha256)[..16],
+                &format_hash(&to.sha256)[..16]
+            )
+        };
+        let mut link = NewLink {
+            nodes: [(node_a.id, node_a.sha256), (node_b.id, node_b.sha256)],
+            note: note.map(String::from),
+            diffs: Vec::new(),
+        };
+        let steps = [
+            (filename(node_a, node_b), bytes_a, bytes_b),
+            (filename(node_b, node_a), bytes_b, bytes_a),
+        ];
+        for (i, (filename, from, to)) in steps.into_iter().enumerate() {
+            let path = self.config.diffs_dir.join(&filename);
+            let written = cancel
+                .check()
+                .map(|_| {
+                    progress.progress(&Progress {
+                        stage: Stage::Diffing,
+                        item: &filename,
+                        current: i + 1,
+                        total: 2,
+                        bytes: to.len() as u64,
+                    })
+                })
+                .and_then(|_| self.diff_engine.create_diff(from, to, &path))
+                .and_then(|size| cancel.check().map(|_| size))
+                .and_then(|size| {
+                    let bytes = fs::read(&path).map_err(DromosError::file(&path))?;
+                    Ok((size, hex::encode(hash_bytes(&bytes))))
+                });
+            match written {
+                Ok((size, sha256)) => link.diffs.push(NewDiff {
+                    filename,
+                    size,
+                    sha256,
+                    output_size: to.len(),
+                }),
+                Err(e) => {
+                    let _ = fs::remove_file(&path);
+                    link.remove_files(&self.config.diffs_dir);
+                    return Err(e);
+                }
+            }
         }
+        Ok(link)
+    }
 
-        Ok((diff_size_ab, diff_size_ba))
+    /// Add a link recorded by [`NewLink::insert`] to the graph, returning
+    /// its diffs' sizes.
+    fn add_link(&mut self, link: NewLink) -> (u64, u64) {
+        let [(id_a, sha256_a), (id_b, sha256_b)] = link.nodes;
+        let sizes = (link.diffs[0].size, link.diffs[1].size);
+        let indices = (
+            self.graph.get_node_by_db_id(id_a),
+            self.graph.get_node_by_db_id(id_b),
+        );
+        let ends = [
+            (sha256_a, sha256_b, indices),
+            (sha256_b, sha256_a, (indices.1, indices.0)),
+        ];
+        for (diff, (source, target, (from, to))) in link.diffs.into_iter().zip(ends) {
+            if let (Some(from), Some(to)) = (from, to) {
+                self.graph.add_edge(
+                    from,
+                    to,
+                    DiffEdge {
+                        db_id: 0,
+                        diff_path: diff.filename,
+                        diff_size: diff.size as i64,
+                        note: link.note.clone(),
+                        output_size: Some(diff.output_size as i64),
+                    },
+                );
+            }
+            self.notify(StorageEvent::EdgeCreated {
+                source,
+                target,
+                diff_size: diff.size as i64,
+            });
+        }
+        sizes
     }
 
     pub fn list(&self) -> (Vec<&RomNode>, Vec<(String, String, i64)>) {
@@ -249,12 +771,86 @@ impl StorageManager {
         (nodes, edges)
     }
 
+    /// Paths this manager stores its database and diffs in
+    pub fn config(&self) -> &StorageConfig {
+        &self.config
+    }
+
+    pub(super) fn graph(&self) -> &RomGraph {
+        &self.graph
+    }
+
+    pub(super) fn repository(&self) -> Repository<'_> {
+        Repository::new(&self.conn)
+    }
+
+    /// Register an observer to be told about every saved change.
+    pub fn add_observer(&mut self, observer: Box<dyn StorageObserver>) -> ObserverId {
+        let id = ObserverId(self.next_observer);
+        self.next_observer += 1;
+        self.observers.push((id, observer));
+        id
+    }
+
+    /// Stop telling an observer about changes, returning it if it was
+    /// registered.
+    pub fn remove_observer(&mut self, id: ObserverId) -> Option<Box<dyn StorageObserver>> {
+        let index = self.observers.iter().position(|(i, _)| *i == id)?;
+        Some(self.observers.remove(index).1)
+    }
+
+    fn notify(&mut self, event: StorageEvent) {
+        for (_, observer) in &mut self.observers {
+            observer.on_event(&event);
+        }
+    }
+
+    /// Whether `open` wiped the library because of a data revision change.
+    pub fn data_wipe(&self) -> Option<DataWipe> {
+        self.data_wipe
+    }
+
+    /// Run an ad-hoc read-only SQL statement against the library database.
+    /// A library on disk is queried through a read-only connection of its
+    /// own, so nothing a query does can reach the one changes are saved
+    /// through.
+    pub fn query_readonly(&self, sql: &str) -> Result<QueryResult> {
+        let Some(path) = self.conn.path().filter(|path| !path.is_empty()) else {
+            return Repository::new(&self.conn).query_readonly(sql);
+        };
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        conn.pragma_update(None, "query_only", true)?;
+        collation::register(&conn)?;
+        Repository::new(&conn).query_readonly(sql)
+    }
+
+    /// Number of ROMs and diff edges in the library
+    pub fn counts(&self) -> (usize, usize) {
+        (self.graph.node_count(), self.graph.edge_count())
+    }
+
     /// Count nodes in the connected component containing a node
     pub fn connected_component_count(&self, sha256: &[u8; 32]) -> Option<usize> {
         let idx = self.graph.get_node_by_hash(sha256)?;
         Some(self.graph.connected_component(idx).len())
     }
 
+    /// Hashes of the nodes in the connected component containing a node
+    /// (empty if there's no such node)
+    pub fn connected_component_hashes(&self, sha256: &[u8; 32]) -> Vec<[u8; 32]> {
+        let Some(idx) = self.graph.get_node_by_hash(sha256) else {
+            return Vec::new();
+        };
+        self.graph
+            .connected_component(idx)
+            .into_iter()
+            .filter_map(|i| self.graph.get_node(i).map(|n| n.sha256))
+            .collect()
+    }
+
     /// Count outgoing links for a node
     pub fn link_count(&self, sha256: &[u8; 32]) -> usize {
         self.graph
@@ -263,25 +859,60 @@ impl StorageManager {
             .unwrap_or(0)
     }
 
-    /// Get neighbors of a node by hash
-    pub fn get_neighbors(&self, sha256: &[u8; 32]) -> Option<Vec<(&RomNode, i64)>> {
+    /// Get neighbors of a node by hash, with the edge to each
+    pub fn get_neighbors(&self, sha256: &[u8; 32]) -> Option<Vec<(&RomNode, &DiffEdge)>> {
         let idx = self.graph.get_node_by_hash(sha256)?;
-        Some(
-            self.graph
-                .neighbors(idx)
-                .into_iter()
-                .map(|(node, edge)| (node, edge.diff_size))
-                .collect(),
-        )
+        Some(self.graph.neighbors(idx))
     }
 
-    /// Find a node by hash prefix (for user convenience)
+    /// Find the node a hash prefix refers to (for user convenience).
+    /// Returns None if no node, or more than one, has the prefix.
     pub fn find_node_by_hash_prefix(&self, prefix: &str) -> Option<&RomNode> {
+        match self.find_nodes_by_hash_prefix(prefix).as_slice() {
+            [node] => Some(node),
+            _ => None,
+        }
+    }
+
+    /// Find every node whose hash starts with `prefix`, sorted by title.
+    pub fn find_nodes_by_hash_prefix(&self, prefix: &str) -> Vec<&RomNode> {
         let prefix_lower = prefix.to_lowercase();
-        self.graph
+        let mut nodes: Vec<&RomNode> = self
+            .graph
             .iter_nodes()
             .map(|(_, node)| node)
-            .find(|node| format_hash(&node.sha256).starts_with(&prefix_lower))
+            .filter(|node| format_hash(&node.sha256).starts_with(&prefix_lower))
+            .collect();
+        nodes.sort_by(|a, b| compare_titles(&a.title, &b.title));
+        nodes
+    }
+
+    /// Find nodes by title (case-insensitive). Exact matches on the title or
+    /// "Title [version]" win; otherwise falls back to substring matches.
+    pub fn find_nodes_by_title(&self, query: &str) -> Vec<&RomNode> {
+        let query_lower = query.to_lowercase();
+        let nodes: Vec<&RomNode> = self.graph.iter_nodes().map(|(_, node)| node).collect();
+
+        let exact: Vec<&RomNode> = nodes
+            .iter()
+            .copied()
+            .filter(|node| {
+                let title = node.title.to_lowercase();
+                let with_version = match node.version.as_deref() {
+                    Some(v) if !v.is_empty() => format!("{} [{}]", title, v.to_lowercase()),
+                    _ => title.clone(),
+                };
+                title == query_lower || with_version == query_lower
+            })
+            .collect();
+        if !exact.is_empty() {
+            return exact;
+        }
+
+        nodes
+            .into_iter()
+            .filter(|node| node.title.to_lowercase().contains(&query_lower))
+            .collect()
     }
 
     /// Get full NodeRow from database (includes header metadata)
@@ -291,11 +922,16 @@ impl StorageManager {
     }
 
     /// Update metadata for a node
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(hash = %format_hash(sha256)),
+    ))]
     pub fn update_node_metadata(
         &mut self,
         sha256: &[u8; 32],
         node_metadata: &NodeMetadata,
     ) -> Result<()> {
+        self.ensure_writable()?;
         let repo = Repository::new(&self.conn);
 
         // Get node from database
@@ -304,6 +940,11 @@ impl StorageManager {
             .ok_or_else(|| DromosError::RomNotFound {
                 hash: format_hash(sha256),
             })?;
+        let mut node_metadata = node_metadata.clone();
+        node_metadata
+            .normalize_edit(&node_row.metadata())
+            .map_err(DromosError::InvalidMetadata)?;
+        let node_metadata = &node_metadata;
 
         // Update database
         repo.update_node_metadata(node_row.id, node_metadata)?;
@@ -316,6 +957,108 @@ impl StorageManager {
             node.version = node_metadata.version.clone();
         }
 
+        self.notify(StorageEvent::MetadataChanged {
+            sha256: *sha256,
+            title: node_metadata.title.clone(),
+        });
+        Ok(())
+    }
+
+    /// Pin or unpin a node as a favorite.
+    pub fn set_favorite(&mut self, sha256: &[u8; 32], favorite: bool) -> Result<()> {
+        self.ensure_writable()?;
+        let node_id = self.node_db_id(sha256)?;
+        Repository::new(&self.conn).set_favorite(node_id, favorite)?;
+        let title = self
+            .get_node_by_hash(sha256)
+            .map(|n| n.title.clone())
+            .unwrap_or_default();
+        self.notify(StorageEvent::MetadataChanged {
+            sha256: *sha256,
+            title,
+        });
+        Ok(())
+    }
+
+    /// Archive or unarchive a node, hiding it from `list` and `search`.
+    pub fn set_archived(&mut self, sha256: &[u8; 32], archived: bool) -> Result<()> {
+        self.ensure_writable()?;
+        let node_id = self.node_db_id(sha256)?;
+        Repository::new(&self.conn).set_archived(node_id, archived)?;
+        let title = self
+            .get_node_by_hash(sha256)
+            .map(|n| n.title.clone())
+            .unwrap_or_default();
+        self.notify(StorageEvent::MetadataChanged {
+            sha256: *sha256,
+            title,
+        });
+        Ok(())
+    }
+
+    /// Hashes of the favorite nodes
+    pub fn favorites(&self) -> Result<HashSet<[u8; 32]>> {
+        let filter = NodeFilter {
+            favorite: true,
+            ..Default::default()
+        };
+        Ok(self
+            .search(&filter)?
+            .into_iter()
+            .map(|r| r.sha256)
+            .collect())
+    }
+
+    /// The metadata changes the CSV file at `path` would make (see [`csv`]).
+    pub fn plan_csv_edits(&self, path: &Path) -> Result<csv::CsvEdits> {
+        csv::read_csv_edits(path, &self.search(&NodeFilter::default())?)
+    }
+
+    /// Apply metadata changes planned from a CSV (see [`csv`]) or a bulk edit
+    /// in one transaction: all of them, or none on failure.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(changes = changes.len()),
+    ))]
+    pub fn apply_metadata_changes(&mut self, changes: &[MetadataChange]) -> Result<()> {
+        self.ensure_writable()?;
+        let changes = changes
+            .iter()
+            .map(|change| {
+                let mut change = change.clone();
+                change
+                    .after
+                    .normalize_edit(&change.before)
+                    .map_err(|reason| {
+                        DromosError::InvalidMetadata(format!("{}: {}", change.after.title, reason))
+                    })?;
+                Ok(change)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let tx = self.conn.unchecked_transaction()?;
+        let repo = Repository::new(&tx);
+        for change in &changes {
+            let node_row =
+                repo.get_node_by_hash(&change.sha256)?
+                    .ok_or_else(|| DromosError::RomNotFound {
+                        hash: format_hash(&change.sha256),
+                    })?;
+            repo.update_node_metadata(node_row.id, &change.after)?;
+        }
+        tx.commit()?;
+
+        for change in &changes {
+            if let Some(idx) = self.graph.get_node_by_hash(&change.sha256)
+                && let Some(node) = self.graph.get_node_mut(idx)
+            {
+                node.title = change.after.title.clone();
+                node.version = change.after.version.clone();
+            }
+            self.notify(StorageEvent::MetadataChanged {
+                sha256: change.sha256,
+                title: change.after.title.clone(),
+            });
+        }
         Ok(())
     }
 
@@ -331,213 +1074,1663 @@ impl StorageManager {
     }
 
     /// Build a ROM by applying diffs from source to target
-    pub fn build_rom(&self, source_path: &Path, target_hash: &[u8; 32]) -> Result<BuildResult> {
-        // Get source metadata and verify it's in DB
-        let source_meta = hash_rom_file(source_path)?;
-        if self.get_node_by_hash(&source_meta.sha256).is_none() {
-            return Err(DromosError::RomNotFound {
-                hash: format_hash(&source_meta.sha256),
-            });
-        }
+    pub fn build_rom(
+        &self,
+        source_path: &Path,
+        target_hash: &[u8; 32],
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<BuildResult> {
+        build_rom(
+            &self.graph,
+            &Repository::new(&self.conn),
+            &self.config.diffs_dir,
+            source_path,
+            target_hash,
+            progress,
+            cancel,
+        )
+    }
 
-        // Find path
-        let path = self
-            .find_path(&source_meta.sha256, target_hash)
-            .ok_or_else(|| DromosError::NoPath {
-                from: format_hash(&source_meta.sha256),
-                to: format_hash(target_hash),
+    /// A node's ROM data, without its file header, from a file on hand:
+    /// one of `sources` or a place [`locate`](Self::locate) recorded the
+    /// node or a node linked to it, read as is if it holds the node's ROM
+    /// or built from it otherwise. SourceNeeded if none of them do.
+    pub fn node_bytes(
+        &self,
+        sha256: &[u8; 32],
+        sources: &[PathBuf],
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<Vec<u8>> {
+        let idx = self
+            .graph
+            .get_node_by_hash(sha256)
+            .ok_or_else(|| DromosError::RomNotFound {
+                hash: format_hash(sha256),
             })?;
+        let repo = Repository::new(&self.conn);
 
-        // Read source bytes (headerless ROM data)
-        let mut current_bytes = read_rom_bytes(source_path)?;
-
-        // Apply each diff in the path
-        for step in path.iter().skip(1) {
-            // Skip source node
-            if let Some(ref edge) = step.edge {
-                let diff_path = self.config.diffs_dir.join(&edge.diff_path);
-                current_bytes = diff::apply_diff(&current_bytes, &diff_path)?;
+        // The node's own recorded files first, then its linked nodes'
+        let linked = self.graph.connected_component(idx);
+        let mut located = Vec::new();
+        for member in std::iter::once(idx).chain(linked.into_iter().filter(|&m| m != idx)) {
+            if let Some(node) = self.graph.get_node(member) {
+                let node_id = self.node_db_id(&node.sha256)?;
+                located.extend(
+                    repo.locations(node_id)?
+                        .into_iter()
+                        .map(|l| PathBuf::from(l.path))
+                        .filter(|path| path.is_file()),
+                );
             }
         }
 
-        // Get target node row (with header metadata)
-        let target_row =
-            self.get_node_row_by_hash(target_hash)?
-                .ok_or_else(|| DromosError::RomNotFound {
-                    hash: format_hash(target_hash),
-                })?;
-
-        Ok(BuildResult {
-            bytes: current_bytes,
-            target_row,
-            steps: path.len() - 1,
+        for path in sources {
+            let metadata = hash_rom_file(path)?;
+            if metadata.sha256 == *sha256 {
+                return read_rom_bytes(path);
+            }
+            if self.graph.get_node_by_hash(&metadata.sha256).is_some() {
+                match self.build_rom(path, sha256, progress, cancel) {
+                    Err(DromosError::NoPath { .. }) => {}
+                    built => return built.map(|b| b.bytes),
+                }
+            }
+        }
+        for path in &located {
+            if hash_rom_file(path).is_ok_and(|m| m.sha256 == *sha256) {
+                return read_rom_bytes(path);
+            }
+        }
+        for path in &located {
+            if let Ok(built) = self.build_rom(path, sha256, progress, cancel) {
+                return Ok(built.bytes);
+            }
+        }
+        Err(DromosError::SourceNeeded {
+            hash: format_hash(sha256),
+            title: self
+                .get_node_by_hash(sha256)
+                .map(|n| n.title.clone())
+                .unwrap_or_default(),
         })
     }
 
-    /// Export nodes/edges to a folder.
+    /// Gather what an export would write, without writing anything.
     /// If `component_hash` is provided, exports only the connected component.
-    pub fn export(
-        &self,
-        output_path: &Path,
-        component_hash: Option<&[u8; 32]>,
-        on_conflict: &mut impl FnMut(&Path) -> Result<exchange::OverwriteAction>,
-    ) -> Result<exchange::ExportStats> {
+    pub fn plan_export(&self, component_hash: Option<&[u8; 32]>) -> Result<exchange::ExportPlan> {
         let repo = Repository::new(&self.conn);
-        exchange::write_folder(
-            output_path,
+        exchange::plan_export(
             &repo,
             &self.graph,
             &self.config.diffs_dir,
+            &self.config.attachments_dir,
             component_hash,
-            on_conflict,
         )
     }
 
-    /// Analyze an export folder for conflicts before importing.
-    pub fn analyze_import(
+    /// Gather what an export of the nodes matching `filter` would write,
+    /// limited to the component containing `component_hash` if given.
+    pub fn plan_filtered_export(
         &self,
-        folder_path: &Path,
-    ) -> Result<(exchange::ExportManifest, Vec<exchange::NodeConflict>)> {
-        let repo = Repository::new(&self.conn);
+        component_hash: Option<&[u8; 32]>,
+        filter: &NodeFilter,
+    ) -> Result<exchange::ExportPlan> {
+        let node_hashes: HashSet<[u8; 32]> = self
+            .search_component(component_hash, filter)?
+            .iter()
+            .map(|row| row.sha256)
+            .collect();
+        exchange::plan_export_of(
+            &Repository::new(&self.conn),
+            &self.config.diffs_dir,
+            &self.config.attachments_dir,
+            &node_hashes,
+        )
+    }
+
+    /// Write a planned export to a folder, replacing existing files only if
+    /// they are in `overwrite`. Cancelling removes the files it created.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(output = %output_path.display()),
+    ))]
+    pub fn export(
+        &self,
+        output_path: &Path,
+        plan: &exchange::ExportPlan,
+        overwrite: &HashSet<PathBuf>,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<exchange::ExportStats> {
+        let stats = exchange::write_folder(output_path, plan, overwrite, progress, cancel)?;
+        if !self.read_only {
+            Repository::new(&self.conn).set_meta(LAST_EXPORT_KEY, &now_timestamp())?;
+        }
+        Ok(stats)
+    }
+
+    /// Analyze an export folder for conflicts before importing.
+    pub fn analyze_import(
+        &self,
+        folder_path: &Path,
+    ) -> Result<(exchange::ExportManifest, Vec<exchange::NodeConflict>)> {
+        let repo = Repository::new(&self.conn);
         exchange::analyze_import(folder_path, &repo)
     }
 
     /// Execute import from an export folder.
+    /// The import is all-or-nothing: on failure or cancellation, database
+    /// changes are rolled back and copied diff files removed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(folder = %folder_path.display()),
+    ))]
     pub fn execute_import(
         &mut self,
         folder_path: &Path,
         manifest: &exchange::ExportManifest,
-        overwrite: bool,
+        resolutions: &HashMap<String, exchange::ConflictResolution>,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
     ) -> Result<exchange::ImportResult> {
-        let repo = Repository::new(&self.conn);
-        exchange::execute_import(
+        self.ensure_writable()?;
+        let tx = self.conn.unchecked_transaction()?;
+        let repo = Repository::new(&tx);
+        let result = exchange::execute_import(
             folder_path,
             manifest,
-            overwrite,
+            resolutions,
             &repo,
             &mut self.graph,
             &self.config.diffs_dir,
+            &self.config.attachments_dir,
+            progress,
+            cancel,
         )
+        .and_then(|result| {
+            repo.set_meta(LAST_IMPORT_KEY, &now_timestamp())?;
+            Ok(result)
+        });
+
+        match result {
+            Ok(result) => {
+                tx.commit()?;
+                self.notify_import(&result);
+                Ok(result)
+            }
+            Err(e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %e, "import rolled back");
+                // Dropping the transaction rolls it back; rebuild the graph to match
+                drop(tx);
+                self.graph = RomGraph::new();
+                self.load_graph_from_db()?;
+                Err(e)
+            }
+        }
     }
 
-    /// Remove a node and all its associated links (edges and diff files)
-    pub fn remove_node(&mut self, sha256: &[u8; 32]) -> Result<RemoveResult> {
+    /// Import an export folder a sync gathered from another library. Nodes
+    /// already here keep their metadata.
+    pub fn import_synced(
+        &mut self,
+        folder: &Path,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<exchange::ImportResult> {
+        self.ensure_writable()?;
+        let (manifest, _) = self.analyze_import(folder)?;
+        self.execute_import(folder, &manifest, &HashMap::new(), progress, cancel)
+    }
+
+    /// Report the changes a committed import made.
+    fn notify_import(&mut self, result: &exchange::ImportResult) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let title = |m: &Self, sha256: &[u8; 32]| {
+            m.get_node_by_hash(sha256)
+                .map(|n| n.title.clone())
+                .unwrap_or_default()
+        };
+        for sha256 in &result.added {
+            let event = StorageEvent::NodeAdded {
+                sha256: *sha256,
+                title: title(self, sha256),
+            };
+            self.notify(event);
+        }
+        for sha256 in &result.overwritten {
+            let event = StorageEvent::MetadataChanged {
+                sha256: *sha256,
+                title: title(self, sha256),
+            };
+            self.notify(event);
+        }
+        for link in &result.linked {
+            self.notify(StorageEvent::EdgeCreated {
+                source: link.source,
+                target: link.target,
+                diff_size: link.diff_size,
+            });
+        }
+    }
+
+    /// Build the nodes matching `filter` into `output`, laid out for a flash
+    /// cart's SD card (see [`sdcard`]). Each is built from whichever of the
+    /// `sources` it's linked to; nodes linked to none are skipped. Existing
+    /// files are replaced. On failure or cancellation, the files it created
+    /// are removed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(output = %output.display(), %layout),
+    ))]
+    pub fn export_sd_card(
+        &self,
+        output: &Path,
+        sources: &[PathBuf],
+        filter: &NodeFilter,
+        layout: SdLayout,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<SdCardExport> {
+        // The source each node can be built from, by node hash
+        let mut source_for: HashMap<[u8; 32], &Path> = HashMap::new();
+        for (path, metadata) in sources
+            .iter()
+            .zip(hash_rom_files(sources, progress, cancel)?)
+        {
+            let metadata = metadata?;
+            let idx = self
+                .graph
+                .get_node_by_hash(&metadata.sha256)
+                .ok_or_else(|| DromosError::RomNotFound {
+                    hash: format_hash(&metadata.sha256),
+                })?;
+            for member in self.graph.connected_component(idx) {
+                if let Some(node) = self.graph.get_node(member) {
+                    source_for.entry(node.sha256).or_insert(path);
+                }
+            }
+        }
+        let (buildable, unreachable): (Vec<NodeRow>, Vec<NodeRow>) = self
+            .search(filter)?
+            .into_iter()
+            .partition(|row| source_for.contains_key(&row.sha256));
+
+        let plan = sdcard::plan_card(&buildable, layout);
         let repo = Repository::new(&self.conn);
+        let mut created = Vec::new();
+        let mut written = Vec::new();
+        for (i, (relative, hash)) in plan.iter().enumerate() {
+            let path = output.join(relative);
+            let result = cancel.check().and_then(|_| {
+                let built = build_rom(
+                    &self.graph,
+                    &repo,
+                    &self.config.diffs_dir,
+                    source_for[hash],
+                    hash,
+                    progress,
+                    cancel,
+                )?;
+                let bytes = match &built.target_row.source_file_header {
+                    Some(header) => reconstruct_nes_file_raw(header, &built.bytes),
+                    None => built.bytes,
+                };
+                progress.progress(&Progress {
+                    stage: Stage::Writing,
+                    item: &relative.display().to_string(),
+                    current: i + 1,
+                    total: plan.len(),
+                    bytes: bytes.len() as u64,
+                });
+                cancel.check()?;
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(DromosError::file(parent))?;
+                }
+                if !path.exists() {
+                    created.push(path.clone());
+                }
+                fs::write(&path, bytes).map_err(DromosError::file(&path))
+            });
+            if let Err(e) = result {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %e, removed = created.len(), "card export stopped");
+                for path in &created {
+                    let _ = fs::remove_file(path);
+                }
+                return Err(e);
+            }
+            written.push(relative.clone());
+        }
 
-        // Get the node from database
-        let node_row = repo
-            .get_node_by_hash(sha256)?
-            .expect("Node must exist in database");
+        Ok(SdCardExport {
+            written,
+            unreachable,
+        })
+    }
+
+    /// Build each of `rows` into `output` under a name made from its title
+    /// (see [`sdcard::plan_card`]), from `sources` or files on hand (see
+    /// [`Self::node_bytes`]). A node that can't be built is reported and the
+    /// rest carry on; wishlist nodes are left out. Existing files are
+    /// replaced. On cancellation, the files it created are removed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(output = %output.display(), nodes = rows.len()),
+    ))]
+    pub fn build_all(
+        &self,
+        rows: &[NodeRow],
+        output: &Path,
+        sources: &[PathBuf],
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<Vec<NodeBuild>> {
+        let rows: Vec<NodeRow> = rows.iter().filter(|row| !row.missing).cloned().collect();
+        let plan = sdcard::plan_card(&rows, SdLayout::Flat);
+        let mut created = Vec::new();
+        let mut builds = Vec::with_capacity(plan.len());
+        for (i, ((relative, hash), row)) in plan.into_iter().zip(&rows).enumerate() {
+            let path = output.join(&relative);
+            let result = cancel.check().and_then(|_| {
+                let data = self.node_bytes(&hash, sources, progress, cancel)?;
+                let bytes = match &row.source_file_header {
+                    Some(header) => reconstruct_nes_file_raw(header, &data),
+                    None => data,
+                };
+                progress.progress(&Progress {
+                    stage: Stage::Writing,
+                    item: &relative.display().to_string(),
+                    current: i + 1,
+                    total: rows.len(),
+                    bytes: bytes.len() as u64,
+                });
+                fs::create_dir_all(output).map_err(DromosError::file(output))?;
+                if !path.exists() {
+                    created.push(path.clone());
+                }
+                fs::write(&path, bytes).map_err(DromosError::file(&path))
+            });
+            let error = match result {
+                Ok(()) => None,
+                Err(DromosError::Cancelled) => {
+                    for path in &created {
+                        let _ = fs::remove_file(path);
+                    }
+                    return Err(DromosError::Cancelled);
+                }
+                Err(e) => Some(e.to_string()),
+            };
+            #[cfg(feature = "tracing")]
+            if let Some(error) = &error {
+                tracing::warn!(node = %format_hash(&hash), %error, "build failed");
+            }
+            builds.push(NodeBuild {
+                sha256: hash,
+                path: relative,
+                error,
+            });
+        }
+        Ok(builds)
+    }
 
-        let title = node_row.title.clone();
+    /// Rebuild every node linked to one of `sources` and check its hash,
+    /// applying every link out of each node that was built correctly, so a
+    /// bad diff is found even where another path to its target works.
+    /// Nothing is written.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(sources = sources.len())))]
+    pub fn validate_all(
+        &self,
+        sources: &[PathBuf],
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<LibraryValidation> {
+        let mut verified = Vec::new();
+        let mut seen = HashSet::new();
+        let mut component = HashSet::new();
+        let mut queue = VecDeque::new();
+        for (path, metadata) in sources
+            .iter()
+            .zip(hash_rom_files(sources, progress, cancel)?)
+        {
+            let metadata = metadata?;
+            let idx = self
+                .graph
+                .get_node_by_hash(&metadata.sha256)
+                .ok_or_else(|| DromosError::RomNotFound {
+                    hash: format_hash(&metadata.sha256),
+                })?;
+            if seen.insert(metadata.sha256) {
+                component.extend(self.graph.connected_component(idx));
+                verified.push(metadata.sha256);
+                queue.push_back((idx, read_rom_bytes(path)?));
+            }
+        }
+        let total: usize = component
+            .iter()
+            .map(|&idx| self.graph.outgoing_edge_count(idx))
+            .sum();
+
+        // Breadth first from the sources, keeping the bytes of each node
+        // built until its own links have been tried
+        let mut edges_checked = 0;
+        let mut broken = Vec::new();
+        while let Some((idx, bytes)) = queue.pop_front() {
+            let Some(from) = self.graph.get_node(idx).map(|n| n.sha256) else {
+                continue;
+            };
+            for (target, edge) in self.graph.neighbors(idx) {
+                cancel.check()?;
+                edges_checked += 1;
+                progress.progress(&Progress {
+                    stage: Stage::Patching,
+                    item: &edge.diff_path,
+                    current: edges_checked,
+                    total,
+                    bytes: edge.diff_size as u64,
+                });
+                let diff_path = self.config.diffs_dir.join(&edge.diff_path);
+                let expected = edge.output_size.map(|size| size as u64);
+                let reason = match diff::apply_diff(&bytes, &diff_path, expected) {
+                    Ok(built) => {
+                        let actual = hash_bytes(&built);
+                        if actual == target.sha256 {
+                            if seen.insert(target.sha256)
+                                && let Some(target_idx) =
+                                    self.graph.get_node_by_hash(&target.sha256)
+                            {
+                                verified.push(target.sha256);
+                                queue.push_back((target_idx, built));
+                            }
+                            continue;
+                        }
+                        format!("built a ROM with SHA-256 {}", format_hash(&actual))
+                    }
+                    Err(e) => e.to_string(),
+                };
+                #[cfg(feature = "tracing")]
+                tracing::warn!(diff = %edge.diff_path, %reason, "broken link");
+                broken.push(BrokenEdge {
+                    from,
+                    to: target.sha256,
+                    diff_path: edge.diff_path.clone(),
+                    reason,
+                });
+            }
+        }
 
-        // Get all edges involving this node
-        let edges = repo.get_edges_for_node(node_row.id)?;
-        let edges_removed = edges.len();
+        let component: HashSet<[u8; 32]> = component
+            .iter()
+            .filter_map(|&idx| self.graph.get_node(idx).map(|n| n.sha256))
+            .collect();
+        let mut unbuilt = Vec::new();
+        let mut unreachable = Vec::new();
+        for row in Repository::new(&self.conn).load_all_nodes()? {
+            if seen.contains(&row.sha256) {
+                continue;
+            }
+            if component.contains(&row.sha256) {
+                unbuilt.push(row);
+            } else if !row.missing {
+                unreachable.push(row);
+            }
+        }
+        Ok(LibraryValidation {
+            verified,
+            edges_checked,
+            broken,
+            unbuilt,
+            unreachable,
+        })
+    }
 
-        // Delete diff files from disk (tolerating missing files)
-        let mut diff_files_removed = 0;
-        for edge in &edges {
-            let diff_path = self.config.diffs_dir.join(&edge.diff_path);
-            match fs::remove_file(&diff_path) {
-                Ok(()) => diff_files_removed += 1,
-                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                    eprintln!("Warning: diff file not found: {}", diff_path.display());
+    /// Every link's diff file as the database records it, for
+    /// [`health::check_diff`]
+    pub fn stored_diffs(&self) -> Result<Vec<StoredDiff>> {
+        let repo = Repository::new(&self.conn);
+        let hashes: HashMap<i64, [u8; 32]> = repo
+            .load_all_nodes()?
+            .into_iter()
+            .map(|row| (row.id, row.sha256))
+            .collect();
+        Ok(repo
+            .load_all_edges()?
+            .into_iter()
+            .filter_map(|edge| {
+                Some(StoredDiff {
+                    from: *hashes.get(&edge.source_id)?,
+                    to: *hashes.get(&edge.target_id)?,
+                    diff_path: edge.diff_path,
+                    diff_size: edge.diff_size,
+                    diff_sha256: edge.diff_sha256,
+                    output_size: edge.output_size,
+                })
+            })
+            .collect())
+    }
+
+    /// Check every diff file without building anything (see
+    /// [`health::check_diff`]). Healthy diffs linked before their checksums
+    /// and output sizes were kept have them recorded, unless the library is
+    /// read-only.
+    pub fn check_diffs(
+        &self,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<DiffCheck> {
+        let diffs = self.stored_diffs()?;
+        let repo = Repository::new(&self.conn);
+        let mut result = DiffCheck::default();
+        for (i, stored) in diffs.iter().enumerate() {
+            cancel.check()?;
+            progress.progress(&Progress {
+                stage: Stage::Hashing,
+                item: &stored.diff_path,
+                current: i + 1,
+                total: diffs.len(),
+                bytes: stored.diff_size as u64,
+            });
+            result.checked += 1;
+            match health::check_diff(&self.config.diffs_dir, stored) {
+                Ok(healthy)
+                    if (stored.diff_sha256.is_none() || stored.output_size.is_none())
+                        && !self.read_only =>
+                {
+                    repo.set_diff_checksum(&stored.diff_path, &healthy.sha256)?;
+                    repo.set_diff_output_size(&stored.diff_path, healthy.output_size as i64)?;
+                    result.checksums_recorded += 1;
                 }
-                Err(e) => {
-                    eprintln!("Warning: failed to delete {}: {}", diff_path.display(), e);
+                Ok(_) => {}
+                Err(problem) => result.problems.push(DiffProblem {
+                    from: stored.from,
+                    to: stored.to,
+                    diff_path: stored.diff_path.clone(),
+                    problem,
+                }),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Load a No-Intro or Redump DAT file, replacing any DAT previously
+    /// imported under the same name.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(path = %path.display()),
+    ))]
+    pub fn import_dat(&mut self, path: &Path) -> Result<DatImport> {
+        self.ensure_writable()?;
+        let dat = dat::read_dat(path)?;
+
+        let tx = self.conn.unchecked_transaction()?;
+        Repository::new(&tx).replace_dat_entries(&dat.name, &dat.entries)?;
+        tx.commit()?;
+
+        let verified = self.dat_matches()?.len();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(dat = %dat.name, entries = dat.entries.len(), verified, "DAT imported");
+        Ok(DatImport {
+            dat_name: dat.name,
+            version: dat.version,
+            entries: dat.entries.len(),
+            verified,
+        })
+    }
+
+    /// A DAT describing the library's ROMs as built, for auditing them with a
+    /// ROM manager: the nodes matching `filter`, limited to the component
+    /// containing `component_hash` if given. Write it with
+    /// [`dat::write_dat`].
+    pub fn plan_dat(
+        &self,
+        name: &str,
+        component_hash: Option<&[u8; 32]>,
+        filter: &NodeFilter,
+    ) -> Result<dat::DatFile> {
+        let rows = self.search_component(component_hash, filter)?;
+        Ok(dat::DatFile {
+            name: name.to_string(),
+            version: Some(chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string()),
+            header_skipper: Some(dat::NES_HEADER_SKIPPER.to_string()),
+            entries: dat::library_entries(&rows),
+        })
+    }
+
+    /// Write a static HTML catalog (see [`site`]) of the nodes matching
+    /// `filter` to `output`, limited to the component containing
+    /// `component_hash` if given. Existing files are replaced. On failure or
+    /// cancellation, the files it created are removed. Returns the number of
+    /// ROMs in the catalog.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(output = %output.display()),
+    ))]
+    pub fn export_site(
+        &self,
+        output: &Path,
+        name: &str,
+        component_hash: Option<&[u8; 32]>,
+        filter: &NodeFilter,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<usize> {
+        let rows = self.search_component(component_hash, filter)?;
+        let files = site::render_site(name, &rows, &self.edge_hashes(), &self.dat_matches()?);
+
+        let mut created = Vec::new();
+        for (i, (relative, contents)) in files.iter().enumerate() {
+            let path = output.join(relative);
+            progress.progress(&Progress {
+                stage: Stage::Writing,
+                item: &relative.display().to_string(),
+                current: i + 1,
+                total: files.len(),
+                bytes: contents.len() as u64,
+            });
+            let result = cancel.check().and_then(|_| {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(DromosError::file(parent))?;
+                }
+                if !path.exists() {
+                    created.push(path.clone());
                 }
+                fs::write(&path, contents).map_err(DromosError::file(&path))
+            });
+            if let Err(e) = result {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %e, removed = created.len(), "catalog export stopped");
+                for path in &created {
+                    let _ = fs::remove_file(path);
+                }
+                return Err(e);
             }
         }
+        Ok(rows.len())
+    }
+
+    /// Have `plugin` write the nodes matching `filter` (limited to the
+    /// component containing `component_hash` if given) and the links
+    /// between them to `output`. Returns the number of ROMs exported.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(plugin = plugin.name(), output = %output.display()),
+    ))]
+    pub fn export_with_plugin(
+        &self,
+        plugin: &Plugin,
+        output: &Path,
+        name: &str,
+        component_hash: Option<&[u8; 32]>,
+        filter: &NodeFilter,
+    ) -> Result<usize> {
+        let rows = self.search_component(component_hash, filter)?;
+        let hashes: HashSet<[u8; 32]> = rows.iter().map(|row| row.sha256).collect();
+        let edges: Vec<site::SiteEdge> = self
+            .edge_hashes()
+            .into_iter()
+            .filter(|(a, b, _)| hashes.contains(a) && hashes.contains(b))
+            .collect();
+        fs::create_dir_all(output).map_err(DromosError::file(output))?;
+        plugin.export(output, name, &rows, &edges)?;
+        Ok(rows.len())
+    }
+
+    /// Every link in the graph by the hashes of its ends, with its diff size
+    pub fn edge_hashes(&self) -> Vec<site::SiteEdge> {
+        self.graph
+            .iter_edges()
+            .filter_map(|(a, b, edge)| {
+                let a = self.graph.get_node(a)?.sha256;
+                let b = self.graph.get_node(b)?.sha256;
+                Some((a, b, edge.diff_size))
+            })
+            .collect()
+    }
+
+    /// The nodes matching `filter`, limited to the component containing
+    /// `component_hash` if given
+    fn search_component(
+        &self,
+        component_hash: Option<&[u8; 32]>,
+        filter: &NodeFilter,
+    ) -> Result<Vec<NodeRow>> {
+        let mut rows = self.search(filter)?;
+        if let Some(hash) = component_hash {
+            let idx =
+                self.graph
+                    .get_node_by_hash(hash)
+                    .ok_or_else(|| DromosError::RomNotFound {
+                        hash: format_hash(hash),
+                    })?;
+            let component: HashSet<[u8; 32]> = self
+                .graph
+                .connected_component(idx)
+                .into_iter()
+                .filter_map(|i| self.graph.get_node(i).map(|n| n.sha256))
+                .collect();
+            rows.retain(|row| component.contains(&row.sha256));
+        }
+        Ok(rows)
+    }
+
+    /// Record the SHA-1, CRC32, and content size from freshly hashed
+    /// `metadata` for a node added before they were kept. Does nothing in a read-only library.
+    pub fn record_checksums(&self, metadata: &RomMetadata) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        Repository::new(&self.conn).record_checksums(metadata)
+    }
 
-        // Delete edges and node from database
-        repo.delete_node(node_row.id)?;
+    /// Remember the name of a known node's file as one of its aliases (see
+    /// [`StorageManager::filename_aliases`]). Does nothing in a read-only
+    /// library.
+    pub fn record_filename(&self, metadata: &RomMetadata) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        if let Some(filename) = &metadata.filename {
+            Repository::new(&self.conn).record_filename_alias(&metadata.sha256, filename)?;
+        }
+        Ok(())
+    }
 
-        // Remove node from in-memory graph
-        if let Some(idx) = self.graph.get_node_by_hash(sha256) {
-            self.graph.remove_node(idx);
+    /// Record what hashing a known node's file learned: its SHA-1, CRC32,
+    /// and content size (see [`StorageManager::record_checksums`]), its name as an alias, and
+    /// for a wishlist node, the file itself, taking it off the wishlist.
+    /// Returns true if the node was wanted. Does nothing in a read-only
+    /// library.
+    pub fn record_file(&mut self, metadata: &RomMetadata) -> Result<bool> {
+        if self.read_only {
+            return Ok(false);
+        }
+        self.record_filename(metadata)?;
+        let repo = Repository::new(&self.conn);
+        if !repo.record_found(metadata)? {
+            repo.record_checksums(metadata)?;
+            return Ok(false);
         }
 
-        Ok(RemoveResult {
+        let mut title = String::new();
+        if let Some(idx) = self.graph.get_node_by_hash(&metadata.sha256)
+            && let Some(node) = self.graph.get_node_mut(idx)
+        {
+            node.filename = metadata.filename.clone();
+            node.rom_type = metadata.rom_type;
+            title = node.title.clone();
+        }
+        self.notify(StorageEvent::MetadataChanged {
+            sha256: metadata.sha256,
             title,
-            edges_removed,
-            diff_files_removed,
+        });
+        Ok(true)
+    }
+
+    /// Loaded DAT entries whose game name contains `text` (see
+    /// [`Repository::dat_entries_named`]), to add to the wishlist
+    pub fn dat_entries_named(&self, text: &str) -> Result<Vec<dat::DatEntry>> {
+        Repository::new(&self.conn).dat_entries_named(text)
+    }
+
+    /// The DAT entry a node matches, if it's a verified good dump
+    pub fn dat_match(&self, sha256: &[u8; 32]) -> Result<Option<DatMatch>> {
+        Repository::new(&self.conn).dat_match(sha256)
+    }
+
+    /// The DAT entry each verified node matches, by node hash
+    pub fn dat_matches(&self) -> Result<HashMap<[u8; 32], DatMatch>> {
+        Repository::new(&self.conn).dat_matches()
+    }
+
+    /// Attach a copy of the file at `path` to a node, or to its link with
+    /// `linked` if given, replacing an attachment with the same file name.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(path = %path.display(), hash = %format_hash(sha256)),
+    ))]
+    pub fn attach_file(
+        &mut self,
+        path: &Path,
+        sha256: &[u8; 32],
+        linked: Option<&[u8; 32]>,
+    ) -> Result<AttachmentRow> {
+        self.ensure_writable()?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| DromosError::FileNotFound {
+                path: path.to_path_buf(),
+            })?;
+        let node_id = self.node_db_id(sha256)?;
+        let linked_id = match linked {
+            Some(other) => Some(self.link_end_db_id(sha256, other)?),
+            None => None,
+        };
+        let bytes = fs::read(path).map_err(DromosError::file(path))?;
+        let contents = hash_bytes(&bytes);
+
+        let stored = self.config.attachments_dir.join(format_hash(&contents));
+        let created = !stored.exists();
+        if created {
+            fs::create_dir_all(&self.config.attachments_dir)
+                .map_err(DromosError::file(&self.config.attachments_dir))?;
+            fs::write(&stored, &bytes).map_err(DromosError::file(&stored))?;
+        }
+
+        let repo = Repository::new(&self.conn);
+        let replaced = repo
+            .get_attachments_for_node(node_id)?
+            .into_iter()
+            .find(|a| a.name == name && a.is_on(sha256, linked));
+        let id = match repo.insert_attachment(
+            node_id,
+            linked_id,
+            &name,
+            &contents,
+            bytes.len() as i64,
+        ) {
+            Ok(id) => id,
+            Err(e) => {
+                if created {
+                    let _ = fs::remove_file(&stored);
+                }
+                return Err(e);
+            }
+        };
+        if let Some(old) = replaced {
+            self.remove_unused_contents(&old.sha256)?;
+        }
+
+        Ok(AttachmentRow {
+            id,
+            node: *sha256,
+            linked_node: linked.copied(),
+            name,
+            sha256: contents,
+            size: bytes.len() as i64,
+            created_at: now_timestamp(),
         })
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::rom::{Mirroring, NesHeader, RomMetadata, RomType};
-    use rusqlite::Connection;
-    use std::path::PathBuf;
+    /// Remove the attachment named `name` from a node, or from its link
+    /// with `linked` if given, deleting its contents if nothing else uses them.
+    pub fn detach_file(
+        &mut self,
+        sha256: &[u8; 32],
+        linked: Option<&[u8; 32]>,
+        name: &str,
+    ) -> Result<AttachmentRow> {
+        self.ensure_writable()?;
+        let attachment = self
+            .attachments(sha256)?
+            .into_iter()
+            .find(|a| a.name == name && a.is_on(sha256, linked))
+            .ok_or_else(|| DromosError::AttachmentNotFound {
+                name: name.to_string(),
+            })?;
+        Repository::new(&self.conn).delete_attachment(attachment.id)?;
+        self.remove_unused_contents(&attachment.sha256)?;
+        Ok(attachment)
+    }
 
-    impl StorageManager {
-        /// Create a StorageManager with in-memory database for testing
-        pub fn new_in_memory(temp_dir: &Path) -> Result<Self> {
-            let config = StorageConfig {
-                db_path: PathBuf::from(":memory:"),
-                diffs_dir: temp_dir.join("diffs"),
-            };
-            config.ensure_dirs_exist()?;
+    /// Files attached to a node and to its links, the node's own first
+    pub fn attachments(&self, sha256: &[u8; 32]) -> Result<Vec<AttachmentRow>> {
+        let node_id = self.node_db_id(sha256)?;
+        Repository::new(&self.conn).get_attachments_for_node(node_id)
+    }
 
-            let mut conn = Connection::open_in_memory()?;
-            run_migrations(&mut conn)?;
+    /// Where an attachment's contents are stored
+    pub fn attachment_path(&self, attachment: &AttachmentRow) -> PathBuf {
+        self.config
+            .attachments_dir
+            .join(format_hash(&attachment.sha256))
+    }
 
-            Ok(StorageManager {
-                conn,
-                graph: RomGraph::new(),
-                config,
-            })
+    /// Delete stored attachment contents that no attachment uses any more.
+    fn remove_unused_contents(&self, contents: &[u8; 32]) -> Result<()> {
+        if !Repository::new(&self.conn).attachment_contents_in_use(contents)? {
+            let path = self.config.attachments_dir.join(format_hash(contents));
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(path = %path.display(), error = %e, "attachment left behind");
+                }
+                _ => {}
+            }
         }
+        Ok(())
+    }
 
-        /// Add a node directly from metadata (bypassing file I/O) for testing
-        pub fn add_node_from_metadata(
-            &mut self,
-            metadata: &RomMetadata,
-            title: &str,
-        ) -> Result<()> {
-            let node_meta = NodeMetadata {
-                title: title.to_string(),
-                ..Default::default()
-            };
-            let repo = Repository::new(&self.conn);
-            let db_id = repo.insert_node(metadata, &node_meta)?;
+    /// A node's database ID, or RomNotFound
+    fn node_db_id(&self, sha256: &[u8; 32]) -> Result<i64> {
+        self.get_node_by_hash(sha256)
+            .map(|n| n.db_id)
+            .ok_or_else(|| DromosError::RomNotFound {
+                hash: format_hash(sha256),
+            })
+    }
 
-            self.graph.add_node(RomNode {
-                db_id,
-                sha256: metadata.sha256,
-                filename: metadata.filename.clone(),
-                title: title.to_string(),
-                version: None,
-                rom_type: metadata.rom_type,
+    /// The database ID of `other`, or NotLinked if it isn't linked to `sha256`
+    fn link_end_db_id(&self, sha256: &[u8; 32], other: &[u8; 32]) -> Result<i64> {
+        let other_id = self.node_db_id(other)?;
+        let linked = self.graph.get_node_by_hash(sha256).is_some_and(|idx| {
+            self.graph
+                .neighbors(idx)
+                .iter()
+                .any(|(node, _)| node.sha256 == *other)
+        });
+        if !linked {
+            return Err(DromosError::NotLinked {
+                from: format_hash(sha256),
+                to: format_hash(other),
             });
+        }
+        Ok(other_id)
+    }
 
-            Ok(())
+    /// Create an empty collection, or CollectionExists if the name (in any
+    /// case) is taken.
+    pub fn create_collection(&mut self, name: &str) -> Result<CollectionRow> {
+        self.ensure_writable()?;
+        let repo = Repository::new(&self.conn);
+        if repo.collection_id(name)?.is_some() {
+            return Err(DromosError::CollectionExists {
+                name: name.to_string(),
+            });
         }
+        let id = repo.insert_collection(name)?;
+        Ok(CollectionRow {
+            id,
+            name: name.to_string(),
+            nodes: 0,
+            created_at: now_timestamp(),
+        })
     }
 
-    fn make_metadata(hash_byte: u8, filename: &str) -> RomMetadata {
-        let mut sha256 = [0u8; 32];
-        sha256[0] = hash_byte;
-        // Create a test header: 2 PRG banks (32KB), 1 CHR bank (8KB), mapper 4, vertical mirroring, battery
-        let header_bytes = vec![
-            b'N', b'E', b'S', 0x1A, // Magic
-            2,    // PRG ROM size (2 x 16KB = 32KB)
-            1,    // CHR ROM size (1 x 8KB = 8KB)
-            0x43, // Flags 6: mapper lo=4, vertical, battery
-            0x00, // Flags 7: mapper hi=0
-            0, 0, 0, 0, 0, 0, 0, 0, // Padding
-        ];
-        RomMetadata {
-            rom_type: RomType::Nes,
-            sha256,
-            filename: Some(filename.to_string()),
-            nes_header: Some(NesHeader {
-                prg_rom_size: 32 * 1024,
-                chr_rom_size: 8 * 1024,
-                has_trainer: false,
+    /// Delete a collection, leaving its nodes in the library. Returns the
+    /// collection as it was.
+    pub fn delete_collection(&mut self, name: &str) -> Result<CollectionRow> {
+        self.ensure_writable()?;
+        let collection = self.collection(name)?;
+        Repository::new(&self.conn).delete_collection(collection.id)?;
+        Ok(collection)
+    }
+
+    /// Add nodes to a collection. Returns the ones that weren't in it yet.
+    pub fn add_to_collection(&mut self, name: &str, nodes: &[[u8; 32]]) -> Result<Vec<[u8; 32]>> {
+        self.change_collection(name, nodes, |repo, collection, node| {
+            repo.add_to_collection(collection, node)
+        })
+    }
+
+    /// Take nodes out of a collection. Returns the ones that were in it.
+    pub fn remove_from_collection(
+        &mut self,
+        name: &str,
+        nodes: &[[u8; 32]],
+    ) -> Result<Vec<[u8; 32]>> {
+        self.change_collection(name, nodes, |repo, collection, node| {
+            repo.remove_from_collection(collection, node)
+        })
+    }
+
+    /// Apply `change` to each node's membership of a collection, all or
+    /// nothing, returning the nodes it changed.
+    fn change_collection(
+        &mut self,
+        name: &str,
+        nodes: &[[u8; 32]],
+        change: impl Fn(&Repository, i64, i64) -> Result<bool>,
+    ) -> Result<Vec<[u8; 32]>> {
+        self.ensure_writable()?;
+        let collection = self.collection(name)?;
+        let ids = nodes
+            .iter()
+            .map(|sha256| self.node_db_id(sha256))
+            .collect::<Result<Vec<_>>>()?;
+        let tx = self.conn.unchecked_transaction()?;
+        let repo = Repository::new(&tx);
+        let mut changed = Vec::new();
+        for (sha256, id) in nodes.iter().zip(ids) {
+            if change(&repo, collection.id, id)? {
+                changed.push(*sha256);
+            }
+        }
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    /// Every collection, ordered by name
+    pub fn collections(&self) -> Result<Vec<CollectionRow>> {
+        Repository::new(&self.conn).load_collections()
+    }
+
+    /// The collection named `name` (in any case), or CollectionNotFound
+    pub fn collection(&self, name: &str) -> Result<CollectionRow> {
+        self.collections()?
+            .into_iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| DromosError::CollectionNotFound {
+                name: name.to_string(),
+            })
+    }
+
+    /// Hash every ROM file under `dir` and classify it against the library:
+    /// a node's file, the same ROM data under a different header, unknown,
+    /// or unreadable. With `record`, each file matching a node is remembered
+    /// as one of its [`locations`](StorageManager::locations). Files are
+    /// returned sorted by path.
+    pub fn locate(
+        &self,
+        dir: &Path,
+        record: bool,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<Vec<LocatedFile>> {
+        if record {
+            self.ensure_writable()?;
+        }
+        let files = find_rom_files(dir)?;
+        let hashed = hash_rom_files(&files, progress, cancel)?;
+        let tx = self.conn.unchecked_transaction()?;
+        let repo = Repository::new(&tx);
+        let mut located = Vec::with_capacity(files.len());
+        for (path, hashed) in files.into_iter().zip(hashed) {
+            let metadata = match hashed {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    located.push(LocatedFile {
+                        path,
+                        matched: LocateMatch::Unreadable,
+                        sha256: None,
+                        node: None,
+                        error: Some(e.to_string()),
+                    });
+                    continue;
+                }
+            };
+            let node = repo
+                .get_node_by_hash(&metadata.sha256)?
+                .filter(|row| !row.missing);
+            let matched = match &node {
+                None => LocateMatch::Unknown,
+                Some(row) => match (&row.source_file_header, &metadata.source_file_header) {
+                    (Some(stored), Some(found)) if stored != found => LocateMatch::HeaderDiffers,
+                    _ => LocateMatch::Exact,
+                },
+            };
+            if record && node.is_some() {
+                let absolute = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                repo.record_location(
+                    &metadata.sha256,
+                    &absolute.to_string_lossy(),
+                    matched == LocateMatch::Exact,
+                )?;
+            }
+            located.push(LocatedFile {
+                path,
+                matched,
+                sha256: Some(metadata.sha256),
+                node,
+                error: None,
+            });
+        }
+        tx.commit()?;
+        Ok(located)
+    }
+
+    /// Every place `locate --record` found a node's file, by path
+    pub fn locations(&self, sha256: &[u8; 32]) -> Result<Vec<LocationRow>> {
+        let node_id = self.node_db_id(sha256)?;
+        Repository::new(&self.conn).locations(node_id)
+    }
+
+    /// Every file name a node has been seen under, oldest first, including
+    /// the one it was added from
+    pub fn filename_aliases(&self, sha256: &[u8; 32]) -> Result<Vec<String>> {
+        let node_id = self.node_db_id(sha256)?;
+        Repository::new(&self.conn).filename_aliases(node_id)
+    }
+
+    /// Names of the collections a node is in
+    pub fn node_collections(&self, sha256: &[u8; 32]) -> Result<Vec<String>> {
+        let node_id = self.node_db_id(sha256)?;
+        Repository::new(&self.conn).collections_for_node(node_id)
+    }
+
+    /// Nodes matching a structured search filter, ordered by title.
+    /// CollectionNotFound if it names a collection that doesn't exist.
+    pub fn search(&self, filter: &NodeFilter) -> Result<Vec<NodeRow>> {
+        for name in &filter.collections {
+            self.collection(name)?;
+        }
+        Repository::new(&self.conn).search_nodes(filter)
+    }
+
+    /// Nodes whose title, filename, or description matches `pattern`,
+    /// ordered by title
+    pub fn search_regex(&self, pattern: &Regex) -> Result<Vec<NodeRow>> {
+        let mut nodes = Vec::new();
+        for node in self.iter_nodes_filtered(&NodeFilter::default()) {
+            let node = node?;
+            let matches = [
+                Some(&node.title),
+                node.filename.as_ref(),
+                node.description.as_ref(),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|text| pattern.is_match(text));
+            if matches {
+                nodes.push(node);
+            }
+        }
+        nodes.sort_by(|a, b| compare_titles(&a.title, &b.title));
+        Ok(nodes)
+    }
+
+    /// Stream the nodes matching a filter in the order they were added,
+    /// reading a page of rows at a time.
+    pub fn iter_nodes_filtered(&self, filter: &NodeFilter) -> Cursor<'_, NodeRow> {
+        Repository::new(&self.conn).iter_nodes_filtered(filter)
+    }
+
+    /// Stream the edges to and from a node, reading a page of rows at a time.
+    pub fn iter_edges_for(&self, sha256: &[u8; 32]) -> Result<Cursor<'_, EdgeRow>> {
+        let repo = Repository::new(&self.conn);
+        let node_row = repo
+            .get_node_by_hash(sha256)?
+            .ok_or_else(|| DromosError::RomNotFound {
+                hash: format_hash(sha256),
+            })?;
+        Ok(repo.iter_edges_for(node_row.id))
+    }
+
+    /// Every node with its full metadata, in database order
+    pub fn node_rows(&self) -> Result<Vec<NodeRow>> {
+        Repository::new(&self.conn).load_all_nodes()
+    }
+
+    /// The most recently added or edited nodes, newest change first
+    pub fn recent_changes(&self, limit: usize) -> Result<Vec<NodeRow>> {
+        Repository::new(&self.conn).load_recently_changed_nodes(limit)
+    }
+
+    /// Summarize library health: counts, recent additions, unlinked nodes,
+    /// missing diff files, and last export/import times.
+    pub fn status(&self, recent_limit: usize) -> Result<LibraryStatus> {
+        let repo = Repository::new(&self.conn);
+
+        let mut unlinked: Vec<RomNode> = self
+            .graph
+            .iter_nodes()
+            .filter(|(idx, _)| self.graph.outgoing_edge_count(*idx) == 0)
+            .map(|(_, node)| node.clone())
+            .collect();
+        unlinked.sort_by(|a, b| compare_titles(&a.title, &b.title));
+
+        let mut total_diff_bytes = 0;
+        let mut missing_diffs = Vec::new();
+        for (_, _, edge) in self.graph.iter_edges() {
+            total_diff_bytes += edge.diff_size;
+            if !self.config.diffs_dir.join(&edge.diff_path).exists() {
+                missing_diffs.push(edge.diff_path.clone());
+            }
+        }
+        missing_diffs.sort();
+
+        Ok(LibraryStatus {
+            node_count: self.graph.node_count(),
+            diff_count: self.graph.edge_count(),
+            total_diff_bytes,
+            recent: repo.load_recent_nodes(recent_limit)?,
+            unlinked,
+            missing_diffs,
+            last_export_at: repo.get_meta(LAST_EXPORT_KEY)?,
+            last_import_at: repo.get_meta(LAST_IMPORT_KEY)?,
+        })
+    }
+
+    /// The nodes `dup` is linked to that `keep` isn't, which merging `dup`
+    /// into `keep` links to `keep`.
+    pub fn merge_relinks(&self, keep: &[u8; 32], dup: &[u8; 32]) -> Vec<[u8; 32]> {
+        let linked_to_keep: HashSet<[u8; 32]> = self
+            .get_neighbors(keep)
+            .unwrap_or_default()
+            .iter()
+            .map(|(node, _)| node.sha256)
+            .collect();
+        self.get_neighbors(dup)
+            .unwrap_or_default()
+            .iter()
+            .map(|(node, _)| node.sha256)
+            .filter(|sha256| sha256 != keep && !linked_to_keep.contains(sha256))
+            .collect()
+    }
+
+    /// Fold the duplicate node `dup` into `keep`, then remove it: `keep`
+    /// takes the merged metadata (see [`dedupe::merge_metadata`]), `dup`'s
+    /// favorite flag, collections, file names, locations, and own
+    /// attachments, and a link to each node in [`Self::merge_relinks`],
+    /// carrying over the link's note. Those links are diffed from ROM data
+    /// built from whichever of the `sources` each node is linked to;
+    /// SourceNeeded names a node none of them reach. The database changes
+    /// in one transaction, so a failure or cancel leaves both nodes as they
+    /// were.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(keep = %format_hash(keep), dup = %format_hash(dup)),
+    ))]
+    pub fn merge_nodes(
+        &mut self,
+        keep: &[u8; 32],
+        dup: &[u8; 32],
+        sources: &[PathBuf],
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<MergeResult> {
+        self.ensure_writable()?;
+        if keep == dup {
+            return Err(DromosError::MergeIntoItself {
+                hash: format_hash(keep),
+            });
+        }
+        let repo = Repository::new(&self.conn);
+        let row = |sha256: &[u8; 32]| {
+            repo.get_node_by_hash(sha256)?
+                .ok_or_else(|| DromosError::RomNotFound {
+                    hash: format_hash(sha256),
+                })
+        };
+        let keep_row = row(keep)?;
+        let dup_row = row(dup)?;
+        let relinks = self.merge_relinks(keep, dup);
+
+        // Build every ROM the new links need before changing anything
+        let mut built: HashMap<[u8; 32], Vec<u8>> = HashMap::new();
+        if !relinks.is_empty() {
+            let mut source_for: HashMap<[u8; 32], &Path> = HashMap::new();
+            for (path, metadata) in sources
+                .iter()
+                .zip(hash_rom_files(sources, progress, cancel)?)
+            {
+                let metadata = metadata?;
+                let idx = self
+                    .graph
+                    .get_node_by_hash(&metadata.sha256)
+                    .ok_or_else(|| DromosError::RomNotFound {
+                        hash: format_hash(&metadata.sha256),
+                    })?;
+                for member in self.graph.connected_component(idx) {
+                    if let Some(node) = self.graph.get_node(member) {
+                        source_for.entry(node.sha256).or_insert(path);
+                    }
+                }
+            }
+            for sha256 in std::iter::once(keep).chain(&relinks) {
+                let source = source_for.get(sha256).ok_or_else(|| {
+                    let title = self
+                        .get_node_by_hash(sha256)
+                        .map(|n| n.title.clone())
+                        .unwrap_or_default();
+                    DromosError::SourceNeeded {
+                        hash: format_hash(sha256),
+                        title,
+                    }
+                })?;
+                let result = build_rom(
+                    &self.graph,
+                    &repo,
+                    &self.config.diffs_dir,
+                    source,
+                    sha256,
+                    progress,
+                    cancel,
+                )?;
+                built.insert(*sha256, result.bytes);
+            }
+        }
+        let others = relinks.iter().map(row).collect::<Result<Vec<_>>>()?;
+        let notes: HashMap<[u8; 32], Option<String>> = self
+            .get_neighbors(dup)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(node, edge)| (node.sha256, edge.note.clone()))
+            .collect();
+        // Made of values already stored, so it's tidied but never refused
+        let mut metadata = dedupe::merge_metadata(&keep_row, &dup_row);
+        let stored = metadata.clone();
+        metadata
+            .normalize_edit(&stored)
+            .map_err(DromosError::InvalidMetadata)?;
+
+        // Write the new links' diffs, then change the database all at once,
+        // removing the diffs again if either fails
+        let mut links = Vec::new();
+        for other in &others {
+            let link = self.write_link(
+                &keep_row,
+                &built[keep],
+                other,
+                &built[&other.sha256],
+                notes[&other.sha256].as_deref(),
+                progress,
+                cancel,
+            );
+            match link {
+                Ok(link) => links.push(link),
+                Err(e) => {
+                    links
+                        .iter()
+                        .for_each(|link| link.remove_files(&self.config.diffs_dir));
+                    return Err(e);
+                }
+            }
+        }
+        let (collections, attachments_moved, deleted) =
+            match self.record_merge(&keep_row, dup_row, &metadata, &links) {
+                Ok(recorded) => recorded,
+                Err(e) => {
+                    links
+                        .iter()
+                        .for_each(|link| link.remove_files(&self.config.diffs_dir));
+                    return Err(e);
+                }
+            };
+
+        for link in links {
+            self.add_link(link);
+        }
+        if let Some(idx) = self.graph.get_node_by_hash(keep)
+            && let Some(node) = self.graph.get_node_mut(idx)
+        {
+            node.title = metadata.title.clone();
+            node.version = metadata.version.clone();
+        }
+        self.notify(StorageEvent::MetadataChanged {
+            sha256: *keep,
+            title: metadata.title.clone(),
+        });
+        let removed = self.finish_removal(deleted)?;
+        Ok(MergeResult {
+            metadata,
+            relinked: relinks,
+            collections,
+            attachments_moved,
+            removed,
+        })
+    }
+
+    /// The database side of [`Self::merge_nodes`], in one transaction:
+    /// record `links`, give `keep_row` its merged `metadata` and what it
+    /// takes from `dup_row`, and delete `dup_row`. Returns the collections
+    /// the kept node joined, how many attachments moved, and the deleted
+    /// duplicate.
+    fn record_merge(
+        &self,
+        keep_row: &NodeRow,
+        dup_row: NodeRow,
+        metadata: &NodeMetadata,
+        links: &[NewLink],
+    ) -> Result<(Vec<String>, usize, DeletedNode)> {
+        let tx = self.conn.unchecked_transaction()?;
+        let repo = Repository::new(&tx);
+        for link in links {
+            link.insert(&repo)?;
+        }
+        repo.update_node_metadata(keep_row.id, metadata)?;
+        if dup_row.favorite && !keep_row.favorite {
+            repo.set_favorite(keep_row.id, true)?;
+        }
+        let collections = repo.move_node_records(dup_row.id, keep_row.id)?;
+
+        let keep_attachments = repo.get_attachments_for_node(keep_row.id)?;
+        let mut attachments_moved = 0;
+        for attachment in repo.get_attachments_for_node(dup_row.id)? {
+            let taken = keep_attachments
+                .iter()
+                .any(|a| a.linked_node.is_none() && a.name == attachment.name);
+            if attachment.linked_node.is_none() && !taken {
+                repo.insert_attachment(
+                    keep_row.id,
+                    None,
+                    &attachment.name,
+                    &attachment.sha256,
+                    attachment.size,
+                )?;
+                attachments_moved += 1;
+            }
+        }
+
+        let deleted = DeletedNode::delete(&repo, dup_row)?;
+        tx.commit()?;
+        Ok((collections, attachments_moved, deleted))
+    }
+
+    /// Remove a node and all its associated links (edges and diff files)
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(hash = %format_hash(sha256)),
+    ))]
+    pub fn remove_node(&mut self, sha256: &[u8; 32]) -> Result<RemoveResult> {
+        self.ensure_writable()?;
+        let repo = Repository::new(&self.conn);
+
+        // Get the node from database
+        let node_row = repo
+            .get_node_by_hash(sha256)?
+            .expect("Node must exist in database");
+
+        // Delete edges, attachments, and node from database, then the files
+        // nothing refers to any more
+        let deleted = DeletedNode::delete(&repo, node_row)?;
+        self.finish_removal(deleted)
+    }
+
+    /// Delete the diff files and unused attachment contents of a node
+    /// already deleted from the database, and drop it from the graph.
+    fn finish_removal(&mut self, deleted: DeletedNode) -> Result<RemoveResult> {
+        // Delete diff files from disk, reporting files that are missing or
+        // can't be deleted
+        let mut diff_files_removed = 0;
+        let mut diff_files_failed = Vec::new();
+        for edge in &deleted.edges {
+            let diff_path = self.config.diffs_dir.join(&edge.diff_path);
+            match fs::remove_file(&diff_path) {
+                Ok(()) => diff_files_removed += 1,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    diff_files_failed.push((diff_path, "not found".to_string()));
+                }
+                Err(e) => diff_files_failed.push((diff_path, e.to_string())),
+            }
+        }
+        for attachment in &deleted.attachments {
+            self.remove_unused_contents(&attachment.sha256)?;
+        }
+
+        // Remove node from in-memory graph
+        let sha256 = deleted.row.sha256;
+        if let Some(idx) = self.graph.get_node_by_hash(&sha256) {
+            self.graph.remove_node(idx);
+        }
+
+        let title = deleted.row.title;
+        self.notify(StorageEvent::NodeRemoved {
+            sha256,
+            title: title.clone(),
+        });
+        Ok(RemoveResult {
+            title,
+            edges_removed: deleted.edges.len(),
+            diff_files_removed,
+            diff_files_failed,
+            attachments_removed: deleted.attachments.len(),
+        })
+    }
+}
+
+/// Apply the diffs along the path from `source_path`'s node to `target_hash`.
+/// Shared by [`StorageManager::build_rom`] and
+/// [`SharedStorageManager::build_rom`](super::SharedStorageManager::build_rom).
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(source = %source_path.display(), target = %format_hash(target_hash)),
+))]
+pub(super) fn build_rom(
+    graph: &RomGraph,
+    repo: &Repository,
+    diffs_dir: &Path,
+    source_path: &Path,
+    target_hash: &[u8; 32],
+    progress: &mut dyn ProgressSink,
+    cancel: &CancelToken,
+) -> Result<BuildResult> {
+    // Get source metadata and verify it's in DB
+    let source_meta = hash_rom_files(&[source_path.to_path_buf()], progress, cancel)?
+        .pop()
+        .unwrap()?;
+    let source_idx =
+        graph
+            .get_node_by_hash(&source_meta.sha256)
+            .ok_or_else(|| DromosError::RomNotFound {
+                hash: format_hash(&source_meta.sha256),
+            })?;
+
+    // Find path
+    let path = graph
+        .get_node_by_hash(target_hash)
+        .and_then(|target_idx| graph.find_path(source_idx, target_idx))
+        .ok_or_else(|| DromosError::NoPath {
+            from: format_hash(&source_meta.sha256),
+            to: format_hash(target_hash),
+        })?;
+
+    // Get target node row (with header metadata)
+    let target_row =
+        repo.get_node_by_hash(target_hash)?
+            .ok_or_else(|| DromosError::RomNotFound {
+                hash: format_hash(target_hash),
+            })?;
+
+    // Read source bytes (headerless ROM data)
+    let mut current_bytes = read_rom_bytes(source_path)?;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        steps = path.len() - 1,
+        source_len = current_bytes.len(),
+        "applying path"
+    );
+
+    // Apply each diff in the path; cancelling stops between steps
+    for (i, step) in path.iter().skip(1).enumerate() {
+        cancel.check()?;
+        // Skip source node
+        if let Some(ref edge) = step.edge {
+            progress.progress(&Progress {
+                stage: Stage::Patching,
+                item: &edge.diff_path,
+                current: i + 1,
+                total: path.len() - 1,
+                bytes: edge.diff_size as u64,
+            });
+            let diff_path = diffs_dir.join(&edge.diff_path);
+            // The last step must build the target's recorded size, even
+            // when the link doesn't record one
+            let last = i + 2 == path.len();
+            let expected = edge
+                .output_size
+                .map(|size| size as u64)
+                .or(target_row.content_size.filter(|_| last));
+            current_bytes =
+                diff::apply_diff(&current_bytes, &diff_path, expected).map_err(|e| {
+                    DromosError::BuildStep {
+                        step: i + 1,
+                        steps: path.len() - 1,
+                        diff_path: edge.diff_path.clone(),
+                        source: Box::new(e),
+                    }
+                })?;
+        }
+    }
+
+    Ok(BuildResult {
+        bytes: current_bytes,
+        target_row,
+        steps: path.len() - 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cancel::CancelToken;
+    use crate::progress::NoProgress;
+    use crate::rom::{Mirroring, NesHeader, RomMetadata, RomType};
+    use crate::storage::JournalMode;
+    use crate::test_support::{rom_file, write_rom};
+
+    fn in_memory_manager(temp_dir: &Path) -> StorageManager {
+        StorageManager::builder()
+            .in_memory()
+            .diffs_dir(temp_dir.join("diffs"))
+            .open()
+            .unwrap()
+    }
+
+    impl StorageManager {
+        /// Add a node directly from metadata (bypassing file I/O) for testing
+        pub fn add_node_from_metadata(
+            &mut self,
+            metadata: &RomMetadata,
+            title: &str,
+        ) -> Result<()> {
+            let node_meta = NodeMetadata {
+                title: title.to_string(),
+                ..Default::default()
+            };
+            let repo = Repository::new(&self.conn);
+            let db_id = repo.insert_node(metadata, &node_meta)?;
+
+            self.graph.add_node(RomNode {
+                db_id,
+                sha256: metadata.sha256,
+                filename: metadata.filename.clone(),
+                title: title.to_string(),
+                version: None,
+                rom_type: metadata.rom_type,
+            });
+
+            Ok(())
+        }
+    }
+
+    fn make_metadata(hash_byte: u8, filename: &str) -> RomMetadata {
+        let mut sha256 = [0u8; 32];
+        sha256[0] = hash_byte;
+        // Create a test header: 2 PRG banks (32KB), 1 CHR bank (8KB), mapper 4, vertical mirroring, battery
+        let header_bytes = vec![
+            b'N', b'E', b'S', 0x1A, // Magic
+            2,    // PRG ROM size (2 x 16KB = 32KB)
+            1,    // CHR ROM size (1 x 8KB = 8KB)
+            0x43, // Flags 6: mapper lo=4, vertical, battery
+            0x00, // Flags 7: mapper hi=0
+            0, 0, 0, 0, 0, 0, 0, 0, // Padding
+        ];
+        RomMetadata {
+            rom_type: RomType::Nes,
+            sha256,
+            sha1: None,
+            crc32: None,
+            content_size: None,
+            filename: Some(filename.to_string()),
+            nes_header: Some(NesHeader {
+                prg_rom_size: 32 * 1024,
+                chr_rom_size: 8 * 1024,
+                has_trainer: false,
                 mapper: 4,
                 mirroring: Mirroring::Vertical,
                 has_battery: true,
@@ -549,255 +2742,1722 @@ mod tests {
     }
 
     #[test]
-    fn test_add_node_and_retrieve() {
+    fn test_add_node_and_retrieve() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        let metadata = make_metadata(0xAA, "test.nes");
+        manager
+            .add_node_from_metadata(&metadata, "Test ROM")
+            .unwrap();
+
+        let node = manager
+            .get_node_by_hash(&metadata.sha256)
+            .expect("Node should exist");
+        assert_eq!(node.title, "Test ROM");
+        assert_eq!(node.sha256[0], 0xAA);
+    }
+
+    #[test]
+    fn test_add_node_applies_tag_rules() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        manager.set_tag_rules(vec![
+            TagRule::new("NROM", "mapper:0").unwrap(),
+            TagRule::new("MMC1", "mapper:1").unwrap(),
+            TagRule::new("translation", "filename:*[T+*").unwrap(),
+        ]);
+        let tags_of = |manager: &StorageManager, sha256: &[u8; 32]| {
+            manager.get_node_row_by_hash(sha256).unwrap().unwrap().tags
+        };
+
+        let plain = manager
+            .add_node(
+                &rom_file(temp_dir.path(), "Kid.nes", 0x11),
+                &NodeMetadata::default(),
+            )
+            .unwrap();
+        assert_eq!(tags_of(&manager, &plain.sha256), vec!["NROM"]);
+
+        // Tags already given aren't repeated, in any case
+        let metadata = NodeMetadata {
+            tags: vec!["nrom".to_string()],
+            ..Default::default()
+        };
+        let translated = manager
+            .add_node(
+                &rom_file(temp_dir.path(), "Kid (J) [T+Eng].nes", 0x22),
+                &metadata,
+            )
+            .unwrap();
+        assert_eq!(
+            tags_of(&manager, &translated.sha256),
+            vec!["nrom", "translation"]
+        );
+        assert!(TagRule::new("hack", "").is_err());
+        assert!(TagRule::new("hack", "mapper:x").is_err());
+    }
+
+    #[test]
+    fn test_add_node_strict() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        // Declares two PRG banks but holds one
+        let path = temp_dir.path().join("short.nes");
+        let mut bytes = vec![b'N', b'E', b'S', 0x1A, 2, 0, 0, 0];
+        bytes.resize(16, 0);
+        bytes.resize(16 + 16 * 1024, 0x33);
+        fs::write(&path, bytes).unwrap();
+
+        manager.set_strict(true);
+        let err = manager
+            .add_node(&path, &NodeMetadata::default())
+            .unwrap_err();
+        assert!(
+            matches!(&err, DromosError::InconsistentHeader { problems, .. } if problems.len() == 1),
+            "{err}"
+        );
+        assert_eq!(manager.list().0.len(), 0);
+
+        manager.set_strict(false);
+        manager.add_node(&path, &NodeMetadata::default()).unwrap();
+        assert_eq!(manager.list().0.len(), 1);
+    }
+
+    #[test]
+    fn test_wanted_node_found_by_its_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let path = temp_dir.path().join("Kid Icarus (J).nes");
+        write_rom(&path, 0x33);
+        let hashed = hash_rom_file(&path).unwrap();
+
+        let wanted = RomMetadata {
+            rom_type: RomType::Nes,
+            sha256: hashed.sha256,
+            sha1: None,
+            crc32: None,
+            content_size: None,
+            filename: None,
+            nes_header: None,
+            source_file_header: None,
+        };
+        let metadata = NodeMetadata {
+            title: "Kid Icarus".to_string(),
+            ..Default::default()
+        };
+        manager.add_wanted(&wanted, &metadata).unwrap();
+        let row = manager
+            .get_node_row_by_hash(&hashed.sha256)
+            .unwrap()
+            .unwrap();
+        assert!(row.missing);
+        assert_eq!(row.filename, None);
+        let wanted_rows = manager.search(&NodeFilter::parse("is:wanted").unwrap());
+        assert_eq!(wanted_rows.unwrap().len(), 1);
+
+        assert!(manager.record_file(&hashed).unwrap());
+        let row = manager
+            .get_node_row_by_hash(&hashed.sha256)
+            .unwrap()
+            .unwrap();
+        assert!(!row.missing);
+        assert_eq!(row.filename.as_deref(), Some("Kid Icarus (J).nes"));
+        assert_eq!(row.sha1, hashed.sha1);
+        assert!(row.source_file_header.is_some());
+        // Only the first sighting finds it
+        assert!(!manager.record_file(&hashed).unwrap());
+    }
+
+    #[test]
+    fn test_filename_aliases() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let path = temp_dir.path().join("Zelda Hack v1.nes");
+        let bytes = write_rom(&path, 0x44);
+        let metadata = NodeMetadata {
+            title: "Zelda Hack".to_string(),
+            ..Default::default()
+        };
+        let added = manager.add_node(&path, &metadata).unwrap();
+
+        let renamed = temp_dir.path().join("zhack_final.nes");
+        fs::write(&renamed, &bytes).unwrap();
+        let hashed = hash_rom_file(&renamed).unwrap();
+        manager.record_file(&hashed).unwrap();
+        manager.record_filename(&hashed).unwrap();
+        assert_eq!(
+            manager.filename_aliases(&added.sha256).unwrap(),
+            vec!["Zelda Hack v1.nes", "zhack_final.nes"]
+        );
+        // The node keeps the name it was added from
+        let row = manager
+            .get_node_row_by_hash(&added.sha256)
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.filename.as_deref(), Some("Zelda Hack v1.nes"));
+
+        let rows = manager
+            .search(&NodeFilter::parse("filename:zhack*").unwrap())
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[test]
+    fn test_locate() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let rom = |fill: u8, flags: u8| {
+            let mut bytes = vec![b'N', b'E', b'S', 0x1A, 1, 0, flags, 0];
+            bytes.resize(16, 0);
+            bytes.resize(16 + 16 * 1024, fill);
+            bytes
+        };
+        let added_path = temp_dir.path().join("added.nes");
+        fs::write(&added_path, rom(0x11, 0)).unwrap();
+        let added = manager
+            .add_node(&added_path, &NodeMetadata::default())
+            .unwrap();
+
+        let messy = temp_dir.path().join("messy");
+        fs::create_dir_all(messy.join("sub")).unwrap();
+        fs::write(messy.join("copy.nes"), rom(0x11, 0)).unwrap();
+        fs::write(messy.join("sub/vertical.nes"), rom(0x11, 1)).unwrap();
+        fs::write(messy.join("sub/other.nes"), rom(0x22, 0)).unwrap();
+        fs::write(messy.join("sub/junk.nes"), b"junk").unwrap();
+
+        let located = manager
+            .locate(&messy, false, &mut NoProgress, &CancelToken::new())
+            .unwrap();
+        let matches: Vec<_> = located
+            .iter()
+            .map(|f| (f.path.strip_prefix(&messy).unwrap(), f.matched))
+            .collect();
+        assert_eq!(
+            matches,
+            vec![
+                (Path::new("copy.nes"), LocateMatch::Exact),
+                (Path::new("sub/junk.nes"), LocateMatch::Unreadable),
+                (Path::new("sub/other.nes"), LocateMatch::Unknown),
+                (Path::new("sub/vertical.nes"), LocateMatch::HeaderDiffers),
+            ]
+        );
+        assert!(located[1].error.is_some());
+        assert!(manager.locations(&added.sha256).unwrap().is_empty());
+
+        // Recording remembers both files holding the node's ROM
+        manager
+            .locate(&messy, true, &mut NoProgress, &CancelToken::new())
+            .unwrap();
+        let locations = manager.locations(&added.sha256).unwrap();
+        assert_eq!(locations.len(), 2);
+        assert!(locations[0].path.ends_with("copy.nes") && locations[0].header_matches);
+        assert!(locations[1].path.ends_with("vertical.nes") && !locations[1].header_matches);
+        manager
+            .locate(&messy, true, &mut NoProgress, &CancelToken::new())
+            .unwrap();
+        assert_eq!(manager.locations(&added.sha256).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_node_bytes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let (rom_a, rom_b) = (
+            rom_file(temp_dir.path(), "a.nes", 0x11),
+            rom_file(temp_dir.path(), "b.nes", 0x22),
+        );
+        let hash_a = manager
+            .add_node(&rom_a, &NodeMetadata::default())
+            .unwrap()
+            .sha256;
+        let hash_b = manager
+            .add_node(&rom_b, &NodeMetadata::default())
+            .unwrap()
+            .sha256;
+        manager
+            .link_nodes(&rom_a, &rom_b, None, &mut NoProgress, &CancelToken::new())
+            .unwrap();
+        let node_bytes = |manager: &StorageManager, sha256, sources: &[PathBuf]| {
+            manager.node_bytes(sha256, sources, &mut NoProgress, &CancelToken::new())
+        };
+
+        // Read from a source holding the ROM, or built from a linked one
+        let data_b = read_rom_bytes(&rom_b).unwrap();
+        assert_eq!(
+            node_bytes(&manager, &hash_b, std::slice::from_ref(&rom_b)).unwrap(),
+            data_b
+        );
+        assert_eq!(
+            node_bytes(&manager, &hash_b, std::slice::from_ref(&rom_a)).unwrap(),
+            data_b
+        );
+        assert!(matches!(
+            node_bytes(&manager, &hash_b, &[]),
+            Err(DromosError::SourceNeeded { .. })
+        ));
+
+        // Or from where locate recorded a linked ROM
+        let found = temp_dir.path().join("found");
+        fs::create_dir(&found).unwrap();
+        fs::rename(&rom_a, found.join("a.nes")).unwrap();
+        manager
+            .locate(&found, true, &mut NoProgress, &CancelToken::new())
+            .unwrap();
+        assert_eq!(node_bytes(&manager, &hash_b, &[]).unwrap(), data_b);
+        assert_eq!(
+            node_bytes(&manager, &hash_a, &[]).unwrap(),
+            read_rom_bytes(&found.join("a.nes")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_build_all() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let mut add = |path: &Path, title: &str| {
+            let metadata = NodeMetadata {
+                title: title.to_string(),
+                ..Default::default()
+            };
+            manager.add_node(path, &metadata).unwrap().sha256
+        };
+        let rom_a = rom_file(temp_dir.path(), "a.nes", 0x11);
+        let rom_b = rom_file(temp_dir.path(), "b.nes", 0x22);
+        let rom_c = rom_file(temp_dir.path(), "c.nes", 0x33);
+        let hash_a = add(&rom_a, "Base");
+        let hash_b = add(&rom_b, "Hack: One");
+        add(&rom_c, "Hack Two");
+        for target in [&rom_b, &rom_c] {
+            manager
+                .link_nodes(&rom_a, target, None, &mut NoProgress, &CancelToken::new())
+                .unwrap();
+        }
+        // A damaged diff fails only the ROM built through it
+        let (_, edge) = manager
+            .get_neighbors(&hash_a)
+            .unwrap()
+            .into_iter()
+            .find(|(node, _)| node.sha256 == hash_b)
+            .unwrap();
+        fs::write(temp_dir.path().join("diffs").join(&edge.diff_path), b"bad").unwrap();
+
+        let rows: Vec<NodeRow> = manager
+            .connected_component_hashes(&hash_a)
+            .iter()
+            .map(|h| manager.get_node_row_by_hash(h).unwrap().unwrap())
+            .collect();
+        let output = temp_dir.path().join("out");
+        let builds = manager
+            .build_all(
+                &rows,
+                &output,
+                std::slice::from_ref(&rom_a),
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
+        assert_eq!(builds.len(), 3);
+        for build in &builds {
+            let path = output.join(&build.path);
+            if build.sha256 == hash_b {
+                assert!(build.error.is_some());
+                assert!(!path.exists());
+            } else {
+                assert_eq!(build.error, None);
+                assert_eq!(hash_rom_file(&path).unwrap().sha256, build.sha256);
+            }
+        }
+        let mut names: Vec<_> = builds.iter().map(|b| b.path.clone()).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                PathBuf::from("Base.nes"),
+                PathBuf::from("Hack - One.nes"),
+                PathBuf::from("Hack Two.nes")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_node_exists() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        let metadata = make_metadata(0xAA, "test.nes");
+
+        assert!(!manager.node_exists(&metadata.sha256));
+
+        manager
+            .add_node_from_metadata(&metadata, "Test ROM")
+            .unwrap();
+
+        assert!(manager.node_exists(&metadata.sha256));
+    }
+
+    #[test]
+    fn test_find_node_by_hash_prefix() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        let metadata = make_metadata(0xAB, "test.nes");
+        manager
+            .add_node_from_metadata(&metadata, "Test ROM")
+            .unwrap();
+
+        // Find by prefix "ab" (first byte is 0xAB)
+        let node = manager
+            .find_node_by_hash_prefix("ab")
+            .expect("Should find by prefix");
+        assert_eq!(node.title, "Test ROM");
+
+        // Should not find with wrong prefix
+        assert!(manager.find_node_by_hash_prefix("cd").is_none());
+    }
+
+    #[test]
+    fn test_find_nodes_by_ambiguous_hash_prefix() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        let mut meta_b = make_metadata(0xAB, "b.nes");
+        meta_b.sha256[1] = 0x01;
+        let mut meta_a = make_metadata(0xAB, "a.nes");
+        meta_a.sha256[1] = 0x02;
+        manager.add_node_from_metadata(&meta_b, "ROM B").unwrap();
+        manager.add_node_from_metadata(&meta_a, "ROM A").unwrap();
+
+        let titles: Vec<&str> = manager
+            .find_nodes_by_hash_prefix("AB")
+            .iter()
+            .map(|n| n.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["ROM A", "ROM B"]);
+
+        // An ambiguous prefix doesn't silently pick one
+        assert!(manager.find_node_by_hash_prefix("ab").is_none());
+        let node = manager.find_node_by_hash_prefix("ab02").unwrap();
+        assert_eq!(node.title, "ROM A");
+        assert!(manager.find_nodes_by_hash_prefix("cd").is_empty());
+    }
+
+    #[test]
+    fn test_link_count() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        let meta_a = make_metadata(0xAA, "a.nes");
+        let meta_b = make_metadata(0xBB, "b.nes");
+
+        manager.add_node_from_metadata(&meta_a, "ROM A").unwrap();
+        manager.add_node_from_metadata(&meta_b, "ROM B").unwrap();
+
+        // Initially no links
+        assert_eq!(manager.link_count(&meta_a.sha256), 0);
+
+        // Manually add edge to the graph (bypassing file creation)
+        let idx_a = manager.graph.get_node_by_hash(&meta_a.sha256).unwrap();
+        let idx_b = manager.graph.get_node_by_hash(&meta_b.sha256).unwrap();
+        manager.graph.add_edge(
+            idx_a,
+            idx_b,
+            DiffEdge {
+                db_id: 1,
+                diff_path: "a_to_b.bsdiff".to_string(),
+                diff_size: 100,
+                note: None,
+                output_size: None,
+            },
+        );
+
+        assert_eq!(manager.link_count(&meta_a.sha256), 1);
+        assert_eq!(manager.link_count(&meta_b.sha256), 0); // outgoing only
+    }
+
+    #[test]
+    fn test_get_neighbors() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        let meta_a = make_metadata(0xAA, "a.nes");
+        let meta_b = make_metadata(0xBB, "b.nes");
+        let meta_c = make_metadata(0xCC, "c.nes");
+
+        manager.add_node_from_metadata(&meta_a, "ROM A").unwrap();
+        manager.add_node_from_metadata(&meta_b, "ROM B").unwrap();
+        manager.add_node_from_metadata(&meta_c, "ROM C").unwrap();
+
+        let idx_a = manager.graph.get_node_by_hash(&meta_a.sha256).unwrap();
+        let idx_b = manager.graph.get_node_by_hash(&meta_b.sha256).unwrap();
+        let idx_c = manager.graph.get_node_by_hash(&meta_c.sha256).unwrap();
+
+        manager.graph.add_edge(
+            idx_a,
+            idx_b,
+            DiffEdge {
+                db_id: 1,
+                diff_path: "a_to_b.bsdiff".to_string(),
+                diff_size: 100,
+                note: None,
+                output_size: None,
+            },
+        );
+        manager.graph.add_edge(
+            idx_a,
+            idx_c,
+            DiffEdge {
+                db_id: 2,
+                diff_path: "a_to_c.bsdiff".to_string(),
+                diff_size: 200,
+                note: None,
+                output_size: None,
+            },
+        );
+
+        let neighbors = manager
+            .get_neighbors(&meta_a.sha256)
+            .expect("Should have neighbors");
+        assert_eq!(neighbors.len(), 2);
+
+        let titles: Vec<&str> = neighbors.iter().map(|(n, _)| n.title.as_str()).collect();
+        assert!(titles.contains(&"ROM B"));
+        assert!(titles.contains(&"ROM C"));
+    }
+
+    #[test]
+    fn test_list() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        let meta_a = make_metadata(0xAA, "a.nes");
+        let meta_b = make_metadata(0xBB, "b.nes");
+
+        manager.add_node_from_metadata(&meta_a, "ROM A").unwrap();
+        manager.add_node_from_metadata(&meta_b, "ROM B").unwrap();
+
+        let (nodes, edges) = manager.list();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(edges.len(), 0);
+    }
+
+    #[test]
+    fn test_find_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        let meta_a = make_metadata(0xAA, "a.nes");
+        let meta_b = make_metadata(0xBB, "b.nes");
+        let meta_c = make_metadata(0xCC, "c.nes");
+
+        manager.add_node_from_metadata(&meta_a, "ROM A").unwrap();
+        manager.add_node_from_metadata(&meta_b, "ROM B").unwrap();
+        manager.add_node_from_metadata(&meta_c, "ROM C").unwrap();
+
+        let idx_a = manager.graph.get_node_by_hash(&meta_a.sha256).unwrap();
+        let idx_b = manager.graph.get_node_by_hash(&meta_b.sha256).unwrap();
+        let idx_c = manager.graph.get_node_by_hash(&meta_c.sha256).unwrap();
+
+        manager.graph.add_edge(
+            idx_a,
+            idx_b,
+            DiffEdge {
+                db_id: 1,
+                diff_path: "a_to_b.bsdiff".to_string(),
+                diff_size: 100,
+                note: None,
+                output_size: None,
+            },
+        );
+        manager.graph.add_edge(
+            idx_b,
+            idx_c,
+            DiffEdge {
+                db_id: 2,
+                diff_path: "b_to_c.bsdiff".to_string(),
+                diff_size: 100,
+                note: None,
+                output_size: None,
+            },
+        );
+
+        let path = manager
+            .find_path(&meta_a.sha256, &meta_c.sha256)
+            .expect("Path should exist");
+        assert_eq!(path.len(), 3); // A -> B -> C
+    }
+
+    #[test]
+    fn test_update_node_metadata() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        let metadata = make_metadata(0xAA, "test.nes");
+        manager
+            .add_node_from_metadata(&metadata, "Original Title")
+            .unwrap();
+
+        // Update metadata
+        let updated = NodeMetadata {
+            title: "Updated Title".to_string(),
+            source_url: Some("https://example.com".to_string()),
+            version: Some("1.0".to_string()),
+            release_date: Some("2024-01-15".to_string()),
+            tags: vec!["action".to_string()],
+            description: Some("A description".to_string()),
+            ..Default::default()
+        };
+        manager
+            .update_node_metadata(&metadata.sha256, &updated)
+            .unwrap();
+
+        // Verify in-memory graph is updated
+        let node = manager
+            .get_node_by_hash(&metadata.sha256)
+            .expect("Node should exist");
+        assert_eq!(node.title, "Updated Title");
+
+        // Verify database is updated
+        let row = manager
+            .get_node_row_by_hash(&metadata.sha256)
+            .unwrap()
+            .expect("NodeRow should exist");
+        assert_eq!(row.title, "Updated Title");
+        assert_eq!(row.source_url, Some("https://example.com".to_string()));
+        assert_eq!(row.version, Some("1.0".to_string()));
+    }
+
+    #[test]
+    fn test_update_node_title_syncs_graph() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        let metadata = make_metadata(0xAA, "test.nes");
+        manager
+            .add_node_from_metadata(&metadata, "Original Title")
+            .unwrap();
+
+        // Update just the title
+        let updated = NodeMetadata {
+            title: "New Title".to_string(),
+            ..Default::default()
+        };
+        manager
+            .update_node_metadata(&metadata.sha256, &updated)
+            .unwrap();
+
+        // Verify in-memory graph node.title is also updated
+        let node = manager
+            .get_node_by_hash(&metadata.sha256)
+            .expect("Node should exist");
+        assert_eq!(node.title, "New Title");
+    }
+
+    #[test]
+    fn test_apply_csv_edits() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let (meta_a, meta_b) = (make_metadata(0xAA, "a.nes"), make_metadata(0xBB, "b.nes"));
+        manager.add_node_from_metadata(&meta_a, "A").unwrap();
+        manager.add_node_from_metadata(&meta_b, "B").unwrap();
+
+        let path = temp_dir.path().join("fixes.csv");
+        fs::write(
+            &path,
+            format!(
+                "hash,title,version,tags\n{},A Fixed,1.1,\"hack, fr\"\n{},B,,\n",
+                format_hash(&meta_a.sha256),
+                format_hash(&meta_b.sha256)
+            ),
+        )
+        .unwrap();
+        let edits = manager.plan_csv_edits(&path).unwrap();
+        assert_eq!(edits.changes.len(), 1);
+        assert_eq!(edits.unchanged, 1);
+        // Planning changes nothing
+        assert_eq!(manager.get_node_by_hash(&meta_a.sha256).unwrap().title, "A");
+
+        manager.apply_metadata_changes(&edits.changes).unwrap();
+        assert_eq!(
+            manager.get_node_by_hash(&meta_a.sha256).unwrap().title,
+            "A Fixed"
+        );
+        let row = manager
+            .get_node_row_by_hash(&meta_a.sha256)
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.version.as_deref(), Some("1.1"));
+        assert_eq!(row.tags, vec!["hack", "fr"]);
+
+        fs::write(&path, "version\n1.0\n").unwrap();
+        assert!(matches!(
+            manager.plan_csv_edits(&path),
+            Err(DromosError::InvalidCsv { .. })
+        ));
+    }
+
+    #[test]
+    fn test_status() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        let meta_a = make_metadata(0xAA, "a.nes");
+        let meta_b = make_metadata(0xBB, "b.nes");
+        let meta_c = make_metadata(0xCC, "c.nes");
+        manager.add_node_from_metadata(&meta_a, "ROM A").unwrap();
+        manager.add_node_from_metadata(&meta_b, "ROM B").unwrap();
+        manager.add_node_from_metadata(&meta_c, "ROM C").unwrap();
+
+        let idx_a = manager.graph.get_node_by_hash(&meta_a.sha256).unwrap();
+        let idx_b = manager.graph.get_node_by_hash(&meta_b.sha256).unwrap();
+        fs::write(manager.config.diffs_dir.join("a_to_b.bsdiff"), b"diff").unwrap();
+        manager.graph.add_edge(
+            idx_a,
+            idx_b,
+            DiffEdge {
+                db_id: 1,
+                diff_path: "a_to_b.bsdiff".to_string(),
+                diff_size: 100,
+                note: None,
+                output_size: None,
+            },
+        );
+        manager.graph.add_edge(
+            idx_b,
+            idx_a,
+            DiffEdge {
+                db_id: 2,
+                diff_path: "b_to_a.bsdiff".to_string(),
+                diff_size: 50,
+                note: None,
+                output_size: None,
+            },
+        );
+
+        let status = manager.status(2).unwrap();
+        assert_eq!(status.node_count, 3);
+        assert_eq!(status.diff_count, 2);
+        assert_eq!(status.total_diff_bytes, 150);
+        assert_eq!(status.recent.len(), 2);
+        assert_eq!(status.recent[0].title, "ROM C");
+        let unlinked: Vec<&str> = status.unlinked.iter().map(|n| n.title.as_str()).collect();
+        assert_eq!(unlinked, vec!["ROM C"]);
+        assert_eq!(status.missing_diffs, vec!["b_to_a.bsdiff".to_string()]);
+        assert_eq!(status.last_export_at, None);
+        assert_eq!(status.last_import_at, None);
+    }
+
+    #[test]
+    fn test_find_nodes_by_title() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        manager
+            .add_node_from_metadata(&make_metadata(0xAA, "a.nes"), "Mario")
+            .unwrap();
+        manager
+            .add_node_from_metadata(&make_metadata(0xBB, "b.nes"), "Mario Hack")
+            .unwrap();
+
+        // Exact match wins over substring matches
+        let found = manager.find_nodes_by_title("mario");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].title, "Mario");
+
+        assert_eq!(manager.find_nodes_by_title("hack").len(), 1);
+        assert_eq!(manager.find_nodes_by_title("ar").len(), 2);
+        assert!(manager.find_nodes_by_title("zelda").is_empty());
+    }
+
+    #[test]
+    fn test_search_regex() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+
+        manager
+            .add_node_from_metadata(&make_metadata(0xAA, "smb (USA).nes"), "Mario")
+            .unwrap();
+        manager
+            .add_node_from_metadata(&make_metadata(0xBB, "zelda (J).nes"), "Zelda")
+            .unwrap();
+
+        let titles = |pattern: &str| -> Vec<String> {
+            let pattern = Regex::new(pattern).unwrap();
+            manager
+                .search_regex(&pattern)
+                .unwrap()
+                .into_iter()
+                .map(|n| n.title)
+                .collect()
+        };
+        assert_eq!(titles("^Ma"), vec!["Mario"]);
+        // Filenames are searched too
+        assert_eq!(titles(r"\(J\)"), vec!["Zelda"]);
+        assert_eq!(titles("o$"), vec!["Mario"]);
+        assert_eq!(titles("."), vec!["Mario", "Zelda"]);
+    }
+
+    #[test]
+    fn test_import_dat_verifies_matching_nodes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        manager
+            .add_node_from_metadata(&make_metadata(0xAA, "smb.nes"), "Mario")
+            .unwrap();
+        let mut zelda = make_metadata(0xBB, "zelda.nes");
+        zelda.sha1 = Some([0xBB; 20]);
+        manager.add_node_from_metadata(&zelda, "Zelda").unwrap();
+        manager
+            .add_node_from_metadata(&make_metadata(0xCC, "hack.nes"), "Hack")
+            .unwrap();
+
+        let dat_path = temp_dir.path().join("nes.dat");
+        let write_dat = |games: &str| {
+            let dat = format!(
+                "<datafile><header><name>NES</name><version>1</version></header>{}</datafile>",
+                games
+            );
+            fs::write(&dat_path, dat).unwrap();
+        };
+        write_dat(&format!(
+            "<game name='Super Mario Bros. (World)'><rom name='smb.nes' sha256='{}'/></game>\
+             <game name='Zelda (USA)'><rom name='zelda.nes' sha1='{}'/></game>",
+            format_hash(&make_metadata(0xAA, "").sha256),
+            hex::encode([0xBB; 20]),
+        ));
+
+        let import = manager.import_dat(&dat_path).unwrap();
+        assert_eq!(import.dat_name, "NES");
+        assert_eq!((import.entries, import.verified), (2, 2));
+        let mario = manager
+            .dat_match(&make_metadata(0xAA, "").sha256)
+            .unwrap()
+            .unwrap();
+        assert_eq!(mario.game, "Super Mario Bros. (World)");
+        assert_eq!(mario.rom_name, "smb.nes");
+        assert!(
+            manager
+                .dat_match(&make_metadata(0xCC, "").sha256)
+                .unwrap()
+                .is_none()
+        );
+
+        // Importing a DAT with the same name replaces its entries
+        write_dat("<game name='Other'><rom name='o.nes' crc='00000000'/></game>");
+        let import = manager.import_dat(&dat_path).unwrap();
+        assert_eq!((import.entries, import.verified), (1, 0));
+        assert!(manager.dat_matches().unwrap().is_empty());
+
+        write_dat("<game name='Broken'>");
+        assert!(matches!(
+            manager.import_dat(&dat_path),
+            Err(DromosError::InvalidDat { .. })
+        ));
+    }
+
+    #[test]
+    fn test_plan_dat() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let meta_a = make_metadata(0xAA, "a.nes");
+        let meta_b = make_metadata(0xBB, "b.nes");
+        let mut meta_c = make_metadata(0xCC, "c.nes");
+        meta_c.sha1 = Some([0xCC; 20]);
+        meta_c.crc32 = Some(0xCCCC);
+        manager.add_node_from_metadata(&meta_a, "Mario").unwrap();
+        manager.add_node_from_metadata(&meta_b, "Mario").unwrap();
+        manager
+            .add_node_from_metadata(&meta_c, "Zelda: Link")
+            .unwrap();
+        let idx_a = manager.graph.get_node_by_hash(&meta_a.sha256).unwrap();
+        let idx_b = manager.graph.get_node_by_hash(&meta_b.sha256).unwrap();
+        manager.graph.add_edge(
+            idx_a,
+            idx_b,
+            DiffEdge {
+                db_id: 1,
+                diff_path: "a_to_b.bsdiff".to_string(),
+                diff_size: 100,
+                note: None,
+                output_size: None,
+            },
+        );
+
+        let games = |dat: &dat::DatFile| -> Vec<String> {
+            dat.entries.iter().map(|e| e.game.clone()).collect()
+        };
+        let dat = manager
+            .plan_dat("Library", None, &NodeFilter::default())
+            .unwrap();
+        assert_eq!(dat.header_skipper.as_deref(), Some(dat::NES_HEADER_SKIPPER));
+        assert_eq!(
+            games(&dat),
+            vec!["Mario [aa000000]", "Mario [bb000000]", "Zelda - Link"]
+        );
+        let zelda = &dat.entries[2];
+        assert_eq!(zelda.rom_name, "Zelda - Link.nes");
+        assert_eq!(zelda.size, Some(40 * 1024));
+        assert_eq!((zelda.sha1, zelda.crc32), (meta_c.sha1, meta_c.crc32));
+        assert_eq!(zelda.sha256, Some(meta_c.sha256));
+
+        let component = manager
+            .plan_dat("Library", Some(&meta_b.sha256), &NodeFilter::default())
+            .unwrap();
+        assert_eq!(component.entries.len(), 2);
+        let filtered = manager
+            .plan_dat("Library", None, &NodeFilter::parse("zelda").unwrap())
+            .unwrap();
+        assert_eq!(games(&filtered), vec!["Zelda - Link"]);
+    }
+
+    #[test]
+    fn test_export_keeps_existing_files_unless_chosen() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        manager
+            .add_node_from_metadata(&make_metadata(0xAA, "a.nes"), "A")
+            .unwrap();
+
+        let output = temp_dir.path().join("export");
+        let plan = manager.plan_export(None).unwrap();
+        assert!(plan.existing_files(&output).is_empty());
+        let mut written = Vec::new();
+        let mut record = |p: &Progress| written.push((p.stage, p.item.to_string(), p.current));
+        let stats = manager
+            .export(
+                &output,
+                &plan,
+                &HashSet::new(),
+                &mut record,
+                &CancelToken::new(),
+            )
+            .unwrap();
+        assert_eq!((stats.nodes, stats.skipped), (1, 0));
+        let index_name = output.join("index.json").display().to_string();
+        assert_eq!(written, vec![(Stage::Writing, index_name, 1)]);
+
+        // A second export finds index.json and leaves it alone unless chosen
+        let index = output.join("index.json");
+        fs::write(&index, "old").unwrap();
+        assert_eq!(plan.existing_files(&output), vec![index.clone()]);
+        let stats = manager
+            .export(
+                &output,
+                &plan,
+                &HashSet::new(),
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(fs::read_to_string(&index).unwrap(), "old");
+
+        let overwrite = HashSet::from([index.clone()]);
+        manager
+            .export(
+                &output,
+                &plan,
+                &overwrite,
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
+        assert_ne!(fs::read_to_string(&index).unwrap(), "old");
+    }
+
+    #[test]
+    fn test_collections() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let (meta_a, meta_b) = (make_metadata(0xAA, "a.nes"), make_metadata(0xBB, "b.nes"));
+        manager.add_node_from_metadata(&meta_a, "A").unwrap();
+        manager.add_node_from_metadata(&meta_b, "B").unwrap();
+
+        manager.create_collection("Faves").unwrap();
+        assert!(matches!(
+            manager.create_collection("faves"),
+            Err(DromosError::CollectionExists { .. })
+        ));
+        let added = manager
+            .add_to_collection("faves", &[meta_a.sha256, meta_b.sha256])
+            .unwrap();
+        assert_eq!(added.len(), 2);
+        let added = manager
+            .add_to_collection("Faves", &[meta_a.sha256])
+            .unwrap();
+        assert!(added.is_empty());
+        assert!(matches!(
+            manager.add_to_collection("Faves", &[[0xCC; 32]]),
+            Err(DromosError::RomNotFound { .. })
+        ));
+        let removed = manager
+            .remove_from_collection("Faves", &[meta_b.sha256])
+            .unwrap();
+        assert_eq!(removed, vec![meta_b.sha256]);
+        assert_eq!(manager.collection("FAVES").unwrap().nodes, 1);
+        assert_eq!(
+            manager.node_collections(&meta_a.sha256).unwrap(),
+            vec!["Faves"]
+        );
+
+        let filter = NodeFilter::parse("collection:faves").unwrap();
+        let plan = manager.plan_filtered_export(None, &filter).unwrap();
+        assert_eq!(plan.manifest.files.len(), 1);
+        assert_eq!(plan.manifest.files[0].title, "A");
+
+        manager.delete_collection("faves").unwrap();
+        assert!(matches!(
+            manager.search(&filter),
+            Err(DromosError::CollectionNotFound { .. })
+        ));
+        assert_eq!(manager.search(&NodeFilter::default()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_attachments_round_trip_through_export() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let (meta_a, meta_b) = (make_metadata(0xAA, "a.nes"), make_metadata(0xBB, "b.nes"));
+        manager.add_node_from_metadata(&meta_a, "A").unwrap();
+        manager.add_node_from_metadata(&meta_b, "B").unwrap();
+        let readme = temp_dir.path().join("README.txt");
+        fs::write(&readme, "hack notes").unwrap();
+
+        let attached = manager.attach_file(&readme, &meta_a.sha256, None).unwrap();
+        assert_eq!(attached.name, "README.txt");
+        assert_eq!(attached.size, 10);
+        assert_eq!(
+            fs::read(manager.attachment_path(&attached)).unwrap(),
+            b"hack notes"
+        );
+        assert!(matches!(
+            manager.attach_file(&readme, &meta_a.sha256, Some(&meta_b.sha256)),
+            Err(DromosError::NotLinked { .. })
+        ));
+        let idx_a = manager.graph.get_node_by_hash(&meta_a.sha256).unwrap();
+        let idx_b = manager.graph.get_node_by_hash(&meta_b.sha256).unwrap();
+        manager.graph.add_edge(
+            idx_b,
+            idx_a,
+            DiffEdge {
+                db_id: 1,
+                diff_path: "b_to_a.bsdiff".to_string(),
+                diff_size: 100,
+                note: None,
+                output_size: None,
+            },
+        );
+        manager
+            .attach_file(&readme, &meta_b.sha256, Some(&meta_a.sha256))
+            .unwrap();
+        assert_eq!(manager.attachments(&meta_a.sha256).unwrap().len(), 2);
+
+        // Another library gets both attachments and one copy of the contents
+        let output = temp_dir.path().join("export");
+        let plan = manager.plan_export(None).unwrap();
+        manager
+            .export(
+                &output,
+                &plan,
+                &HashSet::new(),
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
+        let other_dir = tempfile::tempdir().unwrap();
+        let mut other = in_memory_manager(other_dir.path());
+        let (manifest, _) = other.analyze_import(&output).unwrap();
+        let result = other
+            .execute_import(
+                &output,
+                &manifest,
+                &HashMap::new(),
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
+        assert_eq!(result.attachments_added, 2);
+        let imported = other.attachments(&meta_b.sha256).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert!(imported[0].is_on(&meta_a.sha256, Some(&meta_b.sha256)));
+        assert_eq!(
+            fs::read(other.attachment_path(&imported[0])).unwrap(),
+            b"hack notes"
+        );
+
+        // Contents are deleted once nothing uses them
+        let path = manager.attachment_path(&attached);
+        manager
+            .detach_file(&meta_a.sha256, None, "README.txt")
+            .unwrap();
+        assert!(path.exists());
+        assert!(matches!(
+            manager.detach_file(&meta_a.sha256, None, "README.txt"),
+            Err(DromosError::AttachmentNotFound { .. })
+        ));
+        let removed = manager.remove_node(&meta_b.sha256).unwrap();
+        assert_eq!(removed.attachments_removed, 1);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_link_note() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let mut manager = StorageManager::new_in_memory(temp_dir.path()).unwrap();
-
-        let metadata = make_metadata(0xAA, "test.nes");
+        let mut manager = in_memory_manager(temp_dir.path());
+        let (rom_a, rom_b) = (
+            rom_file(temp_dir.path(), "a.nes", 0x11),
+            rom_file(temp_dir.path(), "b.nes", 0x22),
+        );
+        let hash_a = manager
+            .add_node(&rom_a, &NodeMetadata::default())
+            .unwrap()
+            .sha256;
+        let hash_b = manager
+            .add_node(&rom_b, &NodeMetadata::default())
+            .unwrap()
+            .sha256;
+        let note = "retranslation, fixes item menu bug";
         manager
-            .add_node_from_metadata(&metadata, "Test ROM")
+            .link_nodes(
+                &rom_a,
+                &rom_b,
+                Some(note),
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
             .unwrap();
 
-        let node = manager
-            .get_node_by_hash(&metadata.sha256)
-            .expect("Node should exist");
-        assert_eq!(node.title, "Test ROM");
-        assert_eq!(node.sha256[0], 0xAA);
+        // Both directions carry the note, in the graph and in the database
+        for hash in [hash_a, hash_b] {
+            let neighbors = manager.get_neighbors(&hash).unwrap();
+            assert_eq!(neighbors[0].1.note.as_deref(), Some(note));
+        }
+        manager.graph = RomGraph::new();
+        manager.load_graph_from_db().unwrap();
+        assert_eq!(
+            manager.get_neighbors(&hash_a).unwrap()[0].1.note.as_deref(),
+            Some(note)
+        );
+
+        // And exports keep it
+        let plan = manager.plan_export(None).unwrap();
+        assert!(
+            plan.manifest
+                .diffs
+                .iter()
+                .all(|d| d.note.as_deref() == Some(note))
+        );
     }
 
     #[test]
-    fn test_node_exists() {
+    fn test_merge_nodes() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let mut manager = StorageManager::new_in_memory(temp_dir.path()).unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let roms = [
+            rom_file(temp_dir.path(), "keep.nes", 0x11),
+            rom_file(temp_dir.path(), "dup.nes", 0x22),
+            rom_file(temp_dir.path(), "hack.nes", 0x33),
+        ];
+        let mut hashes = Vec::new();
+        for (rom, tags) in roms.iter().zip([vec![], vec!["hack".to_string()], vec![]]) {
+            let metadata = NodeMetadata {
+                title: "Zelda".to_string(),
+                tags,
+                ..Default::default()
+            };
+            hashes.push(manager.add_node(rom, &metadata).unwrap().sha256);
+        }
+        let (keep, dup, hack) = (hashes[0], hashes[1], hashes[2]);
+        let progress = &mut NoProgress;
+        let cancel = &CancelToken::new();
+        manager
+            .link_nodes(&roms[0], &roms[1], None, progress, cancel)
+            .unwrap();
+        manager
+            .link_nodes(&roms[1], &roms[2], Some("hack"), progress, cancel)
+            .unwrap();
+        manager.create_collection("Favorites").unwrap();
+        manager.add_to_collection("Favorites", &[dup]).unwrap();
+        manager.set_favorite(&dup, true).unwrap();
+        manager.set_favorite(&hack, true).unwrap();
+        manager.set_favorite(&hack, false).unwrap();
+        assert_eq!(manager.favorites().unwrap(), HashSet::from([dup]));
+        assert_eq!(manager.merge_relinks(&keep, &dup), vec![hack]);
 
-        let metadata = make_metadata(0xAA, "test.nes");
+        Repository::new(&manager.conn)
+            .record_location(&dup, "/roms/dup.nes", true)
+            .unwrap();
 
-        assert!(!manager.node_exists(&metadata.sha256));
+        // The hack can't be relinked without a ROM to build from
+        assert!(matches!(
+            manager.merge_nodes(&keep, &dup, &[], progress, cancel),
+            Err(DromosError::SourceNeeded { .. })
+        ));
+        assert!(manager.node_exists(&dup));
+        assert!(matches!(
+            manager.merge_nodes(&keep, &keep, &[], progress, cancel),
+            Err(DromosError::MergeIntoItself { .. })
+        ));
 
-        manager
-            .add_node_from_metadata(&metadata, "Test ROM")
+        // Cancelling once the new link's diffs are underway changes nothing
+        let diffs = || fs::read_dir(temp_dir.path().join("diffs")).unwrap().count();
+        let before = (manager.counts(), diffs());
+        let cancelled = CancelToken::new();
+        let token = cancelled.clone();
+        let mut cancel_at_diff = |p: &Progress| {
+            if p.stage == Stage::Diffing && p.current == 2 {
+                token.cancel();
+            }
+        };
+        assert!(matches!(
+            manager.merge_nodes(
+                &keep,
+                &dup,
+                &[roms[2].clone()],
+                &mut cancel_at_diff,
+                &cancelled
+            ),
+            Err(DromosError::Cancelled)
+        ));
+        assert!(manager.node_exists(&dup));
+        assert_eq!((manager.counts(), diffs()), before);
+        assert_eq!(manager.get_neighbors(&keep).unwrap()[0].0.sha256, dup);
+
+        let result = manager
+            .merge_nodes(&keep, &dup, &[roms[2].clone()], progress, cancel)
             .unwrap();
+        assert_eq!(result.relinked, vec![hack]);
+        assert_eq!(result.collections, vec!["Favorites"]);
+        assert_eq!(result.metadata.tags, vec!["hack"]);
+        assert!(!manager.node_exists(&dup));
+        let neighbors = manager.get_neighbors(&keep).unwrap();
+        assert_eq!(neighbors.len(), 1);
+        assert_eq!(neighbors[0].0.sha256, hack);
+        assert_eq!(neighbors[0].1.note.as_deref(), Some("hack"));
+        assert_eq!(manager.node_collections(&keep).unwrap(), vec!["Favorites"]);
+        assert_eq!(manager.favorites().unwrap(), HashSet::from([keep]));
+        assert_eq!(
+            manager.filename_aliases(&keep).unwrap(),
+            vec!["keep.nes", "dup.nes"]
+        );
+        let locations = manager.locations(&keep).unwrap();
+        assert_eq!(locations[0].path, "/roms/dup.nes");
 
-        assert!(manager.node_exists(&metadata.sha256));
+        // The new link builds the hack from the kept ROM
+        let built = manager
+            .build_rom(&roms[0], &hack, progress, cancel)
+            .unwrap();
+        assert_eq!(built.bytes, read_rom_bytes(&roms[2]).unwrap());
     }
 
     #[test]
-    fn test_find_node_by_hash_prefix() {
+    fn test_cancelled_export_and_link_leave_nothing_behind() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let mut manager = StorageManager::new_in_memory(temp_dir.path()).unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        manager
+            .add_node_from_metadata(&make_metadata(0xAA, "a.nes"), "A")
+            .unwrap();
 
-        let metadata = make_metadata(0xAB, "test.nes");
+        // Two linked ROMs export index.json and two diffs
+        let (rom_a, rom_b) = (
+            rom_file(temp_dir.path(), "a.nes", 0x11),
+            rom_file(temp_dir.path(), "b.nes", 0x22),
+        );
+        manager.add_node(&rom_a, &NodeMetadata::default()).unwrap();
+        manager.add_node(&rom_b, &NodeMetadata::default()).unwrap();
         manager
-            .add_node_from_metadata(&metadata, "Test ROM")
+            .link_nodes(&rom_a, &rom_b, None, &mut NoProgress, &CancelToken::new())
             .unwrap();
 
-        // Find by prefix "ab" (first byte is 0xAB)
-        let node = manager
-            .find_node_by_hash_prefix("ab")
-            .expect("Should find by prefix");
-        assert_eq!(node.title, "Test ROM");
+        // Cancel while the first diff is written, as another thread would
+        let output = temp_dir.path().join("export");
+        let plan = manager.plan_export(None).unwrap();
+        let cancel = CancelToken::new();
+        let token = cancel.clone();
+        let mut cancel_at_diff = |p: &Progress| {
+            if p.current == 2 {
+                token.cancel();
+            }
+        };
+        let result = manager.export(
+            &output,
+            &plan,
+            &HashSet::new(),
+            &mut cancel_at_diff,
+            &cancel,
+        );
+        assert!(matches!(result, Err(DromosError::Cancelled)));
+        assert!(!output.join("index.json").exists());
+        assert_eq!(fs::read_dir(output.join("diffs")).unwrap().count(), 0);
 
-        // Should not find with wrong prefix
-        assert!(manager.find_node_by_hash_prefix("cd").is_none());
+        // A cancelled link leaves no diffs or edges behind
+        let rom_c = rom_file(temp_dir.path(), "c.nes", 0x33);
+        manager.add_node(&rom_c, &NodeMetadata::default()).unwrap();
+        let result = manager.link_nodes(&rom_a, &rom_c, None, &mut NoProgress, &cancel);
+        assert!(matches!(result, Err(DromosError::Cancelled)));
+        assert_eq!(manager.counts().1, 2);
+        assert_eq!(
+            fs::read_dir(temp_dir.path().join("diffs")).unwrap().count(),
+            2
+        );
     }
 
     #[test]
-    fn test_link_count() {
+    fn test_import_copies_diffs_in_parallel() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let mut manager = StorageManager::new_in_memory(temp_dir.path()).unwrap();
-
-        let meta_a = make_metadata(0xAA, "a.nes");
-        let meta_b = make_metadata(0xBB, "b.nes");
-
-        manager.add_node_from_metadata(&meta_a, "ROM A").unwrap();
-        manager.add_node_from_metadata(&meta_b, "ROM B").unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let base = rom_file(temp_dir.path(), "base.nes", 0x10);
+        manager.add_node(&base, &NodeMetadata::default()).unwrap();
+        for i in 1..=4u8 {
+            let hack = rom_file(temp_dir.path(), &format!("hack{i}.nes"), 0x10 + i);
+            manager.add_node(&hack, &NodeMetadata::default()).unwrap();
+            manager
+                .link_nodes(&base, &hack, None, &mut NoProgress, &CancelToken::new())
+                .unwrap();
+        }
+        let output = temp_dir.path().join("export");
+        let plan = manager.plan_export(None).unwrap();
+        manager
+            .export(
+                &output,
+                &plan,
+                &HashSet::new(),
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
 
-        // Initially no links
-        assert_eq!(manager.link_count(&meta_a.sha256), 0);
+        // Every diff is copied, each reported once
+        let other_dir = tempfile::tempdir().unwrap();
+        let mut other = in_memory_manager(other_dir.path());
+        let (manifest, _) = other.analyze_import(&output).unwrap();
+        let mut reported = Vec::new();
+        let mut record = |p: &Progress| reported.push(p.item.to_string());
+        let result = other
+            .execute_import(
+                &output,
+                &manifest,
+                &HashMap::new(),
+                &mut record,
+                &CancelToken::new(),
+            )
+            .unwrap();
+        assert_eq!(result.diffs_copied, 8);
+        reported.retain(|item| item.ends_with(".bsdiff"));
+        reported.sort();
+        reported.dedup();
+        assert_eq!(reported.len(), 8);
+        for edge in &manifest.diffs {
+            assert_eq!(
+                fs::read(other_dir.path().join("diffs").join(&edge.diff_path)).unwrap(),
+                fs::read(output.join("diffs").join(&edge.diff_path)).unwrap()
+            );
+        }
 
-        // Manually add edge to the graph (bypassing file creation)
-        let idx_a = manager.graph.get_node_by_hash(&meta_a.sha256).unwrap();
-        let idx_b = manager.graph.get_node_by_hash(&meta_b.sha256).unwrap();
-        manager.graph.add_edge(
-            idx_a,
-            idx_b,
-            DiffEdge {
-                db_id: 1,
-                diff_path: "a_to_b.bsdiff".to_string(),
-                diff_size: 100,
-            },
+        // One damaged diff fails the import and leaves no files or nodes behind
+        let damaged = output.join("diffs").join(&manifest.diffs[5].diff_path);
+        fs::write(&damaged, b"damaged").unwrap();
+        let third_dir = tempfile::tempdir().unwrap();
+        let mut third = in_memory_manager(third_dir.path());
+        let result = third.execute_import(
+            &output,
+            &manifest,
+            &HashMap::new(),
+            &mut NoProgress,
+            &CancelToken::new(),
+        );
+        assert!(matches!(
+            result,
+            Err(DromosError::DiffChecksumMismatch { .. })
+        ));
+        assert_eq!(third.counts(), (0, 0));
+        assert_eq!(
+            fs::read_dir(third_dir.path().join("diffs"))
+                .unwrap()
+                .count(),
+            0
         );
-
-        assert_eq!(manager.link_count(&meta_a.sha256), 1);
-        assert_eq!(manager.link_count(&meta_b.sha256), 0); // outgoing only
     }
 
     #[test]
-    fn test_get_neighbors() {
+    fn test_metadata_saved_before_checks() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let mut manager = StorageManager::new_in_memory(temp_dir.path()).unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let mut paths = Vec::new();
+        for (name, fill) in [("a.nes", 0x10), ("b.nes", 0x20)] {
+            let path = rom_file(temp_dir.path(), name, fill);
+            manager.add_node(&path, &NodeMetadata::default()).unwrap();
+            paths.push(path);
+        }
+        let legacy = hash_rom_file(&paths[0]).unwrap().sha256;
+        // A tag the old comma-separated prompt accepted
+        let stored = NodeMetadata {
+            title: "Legacy".to_string(),
+            tags: vec!["fan translation".to_string()],
+            ..Default::default()
+        };
+        let id = manager.node_db_id(&legacy).unwrap();
+        Repository::new(&manager.conn)
+            .update_node_metadata(id, &stored)
+            .unwrap();
 
-        let meta_a = make_metadata(0xAA, "a.nes");
-        let meta_b = make_metadata(0xBB, "b.nes");
-        let meta_c = make_metadata(0xCC, "c.nes");
+        // Edits that leave the tag alone still save
+        let mut edited = stored.clone();
+        edited.rating = Some(4);
+        manager.update_node_metadata(&legacy, &edited).unwrap();
+        edited.tags.push("two words".to_string());
+        assert!(matches!(
+            manager.update_node_metadata(&legacy, &edited),
+            Err(DromosError::InvalidMetadata(_))
+        ));
 
-        manager.add_node_from_metadata(&meta_a, "ROM A").unwrap();
-        manager.add_node_from_metadata(&meta_b, "ROM B").unwrap();
-        manager.add_node_from_metadata(&meta_c, "ROM C").unwrap();
+        // Importing it elsewhere reports the node instead of failing
+        let output = temp_dir.path().join("export");
+        let plan = manager.plan_export(None).unwrap();
+        manager
+            .export(
+                &output,
+                &plan,
+                &HashSet::new(),
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
+        let other_dir = tempfile::tempdir().unwrap();
+        let mut other = in_memory_manager(other_dir.path());
+        let (manifest, _) = other.analyze_import(&output).unwrap();
+        let result = other
+            .execute_import(
+                &output,
+                &manifest,
+                &HashMap::new(),
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
+        assert_eq!(result.nodes_added, 1);
+        assert_eq!(result.rejected.len(), 1);
+        assert_eq!(result.rejected[0].sha256, legacy);
+        assert!(result.rejected[0].reason.contains("fan translation"));
+    }
 
-        let idx_a = manager.graph.get_node_by_hash(&meta_a.sha256).unwrap();
-        let idx_b = manager.graph.get_node_by_hash(&meta_b.sha256).unwrap();
-        let idx_c = manager.graph.get_node_by_hash(&meta_c.sha256).unwrap();
+    #[test]
+    fn test_export_sd_card() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let roms = [
+            rom_file(temp_dir.path(), "a.nes", 0x11),
+            rom_file(temp_dir.path(), "b.nes", 0x22),
+            rom_file(temp_dir.path(), "c.nes", 0x33),
+        ];
+        for (rom, title) in roms.iter().zip(["Alpha", "Beta", "Gamma"]) {
+            let metadata = NodeMetadata {
+                title: title.to_string(),
+                ..Default::default()
+            };
+            manager.add_node(rom, &metadata).unwrap();
+        }
+        manager
+            .link_nodes(
+                &roms[0],
+                &roms[1],
+                None,
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
 
-        manager.graph.add_edge(
-            idx_a,
-            idx_b,
-            DiffEdge {
-                db_id: 1,
-                diff_path: "a_to_b.bsdiff".to_string(),
-                diff_size: 100,
-            },
+        // Beta is built from Alpha; Gamma isn't linked to the source
+        let card = temp_dir.path().join("card");
+        let result = manager
+            .export_sd_card(
+                &card,
+                &roms[..1],
+                &NodeFilter::default(),
+                SdLayout::Letter,
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
+        assert_eq!(
+            result.written,
+            vec![PathBuf::from("A/Alpha.nes"), PathBuf::from("B/Beta.nes")]
         );
-        manager.graph.add_edge(
-            idx_a,
-            idx_c,
-            DiffEdge {
-                db_id: 2,
-                diff_path: "a_to_c.bsdiff".to_string(),
-                diff_size: 200,
-            },
+        assert_eq!(result.unreachable.len(), 1);
+        assert_eq!(result.unreachable[0].title, "Gamma");
+        assert_eq!(
+            fs::read(card.join("B/Beta.nes")).unwrap(),
+            fs::read(&roms[1]).unwrap()
         );
 
-        let neighbors = manager
-            .get_neighbors(&meta_a.sha256)
-            .expect("Should have neighbors");
-        assert_eq!(neighbors.len(), 2);
+        // Cancelling removes the files it created
+        let fresh = temp_dir.path().join("fresh");
+        let cancel = CancelToken::new();
+        let token = cancel.clone();
+        let mut cancel_at_second = |p: &Progress| {
+            if p.stage == Stage::Writing && p.current == 2 {
+                token.cancel();
+            }
+        };
+        let result = manager.export_sd_card(
+            &fresh,
+            &roms[..1],
+            &NodeFilter::default(),
+            SdLayout::Flat,
+            &mut cancel_at_second,
+            &cancel,
+        );
+        assert!(matches!(result, Err(DromosError::Cancelled)));
+        assert_eq!(fs::read_dir(&fresh).unwrap().count(), 0);
 
-        let titles: Vec<&str> = neighbors.iter().map(|(n, _)| n.title.as_str()).collect();
-        assert!(titles.contains(&"ROM B"));
-        assert!(titles.contains(&"ROM C"));
+        // Sources must be in the library
+        let stranger = rom_file(temp_dir.path(), "d.nes", 0x44);
+        let result = manager.export_sd_card(
+            &card,
+            &[stranger],
+            &NodeFilter::default(),
+            SdLayout::Flat,
+            &mut NoProgress,
+            &CancelToken::new(),
+        );
+        assert!(matches!(result, Err(DromosError::RomNotFound { .. })));
     }
 
     #[test]
-    fn test_list() {
+    fn test_validate_all() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let mut manager = StorageManager::new_in_memory(temp_dir.path()).unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let roms = [
+            rom_file(temp_dir.path(), "a.nes", 0x11),
+            rom_file(temp_dir.path(), "b.nes", 0x22),
+            rom_file(temp_dir.path(), "c.nes", 0x33),
+            rom_file(temp_dir.path(), "d.nes", 0x44),
+        ];
+        let mut hashes = Vec::new();
+        for (rom, title) in roms.iter().zip(["Alpha", "Beta", "Gamma", "Delta"]) {
+            let metadata = NodeMetadata {
+                title: title.to_string(),
+                ..Default::default()
+            };
+            hashes.push(manager.add_node(rom, &metadata).unwrap().sha256);
+        }
+        for (a, b) in [(0, 1), (1, 2)] {
+            manager
+                .link_nodes(
+                    &roms[a],
+                    &roms[b],
+                    None,
+                    &mut NoProgress,
+                    &CancelToken::new(),
+                )
+                .unwrap();
+        }
 
-        let meta_a = make_metadata(0xAA, "a.nes");
-        let meta_b = make_metadata(0xBB, "b.nes");
+        // Alpha reaches Beta and Gamma; Delta isn't linked
+        let result = manager
+            .validate_all(&roms[..1], &mut NoProgress, &CancelToken::new())
+            .unwrap();
+        assert_eq!(result.verified, hashes[..3]);
+        assert_eq!(result.edges_checked, 4);
+        assert!(result.broken.is_empty());
+        assert!(result.unbuilt.is_empty());
+        assert_eq!(result.unreachable.len(), 1);
+        assert_eq!(result.unreachable[0].title, "Delta");
 
-        manager.add_node_from_metadata(&meta_a, "ROM A").unwrap();
-        manager.add_node_from_metadata(&meta_b, "ROM B").unwrap();
+        // Links record the size of what they build, which applying checks
+        let neighbors = manager.get_neighbors(&hashes[0]).unwrap();
+        assert_eq!(neighbors[0].1.output_size, Some(16 * 1024));
 
-        let (nodes, edges) = manager.list();
-        assert_eq!(nodes.len(), 2);
-        assert_eq!(edges.len(), 0);
+        // A damaged Beta -> Gamma diff leaves Gamma unbuilt
+        let diff_path = manager
+            .get_neighbors(&hashes[1])
+            .unwrap()
+            .into_iter()
+            .find(|(node, _)| node.sha256 == hashes[2])
+            .map(|(_, edge)| edge.diff_path.clone())
+            .unwrap();
+        fs::write(temp_dir.path().join("diffs").join(&diff_path), b"garbage").unwrap();
+        let result = manager
+            .validate_all(&roms[..1], &mut NoProgress, &CancelToken::new())
+            .unwrap();
+        assert_eq!(result.verified, hashes[..2]);
+        assert_eq!(result.edges_checked, 3);
+        assert_eq!(result.broken.len(), 1);
+        assert_eq!(result.broken[0].from, hashes[1]);
+        assert_eq!(result.broken[0].to, hashes[2]);
+        assert_eq!(result.broken[0].diff_path, diff_path);
+        assert_eq!(result.unbuilt.len(), 1);
+        assert_eq!(result.unbuilt[0].title, "Gamma");
     }
 
     #[test]
-    fn test_find_path() {
+    fn test_check_diffs() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let mut manager = StorageManager::new_in_memory(temp_dir.path()).unwrap();
-
-        let meta_a = make_metadata(0xAA, "a.nes");
-        let meta_b = make_metadata(0xBB, "b.nes");
-        let meta_c = make_metadata(0xCC, "c.nes");
-
-        manager.add_node_from_metadata(&meta_a, "ROM A").unwrap();
-        manager.add_node_from_metadata(&meta_b, "ROM B").unwrap();
-        manager.add_node_from_metadata(&meta_c, "ROM C").unwrap();
-
-        let idx_a = manager.graph.get_node_by_hash(&meta_a.sha256).unwrap();
-        let idx_b = manager.graph.get_node_by_hash(&meta_b.sha256).unwrap();
-        let idx_c = manager.graph.get_node_by_hash(&meta_c.sha256).unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
+        let mut roms = Vec::new();
+        for (name, fill) in [("a.nes", 0x11), ("b.nes", 0x22)] {
+            let path = rom_file(temp_dir.path(), name, fill);
+            manager.add_node(&path, &NodeMetadata::default()).unwrap();
+            roms.push(path);
+        }
+        manager
+            .link_nodes(
+                &roms[0],
+                &roms[1],
+                None,
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
 
-        manager.graph.add_edge(
-            idx_a,
-            idx_b,
-            DiffEdge {
-                db_id: 1,
-                diff_path: "a_to_b.bsdiff".to_string(),
-                diff_size: 100,
-            },
-        );
-        manager.graph.add_edge(
-            idx_b,
-            idx_c,
-            DiffEdge {
-                db_id: 2,
-                diff_path: "b_to_c.bsdiff".to_string(),
-                diff_size: 100,
-            },
-        );
+        // Linking records checksums; older diffs get theirs on the first check
+        let result = manager
+            .check_diffs(&mut NoProgress, &CancelToken::new())
+            .unwrap();
+        assert_eq!((result.checked, result.checksums_recorded), (2, 0));
+        assert!(result.problems.is_empty());
+        manager
+            .conn
+            .execute("UPDATE edges SET diff_sha256 = NULL", [])
+            .unwrap();
+        let result = manager
+            .check_diffs(&mut NoProgress, &CancelToken::new())
+            .unwrap();
+        assert_eq!(result.checksums_recorded, 2);
 
-        let path = manager
-            .find_path(&meta_a.sha256, &meta_c.sha256)
-            .expect("Path should exist");
-        assert_eq!(path.len(), 3); // A -> B -> C
+        // Same size, different bytes
+        let stored = manager.stored_diffs().unwrap();
+        let path = temp_dir.path().join("diffs").join(&stored[0].diff_path);
+        let mut bytes = fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xFF;
+        fs::write(&path, bytes).unwrap();
+        let result = manager
+            .check_diffs(&mut NoProgress, &CancelToken::new())
+            .unwrap();
+        assert_eq!(result.problems.len(), 1);
+        assert_eq!(result.problems[0].diff_path, stored[0].diff_path);
+        assert!(result.problems[0].problem.starts_with("SHA-256 is"));
     }
 
     #[test]
-    fn test_update_node_metadata() {
-        let temp_dir = tempfile::tempdir().unwrap();
-        let mut manager = StorageManager::new_in_memory(temp_dir.path()).unwrap();
+    fn test_observers_see_saved_changes() {
+        use std::sync::{Arc, Mutex};
 
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = in_memory_manager(temp_dir.path());
         let metadata = make_metadata(0xAA, "test.nes");
         manager
-            .add_node_from_metadata(&metadata, "Original Title")
+            .add_node_from_metadata(&metadata, "Original")
             .unwrap();
 
-        // Update metadata
-        let updated = NodeMetadata {
-            title: "Updated Title".to_string(),
-            source_url: Some("https://example.com".to_string()),
-            version: Some("1.0".to_string()),
-            release_date: Some("2024-01-15".to_string()),
-            tags: vec!["action".to_string()],
-            description: Some("A description".to_string()),
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&events);
+        let id = manager.add_observer(Box::new(move |event: &StorageEvent| {
+            seen.lock().unwrap().push(event.clone());
+        }));
+
+        let renamed = NodeMetadata {
+            title: "Renamed".to_string(),
+            ..Default::default()
         };
         manager
-            .update_node_metadata(&metadata.sha256, &updated)
+            .update_node_metadata(&metadata.sha256, &renamed)
             .unwrap();
+        manager.remove_node(&metadata.sha256).unwrap();
+        assert!(manager.remove_observer(id).is_some());
+        assert!(manager.remove_observer(id).is_none());
+        manager.add_node_from_metadata(&metadata, "Unseen").unwrap();
 
-        // Verify in-memory graph is updated
-        let node = manager
-            .get_node_by_hash(&metadata.sha256)
-            .expect("Node should exist");
-        assert_eq!(node.title, "Updated Title");
-
-        // Verify database is updated
-        let row = manager
-            .get_node_row_by_hash(&metadata.sha256)
-            .unwrap()
-            .expect("NodeRow should exist");
-        assert_eq!(row.title, "Updated Title");
-        assert_eq!(row.source_url, Some("https://example.com".to_string()));
-        assert_eq!(row.version, Some("1.0".to_string()));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                StorageEvent::MetadataChanged {
+                    sha256: metadata.sha256,
+                    title: "Renamed".to_string(),
+                },
+                StorageEvent::NodeRemoved {
+                    sha256: metadata.sha256,
+                    title: "Renamed".to_string(),
+                },
+            ]
+        );
     }
 
     #[test]
-    fn test_update_node_title_syncs_graph() {
+    fn test_read_only_library() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let mut manager = StorageManager::new_in_memory(temp_dir.path()).unwrap();
+        let config = StorageConfig {
+            db_path: temp_dir.path().join("dromos.db"),
+            diffs_dir: temp_dir.path().join("diffs"),
+            attachments_dir: temp_dir.path().join("attachments"),
+        };
 
-        let metadata = make_metadata(0xAA, "test.nes");
+        // A read-only library must already exist
+        let read_only = || {
+            StorageManager::builder()
+                .paths(config.clone())
+                .read_only(true)
+                .open()
+        };
+        assert!(read_only().is_err());
+
+        let mut manager = StorageManager::builder()
+            .paths(config.clone())
+            .journal_mode(JournalMode::Wal)
+            .cache_size_kib(4096)
+            .open()
+            .unwrap();
         manager
-            .add_node_from_metadata(&metadata, "Original Title")
+            .add_node_from_metadata(&make_metadata(0xAA, "a.nes"), "A")
             .unwrap();
+        drop(manager);
 
-        // Update just the title
-        let updated = NodeMetadata {
-            title: "New Title".to_string(),
+        let hash = make_metadata(0xAA, "a.nes").sha256;
+        let mut manager = read_only().unwrap();
+        assert!(manager.node_exists(&hash));
+        let metadata = NodeMetadata {
+            title: "B".to_string(),
             ..Default::default()
         };
+        assert!(matches!(
+            manager.update_node_metadata(&hash, &metadata),
+            Err(DromosError::ReadOnly)
+        ));
+        assert!(matches!(
+            manager.remove_node(&hash),
+            Err(DromosError::ReadOnly)
+        ));
+    }
+
+    #[test]
+    fn test_query_readonly() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = StorageConfig {
+            db_path: temp_dir.path().join("dromos.db"),
+            diffs_dir: temp_dir.path().join("diffs"),
+            attachments_dir: temp_dir.path().join("attachments"),
+        };
+        let mut manager = StorageManager::builder().paths(config).open().unwrap();
         manager
-            .update_node_metadata(&metadata.sha256, &updated)
+            .add_node_from_metadata(&make_metadata(0xAA, "a.nes"), "A")
             .unwrap();
 
-        // Verify in-memory graph node.title is also updated
-        let node = manager
-            .get_node_by_hash(&metadata.sha256)
-            .expect("Node should exist");
-        assert_eq!(node.title, "New Title");
+        let result = manager
+            .query_readonly("SELECT title FROM nodes ORDER BY title COLLATE TITLE")
+            .unwrap();
+        assert_eq!(result.rows.len(), 1);
+        let attached = temp_dir.path().join("other.db");
+        for sql in [
+            "BEGIN".to_string(),
+            format!("ATTACH '{}' AS other", attached.display()),
+            "PRAGMA foreign_keys = OFF".to_string(),
+        ] {
+            assert!(manager.query_readonly(&sql).is_err(), "{}", sql);
+        }
+        assert!(!attached.exists());
+        // Changes are still saved afterwards
+        manager
+            .add_node_from_metadata(&make_metadata(0xBB, "b.nes"), "B")
+            .unwrap();
     }
 }
@@ -1,3 +1,20 @@
+#[cfg(feature = "tokio")]
+pub mod async_manager;
+pub mod autotag;
+pub mod builder;
+pub mod events;
+pub mod health;
 pub mod manager;
+pub mod shared;
 
-pub use manager::{BuildResult, RemoveResult, StorageManager};
+#[cfg(feature = "tokio")]
+pub use async_manager::AsyncStorageManager;
+pub use autotag::TagRule;
+pub use builder::{JournalMode, StorageManagerBuilder};
+pub use events::{ObserverId, StorageEvent, StorageObserver};
+pub use health::{DiffCheck, DiffProblem, HealthyDiff, StoredDiff};
+pub use manager::{
+    BrokenEdge, BuildResult, DatImport, DataWipe, LibraryStatus, LibraryValidation, LocateMatch,
+    LocatedFile, MergeResult, NodeBuild, RemoveResult, SdCardExport, StorageManager,
+};
+pub use shared::SharedStorageManager;
@@ -0,0 +1,255 @@
+//! A [`StorageManager`] shared between threads.
+//!
+//! Changes go through a single writer, as SQLite allows only one at a time.
+//! Reads don't wait for it: each takes a read-only connection from a small
+//! pool, and graph queries and builds use a snapshot of the graph that is
+//! replaced after every change. With the WAL journal (the default here),
+//! readers see the last committed state while a write is in progress.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::cancel::CancelToken;
+use crate::db::collation;
+use crate::db::{NodeFilter, NodeMetadata, NodeRow, Repository};
+use crate::error::Result;
+use crate::graph::RomGraph;
+use crate::progress::ProgressSink;
+use crate::rom::RomMetadata;
+
+use super::builder::{JournalMode, StorageManagerBuilder};
+use super::manager::build_rom;
+use super::{BuildResult, RemoveResult, StorageManager};
+
+/// Idle read connections kept open for reuse; more are opened as needed.
+const MAX_IDLE_READERS: usize = 8;
+
+/// How long a reader waits on a lock held by the writer before failing,
+/// for journal modes other than WAL.
+const READER_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A thread-safe handle to a library. Clones refer to the same library.
+#[derive(Clone)]
+pub struct SharedStorageManager {
+    inner: Arc<Shared>,
+}
+
+struct Shared {
+    writer: Mutex<StorageManager>,
+    graph: RwLock<Arc<RomGraph>>,
+    /// Idle read-only connections; unused for in-memory libraries, whose
+    /// reads go through the writer's connection.
+    readers: Mutex<Vec<Connection>>,
+    db_path: Option<PathBuf>,
+    diffs_dir: PathBuf,
+}
+
+impl SharedStorageManager {
+    /// Open the library with `options`, using the WAL journal unless another
+    /// journal mode was chosen.
+    pub fn open(mut options: StorageManagerBuilder) -> Result<Self> {
+        if options.journal_mode.is_none() && !options.in_memory {
+            options.journal_mode = Some(JournalMode::Wal);
+        }
+        let in_memory = options.in_memory;
+        let manager = options.open()?;
+        let config = manager.config().clone();
+        Ok(SharedStorageManager {
+            inner: Arc::new(Shared {
+                graph: RwLock::new(Arc::new(manager.graph().clone())),
+                writer: Mutex::new(manager),
+                readers: Mutex::new(Vec::new()),
+                db_path: (!in_memory).then_some(config.db_path),
+                diffs_dir: config.diffs_dir,
+            }),
+        })
+    }
+
+    /// Run `f` with exclusive access to the manager, then refresh the graph
+    /// snapshot. Other writers wait; readers don't.
+    pub fn write<T>(&self, f: impl FnOnce(&mut StorageManager) -> Result<T>) -> Result<T> {
+        let mut manager = self.inner.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let result = f(&mut manager);
+        // Refresh even on failure: a failed import reloads the graph
+        let snapshot = Arc::new(manager.graph().clone());
+        *self.inner.graph.write().unwrap_or_else(|e| e.into_inner()) = snapshot;
+        result
+    }
+
+    /// Run `f` on a read-only connection from the pool.
+    pub fn read<T>(&self, f: impl FnOnce(&Repository) -> Result<T>) -> Result<T> {
+        let Some(db_path) = &self.inner.db_path else {
+            let manager = self.inner.writer.lock().unwrap_or_else(|e| e.into_inner());
+            return f(&manager.repository());
+        };
+        let idle = self
+            .inner
+            .readers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop();
+        let conn = match idle {
+            Some(conn) => conn,
+            None => open_reader(db_path)?,
+        };
+        let result = f(&Repository::new(&conn));
+        let mut readers = self.inner.readers.lock().unwrap_or_else(|e| e.into_inner());
+        if readers.len() < MAX_IDLE_READERS {
+            readers.push(conn);
+        }
+        result
+    }
+
+    /// The graph as of the last completed change. It stays valid, but goes
+    /// stale once another change completes; call again for the latest.
+    pub fn graph(&self) -> Arc<RomGraph> {
+        Arc::clone(&self.inner.graph.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn add_node(&self, path: &Path, metadata: &NodeMetadata) -> Result<RomMetadata> {
+        self.write(|m| m.add_node(path, metadata))
+    }
+
+    pub fn link_nodes(
+        &self,
+        path_a: &Path,
+        path_b: &Path,
+        note: Option<&str>,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<(u64, u64)> {
+        self.write(|m| m.link_nodes(path_a, path_b, note, progress, cancel))
+    }
+
+    pub fn update_node_metadata(&self, sha256: &[u8; 32], metadata: &NodeMetadata) -> Result<()> {
+        self.write(|m| m.update_node_metadata(sha256, metadata))
+    }
+
+    pub fn remove_node(&self, sha256: &[u8; 32]) -> Result<RemoveResult> {
+        self.write(|m| m.remove_node(sha256))
+    }
+
+    pub fn get_node_row_by_hash(&self, sha256: &[u8; 32]) -> Result<Option<NodeRow>> {
+        self.read(|repo| repo.get_node_by_hash(sha256))
+    }
+
+    pub fn search(&self, filter: &NodeFilter) -> Result<Vec<NodeRow>> {
+        self.read(|repo| repo.iter_nodes_filtered(filter).collect())
+    }
+
+    /// See [`StorageManager::build_rom`]. Builds run in parallel with each
+    /// other and with changes, against the current graph snapshot.
+    pub fn build_rom(
+        &self,
+        source_path: &Path,
+        target_hash: &[u8; 32],
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<BuildResult> {
+        let graph = self.graph();
+        self.read(|repo| {
+            build_rom(
+                &graph,
+                repo,
+                &self.inner.diffs_dir,
+                source_path,
+                target_hash,
+                progress,
+                cancel,
+            )
+        })
+    }
+}
+
+fn open_reader(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    conn.busy_timeout(READER_BUSY_TIMEOUT)?;
+    collation::register(&conn)?;
+    Ok(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::NoProgress;
+    use crate::rom::RomType;
+    use std::thread;
+    use tempfile::TempDir;
+
+    fn make_metadata(byte: u8) -> RomMetadata {
+        RomMetadata {
+            rom_type: RomType::Nes,
+            sha256: [byte; 32],
+            sha1: None,
+            crc32: None,
+            content_size: None,
+            filename: Some(format!("rom_{:02x}.nes", byte)),
+            nes_header: None,
+            source_file_header: None,
+        }
+    }
+
+    #[test]
+    fn test_reads_and_writes_from_many_threads() {
+        let temp_dir = TempDir::new().unwrap();
+        let shared = SharedStorageManager::open(
+            StorageManager::builder()
+                .db_path(temp_dir.path().join("dromos.db"))
+                .diffs_dir(temp_dir.path().join("diffs")),
+        )
+        .unwrap();
+
+        let writers: Vec<_> = (0..4u8)
+            .map(|t| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    for i in 0..8u8 {
+                        let metadata = make_metadata(t * 8 + i + 1);
+                        shared
+                            .write(|m| m.add_node_from_metadata(&metadata, "Shared ROM"))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    for _ in 0..8 {
+                        let rows = shared.search(&NodeFilter::default()).unwrap();
+                        assert!(rows.len() <= 32);
+                    }
+                })
+            })
+            .collect();
+        for handle in writers.into_iter().chain(readers) {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(shared.graph().node_count(), 32);
+        assert_eq!(shared.search(&NodeFilter::default()).unwrap().len(), 32);
+        let row = shared.get_node_row_by_hash(&[32; 32]).unwrap().unwrap();
+        assert_eq!(row.filename.as_deref(), Some("rom_20.nes"));
+
+        // A snapshot taken before a change keeps its contents
+        let before = shared.graph();
+        shared.remove_node(&[1; 32]).unwrap();
+        assert_eq!(before.node_count(), 32);
+        assert_eq!(shared.graph().node_count(), 31);
+
+        let missing = shared.build_rom(
+            &temp_dir.path().join("absent.nes"),
+            &[2; 32],
+            &mut NoProgress,
+            &CancelToken::new(),
+        );
+        assert!(missing.is_err());
+    }
+}
@@ -0,0 +1,641 @@
+//! `dromos sync`: bringing this library and another machine's in step,
+//! through its `dromos serve` over HTTP, or `dromos serve --stdio` over SSH.
+//!
+//! Both sides' manifests are compared, and only the nodes, links, diffs,
+//! and attachments one side is missing are sent to it, in both directions.
+//! Files go one request at a time, so no request grows with the library.
+//! Nodes both libraries have keep their own metadata; differences are
+//! reported, to settle with `export` and `import`.
+//!
+//! `import http://...` uses the same connection to download part of a
+//! remote library as an export folder, and imports that as usual.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, BufReader, Read as _, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::cancel::CancelToken;
+use crate::error::{DromosError, Result};
+use crate::exchange::sync::without_dangling;
+use crate::exchange::{ExportManifest, ImportResult, SyncPlan, plan_sync};
+use crate::progress::{Progress, ProgressSink, Stage};
+use crate::server::{MAX_BODY_BYTES, StdioReply, StdioRequest};
+use crate::storage::StorageManager;
+
+/// Program run on the other machine for SSH remotes without a command.
+const DEFAULT_REMOTE_COMMAND: &str = "dromos";
+
+/// Another machine's library.
+pub struct Remote {
+    name: String,
+    transport: Transport,
+}
+
+enum Transport {
+    Http {
+        base: String,
+        agent: ureq::Agent,
+        /// Sent with every request, for servers that require one
+        token: Option<String>,
+    },
+    Ssh {
+        child: Child,
+        /// Taken when closing, so the other side sees the end of input
+        stdin: Option<ChildStdin>,
+        stdout: BufReader<ChildStdout>,
+    },
+}
+
+/// What a sync changed.
+#[derive(Debug, Default, Serialize)]
+pub struct SyncResult {
+    /// Changes to this library
+    pub pulled: ImportResult,
+    /// Changes to the remote library
+    pub pushed: ImportResult,
+    /// Hashes of nodes whose metadata differs between the libraries
+    pub conflicts: Vec<String>,
+}
+
+/// The part of a remote library to import; all of it when empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    /// Hash prefix of a ROM whose linked group to import
+    pub component: Option<String>,
+    /// Import ROMs with any of these tags
+    pub tags: Vec<String>,
+}
+
+impl Remote {
+    /// Connect to `spec`: the `http://host:port` address of `dromos serve`,
+    /// with `?token=<token>` if it printed one, or `[user@]host[:command]`
+    /// to run `command serve --stdio` over SSH (`dromos` by default).
+    pub fn connect(spec: &str) -> Result<Remote> {
+        let mut name = spec;
+        let transport = if spec.starts_with("http://") {
+            let (address, query) = spec.split_once('?').unwrap_or((spec, ""));
+            // Kept out of messages
+            name = address;
+            Transport::Http {
+                base: address.trim_end_matches('/').to_string(),
+                agent: ureq::Agent::new(),
+                token: query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("token="))
+                    .map(str::to_string),
+            }
+        } else if spec.starts_with("https://") {
+            return Err(DromosError::Sync {
+                remote: spec.to_string(),
+                reason: "HTTPS isn't supported; use http:// or SSH".to_string(),
+            });
+        } else {
+            let (host, command) = spec
+                .split_once(':')
+                .unwrap_or((spec, DEFAULT_REMOTE_COMMAND));
+            let mut child = Command::new("ssh")
+                .args(["-T", "--", host, command, "serve", "--stdio"])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|e| DromosError::Sync {
+                    remote: spec.to_string(),
+                    reason: format!("cannot run ssh: {}", e),
+                })?;
+            let stdin = child.stdin.take();
+            let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+            Transport::Ssh {
+                child,
+                stdin,
+                stdout,
+            }
+        };
+        Ok(Remote {
+            name: name.to_string(),
+            transport,
+        })
+    }
+
+    /// The remote as given to [`Remote::connect`].
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Compare this library with the remote one.
+    pub fn plan(&mut self, storage: &StorageManager) -> Result<SyncPlan> {
+        let remote: ExportManifest = self.request_json("GET", "/api/sync/manifest", &())?;
+        let local = storage.plan_export(None)?.manifest;
+        if remote.dromos_export.data_revision != local.dromos_export.data_revision {
+            return Err(self.error(format!(
+                "data revision mismatch: remote has {}, local has {}",
+                remote.dromos_export.data_revision, local.dromos_export.data_revision
+            )));
+        }
+        Ok(plan_sync(&local, &remote))
+    }
+
+    /// Copy what each library is missing from the other, as planned.
+    pub fn sync(
+        &mut self,
+        storage: &mut StorageManager,
+        plan: SyncPlan,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<SyncResult> {
+        let mut result = SyncResult {
+            conflicts: plan.conflicts,
+            ..Default::default()
+        };
+        if !plan.pull.is_empty() {
+            // Gathered in a folder removed when dropped
+            let folder = tempfile::Builder::new().prefix("dromos-sync-").tempdir()?;
+            self.download_files(&plan.pull, folder.path(), progress, cancel)?;
+            result.pulled = storage.import_synced(folder.path(), progress, cancel)?;
+        }
+        if !plan.push.is_empty() {
+            self.upload_files(storage, &plan.push, progress, cancel)?;
+            cancel.check()?;
+            result.pushed = self.request_json("POST", "/api/sync/import", &plan.push)?;
+        }
+        Ok(result)
+    }
+
+    /// Send the diffs and attachments `manifest` lists, one request each,
+    /// for the remote's import of it. Diffs this library lacks are left
+    /// out, as in exports.
+    fn upload_files(
+        &mut self,
+        storage: &StorageManager,
+        manifest: &ExportManifest,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<()> {
+        let config = storage.config();
+        let files: Vec<(String, PathBuf)> = manifest_files(manifest)?
+            .into_iter()
+            .filter_map(|(relative, _)| {
+                let path = match relative.strip_prefix("diffs/") {
+                    Some(name) => config.diffs_dir.join(name),
+                    None => config
+                        .attachments_dir
+                        .join(relative.strip_prefix("attachments/")?),
+                };
+                path.is_file().then_some((relative, path))
+            })
+            .collect();
+        for (i, (relative, path)) in files.iter().enumerate() {
+            cancel.check()?;
+            let contents = fs::read(path).map_err(DromosError::file(path))?;
+            progress.progress(&Progress {
+                stage: Stage::Uploading,
+                item: relative,
+                current: i + 1,
+                total: files.len(),
+                bytes: contents.len() as u64,
+            });
+            let url = format!("/api/sync/incoming/{}", relative);
+            let (status, reply) =
+                self.request("PUT", &url, "application/octet-stream", &contents)?;
+            if !(200..300).contains(&status) {
+                return Err(self.failure("PUT", &url, status, &reply));
+            }
+        }
+        Ok(())
+    }
+
+    /// Download `selection` from the remote library into `folder` as an
+    /// export folder, fetching its diffs and attachments one at a time.
+    /// Links and attachments involving ROMs that are neither selected nor
+    /// in `local` (by hash) are left out, since importing would skip them.
+    pub fn download_export(
+        &mut self,
+        selection: &Selection,
+        local: &HashSet<String>,
+        folder: &Path,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<ExportManifest> {
+        let mut query: Vec<String> = selection
+            .tags
+            .iter()
+            .map(|tag| format!("tag={}", percent_encode(tag)))
+            .collect();
+        if let Some(component) = &selection.component {
+            query.push(format!("component={}", percent_encode(component)));
+        }
+        let url = format!("/api/sync/manifest?{}", query.join("&"));
+        let manifest: ExportManifest = self.request_json("GET", &url, &())?;
+        let manifest = without_dangling(&manifest, local);
+        self.download_files(&manifest, folder, progress, cancel)?;
+        Ok(manifest)
+    }
+
+    /// Fetch the diffs and attachments `manifest` lists into `folder`, one
+    /// request each, and write the manifest beside them as an export folder.
+    fn download_files(
+        &mut self,
+        manifest: &ExportManifest,
+        folder: &Path,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Result<()> {
+        let files = manifest_files(manifest)?;
+        for dir in ["diffs", "attachments"] {
+            let dir = folder.join(dir);
+            fs::create_dir_all(&dir).map_err(DromosError::file(&dir))?;
+        }
+        for (i, (relative, size)) in files.iter().enumerate() {
+            cancel.check()?;
+            progress.progress(&Progress {
+                stage: Stage::Downloading,
+                item: relative,
+                current: i + 1,
+                total: files.len(),
+                bytes: *size,
+            });
+            let (status, contents) = self.request(
+                "GET",
+                &format!("/api/sync/{}", relative),
+                "application/json",
+                &[],
+            )?;
+            // Diffs the remote library lacks are left out, as in exports
+            if status == 404 && relative.starts_with("diffs/") {
+                continue;
+            }
+            if status != 200 {
+                return Err(self.error(format!("GET {}: status {}", relative, status)));
+            }
+            let path = folder.join(relative);
+            fs::write(&path, contents).map_err(DromosError::file(&path))?;
+        }
+        let index = folder.join("index.json");
+        fs::write(&index, serde_json::to_vec(manifest)?).map_err(DromosError::file(&index))
+    }
+
+    /// Send `body` as JSON, and read the JSON reply.
+    fn request_json<T: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        url: &str,
+        body: &impl Serialize,
+    ) -> Result<T> {
+        let body = match method {
+            "GET" => Vec::new(),
+            _ => serde_json::to_vec(body)?,
+        };
+        let (status, reply) = self.request(method, url, "application/json", &body)?;
+        if !(200..300).contains(&status) {
+            return Err(self.failure(method, url, status, &reply));
+        }
+        serde_json::from_slice(&reply)
+            .map_err(|e| self.error(format!("unexpected reply to {} {}: {}", method, url, e)))
+    }
+
+    /// The error for an unsuccessful reply.
+    fn failure(&self, method: &str, url: &str, status: u16, reply: &[u8]) -> DromosError {
+        // Errors come back as {"error": {"message": ...}}
+        let message = serde_json::from_slice::<Value>(reply)
+            .ok()
+            .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| format!("{} {}: status {}", method, url, status));
+        self.error(message)
+    }
+
+    /// Send a request with a `content_type` body, returning the reply's
+    /// status and body.
+    fn request(
+        &mut self,
+        method: &str,
+        url: &str,
+        content_type: &str,
+        body: &[u8],
+    ) -> Result<(u16, Vec<u8>)> {
+        let sent = match &mut self.transport {
+            Transport::Http { base, agent, token } => {
+                let mut request = agent
+                    .request(method, &format!("{}{}", base, url))
+                    .set("Content-Type", content_type);
+                if let Some(token) = token {
+                    request = request.set("Authorization", &format!("Bearer {}", token));
+                }
+                let response = request.send_bytes(body);
+                match response {
+                    Ok(response) | Err(ureq::Error::Status(_, response)) => {
+                        let status = response.status();
+                        let mut reply = Vec::new();
+                        match response
+                            .into_reader()
+                            .take(MAX_BODY_BYTES + 1)
+                            .read_to_end(&mut reply)
+                        {
+                            Ok(_) if reply.len() as u64 > MAX_BODY_BYTES => {
+                                Err(too_large(method, url))
+                            }
+                            Ok(_) => Ok((status, reply)),
+                            Err(e) => Err(e.to_string()),
+                        }
+                    }
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            Transport::Ssh { stdin, stdout, .. } => {
+                let request = StdioRequest {
+                    method: method.to_string(),
+                    url: url.to_string(),
+                    length: body.len(),
+                };
+                stdio_exchange(stdin.as_mut(), stdout, &request, body).and_then(
+                    |(status, length)| {
+                        if length as u64 > MAX_BODY_BYTES {
+                            return Err(too_large(method, url));
+                        }
+                        let mut reply = vec![0; length];
+                        stdout.read_exact(&mut reply).map_err(|_| closed())?;
+                        Ok((status, reply))
+                    },
+                )
+            }
+        };
+        sent.map_err(|reason| self.error(reason))
+    }
+
+    fn error(&self, reason: String) -> DromosError {
+        DromosError::Sync {
+            remote: self.name.clone(),
+            reason,
+        }
+    }
+}
+
+impl Drop for Remote {
+    fn drop(&mut self) {
+        if let Transport::Ssh { child, stdin, .. } = &mut self.transport {
+            // The other side stops at the end of its input
+            drop(stdin.take());
+            let _ = child.wait();
+        }
+    }
+}
+
+/// The diffs and attachments `manifest` lists, as paths in an export folder
+/// with their sizes. File names come from another library, so any that
+/// would land outside the folder are refused.
+fn manifest_files(manifest: &ExportManifest) -> Result<Vec<(String, u64)>> {
+    let mut files: Vec<(String, u64)> = Vec::new();
+    let mut seen = HashSet::new();
+    for edge in &manifest.diffs {
+        files.push((
+            format!("diffs/{}", plain_name(&edge.diff_path)?),
+            edge.diff_size as u64,
+        ));
+    }
+    for attachment in &manifest.attachments {
+        if seen.insert(&attachment.sha256) {
+            let name = plain_name(&attachment.sha256)?;
+            files.push((format!("attachments/{}", name), attachment.size as u64));
+        }
+    }
+    Ok(files)
+}
+
+fn too_large(method: &str, url: &str) -> String {
+    format!("the reply to {} {} is too large", method, url)
+}
+
+fn closed() -> String {
+    "the connection closed (is dromos installed there?)".to_string()
+}
+
+/// `name`, if it's a file name rather than a path.
+pub(crate) fn plain_name(name: &str) -> Result<&str> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(DromosError::Import(format!("Invalid file name: {}", name)));
+    }
+    Ok(name)
+}
+
+/// `text` with everything but unreserved URL characters `%XX`-escaped.
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Write one request to a `serve --stdio` process and read its reply's
+/// header, returning the status and the length of the body that follows.
+fn stdio_exchange(
+    stdin: Option<&mut ChildStdin>,
+    stdout: &mut BufReader<ChildStdout>,
+    request: &StdioRequest,
+    body: &[u8],
+) -> std::result::Result<(u16, usize), String> {
+    let stdin = stdin.ok_or_else(closed)?;
+    let mut header = serde_json::to_vec(request).map_err(|e| e.to_string())?;
+    header.push(b'\n');
+    stdin
+        .write_all(&header)
+        .and_then(|_| stdin.write_all(body))
+        .and_then(|_| stdin.flush())
+        .map_err(|_| closed())?;
+
+    let mut line = String::new();
+    if stdout.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+        return Err(closed());
+    }
+    let reply: StdioReply =
+        serde_json::from_str(&line).map_err(|e| format!("unexpected reply: {}", e))?;
+    Ok((reply.status, reply.length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::NodeMetadata;
+    use crate::progress::NoProgress;
+    use crate::server::Server;
+    use crate::test_support::write_rom;
+    use std::path::Path;
+
+    fn library(dir: &Path) -> StorageManager {
+        StorageManager::builder()
+            .in_memory()
+            .diffs_dir(dir.join("diffs"))
+            .attachments_dir(dir.join("attachments"))
+            .open()
+            .unwrap()
+    }
+
+    /// Add ROMs filled with `fills`, titled `titles`, linking the first to
+    /// the rest.
+    fn add_linked(storage: &mut StorageManager, dir: &Path, roms: &[(u8, &str)]) {
+        let mut paths = Vec::new();
+        for (fill, title) in roms {
+            let path = dir.join(format!("{:02x}.nes", fill));
+            write_rom(&path, *fill);
+            let metadata = NodeMetadata {
+                title: title.to_string(),
+                ..Default::default()
+            };
+            storage.add_node(&path, &metadata).unwrap();
+            paths.push(path);
+        }
+        for path in &paths[1..] {
+            storage
+                .link_nodes(&paths[0], path, None, &mut NoProgress, &CancelToken::new())
+                .unwrap();
+        }
+    }
+
+    /// Serve a library set up by `setup` on a free port, until cancelled.
+    fn serve(
+        dir: &Path,
+        setup: impl FnOnce(&mut StorageManager, &Path) + Send + 'static,
+    ) -> (String, CancelToken, std::thread::JoinHandle<Result<()>>) {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let address = server.address();
+        let stop = CancelToken::new();
+        let serving = {
+            let (stop, dir) = (stop.clone(), dir.to_path_buf());
+            std::thread::spawn(move || {
+                let mut storage = library(&dir);
+                setup(&mut storage, &dir);
+                server.run(&mut storage, &stop, &mut |_| {})
+            })
+        };
+        (address, stop, serving)
+    }
+
+    #[test]
+    fn test_import_from_server() {
+        let (local_dir, server_dir) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let mut local = library(local_dir.path());
+        add_linked(&mut local, local_dir.path(), &[(0xEA, "Base")]);
+        let (address, stop, serving) = serve(server_dir.path(), |storage, dir| {
+            add_linked(
+                storage,
+                dir,
+                &[(0xEA, "Base"), (0x60, "Hack"), (0x20, "Other")],
+            );
+            let hack = storage.find_nodes_by_title("Hack")[0].sha256;
+            let metadata = NodeMetadata {
+                title: "Hack".to_string(),
+                tags: vec!["Hack".to_string()],
+                ..Default::default()
+            };
+            storage.update_node_metadata(&hack, &metadata).unwrap();
+        });
+
+        let folder = local_dir.path().join("download");
+        let selection = Selection {
+            component: None,
+            tags: vec!["hack".to_string()],
+        };
+        let present = local
+            .node_rows()
+            .unwrap()
+            .iter()
+            .map(|row| crate::rom::format_hash(&row.sha256))
+            .collect();
+        let mut remote = Remote::connect(&format!("http://{}", address)).unwrap();
+        let manifest = remote
+            .download_export(
+                &selection,
+                &present,
+                &folder,
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
+        // The hack, and its links to the base this library has
+        assert_eq!(manifest.files.len(), 1);
+        assert_eq!(manifest.diffs.len(), 2);
+        assert_eq!(std::fs::read_dir(folder.join("diffs")).unwrap().count(), 2);
+
+        let (manifest, conflicts) = local.analyze_import(&folder).unwrap();
+        assert!(conflicts.is_empty());
+        let result = local
+            .execute_import(
+                &folder,
+                &manifest,
+                &Default::default(),
+                &mut NoProgress,
+                &CancelToken::new(),
+            )
+            .unwrap();
+        assert_eq!((result.nodes_added, result.edges_added), (1, 2));
+        let hack = local.find_nodes_by_title("Hack")[0].sha256;
+        let base = local_dir.path().join("ea.nes");
+        local
+            .build_rom(&base, &hack, &mut NoProgress, &CancelToken::new())
+            .unwrap();
+
+        stop.cancel();
+        assert!(serving.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_sync_over_http() {
+        let (desktop_dir, laptop_dir) =
+            (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let mut desktop = library(desktop_dir.path());
+        add_linked(
+            &mut desktop,
+            desktop_dir.path(),
+            &[(0xEA, "Base"), (0x60, "Hack")],
+        );
+
+        let (address, stop, serving) = serve(laptop_dir.path(), |laptop, dir| {
+            add_linked(
+                laptop,
+                dir,
+                &[(0xEA, "Base (laptop)"), (0x20, "Translation")],
+            );
+        });
+
+        let mut remote = Remote::connect(&format!("http://{}/", address)).unwrap();
+        let plan = remote.plan(&desktop).unwrap();
+        assert_eq!(plan.pull.files.len(), 1);
+        assert_eq!(plan.push.files.len(), 1);
+        assert_eq!(plan.conflicts.len(), 1);
+
+        let result = remote
+            .sync(&mut desktop, plan, &mut NoProgress, &CancelToken::new())
+            .unwrap();
+        assert_eq!(result.pulled.nodes_added, 1);
+        assert_eq!(result.pulled.edges_added, 2);
+        assert_eq!(result.pushed.nodes_added, 1);
+        assert_eq!(result.pushed.diffs_copied, 2);
+        // The files sent ahead of the import don't stay behind
+        assert!(!laptop_dir.path().join("sync-incoming").exists());
+
+        // The translation's diffs came along, so it builds here
+        let translation = desktop.find_nodes_by_title("Translation")[0].sha256;
+        let base = desktop_dir.path().join("ea.nes");
+        desktop
+            .build_rom(&base, &translation, &mut NoProgress, &CancelToken::new())
+            .unwrap();
+        // Only the differing metadata is left
+        let again = remote.plan(&desktop).unwrap();
+        assert!(again.is_empty());
+        assert_eq!(again.conflicts.len(), 1);
+
+        stop.cancel();
+        assert!(matches!(
+            serving.join().unwrap(),
+            Err(DromosError::Cancelled)
+        ));
+        assert!(Remote::connect("https://example.com").is_err());
+    }
+}
@@ -0,0 +1,25 @@
+//! Helpers shared by the unit tests of several modules.
+
+use std::path::{Path, PathBuf};
+
+/// A minimal iNES ROM: a 16-byte header declaring one 16 KiB PRG bank,
+/// followed by that bank filled with `fill`.
+pub(crate) fn nes_rom(fill: u8) -> Vec<u8> {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend(std::iter::repeat_n(fill, 16384));
+    rom
+}
+
+/// Write [`nes_rom`] to `path`, returning its bytes.
+pub(crate) fn write_rom(path: &Path, fill: u8) -> Vec<u8> {
+    let rom = nes_rom(fill);
+    std::fs::write(path, &rom).unwrap();
+    rom
+}
+
+/// Write [`nes_rom`] to `name` in `dir`, returning its path.
+pub(crate) fn rom_file(dir: &Path, name: &str, fill: u8) -> PathBuf {
+    let path = dir.join(name);
+    write_rom(&path, fill);
+    path
+}
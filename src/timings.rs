@@ -0,0 +1,144 @@
+//! Optional wall-time instrumentation for `--timings`.
+//!
+//! Expensive operations open a [`span`] for their phase. When timings are
+//! enabled, the elapsed time is added to a per-thread total that the CLI
+//! reports after each command; when disabled, spans cost a flag check.
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// An instrumented kind of work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Hashing,
+    DiffCreation,
+    PathFinding,
+    DiffApplication,
+}
+
+impl Phase {
+    const ALL: [Phase; 4] = [
+        Phase::Hashing,
+        Phase::DiffCreation,
+        Phase::PathFinding,
+        Phase::DiffApplication,
+    ];
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Hashing => write!(f, "hashing"),
+            Phase::DiffCreation => write!(f, "diff creation"),
+            Phase::PathFinding => write!(f, "path finding"),
+            Phase::DiffApplication => write!(f, "diff application"),
+        }
+    }
+}
+
+/// Accumulated time and call count for one phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseTiming {
+    pub phase: Phase,
+    pub total: Duration,
+    pub calls: u32,
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static TOTALS: RefCell<Vec<PhaseTiming>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Turn collection on or off for the current thread.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.with(|e| e.set(enabled));
+}
+
+pub fn enabled() -> bool {
+    ENABLED.with(|e| e.get())
+}
+
+/// Clear accumulated totals (called before each command).
+pub fn reset() {
+    TOTALS.with(|t| t.borrow_mut().clear());
+}
+
+/// Totals for every phase that ran since the last reset, in a fixed order.
+pub fn snapshot() -> Vec<PhaseTiming> {
+    TOTALS.with(|t| {
+        let totals = t.borrow();
+        Phase::ALL
+            .iter()
+            .filter_map(|phase| totals.iter().find(|p| p.phase == *phase).copied())
+            .collect()
+    })
+}
+
+fn record(phase: Phase, elapsed: Duration) {
+    TOTALS.with(|t| {
+        let mut totals = t.borrow_mut();
+        match totals.iter_mut().find(|p| p.phase == phase) {
+            Some(entry) => {
+                entry.total += elapsed;
+                entry.calls += 1;
+            }
+            None => totals.push(PhaseTiming {
+                phase,
+                total: elapsed,
+                calls: 1,
+            }),
+        }
+    });
+}
+
+/// Guard that records the time until it is dropped.
+pub struct Span {
+    phase: Phase,
+    start: Option<Instant>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            record(self.phase, start.elapsed());
+        }
+    }
+}
+
+/// Start timing `phase`; the time is recorded when the returned guard drops.
+pub fn span(phase: Phase) -> Span {
+    Span {
+        phase,
+        start: enabled().then(Instant::now),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_accumulate_only_when_enabled() {
+        reset();
+        set_enabled(false);
+        drop(span(Phase::Hashing));
+        assert!(snapshot().is_empty());
+
+        set_enabled(true);
+        drop(span(Phase::DiffApplication));
+        drop(span(Phase::Hashing));
+        drop(span(Phase::Hashing));
+        set_enabled(false);
+
+        let totals = snapshot();
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].phase, Phase::Hashing);
+        assert_eq!(totals[0].calls, 2);
+        assert_eq!(totals[1].phase, Phase::DiffApplication);
+        assert_eq!(totals[1].calls, 1);
+
+        reset();
+        assert!(snapshot().is_empty());
+    }
+}
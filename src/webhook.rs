@@ -0,0 +1,224 @@
+//! Webhooks: library changes POSTed as JSON to the URLs set with
+//! `webhook.<name> = <url>` while `dromos serve` or `dromos watch` runs.
+//!
+//! Each [`StorageEvent`] becomes one request, with a body like:
+//!
+//! ```text
+//! {"event": "node_added", "sha256": "...", "title": "Super Mario Bros.",
+//!  "at": "2026-10-17T12:00:00+00:00", "content": "Added Super Mario Bros."}
+//! ```
+//!
+//! Events are `node_added`, `node_updated`, and `node_removed` (with
+//! `sha256` and `title`), and `link_created` (with `source`, `target`, and
+//! `diff_size`; linking two ROMs creates one each way). `content` sums the
+//! event up in words, which is what Discord shows for its webhooks.
+//!
+//! Requests are sent in order from a background thread, so a slow endpoint
+//! never holds up the library; failures are collected for the caller to
+//! report with [`Webhooks::failures`].
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+
+use crate::rom::format_hash;
+use crate::storage::{StorageEvent, StorageObserver};
+
+/// Longest a webhook is waited on before its request counts as failed.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Webhooks being sent to, until dropped.
+pub struct Webhooks {
+    /// Dropped first when stopping, so the worker ends once it's caught up
+    events: Option<Sender<Value>>,
+    failures: Receiver<Failure>,
+    worker: Option<JoinHandle<()>>,
+}
+
+/// A webhook request that didn't get a successful reply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    /// Name of the webhook in the config file
+    pub name: String,
+    /// The event that wasn't delivered, e.g. `node_added`
+    pub event: String,
+    pub reason: String,
+}
+
+impl Webhooks {
+    /// Start sending to `hooks` (name -> URL).
+    pub fn start(hooks: BTreeMap<String, String>) -> Webhooks {
+        let (events, queue) = mpsc::channel::<Value>();
+        let (report, failures) = mpsc::channel();
+        let worker = std::thread::spawn(move || {
+            let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+            for payload in queue {
+                let body = payload.to_string();
+                for (name, url) in &hooks {
+                    let sent = agent
+                        .post(url)
+                        .set("Content-Type", "application/json")
+                        .send_bytes(body.as_bytes());
+                    if let Err(e) = sent {
+                        let _ = report.send(Failure {
+                            name: name.clone(),
+                            event: payload["event"].as_str().unwrap_or_default().to_string(),
+                            reason: e.to_string(),
+                        });
+                    }
+                }
+            }
+        });
+        Webhooks {
+            events: Some(events),
+            failures,
+            worker: Some(worker),
+        }
+    }
+
+    /// An observer queueing each change it hears about, for
+    /// [`StorageManager::add_observer`](crate::storage::StorageManager::add_observer).
+    pub fn observer(&self) -> Box<dyn StorageObserver> {
+        let events = self.events.clone();
+        Box::new(move |event: &StorageEvent| {
+            if let Some(events) = &events {
+                // The worker is gone only once these webhooks are dropped
+                let _ = events.send(payload(event));
+            }
+        })
+    }
+
+    /// Requests that failed since last asked.
+    pub fn failures(&self) -> Vec<Failure> {
+        self.failures.try_iter().collect()
+    }
+
+    /// Send the changes already queued, then stop, returning the requests
+    /// that failed since last asked.
+    pub fn finish(mut self) -> Vec<Failure> {
+        self.stop();
+        self.failures()
+    }
+
+    fn stop(&mut self) {
+        self.events = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for Webhooks {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// The JSON body sent for `event`.
+pub fn payload(event: &StorageEvent) -> Value {
+    let at = chrono::Utc::now().to_rfc3339();
+    match event {
+        StorageEvent::NodeAdded { sha256, title } => json!({
+            "event": "node_added",
+            "sha256": format_hash(sha256),
+            "title": title,
+            "at": at,
+            "content": format!("Added {}", title),
+        }),
+        StorageEvent::MetadataChanged { sha256, title } => json!({
+            "event": "node_updated",
+            "sha256": format_hash(sha256),
+            "title": title,
+            "at": at,
+            "content": format!("Updated {}", title),
+        }),
+        StorageEvent::NodeRemoved { sha256, title } => json!({
+            "event": "node_removed",
+            "sha256": format_hash(sha256),
+            "title": title,
+            "at": at,
+            "content": format!("Removed {}", title),
+        }),
+        StorageEvent::EdgeCreated {
+            source,
+            target,
+            diff_size,
+        } => json!({
+            "event": "link_created",
+            "source": format_hash(source),
+            "target": format_hash(target),
+            "diff_size": diff_size,
+            "at": at,
+            "content": format!(
+                "Linked {} to {}",
+                &format_hash(source)[..16],
+                &format_hash(target)[..16]
+            ),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::time::Instant;
+
+    #[test]
+    fn test_webhooks_post_events() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        // Answers one request, then stops listening
+        let endpoint = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let reply = "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n";
+            reader.get_mut().write_all(reply.as_bytes()).unwrap();
+            serde_json::from_slice::<Value>(&body).unwrap()
+        });
+
+        let hooks = BTreeMap::from([("indexer".to_string(), url)]);
+        let webhooks = Webhooks::start(hooks);
+        let mut observer = webhooks.observer();
+        observer.on_event(&StorageEvent::NodeAdded {
+            sha256: [0xAB; 32],
+            title: "Alpha".to_string(),
+        });
+        let body = endpoint.join().unwrap();
+        assert_eq!(body["event"], "node_added");
+        assert_eq!(body["sha256"], format_hash(&[0xAB; 32]));
+        assert_eq!(body["content"], "Added Alpha");
+
+        // Nothing listens any more, so this one fails
+        observer.on_event(&StorageEvent::NodeRemoved {
+            sha256: [0xAB; 32],
+            title: "Alpha".to_string(),
+        });
+        let deadline = Instant::now() + TIMEOUT;
+        let mut failures = Vec::new();
+        while failures.is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+            failures = webhooks.failures();
+        }
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].name, "indexer");
+        assert_eq!(failures[0].event, "node_removed");
+    }
+}
@@ -0,0 +1,98 @@
+//! Serde field helpers giving core types the same wire format as `--json`
+//! output and exports: hashes as lowercase hex, raw bytes as base64.
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::rom::parse_hash;
+
+/// A `[u8; 32]` SHA-256 as a 64-character hex string.
+pub mod hash {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(hash: &[u8; 32], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&hex::encode(hash))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<[u8; 32], D::Error> {
+        let text = String::deserialize(d)?;
+        parse_hash(&text).ok_or_else(|| D::Error::custom(format!("invalid hash: {}", text)))
+    }
+}
+
+/// A list of hashes, each as [`hash`] writes it.
+#[cfg(feature = "native")]
+pub mod hashes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(hashes: &[[u8; 32]], s: S) -> Result<S::Ok, S::Error> {
+        let hex: Vec<String> = hashes.iter().map(hex::encode).collect();
+        hex.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<[u8; 32]>, D::Error> {
+        Vec::<String>::deserialize(d)?
+            .iter()
+            .map(|text| {
+                parse_hash(text).ok_or_else(|| D::Error::custom(format!("invalid hash: {}", text)))
+            })
+            .collect()
+    }
+}
+
+/// Bytes as a base64 string.
+#[cfg(feature = "native")]
+pub mod bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&BASE64.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        BASE64
+            .decode(String::deserialize(d)?)
+            .map_err(D::Error::custom)
+    }
+}
+
+/// An optional fixed-size hash (e.g. a SHA-1) as a hex string or null.
+pub mod optional_hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        hash: &Option<[u8; N]>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        hash.as_ref().map(hex::encode).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        d: D,
+    ) -> Result<Option<[u8; N]>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|text| {
+                hex::decode(&text)
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| D::Error::custom(format!("invalid hash: {}", text)))
+            })
+            .transpose()
+    }
+}
+
+/// Optional bytes as a base64 string or null.
+pub mod optional_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &Option<Vec<u8>>, s: S) -> Result<S::Ok, S::Error> {
+        bytes.as_ref().map(|b| BASE64.encode(b)).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|text| BASE64.decode(text).map_err(D::Error::custom))
+            .transpose()
+    }
+}